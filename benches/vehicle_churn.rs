@@ -0,0 +1,116 @@
+//! Tick times while vehicles come and go, with and without recycling
+//!
+//! Builds a grid city busy enough that vehicles set off and arrive every
+//! tick, runs the same seeded rush hour with the default car pool and with
+//! recycling turned off, and prints the mean, 99th percentile and worst
+//! tick for each. The two runs drive exactly the same trips.
+//!
+//! Run with `cargo bench --bench vehicle_churn --no-default-features`.
+
+use std::time::{Duration, Instant};
+
+use traffic_sim::simulation::{Position, SimWorld, CAR_POOL_CAPACITY};
+
+/// Intersections along each side of the grid
+const GRID: usize = 10;
+
+/// Distance between neighbouring intersections
+const SPACING: f32 = 20.0;
+
+/// Simulation ticks to time
+const TICKS: usize = 3000;
+
+/// Seconds per tick
+const DELTA_SECS: f32 = 0.1;
+
+/// A grid of two-way roads with a house, factory or shop off every edge
+/// intersection, in turn
+fn build_city(seed: u64) -> SimWorld {
+    let mut world = SimWorld::new_with_seed(seed);
+    let half = (GRID - 1) as f32 * SPACING / 2.0;
+    let mut grid = Vec::new();
+    for row in 0..GRID {
+        for col in 0..GRID {
+            let position = Position::new(col as f32 * SPACING - half, 0.0, row as f32 * SPACING - half);
+            grid.push(world.add_intersection(position));
+        }
+    }
+    for row in 0..GRID {
+        for col in 0..GRID {
+            let at = grid[row * GRID + col];
+            if col + 1 < GRID {
+                let _ = world.add_two_way_road(at, grid[row * GRID + col + 1]);
+            }
+            if row + 1 < GRID {
+                let _ = world.add_two_way_road(at, grid[(row + 1) * GRID + col]);
+            }
+        }
+    }
+
+    let mut placed = 0;
+    for row in 0..GRID {
+        for col in 0..GRID {
+            let edge = row == 0 || col == 0 || row == GRID - 1 || col == GRID - 1;
+            if !edge {
+                continue;
+            }
+            let at = grid[row * GRID + col];
+            let position = world.road_network.get_intersection_position(at).copied().unwrap();
+            let outward = |value: f32| value.signum() * (value.abs() + SPACING / 2.0);
+            let lot = world.add_intersection(Position::new(
+                outward(position.x),
+                0.0,
+                outward(position.z),
+            ));
+            let _ = world.add_two_way_road(at, lot);
+            match placed % 4 {
+                0 | 1 => {
+                    world.add_apartment(lot);
+                }
+                2 => {
+                    world.add_factory(lot);
+                }
+                _ => {
+                    world.add_shop(lot);
+                }
+            }
+            placed += 1;
+        }
+    }
+    world
+}
+
+/// Time every tick of a run with a car pool of `capacity`
+fn run(capacity: usize) -> (Vec<Duration>, u64) {
+    let mut world = build_city(42);
+    world.set_car_pool_capacity(capacity);
+    let ticks = (0..TICKS)
+        .map(|_| {
+            let started = Instant::now();
+            world.tick(DELTA_SECS);
+            started.elapsed()
+        })
+        .collect();
+    (ticks, world.car_pool().reused())
+}
+
+fn report(label: &str, mut ticks: Vec<Duration>, reused: u64) {
+    ticks.sort();
+    let mean = ticks.iter().sum::<Duration>() / ticks.len() as u32;
+    let p99 = ticks[ticks.len() * 99 / 100];
+    let worst = ticks[ticks.len() - 1];
+    println!(
+        "{:<12} mean {:>9.1?}  p99 {:>9.1?}  worst {:>9.1?}  recycled {}",
+        label, mean, p99, worst, reused
+    );
+}
+
+fn main() {
+    // Warm up caches and the allocator before timing anything
+    run(CAR_POOL_CAPACITY);
+
+    let (pooled, reused) = run(CAR_POOL_CAPACITY);
+    let (unpooled, _) = run(0);
+    report("pooled", pooled, reused);
+    report("no pool", unpooled, 0);
+}
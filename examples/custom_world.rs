@@ -0,0 +1,63 @@
+//! Build a small town by hand and drive it for a while
+//!
+//! Lays out a main street with apartments at one end, a factory at the
+//! other and a shop on a side street, then runs the simulation and prints
+//! the trips completed.
+//!
+//! Run with `cargo run --example custom_world --no-default-features`.
+
+use anyhow::{ensure, Result};
+use traffic_sim::simulation::{Position, SimWorld};
+
+/// Simulation ticks to run
+const TICKS: usize = 2000;
+
+/// Seconds per tick
+const DELTA_SECS: f32 = 0.1;
+
+/// Build the town and run it, returning the finished world
+fn run() -> Result<SimWorld> {
+    // A seeded world with game rules, so runs are reproducible and scored
+    let mut world = SimWorld::new_with_seed(1);
+    world.game_state = SimWorld::new_with_game().game_state;
+
+    let homes = world.add_intersection(Position::new(-40.0, 0.0, 0.0));
+    let middle = world.add_intersection(Position::new(0.0, 0.0, 0.0));
+    let works = world.add_intersection(Position::new(40.0, 0.0, 0.0));
+    let corner = world.add_intersection(Position::new(0.0, 0.0, 30.0));
+    world.add_two_way_road(homes, middle)?;
+    world.add_two_way_road(middle, works)?;
+    world.add_two_way_road(middle, corner)?;
+
+    world.add_apartment(homes);
+    world.add_factory(works);
+    world.add_shop(corner);
+
+    for _ in 0..TICKS {
+        world.tick(DELTA_SECS);
+    }
+
+    let game_state = world.game_state.as_ref().expect("the world is a game");
+    println!("{}", game_state.summary());
+    ensure!(
+        game_state.total_deliveries() > 0,
+        "no trips finished in {} ticks",
+        TICKS
+    );
+    Ok(world)
+}
+
+fn main() -> Result<()> {
+    run()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn town_completes_trips() {
+        let world = super::run().unwrap();
+        let game_state = world.game_state.unwrap();
+        assert!(game_state.worker_trips_completed > 0);
+    }
+}
@@ -0,0 +1,59 @@
+//! Follow what happens to a game's finances as it plays
+//!
+//! Takes out insurance on the test world, then drains the game's
+//! [`GameEvent`]s after every tick and prints each one with the time it
+//! happened. This is how the UI fills its event log.
+//!
+//! Run with `cargo run --example events --no-default-features`.
+
+use anyhow::{ensure, Result};
+use traffic_sim::simulation::{BuildCommand, GameEvent, SimWorld, INSURANCE_PERIOD_SECS};
+
+/// Seconds per tick
+const DELTA_SECS: f32 = 0.1;
+
+/// Play the test world for `secs` seconds, returning every event raised
+fn run(secs: f32) -> Result<Vec<(f32, GameEvent)>> {
+    let mut world = SimWorld::create_test_world_with_seed(3);
+    world.game_state = SimWorld::new_with_game().game_state;
+    ensure!(
+        world.apply_command(&BuildCommand::SetInsurance { insured: true })?,
+        "Couldn't afford insurance"
+    );
+
+    let mut events = Vec::new();
+    while world.time < secs {
+        world.tick(DELTA_SECS);
+        let Some(game_state) = world.game_state.as_mut() else {
+            continue;
+        };
+        for event in game_state.take_events() {
+            println!("[{:>6.1}s] {:?}", world.time, event);
+            events.push((world.time, event));
+        }
+    }
+    Ok(events)
+}
+
+fn main() -> Result<()> {
+    let events = run(5.0 * INSURANCE_PERIOD_SECS)?;
+    println!("{} events", events.len());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn premiums_are_reported_every_period() {
+        let events = run(2.5 * INSURANCE_PERIOD_SECS).unwrap();
+        let premiums = events
+            .iter()
+            .filter(|(_, event)| matches!(event, GameEvent::PremiumPaid { .. }))
+            .count();
+        // The first premium is taken with the policy, before the first tick
+        assert_eq!(premiums, 3);
+        assert!(events.windows(2).all(|pair| pair[0].0 <= pair[1].0));
+    }
+}
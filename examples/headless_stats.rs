@@ -0,0 +1,47 @@
+//! Run the built-in test world headless and report on it
+//!
+//! Prints the same summary as `traffic_sim --ticks`, then scores the run
+//! and prints the [`RunSummary`] as JSON, ready to be compared against
+//! other runs.
+//!
+//! Run with `cargo run --example headless_stats --no-default-features --features stats,serialization`.
+
+use anyhow::Result;
+use traffic_sim::simulation::{RunSummary, SimWorld};
+
+/// Simulation ticks to run
+const TICKS: usize = 3000;
+
+/// Seconds per tick
+const DELTA_SECS: f32 = 0.1;
+
+/// Run the test world and score it
+fn run(seed: u64) -> Result<RunSummary> {
+    let mut world = SimWorld::create_test_world_with_seed(seed);
+    world.game_state = SimWorld::new_with_game().game_state;
+    for _ in 0..TICKS {
+        world.tick(DELTA_SECS);
+    }
+    world.print_summary();
+
+    let game_state = world.game_state.as_ref().expect("the world is a game");
+    let summary = RunSummary::from_game_state("example", game_state);
+    println!("{}", summary.to_json()?);
+    Ok(summary)
+}
+
+fn main() -> Result<()> {
+    run(42)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn seeded_runs_score_the_same() {
+        let first = super::run(7).unwrap();
+        assert!(first.verify());
+        assert!(first.worker_trips > 0);
+        assert_eq!(super::run(7).unwrap(), first);
+    }
+}
@@ -1,7 +1,13 @@
 //! Traffic Simulation Library
 //!
 //! A traffic simulation library that can run independently or with a Bevy UI.
+//!
+//! Without features only the simulation core is built. The `stats`,
+//! `serialization`, `server` and `generator` features add the optional parts,
+//! and `cli` and `ui` turn on everything the binary and the game need.
 
+#[cfg(feature = "server")]
+pub mod multiplayer;
 pub mod simulation;
 
 #[cfg(feature = "ui")]
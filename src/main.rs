@@ -14,6 +14,9 @@ use clap::Parser;
 #[command(name = "traffic_sim")]
 #[command(about = "Traffic management game - Build roads and manage deliveries!")]
 struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// Run with the Bevy game engine UI to play the game
     #[arg(long)]
     ui: bool,
@@ -26,22 +29,521 @@ struct Cli {
     #[arg(long, default_value = "0.1")]
     delta: f32,
 
-    /// Random seed for reproducible simulations (test mode only)
+    /// Random seed for reproducible simulations (test mode, or the world a
+    /// multiplayer host creates)
     #[arg(long, default_value = "42")]
     seed: u64,
 
     /// Display the simulation visually in the CLI with periodic updates
     #[arg(long)]
     cli_display: bool,
+
+    /// Let the autopilot build a city from an empty map (headless mode)
+    #[arg(long)]
+    autoplay: bool,
+
+    /// Ramp up demand on the test world until deliveries miss their SLA
+    /// (headless mode)
+    ///
+    /// Uses the demand ramp from --economy, or a default ramp if it has none.
+    #[arg(long, conflicts_with = "autoplay")]
+    stress_test: bool,
+
+    /// Host a LAN co-op session other players can join
+    ///
+    /// Without --ui, runs the test world in real time for --ticks ticks so
+    /// spectators can watch it.
+    #[arg(long, value_name = "ADDR", conflicts_with_all = ["join", "spectate"])]
+    host: Option<String>,
+
+    /// Join a LAN co-op session hosted at ADDR (UI mode)
+    #[arg(long, value_name = "ADDR", conflicts_with = "spectate")]
+    join: Option<String>,
+
+    /// Watch a session hosted at ADDR without building (UI mode)
+    #[arg(long, value_name = "ADDR")]
+    spectate: Option<String>,
+
+    /// Start the UI with the interactive tutorial
+    #[arg(long)]
+    tutorial: bool,
+
+    /// Lay a river down the east side of the map that only bridges and
+    /// ferries can cross (UI mode)
+    #[arg(long)]
+    river: bool,
+
+    /// Load the ground's heights from a TOML heightmap (UI mode)
+    #[arg(long, value_name = "PATH", conflicts_with = "hills")]
+    heightmap: Option<std::path::PathBuf>,
+
+    /// Raise rolling hills up to HEIGHT high, shaped by --seed (UI mode)
+    #[arg(long, value_name = "HEIGHT")]
+    hills: Option<f32>,
+
+    /// Drive on the left instead of the right (UI mode)
+    #[arg(long)]
+    left_hand_traffic: bool,
+
+    /// Start the UI with the camera touring the city's highlights, for
+    /// demos and attract screens
+    #[arg(long)]
+    cinematic: bool,
+
+    /// Play a scripted camera tour from a TOML file over the tour's city
+    /// (its save, or the test world of --seed), for demo footage that comes
+    /// out the same every time (UI mode)
+    #[arg(
+        long,
+        value_name = "PATH",
+        conflicts_with_all = ["tutorial", "cinematic", "host", "join", "spectate"]
+    )]
+    camera_tour: Option<std::path::PathBuf>,
+
+    /// File where unlocked achievements are saved between UI sessions
+    #[arg(long, value_name = "PATH", default_value = "achievements.toml")]
+    achievements: std::path::PathBuf,
+
+    /// File where the best score for each scenario is saved between runs
+    #[arg(long, value_name = "PATH", default_value = "best_scores.json")]
+    best_scores: std::path::PathBuf,
+
+    /// Write a JSON summary of the headless run, including its score
+    #[arg(long, value_name = "PATH")]
+    summary_json: Option<std::path::PathBuf>,
+
+    /// Checkpoint a long headless run (plain or --autoplay) to this file, and
+    /// resume from it if it exists
+    ///
+    /// Runs are deterministic, so resuming quietly re-simulates the ticks
+    /// already done, checks the world matches the checkpoint and carries on.
+    /// The file is removed when the run finishes.
+    #[arg(
+        long,
+        value_name = "PATH",
+        conflicts_with_all = ["ui", "stress_test", "host", "cli_display"]
+    )]
+    checkpoint: Option<std::path::PathBuf>,
+
+    /// Ticks between checkpoints
+    #[arg(long, value_name = "TICKS", default_value = "10000", requires = "checkpoint")]
+    checkpoint_every: u32,
+
+    /// Resume a plain headless run from a snapshot written by --snapshot
+    /// instead of starting the test world
+    ///
+    /// The world carries on with its own traffic, game progress and economy;
+    /// --ticks more ticks are run.
+    #[arg(
+        long,
+        value_name = "PATH",
+        conflicts_with_all = ["ui", "autoplay", "stress_test", "host", "cli_display", "checkpoint"]
+    )]
+    load: Option<std::path::PathBuf>,
+
+    /// Write a snapshot of the whole world, traffic included, to this file
+    /// when a plain headless run ends, for --load to resume
+    #[arg(
+        long,
+        value_name = "PATH",
+        conflicts_with_all = ["ui", "autoplay", "stress_test", "host", "cli_display"]
+    )]
+    snapshot: Option<std::path::PathBuf>,
+
+    /// File the UI autosaves the city to, offering to restore it after a crash
+    #[arg(long, value_name = "PATH", default_value = "autosave.json")]
+    autosave: std::path::PathBuf,
+
+    /// Write per-road traffic statistics of the headless run to a CSV file
+    ///
+    /// Each road gets the vehicles on it at the end of the run and how many
+    /// drove onto it over the whole run. Labor statistics (worker shifts
+    /// requested, fulfilled and rejected by day, factory and house) go to a
+    /// second file next to it, with the extension `.labor.csv`.
+    #[arg(long, value_name = "PATH")]
+    stats_csv: Option<std::path::PathBuf>,
+
+    /// Write a report of the run to a file when it ends (headless runs, or
+    /// when the UI exits)
+    ///
+    /// The report has a map of the city, the run's statistics, a table for
+    /// each kind of building and the most congested roads. It is HTML for a
+    /// path ending in .html or .htm and Markdown otherwise.
+    #[arg(long, value_name = "PATH")]
+    report: Option<std::path::PathBuf>,
+
+    /// Warn when the world has more than COUNT cars (UI, headless and
+    /// --autoplay)
+    #[arg(long, value_name = "COUNT")]
+    max_cars: Option<usize>,
+
+    /// Warn when the world has more than COUNT one-way roads (a two-way road
+    /// counts twice)
+    #[arg(long, value_name = "COUNT")]
+    max_roads: Option<usize>,
+
+    /// Warn when the world has more than COUNT buildings, not counting trees
+    /// and parks
+    #[arg(long, value_name = "COUNT")]
+    max_buildings: Option<usize>,
+
+    /// Print the approximate memory held by the world's collections at the
+    /// end of a headless run
+    #[arg(long)]
+    memory_report: bool,
+
+    /// Keep up to COUNT recently found routes for reuse; the cache is off
+    /// unless set (2048 suits most worlds)
+    #[arg(long, value_name = "COUNT", default_value_t = 0)]
+    path_cache_size: usize,
+
+    /// When the path cache is full, drop the route cached longest ago
+    /// rather than the one used longest ago
+    #[arg(long)]
+    path_cache_evict_oldest: bool,
+
+    /// Seconds a trip may take before a plain headless run's diagnosis
+    /// flags it
+    #[arg(long, value_name = "SECS", default_value_t = simulation::ANOMALY_MAX_TRIP_SECS)]
+    max_trip_secs: f32,
+
+    /// Fail a plain headless run if its diagnosis finds any anomalies:
+    /// factories that never dispatched a truck, roads never used or trips
+    /// that took longer than --max-trip-secs
+    #[arg(long)]
+    fail_on_anomalies: bool,
+
+    /// Threads finding routes for new commuters and trucks in the background
+    /// (0 finds them inline)
+    ///
+    /// Vehicles wait a tick at their start for their route. The offline UI
+    /// uses a thread per spare core by default; headless runs route inline
+    /// unless this is given. Runs with any number of threads match each
+    /// other, but not runs that route inline.
+    #[arg(long, value_name = "THREADS", conflicts_with_all = ["host", "join", "spectate"])]
+    route_workers: Option<usize>,
+
+    /// Load game economy parameters (costs, revenues, goals) from a TOML file
+    #[arg(long, value_name = "PATH")]
+    economy: Option<std::path::PathBuf>,
+
+    /// Watch the --economy file while the UI runs and put its changes into
+    /// effect when it is saved, for balancing an economy
+    ///
+    /// Costs, revenues, goals, incident rates, the demand ramp and the
+    /// intersection rules change at once; the starting budget and the event
+    /// schedule wait for a new game. Offline games only.
+    #[arg(long, requires = "economy", conflicts_with_all = ["host", "join", "spectate"])]
+    watch_economy: bool,
+
+    /// Load extra building and vehicle types from the content packs (*.toml) in a directory
+    #[arg(long, value_name = "DIR")]
+    content: Option<std::path::PathBuf>,
+
+    /// Check that every apartment has a route to every factory and back,
+    /// and every factory to every shop and back, and exit
+    ///
+    /// Audits the city in the save at PATH, or the test world of --seed.
+    /// Lists each trip with no route and the nearest missing link that would
+    /// give it one, and exits with an error if there are any.
+    #[arg(long, value_name = "PATH", num_args = 0..=1, conflicts_with = "ui")]
+    audit: Option<Option<std::path::PathBuf>>,
+
+    /// Write the economy parameters to a TOML file and exit
+    ///
+    /// Exports the file given with --economy if present, otherwise the defaults.
+    #[arg(long, value_name = "PATH")]
+    export_economy: Option<std::path::PathBuf>,
+}
+
+#[derive(clap::Subcommand)]
+enum Command {
+    /// Load a save or the test world and print information about it without
+    /// running the simulation
+    Inspect(InspectArgs),
+    /// Run an autopilot-built city for a long time, checking the world stays
+    /// consistent, its memory doesn't keep growing and traffic keeps moving
+    ///
+    /// Meant to be run nightly: exits with an error as soon as a check
+    /// fails.
+    Soak(SoakArgs),
+    /// Fit a save's demand to traffic counted on its roads and write the
+    /// fitted demand weights into the save's economy
+    Calibrate(CalibrateArgs),
+}
+
+#[derive(clap::Args)]
+struct InspectArgs {
+    /// Save file to inspect; without one, the test world of --seed
+    #[arg(long, value_name = "PATH")]
+    save: Option<std::path::PathBuf>,
+
+    /// Seed of the test world to inspect
+    #[arg(long, default_value = "42", conflicts_with = "save")]
+    seed: u64,
+
+    /// List every road with its ends, length and settings
+    #[arg(long)]
+    roads: bool,
+
+    /// Print the shortest route for a car and a truck between two
+    /// intersections, by ID
+    #[arg(long, num_args = 2, value_names = ["FROM", "TO"])]
+    shortest: Option<Vec<usize>>,
+
+    /// Print counts of what the world holds and the game's progress (the
+    /// default when nothing else is asked for)
+    #[arg(long)]
+    stats: bool,
+
+    /// Spread the city's trips over its roads as steady flows instead of
+    /// simulating vehicles, and print the busiest roads
+    ///
+    /// A quick what-if check of a layout: the flows settle where no driver
+    /// could save much time by switching routes.
+    #[arg(long)]
+    assign: bool,
+
+    /// Print how the roads of another save differ from these: the
+    /// intersections and roads added, removed, moved or modified
+    #[arg(long, value_name = "PATH")]
+    diff: Option<std::path::PathBuf>,
+
+    /// Draw a map of the city into a PNG image at PATH
+    #[arg(long, value_name = "PATH")]
+    map: Option<std::path::PathBuf>,
+
+    /// Width of the map image in pixels
+    #[arg(long, default_value = "600", requires = "map")]
+    map_width: u32,
+}
+
+#[derive(clap::Args)]
+struct SoakArgs {
+    /// Seed of the world the autopilot builds on
+    #[arg(long, default_value = "42")]
+    seed: u64,
+
+    /// Ticks the autopilot builds the city for before the soak starts
+    #[arg(long, default_value = "30000")]
+    build_ticks: u32,
+
+    /// Ticks to soak the finished city for
+    #[arg(long, default_value = "2000000")]
+    ticks: u32,
+
+    /// Time delta per tick in seconds
+    #[arg(long, default_value = "0.1")]
+    delta: f32,
+
+    /// Ticks between invariant checks
+    #[arg(long, default_value = "1000")]
+    check_every: u32,
+
+    /// Ticks between lines logging the state hashes and memory
+    #[arg(long, default_value = "20000")]
+    log_every: u32,
+
+    /// Fraction memory may grow by over its size early in the soak
+    #[arg(long, default_value = "0.5")]
+    memory_tolerance: f32,
+
+    /// Seconds vehicles may be out with no trip or delivery completed
+    /// before the city counts as deadlocked
+    #[arg(long, default_value = "600")]
+    stall_secs: f32,
+}
+
+#[derive(clap::Args)]
+struct CalibrateArgs {
+    /// Save to calibrate
+    #[arg(long, value_name = "PATH")]
+    save: std::path::PathBuf,
+
+    /// CSV of vehicles a minute counted on roads, as from,to,per_minute
+    /// lines naming each road by its intersection IDs
+    #[arg(long, value_name = "PATH")]
+    counts: std::path::PathBuf,
+
+    /// Largest share a counted road's flow may be off its count by
+    #[arg(long, default_value = "0.1")]
+    tolerance: f32,
+
+    /// Most rounds of adjusting the demand
+    #[arg(long, default_value = "30")]
+    max_rounds: usize,
+
+    /// Write the calibrated save here instead of over the original
+    #[arg(long, value_name = "PATH")]
+    output: Option<std::path::PathBuf>,
 }
 
 fn main() {
     let cli = Cli::parse();
 
+    if let Some(Command::Inspect(args)) = &cli.command {
+        match inspect(args) {
+            Ok(report) => print!("{}", report),
+            Err(e) => {
+                eprintln!("Error: {:#}", e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+    if let Some(Command::Calibrate(args)) = &cli.command {
+        match calibrate(args) {
+            Ok(report) => print!("{}", report),
+            Err(e) => {
+                eprintln!("Error: {:#}", e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+    if let Some(Command::Soak(args)) = &cli.command {
+        if let Err(e) = run_soak(args) {
+            eprintln!("Error: {:#}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    let economy = match &cli.economy {
+        Some(path) => match simulation::EconomyConfig::load(path) {
+            Ok(economy) => Some(economy),
+            Err(e) => {
+                eprintln!("Error: {:#}", e);
+                std::process::exit(1);
+            }
+        },
+        None => None,
+    };
+
+    let mut content = simulation::ContentRegistry::default();
+    if let Some(dir) = &cli.content {
+        match content.load_dir(dir) {
+            Ok(count) => println!(
+                "Loaded {} content pack(s): {} building type(s), {} vehicle type(s)",
+                count,
+                content.buildings().len(),
+                content.vehicles().len()
+            ),
+            Err(e) => {
+                eprintln!("Error: {:#}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    if let Some(path) = &cli.export_economy {
+        let economy = economy.unwrap_or_default();
+        if let Err(e) = economy.save(path) {
+            eprintln!("Error: {:#}", e);
+            std::process::exit(1);
+        }
+        println!("Wrote economy parameters to {}", path.display());
+        return;
+    }
+
+    if let Some(save) = &cli.audit {
+        match load_city(save.as_deref(), cli.seed) {
+            Ok((description, world)) => {
+                let audit = world.audit_network();
+                println!("=== NETWORK AUDIT: {} ===", description);
+                print!("{}", audit);
+                if !audit.is_complete() {
+                    std::process::exit(1);
+                }
+            }
+            Err(e) => {
+                eprintln!("Error: {:#}", e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    let budgets = simulation::EntityBudgets {
+        max_cars: cli.max_cars,
+        max_roads: cli.max_roads,
+        max_buildings: cli.max_buildings,
+    };
+    let diagnostics = RunDiagnostics {
+        watch: simulation::BudgetWatch::new(budgets),
+        memory_report: cli.memory_report,
+        path_cache: simulation::PathCacheConfig {
+            capacity: cli.path_cache_size,
+            eviction: if cli.path_cache_evict_oldest {
+                simulation::PathCacheEviction::Oldest
+            } else {
+                simulation::PathCacheEviction::Lru
+            },
+            ..simulation::PathCacheConfig::default()
+        },
+        alarms_seen: 0,
+        anomalies: simulation::AnomalyConfig {
+            max_trip_secs: cli.max_trip_secs,
+        },
+        fail_on_anomalies: cli.fail_on_anomalies,
+    };
+
     if cli.ui {
         #[cfg(feature = "ui")]
         {
-            run_with_ui();
+            let heightmap = match (&cli.heightmap, cli.hills) {
+                (Some(path), _) => match simulation::Heightmap::load(path) {
+                    Ok(heightmap) => Some(heightmap),
+                    Err(e) => {
+                        eprintln!("Error: {:#}", e);
+                        std::process::exit(1);
+                    }
+                },
+                // Cover the ground plane
+                (None, Some(height)) => Some(simulation::Heightmap::hills(
+                    cli.seed, -100.0, -100.0, 200.0, height,
+                )),
+                (None, None) => None,
+            };
+            let network = match (cli.host, cli.join, cli.spectate) {
+                (Some(addr), _, _) => NetworkRole::Host {
+                    addr,
+                    seed: cli.seed,
+                },
+                (None, Some(addr), _) => NetworkRole::Join(addr),
+                (None, None, Some(addr)) => NetworkRole::Spectate(addr),
+                (None, None, None) => NetworkRole::Offline,
+            };
+            let camera_tour = match cli.camera_tour.as_deref().map(simulation::CameraTour::load) {
+                Some(Ok(tour)) => Some(tour),
+                Some(Err(e)) => {
+                    eprintln!("Error: {:#}", e);
+                    std::process::exit(1);
+                }
+                None => None,
+            };
+            // Neither the tutorial's city nor a tour's is worth keeping
+            let autosave = (!cli.tutorial && camera_tour.is_none()).then_some(cli.autosave);
+            run_with_ui(
+                economy,
+                content,
+                cli.tutorial,
+                cli.cinematic,
+                camera_tour,
+                cli.river,
+                heightmap,
+                cli.left_hand_traffic,
+                cli.achievements,
+                cli.best_scores,
+                network,
+                cli.seed,
+                autosave,
+                budgets,
+                cli.route_workers
+                    .unwrap_or_else(simulation::default_route_workers),
+                cli.report,
+                cli.economy.filter(|_| cli.watch_economy),
+            );
         }
         #[cfg(not(feature = "ui"))]
         {
@@ -57,14 +559,90 @@ fn main() {
         println!("===========================================");
         println!();
 
-        if cli.cli_display {
+        if cli.join.is_some() || cli.spectate.is_some() {
+            eprintln!("Error: --join and --spectate need the UI (--ui)");
+            std::process::exit(1);
+        }
+
+        let route_workers = cli.route_workers.unwrap_or(0);
+        if let Some(addr) = &cli.host {
+            if let Err(e) =
+                run_headless_host(addr, cli.ticks, cli.delta, cli.seed, economy, &content)
+            {
+                eprintln!("Error: {:#}", e);
+                std::process::exit(1);
+            }
+        } else if cli.stress_test {
+            run_stress_test(
+                cli.ticks,
+                cli.delta,
+                cli.seed,
+                economy,
+                route_workers,
+                cli.stats_csv.as_deref(),
+                cli.report.as_deref(),
+            );
+        } else if cli.autoplay {
+            let run = checkpoint_run("autopilot", cli.seed, cli.delta, route_workers);
+            run_autoplay(
+                cli.ticks,
+                cli.delta,
+                cli.seed,
+                economy,
+                route_workers,
+                &cli.best_scores,
+                cli.summary_json.as_deref(),
+                cli.stats_csv.as_deref(),
+                cli.report.as_deref(),
+                open_checkpoint(cli.checkpoint.as_deref(), cli.checkpoint_every, run),
+                diagnostics,
+            );
+        } else if cli.cli_display {
             run_headless_with_display(cli.ticks, cli.delta, cli.seed);
         } else {
-            run_headless(cli.ticks, cli.delta, cli.seed);
+            let run = checkpoint_run("test-world", cli.seed, cli.delta, route_workers);
+            run_headless(
+                cli.ticks,
+                cli.delta,
+                cli.seed,
+                economy,
+                cli.load.as_deref(),
+                cli.snapshot.as_deref(),
+                route_workers,
+                &cli.best_scores,
+                cli.summary_json.as_deref(),
+                cli.stats_csv.as_deref(),
+                cli.report.as_deref(),
+                open_checkpoint(cli.checkpoint.as_deref(), cli.checkpoint_every, run),
+                diagnostics,
+            );
         }
     }
 }
 
+/// How a validation run starts and what it writes besides its results; the
+/// default plays the test world inline with the default economy and writes
+/// nothing
+#[derive(Default)]
+struct ValidationOptions<'a> {
+    /// Economy used to score the run
+    economy: simulation::EconomyConfig,
+    /// Snapshot to resume instead of the test world, if any
+    load_path: Option<&'a std::path::Path>,
+    /// File to write a snapshot of the world to at the end, if any
+    snapshot_path: Option<&'a std::path::Path>,
+    /// Threads finding routes in the background (0 for inline)
+    route_workers: usize,
+    /// File to write the per-road statistics CSV to, if any
+    stats_path: Option<&'a std::path::Path>,
+    /// File to write the end-of-run report to, if any
+    report_path: Option<&'a std::path::Path>,
+    /// Checkpoints to write and resume from, if any
+    checkpoint: Option<Checkpointer>,
+    /// Entity budgets to warn about and whether to report memory
+    diagnostics: RunDiagnostics,
+}
+
 /// Helper function to run simulation with validation
 ///
 /// Runs a simulation for the specified number of ticks and validates
@@ -75,6 +653,7 @@ fn main() {
 /// * `ticks` - Number of simulation ticks to run
 /// * `delta` - Time delta per tick in seconds
 /// * `seed` - Random seed for deterministic simulation
+/// * `options` - Where the run starts from and what it writes along the way
 ///
 /// # Returns
 /// A tuple containing:
@@ -82,19 +661,47 @@ fn main() {
 /// * `total_deliveries` - Total number of deliveries completed
 /// * `max_cars_observed` - Maximum number of concurrent cars
 /// * `errors` - List of error messages (if any)
+/// * `summary` - Scored summary of the run
 fn run_simulation_validation(
     ticks: u32,
     delta: f32,
     seed: u64,
-) -> (bool, usize, usize, Vec<String>) {
-    let mut world = simulation::SimWorld::create_test_world_with_seed(seed);
+    options: ValidationOptions,
+) -> (bool, usize, usize, Vec<String>, simulation::RunSummary) {
+    let ValidationOptions {
+        economy,
+        load_path,
+        snapshot_path,
+        route_workers,
+        stats_path,
+        report_path,
+        mut checkpoint,
+        mut diagnostics,
+    } = options;
+    let mut world = match load_path {
+        Some(path) => match simulation::SimWorld::load_from_file(path) {
+            Ok(world) => world,
+            Err(e) => {
+                eprintln!("Error: {:#}", e);
+                std::process::exit(1);
+            }
+        },
+        None => simulation::SimWorld::create_test_world_with_seed(seed),
+    };
+    // Track the game state for scoring; the test world is already built, so
+    // its construction isn't charged to the budget. A resumed world keeps
+    // the game it was playing.
+    if world.game_state.is_none() {
+        world.game_state = Some(simulation::GameState::with_economy(economy));
+    }
+    world.set_route_workers(route_workers);
+    world.set_path_cache_config(diagnostics.path_cache);
 
-    // Track initial state for validation
-    let initial_apartments = world.apartments.len();
-    let initial_factories = world.factories.len();
-    let initial_shops = world.shops.len();
-    let initial_intersections = world.road_network.intersection_count();
-    let initial_roads = world.road_network.road_count();
+    // Nothing should be built or knocked down while the run plays out;
+    // listen for it rather than recounting everything
+    world.drain_events();
+    let mut roads_changed = false;
+    let mut buildings_changed = false;
 
     let mut max_cars_observed = 0usize;
     let mut errors: Vec<String> = Vec::new();
@@ -102,70 +709,94 @@ fn run_simulation_validation(
     // Run simulation without delays
     for tick in 1..=ticks {
         world.tick(delta);
+        if let Some(checkpoint) = &mut checkpoint {
+            checkpoint.after_tick(tick, &world);
+        }
+        diagnostics.after_tick(&world);
 
         // Track maximum concurrent cars
         max_cars_observed = max_cars_observed.max(world.cars.len());
 
-        // Validate simulation state periodically
-        if tick % 100 == 0 {
-            // Check for structural integrity
-            if world.road_network.intersection_count() != initial_intersections {
-                errors.push(format!(
-                    "Tick {}: Intersection count changed from {} to {}",
-                    tick,
-                    initial_intersections,
-                    world.road_network.intersection_count()
-                ));
-            }
-            if world.road_network.road_count() != initial_roads {
-                errors.push(format!(
-                    "Tick {}: Road count changed from {} to {}",
-                    tick,
-                    initial_roads,
-                    world.road_network.road_count()
-                ));
-            }
-            if world.apartments.len() != initial_apartments {
-                errors.push(format!(
-                    "Tick {}: Apartment count changed from {} to {}",
-                    tick,
-                    initial_apartments,
-                    world.apartments.len()
-                ));
-            }
-            if world.factories.len() != initial_factories {
-                errors.push(format!(
-                    "Tick {}: Factory count changed from {} to {}",
-                    tick,
-                    initial_factories,
-                    world.factories.len()
-                ));
-            }
-            if world.shops.len() != initial_shops {
-                errors.push(format!(
-                    "Tick {}: Shop count changed from {} to {}",
-                    tick,
-                    initial_shops,
-                    world.shops.len()
-                ));
+        for event in world.drain_events() {
+            match event {
+                simulation::SimEvent::BuildingAdded { .. }
+                | simulation::SimEvent::BuildingRemoved { .. } => buildings_changed = true,
+                event if event.is_layout() => roads_changed = true,
+                _ => continue,
             }
+            errors.push(format!("Tick {}: Unexpected {:?}", tick, event));
         }
     }
 
+    if let Some(checkpoint) = checkpoint {
+        checkpoint.finish();
+    }
+    diagnostics.finish(&world);
+
     // Calculate total deliveries
     let total_deliveries: usize = world.shops.values().map(|s| s.cars_received).sum();
 
+    let scenario = match load_path {
+        Some(path) => format!("snapshot/{}/ticks-{}/delta-{}", path.display(), ticks, delta),
+        None => format!("test-world/seed-{}/ticks-{}/delta-{}", seed, ticks, delta),
+    };
+    let summary = simulation::RunSummary::from_game_state(
+        &scenario,
+        world.game_state.as_ref().expect("game state attached above"),
+    );
+
     // Print test results
     println!("=== SIMULATION RESULTS ===");
     println!("Simulation time: {:.2}s", world.time);
     println!("Max concurrent cars: {}", max_cars_observed);
     println!("Total deliveries to shops: {}", total_deliveries);
     println!("Final car count: {}", world.cars.len());
+    println!("Worker trips: {}", summary.worker_trips);
+    println!("Average commute: {:.1}", summary.average_commute);
+    print_employment(&world);
+    print_catchments(&world);
+    print_drivers(&world);
+    print_speed_goal(&world);
+    if world.intersection_rules().late_truck_preemption {
+        let preemptions = world.signal_preemptions();
+        println!(
+            "Signal preemptions for late trucks: {} ({:.1}s of green cut from other roads)",
+            preemptions.count, preemptions.green_cut_secs
+        );
+    }
+    println!("Money: ${}", summary.money);
+    println!("Score: {}", summary.score);
+    println!();
+    if let Some(path) = stats_path {
+        save_road_stats(&world, path);
+    }
+    if let Some(path) = report_path {
+        save_report(&world, path, &format!("Test world run, seed {}", seed));
+    }
+    if let Some(path) = snapshot_path {
+        match world.save_to_file(path) {
+            Ok(()) => println!("Wrote snapshot to {}", path.display()),
+            Err(e) => eprintln!("Error: {:#}", e),
+        }
+    }
+
+    // Diagnosis: anomalies in the run's statistics
+    let health = world.find_anomalies(&diagnostics.anomalies);
+    println!("=== DIAGNOSIS ===");
+    print!("{}", health);
     println!();
 
     // Validation checks
     let mut validation_passed = true;
 
+    // Check: Nothing odd in the statistics, if asked
+    if diagnostics.fail_on_anomalies && !health.is_healthy() {
+        for anomaly in &health.anomalies {
+            errors.push(format!("Anomaly: {}", anomaly));
+        }
+        validation_passed = false;
+    }
+
     // Check: Cars should have spawned during simulation
     if max_cars_observed == 0 {
         errors.push("FAIL: No cars were ever spawned during simulation".to_string());
@@ -173,18 +804,13 @@ fn run_simulation_validation(
     }
 
     // Check: Road network should be intact
-    if world.road_network.intersection_count() != initial_intersections
-        || world.road_network.road_count() != initial_roads
-    {
+    if roads_changed {
         errors.push("FAIL: Road network was unexpectedly modified".to_string());
         validation_passed = false;
     }
 
     // Check: Buildings should be intact
-    if world.apartments.len() != initial_apartments
-        || world.factories.len() != initial_factories
-        || world.shops.len() != initial_shops
-    {
+    if buildings_changed {
         errors.push("FAIL: Buildings were unexpectedly modified".to_string());
         validation_passed = false;
     }
@@ -194,6 +820,7 @@ fn run_simulation_validation(
         total_deliveries,
         max_cars_observed,
         errors,
+        summary,
     )
 }
 
@@ -226,7 +853,9 @@ fn print_validation_results_with_mode(
         println!("PASS: Road network integrity maintained");
     }
 
-    if errors.iter().any(|e| {
+    let (anomalies, errors_found): (Vec<&String>, Vec<&String>) =
+        errors.iter().partition(|e| e.starts_with("Anomaly"));
+    if errors_found.iter().any(|e| {
         e.contains("Building") || e.contains("House") || e.contains("Factory") || e.contains("Shop")
     }) {
         println!("FAIL: Buildings were unexpectedly modified");
@@ -234,6 +863,10 @@ fn print_validation_results_with_mode(
         println!("PASS: Building integrity maintained");
     }
 
+    if !anomalies.is_empty() {
+        println!("FAIL: {} anomalies in the run's statistics", anomalies.len());
+    }
+
     // Print any errors
     if !errors.is_empty() {
         println!();
@@ -276,13 +909,69 @@ fn print_validation_results(
 /// * `ticks` - Number of simulation ticks to run
 /// * `delta` - Time delta per tick in seconds
 /// * `seed` - Random seed for deterministic simulation
-fn run_headless(ticks: u32, delta: f32, seed: u64) {
+/// * `economy` - Custom economy, if any
+/// * `load_path` - Snapshot to resume instead of the test world, if any
+/// * `snapshot_path` - File to write a snapshot of the world to at the end, if any
+/// * `route_workers` - Threads finding routes in the background (0 for inline)
+/// * `best_scores_path` - File the best score per scenario is kept in
+/// * `summary_path` - File to write the run summary JSON to, if any
+/// * `stats_path` - File to write the per-road statistics CSV to, if any
+/// * `report_path` - File to write the end-of-run report to, if any
+/// * `checkpoint` - Checkpoints to write and resume from, if any
+/// * `diagnostics` - Entity budgets to warn about and whether to report memory
+#[allow(clippy::too_many_arguments)]
+fn run_headless(
+    ticks: u32,
+    delta: f32,
+    seed: u64,
+    economy: Option<simulation::EconomyConfig>,
+    load_path: Option<&std::path::Path>,
+    snapshot_path: Option<&std::path::Path>,
+    route_workers: usize,
+    best_scores_path: &std::path::Path,
+    summary_path: Option<&std::path::Path>,
+    stats_path: Option<&std::path::Path>,
+    report_path: Option<&std::path::Path>,
+    checkpoint: Option<Checkpointer>,
+    diagnostics: RunDiagnostics,
+) {
     println!("Running traffic simulation in headless mode...");
-    println!("Ticks: {}, Delta: {}s, Seed: {}", ticks, delta, seed);
+    match load_path {
+        Some(path) => println!("Ticks: {}, Delta: {}s, Resuming: {}", ticks, delta, path.display()),
+        None => println!("Ticks: {}, Delta: {}s, Seed: {}", ticks, delta, seed),
+    }
     println!();
 
-    let (validation_passed, total_deliveries, max_cars_observed, errors) =
-        run_simulation_validation(ticks, delta, seed);
+    let custom_economy = economy.is_some();
+    let (validation_passed, total_deliveries, max_cars_observed, errors, mut summary) =
+        run_simulation_validation(
+            ticks,
+            delta,
+            seed,
+            ValidationOptions {
+                economy: economy.unwrap_or_default(),
+                load_path,
+                snapshot_path,
+                route_workers,
+                stats_path,
+                report_path,
+                checkpoint,
+                diagnostics,
+            },
+        );
+
+    // Runs with a custom economy are a different scenario from the default one
+    if custom_economy {
+        summary.scenario.push_str("/custom-economy");
+    }
+    record_best_score(&summary, best_scores_path);
+    if let Some(path) = summary_path {
+        match summary.save_json(path) {
+            Ok(()) => println!("Wrote run summary to {}", path.display()),
+            Err(e) => eprintln!("Error: {:#}", e),
+        }
+    }
+    println!();
 
     // Print validation results
     print_validation_results(
@@ -297,6 +986,715 @@ fn run_headless(ticks: u32, delta: f32, seed: u64) {
     }
 }
 
+/// Record a run against the best score for its scenario and report the result
+fn record_best_score(summary: &simulation::RunSummary, path: &std::path::Path) {
+    let mut best_scores = match simulation::BestScores::load(path) {
+        Ok(best_scores) => best_scores,
+        Err(e) => {
+            eprintln!("Warning: {:#}", e);
+            return;
+        }
+    };
+    let previous_best = best_scores.best(&summary.scenario).map(|best| best.score);
+    if best_scores.record(summary) {
+        match previous_best {
+            Some(previous) => println!("New best score for {} (was {})", summary.scenario, previous),
+            None => println!("First score recorded for {}", summary.scenario),
+        }
+        if let Err(e) = best_scores.save(path) {
+            eprintln!("Warning: {:#}", e);
+        }
+    } else if let Some(previous) = previous_best {
+        println!("Best score for {}: {}", summary.scenario, previous);
+    }
+}
+
+/// Let the autopilot build a city from an empty map and score the run
+///
+/// Stops early once the game is won or lost. With a custom economy the score
+/// is compared against the best human score for the same economy.
+///
+/// # Arguments
+/// * `ticks` - Number of simulation ticks to run
+/// * `delta` - Time delta per tick in seconds
+/// * `seed` - Random seed for deterministic simulation
+/// * `economy` - Custom economy, if any
+/// * `route_workers` - Threads finding routes in the background (0 for inline)
+/// * `best_scores_path` - File the best score per scenario is kept in
+/// * `summary_path` - File to write the run summary JSON to, if any
+/// * `stats_path` - File to write the per-road statistics CSV to, if any
+/// * `report_path` - File to write the end-of-run report to, if any
+/// * `checkpoint` - Checkpoints to write and resume from, if any
+/// * `diagnostics` - Entity budgets to warn about and whether to report memory
+#[allow(clippy::too_many_arguments)]
+fn run_autoplay(
+    ticks: u32,
+    delta: f32,
+    seed: u64,
+    economy: Option<simulation::EconomyConfig>,
+    route_workers: usize,
+    best_scores_path: &std::path::Path,
+    summary_path: Option<&std::path::Path>,
+    stats_path: Option<&std::path::Path>,
+    report_path: Option<&std::path::Path>,
+    mut checkpoint: Option<Checkpointer>,
+    mut diagnostics: RunDiagnostics,
+) {
+    println!("Running the autopilot...");
+    println!("Ticks: {}, Delta: {}s, Seed: {}", ticks, delta, seed);
+    println!();
+
+    let custom_economy = economy.is_some();
+    let mut world = simulation::SimWorld::new_with_seed(seed);
+    world.game_state = Some(simulation::GameState::with_economy(
+        economy.unwrap_or_default(),
+    ));
+    world.set_route_workers(route_workers);
+    world.set_path_cache_config(diagnostics.path_cache);
+    let mut autopilot = simulation::Autopilot::new();
+
+    for tick in 1..=ticks {
+        let replaying = checkpoint
+            .as_ref()
+            .is_some_and(|checkpoint| checkpoint.replaying(tick));
+        for command in autopilot.step(&mut world) {
+            if !replaying {
+                println!("[{:>6.1}s] Built {}", world.time, command.describe());
+            }
+        }
+        world.tick(delta);
+        if let Some(checkpoint) = &mut checkpoint {
+            checkpoint.after_tick(tick, &world);
+        }
+        diagnostics.after_tick(&world);
+        let game_state = world.game_state.as_ref().expect("autoplay runs a game");
+        if game_state.is_won || game_state.is_lost {
+            break;
+        }
+    }
+    if let Some(checkpoint) = checkpoint {
+        checkpoint.finish();
+    }
+    diagnostics.finish(&world);
+
+    println!();
+    world.print_summary();
+    world.draw_map();
+    println!();
+
+    let game_state = world.game_state.as_ref().expect("autoplay runs a game");
+    let mut scenario = format!("autopilot/seed-{}/ticks-{}/delta-{}", seed, ticks, delta);
+    if custom_economy {
+        scenario.push_str("/custom-economy");
+    }
+    let summary = simulation::RunSummary::from_game_state(&scenario, game_state);
+    println!("{}", game_state.summary());
+    print_speed_goal(&world);
+    println!("Score: {}", summary.score);
+    record_best_score(&summary, best_scores_path);
+    if custom_economy {
+        match simulation::BestScores::load(best_scores_path) {
+            Ok(best_scores) => match best_scores.best("custom-economy") {
+                Some(human) => println!(
+                    "Best human score with this economy: {} (autopilot {})",
+                    human.score, summary.score
+                ),
+                None => println!("No human score recorded with a custom economy yet"),
+            },
+            Err(e) => eprintln!("Warning: {:#}", e),
+        }
+    }
+    if let Some(path) = summary_path {
+        match summary.save_json(path) {
+            Ok(()) => println!("Wrote run summary to {}", path.display()),
+            Err(e) => eprintln!("Error: {:#}", e),
+        }
+    }
+    if let Some(path) = stats_path {
+        save_road_stats(&world, path);
+    }
+    if let Some(path) = report_path {
+        save_report(&world, path, &format!("Autopilot run, seed {}", seed));
+    }
+}
+
+/// Ramp up demand on the test world until deliveries can't keep up
+///
+/// Stops as soon as a shop order misses the delivery SLA and reports the
+/// demand level it happened at.
+///
+/// # Arguments
+/// * `ticks` - Maximum number of simulation ticks to run
+/// * `delta` - Time delta per tick in seconds
+/// * `seed` - Random seed for deterministic simulation
+/// * `economy` - Custom economy, if any; its demand ramp is used when it has one
+/// * `route_workers` - Threads finding routes in the background (0 for inline)
+/// * `stats_path` - File to write the per-road statistics CSV to, if any
+/// * `report_path` - File to write the end-of-run report to, if any
+fn run_stress_test(
+    ticks: u32,
+    delta: f32,
+    seed: u64,
+    economy: Option<simulation::EconomyConfig>,
+    route_workers: usize,
+    stats_path: Option<&std::path::Path>,
+    report_path: Option<&std::path::Path>,
+) {
+    let mut economy = economy.unwrap_or_default();
+    let ramp = economy
+        .demand_ramp
+        .get_or_insert_with(simulation::DemandRamp::default)
+        .clone();
+    println!("Running a demand stress test...");
+    println!("Ticks: {}, Delta: {}s, Seed: {}", ticks, delta, seed);
+    println!(
+        "Demand starts at x{:.2} and grows {:?} by {} per minute (up to x{:.2}); orders are due within {:.0}s",
+        ramp.start_level, ramp.curve, ramp.growth_per_minute, ramp.max_level, ramp.delivery_sla_secs
+    );
+    println!();
+
+    let mut world = simulation::SimWorld::create_test_world_with_seed(seed);
+    world.game_state = Some(simulation::GameState::with_economy(economy));
+    world.set_route_workers(route_workers);
+    let mut next_report = 60.0;
+    for _ in 0..ticks {
+        world.tick(delta);
+        if world.time >= next_report {
+            next_report += 60.0;
+            println!(
+                "[{:>6.0}s] Demand x{:.2}: {} open orders, {} deliveries",
+                world.time,
+                world.demand_level(),
+                world.open_orders(),
+                world
+                    .game_state
+                    .as_ref()
+                    .map_or(0, |game_state| game_state.shop_deliveries_completed)
+            );
+        }
+        if world.sla_breach().is_some() {
+            break;
+        }
+    }
+
+    println!();
+    match world.sla_breach() {
+        Some(breach) => println!(
+            "Delivery SLA broke at demand x{:.2} after {:.1}s",
+            breach.level, breach.time
+        ),
+        None => println!(
+            "Deliveries kept up through demand x{:.2} ({:.1}s)",
+            world.demand_level(),
+            world.time
+        ),
+    }
+    print_drivers(&world);
+    if let Some(path) = stats_path {
+        save_road_stats(&world, path);
+    }
+    if let Some(path) = report_path {
+        save_report(&world, path, &format!("Demand stress test, seed {}", seed));
+    }
+}
+
+/// Build a city with the autopilot, then run it for a long time, failing
+/// as soon as an invariant breaks, memory keeps growing or traffic stops
+///
+/// Memory is measured against the most the world held over the first tenth
+/// of the soak, once the city has settled into its daily routine. Game
+/// events are taken every tick, as a front-end would.
+fn run_soak(args: &SoakArgs) -> anyhow::Result<()> {
+    use anyhow::{bail, ensure, Context};
+    use traffic_sim::multiplayer::state_checksum;
+
+    ensure!(
+        args.check_every > 0 && args.log_every > 0,
+        "Checks and logs must be at least a tick apart"
+    );
+    println!("Soaking an autopilot city...");
+    println!(
+        "Build ticks: {}, Ticks: {}, Delta: {}s, Seed: {}",
+        args.build_ticks, args.ticks, args.delta, args.seed
+    );
+    println!();
+
+    let mut world = simulation::SimWorld::new_with_seed(args.seed);
+    world.game_state = Some(simulation::GameState::with_economy(
+        simulation::EconomyConfig::default(),
+    ));
+    let mut autopilot = simulation::Autopilot::new();
+    for _ in 0..args.build_ticks {
+        autopilot.step(&mut world);
+        world.tick(args.delta);
+        if let Some(game_state) = &mut world.game_state {
+            game_state.take_events();
+        }
+    }
+    world
+        .check_invariants()
+        .context("The city was inconsistent once built")?;
+    println!(
+        "Built {} buildings and {} roads in {:.0}s",
+        world.building_count(),
+        world.road_network.road_count(),
+        world.time
+    );
+
+    let completed = |world: &simulation::SimWorld| {
+        world.game_state.as_ref().map_or(0, |game_state| {
+            game_state.worker_trips_completed + game_state.shop_deliveries_completed
+        })
+    };
+    let warm_up = args.ticks / 10;
+    let mut baseline: Option<simulation::MemoryReport> = None;
+    let mut last_completed = (world.time, completed(&world));
+    for tick in 1..=args.ticks {
+        world.tick(args.delta);
+        if let Some(game_state) = &mut world.game_state {
+            game_state.take_events();
+        }
+
+        let done = completed(&world);
+        if done != last_completed.1 || world.cars.is_empty() {
+            last_completed = (world.time, done);
+        } else if world.time - last_completed.0 > args.stall_secs {
+            bail!(
+                "Deadlock at tick {} ({:.1}s): {} vehicles out and nothing completed since {:.1}s",
+                tick,
+                world.time,
+                world.cars.len(),
+                last_completed.0
+            );
+        }
+
+        if tick.is_multiple_of(args.check_every) {
+            world
+                .check_invariants()
+                .with_context(|| format!("Tick {}", tick))?;
+        }
+
+        if !tick.is_multiple_of(args.log_every) && tick != args.ticks {
+            continue;
+        }
+        let memory = world.memory_report();
+        println!(
+            "[tick {:>8} {:>9.1}s] state {:016x} save {:016x} {:>5} vehicles {:>10}",
+            tick,
+            world.time,
+            state_checksum(&world),
+            world.state_hash(),
+            world.cars.len(),
+            simulation::format_bytes(memory.total_bytes())
+        );
+        let Some(early) = &baseline else {
+            baseline = Some(memory);
+            continue;
+        };
+        if tick <= warm_up {
+            if memory.total_bytes() > early.total_bytes() {
+                baseline = Some(memory);
+            }
+            continue;
+        }
+        let limit = early.total_bytes() as f32 * (1.0 + args.memory_tolerance);
+        if memory.total_bytes() as f32 > limit {
+            let grown = memory
+                .categories
+                .iter()
+                .max_by_key(|category| {
+                    let before = early.category(category.name).map_or(0, |usage| usage.bytes);
+                    category.bytes as i64 - before as i64
+                })
+                .map_or("nothing", |category| category.name);
+            bail!(
+                "Memory grew from {} to {} by tick {}, most of it in {}:\n{}",
+                simulation::format_bytes(early.total_bytes()),
+                simulation::format_bytes(memory.total_bytes()),
+                tick,
+                grown,
+                memory
+            );
+        }
+    }
+
+    println!();
+    println!("=== MEMORY (approximate) ===");
+    println!("{}", world.memory_report());
+    println!();
+    println!(
+        "Soak passed: {} ticks ({:.0}s) with {} trips and deliveries completed",
+        args.ticks,
+        world.time,
+        completed(&world)
+    );
+    Ok(())
+}
+
+/// Print how many of the worker shifts houses asked for were worked
+fn print_employment(world: &simulation::SimWorld) {
+    let labor = world.labor_totals();
+    match labor.employment_rate() {
+        Some(rate) => println!(
+            "Employment: {:.0}% ({} of {} shifts worked, {} turned away)",
+            rate * 100.0,
+            labor.fulfilled,
+            labor.requested,
+            labor.rejected
+        ),
+        None => println!("Employment: no shifts requested"),
+    }
+}
+
+/// Print where each shop's deliveries came from, and the houses none drew on
+fn print_catchments(world: &simulation::SimWorld) {
+    for catchment in world.shop_catchments() {
+        println!(
+            "Shop {}: {} deliveries, ${} from {} factories and {} houses",
+            catchment.shop_id.0 .0,
+            catchment.deliveries,
+            catchment.revenue,
+            catchment.factories.len(),
+            catchment.apartments.len()
+        );
+    }
+    let underserved = world.underserved_apartments();
+    if !underserved.is_empty() {
+        println!("Houses no shop drew on: {}", underserved.len());
+    }
+}
+
+/// Print how many drivers of each kind set off and how they drove, when the
+/// economy mixes them
+fn print_drivers(world: &simulation::SimWorld) {
+    if world.driver_mix().is_none() {
+        return;
+    }
+    for summary in world.driver_summaries() {
+        println!(
+            "Drivers ({}): {} at speed x{:.2}, following gap x{:.2}, gap acceptance x{:.2}",
+            summary.kind.label(),
+            summary.drivers,
+            summary.average.speed_factor,
+            summary.average.following_gap,
+            summary.average.gap_acceptance
+        );
+    }
+}
+
+/// Print how many rush hours met the economy's speed goal, when it has one
+fn print_speed_goal(world: &simulation::SimWorld) {
+    let (Some(goal), Some(game_state)) = (world.speed_goal(), &world.game_state) else {
+        return;
+    };
+    println!(
+        "Rush hour speed goal ({:.1}+): met {} of {} needed, missed {}",
+        goal.min_average_speed,
+        game_state.speed_goal.met,
+        goal.rush_hours,
+        game_state.speed_goal.missed
+    );
+}
+
+/// Write a run's per-road, labor and emissions statistics and report the
+/// result
+fn save_road_stats(world: &simulation::SimWorld, path: &std::path::Path) {
+    match world.save_road_stats_csv(path) {
+        Ok(()) => println!("Wrote road statistics to {}", path.display()),
+        Err(e) => eprintln!("Error: {:#}", e),
+    }
+    let labor_path = path.with_extension("labor.csv");
+    match world.save_labor_stats_csv(&labor_path) {
+        Ok(()) => println!("Wrote labor statistics to {}", labor_path.display()),
+        Err(e) => eprintln!("Error: {:#}", e),
+    }
+    let emissions_path = path.with_extension("emissions.csv");
+    match world.save_emissions_csv(&emissions_path) {
+        Ok(()) => println!("Wrote emissions to {}", emissions_path.display()),
+        Err(e) => eprintln!("Error: {:#}", e),
+    }
+}
+
+/// Write a run's end-of-run report and report the result
+fn save_report(world: &simulation::SimWorld, path: &std::path::Path, title: &str) {
+    match world.save_run_report(path, title) {
+        Ok(()) => println!("Wrote run report to {}", path.display()),
+        Err(e) => eprintln!("Error: {:#}", e),
+    }
+}
+
+/// Entity budget warnings, alarms, the memory report and the count of
+/// recurring warnings of a headless run, what its diagnosis flags and how
+/// its path cache is sized
+#[derive(Default)]
+struct RunDiagnostics {
+    watch: simulation::BudgetWatch,
+    /// Print the memory report when the run ends
+    memory_report: bool,
+    /// How many routes to cache and which to drop first
+    path_cache: simulation::PathCacheConfig,
+    /// Alarm reports already printed
+    alarms_seen: usize,
+    /// What the diagnosis of a plain headless run counts as an anomaly
+    anomalies: simulation::AnomalyConfig,
+    /// Fail the run if the diagnosis finds anything
+    fail_on_anomalies: bool,
+}
+
+impl RunDiagnostics {
+    /// Warn about budgets the world has just gone over and alarms that have
+    /// just gone off
+    fn after_tick(&mut self, world: &simulation::SimWorld) {
+        for overrun in self.watch.check(world) {
+            eprintln!("Warning: [{:>6.1}s] {}", world.time, overrun);
+        }
+        for report in &world.alarm_reports()[self.alarms_seen..] {
+            eprintln!(
+                "Alarm: [{:>6.1}s] {}: {} {:.2}; {}",
+                report.time,
+                report.name,
+                report.metric.label(),
+                report.value,
+                report.context
+            );
+        }
+        self.alarms_seen = world.alarm_reports().len();
+    }
+
+    /// Print the memory report, if asked for, how often each alarm went off
+    /// and how many of each recurring warning there were
+    fn finish(&self, world: &simulation::SimWorld) {
+        if self.memory_report {
+            println!("=== MEMORY (approximate) ===");
+            println!("{}", world.memory_report());
+            let cache = world.path_cache_stats();
+            println!(
+                "path cache: {:.0}% of {} lookups hit, {} / {} routes, {} evicted, {} expired, {} invalidations ({} in the last minute)",
+                cache.hit_rate() * 100.0,
+                cache.hits + cache.misses,
+                cache.entries,
+                cache.capacity,
+                cache.evictions,
+                cache.expirations,
+                cache.invalidations,
+                cache.invalidations_per_minute
+            );
+            println!();
+        }
+        if !world.alarm_reports().is_empty() {
+            println!("=== ALARMS ===");
+            for (index, config) in world.alarm_configs().iter().enumerate() {
+                let count = world
+                    .alarm_reports()
+                    .iter()
+                    .filter(|report| report.alarm == index)
+                    .count();
+                println!(
+                    "{:<26} {:>8}  ({} {} for {:.0}s)",
+                    config.name,
+                    count,
+                    config.metric.label(),
+                    config.condition(),
+                    config.for_secs
+                );
+            }
+            println!();
+        }
+        if world.warnings().total() > 0 {
+            println!("=== WARNINGS ===");
+            for (kind, count) in world.warnings().counts() {
+                println!("{:<26} {:>8}", kind.label(), count);
+            }
+            println!();
+        }
+    }
+}
+
+/// Progress of a headless run, for resuming it after a crash
+#[derive(serde::Serialize, serde::Deserialize)]
+struct Checkpoint {
+    /// Mode, seed and delta of the run
+    run: String,
+    /// Ticks completed
+    ticks: u32,
+    /// [`traffic_sim::multiplayer::state_checksum`] after those ticks
+    checksum: u64,
+}
+
+/// Writes checkpoints of a headless run, and checks a resumed run catches up
+/// with the checkpoint it resumes from
+struct Checkpointer {
+    path: std::path::PathBuf,
+    /// Ticks between checkpoints
+    every: u32,
+    run: String,
+    /// Checkpoint the run is resuming from
+    resume: Option<Checkpoint>,
+}
+
+impl Checkpointer {
+    /// Checkpoint a run to `path`, resuming from the checkpoint already
+    /// there if any
+    fn open(path: &std::path::Path, every: u32, run: String) -> anyhow::Result<Self> {
+        use anyhow::Context;
+
+        anyhow::ensure!(every > 0, "Checkpoints must be at least a tick apart");
+        let resume = if path.exists() {
+            let text = std::fs::read_to_string(path)
+                .with_context(|| format!("Failed to read checkpoint {}", path.display()))?;
+            let checkpoint: Checkpoint = serde_json::from_str(&text)
+                .with_context(|| format!("Invalid checkpoint {}", path.display()))?;
+            anyhow::ensure!(
+                checkpoint.run == run,
+                "{} is a checkpoint of {}, not {}",
+                path.display(),
+                checkpoint.run,
+                run
+            );
+            println!(
+                "Resuming from the checkpoint at tick {} in {}",
+                checkpoint.ticks,
+                path.display()
+            );
+            Some(checkpoint)
+        } else {
+            None
+        };
+        Ok(Self {
+            path: path.to_path_buf(),
+            every,
+            run,
+            resume,
+        })
+    }
+
+    /// Whether a tick (counting from 1) was already run before the run
+    /// resumed
+    fn replaying(&self, tick: u32) -> bool {
+        self.resume
+            .as_ref()
+            .is_some_and(|checkpoint| tick <= checkpoint.ticks)
+    }
+
+    /// Check the world against the checkpoint being resumed from, or write a
+    /// new one if it is time; exits if the world doesn't match
+    fn after_tick(&mut self, tick: u32, world: &simulation::SimWorld) {
+        use traffic_sim::multiplayer::state_checksum;
+
+        if let Some(checkpoint) = &self.resume {
+            if tick == checkpoint.ticks {
+                if state_checksum(world) != checkpoint.checksum {
+                    eprintln!(
+                        "Error: the run doesn't match the checkpoint at tick {} (different economy?)",
+                        tick
+                    );
+                    std::process::exit(1);
+                }
+                println!("Caught up with the checkpoint at tick {}", tick);
+            }
+        }
+        if !tick.is_multiple_of(self.every) || self.replaying(tick) {
+            return;
+        }
+        let checkpoint = Checkpoint {
+            run: self.run.clone(),
+            ticks: tick,
+            checksum: state_checksum(world),
+        };
+        let written = serde_json::to_string_pretty(&checkpoint)
+            .map_err(anyhow::Error::from)
+            .and_then(|json| simulation::write_atomically(&self.path, &json));
+        if let Err(e) = written {
+            eprintln!("Warning: couldn't write checkpoint: {:#}", e);
+        }
+    }
+
+    /// Remove the checkpoint once the run has finished
+    fn finish(self) {
+        if let Err(e) = std::fs::remove_file(&self.path) {
+            if e.kind() != std::io::ErrorKind::NotFound {
+                eprintln!("Warning: couldn't remove checkpoint {}: {}", self.path.display(), e);
+            }
+        }
+    }
+}
+
+/// Name of a headless run for its checkpoints
+///
+/// Routing in the background makes a different run from routing inline.
+fn checkpoint_run(scenario: &str, seed: u64, delta: f32, route_workers: usize) -> String {
+    let run = format!("{}/seed-{}/delta-{}", scenario, seed, delta);
+    if route_workers > 0 {
+        format!("{}/background-routing", run)
+    } else {
+        run
+    }
+}
+
+/// Open the checkpoint file given on the command line, if any, exiting on
+/// error
+fn open_checkpoint(
+    path: Option<&std::path::Path>,
+    every: u32,
+    run: String,
+) -> Option<Checkpointer> {
+    let path = path?;
+    match Checkpointer::open(path, every, run) {
+        Ok(checkpointer) => Some(checkpointer),
+        Err(e) => {
+            eprintln!("Error: {:#}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Host the test world headless in real time so spectators can watch it
+///
+/// # Arguments
+/// * `addr` - Address to listen on
+/// * `ticks` - Number of simulation ticks to run
+/// * `delta` - Time delta per tick in seconds
+/// * `seed` - Random seed shared with everyone who joins
+/// * `economy` - Custom economy, if any
+/// * `content` - Content packs shared with everyone who joins
+fn run_headless_host(
+    addr: &str,
+    ticks: u32,
+    delta: f32,
+    seed: u64,
+    economy: Option<simulation::EconomyConfig>,
+    content: &simulation::ContentRegistry,
+) -> anyhow::Result<()> {
+    use traffic_sim::multiplayer::{LockstepHost, SessionSetup};
+
+    let mut setup = SessionSetup::new(seed, economy.unwrap_or_default(), content);
+    setup.test_world = true;
+    let mut world = setup.create_world()?;
+    let mut host = LockstepHost::bind(addr, setup)?;
+    println!("Hosting the test world on {}", host.local_addr()?);
+    println!("Watch with: cargo run -- --ui --spectate <this machine>:<port>");
+    println!("Ticks: {}, Delta: {}s, Seed: {}", ticks, delta, seed);
+    println!();
+
+    let tick_duration = std::time::Duration::from_secs_f32(delta);
+    let ticks_per_report = (10.0 / delta).ceil() as u32;
+    for tick in 1..=ticks {
+        let started = std::time::Instant::now();
+        host.step(&mut world, delta, Vec::new());
+        if tick % ticks_per_report == 0 {
+            println!(
+                "{:.0}s simulated - {} cars, {} player(s), {} spectator(s)",
+                world.time,
+                world.cars.len(),
+                host.client_count(),
+                host.spectator_count()
+            );
+        }
+        std::thread::sleep(tick_duration.saturating_sub(started.elapsed()));
+    }
+    Ok(())
+}
+
 /// Run the simulation in headless mode with CLI display
 ///
 /// This mode runs the simulation for a fixed number of ticks and prints
@@ -357,6 +1755,350 @@ fn run_headless_with_display(ticks: u32, delta: f32, seed: u64) {
     world.draw_map();
 }
 
+/// Load the save or test world an `inspect` command names and describe what
+/// it asks about
+/// The city in a save, or the test world of `seed`, with a line saying
+/// which it is
+fn load_city(
+    save: Option<&std::path::Path>,
+    seed: u64,
+) -> anyhow::Result<(String, simulation::SimWorld)> {
+    use anyhow::Context;
+
+    match save {
+        Some(path) => {
+            let save = simulation::SaveFile::load(path)?;
+            let mut description = format!(
+                "Save {}: version {}, seed {}, {} commands",
+                path.display(),
+                save.version,
+                save.seed,
+                save.commands.len()
+            );
+            if let Some(metadata) = &save.metadata {
+                description.push_str(&format!("\nSave metadata: {}", metadata.summary()));
+            }
+            let world = save
+                .create_world()
+                .with_context(|| format!("Failed to rebuild the city in {}", path.display()))?;
+            Ok((description, world))
+        }
+        None => Ok((
+            format!("Test world, seed {}", seed),
+            simulation::SimWorld::create_test_world_with_seed(seed),
+        )),
+    }
+}
+
+fn inspect(args: &InspectArgs) -> anyhow::Result<String> {
+    use anyhow::Context;
+    use simulation::{IntersectionId, SimId, VehicleType};
+    use std::fmt::Write as _;
+
+    let mut report = String::new();
+    let (description, world) = load_city(args.save.as_deref(), args.seed)?;
+    writeln!(report, "{}", description)?;
+
+    let asked = args.roads
+        || args.shortest.is_some()
+        || args.assign
+        || args.diff.is_some()
+        || args.map.is_some();
+    if args.stats || !asked {
+        let network = &world.road_network;
+        let two_way = network.roads().values().filter(|road| road.is_two_way).count();
+        writeln!(
+            report,
+            "Intersections: {}, roads: {} counting each direction ({} two-way)",
+            network.intersection_count(),
+            network.road_count(),
+            two_way / 2
+        )?;
+        writeln!(
+            report,
+            "Apartments: {}, factories: {}, shops: {}, special buildings: {}, decorations: {}",
+            world.apartments.len(),
+            world.factories.len(),
+            world.shops.len(),
+            world.special_buildings.len(),
+            world.decorations.len()
+        )?;
+        let signals = world
+            .intersections
+            .values()
+            .filter(|intersection| intersection.signal.is_some())
+            .count();
+        let closed = network.roads().values().filter(|road| road.closed).count();
+        writeln!(report, "Signals: {}, closed roads: {}", signals, closed)?;
+        if let Some(game_state) = &world.game_state {
+            writeln!(
+                report,
+                "Money: ${}, worker trips: {}, shop deliveries: {}, game time: {:.1}s",
+                game_state.money,
+                game_state.worker_trips_completed,
+                game_state.shop_deliveries_completed,
+                game_state.time
+            )?;
+        }
+        writeln!(report, "State hash: {:016x}", world.state_hash())?;
+    }
+
+    if let Some(path) = &args.map {
+        std::fs::write(path, world.map_png(args.map_width))
+            .with_context(|| format!("Failed to write map {}", path.display()))?;
+        writeln!(report, "Map written to {}", path.display())?;
+    }
+
+    if args.roads {
+        writeln!(report, "=== ROADS ===")?;
+        for road in world.road_network.roads().values() {
+            writeln!(
+                report,
+                "  Road {:>4}: {:>4} -> {:<4} {:>6.1} long, {}, {}, {}{}{}",
+                road.id.0 .0,
+                road.start_intersection.0 .0,
+                road.end_intersection.0 .0,
+                road.length,
+                if road.is_two_way { "two-way" } else { "one-way" },
+                road.kind.label(),
+                road.restriction.label(),
+                if road.sensor { ", sensor" } else { "" },
+                if road.closed { ", closed" } else { "" }
+            )?;
+        }
+    }
+
+    if let Some(ends) = &args.shortest {
+        let [from, to] = ends[..] else {
+            anyhow::bail!("--shortest takes two intersection IDs");
+        };
+        let (from, to) = (IntersectionId(SimId(from)), IntersectionId(SimId(to)));
+        for id in [from, to] {
+            anyhow::ensure!(
+                world.intersections.contains_key(&id),
+                "There's no intersection {}",
+                id.0 .0
+            );
+        }
+        writeln!(report, "=== SHORTEST ROUTE {} -> {} ===", from.0 .0, to.0 .0)?;
+        for vehicle_type in [VehicleType::Car, VehicleType::Truck] {
+            match world.estimate_route(from, to, vehicle_type) {
+                Some(route) => {
+                    let path: Vec<String> = std::iter::once(from)
+                        .chain(route.path)
+                        .map(|id| id.0 .0.to_string())
+                        .collect();
+                    writeln!(
+                        report,
+                        "  {:?}: {:.1} long, about {:.1}s, via {}",
+                        vehicle_type,
+                        route.distance,
+                        route.secs,
+                        path.join(" -> ")
+                    )?;
+                }
+                None => writeln!(report, "  {:?}: no route", vehicle_type)?,
+            }
+        }
+    }
+
+    if args.assign {
+        let assignment = world.assign_traffic(&simulation::AssignmentConfig::default());
+        writeln!(report, "=== TRAFFIC ASSIGNMENT ===")?;
+        writeln!(
+            report,
+            "Demand: {:.1} vehicles/min routed, {:.1} with no route",
+            assignment.routed_per_minute, assignment.unrouted_per_minute
+        )?;
+        writeln!(
+            report,
+            "Settled in {} rounds (relative gap {:.4}), average trip {:.1}s",
+            assignment.iterations,
+            assignment.relative_gap,
+            assignment.average_trip_secs()
+        )?;
+        for flow in assignment.busiest_roads(10) {
+            let Some(road) = world.road_network.get_road(flow.road_id) else {
+                continue;
+            };
+            writeln!(
+                report,
+                "  Road {:>4}: {:>4} -> {:<4} {:>5.1}/min, {:>3.0}% of capacity, {:.1}s (free flow {:.1}s)",
+                road.id.0 .0,
+                road.start_intersection.0 .0,
+                road.end_intersection.0 .0,
+                flow.flow,
+                flow.volume_to_capacity * 100.0,
+                flow.travel_secs,
+                flow.free_flow_secs
+            )?;
+        }
+    }
+
+    if let Some(path) = &args.diff {
+        let other = simulation::SaveFile::load(path)?
+            .create_world()
+            .with_context(|| format!("Failed to rebuild the city in {}", path.display()))?;
+        writeln!(report, "=== ROAD CHANGES IN {} ===", path.display())?;
+        write!(report, "{}", world.road_network.diff(&other.road_network))?;
+    }
+    Ok(report)
+}
+
+/// Fit a save's demand to road counts and write the weights into its
+/// economy, returning how close each counted road got
+fn calibrate(args: &CalibrateArgs) -> anyhow::Result<String> {
+    use anyhow::Context;
+    use std::fmt::Write as _;
+
+    let mut save = simulation::SaveFile::load(&args.save)?;
+    let world = save
+        .create_world()
+        .with_context(|| format!("Failed to rebuild the city in {}", args.save.display()))?;
+    let counts = simulation::load_road_counts(&args.counts)?;
+    let config = simulation::CalibrationConfig {
+        tolerance: args.tolerance,
+        max_rounds: args.max_rounds,
+        ..Default::default()
+    };
+    let calibration = world.calibrate_demand(&counts, &config)?;
+
+    let mut report = String::new();
+    writeln!(report, "=== DEMAND CALIBRATION ===")?;
+    if calibration.converged {
+        writeln!(
+            report,
+            "Every counted road within {:.0}% after {} rounds",
+            args.tolerance * 100.0,
+            calibration.rounds
+        )?;
+    } else {
+        writeln!(
+            report,
+            "Closest fit after {} rounds is off by up to {:.0}% (tolerance {:.0}%)",
+            calibration.rounds,
+            calibration.worst_error() * 100.0,
+            args.tolerance * 100.0
+        )?;
+    }
+    for road in &calibration.roads {
+        let Some(built) = world.road_network.get_road(road.road_id) else {
+            continue;
+        };
+        writeln!(
+            report,
+            "  Road {:>4}: {:>4} -> {:<4} counted {:>5.1}/min, simulated {:>5.1}/min ({:>3.0}% off)",
+            built.id.0 .0,
+            built.start_intersection.0 .0,
+            built.end_intersection.0 .0,
+            road.target,
+            road.flow,
+            road.error() * 100.0
+        )?;
+    }
+
+    // A save with the game's progress replays its economy from there
+    save.economy.demand_weights = calibration.weights.clone();
+    if let Some(game_state) = &mut save.game_state {
+        game_state.economy.demand_weights = calibration.weights;
+    }
+    let output = args.output.as_deref().unwrap_or(&args.save);
+    save.save(output)?;
+    writeln!(report, "Wrote the calibrated demand to {}", output.display())?;
+    Ok(report)
+}
+
+/// How the UI takes part in a multiplayer session
+#[cfg(feature = "ui")]
+enum NetworkRole {
+    Offline,
+    Host { addr: String, seed: u64 },
+    Join(String),
+    Spectate(String),
+}
+
+/// Start autosaving an offline game to `path`
+///
+/// If the last game saving there didn't exit cleanly, the player is offered
+/// its autosave; otherwise the world is rebuilt from a fresh save so the two
+/// start out the same.
+#[cfg(feature = "ui")]
+fn start_autosave(
+    path: std::path::PathBuf,
+    seed: u64,
+    sim_world: &mut ui::SimWorldResource,
+) -> anyhow::Result<ui::Autosave> {
+    use simulation::{RunningMarker, SaveFile};
+    use std::io::Write;
+
+    let mut save = None;
+    if RunningMarker::left_behind(&path) && path.exists() {
+        let restorable = SaveFile::load(&path);
+        let metadata = restorable.as_ref().ok().and_then(|save| save.metadata.as_ref());
+        if let Some(metadata) = metadata {
+            println!("Autosave: {}", metadata.summary());
+        }
+        print!(
+            "The last game didn't exit cleanly. Restore the autosave from {}? [Y/n] ",
+            path.display()
+        );
+        std::io::stdout().flush()?;
+        let mut answer = String::new();
+        std::io::stdin().read_line(&mut answer)?;
+        if !answer.trim().eq_ignore_ascii_case("n") {
+            let restored = restorable?;
+            println!("Restored {} commands", restored.commands.len());
+            save = Some(restored);
+        }
+    }
+    let save = save.unwrap_or_else(|| SaveFile::of_world(seed, &sim_world.0));
+    sim_world.0 = save.create_world()?;
+    ui::Autosave::start(save, path)
+}
+
+/// Start hosting or join a multiplayer session, replacing the world with the
+/// session's shared one
+#[cfg(feature = "ui")]
+fn start_multiplayer(
+    network: NetworkRole,
+    sim_world: &mut ui::SimWorldResource,
+) -> anyhow::Result<ui::MultiplayerSession> {
+    use traffic_sim::multiplayer::{LockstepClient, LockstepHost, SessionSetup};
+
+    match network {
+        NetworkRole::Offline => Ok(ui::MultiplayerSession::Offline),
+        NetworkRole::Host { addr, seed } => {
+            let economy = sim_world
+                .0
+                .game_state
+                .as_ref()
+                .map(|game_state| game_state.economy.clone())
+                .unwrap_or_default();
+            let mut setup = SessionSetup::new(seed, economy, &sim_world.0.content);
+            setup.water = sim_world.0.water.clone();
+            setup.heightmap = sim_world.0.heightmap.clone();
+            setup.bounds = sim_world.0.configured_bounds();
+            setup.driving_side = sim_world.0.driving_side;
+            sim_world.0 = setup.create_world()?;
+            let host = LockstepHost::bind(addr.as_str(), setup)?;
+            println!("Hosting co-op session on {}", host.local_addr()?);
+            Ok(ui::MultiplayerSession::Host(host))
+        }
+        NetworkRole::Join(addr) => {
+            let mut client = LockstepClient::connect(addr.as_str())?;
+            sim_world.0 = client.create_world()?;
+            println!("Joined co-op session at {} (tick {})", addr, client.tick());
+            Ok(ui::MultiplayerSession::Client(client))
+        }
+        NetworkRole::Spectate(addr) => {
+            let mut client = LockstepClient::spectate(addr.as_str())?;
+            sim_world.0 = client.create_world()?;
+            println!("Spectating session at {} (tick {})", addr, client.tick());
+            Ok(ui::MultiplayerSession::Client(client))
+        }
+    }
+}
+
 #[cfg(feature = "ui")]
 /// Run the simulation with the Bevy game engine UI
 ///
@@ -364,22 +2106,138 @@ fn run_headless_with_display(ticks: u32, delta: f32, seed: u64) {
 /// - Build roads and buildings to create delivery networks
 /// - Earn money from successful deliveries
 /// - Reach the goal to win the game!
-fn run_with_ui() {
-    use ui::UI_STARTING_BUDGET;
+#[allow(clippy::too_many_arguments)]
+fn run_with_ui(
+    economy: Option<simulation::EconomyConfig>,
+    content: simulation::ContentRegistry,
+    tutorial: bool,
+    cinematic: bool,
+    camera_tour: Option<simulation::CameraTour>,
+    river: bool,
+    heightmap: Option<simulation::Heightmap>,
+    left_hand_traffic: bool,
+    achievements_path: std::path::PathBuf,
+    best_scores_path: std::path::PathBuf,
+    network: NetworkRole,
+    seed: u64,
+    autosave_path: Option<std::path::PathBuf>,
+    budgets: simulation::EntityBudgets,
+    route_workers: usize,
+    report_path: Option<std::path::PathBuf>,
+    watch_economy: Option<std::path::PathBuf>,
+) {
+    use ui::SimWorldResource;
     use bevy::log::LogPlugin;
     use bevy::prelude::*;
 
+    // Without a custom economy the UI runs as a sandbox with a larger budget
+    let sandbox = economy.is_none();
+    let scenario = if sandbox {
+        ui::UI_SCENARIO
+    } else {
+        "custom-economy"
+    };
+    let mut sim_world = economy
+        .map(SimWorldResource::with_economy)
+        .unwrap_or_default();
+    sim_world.0.content = content;
+    if river {
+        sim_world.0.add_water(simulation::RIVER);
+    }
+    if let Some(heightmap) = heightmap {
+        sim_world.0.heightmap = Some(heightmap);
+    }
+    if left_hand_traffic {
+        sim_world.0.driving_side = simulation::DrivingSide::Left;
+    }
+    // A tour is played over its own city
+    if let Some(tour) = &camera_tour {
+        match tour.create_world(seed) {
+            Ok(mut world) => {
+                if world.game_state.is_none() {
+                    world.game_state = sim_world.0.game_state.take();
+                }
+                sim_world.0 = world;
+            }
+            Err(e) => {
+                eprintln!("Error: {:#}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+    // Multiplayer worlds belong to the session, so only offline games autosave
+    let autosave = match (&network, autosave_path) {
+        (NetworkRole::Offline, Some(path)) => match start_autosave(path, seed, &mut sim_world) {
+            Ok(autosave) => Some(autosave),
+            Err(e) => {
+                eprintln!("Error: {:#}", e);
+                std::process::exit(1);
+            }
+        },
+        _ => None,
+    };
+    let session = match start_multiplayer(network, &mut sim_world) {
+        Ok(session) => session,
+        Err(e) => {
+            eprintln!("Error: {:#}", e);
+            std::process::exit(1);
+        }
+    };
+    // Every peer in a session has to route the same way, so only offline
+    // games route in the background
+    if matches!(session, ui::MultiplayerSession::Offline) {
+        sim_world.0.set_route_workers(route_workers);
+    }
+    let economy = sim_world
+        .0
+        .game_state
+        .as_ref()
+        .map(|game_state| game_state.economy.clone())
+        .unwrap_or_default();
+
     println!("===========================================");
     println!("  Traffic Management Game");
     println!("===========================================");
     println!();
     println!("🎮 OBJECTIVE:");
-    println!("  Complete 50 shop deliveries OR earn $5000");
+    println!(
+        "  Complete {} shop deliveries OR earn ${}",
+        economy.goal_deliveries, economy.goal_money
+    );
     println!();
     println!("💰 ECONOMICS:");
-    println!("  Starting Budget: ${} (UI sandbox)", UI_STARTING_BUDGET);
-    println!("  Road: $50 | House: $200 | Factory: $500 | Shop: $300");
-    println!("  Earn $10 per worker trip, $50 per shop delivery");
+    if sandbox {
+        println!("  Starting Budget: ${} (UI sandbox)", economy.starting_budget);
+    } else {
+        println!("  Starting Budget: ${}", economy.starting_budget);
+    }
+    println!(
+        "  Road: ${} | House: ${} | Factory: ${} | Shop: ${}",
+        economy.cost_road, economy.cost_apartment, economy.cost_factory, economy.cost_shop
+    );
+    println!(
+        "  School: ${} | Hospital: ${} | Depot: ${} | Rail Terminal: ${}",
+        economy.cost_school, economy.cost_hospital, economy.cost_depot, economy.cost_terminal
+    );
+    println!("  Bridge: ${} | Ferry: ${}", economy.cost_bridge, economy.cost_ferry);
+    println!("  Tree: ${} | Park: ${}", economy.cost_tree, economy.cost_park);
+    println!("  Bus stop: ${}", economy.cost_bus_stop);
+    println!("  Roads cut into steep hillsides cost more, and climbs slow traffic down");
+    println!(
+        "  Earn ${} per worker trip, ${} per shop delivery",
+        economy.revenue_worker_delivery, economy.revenue_shop_delivery
+    );
+    println!(
+        "  Cleanup: ${} per breakdown, ${} per collision (insurance: ${} every {}s)",
+        economy.cost_breakdown_cleanup,
+        economy.cost_collision_cleanup,
+        economy.insurance_premium,
+        economy.insurance_period_secs
+    );
+    println!(
+        "  Replace a worn car or truck for ${}",
+        economy.cost_vehicle_replacement
+    );
     println!("  Start with a blank map so you can design your own layout");
     println!();
     println!("🕹️ CONTROLS:");
@@ -388,6 +2246,7 @@ fn run_with_ui() {
     println!("    Q/E         - Rotate camera around center");
     println!("    Z/X         - Zoom in/out");
     println!("    Click+Drag  - Orbital rotation");
+    println!("    F7          - Tour the city's highlights (or restart a --camera-tour)");
     println!("    ESC         - Exit");
     println!();
     println!("  Building:");
@@ -395,6 +2254,29 @@ fn run_with_ui() {
     println!("    2 or Button - House mode (click to place)");
     println!("    3 or Button - Factory mode (click to place)");
     println!("    4 or Button - Shop mode (click to place)");
+    println!("    5 or Button - Signal mode (click an intersection)");
+    println!("    6 or Button - School mode (click to place)");
+    println!("    7 or Button - Hospital mode (click to place)");
+    println!("    8 or Button - Cul-de-sac mode (click entrance, then dead end; [ ] lots)");
+    println!("    F8 or Button - Junction mode (click centre, then direction; [ ] preset)");
+    println!("    B or Button - Bridge mode (click two points, across water)");
+    println!("    F or Button - Ferry mode (click two landings, across water)");
+    println!("    O or Button - Tree mode (click to plant, clear of roads)");
+    println!("    P or Button - Park mode (click to place, clear of roads)");
+    println!("    Drag intersection - Move it (no build mode selected)");
+    println!("    Right-click - Road menu (vehicle restrictions, direction)");
+    println!("    V           - Cycle content pack building types");
+    if tutorial {
+        println!("    F1          - Skip the tutorial");
+    }
+    println!("    H           - Show achievements");
+    println!("    K           - Show memory use, entity budgets and warning counts");
+    println!("    L           - Preview routes from the intersection under the cursor");
+    println!("    N           - Show shop catchments");
+    println!("    I           - Take out or cancel insurance");
+    println!("    R           - Replace worn vehicles");
+    println!("    C           - Copy the city into an experimental world");
+    println!("    Tab         - Switch between the live and experimental worlds");
     println!();
     println!("💡 TIPS:");
     println!("  • Houses send workers to factories");
@@ -404,25 +2286,51 @@ fn run_with_ui() {
     println!("===========================================");
     println!();
 
-    App::new()
-        .add_plugins(
-            DefaultPlugins
-                .set(LogPlugin {
-                    filter: "warn,traffic_sim=debug".to_string(),
-                    level: bevy::log::Level::DEBUG,
-                    ..default()
-                })
-                .set(WindowPlugin {
-                    primary_window: Some(Window {
-                        title: "Traffic Management Game".into(),
-                        resolution: (1280, 720).into(),
-                        ..default()
-                    }),
+    let mut app = App::new();
+    app.add_plugins(
+        DefaultPlugins
+            .set(LogPlugin {
+                filter: "warn,traffic_sim=debug".to_string(),
+                level: bevy::log::Level::DEBUG,
+                ..default()
+            })
+            .set(WindowPlugin {
+                primary_window: Some(Window {
+                    title: "Traffic Management Game".into(),
+                    resolution: (1280, 720).into(),
                     ..default()
                 }),
-        )
-        .add_plugins(ui::TrafficSimUIPlugin)
-        .run();
+                ..default()
+            }),
+    )
+    .insert_resource(sim_world)
+    .insert_resource(session)
+    .insert_resource(if tutorial {
+        ui::Tutorial::started()
+    } else {
+        ui::Tutorial::default()
+    })
+    .insert_resource(match camera_tour {
+        Some(tour) => ui::Cinematic::scripted(tour),
+        None if cinematic => ui::Cinematic::started(),
+        None => ui::Cinematic::default(),
+    })
+    .insert_resource(ui::Achievements::load(achievements_path))
+    .insert_resource(ui::RunScores::load(scenario, best_scores_path))
+    .insert_resource(ui::RecapScreen::new(seed))
+    .insert_resource(ui::EntityBudgetWatch(simulation::BudgetWatch::new(budgets)))
+    .add_plugins(ui::TrafficSimUIPlugin);
+    if let Some(autosave) = autosave {
+        app.insert_resource(autosave);
+    }
+    if let Some(path) = report_path {
+        app.insert_resource(ui::RunReport(path));
+    }
+    if let Some(path) = watch_economy {
+        println!("Watching {} for economy changes", path.display());
+        app.insert_resource(ui::EconomyReloader::new(simulation::EconomyWatcher::new(path)));
+    }
+    app.run();
 }
 
 /// Helper function to run a simulation test with validation
@@ -448,8 +2356,8 @@ fn run_simulation_test(ticks: u32, delta: f32, seed: u64) -> (bool, usize, usize
     println!("Ticks: {}, Delta: {}s, Seed: {}", ticks, delta, seed);
     println!();
 
-    let (validation_passed, total_deliveries, max_cars_observed, errors) =
-        run_simulation_validation(ticks, delta, seed);
+    let (validation_passed, total_deliveries, max_cars_observed, errors, _summary) =
+        run_simulation_validation(ticks, delta, seed, ValidationOptions::default());
 
     // Print validation results (same as headless mode but with "TEST" prefix)
     print_test_validation_results(
@@ -507,7 +2415,6 @@ mod tests {
 
         // Assert reasonable number of deliveries for 1000 ticks
         // We expect at least MIN_EXPECTED_DELIVERIES to ensure the simulation is functioning
-        // Note: Some non-determinism exists even with seeding due to HashMap iteration order
         assert!(
             total_deliveries >= MIN_EXPECTED_DELIVERIES,
             "Expected at least {} deliveries in 1000 ticks, got {}. The simulation may not be functioning properly.",
@@ -920,4 +2827,132 @@ mod tests {
 
         println!("TRAFFIC DENSITY TEST PASSED");
     }
+
+    /// Tests that a headless run resumes from the checkpoint a crashed run
+    /// left behind, and that checkpoints of other runs are refused
+    #[test]
+    fn test_checkpoint_resume() {
+        let path = std::env::temp_dir().join(format!(
+            "traffic_sim_checkpoint_{}.json",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&path);
+        let run = "test-world/seed-7/delta-0.1".to_string();
+
+        // A run that dies after 100 ticks, checkpointing every 40
+        let mut checkpoint = Checkpointer::open(&path, 40, run.clone()).unwrap();
+        let mut world = simulation::SimWorld::create_test_world_with_seed(7);
+        world.game_state = Some(simulation::GameState::with_economy(
+            simulation::EconomyConfig::default(),
+        ));
+        for tick in 1..=100 {
+            world.tick(0.1);
+            checkpoint.after_tick(tick, &world);
+        }
+        drop(checkpoint);
+        let left: Checkpoint =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(left.ticks, 80);
+
+        assert!(Checkpointer::open(&path, 40, "test-world/seed-8/delta-0.1".to_string()).is_err());
+
+        // Resuming re-simulates up to tick 80, where the checksums must match
+        let resumed = Checkpointer::open(&path, 40, run).unwrap();
+        assert!(resumed.replaying(80) && !resumed.replaying(81));
+        let (validation_passed, _, _, errors, _) = run_simulation_validation(
+            120,
+            0.1,
+            7,
+            ValidationOptions {
+                checkpoint: Some(resumed),
+                ..Default::default()
+            },
+        );
+        assert!(validation_passed && errors.is_empty());
+        assert!(!path.exists(), "A finished run removes its checkpoint");
+    }
+
+    /// Tests that inspecting a save describes it without running it, and
+    /// refuses routes to intersections it doesn't have
+    #[test]
+    fn test_inspect_save() {
+        let mut args = InspectArgs {
+            save: Some(
+                std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+                    .join("tests/saves")
+                    .join(format!("v{}.json", simulation::SAVE_VERSION)),
+            ),
+            seed: 42,
+            roads: true,
+            shortest: Some(vec![0, 10]),
+            stats: true,
+            assign: false,
+            diff: None,
+            map: None,
+            map_width: 600,
+        };
+        let report = inspect(&args).unwrap();
+        assert!(report.contains("Apartments: 1, factories: 1, shops: 1"));
+        assert!(report.contains("Save metadata: saved") && report.contains("$"));
+        assert!(report.contains("worker trips: 5"));
+        assert!(report.contains("Road   11:    4 -> 10     15.0 long, two-way, bridge"));
+        assert!(report.contains("Car: 75.0 long") && report.contains("via 0 -> 1 -> 4 -> 10"));
+
+        // Asking for nothing in particular gives the stats
+        args.roads = false;
+        args.shortest = None;
+        args.stats = false;
+        let report = inspect(&args).unwrap();
+        assert!(report.contains("Intersections: 6") && !report.contains("=== ROADS ==="));
+
+        // The assignment sends the save's commuters and deliveries over the
+        // bridge
+        args.assign = true;
+        let report = inspect(&args).unwrap();
+        assert!(report.contains("=== TRAFFIC ASSIGNMENT ===") && !report.contains("Intersections"));
+        assert!(report.contains("0.0 with no route") && report.contains("Road   11:"));
+
+        // A save's roads don't differ from its own
+        args.assign = false;
+        args.diff = args.save.clone();
+        let report = inspect(&args).unwrap();
+        assert!(report.contains("=== ROAD CHANGES IN") && report.contains("No differences"));
+        args.diff = Some("no/such/save.json".into());
+        assert!(inspect(&args).is_err());
+        args.diff = None;
+
+        // The map is drawn without running the city
+        let map = std::env::temp_dir().join(format!("traffic_sim_map_{}.png", std::process::id()));
+        args.map = Some(map.clone());
+        let report = inspect(&args).unwrap();
+        assert!(report.contains("Map written to") && !report.contains("Intersections"));
+        let png = std::fs::read(&map).unwrap();
+        std::fs::remove_file(&map).unwrap();
+        assert!(png.starts_with(b"\x89PNG") && png[16..20] == 600u32.to_be_bytes());
+        args.map = None;
+
+        args.shortest = Some(vec![0, 999]);
+        assert!(inspect(&args).is_err());
+    }
+
+    /// Tests that a short soak of an autopilot city passes, and that one
+    /// allowing no time between completed trips counts as deadlocked
+    #[test]
+    fn test_soak() {
+        let mut args = SoakArgs {
+            seed: 42,
+            build_ticks: 3000,
+            ticks: 3000,
+            delta: 0.1,
+            check_every: 100,
+            log_every: 1000,
+            memory_tolerance: 0.5,
+            stall_secs: 600.0,
+        };
+        run_soak(&args).unwrap();
+
+        args.stall_secs = 0.0;
+        let error = format!("{:#}", run_soak(&args).unwrap_err());
+        assert!(error.starts_with("Deadlock"), "{}", error);
+    }
 }
@@ -0,0 +1,644 @@
+//! LAN co-op prototype: several players building in one shared world
+//!
+//! Uses deterministic lockstep. The host is authoritative: every tick it
+//! gathers the build commands issued by itself and by each client, applies
+//! them, advances the simulation and broadcasts the result as a [`Turn`]. Clients
+//! never tick on their own; they apply each turn exactly as the host did.
+//! The simulation is deterministic for a given seed, so every peer ends up
+//! with the same world, and each turn carries a checksum of the host's state
+//! so a client can tell if it has drifted. Players who join late receive the
+//! full turn history and replay it to catch up.
+//!
+//! Spectators join the same way but can't build. They are sent turns in
+//! batches covering [`SPECTATOR_BATCH_SECS`] of simulated time and play each
+//! batch back at real-time pace, so viewers see smooth traffic while the host
+//! only sends a couple of small messages a second.
+//!
+//! Messages are newline-delimited JSON over TCP.
+
+use anyhow::{anyhow, bail, Context, Result};
+use log::{info, warn};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::io::{BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::sync::Mutex;
+
+use crate::simulation::{
+    BuildCommand, ContentPack, ContentRegistry, DrivingSide, EconomyConfig, GameState, Heightmap,
+    SimWorld, WaterRegion, WorldBounds,
+};
+
+/// Version of the message format; peers must match exactly
+pub const PROTOCOL_VERSION: u32 = 31;
+
+/// Simulated seconds of turns sent to spectators in each batch
+pub const SPECTATOR_BATCH_SECS: f32 = 0.5;
+
+/// Simulated seconds a spectator may fall behind before skipping ahead
+pub const SPECTATOR_MAX_DELAY_SECS: f32 = 2.0;
+
+/// Everything a peer needs to build the session's starting world
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionSetup {
+    pub protocol_version: u32,
+    /// Seed for the simulation's RNG
+    pub seed: u64,
+    pub economy: EconomyConfig,
+    /// Content pack definitions loaded by the host
+    pub content: ContentPack,
+    /// Start from the prebuilt test world instead of a blank map
+    #[serde(default)]
+    pub test_world: bool,
+    /// Water on the map
+    #[serde(default)]
+    pub water: Vec<WaterRegion>,
+    /// Ground heights, if the map isn't flat
+    #[serde(default)]
+    pub heightmap: Option<Heightmap>,
+    /// The rectangle the city is built in, if the host's map sets one
+    #[serde(default)]
+    pub bounds: Option<WorldBounds>,
+    /// Side of the road traffic drives on
+    #[serde(default)]
+    pub driving_side: DrivingSide,
+}
+
+impl SessionSetup {
+    /// Describe a session using the host's economy and content packs
+    pub fn new(seed: u64, economy: EconomyConfig, content: &ContentRegistry) -> Self {
+        Self {
+            protocol_version: PROTOCOL_VERSION,
+            seed,
+            economy,
+            content: ContentPack {
+                buildings: content.buildings().to_vec(),
+                vehicles: content.vehicles().to_vec(),
+            },
+            test_world: false,
+            water: Vec::new(),
+            heightmap: None,
+            bounds: None,
+            driving_side: DrivingSide::default(),
+        }
+    }
+
+    /// Build the seeded starting world every peer begins from
+    pub fn create_world(&self) -> Result<SimWorld> {
+        let mut world = SimWorld::new_with_seed(self.seed);
+        world.game_state = Some(GameState::with_economy(self.economy.clone()));
+        world
+            .content
+            .register_pack(self.content.clone())
+            .context("Failed to register the session's content packs")?;
+        if let Some(heightmap) = &self.heightmap {
+            world.set_heightmap(heightmap.clone())?;
+        }
+        if self.test_world {
+            world = SimWorld::build_test_world(world);
+        }
+        for region in &self.water {
+            world.add_water(*region);
+        }
+        world.set_bounds(self.bounds)?;
+        world.driving_side = self.driving_side;
+        Ok(world)
+    }
+}
+
+/// One or more simulation ticks and the commands applied before them
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Turn {
+    /// Index of the first tick this turn covers
+    pub tick: u64,
+    /// Number of ticks this turn covers
+    pub ticks: u32,
+    /// Time delta of each tick in seconds
+    pub delta: f32,
+    /// Commands applied before the first tick, in order
+    pub commands: Vec<BuildCommand>,
+    /// [`state_checksum`] of the host's world after the last tick
+    pub checksum: u64,
+}
+
+impl Turn {
+    /// Apply the turn's commands and run its ticks, returning the new checksum
+    ///
+    /// Commands that fail (e.g. for lack of funds) are logged and skipped; they
+    /// fail identically on every peer.
+    pub fn run(&self, world: &mut SimWorld) -> u64 {
+        world.apply_commands(&self.commands);
+        for _ in 0..self.ticks {
+            world.tick(self.delta);
+        }
+        state_checksum(world)
+    }
+
+    /// Simulated seconds this turn covers
+    pub fn duration(&self) -> f32 {
+        self.ticks as f32 * self.delta
+    }
+
+    /// Fold the following turn into this one if it has no commands of its
+    /// own, returning whether it was merged
+    fn absorb(&mut self, next: &Turn) -> bool {
+        if !next.commands.is_empty() || next.delta != self.delta {
+            return false;
+        }
+        self.ticks += next.ticks;
+        self.checksum = next.checksum;
+        true
+    }
+}
+
+/// Append a turn to a list, merging command-free ticks into the last turn
+fn push_turn(turns: &mut Vec<Turn>, turn: Turn) {
+    if let Some(last) = turns.last_mut() {
+        if last.absorb(&turn) {
+            return;
+        }
+    }
+    turns.push(turn);
+}
+
+/// Hash of the parts of the world that drift first if peers diverge
+pub fn state_checksum(world: &SimWorld) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    world.time.to_bits().hash(&mut hasher);
+    world.road_network.road_count().hash(&mut hasher);
+    world.apartments.len().hash(&mut hasher);
+    world.factories.len().hash(&mut hasher);
+    world.shops.len().hash(&mut hasher);
+    world.special_buildings.len().hash(&mut hasher);
+    world.decorations.len().hash(&mut hasher);
+    if let Some(game_state) = &world.game_state {
+        game_state.money.hash(&mut hasher);
+        game_state.worker_trips_completed.hash(&mut hasher);
+        game_state.shop_deliveries_completed.hash(&mut hasher);
+        game_state.incidents.hash(&mut hasher);
+    }
+    for car in world.cars.values() {
+        car.id.0 .0.hash(&mut hasher);
+        car.position.x.to_bits().hash(&mut hasher);
+        car.position.z.to_bits().hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+/// Messages sent from the host to clients
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HostMessage {
+    /// First message on a new connection
+    Welcome {
+        setup: Box<SessionSetup>,
+        /// Every turn played so far
+        history: Vec<Turn>,
+    },
+    /// The next turn to apply
+    Turn(Turn),
+}
+
+/// Messages sent from a client to the host
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ClientMessage {
+    /// A build command to include in the next turn
+    Command(BuildCommand),
+    /// Watch without building; turns are then sent in batches
+    Spectate,
+}
+
+/// A TCP connection exchanging newline-delimited JSON messages
+///
+/// Incoming messages are read and parsed on a background thread so polling
+/// never blocks the game loop.
+struct Connection<In> {
+    writer: TcpStream,
+    incoming: Mutex<Receiver<Result<In, String>>>,
+    peer: SocketAddr,
+}
+
+impl<In: DeserializeOwned + Send + 'static> Connection<In> {
+    fn new(stream: TcpStream) -> Result<Self> {
+        stream
+            .set_nonblocking(false)
+            .context("Failed to configure connection")?;
+        stream.set_nodelay(true).ok();
+        let peer = stream.peer_addr().context("Failed to read peer address")?;
+        let reader = stream.try_clone().context("Failed to clone connection")?;
+
+        let (sender, receiver) = mpsc::channel();
+        std::thread::spawn(move || {
+            for line in BufReader::new(reader).lines() {
+                let message = match line {
+                    Ok(line) => serde_json::from_str(&line).map_err(|e| e.to_string()),
+                    Err(e) => Err(e.to_string()),
+                };
+                let failed = message.is_err();
+                if sender.send(message).is_err() || failed {
+                    break;
+                }
+            }
+        });
+
+        Ok(Self {
+            writer: stream,
+            incoming: Mutex::new(receiver),
+            peer,
+        })
+    }
+
+    fn send<Out: Serialize>(&mut self, message: &Out) -> Result<()> {
+        let mut line = serde_json::to_vec(message).context("Failed to serialize message")?;
+        line.push(b'\n');
+        self.writer
+            .write_all(&line)
+            .with_context(|| format!("Failed to send to {}", self.peer))
+    }
+
+    /// Next message if one has arrived; errors once the peer has gone
+    fn try_recv(&self) -> Result<Option<In>> {
+        let incoming = self
+            .incoming
+            .lock()
+            .map_err(|_| anyhow!("Connection poisoned"))?;
+        match incoming.try_recv() {
+            Ok(Ok(message)) => Ok(Some(message)),
+            Ok(Err(e)) => bail!("Bad message from {}: {}", self.peer, e),
+            Err(TryRecvError::Empty) => Ok(None),
+            Err(TryRecvError::Disconnected) => bail!("{} disconnected", self.peer),
+        }
+    }
+
+    /// Wait for the next message
+    fn recv(&self) -> Result<In> {
+        let incoming = self
+            .incoming
+            .lock()
+            .map_err(|_| anyhow!("Connection poisoned"))?;
+        match incoming.recv() {
+            Ok(Ok(message)) => Ok(message),
+            Ok(Err(e)) => bail!("Bad message from {}: {}", self.peer, e),
+            Err(_) => bail!("{} disconnected", self.peer),
+        }
+    }
+}
+
+/// A connected player or spectator, as seen by the host
+struct Peer {
+    connection: Connection<ClientMessage>,
+    spectator: bool,
+    /// Turns not yet sent to a spectator
+    batch: Vec<Turn>,
+}
+
+impl Peer {
+    /// Send a turn now (players) or once a batch is full (spectators)
+    fn send_turn(&mut self, turn: &Turn) -> Result<()> {
+        if !self.spectator {
+            return self.connection.send(&HostMessage::Turn(turn.clone()));
+        }
+        push_turn(&mut self.batch, turn.clone());
+        let batched: f32 = self.batch.iter().map(Turn::duration).sum();
+        if batched >= SPECTATOR_BATCH_SECS {
+            for turn in std::mem::take(&mut self.batch) {
+                self.connection.send(&HostMessage::Turn(turn))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The authoritative peer that runs the simulation and orders all commands
+pub struct LockstepHost {
+    listener: TcpListener,
+    peers: Vec<Peer>,
+    setup: SessionSetup,
+    /// Turns played so far, with runs of command-free ticks merged
+    history: Vec<Turn>,
+    next_tick: u64,
+}
+
+impl LockstepHost {
+    /// Start listening for players
+    pub fn bind(addr: impl ToSocketAddrs, setup: SessionSetup) -> Result<Self> {
+        let listener = TcpListener::bind(addr).context("Failed to open multiplayer port")?;
+        listener
+            .set_nonblocking(true)
+            .context("Failed to configure multiplayer port")?;
+        Ok(Self {
+            listener,
+            peers: Vec::new(),
+            setup,
+            history: Vec::new(),
+            next_tick: 0,
+        })
+    }
+
+    /// Address players connect to
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        self.listener
+            .local_addr()
+            .context("Failed to read multiplayer address")
+    }
+
+    /// Session the host is running
+    pub fn setup(&self) -> &SessionSetup {
+        &self.setup
+    }
+
+    /// Number of connected players, not counting spectators
+    pub fn client_count(&self) -> usize {
+        self.peers.iter().filter(|peer| !peer.spectator).count()
+    }
+
+    /// Number of connected spectators
+    pub fn spectator_count(&self) -> usize {
+        self.peers.iter().filter(|peer| peer.spectator).count()
+    }
+
+    /// Number of ticks played so far
+    pub fn tick(&self) -> u64 {
+        self.next_tick
+    }
+
+    /// Play one tick: admit new players, gather everyone's commands, apply
+    /// them, advance the world and broadcast the turn
+    pub fn step(
+        &mut self,
+        world: &mut SimWorld,
+        delta: f32,
+        local_commands: Vec<BuildCommand>,
+    ) -> Turn {
+        self.accept_peers();
+
+        let mut commands = local_commands;
+        self.peers.retain_mut(|peer| loop {
+            match peer.connection.try_recv() {
+                Ok(Some(ClientMessage::Command(command))) if peer.spectator => {
+                    warn!(
+                        "Ignoring {} from spectator {}",
+                        command.describe(),
+                        peer.connection.peer
+                    );
+                }
+                Ok(Some(ClientMessage::Command(command))) => commands.push(command),
+                Ok(Some(ClientMessage::Spectate)) => {
+                    info!("{} is spectating", peer.connection.peer);
+                    peer.spectator = true;
+                }
+                Ok(None) => break true,
+                Err(e) => {
+                    info!("Player left: {:#}", e);
+                    break false;
+                }
+            }
+        });
+
+        let mut turn = Turn {
+            tick: self.next_tick,
+            ticks: 1,
+            delta,
+            commands,
+            checksum: 0,
+        };
+        turn.checksum = turn.run(world);
+        self.next_tick += 1;
+
+        self.peers.retain_mut(|peer| match peer.send_turn(&turn) {
+            Ok(()) => true,
+            Err(e) => {
+                info!("Player left: {:#}", e);
+                false
+            }
+        });
+
+        // Keep the full history so late joiners can replay it
+        push_turn(&mut self.history, turn.clone());
+        turn
+    }
+
+    fn accept_peers(&mut self) {
+        loop {
+            let stream = match self.listener.accept() {
+                Ok((stream, _)) => stream,
+                Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => return,
+                Err(e) => {
+                    warn!("Failed to accept player: {}", e);
+                    return;
+                }
+            };
+            let welcome = HostMessage::Welcome {
+                setup: Box::new(self.setup.clone()),
+                history: self.history.clone(),
+            };
+            match Connection::new(stream).and_then(|mut connection| {
+                connection.send(&welcome)?;
+                Ok(connection)
+            }) {
+                Ok(connection) => {
+                    info!("Player joined from {}", connection.peer);
+                    self.peers.push(Peer {
+                        connection,
+                        spectator: false,
+                        batch: Vec::new(),
+                    });
+                }
+                Err(e) => warn!("Failed to admit player: {:#}", e),
+            }
+        }
+    }
+}
+
+/// A peer that sends its commands to the host and mirrors the host's turns
+pub struct LockstepClient {
+    connection: Connection<HostMessage>,
+    setup: SessionSetup,
+    /// History received on joining, replayed by [`Self::create_world`]
+    catch_up: Vec<Turn>,
+    next_tick: u64,
+    desynced: bool,
+    spectator: bool,
+    /// Received turns a spectator hasn't played back yet; the front turn may
+    /// be partly played
+    playback: VecDeque<Turn>,
+    /// Real seconds of playback owed to the spectator's world
+    playback_clock: f32,
+    /// Whether a spectator is waiting for turns to build up before playing
+    buffering: bool,
+}
+
+impl LockstepClient {
+    /// Connect to a host as a player and wait for its welcome
+    pub fn connect(addr: impl ToSocketAddrs) -> Result<Self> {
+        let stream = TcpStream::connect(addr).context("Failed to connect to host")?;
+        let connection = Connection::new(stream)?;
+        let HostMessage::Welcome { setup, history } = connection.recv()? else {
+            bail!("Host did not send a welcome");
+        };
+        if setup.protocol_version != PROTOCOL_VERSION {
+            bail!(
+                "Host uses protocol version {}, this build uses {}",
+                setup.protocol_version,
+                PROTOCOL_VERSION
+            );
+        }
+        Ok(Self {
+            connection,
+            setup: *setup,
+            catch_up: history,
+            next_tick: 0,
+            desynced: false,
+            spectator: false,
+            playback: VecDeque::new(),
+            playback_clock: 0.0,
+            buffering: true,
+        })
+    }
+
+    /// Connect to a host as a read-only spectator
+    pub fn spectate(addr: impl ToSocketAddrs) -> Result<Self> {
+        let mut client = Self::connect(addr)?;
+        client.connection.send(&ClientMessage::Spectate)?;
+        client.spectator = true;
+        Ok(client)
+    }
+
+    /// Session the host is running
+    pub fn setup(&self) -> &SessionSetup {
+        &self.setup
+    }
+
+    /// Number of ticks applied so far
+    pub fn tick(&self) -> u64 {
+        self.next_tick
+    }
+
+    /// Whether a turn's checksum has ever disagreed with the local world
+    pub fn is_desynced(&self) -> bool {
+        self.desynced
+    }
+
+    /// Whether this client only watches
+    pub fn is_spectator(&self) -> bool {
+        self.spectator
+    }
+
+    /// Build the session's world and replay the history received on joining
+    pub fn create_world(&mut self) -> Result<SimWorld> {
+        let mut world = self.setup.create_world()?;
+        for turn in std::mem::take(&mut self.catch_up) {
+            self.apply(&mut world, &turn);
+        }
+        Ok(world)
+    }
+
+    /// Ask the host to include a command in its next turn
+    pub fn send(&mut self, command: BuildCommand) -> Result<()> {
+        if self.spectator {
+            bail!("Spectators can't build");
+        }
+        self.connection.send(&ClientMessage::Command(command))
+    }
+
+    /// Apply every turn that has arrived, returning how many were applied
+    ///
+    /// Errors once the host has gone.
+    pub fn poll(&mut self, world: &mut SimWorld) -> Result<usize> {
+        let mut applied = 0;
+        while let Some(turn) = self.receive()? {
+            self.apply(world, &turn);
+            applied += 1;
+        }
+        Ok(applied)
+    }
+
+    /// Play received turns back at real-time pace, returning the number of
+    /// ticks run
+    ///
+    /// Used by spectators, who receive turns in batches: `elapsed` real
+    /// seconds of simulation are played per call, so traffic moves smoothly
+    /// between batches. A viewer that falls more than
+    /// [`SPECTATOR_MAX_DELAY_SECS`] behind skips ahead. Errors once the host
+    /// has gone.
+    pub fn poll_paced(&mut self, world: &mut SimWorld, elapsed: f32) -> Result<usize> {
+        while let Some(turn) = self.receive()? {
+            self.playback.push_back(turn);
+        }
+
+        let buffered: f32 = self.playback.iter().map(Turn::duration).sum();
+        // After running dry, wait for a spare batch so the next one arriving
+        // a little late doesn't stall playback again
+        if self.buffering {
+            if buffered < 2.0 * SPECTATOR_BATCH_SECS {
+                return Ok(0);
+            }
+            self.buffering = false;
+        }
+        self.playback_clock += elapsed;
+        if buffered - self.playback_clock > SPECTATOR_MAX_DELAY_SECS {
+            self.playback_clock = buffered - SPECTATOR_BATCH_SECS;
+        }
+
+        let mut ticks = 0;
+        while let Some(turn) = self.playback.front_mut() {
+            if self.playback_clock < turn.delta {
+                break;
+            }
+            // Commands go before the turn's first tick
+            world.apply_commands(&std::mem::take(&mut turn.commands));
+            world.tick(turn.delta);
+            self.playback_clock -= turn.delta;
+            ticks += 1;
+            turn.tick += 1;
+            turn.ticks -= 1;
+            self.next_tick = turn.tick;
+            if turn.ticks == 0 {
+                let checksum = turn.checksum;
+                self.playback.pop_front();
+                self.check(state_checksum(world), checksum);
+            }
+        }
+        if self.playback.is_empty() {
+            // Don't bank time while waiting for the next batch
+            self.playback_clock = 0.0;
+            self.buffering = true;
+        }
+        Ok(ticks)
+    }
+
+    /// Next turn from the host, if one has arrived
+    fn receive(&mut self) -> Result<Option<Turn>> {
+        loop {
+            match self.connection.try_recv()? {
+                Some(HostMessage::Turn(turn)) => return Ok(Some(turn)),
+                Some(HostMessage::Welcome { .. }) => warn!("Ignoring repeated welcome from host"),
+                None => return Ok(None),
+            }
+        }
+    }
+
+    fn apply(&mut self, world: &mut SimWorld, turn: &Turn) {
+        if turn.tick != self.next_tick {
+            warn!(
+                "Expected turn for tick {}, got tick {}",
+                self.next_tick, turn.tick
+            );
+        }
+        let checksum = turn.run(world);
+        self.next_tick = turn.tick + u64::from(turn.ticks);
+        self.check(checksum, turn.checksum);
+    }
+
+    /// Compare the local world's checksum against the host's
+    fn check(&mut self, local: u64, host: u64) {
+        if local != host && !self.desynced {
+            warn!("World out of sync with the host at tick {}", self.next_tick);
+            self.desynced = true;
+        }
+    }
+}
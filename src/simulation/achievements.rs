@@ -0,0 +1,160 @@
+//! Achievement tracking for the management game
+//!
+//! Achievements are evaluated from the world's game state and traffic after
+//! each tick. Unlocks persist across runs in a small TOML file.
+
+#[cfg(feature = "serialization")]
+use anyhow::{Context, Result};
+#[cfg(feature = "serialization")]
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+#[cfg(feature = "serialization")]
+use std::path::Path;
+
+use super::world::SimWorld;
+
+/// Shop deliveries needed for [`Achievement::HundredDeliveries`]
+pub const ACHIEVEMENT_DELIVERY_COUNT: usize = 100;
+/// Money below which the player counts as running on a shoestring
+pub const ACHIEVEMENT_LOW_FUNDS: i32 = 100;
+/// Simulated seconds the player must stay solvent below [`ACHIEVEMENT_LOW_FUNDS`]
+pub const ACHIEVEMENT_LOW_FUNDS_SECS: f32 = 60.0;
+/// Vehicles on the road needed for [`Achievement::FreeFlowingCity`]
+pub const ACHIEVEMENT_FREE_FLOW_CARS: usize = 500;
+/// Cars per unit of road length above which a road counts as congested
+pub const CONGESTED_ROAD_DENSITY: f32 = 0.8;
+
+/// A single unlockable achievement
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serialization", serde(rename_all = "snake_case"))]
+pub enum Achievement {
+    /// Complete the first shop delivery
+    FirstDelivery,
+    /// Complete 100 shop deliveries in one game
+    HundredDeliveries,
+    /// Stay solvent for a minute with less than $100
+    ShoestringBudget,
+    /// Have 500 vehicles on the road without a single congested road
+    FreeFlowingCity,
+}
+
+impl Achievement {
+    /// Every achievement, in display order
+    pub const ALL: [Achievement; 4] = [
+        Achievement::FirstDelivery,
+        Achievement::HundredDeliveries,
+        Achievement::ShoestringBudget,
+        Achievement::FreeFlowingCity,
+    ];
+
+    /// Short display name
+    pub fn title(&self) -> &'static str {
+        match self {
+            Achievement::FirstDelivery => "First Delivery",
+            Achievement::HundredDeliveries => "Logistics Empire",
+            Achievement::ShoestringBudget => "Shoestring Budget",
+            Achievement::FreeFlowingCity => "Free-Flowing City",
+        }
+    }
+
+    /// What the player has to do to unlock it
+    pub fn description(&self) -> String {
+        match self {
+            Achievement::FirstDelivery => "Complete your first shop delivery".to_string(),
+            Achievement::HundredDeliveries => {
+                format!("Complete {} shop deliveries", ACHIEVEMENT_DELIVERY_COUNT)
+            }
+            Achievement::ShoestringBudget => format!(
+                "Stay solvent for {:.0}s with less than ${}",
+                ACHIEVEMENT_LOW_FUNDS_SECS, ACHIEVEMENT_LOW_FUNDS
+            ),
+            Achievement::FreeFlowingCity => format!(
+                "Have {} vehicles on the road with no congested roads",
+                ACHIEVEMENT_FREE_FLOW_CARS
+            ),
+        }
+    }
+}
+
+/// Unlocked achievements plus the bookkeeping needed to evaluate them
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+pub struct AchievementTracker {
+    /// Achievements unlocked so far, in this run or earlier ones
+    pub unlocked: BTreeSet<Achievement>,
+    /// Simulation time at which money last dropped below the low-funds mark
+    #[cfg_attr(feature = "serialization", serde(skip))]
+    low_funds_since: Option<f32>,
+}
+
+impl AchievementTracker {
+    /// Check whether an achievement is unlocked
+    pub fn is_unlocked(&self, achievement: Achievement) -> bool {
+        self.unlocked.contains(&achievement)
+    }
+
+    /// Evaluate all achievements against the world
+    ///
+    /// Returns the achievements unlocked by this call, in display order.
+    pub fn evaluate(&mut self, world: &SimWorld) -> Vec<Achievement> {
+        let Some(game_state) = &world.game_state else {
+            return Vec::new();
+        };
+
+        // Track how long the player has been running on low funds
+        if game_state.money >= 0 && game_state.money < ACHIEVEMENT_LOW_FUNDS {
+            self.low_funds_since.get_or_insert(world.time);
+        } else {
+            self.low_funds_since = None;
+        }
+
+        let mut newly_unlocked = Vec::new();
+        for achievement in Achievement::ALL {
+            if self.is_unlocked(achievement) {
+                continue;
+            }
+            let earned = match achievement {
+                Achievement::FirstDelivery => game_state.shop_deliveries_completed > 0,
+                Achievement::HundredDeliveries => {
+                    game_state.shop_deliveries_completed >= ACHIEVEMENT_DELIVERY_COUNT
+                }
+                Achievement::ShoestringBudget => self
+                    .low_funds_since
+                    .is_some_and(|since| world.time - since >= ACHIEVEMENT_LOW_FUNDS_SECS),
+                Achievement::FreeFlowingCity => {
+                    world.cars.len() >= ACHIEVEMENT_FREE_FLOW_CARS
+                        && world.road_network.get_all_roads().all(|(road_id, _)| {
+                            world.road_network.calculate_traffic_density(*road_id)
+                                < CONGESTED_ROAD_DENSITY
+                        })
+                }
+            };
+            if earned {
+                self.unlocked.insert(achievement);
+                newly_unlocked.push(achievement);
+            }
+        }
+        newly_unlocked
+    }
+
+    /// Load previously unlocked achievements, starting fresh if the file doesn't exist
+    #[cfg(feature = "serialization")]
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read achievements file {}", path.display()))?;
+        toml::from_str(&text)
+            .with_context(|| format!("Invalid achievements file {}", path.display()))
+    }
+
+    /// Save unlocked achievements
+    #[cfg(feature = "serialization")]
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let text = toml::to_string_pretty(self).context("Failed to serialize achievements")?;
+        std::fs::write(path, text)
+            .with_context(|| format!("Failed to write achievements file {}", path.display()))
+    }
+}
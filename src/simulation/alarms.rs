@@ -0,0 +1,317 @@
+//! KPI alarms
+//!
+//! An economy can list [`AlarmConfig`]s that watch a measure of how the city
+//! is doing, such as deliveries per minute or how crowded the busiest road
+//! is, and go off once it has been below or above a threshold for a set
+//! time, e.g. deliveries/min below 2 for 60 seconds. An alarm going off is
+//! recorded as an [`AlarmReport`] with the measure's value, what the roads
+//! looked like and where traffic was densest, reported as a [`GameEvent`]
+//! and logged. It clears once the measure is back on the right side of the
+//! threshold, and can then go off again.
+//!
+//! Rates over the last minute aren't known until a minute has gone by, so
+//! alarms on them can't go off before then. Alarms start over when a save
+//! is loaded, like traffic.
+//!
+//! [`GameEvent`]: super::GameEvent
+
+use std::collections::VecDeque;
+
+use anyhow::{ensure, Result};
+use log::warn;
+#[cfg(feature = "serialization")]
+use serde::{Deserialize, Serialize};
+
+use super::types::{Position, RoadId};
+use super::world::SimWorld;
+
+/// Seconds of history the per-minute rates are taken over
+const RATE_WINDOW_SECS: f32 = 60.0;
+
+/// A measure of how the city is doing that an alarm can watch
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serialization", serde(rename_all = "snake_case"))]
+pub enum AlarmMetric {
+    /// Shop deliveries completed over the last minute
+    DeliveriesPerMinute,
+    /// Worker trips completed over the last minute
+    WorkerTripsPerMinute,
+    /// Vehicles per unit of length on the most crowded road
+    RoadDensity,
+    /// Average speed of the vehicles driving a road
+    AverageSpeed,
+    /// Vehicles in the world
+    Vehicles,
+}
+
+impl AlarmMetric {
+    /// Name for display
+    pub fn label(self) -> &'static str {
+        match self {
+            AlarmMetric::DeliveriesPerMinute => "deliveries/min",
+            AlarmMetric::WorkerTripsPerMinute => "worker trips/min",
+            AlarmMetric::RoadDensity => "road density",
+            AlarmMetric::AverageSpeed => "average speed",
+            AlarmMetric::Vehicles => "vehicles",
+        }
+    }
+}
+
+/// An alarm that goes off when a measure stays past a threshold
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serialization", serde(deny_unknown_fields))]
+pub struct AlarmConfig {
+    /// Name shown when it goes off
+    pub name: String,
+    pub metric: AlarmMetric,
+    /// Go off when the measure is below this...
+    #[cfg_attr(feature = "serialization", serde(default))]
+    pub below: Option<f32>,
+    /// ...or above this
+    #[cfg_attr(feature = "serialization", serde(default))]
+    pub above: Option<f32>,
+    /// Seconds the measure has to stay past the threshold
+    #[cfg_attr(feature = "serialization", serde(default))]
+    pub for_secs: f32,
+}
+
+impl AlarmConfig {
+    /// Reject alarms without exactly one threshold or that wait for a
+    /// negative time
+    pub fn validate(&self) -> Result<()> {
+        ensure!(!self.name.is_empty(), "alarms need a name");
+        ensure!(
+            self.below.is_some() != self.above.is_some(),
+            "alarm '{}' needs one of below or above",
+            self.name
+        );
+        ensure!(
+            self.for_secs >= 0.0,
+            "alarm '{}' for_secs must not be negative (got {})",
+            self.name,
+            self.for_secs
+        );
+        Ok(())
+    }
+
+    /// Whether a value of the measure is past the threshold
+    pub fn is_breached_by(&self, value: f32) -> bool {
+        match (self.below, self.above) {
+            (Some(below), _) => value < below,
+            (None, Some(above)) => value > above,
+            (None, None) => false,
+        }
+    }
+
+    /// The threshold, e.g. "< 2.0", for display
+    pub fn condition(&self) -> String {
+        match (self.below, self.above) {
+            (Some(below), _) => format!("< {}", below),
+            (None, Some(above)) => format!("> {}", above),
+            (None, None) => String::new(),
+        }
+    }
+}
+
+/// An alarm going off
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+pub struct AlarmReport {
+    /// Index of the alarm in the economy's list
+    pub alarm: usize,
+    pub name: String,
+    pub metric: AlarmMetric,
+    /// The measure's value when it went off
+    pub value: f32,
+    /// Simulation time it went off
+    pub time: f32,
+    /// Middle of the road traffic was densest on, if any had vehicles
+    pub location: Option<Position>,
+    /// What the roads looked like, for the log
+    pub context: String,
+}
+
+/// Whether an alarm's measure is past its threshold, and since when
+#[derive(Debug, Clone, Copy, Default)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+struct AlarmState {
+    breached_since: Option<f32>,
+    /// Index in the reports while the alarm is going off
+    report: Option<usize>,
+}
+
+/// The alarms' states and the history their rates are taken from
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+pub(super) struct AlarmMonitor {
+    states: Vec<AlarmState>,
+    /// Simulation time with the deliveries and worker trips completed by
+    /// then, covering the last minute
+    history: VecDeque<(f32, usize, usize)>,
+    /// Every time an alarm went off, oldest first
+    reports: Vec<AlarmReport>,
+}
+
+impl SimWorld {
+    /// The alarms of the game's economy
+    pub fn alarm_configs(&self) -> &[AlarmConfig] {
+        self.game_state
+            .as_ref()
+            .map_or(&[], |game_state| game_state.economy.alarms.as_slice())
+    }
+
+    /// Every time an alarm went off, oldest first
+    pub fn alarm_reports(&self) -> &[AlarmReport] {
+        &self.alarms.reports
+    }
+
+    /// The alarms going off now, in the economy's order
+    pub fn active_alarms(&self) -> impl Iterator<Item = &AlarmReport> {
+        self.alarms
+            .states
+            .iter()
+            .filter_map(|state| state.report)
+            .map(|report| &self.alarms.reports[report])
+    }
+
+    /// The road with the most vehicles per unit of length, if any has
+    /// vehicles, and that density
+    fn densest_road(&self) -> Option<(RoadId, f32)> {
+        self.road_network
+            .roads()
+            .keys()
+            .map(|&road_id| {
+                (
+                    road_id,
+                    self.road_network.calculate_traffic_density(road_id),
+                )
+            })
+            .filter(|(_, density)| *density > 0.0)
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+    }
+
+    /// A measure's value now, if it is known yet
+    pub fn alarm_metric(&self, metric: AlarmMetric) -> Option<f32> {
+        match metric {
+            AlarmMetric::DeliveriesPerMinute | AlarmMetric::WorkerTripsPerMinute => {
+                let game_state = self.game_state.as_ref()?;
+                let &(since, deliveries, trips) = self.alarms.history.front()?;
+                let minutes = (self.time - since) / 60.0;
+                if self.time - since < RATE_WINDOW_SECS - 1e-3 {
+                    return None;
+                }
+                let done = if metric == AlarmMetric::DeliveriesPerMinute {
+                    game_state.shop_deliveries_completed - deliveries
+                } else {
+                    game_state.worker_trips_completed - trips
+                };
+                Some(done as f32 / minutes)
+            }
+            AlarmMetric::RoadDensity => {
+                Some(self.densest_road().map_or(0.0, |(_, density)| density))
+            }
+            AlarmMetric::AverageSpeed => self.network_average_speed(),
+            AlarmMetric::Vehicles => Some(self.cars.len() as f32),
+        }
+    }
+
+    /// Note how far the rates have got, then raise the alarms whose
+    /// measures have been past their thresholds long enough and clear the
+    /// ones whose measures are back
+    pub(super) fn update_alarms(&mut self) {
+        let configs = self.alarm_configs().to_vec();
+        if configs.is_empty() {
+            return;
+        }
+        // The economy's alarms may have been reloaded
+        if self.alarms.states.len() != configs.len() {
+            self.alarms.states = vec![AlarmState::default(); configs.len()];
+        }
+        if let Some(game_state) = &self.game_state {
+            let history = &mut self.alarms.history;
+            history.push_back((
+                self.time,
+                game_state.shop_deliveries_completed,
+                game_state.worker_trips_completed,
+            ));
+            // Keep the newest sample at least a minute old
+            while history
+                .get(1)
+                .is_some_and(|&(time, _, _)| time <= self.time - RATE_WINDOW_SECS)
+            {
+                history.pop_front();
+            }
+        }
+
+        for (index, config) in configs.iter().enumerate() {
+            let value = self.alarm_metric(config.metric);
+            let breached = value.is_some_and(|value| config.is_breached_by(value));
+            let state = &mut self.alarms.states[index];
+            if !breached {
+                state.breached_since = None;
+                if state.report.take().is_some() {
+                    if let Some(game_state) = &mut self.game_state {
+                        game_state.record_alarm_cleared(index);
+                    }
+                }
+                continue;
+            }
+            let since = *state.breached_since.get_or_insert(self.time);
+            if state.report.is_some() || self.time - since < config.for_secs - 1e-3 {
+                continue;
+            }
+            let value = value.unwrap_or_default();
+            let report = self.alarm_report(index, config, value);
+            warn!(
+                "Alarm '{}' at {:.1}s: {} {:.2} ({} for {:.0}s); {}",
+                report.name,
+                report.time,
+                config.metric.label(),
+                value,
+                config.condition(),
+                config.for_secs,
+                report.context
+            );
+            self.alarms.states[index].report = Some(self.alarms.reports.len());
+            self.alarms.reports.push(report);
+            if let Some(game_state) = &mut self.game_state {
+                game_state.record_alarm_raised(index, value);
+            }
+        }
+    }
+
+    fn alarm_report(&self, alarm: usize, config: &AlarmConfig, value: f32) -> AlarmReport {
+        let densest = self.densest_road();
+        let road = densest.and_then(|(road_id, _)| self.road_network.get_road(road_id));
+        let location = road.and_then(|road| {
+            let start = self
+                .road_network
+                .get_intersection_position(road.start_intersection)?;
+            let end = self
+                .road_network
+                .get_intersection_position(road.end_intersection)?;
+            Some(start.lerp(end, 0.5))
+        });
+        let mut context = format!("{} vehicles", self.cars.len());
+        if let Some(speed) = self.network_average_speed() {
+            context.push_str(&format!(" averaging {:.1}", speed));
+        }
+        if let (Some(road), Some((_, density))) = (road, densest) {
+            context.push_str(&format!(
+                ", densest road {} ({} -> {}) at {:.2}/unit",
+                road.id.0 .0, road.start_intersection.0 .0, road.end_intersection.0 .0, density
+            ));
+        }
+        AlarmReport {
+            alarm,
+            name: config.name.clone(),
+            metric: config.metric,
+            value,
+            time: self.time,
+            location,
+            context,
+        }
+    }
+}
@@ -0,0 +1,205 @@
+//! Anomalies in a finished run
+//!
+//! A run can pass every structural check and still have gone wrong in ways
+//! only its statistics show: a factory whose truck never left, a road no
+//! vehicle ever drove, trips that took far longer than any trip should.
+//! [`SimWorld::find_anomalies`] looks over the statistics at the end of a
+//! run and lists each of these with what it points at, so a scenario
+//! author can see where a layout went wrong and a CI run can flag it.
+//!
+//! Anomalies are hints, not failures: a road kept for later growth is
+//! never driven, and a short run may end before a slow factory's first
+//! delivery. They are only meaningful once a run has had time to settle.
+
+use std::collections::BTreeSet;
+use std::fmt;
+
+use super::trip_stats::TripPurpose;
+use super::types::{CarId, FactoryId, IntersectionId, RoadId, VehicleType};
+use super::world::SimWorld;
+
+/// Seconds a trip may take before it is flagged by default
+pub const ANOMALY_MAX_TRIP_SECS: f32 = 300.0;
+
+/// What counts as an anomaly
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AnomalyConfig {
+    /// Seconds a trip may take, finished or still under way
+    pub max_trip_secs: f32,
+}
+
+impl Default for AnomalyConfig {
+    fn default() -> Self {
+        Self {
+            max_trip_secs: ANOMALY_MAX_TRIP_SECS,
+        }
+    }
+}
+
+/// Something in a run's statistics worth looking at
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Anomaly {
+    /// A factory whose truck never set off with a delivery
+    IdleFactory {
+        factory: FactoryId,
+        intersection: IntersectionId,
+    },
+    /// A road no vehicle ever drove onto; each direction of a two-way road
+    /// counts separately
+    UnusedRoad {
+        road: RoadId,
+        start: IntersectionId,
+        end: IntersectionId,
+    },
+    /// Finished trips of one kind, the slowest of which took too long
+    SlowTrips {
+        purpose: TripPurpose,
+        vehicle_type: VehicleType,
+        longest_secs: f32,
+    },
+    /// A vehicle still on a trip that has taken too long
+    OverdueVehicle {
+        car: CarId,
+        purpose: TripPurpose,
+        trip_secs: f32,
+    },
+}
+
+impl fmt::Display for Anomaly {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Anomaly::IdleFactory {
+                factory,
+                intersection,
+            } => write!(
+                f,
+                "Factory {} at intersection {} never dispatched a truck",
+                factory.0 .0, intersection.0 .0
+            ),
+            Anomaly::UnusedRoad { road, start, end } => write!(
+                f,
+                "Road {} ({} -> {}) was never used",
+                road.0 .0, start.0 .0, end.0 .0
+            ),
+            Anomaly::SlowTrips {
+                purpose,
+                vehicle_type,
+                longest_secs,
+            } => write!(
+                f,
+                "{} trips by {:?} took up to {:.0}s",
+                purpose.label(),
+                vehicle_type,
+                longest_secs
+            ),
+            Anomaly::OverdueVehicle {
+                car,
+                purpose,
+                trip_secs,
+            } => write!(
+                f,
+                "Vehicle {} has been on its {} trip for {:.0}s",
+                car.0 .0,
+                purpose.label().to_lowercase(),
+                trip_secs
+            ),
+        }
+    }
+}
+
+/// The anomalies found at the end of a run
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HealthReport {
+    /// Simulation time the run had reached
+    pub time: f32,
+    /// Seconds a trip was allowed
+    pub max_trip_secs: f32,
+    /// Factories first, then roads, then trips, each in id order
+    pub anomalies: Vec<Anomaly>,
+}
+
+impl HealthReport {
+    /// Whether nothing was found
+    pub fn is_healthy(&self) -> bool {
+        self.anomalies.is_empty()
+    }
+}
+
+impl fmt::Display for HealthReport {
+    /// A line per anomaly
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_healthy() {
+            return writeln!(f, "No anomalies after {:.0}s", self.time);
+        }
+        writeln!(
+            f,
+            "{} anomalies after {:.0}s (trips allowed {:.0}s):",
+            self.anomalies.len(),
+            self.time,
+            self.max_trip_secs
+        )?;
+        for anomaly in &self.anomalies {
+            writeln!(f, "  {}", anomaly)?;
+        }
+        Ok(())
+    }
+}
+
+impl SimWorld {
+    /// Look over the run's statistics for factories that never dispatched
+    /// a truck, roads never used and trips that took too long
+    pub fn find_anomalies(&self, config: &AnomalyConfig) -> HealthReport {
+        let mut anomalies = Vec::new();
+
+        // A truck's delivery is credited to its factory when it arrives,
+        // so a factory has dispatched if it was credited or its truck is out
+        let delivered: BTreeSet<FactoryId> = self
+            .shop_catchments()
+            .iter()
+            .flat_map(|catchment| catchment.factories.iter().map(|(id, _)| *id))
+            .collect();
+        for factory in self.factories.values() {
+            if factory.truck.is_none() && !delivered.contains(&factory.id) {
+                anomalies.push(Anomaly::IdleFactory {
+                    factory: factory.id,
+                    intersection: factory.intersection_id,
+                });
+            }
+        }
+
+        for road in self.road_network.roads().values() {
+            if self.road_network.traffic_volume(road.id) == 0 {
+                anomalies.push(Anomaly::UnusedRoad {
+                    road: road.id,
+                    start: road.start_intersection,
+                    end: road.end_intersection,
+                });
+            }
+        }
+
+        for row in self.trip_statistics() {
+            if row.stats.longest_secs > config.max_trip_secs {
+                anomalies.push(Anomaly::SlowTrips {
+                    purpose: row.purpose,
+                    vehicle_type: row.vehicle_type,
+                    longest_secs: row.stats.longest_secs,
+                });
+            }
+        }
+        for car in self.cars.values() {
+            if car.trip_secs > config.max_trip_secs {
+                anomalies.push(Anomaly::OverdueVehicle {
+                    car: car.id,
+                    purpose: car.purpose(),
+                    trip_secs: car.trip_secs,
+                });
+            }
+        }
+
+        HealthReport {
+            time: self.time,
+            max_trip_secs: config.max_trip_secs,
+            anomalies,
+        }
+    }
+}
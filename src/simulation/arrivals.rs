@@ -0,0 +1,493 @@
+//! Handling the vehicles that finished their trips in a tick
+//!
+//! Arrivals are handled in three phases, so what one arrival does can't
+//! change how another in the same tick is handled or depend on the order
+//! vehicles were updated in:
+//!
+//! 1. **Collect**: each vehicle that arrived (or whose trip ended
+//!    unexpectedly) becomes an [`Arrival`], read from the world as it was
+//!    when the vehicles stopped moving, without changing anything.
+//! 2. **Resolve**: the arrivals take effect on their buildings and the game
+//!    and the vehicles are removed. Vehicles getting home go first, so a
+//!    truck back at its factory frees it for a worker arriving the same
+//!    tick; otherwise arrivals go in car ID order. An arrival that sends a
+//!    vehicle back, such as a truck that has delivered or a worker turned
+//!    away, leaves a [`ReturnTrip`] instead of spawning it.
+//! 3. **Apply**: the return trips set off, in the order their arrivals were
+//!    resolved in. They start moving next tick.
+
+use super::car::CarUpdateResult;
+use super::events::SimEvent;
+use super::types::{ApartmentId, CarId, FactoryId, IntersectionId, SpecialBuildingId};
+use super::types::{TripType, VehicleType};
+use super::warnings::SimWarning;
+use super::world::SimWorld;
+
+/// A vehicle that finished its trip this tick, as it was when it stopped
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Arrival {
+    /// A worker reached the factory they were sent to
+    WorkerAtFactory {
+        car_id: CarId,
+        factory: IntersectionId,
+        apartment: Option<ApartmentId>,
+    },
+    /// A worker got home from their shift
+    WorkerHome {
+        car_id: CarId,
+        apartment: Option<ApartmentId>,
+        factory: Option<FactoryId>,
+        commute_secs: f32,
+    },
+    /// A truck reached the shop it was delivering to
+    Delivery {
+        car_id: CarId,
+        shop: IntersectionId,
+        factory: Option<FactoryId>,
+        late: bool,
+    },
+    /// A truck got back to its factory
+    TruckHome {
+        car_id: CarId,
+        factory: Option<FactoryId>,
+        late: bool,
+        price: f32,
+    },
+    /// A school, hospital, depot or terminal's vehicle arrived
+    Special {
+        car_id: CarId,
+        dest: IntersectionId,
+        trip_type: TripType,
+    },
+    /// Any other vehicle, which has nothing left to do
+    Finished { car_id: CarId },
+    /// A vehicle whose trip ended unexpectedly
+    Stranded { car_id: CarId },
+}
+
+impl Arrival {
+    fn car_id(&self) -> CarId {
+        match *self {
+            Arrival::WorkerAtFactory { car_id, .. }
+            | Arrival::WorkerHome { car_id, .. }
+            | Arrival::Delivery { car_id, .. }
+            | Arrival::TruckHome { car_id, .. }
+            | Arrival::Special { car_id, .. }
+            | Arrival::Finished { car_id }
+            | Arrival::Stranded { car_id } => car_id,
+        }
+    }
+
+    /// Whether the vehicle is done with its building, which may free it for
+    /// the other arrivals
+    fn frees_building(&self) -> bool {
+        match self {
+            Arrival::WorkerHome { .. }
+            | Arrival::TruckHome { .. }
+            | Arrival::Finished { .. }
+            | Arrival::Stranded { .. } => true,
+            Arrival::Special { trip_type, .. } => *trip_type == TripType::Return,
+            Arrival::WorkerAtFactory { .. } | Arrival::Delivery { .. } => false,
+        }
+    }
+}
+
+/// A vehicle to send back once every arrival has been resolved
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) enum ReturnTrip {
+    /// A worker turned away at the factory, heading home in place of the
+    /// car that arrived
+    Worker {
+        arrived: CarId,
+        from: IntersectionId,
+        home: IntersectionId,
+        apartment: ApartmentId,
+        factory: Option<FactoryId>,
+    },
+    /// A truck heading back to its factory after a delivery
+    Truck {
+        from: IntersectionId,
+        home: IntersectionId,
+        factory: FactoryId,
+        late: bool,
+        price: f32,
+    },
+    /// A special building's vehicle heading back; `home` is `None` if it
+    /// has nowhere to return to
+    Special {
+        arrived: CarId,
+        from: IntersectionId,
+        home: Option<IntersectionId>,
+        building: SpecialBuildingId,
+        vehicle_type: VehicleType,
+        apartment: Option<ApartmentId>,
+    },
+}
+
+impl SimWorld {
+    /// Handle the vehicles that finished their trips, as reported by
+    /// updating the cars
+    ///
+    /// The result is the same whatever order the results are in.
+    pub fn process_arrivals(&mut self, car_results: Vec<(CarId, CarUpdateResult)>) {
+        let mut arrivals: Vec<Arrival> = car_results
+            .into_iter()
+            .filter_map(|(car_id, result)| self.collect_arrival(car_id, result))
+            .collect();
+        arrivals.sort_by_key(|arrival| (!arrival.frees_building(), arrival.car_id()));
+
+        let return_trips: Vec<ReturnTrip> = arrivals
+            .into_iter()
+            .filter_map(|arrival| self.resolve_arrival(arrival))
+            .collect();
+
+        for return_trip in return_trips {
+            self.set_off_return_trip(return_trip);
+        }
+    }
+
+    /// Work out what a vehicle's update result means, without changing
+    /// anything
+    fn collect_arrival(&self, car_id: CarId, result: CarUpdateResult) -> Option<Arrival> {
+        let dest = match result {
+            // Rerouted before arrivals are processed
+            CarUpdateResult::Continue | CarUpdateResult::LeftRemovedRoad(_) => return None,
+            CarUpdateResult::Despawn => return Some(Arrival::Stranded { car_id }),
+            CarUpdateResult::ArrivedAtDestination(dest) => dest,
+        };
+        let Some(car) = self.cars.get(&car_id) else {
+            return Some(Arrival::Finished { car_id });
+        };
+        // Trips to and from schools, hospitals and depots follow their own rules
+        if car.origin_special.is_some() {
+            return Some(Arrival::Special {
+                car_id,
+                dest,
+                trip_type: car.trip_type,
+            });
+        }
+        Some(match (car.vehicle_type, car.trip_type) {
+            (VehicleType::Car, TripType::Outbound) => Arrival::WorkerAtFactory {
+                car_id,
+                factory: dest,
+                apartment: car.origin_apartment,
+            },
+            (VehicleType::Car, TripType::Return) => Arrival::WorkerHome {
+                car_id,
+                apartment: car.origin_apartment,
+                factory: car.origin_factory,
+                commute_secs: car.trip_secs,
+            },
+            (VehicleType::Truck, TripType::Outbound) => Arrival::Delivery {
+                car_id,
+                shop: dest,
+                factory: car.origin_factory,
+                late: car.is_overdue(),
+            },
+            (VehicleType::Truck, TripType::Return) => Arrival::TruckHome {
+                car_id,
+                factory: car.origin_factory,
+                late: car.delivered_late,
+                price: car.delivery_price,
+            },
+            // Hospital ambulances, depot tow trucks and terminal vans are
+            // special; any others have nothing left to do
+            (VehicleType::Ambulance | VehicleType::TowTruck | VehicleType::Van, _) => {
+                Arrival::Finished { car_id }
+            }
+        })
+    }
+
+    /// Let an arrival take effect and remove its vehicle, returning the trip
+    /// back it leads to, if any
+    fn resolve_arrival(&mut self, arrival: Arrival) -> Option<ReturnTrip> {
+        if let Arrival::Stranded { car_id } = arrival {
+            self.end_trip_unexpectedly(car_id);
+            return None;
+        }
+        #[cfg(feature = "stats")]
+        self.record_trip(arrival.car_id());
+        // A vehicle already removed, such as towed away by an earlier
+        // arrival, has nothing left to do
+        if !self.cars.contains_key(&arrival.car_id()) {
+            return None;
+        }
+        match arrival {
+            Arrival::WorkerAtFactory {
+                car_id,
+                factory,
+                apartment,
+            } => self.resolve_worker_at_factory(car_id, factory, apartment),
+            Arrival::WorkerHome {
+                car_id,
+                apartment,
+                factory,
+                commute_secs,
+            } => {
+                self.resolve_worker_home(car_id, apartment, factory, commute_secs);
+                None
+            }
+            Arrival::Delivery {
+                car_id,
+                shop,
+                factory,
+                late,
+            } => {
+                // Truck delivered to shop, at the price before it restocked
+                let price = self.shop_delivery_price(shop);
+                if let Some(shop) = self.shops.values_mut().find(|s| s.intersection_id == shop) {
+                    shop.receive_delivery(self.time);
+                    let shop = shop.id;
+                    self.raise(SimEvent::DeliveryCompleted {
+                        shop,
+                        factory,
+                        late,
+                    });
+                }
+                #[cfg(feature = "stats")]
+                if let Some(factory_id) = factory {
+                    self.record_shop_delivery(shop, factory_id, late, price);
+                }
+                self.remove_car(car_id);
+                // The truck heads back to its factory
+                let factory_id = factory?;
+                let home = self.factories.get(&factory_id)?.intersection_id;
+                Some(ReturnTrip::Truck {
+                    from: shop,
+                    home,
+                    factory: factory_id,
+                    late,
+                    price,
+                })
+            }
+            Arrival::TruckHome {
+                car_id,
+                factory,
+                late,
+                price,
+            } => {
+                // Truck returned to factory - clear reference and despawn
+                if let Some(factory) = factory.and_then(|id| self.factories.get_mut(&id)) {
+                    factory.truck = None;
+                }
+                // Track shop delivery completion in game state
+                if let Some(game_state) = &mut self.game_state {
+                    game_state.complete_priced_shop_delivery(late, price);
+                }
+                self.remove_car(car_id);
+                None
+            }
+            Arrival::Special { car_id, dest, .. } => self.resolve_special_arrival(car_id, dest),
+            Arrival::Finished { car_id } | Arrival::Stranded { car_id } => {
+                self.remove_car(car_id);
+                None
+            }
+        }
+    }
+
+    /// Clock a worker in at the factory, or turn them away home if it's on
+    /// strike or its truck is out
+    fn resolve_worker_at_factory(
+        &mut self,
+        car_id: CarId,
+        dest: IntersectionId,
+        origin_apartment: Option<ApartmentId>,
+    ) -> Option<ReturnTrip> {
+        let mut worker_accepted = false;
+        let mut destination_factory: Option<FactoryId> = None;
+        // Striking factories turn them away
+        if let Some(apartment_id) = origin_apartment.filter(|_| !self.factory_on_strike(dest)) {
+            if let Some((factory_id, factory)) = self
+                .factories
+                .iter_mut()
+                .find(|(_, f)| f.intersection_id == dest)
+            {
+                worker_accepted = factory.receive_worker(apartment_id);
+                destination_factory = Some(*factory_id);
+            }
+        }
+        #[cfg(feature = "stats")]
+        {
+            self.record_worker_arrival(origin_apartment, dest, worker_accepted);
+            self.commute_modes.driven += 1;
+        }
+        if let (Some(apartment), Some(factory)) = (origin_apartment, destination_factory) {
+            self.raise(if worker_accepted {
+                SimEvent::WorkerArrived { apartment, factory }
+            } else {
+                SimEvent::FactoryRejectedWorker { apartment, factory }
+            });
+        }
+        self.remove_car(car_id);
+
+        let apartment_id = origin_apartment?;
+        if worker_accepted {
+            // Clear apartment slot since worker is at factory (will be set
+            // when return car spawns)
+            self.clear_apartment_slot(apartment_id, car_id);
+            return None;
+        }
+        // Factory rejected worker (truck out, full or on strike), send them
+        // back home
+        let home = self.apartments.get(&apartment_id)?.intersection_id;
+        Some(ReturnTrip::Worker {
+            arrived: car_id,
+            from: dest,
+            home,
+            apartment: apartment_id,
+            factory: destination_factory,
+        })
+    }
+
+    /// Count a worker's commute home and free their car's slot
+    fn resolve_worker_home(
+        &mut self,
+        car_id: CarId,
+        origin_apartment: Option<ApartmentId>,
+        origin_factory: Option<FactoryId>,
+        commute_secs: f32,
+    ) {
+        let commute_distance = match (origin_apartment, origin_factory) {
+            (Some(apartment_id), Some(factory_id)) => {
+                let apartment_position = self
+                    .apartments
+                    .get(&apartment_id)
+                    .and_then(|apartment| {
+                        self.road_network
+                            .get_intersection_position(apartment.intersection_id)
+                    })
+                    .copied();
+                let factory_position = self
+                    .factories
+                    .get(&factory_id)
+                    .and_then(|factory| {
+                        self.road_network
+                            .get_intersection_position(factory.intersection_id)
+                    })
+                    .copied();
+
+                match (apartment_position, factory_position) {
+                    (Some(apartment_pos), Some(factory_pos)) => {
+                        apartment_pos.distance(&factory_pos)
+                    }
+                    _ => {
+                        self.warnings.warn(
+                            SimWarning::CommuteWithoutBuildings,
+                            self.time,
+                            || "Missing apartment or factory position for worker commute; defaulting to a zero-distance commute, which applies the maximum commute penalty".to_string(),
+                        );
+                        0.0
+                    }
+                }
+            }
+            _ => {
+                self.warnings.warn(
+                    SimWarning::CommuteWithoutWorker,
+                    self.time,
+                    || "Missing worker identifiers for commute penalty; defaulting to a zero-distance commute, which applies the maximum commute penalty".to_string(),
+                );
+                0.0
+            }
+        };
+        #[cfg(feature = "stats")]
+        {
+            self.commute_modes.driven += 1;
+        }
+        // Worker returned home - clear car reference and despawn
+        if let Some(apartment_id) = origin_apartment {
+            self.clear_apartment_slot(apartment_id, car_id);
+        }
+        // Track worker trip completion in game state
+        let appeal =
+            origin_apartment.map_or(0.0, |apartment_id| self.apartment_appeal(apartment_id));
+        let price = self.worker_shift_price(origin_factory);
+        if let Some(game_state) = &mut self.game_state {
+            game_state.complete_priced_worker_trip(commute_distance, commute_secs, appeal, price);
+        }
+        self.remove_car(car_id);
+    }
+
+    /// Empty the apartment's slot holding a car
+    fn clear_apartment_slot(&mut self, apartment_id: ApartmentId, car_id: CarId) {
+        self.replace_apartment_slot(apartment_id, car_id, None);
+    }
+
+    fn replace_apartment_slot(
+        &mut self,
+        apartment_id: ApartmentId,
+        car_id: CarId,
+        replacement: Option<CarId>,
+    ) {
+        if let Some(apartment) = self.apartments.get_mut(&apartment_id) {
+            if let Some(slot) = apartment
+                .cars
+                .iter_mut()
+                .find(|slot| **slot == Some(car_id))
+            {
+                *slot = replacement;
+            }
+        }
+    }
+
+    /// Spawn a vehicle heading back, taking over its building's reference
+    /// to the vehicle that arrived
+    fn set_off_return_trip(&mut self, return_trip: ReturnTrip) {
+        match return_trip {
+            ReturnTrip::Worker {
+                arrived,
+                from,
+                home,
+                apartment,
+                factory,
+            } => {
+                // Spawn car returning home; if it can't, just clear the slot
+                let returning = self
+                    .spawn_vehicle(
+                        from,
+                        home,
+                        VehicleType::Car,
+                        TripType::Return,
+                        Some(apartment),
+                        factory,
+                    )
+                    .ok();
+                self.replace_apartment_slot(apartment, arrived, returning);
+            }
+            ReturnTrip::Truck {
+                from,
+                home,
+                factory,
+                late,
+                price,
+            } => {
+                let returning = self.spawn_vehicle(
+                    from,
+                    home,
+                    VehicleType::Truck,
+                    TripType::Return,
+                    None,
+                    Some(factory),
+                );
+                // A truck that can't return just clears the reference
+                let truck = returning.ok();
+                if let Some(factory) = self.factories.get_mut(&factory) {
+                    factory.truck = truck;
+                }
+                if let Some(truck) = truck.and_then(|truck| self.cars.get_mut(&truck)) {
+                    truck.delivered_late = late;
+                    truck.delivery_price = price;
+                }
+            }
+            ReturnTrip::Special {
+                arrived,
+                from,
+                home,
+                building,
+                vehicle_type,
+                apartment,
+            } => {
+                self.set_off_special_return(arrived, from, home, building, vehicle_type, apartment)
+            }
+        }
+    }
+}
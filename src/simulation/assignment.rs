@@ -0,0 +1,354 @@
+//! Macroscopic traffic assignment
+//!
+//! Instead of driving individual vehicles, [`SimWorld::assign_traffic`]
+//! spreads the city's trips over the roads as steady flows, which answers
+//! "where would traffic go with this layout?" at once. It works from the
+//! same roads and buildings as the microscopic simulation: each house sends
+//! its workers to the factories that would take them and back, and each
+//! factory sends trucks to the shops and back, scaled by the demand ramp's
+//! level if there is one (see [`SimWorld::od_demand`]). Closed roads and
+//! roads a vehicle type may not use are left out.
+//!
+//! Each road's travel time grows with its flow on a BPR curve,
+//! `free_flow * (1 + alpha * (flow / capacity)^beta)`, and flows settle by
+//! the method of successive averages: every round sends all of the demand
+//! down the routes that are quickest under the current flows and moves the
+//! flows a shrinking step towards that, until no driver could save much time
+//! by switching routes (user equilibrium). The relative gap says how close
+//! it got.
+
+use super::eta::{free_flow_secs, ETA_INTERSECTION_SECS};
+use super::types::{IntersectionId, RoadId, SimMap, VehicleType};
+use super::world::SimWorld;
+
+/// Relative gap below which an assignment counts as settled
+pub const ASSIGNMENT_GAP_TOLERANCE: f32 = 0.001;
+
+/// Demand and road settings for a traffic assignment
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AssignmentConfig {
+    /// Trips to work each house car makes a minute at demand level 1
+    pub worker_trips_per_car_minute: f32,
+    /// Deliveries each factory sends a minute at demand level 1
+    pub deliveries_per_factory_minute: f32,
+    /// Vehicles a minute a road carries before it counts as full
+    pub road_capacity_per_minute: f32,
+    /// BPR curve's `alpha`: how much slower a full road is
+    pub alpha: f32,
+    /// BPR curve's `beta`: how sharply roads slow as they fill
+    pub beta: f32,
+    /// Most rounds of averaging before giving up on settling
+    pub max_iterations: usize,
+}
+
+impl Default for AssignmentConfig {
+    fn default() -> Self {
+        Self {
+            worker_trips_per_car_minute: 0.5,
+            deliveries_per_factory_minute: 1.0,
+            road_capacity_per_minute: 30.0,
+            alpha: 0.15,
+            beta: 4.0,
+            max_iterations: 50,
+        }
+    }
+}
+
+impl AssignmentConfig {
+    /// Seconds to drive a road carrying `flow` vehicles a minute, given its
+    /// free-flow time
+    pub fn travel_secs(&self, free_flow_secs: f32, flow: f32) -> f32 {
+        let load = flow / self.road_capacity_per_minute;
+        free_flow_secs * (1.0 + self.alpha * load.powf(self.beta))
+    }
+}
+
+/// Vehicles a minute wanting to go from one intersection to another
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OdDemand {
+    pub from: IntersectionId,
+    pub to: IntersectionId,
+    pub vehicle_type: VehicleType,
+    pub per_minute: f32,
+}
+
+/// A road's share of an assignment
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RoadFlow {
+    pub road_id: RoadId,
+    /// Vehicles a minute
+    pub flow: f32,
+    /// Seconds to drive it with no traffic, crossing its end included
+    pub free_flow_secs: f32,
+    /// Seconds to drive it under the assigned flow
+    pub travel_secs: f32,
+    /// Flow as a share of the road's capacity
+    pub volume_to_capacity: f32,
+}
+
+/// Flows settled on every road
+#[derive(Debug, Clone, PartialEq)]
+pub struct Assignment {
+    /// Every road that may be driven, assigned flow or not
+    pub roads: SimMap<RoadId, RoadFlow>,
+    /// Demand assigned, in vehicles a minute
+    pub routed_per_minute: f32,
+    /// Demand with no route, in vehicles a minute
+    pub unrouted_per_minute: f32,
+    /// Rounds of averaging run
+    pub iterations: usize,
+    /// Share of the total travel time drivers could save by all switching
+    /// to the quickest routes (0 at equilibrium)
+    pub relative_gap: f32,
+}
+
+impl Assignment {
+    /// Average seconds a trip takes under the assigned flows
+    pub fn average_trip_secs(&self) -> f32 {
+        let vehicle_secs: f32 = self
+            .roads
+            .values()
+            .map(|road| road.flow * road.travel_secs)
+            .sum();
+        vehicle_secs / self.routed_per_minute.max(f32::EPSILON)
+    }
+
+    /// The roads most over capacity first, at most `limit` of them, leaving
+    /// out those with no flow
+    pub fn busiest_roads(&self, limit: usize) -> Vec<&RoadFlow> {
+        let mut roads: Vec<&RoadFlow> = self
+            .roads
+            .values()
+            .filter(|road| road.flow > 0.0)
+            .collect();
+        roads.sort_by(|a, b| b.volume_to_capacity.total_cmp(&a.volume_to_capacity));
+        roads.truncate(limit);
+        roads
+    }
+}
+
+/// Find the quickest route for each trip at the given road times, passing
+/// each trip with the roads of its route, or `None` if it has none
+pub(super) fn route_trips(
+    world: &SimWorld,
+    demand: &[OdDemand],
+    road_secs: &SimMap<RoadId, f32>,
+    mut visit: impl FnMut(usize, Option<Vec<RoadId>>),
+) {
+    let network = &world.road_network;
+    // One search serves every trip leaving the same place in the same kind
+    // of vehicle
+    let mut origins: SimMap<(IntersectionId, VehicleType), Vec<usize>> = SimMap::default();
+    for (index, trip) in demand.iter().enumerate() {
+        origins
+            .entry((trip.from, trip.vehicle_type))
+            .or_default()
+            .push(index);
+    }
+    for ((origin, vehicle_type), trips) in origins {
+        let tree = network.cheapest_paths_from(origin, |road| {
+            road.allows(vehicle_type)
+                .then(|| road_secs.get(&road.id).copied())
+                .flatten()
+        });
+        for index in trips {
+            let Some(path) = tree.path_to(demand[index].to) else {
+                visit(index, None);
+                continue;
+            };
+            let mut from = origin;
+            let mut roads = Vec::with_capacity(path.len());
+            for to in path {
+                if let Ok(road_id) = network.find_road_between(from, to) {
+                    roads.push(road_id);
+                }
+                from = to;
+            }
+            visit(index, Some(roads));
+        }
+    }
+}
+
+/// Flow on each road from sending all of the demand down the quickest
+/// routes at the given road times, and the demand that had no route
+fn all_or_nothing(
+    world: &SimWorld,
+    demand: &[OdDemand],
+    road_secs: &SimMap<RoadId, f32>,
+) -> (SimMap<RoadId, f32>, f32) {
+    let mut flows: SimMap<RoadId, f32> = SimMap::default();
+    let mut unrouted = 0.0;
+    route_trips(world, demand, road_secs, |index, roads| {
+        let per_minute = demand[index].per_minute;
+        match roads {
+            Some(roads) => {
+                for road_id in roads {
+                    *flows.entry(road_id).or_default() += per_minute;
+                }
+            }
+            None => unrouted += per_minute,
+        }
+    });
+    (flows, unrouted)
+}
+
+impl SimWorld {
+    /// Trips the city's buildings want to make, in vehicles a minute
+    ///
+    /// Each house's workers are split evenly between the factories that
+    /// would take them, and each factory's deliveries between the shops,
+    /// with a trip back for each; the demand ramp's level scales both, and
+    /// the economy's demand weights scale the trips of the houses and
+    /// factories they list.
+    pub fn od_demand(&self, config: &AssignmentConfig) -> Vec<OdDemand> {
+        let weights = self.demand_weights();
+        self.building_demand(config)
+            .into_iter()
+            .map(|(building, mut trip)| {
+                trip.per_minute *= weights.get(&building).copied().unwrap_or(1.0);
+                trip
+            })
+            .collect()
+    }
+
+    /// Trips the city's buildings would make at their usual demand, each
+    /// with the intersection of the house or factory whose trip it is
+    pub(super) fn building_demand(
+        &self,
+        config: &AssignmentConfig,
+    ) -> Vec<(IntersectionId, OdDemand)> {
+        let level = self.demand_level();
+        let mut demand = Vec::new();
+        let mut round_trip = |building, from, to, vehicle_type, per_minute| {
+            for (from, to) in [(from, to), (to, from)] {
+                let trip = OdDemand {
+                    from,
+                    to,
+                    vehicle_type,
+                    per_minute,
+                };
+                demand.push((building, trip));
+            }
+        };
+
+        let factories: Vec<IntersectionId> = self
+            .factories
+            .values()
+            .map(|factory| factory.intersection_id)
+            .filter(|intersection| !self.factory_on_strike(*intersection))
+            .collect();
+        for apartment in self.apartments.values() {
+            let per_minute = apartment.cars.len() as f32 * config.worker_trips_per_car_minute
+                * level
+                / factories.len().max(1) as f32;
+            let home = apartment.intersection_id;
+            for &factory in &factories {
+                round_trip(home, home, factory, VehicleType::Car, per_minute);
+            }
+        }
+
+        let shops: Vec<IntersectionId> = self.shops.values().map(|shop| shop.intersection_id).collect();
+        for factory in self.factories.values() {
+            let per_minute =
+                config.deliveries_per_factory_minute * level / shops.len().max(1) as f32;
+            let site = factory.intersection_id;
+            for &shop in &shops {
+                round_trip(site, site, shop, VehicleType::Truck, per_minute);
+            }
+        }
+        demand
+    }
+
+    /// Settle the city's demand on its roads without simulating vehicles
+    pub fn assign_traffic(&self, config: &AssignmentConfig) -> Assignment {
+        self.assign_demand(&self.od_demand(config), config)
+    }
+
+    /// Settle the given demand on the city's roads
+    pub(super) fn assign_demand(
+        &self,
+        demand: &[OdDemand],
+        config: &AssignmentConfig,
+    ) -> Assignment {
+        let total: f32 = demand.iter().map(|trip| trip.per_minute).sum();
+        let car_speed = VehicleType::Car.typical_speed();
+        let free_flow: SimMap<RoadId, f32> = self
+            .road_network
+            .roads()
+            .values()
+            .filter(|road| !road.closed)
+            .map(|road| {
+                let secs = free_flow_secs(road, road.length, car_speed) + ETA_INTERSECTION_SECS;
+                (road.id, secs)
+            })
+            .collect();
+        let travel_secs = |flows: &SimMap<RoadId, f32>| -> SimMap<RoadId, f32> {
+            free_flow
+                .iter()
+                .map(|(road_id, secs)| {
+                    let flow = flows.get(road_id).copied().unwrap_or(0.0);
+                    (*road_id, config.travel_secs(*secs, flow))
+                })
+                .collect()
+        };
+
+        let (mut flows, unrouted) = all_or_nothing(self, demand, &free_flow);
+        let mut iterations = 1;
+        let mut relative_gap = 0.0;
+        while iterations < config.max_iterations {
+            let secs = travel_secs(&flows);
+            let (target, _) = all_or_nothing(self, demand, &secs);
+            let total_secs = |flows: &SimMap<RoadId, f32>| -> f32 {
+                flows
+                    .iter()
+                    .map(|(road_id, flow)| flow * secs.get(road_id).copied().unwrap_or(0.0))
+                    .sum()
+            };
+            let current = total_secs(&flows);
+            relative_gap = if current > 0.0 {
+                (current - total_secs(&target)) / current
+            } else {
+                0.0
+            };
+            if relative_gap < ASSIGNMENT_GAP_TOLERANCE {
+                break;
+            }
+            // Step towards the quickest routes by a shrinking share
+            iterations += 1;
+            let step = 1.0 / iterations as f32;
+            for road_id in free_flow.keys() {
+                let flow = flows.get(road_id).copied().unwrap_or(0.0);
+                let goal = target.get(road_id).copied().unwrap_or(0.0);
+                let next = flow + (goal - flow) * step;
+                if next > 0.0 {
+                    flows.insert(*road_id, next);
+                } else {
+                    flows.remove(road_id);
+                }
+            }
+        }
+
+        let secs = travel_secs(&flows);
+        let roads = free_flow
+            .iter()
+            .map(|(road_id, free_flow_secs)| {
+                let flow = flows.get(road_id).copied().unwrap_or(0.0);
+                let road = RoadFlow {
+                    road_id: *road_id,
+                    flow,
+                    free_flow_secs: *free_flow_secs,
+                    travel_secs: secs[road_id],
+                    volume_to_capacity: flow / config.road_capacity_per_minute,
+                };
+                (*road_id, road)
+            })
+            .collect();
+        Assignment {
+            roads,
+            routed_per_minute: total - unrouted,
+            unrouted_per_minute: unrouted,
+            iterations,
+            relative_gap,
+        }
+    }
+}
@@ -0,0 +1,250 @@
+//! Auditing a network for trips with no route
+//!
+//! [`SimWorld::audit_network`] checks every trip the city's buildings make
+//! between each other: workers driving from each apartment to each factory
+//! and home again, and trucks from each factory to each shop and back. Each
+//! trip with no route is listed with the nearest missing link: the shortest
+//! straight road that would join somewhere the trip can get to with
+//! somewhere it can finish from. It's a quick check that a scenario's
+//! layout is complete before running it. Closed roads count as open, since
+//! closures don't last; restrictions on trucks count.
+
+use std::collections::BTreeSet;
+use std::fmt;
+
+use super::types::{IntersectionId, SimMap, VehicleType};
+use super::world::SimWorld;
+
+/// A trip one building makes to another
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum AuditTrip {
+    /// A worker from their apartment to a factory
+    ToWork,
+    /// A worker from the factory home
+    Home,
+    /// A truck from its factory to a shop
+    Delivery,
+    /// A truck from the shop back to its factory
+    Return,
+}
+
+impl AuditTrip {
+    /// The buildings the trip runs between, for display
+    pub fn label(self) -> &'static str {
+        match self {
+            AuditTrip::ToWork => "apartment -> factory",
+            AuditTrip::Home => "factory -> apartment",
+            AuditTrip::Delivery => "factory -> shop",
+            AuditTrip::Return => "shop -> factory",
+        }
+    }
+
+    fn vehicle_type(self) -> VehicleType {
+        match self {
+            AuditTrip::ToWork | AuditTrip::Home => VehicleType::Car,
+            AuditTrip::Delivery | AuditTrip::Return => VehicleType::Truck,
+        }
+    }
+}
+
+/// The shortest straight road that would give a trip a route
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MissingLink {
+    /// An intersection the trip can get to
+    pub from: IntersectionId,
+    /// An intersection the trip can finish from
+    pub to: IntersectionId,
+    /// Straight-line length of the road
+    pub length: f32,
+}
+
+/// A trip between two buildings that has no route
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UnreachablePair {
+    pub trip: AuditTrip,
+    /// Intersection of the building the trip starts at
+    pub from: IntersectionId,
+    /// Intersection of the building the trip ends at
+    pub to: IntersectionId,
+    /// `None` only if the intersections have no positions
+    pub missing_link: Option<MissingLink>,
+}
+
+/// Which of the city's trips have a route
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct NetworkAudit {
+    /// Trips checked
+    pub trips: usize,
+    /// Trips with no route, by trip and then intersections
+    pub unreachable: Vec<UnreachablePair>,
+}
+
+impl NetworkAudit {
+    /// Whether every trip has a route
+    pub fn is_complete(&self) -> bool {
+        self.unreachable.is_empty()
+    }
+}
+
+impl fmt::Display for NetworkAudit {
+    /// A line per trip with no route, with its missing link
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_complete() {
+            return writeln!(f, "All {} trips have a route", self.trips);
+        }
+        writeln!(
+            f,
+            "{} of {} trips have no route",
+            self.unreachable.len(),
+            self.trips
+        )?;
+        for pair in &self.unreachable {
+            write!(
+                f,
+                "  {}: {} -> {}",
+                pair.trip.label(),
+                pair.from.0 .0,
+                pair.to.0 .0
+            )?;
+            match pair.missing_link {
+                Some(link) => writeln!(
+                    f,
+                    ", nearest missing link {} -> {} ({:.1} long)",
+                    link.from.0 .0, link.to.0 .0, link.length
+                )?,
+                None => writeln!(f)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Where the roads a vehicle may drive lead, each way
+struct Reach {
+    forward: SimMap<IntersectionId, Vec<IntersectionId>>,
+    backward: SimMap<IntersectionId, Vec<IntersectionId>>,
+}
+
+impl Reach {
+    fn new(world: &SimWorld, vehicle_type: VehicleType) -> Self {
+        let mut reach = Reach {
+            forward: SimMap::default(),
+            backward: SimMap::default(),
+        };
+        for road in world.road_network.roads().values() {
+            if !road.restriction.allows(vehicle_type) {
+                continue;
+            }
+            let (start, end) = (road.start_intersection, road.end_intersection);
+            reach.forward.entry(start).or_default().push(end);
+            reach.backward.entry(end).or_default().push(start);
+        }
+        reach
+    }
+
+    /// Intersections reachable from `origin` (or, backwards, that can reach
+    /// it), including itself
+    fn reachable(&self, origin: IntersectionId, backwards: bool) -> BTreeSet<IntersectionId> {
+        let links = if backwards {
+            &self.backward
+        } else {
+            &self.forward
+        };
+        let mut seen = BTreeSet::from([origin]);
+        let mut stack = vec![origin];
+        while let Some(at) = stack.pop() {
+            for &next in links.get(&at).into_iter().flatten() {
+                if seen.insert(next) {
+                    stack.push(next);
+                }
+            }
+        }
+        seen
+    }
+}
+
+impl SimWorld {
+    /// Check every trip between the city's buildings for a route, finding
+    /// the nearest missing link of each that has none
+    pub fn audit_network(&self) -> NetworkAudit {
+        let apartments: BTreeSet<IntersectionId> = self
+            .apartments
+            .values()
+            .map(|apartment| apartment.intersection_id)
+            .collect();
+        let factories: BTreeSet<IntersectionId> = self
+            .factories
+            .values()
+            .map(|factory| factory.intersection_id)
+            .collect();
+        let shops: BTreeSet<IntersectionId> = self
+            .shops
+            .values()
+            .map(|shop| shop.intersection_id)
+            .collect();
+
+        let mut audit = NetworkAudit::default();
+        let trips = [
+            (AuditTrip::ToWork, &apartments, &factories),
+            (AuditTrip::Home, &factories, &apartments),
+            (AuditTrip::Delivery, &factories, &shops),
+            (AuditTrip::Return, &shops, &factories),
+        ];
+        for (trip, starts, ends) in trips {
+            let reach = Reach::new(self, trip.vehicle_type());
+            let mut arrivals: SimMap<IntersectionId, BTreeSet<IntersectionId>> = SimMap::default();
+            for &from in starts {
+                let reachable = reach.reachable(from, false);
+                for &to in ends {
+                    // Buildings at the same intersection need no road
+                    if from == to {
+                        continue;
+                    }
+                    audit.trips += 1;
+                    if reachable.contains(&to) {
+                        continue;
+                    }
+                    let finishing = arrivals
+                        .entry(to)
+                        .or_insert_with(|| reach.reachable(to, true));
+                    audit.unreachable.push(UnreachablePair {
+                        trip,
+                        from,
+                        to,
+                        missing_link: self.nearest_missing_link(&reachable, finishing),
+                    });
+                }
+            }
+        }
+        audit
+    }
+
+    /// The shortest straight road from one set of intersections to another
+    fn nearest_missing_link(
+        &self,
+        from: &BTreeSet<IntersectionId>,
+        to: &BTreeSet<IntersectionId>,
+    ) -> Option<MissingLink> {
+        let positions = self.road_network.intersection_positions();
+        let mut nearest: Option<MissingLink> = None;
+        for &start in from {
+            let Some(start_position) = positions.get(&start) else {
+                continue;
+            };
+            for &end in to {
+                let Some(end_position) = positions.get(&end) else {
+                    continue;
+                };
+                let length = start_position.distance(end_position);
+                if start != end && nearest.is_none_or(|link| length < link.length) {
+                    nearest = Some(MissingLink {
+                        from: start,
+                        to: end,
+                        length,
+                    });
+                }
+            }
+        }
+        nearest
+    }
+}
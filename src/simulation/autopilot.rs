@@ -0,0 +1,360 @@
+//! Autopilot city builder
+//!
+//! An AI player that grows a city from an empty map within the game's
+//! budget. It builds on a square grid: each decision either links a
+//! congested road to a neighbouring grid point so traffic can spread out, or
+//! places the building type the city is shortest of. Sites are ranked by
+//! travel distance over the road network, so commutes stay healthy and
+//! deliveries stay short, and by how busy the roads next to them are. It
+//! keeps enough money back to clean up after a crash, so one incident can't
+//! bankrupt it.
+//!
+//! The autopilot builds through [`BuildCommand`]s like a human player, so its
+//! runs score the same way and make a baseline for human layouts.
+
+use std::collections::BTreeMap;
+
+
+use super::achievements::CONGESTED_ROAD_DENSITY;
+use super::command::BuildCommand;
+use super::content::BuildingKind;
+use super::economy::EconomyConfig;
+use super::types::{IntersectionId, Position, SimMap};
+use super::warnings::SimWarning;
+use super::world::SimWorld;
+
+/// Distance between neighbouring grid points the autopilot builds on
+///
+/// Longer than the default healthy commute, so no worker commutes to a
+/// factory next door.
+pub const AUTOPILOT_GRID_SPACING: f32 = 20.0;
+
+/// Simulated seconds between autopilot decisions
+pub const AUTOPILOT_DECISION_SECS: f32 = 5.0;
+
+/// Apartments the autopilot builds per factory, enough workers for the
+/// shifts each delivery takes
+const APARTMENTS_PER_FACTORY: usize = 3;
+
+/// Extra cost of a site whose commute would be shorter than healthy
+const UNHEALTHY_SITE_PENALTY: f32 = 1000.0;
+
+/// Extra cost of a site that can't reach the buildings it needs
+const UNREACHABLE_SITE_PENALTY: f32 = 10_000.0;
+
+/// Cost per unit of distance from the origin, keeping the city compact
+const SPRAWL_COST: f32 = 0.1;
+
+/// A point on the autopilot's grid, in grid steps from the origin
+type GridPoint = (i32, i32);
+
+/// AI player that builds a city on its own
+#[derive(Debug, Clone)]
+pub struct Autopilot {
+    /// Distance between neighbouring grid points
+    spacing: f32,
+    /// Simulated seconds between decisions
+    decision_secs: f32,
+    /// Simulation time of the next decision
+    next_decision: f32,
+}
+
+impl Default for Autopilot {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Autopilot {
+    pub fn new() -> Self {
+        Self {
+            spacing: AUTOPILOT_GRID_SPACING,
+            decision_secs: AUTOPILOT_DECISION_SECS,
+            next_decision: 0.0,
+        }
+    }
+
+    /// Make a decision if one is due and build it, returning what was built
+    ///
+    /// Call before each [`SimWorld::tick`].
+    pub fn step(&mut self, world: &mut SimWorld) -> Vec<BuildCommand> {
+        if world.time < self.next_decision {
+            return Vec::new();
+        }
+        self.next_decision = world.time + self.decision_secs;
+
+        let mut built = Vec::new();
+        for command in self.plan(world) {
+            match world.apply_command(&command) {
+                Ok(true) => built.push(command),
+                // Later commands depend on earlier ones, e.g. a building on
+                // the end of a new road
+                Ok(false) => break,
+                Err(e) => {
+                    let time = world.time;
+                    world.warnings.warn(SimWarning::AutopilotBuild, time, || {
+                        format!("Autopilot failed to build {}: {:#}", command.describe(), e)
+                    });
+                    break;
+                }
+            }
+        }
+        built
+    }
+
+    /// Decide what to build next, to be applied in order
+    ///
+    /// Returns nothing while saving up for the next building.
+    pub fn plan(&self, world: &SimWorld) -> Vec<BuildCommand> {
+        let economy = world
+            .game_state
+            .as_ref()
+            .map(|game_state| game_state.economy.clone())
+            .unwrap_or_default();
+        // Money kept back to pay for an incident's cleanup, which can't be
+        // declined
+        let money = world.game_state.as_ref().map_or(i32::MAX, |game_state| {
+            let reserve = if game_state.insured {
+                0
+            } else {
+                economy
+                    .cost_breakdown_cleanup
+                    .max(economy.cost_collision_cleanup)
+            };
+            game_state.money - reserve
+        });
+        let sites = self.sites(world);
+
+        if sites.is_empty() {
+            // Lay the first stretch of road at the origin
+            if money < economy.cost_road {
+                return Vec::new();
+            }
+            return vec![self.road((0, 0), (1, 0))];
+        }
+
+        if money >= economy.cost_road {
+            if let Some(command) = self.plan_relief_road(world, &sites) {
+                return vec![command];
+            }
+        }
+
+        // Replace worn vehicles when that still leaves enough for a road
+        let worn = world.worn_vehicle_count() as i32;
+        if worn > 0 && money - economy.cost_vehicle_replacement * worn >= economy.cost_road {
+            return vec![BuildCommand::ReplaceWornVehicles];
+        }
+
+        self.plan_building(world, &sites, Self::wanted_kind(world), &economy, money)
+            .unwrap_or_default()
+    }
+
+    /// The building type the city is shortest of
+    ///
+    /// Builds an apartment, a factory and a shop first, then keeps one shop
+    /// per factory and [`APARTMENTS_PER_FACTORY`] apartments per factory.
+    fn wanted_kind(world: &SimWorld) -> BuildingKind {
+        let apartments = world.apartments.len();
+        let factories = world.factories.len();
+        let shops = world.shops.len();
+        if apartments == 0 {
+            BuildingKind::Apartment
+        } else if factories == 0 {
+            BuildingKind::Factory
+        } else if shops < factories {
+            BuildingKind::Shop
+        } else if apartments < APARTMENTS_PER_FACTORY * factories {
+            BuildingKind::Apartment
+        } else {
+            BuildingKind::Factory
+        }
+    }
+
+    /// Link an end of the busiest congested road to a neighbouring grid
+    /// point it isn't connected to yet, giving traffic another way round
+    fn plan_relief_road(
+        &self,
+        world: &SimWorld,
+        sites: &BTreeMap<GridPoint, IntersectionId>,
+    ) -> Option<BuildCommand> {
+        let network = &world.road_network;
+        let (_, road) = network
+            .get_all_roads()
+            .map(|(road_id, road)| (network.calculate_traffic_density(*road_id), road))
+            .filter(|(density, _)| *density > CONGESTED_ROAD_DENSITY)
+            .max_by(|(a, _), (b, _)| a.total_cmp(b))?;
+
+        for end in [road.start_intersection, road.end_intersection] {
+            let Some((&point, _)) = sites.iter().find(|(_, site)| **site == end) else {
+                continue;
+            };
+            for neighbour in neighbours(point) {
+                if let Some(&other) = sites.get(&neighbour) {
+                    if network.find_road_between(end, other).is_err() {
+                        return Some(self.road(point, neighbour));
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    /// Pick the cheapest affordable site for a building, either a free grid
+    /// point on the network or a new one reached by one more road
+    fn plan_building(
+        &self,
+        world: &SimWorld,
+        sites: &BTreeMap<GridPoint, IntersectionId>,
+        kind: BuildingKind,
+        economy: &EconomyConfig,
+        money: i32,
+    ) -> Option<Vec<BuildCommand>> {
+        let building_cost = match kind {
+            BuildingKind::Apartment => economy.cost_apartment,
+            BuildingKind::Factory => economy.cost_factory,
+            BuildingKind::Shop => economy.cost_shop,
+        };
+        if money < building_cost {
+            return None;
+        }
+
+        let network = &world.road_network;
+        let apartments: Vec<_> = world.apartments.values().map(|a| a.intersection_id).collect();
+        let factories: Vec<_> = world.factories.values().map(|f| f.intersection_id).collect();
+        let shops: Vec<_> = world.shops.values().map(|s| s.intersection_id).collect();
+        let to_apartment = network.travel_distances(&apartments);
+        let to_factory = network.travel_distances(&factories);
+        let to_shop = network.travel_distances(&shops);
+        let healthy = world.commute_healthy_distance();
+
+        // Cost of building at a grid point reached from `site` after `extra`
+        // more road
+        let site_cost = |site: IntersectionId, extra: f32, point: GridPoint| {
+            let travel = |distances: &SimMap<IntersectionId, f32>, min_distance: f32| {
+                if distances.is_empty() {
+                    return 0.0;
+                }
+                match distances.get(&site) {
+                    Some(distance) if distance + extra < min_distance => {
+                        distance + extra + UNHEALTHY_SITE_PENALTY
+                    }
+                    Some(distance) => distance + extra,
+                    None => UNREACHABLE_SITE_PENALTY,
+                }
+            };
+            let access = match kind {
+                BuildingKind::Apartment => travel(&to_factory, healthy),
+                BuildingKind::Factory => travel(&to_apartment, healthy) + travel(&to_shop, 0.0),
+                BuildingKind::Shop => travel(&to_factory, 0.0),
+            };
+            // Each car on the roads by the site counts like one more grid step
+            let congestion: f32 = network
+                .get_roads_at_intersection(site)
+                .into_iter()
+                .map(|road_id| network.calculate_traffic_density(road_id))
+                .sum::<f32>()
+                * self.spacing
+                * self.spacing;
+            let sprawl = self.position(point).distance(&Position::default()) * SPRAWL_COST;
+            access + congestion + sprawl
+        };
+
+        let mut best: Option<(f32, Vec<BuildCommand>)> = None;
+        let mut consider = |cost: f32, commands: Vec<BuildCommand>| {
+            if best.as_ref().is_none_or(|(best_cost, _)| cost < *best_cost) {
+                best = Some((cost, commands));
+            }
+        };
+        for (&point, &site) in sites {
+            // Grid points already built on or with a road straight through
+            // can't take a building
+            if world
+                .building_site(self.position(point), self.snap_distance())
+                .is_ok()
+            {
+                consider(
+                    site_cost(site, 0.0, point),
+                    vec![self.building(kind, point)],
+                );
+            }
+            if money < building_cost + economy.cost_road {
+                continue;
+            }
+            for neighbour in neighbours(point) {
+                if sites.contains_key(&neighbour) || !self.is_clear(world, neighbour) {
+                    continue;
+                }
+                consider(
+                    site_cost(site, self.spacing, neighbour),
+                    vec![self.road(point, neighbour), self.building(kind, neighbour)],
+                );
+            }
+        }
+        best.map(|(_, commands)| commands)
+    }
+
+    /// Intersections on grid points, by grid point
+    fn sites(&self, world: &SimWorld) -> BTreeMap<GridPoint, IntersectionId> {
+        world
+            .road_network
+            .intersection_positions()
+            .iter()
+            .filter_map(|(id, position)| {
+                let point = (
+                    (position.x / self.spacing).round() as i32,
+                    (position.z / self.spacing).round() as i32,
+                );
+                (position.distance(&self.position(point)) < self.snap_distance())
+                    .then_some((point, *id))
+            })
+            .collect()
+    }
+
+    /// Whether a grid point is inside the world and clear of intersections
+    /// and roads, so building there won't snap to or split anything
+    fn is_clear(&self, world: &SimWorld, point: GridPoint) -> bool {
+        let position = self.position(point);
+        if !world.bounds().contains(&position) {
+            return false;
+        }
+        let network = &world.road_network;
+        let near_intersection = network
+            .find_closest_intersection(&position)
+            .and_then(|id| network.get_intersection_position(id))
+            .is_some_and(|other| position.distance(other) <= self.snap_distance());
+        let near_road = network
+            .find_closest_point_on_road(&position)
+            .is_some_and(|(_, closest, _, _)| position.distance(&closest) <= self.snap_distance());
+        !near_intersection && !near_road
+    }
+
+    fn position(&self, (x, z): GridPoint) -> Position {
+        Position::new(x as f32 * self.spacing, 0.0, z as f32 * self.spacing)
+    }
+
+    fn snap_distance(&self) -> f32 {
+        self.spacing / 4.0
+    }
+
+    fn road(&self, start: GridPoint, end: GridPoint) -> BuildCommand {
+        BuildCommand::Road {
+            start: self.position(start),
+            end: self.position(end),
+            snap_distance: self.snap_distance(),
+        }
+    }
+
+    fn building(&self, kind: BuildingKind, point: GridPoint) -> BuildCommand {
+        BuildCommand::Building {
+            kind,
+            variant: None,
+            position: self.position(point),
+            snap_distance: self.snap_distance(),
+        }
+    }
+}
+
+/// The four grid points next to a point, in a fixed order
+fn neighbours((x, z): GridPoint) -> [GridPoint; 4] {
+    [(x + 1, z), (x, z + 1), (x - 1, z), (x, z - 1)]
+}
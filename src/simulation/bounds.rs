@@ -0,0 +1,282 @@
+//! The part of the map a city is built on
+//!
+//! A world has [`WorldBounds`]: the rectangle its roads and buildings lie
+//! in. A map can set them with [`SimWorld::set_bounds`]; otherwise they are
+//! derived, as a [`DEFAULT_WORLD_SIZE`] square round the origin widened to
+//! cover the terrain and every intersection already built. Every
+//! [`BuildCommand`] must build inside them, and the front-end sizes its
+//! ground and keeps its camera over them.
+//!
+//! Positions can be clamped into the bounds and snapped to a grid. An
+//! importer placing a real street map converts latitude and longitude to
+//! positions on the map with a [`MapProjection`], such as an
+//! [`Equirectangular`] projection about a point in the middle of the map.
+//!
+//! [`BuildCommand`]: super::command::BuildCommand
+
+use anyhow::{bail, ensure, Result};
+#[cfg(feature = "serialization")]
+use serde::{Deserialize, Serialize};
+
+use super::elevation::Heightmap;
+use super::emissions::WORLD_UNIT_METRES;
+use super::region::Region;
+use super::types::Position;
+use super::world::SimWorld;
+
+/// Side of the square round the origin a world without bounds of its own is
+/// built in
+pub const DEFAULT_WORLD_SIZE: f32 = 200.0;
+
+/// Mean radius of the Earth, for projecting latitude and longitude
+const EARTH_RADIUS_METRES: f64 = 6_371_000.0;
+
+/// The rectangle of the map a world is built in, aligned with the world's
+/// axes
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+pub struct WorldBounds {
+    pub min_x: f32,
+    pub min_z: f32,
+    pub max_x: f32,
+    pub max_z: f32,
+}
+
+impl Default for WorldBounds {
+    fn default() -> Self {
+        Self::around(Position::default(), DEFAULT_WORLD_SIZE / 2.0)
+    }
+}
+
+impl WorldBounds {
+    /// The rectangle between two opposite corners
+    pub fn new(corner: Position, opposite: Position) -> Self {
+        Self {
+            min_x: corner.x.min(opposite.x),
+            min_z: corner.z.min(opposite.z),
+            max_x: corner.x.max(opposite.x),
+            max_z: corner.z.max(opposite.z),
+        }
+    }
+
+    /// The square reaching `half_size` either way from `center`
+    pub fn around(center: Position, half_size: f32) -> Self {
+        Self {
+            min_x: center.x - half_size,
+            min_z: center.z - half_size,
+            max_x: center.x + half_size,
+            max_z: center.z + half_size,
+        }
+    }
+
+    /// The default square widened to cover a heightmap and some positions
+    pub fn covering(
+        heightmap: Option<&Heightmap>,
+        positions: impl IntoIterator<Item = Position>,
+    ) -> Self {
+        let mut bounds = Self::default();
+        if let Some(heightmap) = heightmap {
+            let (max_x, max_z) = heightmap.max_corner();
+            bounds = bounds
+                .expanded_to(&Position::new(heightmap.min_x, 0.0, heightmap.min_z))
+                .expanded_to(&Position::new(max_x, 0.0, max_z));
+        }
+        positions
+            .into_iter()
+            .fold(bounds, |bounds, position| bounds.expanded_to(&position))
+    }
+
+    /// The smallest rectangle covering this one and a point
+    pub fn expanded_to(&self, position: &Position) -> Self {
+        Self {
+            min_x: self.min_x.min(position.x),
+            min_z: self.min_z.min(position.z),
+            max_x: self.max_x.max(position.x),
+            max_z: self.max_z.max(position.z),
+        }
+    }
+
+    /// Whether a point is in the rectangle, whatever its height
+    pub fn contains(&self, position: &Position) -> bool {
+        (self.min_x..=self.max_x).contains(&position.x)
+            && (self.min_z..=self.max_z).contains(&position.z)
+    }
+
+    /// Extent along X
+    pub fn width(&self) -> f32 {
+        self.max_x - self.min_x
+    }
+
+    /// Extent along Z
+    pub fn depth(&self) -> f32 {
+        self.max_z - self.min_z
+    }
+
+    /// The middle of the rectangle, at ground level
+    pub fn center(&self) -> Position {
+        Position::new(
+            (self.min_x + self.max_x) / 2.0,
+            0.0,
+            (self.min_z + self.max_z) / 2.0,
+        )
+    }
+
+    /// The same rectangle, for finding the vehicles in it
+    pub fn region(&self) -> Region {
+        Region {
+            min_x: self.min_x,
+            min_z: self.min_z,
+            max_x: self.max_x,
+            max_z: self.max_z,
+        }
+    }
+}
+
+/// Converts between latitude and longitude and positions on the map, for
+/// importing real street maps
+pub trait MapProjection {
+    /// Where a point given in degrees lies on the map, at ground level
+    fn project(&self, latitude: f64, longitude: f64) -> Position;
+
+    /// Latitude and longitude, in degrees, of a point on the map
+    fn unproject(&self, position: &Position) -> (f64, f64);
+}
+
+/// Projects the Earth flat about an origin, with X running east and Z north
+///
+/// Distances are true near the origin, which is plenty for the few
+/// kilometres a city covers.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Equirectangular {
+    /// Latitude, in degrees, of the map's origin
+    pub origin_latitude: f64,
+    /// Longitude, in degrees, of the map's origin
+    pub origin_longitude: f64,
+}
+
+impl Equirectangular {
+    pub fn new(origin_latitude: f64, origin_longitude: f64) -> Self {
+        Self {
+            origin_latitude,
+            origin_longitude,
+        }
+    }
+
+    /// World units per radian east and north of the origin
+    fn scale(&self) -> (f64, f64) {
+        let north = EARTH_RADIUS_METRES / WORLD_UNIT_METRES as f64;
+        (north * self.origin_latitude.to_radians().cos(), north)
+    }
+}
+
+impl MapProjection for Equirectangular {
+    fn project(&self, latitude: f64, longitude: f64) -> Position {
+        let (east, north) = self.scale();
+        Position::new(
+            ((longitude - self.origin_longitude).to_radians() * east) as f32,
+            0.0,
+            ((latitude - self.origin_latitude).to_radians() * north) as f32,
+        )
+    }
+
+    fn unproject(&self, position: &Position) -> (f64, f64) {
+        let (east, north) = self.scale();
+        (
+            self.origin_latitude + (position.z as f64 / north).to_degrees(),
+            self.origin_longitude + (position.x as f64 / east).to_degrees(),
+        )
+    }
+}
+
+impl Position {
+    /// The nearest point inside `bounds`, at the same height
+    pub fn clamp_to(&self, bounds: &WorldBounds) -> Position {
+        Position {
+            x: self.x.clamp(bounds.min_x, bounds.max_x),
+            z: self.z.clamp(bounds.min_z, bounds.max_z),
+            ..*self
+        }
+    }
+
+    /// The nearest corner of a grid of `cell`-sized squares from the origin,
+    /// at the same height; the point itself for a grid of no size
+    pub fn snap_to_grid(&self, cell: f32) -> Position {
+        if cell <= 0.0 {
+            return *self;
+        }
+        Position {
+            x: (self.x / cell).round() * cell,
+            z: (self.z / cell).round() * cell,
+            ..*self
+        }
+    }
+
+    /// Where a point given in degrees lies on the map under `projection`
+    pub fn from_lat_lon(latitude: f64, longitude: f64, projection: &impl MapProjection) -> Self {
+        projection.project(latitude, longitude)
+    }
+
+    /// Latitude and longitude, in degrees, of the point under `projection`
+    pub fn to_lat_lon(&self, projection: &impl MapProjection) -> (f64, f64) {
+        projection.unproject(self)
+    }
+}
+
+impl SimWorld {
+    /// The rectangle the world is built in: the bounds it was given, or the
+    /// default square widened to cover its terrain and intersections
+    pub fn bounds(&self) -> WorldBounds {
+        self.bounds.unwrap_or_else(|| {
+            WorldBounds::covering(
+                self.heightmap.as_ref(),
+                self.intersections
+                    .values()
+                    .map(|intersection| intersection.position),
+            )
+        })
+    }
+
+    /// The bounds the world was given, if any
+    pub fn configured_bounds(&self) -> Option<WorldBounds> {
+        self.bounds
+    }
+
+    /// Give the world bounds of its own, or (with `None`) go back to
+    /// deriving them
+    ///
+    /// The bounds must have some size and cover every intersection.
+    pub fn set_bounds(&mut self, bounds: Option<WorldBounds>) -> Result<()> {
+        if let Some(bounds) = bounds {
+            ensure!(
+                bounds.width() > 0.0 && bounds.depth() > 0.0,
+                "The world's bounds must have some size"
+            );
+            let outside = self
+                .intersections
+                .values()
+                .find(|intersection| !bounds.contains(&intersection.position));
+            if let Some(intersection) = outside {
+                bail!("Intersection {:?} is outside the bounds", intersection.id);
+            }
+        }
+        self.bounds = bounds;
+        self.changes.mark_layout();
+        Ok(())
+    }
+
+    /// Fail unless a point is inside the world's bounds
+    pub(super) fn ensure_in_bounds(&self, position: &Position) -> Result<()> {
+        let bounds = self.bounds();
+        ensure!(
+            bounds.contains(position),
+            "({:.1}, {:.1}) is outside the world, which runs from ({:.1}, {:.1}) to ({:.1}, {:.1})",
+            position.x,
+            position.z,
+            bounds.min_x,
+            bounds.min_z,
+            bounds.max_x,
+            bounds.max_z
+        );
+        Ok(())
+    }
+}
@@ -2,30 +2,65 @@
 //!
 //! Apartments, factories, and shops - standalone implementations.
 
+use std::collections::VecDeque;
+#[cfg(feature = "serialization")]
+use serde::{Deserialize, Serialize};
+
+use super::factory::{
+    FACTORY_MATERIALS_PER_DELIVERY, FACTORY_SHIFTS_PER_DELIVERY, FACTORY_WORK_TIME,
+};
+use super::fleet::VehicleRecord;
 use super::types::{CarId, FactoryId, ApartmentId, IntersectionId, ShopId};
 
 /// An apartment in the simulation
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
 #[allow(dead_code)]
 pub struct SimApartment {
     pub id: ApartmentId,
     pub intersection_id: IntersectionId,
     /// The cars owned by this apartment (10 total, if out driving)
     pub cars: Vec<Option<CarId>>,
+    /// Age and mileage of the car in each slot
+    pub vehicles: Vec<VehicleRecord>,
+    /// Content pack building type this apartment was built from, if any
+    pub variant: Option<String>,
+    /// Earliest time the next worker may leave, under a demand ramp
+    pub next_departure: f32,
 }
 
 impl SimApartment {
-    pub fn new(id: ApartmentId, intersection_id: IntersectionId) -> Self {
-        Self {
+    /// Create an apartment whose cars are bought at `now`
+    pub fn new(id: ApartmentId, intersection_id: IntersectionId, now: f32) -> Self {
+        let mut apartment = Self {
             id,
             intersection_id,
-            cars: vec![None; 10],
-        }
+            cars: Vec::new(),
+            vehicles: Vec::new(),
+            variant: None,
+            next_departure: 0.0,
+        };
+        apartment.set_car_count(10, now);
+        apartment
+    }
+
+    /// Give the apartment `count` new cars, all at home
+    pub fn set_car_count(&mut self, count: usize, now: f32) {
+        self.cars = vec![None; count];
+        self.vehicles = vec![VehicleRecord::new(now); count];
+    }
+
+    /// Give the apartment `count` more new cars, at home
+    pub fn add_cars(&mut self, count: usize, now: f32) {
+        self.cars.extend(std::iter::repeat_n(None, count));
+        self.vehicles
+            .extend(std::iter::repeat_n(VehicleRecord::new(now), count));
     }
 }
 
 /// A factory in the simulation
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
 pub struct SimFactory {
     pub id: FactoryId,
     pub intersection_id: IntersectionId,
@@ -37,10 +72,25 @@ pub struct SimFactory {
     pub max_deliveries: u32,
     /// The truck owned by this factory (if out making delivery)
     pub truck: Option<CarId>,
+    /// Age and mileage of the factory's truck
+    pub truck_record: VehicleRecord,
+    /// Seconds each worker spends at the factory
+    pub work_time: f32,
+    /// Worker shifts it takes to finish one delivery
+    pub shifts_per_delivery: u32,
+    /// Shifts worked toward the next delivery
+    pub shifts_worked: u32,
+    /// Loads of raw materials each delivery uses, when factories need them
+    pub materials_per_delivery: u32,
+    /// Content pack building type this factory was built from, if any
+    pub variant: Option<String>,
+    /// Loads of raw materials brought in from a rail terminal
+    pub materials: u32,
 }
 
 impl SimFactory {
-    pub fn new(id: FactoryId, intersection_id: IntersectionId) -> Self {
+    /// Create a factory whose truck is bought at `now`
+    pub fn new(id: FactoryId, intersection_id: IntersectionId, now: f32) -> Self {
         Self {
             id,
             intersection_id,
@@ -48,17 +98,40 @@ impl SimFactory {
             deliveries_ready: 0,
             max_deliveries: 2,
             truck: None,
+            truck_record: VehicleRecord::new(now),
+            work_time: FACTORY_WORK_TIME,
+            shifts_per_delivery: FACTORY_SHIFTS_PER_DELIVERY,
+            shifts_worked: 0,
+            materials_per_delivery: FACTORY_MATERIALS_PER_DELIVERY,
+            variant: None,
+            materials: 0,
         }
     }
 }
 
 /// A shop in the simulation
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
 pub struct SimShop {
     pub id: ShopId,
     pub intersection_id: IntersectionId,
     /// Number of deliveries received
     pub cars_received: usize,
+    /// Simulation time of the latest delivery, if any
+    pub last_delivery: Option<f32>,
+    /// Content pack building type this shop was built from, if any
+    pub variant: Option<String>,
+    /// Times of the orders waiting for a delivery, oldest first (demand
+    /// ramp only)
+    pub orders: VecDeque<f32>,
+    /// How many of the oldest orders a truck is on its way to, as of the
+    /// last dispatch
+    pub orders_en_route: usize,
+    /// Fraction of the next order placed so far
+    pub order_progress: f32,
+    /// Goods on the shelves: one a delivery, sold off over time (see
+    /// [`super::pricing`])
+    pub stock: f32,
 }
 
 impl SimShop {
@@ -67,11 +140,20 @@ impl SimShop {
             id,
             intersection_id,
             cars_received: 0,
+            last_delivery: None,
+            variant: None,
+            orders: VecDeque::new(),
+            orders_en_route: 0,
+            order_progress: 0.0,
+            stock: 0.0,
         }
     }
 
-    /// Receive a delivery
-    pub fn receive_delivery(&mut self) {
+    /// Receive a delivery at `time`, filling the oldest open order
+    pub fn receive_delivery(&mut self, time: f32) {
         self.cars_received += 1;
+        self.last_delivery = Some(time);
+        self.orders.pop_front();
+        self.stock += 1.0;
     }
 }
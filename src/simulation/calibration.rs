@@ -0,0 +1,303 @@
+//! Demand calibration from observed traffic counts
+//!
+//! A city rebuilt from a real network should carry the traffic counted on
+//! its roads. [`SimWorld::calibrate_demand`] fits the city's demand to
+//! counts on some of its roads: each round it settles the demand on the
+//! roads with the traffic assignment, then scales each house's and
+//! factory's trips by how far the counted roads its trips use are over or
+//! under their counts, until every counted road is within tolerance. The
+//! result is a [`DemandWeight`] per house and factory, which an economy's
+//! `demand_weights` applies to [`SimWorld::od_demand`] from then on.
+//!
+//! Counts are read from a CSV of `from,to,per_minute` lines, naming each
+//! road by the intersections it runs between like scenario events do.
+
+use std::path::Path;
+
+use anyhow::{bail, ensure, Context, Result};
+#[cfg(feature = "serialization")]
+use serde::{Deserialize, Serialize};
+
+use super::assignment::{route_trips, AssignmentConfig};
+use super::types::{IntersectionId, RoadId, SimId, SimMap};
+use super::world::SimWorld;
+
+/// Header line of a road counts CSV
+pub const ROAD_COUNTS_CSV_HEADER: &str = "from,to,per_minute";
+
+/// Counts below this many vehicles a minute are matched to within the
+/// tolerance of this many instead of a share of the count
+const COUNT_FLOOR: f32 = 1.0;
+
+/// Most a building's weight changes by in one round, either way
+const MAX_ROUND_FACTOR: f32 = 2.0;
+
+/// How much of its usual demand a house or factory sends
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serialization", serde(deny_unknown_fields))]
+pub struct DemandWeight {
+    /// The building, by its intersection
+    pub building: IntersectionId,
+    /// 1 sends its usual trips, 2 twice as many
+    pub weight: f32,
+}
+
+impl DemandWeight {
+    /// Reject negative or infinite weights
+    pub fn validate(&self) -> Result<()> {
+        ensure!(
+            self.weight.is_finite() && self.weight >= 0.0,
+            "demand weight of building {:?} must be a non-negative number (got {})",
+            self.building,
+            self.weight
+        );
+        Ok(())
+    }
+}
+
+/// Vehicles a minute counted on a road
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RoadCount {
+    pub from: IntersectionId,
+    pub to: IntersectionId,
+    pub per_minute: f32,
+}
+
+/// Read road counts from CSV text, skipping the header and blank lines
+pub fn parse_road_counts(text: &str) -> Result<Vec<RoadCount>> {
+    let mut counts = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || (index == 0 && line == ROAD_COUNTS_CSV_HEADER) {
+            continue;
+        }
+        let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+        let [from, to, per_minute] = fields[..] else {
+            bail!(
+                "line {}: expected {}, got '{}'",
+                index + 1,
+                ROAD_COUNTS_CSV_HEADER,
+                line
+            );
+        };
+        let id = |field: &str| -> Result<IntersectionId> {
+            let id = field
+                .parse()
+                .with_context(|| format!("line {}: bad intersection ID '{}'", index + 1, field))?;
+            Ok(IntersectionId(SimId(id)))
+        };
+        let per_minute: f32 = per_minute
+            .parse()
+            .with_context(|| format!("line {}: bad count '{}'", index + 1, per_minute))?;
+        ensure!(
+            per_minute.is_finite() && per_minute >= 0.0,
+            "line {}: count must not be negative (got {})",
+            index + 1,
+            per_minute
+        );
+        counts.push(RoadCount {
+            from: id(from)?,
+            to: id(to)?,
+            per_minute,
+        });
+    }
+    Ok(counts)
+}
+
+/// Read road counts from a CSV file
+pub fn load_road_counts(path: &Path) -> Result<Vec<RoadCount>> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read road counts {}", path.display()))?;
+    parse_road_counts(&text).with_context(|| format!("Invalid road counts {}", path.display()))
+}
+
+/// How close calibration has to get and how long it may take
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CalibrationConfig {
+    /// Largest share a counted road's flow may be off its count by
+    pub tolerance: f32,
+    /// Most rounds of adjusting the demand before giving up
+    pub max_rounds: usize,
+    /// Demand and road settings the flows are settled with
+    pub assignment: AssignmentConfig,
+}
+
+impl Default for CalibrationConfig {
+    fn default() -> Self {
+        Self {
+            tolerance: 0.1,
+            max_rounds: 30,
+            assignment: AssignmentConfig::default(),
+        }
+    }
+}
+
+/// A counted road's flow after calibrating
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CalibratedRoad {
+    pub road_id: RoadId,
+    /// Vehicles a minute counted
+    pub target: f32,
+    /// Vehicles a minute with the calibrated demand
+    pub flow: f32,
+}
+
+impl CalibratedRoad {
+    /// How far the flow is off the count, as a share of the count
+    pub fn error(&self) -> f32 {
+        (self.flow - self.target).abs() / self.target.max(COUNT_FLOOR)
+    }
+}
+
+/// Demand fitted to road counts
+#[derive(Debug, Clone, PartialEq)]
+pub struct Calibration {
+    /// Weight of every house and factory, in intersection order
+    pub weights: Vec<DemandWeight>,
+    /// Every counted road, in the order counted
+    pub roads: Vec<CalibratedRoad>,
+    /// Rounds of settling the demand run
+    pub rounds: usize,
+    /// Whether every counted road is within the tolerance
+    pub converged: bool,
+}
+
+impl Calibration {
+    /// Largest share any counted road is off its count by
+    pub fn worst_error(&self) -> f32 {
+        self.roads
+            .iter()
+            .map(CalibratedRoad::error)
+            .fold(0.0, f32::max)
+    }
+}
+
+impl SimWorld {
+    /// The economy's demand weights, by building
+    pub fn demand_weights(&self) -> SimMap<IntersectionId, f32> {
+        self.game_state
+            .as_ref()
+            .map(|game_state| {
+                game_state
+                    .economy
+                    .demand_weights
+                    .iter()
+                    .map(|weight| (weight.building, weight.weight))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Fit the demand of the city's houses and factories to traffic counted
+    /// on its roads, starting from the economy's demand weights
+    ///
+    /// Fails if a counted road isn't in the city. Buildings none of whose
+    /// trips use a counted road keep their weight.
+    pub fn calibrate_demand(
+        &self,
+        counts: &[RoadCount],
+        config: &CalibrationConfig,
+    ) -> Result<Calibration> {
+        ensure!(!counts.is_empty(), "No road counts to calibrate against");
+        let mut targets: SimMap<RoadId, f32> = SimMap::default();
+        let mut counted = Vec::with_capacity(counts.len());
+        for count in counts {
+            let road_id = self
+                .road_network
+                .find_road_between(count.from, count.to)
+                .with_context(|| {
+                    format!("No counted road from {:?} to {:?}", count.from, count.to)
+                })?;
+            ensure!(
+                targets.insert(road_id, count.per_minute).is_none(),
+                "Road from {:?} to {:?} is counted twice",
+                count.from,
+                count.to
+            );
+            counted.push((road_id, count.per_minute));
+        }
+
+        let base = self.building_demand(&config.assignment);
+        let mut weights = self.demand_weights();
+        for (building, _) in &base {
+            weights.entry(*building).or_insert(1.0);
+        }
+
+        let mut rounds = 0;
+        loop {
+            rounds += 1;
+            let demand: Vec<_> = base
+                .iter()
+                .map(|(building, trip)| {
+                    let mut trip = *trip;
+                    trip.per_minute *= weights[building];
+                    trip
+                })
+                .collect();
+            let assignment = self.assign_demand(&demand, &config.assignment);
+            let roads: Vec<CalibratedRoad> = counted
+                .iter()
+                .map(|&(road_id, target)| CalibratedRoad {
+                    road_id,
+                    target,
+                    flow: assignment.roads.get(&road_id).map_or(0.0, |road| road.flow),
+                })
+                .collect();
+            let converged = roads.iter().all(|road| road.error() <= config.tolerance);
+            if converged || rounds >= config.max_rounds {
+                return Ok(Calibration {
+                    weights: weights
+                        .into_iter()
+                        .map(|(building, weight)| DemandWeight { building, weight })
+                        .collect(),
+                    roads,
+                    rounds,
+                    converged,
+                });
+            }
+
+            // Each trip wants scaling by the average of how far the counted
+            // roads on its route are off, in proportion; a building moves
+            // by the average of its trips'
+            let ratios: SimMap<RoadId, f32> = roads
+                .iter()
+                .map(|road| {
+                    let ratio = road.target.max(f32::EPSILON) / road.flow.max(f32::EPSILON);
+                    (road.road_id, ratio.ln())
+                })
+                .collect();
+            let road_secs = assignment
+                .roads
+                .iter()
+                .map(|(road_id, road)| (*road_id, road.travel_secs))
+                .collect();
+            let mut pulls: SimMap<IntersectionId, (f32, f32)> = SimMap::default();
+            route_trips(self, &demand, &road_secs, |index, route| {
+                let logs: Vec<f32> = route
+                    .iter()
+                    .flatten()
+                    .filter_map(|road_id| ratios.get(road_id).copied())
+                    .collect();
+                if logs.is_empty() {
+                    return;
+                }
+                let (building, trip) = &base[index];
+                let pull = pulls.entry(*building).or_default();
+                pull.0 += trip.per_minute * logs.iter().sum::<f32>() / logs.len() as f32;
+                pull.1 += trip.per_minute;
+            });
+            for (building, (log_sum, share)) in pulls {
+                if share <= 0.0 {
+                    continue;
+                }
+                let factor = (log_sum / share)
+                    .exp()
+                    .clamp(1.0 / MAX_ROUND_FACTOR, MAX_ROUND_FACTOR);
+                if let Some(weight) = weights.get_mut(&building) {
+                    *weight *= factor;
+                }
+            }
+        }
+    }
+}
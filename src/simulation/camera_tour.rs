@@ -0,0 +1,181 @@
+//! Scripted camera tours
+//!
+//! Demo footage is only worth comparing between versions if it is shot the
+//! same way each time. A [`CameraTour`] is a camera path written in a TOML
+//! file: keyframes giving, at a time into the tour, the point the camera
+//! looks at and how far it is zoomed in. The UI plays it back while the
+//! city named in the tour (a save, or the test world of the run's seed)
+//! runs, so the same tour of the same city makes the same video.
+//!
+//! ```toml
+//! save = "demo-city.json"   # relative to the tour file; the test world without it
+//!
+//! [[keyframes]]
+//! time = 0.0
+//! position = { x = 20.0, y = 0.0, z = 20.0 }
+//! zoom = 1.0
+//! caption = "The city centre"
+//!
+//! [[keyframes]]
+//! time = 8.0
+//! position = { x = 40.0, y = 0.0, z = 0.0 }
+//! zoom = 2.0
+//! ```
+//!
+//! Times are seconds of simulation time since the tour began, so the
+//! camera keeps step with the traffic however fast the machine recording it
+//! draws frames. Between keyframes the camera eases from one to the next.
+//! It waits at the first until its time and holds at the last once the
+//! tour is over. A zoom of 1 is the cinematic camera's usual view and 2
+//! twice as close. Each caption shows from its keyframe until the next
+//! keyframe with one.
+
+use anyhow::{ensure, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use super::save::SaveFile;
+use super::types::Position;
+use super::world::SimWorld;
+
+/// Where the camera is at one moment of a tour
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CameraKeyframe {
+    /// Seconds of simulation time into the tour
+    pub time: f32,
+    /// Point the camera looks at
+    pub position: Position,
+    /// How far the camera is zoomed in, 1 being the usual view
+    #[serde(default = "default_zoom")]
+    pub zoom: f32,
+    /// Text shown from this keyframe on
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub caption: Option<String>,
+}
+
+fn default_zoom() -> f32 {
+    1.0
+}
+
+/// The camera at a moment of a tour, between its keyframes
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CameraPose<'a> {
+    /// Point the camera looks at
+    pub position: Position,
+    /// How far the camera is zoomed in, 1 being the usual view
+    pub zoom: f32,
+    /// The latest caption reached, if any
+    pub caption: Option<&'a str>,
+}
+
+/// A camera path to play back over a city
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CameraTour {
+    /// Save the city is loaded from; the test world without one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub save: Option<PathBuf>,
+    /// The camera's keyframes, in time order
+    pub keyframes: Vec<CameraKeyframe>,
+}
+
+impl CameraTour {
+    /// Parse and validate a tour from TOML
+    pub fn from_toml_str(text: &str) -> Result<Self> {
+        let tour: Self = toml::from_str(text).context("Failed to parse camera tour TOML")?;
+        tour.validate()?;
+        Ok(tour)
+    }
+
+    /// Load a tour from a TOML file, resolving its save next to the file
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read camera tour {}", path.display()))?;
+        let mut tour = Self::from_toml_str(&text)
+            .with_context(|| format!("Invalid camera tour {}", path.display()))?;
+        if let (Some(save), Some(dir)) = (&tour.save, path.parent()) {
+            tour.save = Some(dir.join(save));
+        }
+        Ok(tour)
+    }
+
+    /// Reject tours with no keyframes, keyframes out of order or zooms that
+    /// aren't positive
+    pub fn validate(&self) -> Result<()> {
+        ensure!(
+            !self.keyframes.is_empty(),
+            "A camera tour needs at least one keyframe"
+        );
+        let mut last = 0.0;
+        for (index, keyframe) in self.keyframes.iter().enumerate() {
+            ensure!(
+                keyframe.time >= last,
+                "Keyframe {} at {}s comes before the one before it",
+                index + 1,
+                keyframe.time
+            );
+            ensure!(
+                keyframe.zoom > 0.0,
+                "Keyframe {} has zoom {}; zoom must be positive",
+                index + 1,
+                keyframe.zoom
+            );
+            last = keyframe.time;
+        }
+        Ok(())
+    }
+
+    /// Seconds from the start of the tour to its last keyframe
+    pub fn duration(&self) -> f32 {
+        self.keyframes.last().map_or(0.0, |keyframe| keyframe.time)
+    }
+
+    /// Where the camera is `time` seconds into the tour
+    pub fn pose_at(&self, time: f32) -> CameraPose<'_> {
+        let next = self
+            .keyframes
+            .iter()
+            .position(|keyframe| keyframe.time > time)
+            .unwrap_or(self.keyframes.len());
+        let caption = self.keyframes[..next.max(1)]
+            .iter()
+            .rev()
+            .find_map(|keyframe| keyframe.caption.as_deref());
+        let (from, to) = match next {
+            0 => (&self.keyframes[0], &self.keyframes[0]),
+            next if next == self.keyframes.len() => {
+                (&self.keyframes[next - 1], &self.keyframes[next - 1])
+            }
+            next => (&self.keyframes[next - 1], &self.keyframes[next]),
+        };
+        let span = to.time - from.time;
+        let t = if span > 0.0 {
+            ((time - from.time) / span).clamp(0.0, 1.0)
+        } else {
+            1.0
+        };
+        // Ease in and out
+        let t = t * t * (3.0 - 2.0 * t);
+        CameraPose {
+            position: Position::new(
+                from.position.x + (to.position.x - from.position.x) * t,
+                from.position.y + (to.position.y - from.position.y) * t,
+                from.position.z + (to.position.z - from.position.z) * t,
+            ),
+            zoom: from.zoom + (to.zoom - from.zoom) * t,
+            caption,
+        }
+    }
+
+    /// The city the tour is played over: its save, or the test world of
+    /// `seed`
+    pub fn create_world(&self, seed: u64) -> Result<SimWorld> {
+        match &self.save {
+            Some(path) => SaveFile::load(path)?
+                .create_world()
+                .with_context(|| format!("Failed to rebuild the city in {}", path.display())),
+            None => Ok(SimWorld::create_test_world_with_seed(seed)),
+        }
+    }
+}
@@ -4,24 +4,100 @@
 
 use anyhow::{Context, Result};
 use ordered_float::OrderedFloat;
-
+#[cfg(feature = "serialization")]
+use serde::{Deserialize, Serialize};
+
+use super::control::{STOP_SIGN_SECS, YIELD_GAP_SECS};
+use super::cornering::{CurveSpeedLimit, TurnArc};
+use super::crossing::crossing_secs;
+use super::driving_side::DrivingSide;
+use super::elevation::slope_speed_factor;
+use super::ferry::SimFerry;
+use super::fleet::wear_speed_factor;
+use super::handling::VehicleHandling;
 use super::intersection::SimIntersection;
+use super::personality::Personality;
 use super::road_network::SimRoadNetwork;
+use super::road_removal::RoadExit;
+use super::rules::{
+    turn_angle, IntersectionRules, Turn, PEDESTRIAN_CROSSING_MIN_ROADS, PEDESTRIAN_YIELD_SECS,
+    PREEMPTION_WINDOW_SECS,
+};
 use super::types::{
-    CarId, FactoryId, ApartmentId, IntersectionId, Position, RoadId, TripType, VehicleType, CAR_LENGTH,
+    CarId, FactoryId, ApartmentId, IntersectionId, Position, RoadId, RoadKind, SimMap, SimRoad, SpecialBuildingId, TripType, VehicleType, CAR_LENGTH,
     INTERSECTION_APPROACH_DISTANCE, SAFE_FOLLOWING_MULTIPLIER,
 };
 
+/// Offset of a car from the middle of a two-way road, into its own lane
+const LANE_OFFSET: f32 = 0.15;
+
+/// Where a car `progress_ratio` of the way along a road from `start` to `end`
+/// is drawn
+fn lane_position(
+    start: &Position,
+    end: &Position,
+    progress_ratio: f32,
+    is_two_way: bool,
+    side: DrivingSide,
+) -> Position {
+    let mut position = start.lerp(end, progress_ratio);
+
+    // Apply lane offset for two-way roads
+    if is_two_way {
+        let offset = side.lane_offset(start, end, LANE_OFFSET);
+        position.x += offset.x;
+        position.z += offset.z;
+    }
+
+    position
+}
+
+/// The arc from the end of road `from` onto road `to`; `None` where either is
+/// a ferry crossing, which vehicles drive straight on and off
+fn turn_arc_between(
+    road_network: &SimRoadNetwork,
+    from: &SimRoad,
+    to: &SimRoad,
+    side: DrivingSide,
+) -> Option<TurnArc> {
+    if from.kind == RoadKind::Ferry || to.kind == RoadKind::Ferry {
+        return None;
+    }
+    let start = road_network.get_intersection_position(from.start_intersection)?;
+    let corner = road_network.get_intersection_position(from.end_intersection)?;
+    let end = road_network.get_intersection_position(to.end_intersection)?;
+    // Short roads only have room for half an arc at each end
+    let reach = INTERSECTION_APPROACH_DISTANCE
+        .min(from.length / 2.0)
+        .min(to.length / 2.0);
+    if reach <= 0.0 {
+        return None;
+    }
+    let entry = lane_position(start, corner, 1.0 - reach / from.length, from.is_two_way, side);
+    let exit = lane_position(corner, end, reach / to.length, to.is_two_way, side);
+    Some(TurnArc::new(
+        from.end_intersection,
+        *corner,
+        entry,
+        from.angle,
+        exit,
+        to.angle,
+        reach,
+    ))
+}
+
 /// Result of a car update indicating what action should be taken
 #[derive(Debug, Clone)]
 pub enum CarUpdateResult {
     Continue,                             // Car continues moving
     Despawn,                              // Car should be despawned
     ArrivedAtDestination(IntersectionId), // Car arrived at destination
+    LeftRemovedRoad(IntersectionId),      // Car got off a removed road here
 }
 
 /// A car in the traffic simulation
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
 #[allow(dead_code)]
 pub struct SimCar {
     pub id: CarId,
@@ -40,6 +116,48 @@ pub struct SimCar {
     pub origin_apartment: Option<ApartmentId>,
     /// The factory this truck belongs to (for trucks)
     pub origin_factory: Option<FactoryId>,
+    /// The school or hospital this trip is to or from
+    pub origin_special: Option<SpecialBuildingId>,
+    /// Content pack vehicle type, if the owning building uses one
+    pub variant: Option<String>,
+    /// Seconds left stopped after a breakdown or collision
+    pub stalled_secs: f32,
+    /// Wear of the vehicle making this trip, from 0 (new) to 1 (worn out)
+    pub wear: f32,
+    /// Distance driven since it was last added to the vehicle's record
+    pub odometer: f32,
+    /// Seconds since this trip set off, including time spent stopped
+    pub trip_secs: f32,
+    /// Distance per second covered in the last update (0 while stopped)
+    pub current_speed: f32,
+    /// Litres of fuel burned on this trip
+    pub fuel_litres: f32,
+    /// Seconds of this trip spent standing with the engine running
+    pub idle_secs: f32,
+    /// Seconds the vehicle has been standing still since it last moved
+    pub stopped_secs: f32,
+    /// Seconds a delivery truck has to reach its shop
+    pub delivery_deadline: Option<f32>,
+    /// Whether a returning truck's delivery arrived after its deadline
+    pub delivered_late: bool,
+    /// Share of the usual revenue a returning truck's delivery sold at
+    pub delivery_price: f32,
+    /// Destination of a vehicle waiting at its start for a route there.
+    /// Until it sets off its path is empty and its road is only a
+    /// placeholder.
+    pub awaiting_route: Option<IntersectionId>,
+    /// Whether the vehicle is waiting at its start for room on its first
+    /// road. It isn't on the road until it gets in.
+    pub queued_at_start: bool,
+    /// The arc the vehicle is finishing onto its current road, if it is
+    /// still within it. It holds the intersection it turned through until
+    /// it is clear.
+    pub turn: Option<TurnArc>,
+    /// How the driver drives; their speed factor is already in `speed`
+    pub personality: Personality,
+    /// Where the vehicle is getting off the road it was on, which has been
+    /// removed. Until it gets there it is off the road network.
+    pub road_exit: Option<RoadExit>,
 }
 
 impl SimCar {
@@ -70,21 +188,95 @@ impl SimCar {
             trip_type,
             origin_apartment,
             origin_factory,
+            origin_special: None,
+            variant: None,
+            stalled_secs: 0.0,
+            wear: 0.0,
+            odometer: 0.0,
+            trip_secs: 0.0,
+            current_speed: 0.0,
+            fuel_litres: 0.0,
+            idle_secs: 0.0,
+            stopped_secs: 0.0,
+            delivery_deadline: None,
+            delivered_late: false,
+            delivery_price: 1.0,
+            awaiting_route: None,
+            queued_at_start: false,
+            turn: None,
+            personality: Personality::default(),
+            road_exit: None,
         }
     }
 
+    /// Where this trip is headed, whether or not it has set off yet
+    pub fn destination(&self) -> Option<IntersectionId> {
+        self.awaiting_route.or_else(|| self.path.last().copied())
+    }
+
+    /// Whether this truck has run past its delivery deadline
+    pub fn is_overdue(&self) -> bool {
+        self.delivery_deadline
+            .is_some_and(|deadline| self.trip_secs > deadline)
+    }
+
+    /// Whether this truck is close enough to its delivery deadline, or past
+    /// it, to preempt signals
+    pub fn is_near_deadline(&self) -> bool {
+        self.delivery_deadline
+            .is_some_and(|deadline| self.trip_secs >= deadline - PREEMPTION_WINDOW_SECS)
+    }
+
+    /// Share of the speed it could drive its road at that the vehicle covered
+    /// in its last update, from 0 (stopped) to 1 (free flow)
+    ///
+    /// The speed it could drive at is its own speed, slowed by wear and by
+    /// the road's grade, so only traffic, signals and bends bring it down.
+    /// None while it isn't driving a road: waiting to set off, or on a ferry.
+    pub fn speed_share(&self, road_network: &SimRoadNetwork) -> Option<f32> {
+        if self.awaiting_route.is_some() || self.queued_at_start {
+            return None;
+        }
+        let road = road_network.get_road(self.current_road)?;
+        if road.kind == RoadKind::Ferry {
+            return None;
+        }
+        let free_flow = self.speed * wear_speed_factor(self.wear) * slope_speed_factor(road.grade);
+        if free_flow <= 0.0 {
+            return None;
+        }
+        Some((self.current_speed / free_flow).clamp(0.0, 1.0))
+    }
+
     /// Update car movement logic
     /// Returns CarUpdateResult indicating what action should be taken with the car
+    #[allow(clippy::too_many_arguments)]
     pub fn update(
         &mut self,
         delta_secs: f32,
         road_network: &mut SimRoadNetwork,
-        intersections: &mut std::collections::HashMap<IntersectionId, SimIntersection>,
+        intersections: &mut SimMap<IntersectionId, SimIntersection>,
+        ferries: &SimMap<RoadId, SimFerry>,
+        rules: IntersectionRules,
+        curve_speed: CurveSpeedLimit,
+        handling: VehicleHandling,
+        side: DrivingSide,
     ) -> Result<CarUpdateResult> {
+        // Stay put until the route comes back and there's room to set off
+        if self.awaiting_route.is_some() || self.queued_at_start {
+            return Ok(CarUpdateResult::Continue);
+        }
+
+        // Get off a road removed from under the vehicle
+        if let Some(exit) = self.road_exit {
+            return Ok(self.drive_to_exit(exit, delta_secs));
+        }
+
         // Check if we've reached the final destination
         if self.path.is_empty() {
             return Ok(CarUpdateResult::Despawn);
         }
+        self.trip_secs += delta_secs;
 
         // Get the current road
         let current_road = road_network
@@ -95,11 +287,7 @@ impl SimCar {
         // Get the target intersection (first item in path)
         let target_intersection_id = *self.path.first().context("Path is empty")?;
 
-        // Get start and end intersection positions
-        let start_pos = *road_network
-            .get_intersection_position(self.start_intersection)
-            .context("Start intersection not found")?;
-
+        // Get the end intersection's position
         let end_pos = *road_network
             .get_intersection_position(target_intersection_id)
             .context("Target intersection not found")?;
@@ -116,14 +304,53 @@ impl SimCar {
             .flatten();
 
         // Update distance along the road
-        let mut distance_delta = self.speed * delta_secs;
+        // Worn vehicles can't keep up their speed, and nor can vehicles climbing
+        let mut distance_delta = self.speed
+            * wear_speed_factor(self.wear)
+            * slope_speed_factor(current_road.grade)
+            * delta_secs;
+
+        // Slow into a bend at the end of the road
+        let distance_to_intersection = road_length - self.distance_along_road.into_inner();
+        let next_turn_angle = self.next_turn_angle(road_network, &current_road);
+        if distance_to_intersection <= INTERSECTION_APPROACH_DISTANCE {
+            if let Some(angle) = next_turn_angle {
+                let corner_speed =
+                    curve_speed.max_speed_for_turn(angle) * handling.corner_speed_factor;
+                distance_delta = distance_delta.min(corner_speed * delta_secs);
+            }
+        }
+        // and keep to that speed until round it
+        if let Some(turn) = &self.turn {
+            let corner_speed =
+                curve_speed.max_speed_for_turn(turn.change) * handling.corner_speed_factor;
+            distance_delta = distance_delta.min(corner_speed * delta_secs);
+        }
+        // Heavy vehicles take a while to get back up to speed
+        distance_delta = distance_delta
+            .min(handling.max_speed_after(self.current_speed, delta_secs) * delta_secs);
+
+        // A broken-down or crashed vehicle stays put until cleared, and
+        // neither it nor a vehicle on a ferry has its engine running
+        let engine_running = self.stalled_secs <= 0.0 && current_road.kind != RoadKind::Ferry;
+        if self.stalled_secs > 0.0 {
+            self.stalled_secs = (self.stalled_secs - delta_secs).max(0.0);
+            distance_delta = 0.0;
+        }
+
+        // Vehicles on a ferry crossing are carried over by the ferry
+        if current_road.kind == RoadKind::Ferry {
+            distance_delta = 0.0;
+        }
 
         // Track whether we're blocked by a car ahead
         let mut blocked_by_car_ahead = false;
 
         if let Some((ahead_distance, _)) = ahead_car_option {
             let ahead_car_distance_diff = ahead_distance - self.distance_along_road;
-            let safe_following_distance = CAR_LENGTH * SAFE_FOLLOWING_MULTIPLIER;
+            let safe_following_distance = CAR_LENGTH
+                * SAFE_FOLLOWING_MULTIPLIER
+                * self.personality.profile.following_gap;
             if ahead_car_distance_diff <= OrderedFloat(distance_delta + safe_following_distance) {
                 distance_delta = 0.0;
                 blocked_by_car_ahead = true;
@@ -134,33 +361,116 @@ impl SimCar {
         // Only try to acquire intersection lock if we're not blocked by a car ahead
         // BUT if we already hold the lock, we still need to check if we can proceed
         // This prevents acquiring new locks when blocked, while maintaining existing locks
-        let distance_to_intersection = road_length - self.distance_along_road.into_inner();
-
-        if distance_to_intersection <= INTERSECTION_APPROACH_DISTANCE {
+        // A car still rounding the last intersection gets clear of it first
+        if distance_to_intersection <= INTERSECTION_APPROACH_DISTANCE && self.turn.is_none() {
             let target_intersection = intersections
                 .get_mut(&target_intersection_id)
                 .context("Failed to get intersection")?;
 
-            // Only check/acquire intersection if:
+            // Wait at a red light unless already crossing (ambulances don't
+            // wait). Otherwise only check/acquire intersection if:
             // 1. We're not blocked by a car ahead, OR
             // 2. We already hold the lock on this intersection
-            if (!blocked_by_car_ahead || target_intersection.is_held_by(self.id))
-                && !target_intersection.can_proceed(self.id)
+            let holds_lock = target_intersection.is_held_by(self.id);
+            let turn = next_turn_angle.map(Turn::of_angle);
+            // Under right turn on red, a vehicle that has stopped may turn
+            // against the light onto the kerbside (right, driving on the
+            // right)
+            let right_on_red = rules.right_turn_on_red
+                && turn == Some(side.kerbside_turn())
+                && self.current_speed == 0.0;
+            let mut red_light = !holds_lock
+                && self.vehicle_type != VehicleType::Ambulance
+                && !right_on_red
+                && !target_intersection.is_green_for(self.current_road);
+            // A late truck may switch the light to green instead
+            if red_light && rules.late_truck_preemption && self.is_near_deadline() {
+                red_light = !target_intersection.preempt_signal(self.current_road);
+            }
+            let ferry_closed = !holds_lock && !self.can_board_next(road_network, ferries);
+            // Nobody drives past a barricade
+            let road_closed = !holds_lock && self.next_road_closed(road_network);
+            // Nor into the intersection without room to get out of it
+            let no_room = !holds_lock && !self.next_road_has_room(road_network);
+            // The intersection is held while the vehicle drives across it
+            // and long vehicles swing wide to turn
+            let turn = turn.unwrap_or(Turn::Straight);
+            let mut width = road_network.intersection_width(target_intersection_id);
+            if turn.is_turn() {
+                width *= handling.turn_width_factor;
+            }
+            let mut crossing_time = crossing_secs(
+                width,
+                turn,
+                side,
+                self.vehicle_type.length(),
+                self.speed * wear_speed_factor(self.wear),
+            ) * self.personality.profile.gap_acceptance;
+            // Turning vehicles wait for pedestrians crossing at junctions
+            if rules.yield_to_pedestrians
+                && self.vehicle_type != VehicleType::Ambulance
+                && turn.is_turn()
+                && road_network.neighbour_count(target_intersection_id)
+                    >= PEDESTRIAN_CROSSING_MIN_ROADS
+            {
+                crossing_time += PEDESTRIAN_YIELD_SECS;
+            }
+            // Ambulances don't stop at stop signs
+            let stopped = self.vehicle_type == VehicleType::Ambulance
+                || self.stopped_secs >= STOP_SIGN_SECS;
+            let yield_gap =
+                YIELD_GAP_SECS * self.personality.profile.gap_acceptance * handling.gap_factor;
+            if red_light
+                || ferry_closed
+                || road_closed
+                || no_room
+                || ((!blocked_by_car_ahead || holds_lock)
+                    && !target_intersection.admit(self.id, crossing_time, stopped, yield_gap))
             {
                 distance_delta = 0.0;
             }
         }
 
         self.distance_along_road += distance_delta;
+        self.odometer += distance_delta;
+        let previous_speed = self.current_speed;
+        if delta_secs > 0.0 {
+            self.current_speed = distance_delta / delta_secs;
+        }
+        if distance_delta > 0.0 {
+            self.stopped_secs = 0.0;
+        } else {
+            self.stopped_secs += delta_secs;
+        }
+        if engine_running {
+            self.burn_fuel(distance_delta, delta_secs, previous_speed);
+            // Idling counts against the intersection the vehicle is waiting for
+            if distance_delta <= 0.0 {
+                if let Some(intersection) = intersections.get_mut(&target_intersection_id) {
+                    intersection.idling.record(self.vehicle_type, delta_secs);
+                }
+            }
+        }
 
         // Check if we've reached the end of the current road
         if self.distance_along_road >= OrderedFloat(road_length) {
+            // Turn onto the next road, if there is one
+            let arc = self.next_turn_arc(road_network, &current_road, side);
+
             // Remove the intersection we just reached from the path
             let reached_intersection = self.path.remove(0);
 
-            // Release the intersection lock
-            if let Some(intersection) = intersections.get_mut(&reached_intersection) {
-                intersection.release(self.id);
+            // Release the intersection lock, unless rounding it onto the
+            // next road
+            if let Some(turn) = self.turn.take() {
+                if let Some(intersection) = intersections.get_mut(&turn.intersection) {
+                    intersection.release(self.id);
+                }
+            }
+            if arc.is_none() {
+                if let Some(intersection) = intersections.get_mut(&reached_intersection) {
+                    intersection.release(self.id);
+                }
             }
 
             if self.path.is_empty() {
@@ -188,28 +498,23 @@ impl SimCar {
 
             self.current_road = next_road_id;
             self.distance_along_road = OrderedFloat(0.0);
+            self.turn = arc;
 
             let new_road = road_network
                 .get_road(next_road_id)
                 .context("Failed to get next road")?;
 
             self.start_intersection = new_road.start_intersection;
-            self.angle = new_road.angle;
-        } else {
-            // Interpolate position along current road
-            let progress_ratio = self.distance_along_road.into_inner() / road_length;
-            let mut position = start_pos.lerp(&end_pos, progress_ratio);
-
-            // Apply lane offset for two-way roads
-            if current_road.is_two_way {
-                const LANE_OFFSET: f32 = 0.15;
-                let offset = start_pos.perpendicular_offset(&end_pos, LANE_OFFSET);
-                position.x += offset.x;
-                position.z += offset.z;
+        } else if let Some(turn) = self.turn {
+            // Clear of the intersection once round the arc
+            if self.distance_along_road >= OrderedFloat(turn.reach) {
+                if let Some(intersection) = intersections.get_mut(&turn.intersection) {
+                    intersection.release(self.id);
+                }
+                self.turn = None;
             }
-
-            self.position = position;
         }
+        self.place(road_network, side)?;
 
         road_network.update_car_road_position(
             self.id,
@@ -222,4 +527,152 @@ impl SimCar {
 
         Ok(CarUpdateResult::Continue)
     }
+
+    /// The change in heading the car makes at the end of its current road
+    /// (see [`turn_angle`]), or `None` if its trip ends there
+    fn next_turn_angle(
+        &self,
+        road_network: &SimRoadNetwork,
+        current_road: &SimRoad,
+    ) -> Option<f32> {
+        let [landing, next, ..] = self.path[..] else {
+            return None;
+        };
+        let next_road = road_network.find_road_between(landing, next).ok()?;
+        let next_road = road_network.get_road(next_road)?;
+        Some(turn_angle(current_road.angle, next_road.angle))
+    }
+
+    /// Put the car where it is drawn: along its lane, or round the arc
+    /// through the intersection it is turning at
+    fn place(&mut self, road_network: &SimRoadNetwork, side: DrivingSide) -> Result<()> {
+        let road = road_network
+            .get_road(self.current_road)
+            .context("Road not found")?;
+        let distance = self.distance_along_road.into_inner();
+        let arc = match self.turn {
+            Some(turn) => Some((turn, distance)),
+            None => self
+                .next_turn_arc(road_network, road, side)
+                .map(|arc| (arc, distance - road.length))
+                .filter(|(arc, from_intersection)| *from_intersection > -arc.reach),
+        };
+        if let Some((arc, from_intersection)) = arc {
+            (self.position, self.angle) = arc.point_at(from_intersection);
+            return Ok(());
+        }
+
+        let start = road_network
+            .get_intersection_position(road.start_intersection)
+            .context("Start intersection not found")?;
+        let end = road_network
+            .get_intersection_position(road.end_intersection)
+            .context("End intersection not found")?;
+        if road.length > 0.0 {
+            self.position =
+                lane_position(start, end, distance / road.length, road.is_two_way, side);
+        }
+        self.angle = road.angle;
+        Ok(())
+    }
+
+    /// The arc the car will drive from its current road onto the next road
+    /// on its route, or `None` if its trip ends at the end of this road
+    fn next_turn_arc(
+        &self,
+        road_network: &SimRoadNetwork,
+        current_road: &SimRoad,
+        side: DrivingSide,
+    ) -> Option<TurnArc> {
+        let [landing, next, ..] = self.path[..] else {
+            return None;
+        };
+        let next_road = road_network.find_road_between(landing, next).ok()?;
+        let next_road = road_network.get_road(next_road)?;
+        turn_arc_between(road_network, current_road, next_road, side)
+    }
+
+    /// Whether the next road on the car's route has room past the arc onto
+    /// it, so the car won't be left blocking the intersection
+    fn next_road_has_room(&self, road_network: &SimRoadNetwork) -> bool {
+        let [landing, next, ..] = self.path[..] else {
+            return true;
+        };
+        let Ok(next_road) = road_network.find_road_between(landing, next) else {
+            return true;
+        };
+        if road_network
+            .get_road(next_road)
+            .is_none_or(|road| road.kind == RoadKind::Ferry)
+        {
+            return true;
+        }
+        road_network
+            .first_car_distance(next_road)
+            .is_none_or(|distance| {
+                distance >= INTERSECTION_APPROACH_DISTANCE + CAR_LENGTH * SAFE_FOLLOWING_MULTIPLIER
+            })
+    }
+
+    /// Whether the next road on the car's route is closed
+    fn next_road_closed(&self, road_network: &SimRoadNetwork) -> bool {
+        let [landing, next, ..] = self.path[..] else {
+            return false;
+        };
+        road_network
+            .find_road_between(landing, next)
+            .ok()
+            .and_then(|road_id| road_network.get_road(road_id))
+            .is_some_and(|road| road.closed)
+    }
+
+    /// Whether the car may drive on to the next road on its route, which it
+    /// can't while that is a ferry crossing without the ferry in at this
+    /// landing with room on board
+    fn can_board_next(
+        &self,
+        road_network: &SimRoadNetwork,
+        ferries: &SimMap<RoadId, SimFerry>,
+    ) -> bool {
+        let [landing, next, ..] = self.path[..] else {
+            return true;
+        };
+        let Ok(next_road) = road_network.find_road_between(landing, next) else {
+            return true;
+        };
+        if road_network
+            .get_road(next_road)
+            .is_none_or(|road| road.kind != RoadKind::Ferry)
+        {
+            return true;
+        }
+        ferries
+            .get(&road_network.canonical_road(next_road))
+            .is_some_and(|ferry| ferry.has_room(next_road, road_network))
+    }
+
+    /// Keep the car the same share of the way along its road after the road
+    /// was reshaped, its length changing by `scale`
+    pub fn follow_reshaped_road(
+        &mut self,
+        scale: f32,
+        road_network: &SimRoadNetwork,
+        side: DrivingSide,
+    ) {
+        self.distance_along_road = OrderedFloat(self.distance_along_road.into_inner() * scale);
+        let Some(road) = road_network.get_road(self.current_road) else {
+            return;
+        };
+        let (Some(start), Some(end)) = (
+            road_network.get_intersection_position(road.start_intersection),
+            road_network.get_intersection_position(road.end_intersection),
+        ) else {
+            return;
+        };
+        self.angle = road.angle;
+        if road.length > 0.0 {
+            let progress_ratio = self.distance_along_road.into_inner() / road.length;
+            self.position = lane_position(start, end, progress_ratio, road.is_two_way, side);
+        }
+    }
 }
@@ -5,15 +5,24 @@
 
 use anyhow::{Context, Result};
 use ordered_float::OrderedFloat;
-use std::collections::HashMap;
 
 use super::building::{SimApartment, SimFactory};
 use super::car::{CarUpdateResult, SimCar};
+use super::car_pool::CarPool;
+use super::cornering::CurveSpeedLimit;
+use super::driving_side::DrivingSide;
+use super::ferry::SimFerry;
+use super::handling::HandlingConfig;
 use super::intersection::SimIntersection;
+use super::render::{ChangeLog, VisualId};
 use super::road_network::SimRoadNetwork;
-use super::types::{ApartmentId, CarId, FactoryId, IntersectionId, TripType, VehicleType};
+use super::rules::IntersectionRules;
+use super::types::{
+    ApartmentId, CarId, FactoryId, IntersectionId, RoadId, SimMap, TripType, VehicleType,
+};
 
-/// Spawn a vehicle from a given intersection to a destination
+/// Spawn a vehicle waiting at its starting intersection for a route to its
+/// destination, which [`set_off`] starts it along
 ///
 /// # Arguments
 /// * `car_id` - The pre-generated car ID
@@ -23,12 +32,13 @@ use super::types::{ApartmentId, CarId, FactoryId, IntersectionId, TripType, Vehi
 /// * `trip_type` - The type of trip (Outbound or Return)
 /// * `origin_apartment` - The apartment this car belongs to (for cars)
 /// * `origin_factory` - The factory this truck belongs to (for trucks)
-/// * `road_network` - The road network to use for pathfinding
+/// * `road_network` - The road network it starts on
+/// * `pool` - Finished vehicles to build the new one in
 /// * `speed` - The speed of the vehicle
 ///
-/// Returns the new car if successful
+/// The vehicle isn't on any road until it sets off.
 #[allow(clippy::too_many_arguments)]
-pub fn spawn_vehicle(
+pub fn spawn_waiting_vehicle(
     car_id: CarId,
     from_intersection: IntersectionId,
     to_intersection: IntersectionId,
@@ -36,7 +46,8 @@ pub fn spawn_vehicle(
     trip_type: TripType,
     origin_apartment: Option<ApartmentId>,
     origin_factory: Option<FactoryId>,
-    road_network: &mut SimRoadNetwork,
+    road_network: &SimRoadNetwork,
+    pool: &mut CarPool,
     speed: f32,
 ) -> Result<SimCar> {
     // Find connected roads from the starting intersection
@@ -44,14 +55,51 @@ pub fn spawn_vehicle(
         .get_connected_roads(from_intersection)
         .context("Starting intersection not found in road network")?;
 
-    if connected_roads.is_empty() {
+    let Some(&(placeholder_road, _)) = connected_roads.first() else {
         anyhow::bail!("No roads connected to starting intersection");
-    }
+    };
+
+    let placeholder_angle = road_network
+        .get_road(placeholder_road)
+        .context("Road not found")?
+        .angle;
+
+    let start_pos = *road_network
+        .get_intersection_position(from_intersection)
+        .context("Start intersection position not found")?;
 
-    // Find the path
-    let path = road_network
-        .find_path(from_intersection, to_intersection)
-        .context("No path found to destination")?;
+    let mut car = pool.take(|path| {
+        SimCar::new(
+            car_id,
+            speed,
+            placeholder_road,
+            from_intersection,
+            path,
+            start_pos,
+            placeholder_angle,
+            vehicle_type,
+            trip_type,
+            origin_apartment,
+            origin_factory,
+        )
+    });
+    car.awaiting_route = Some(to_intersection);
+    Ok(car)
+}
+
+/// Start a waiting vehicle along its route
+///
+/// The vehicle is left queued at its start, off the road, until
+/// [`join_first_road`] puts it on the first road.
+pub fn set_off(
+    car: &mut SimCar,
+    path: Vec<IntersectionId>,
+    road_network: &SimRoadNetwork,
+) -> Result<()> {
+    let from_intersection = car.start_intersection;
+    let to_intersection = car
+        .awaiting_route
+        .context("Vehicle has already set off")?;
 
     if path.is_empty() && from_intersection != to_intersection {
         anyhow::bail!("Empty path but different start/end");
@@ -67,37 +115,30 @@ pub fn spawn_vehicle(
         .get_road(road_id)
         .context("Road not found")?;
 
-    let road_angle = road.angle;
-
-    let start_pos = *road_network
-        .get_intersection_position(from_intersection)
-        .context("Start intersection position not found")?;
+    car.current_road = road_id;
+    car.angle = road.angle;
+    car.path = path;
+    car.awaiting_route = None;
+    car.queued_at_start = true;
+    Ok(())
+}
 
-    let car = SimCar::new(
-        car_id,
-        speed,
-        road_id,
-        from_intersection,
-        path,
-        start_pos,
-        road_angle,
-        vehicle_type,
-        trip_type,
-        origin_apartment,
-        origin_factory,
-    );
+/// Put a vehicle queued at its start onto the beginning of its first road
+pub fn join_first_road(car: &mut SimCar, road_network: &mut SimRoadNetwork) -> Result<()> {
+    road_network
+        .get_road(car.current_road)
+        .context("First road no longer exists")?;
+    car.queued_at_start = false;
 
     // Register car on road
     road_network.update_car_road_position(
-        car_id,
-        road_id,
+        car.id,
+        car.current_road,
         OrderedFloat(0.0),
         false,
         None,
         OrderedFloat(0.0),
-    )?;
-
-    Ok(car)
+    )
 }
 
 /// Despawn a car and clean up references
@@ -105,25 +146,25 @@ pub fn spawn_vehicle(
 /// # Arguments
 /// * `car_id` - The ID of the car to despawn
 /// * `cars` - The cars collection
+/// * `pool` - Where the car is kept for reuse
 /// * `road_network` - The road network for tracking cleanup
 /// * `apartments` - The apartments collection for reference cleanup
 /// * `factories` - The factories collection for reference cleanup
 pub fn despawn_car(
     car_id: CarId,
-    cars: &mut HashMap<CarId, SimCar>,
+    cars: &mut SimMap<CarId, SimCar>,
+    pool: &mut CarPool,
     road_network: &mut SimRoadNetwork,
-    apartments: &mut HashMap<ApartmentId, SimApartment>,
-    factories: &mut HashMap<FactoryId, SimFactory>,
+    apartments: &mut SimMap<ApartmentId, SimApartment>,
+    factories: &mut SimMap<FactoryId, SimFactory>,
 ) {
-    // Get car info before removing
-    let car_info = cars
-        .get(&car_id)
-        .map(|c| (c.origin_apartment, c.origin_factory));
-
-    cars.remove(&car_id);
+    let car = cars.remove(&car_id);
     road_network.remove_car_from_tracking(car_id);
 
-    if let Some((origin_apartment, origin_factory)) = car_info {
+    if let Some(car) = car {
+        let (origin_apartment, origin_factory) = (car.origin_apartment, car.origin_factory);
+        pool.release(car);
+
         // Clear apartment car reference
         if let Some(apartment_id) = origin_apartment {
             if let Some(apartment) = apartments.get_mut(&apartment_id) {
@@ -149,42 +190,53 @@ pub fn despawn_car(
 
 /// Update all cars in the simulation
 ///
+/// Cars that moved, turned, started or stopped being stalled, or went past
+/// their delivery deadline are recorded in `changes`.
+///
 /// Returns a list of (car_id, result) tuples for cars that need special handling
+#[allow(clippy::too_many_arguments)]
 pub fn update_cars(
     delta_secs: f32,
-    cars: &mut HashMap<CarId, SimCar>,
+    cars: &mut SimMap<CarId, SimCar>,
     road_network: &mut SimRoadNetwork,
-    intersections: &mut HashMap<IntersectionId, SimIntersection>,
+    intersections: &mut SimMap<IntersectionId, SimIntersection>,
+    ferries: &SimMap<RoadId, SimFerry>,
+    rules: IntersectionRules,
+    curve_speed: CurveSpeedLimit,
+    handling: &HandlingConfig,
+    side: DrivingSide,
+    changes: &mut ChangeLog,
 ) -> Vec<(CarId, CarUpdateResult)> {
     let mut results = Vec::new();
 
-    // Collect car IDs to avoid borrow issues
-    let car_ids: Vec<CarId> = cars.keys().copied().collect();
-
-    for car_id in car_ids {
-        // Get car mutably, update it, then process result
-        if let Some(mut car) = cars.remove(&car_id) {
-            let result = car.update(delta_secs, road_network, intersections);
+    // Each car is updated where it is, without taking it out of the map
+    for (&car_id, car) in cars.iter_mut() {
+        let before = (car.position, car.angle, car.stalled_secs > 0.0, car.is_overdue());
+        let result = car.update(
+            delta_secs,
+            road_network,
+            intersections,
+            ferries,
+            rules,
+            curve_speed,
+            handling.get(car.vehicle_type),
+            side,
+        );
+        if (car.position, car.angle, car.stalled_secs > 0.0, car.is_overdue()) != before {
+            changes.mark(VisualId::Vehicle(car_id));
+        }
 
-            match result {
-                Ok(CarUpdateResult::Continue) => {
-                    cars.insert(car_id, car);
-                }
-                Ok(CarUpdateResult::Despawn) => {
-                    // Put car back temporarily so tick() can read its info
-                    cars.insert(car_id, car);
-                    results.push((car_id, CarUpdateResult::Despawn));
-                }
-                Ok(CarUpdateResult::ArrivedAtDestination(dest)) => {
-                    // Put car back temporarily so tick() can read its info
-                    cars.insert(car_id, car);
-                    results.push((car_id, CarUpdateResult::ArrivedAtDestination(dest)));
-                }
-                Err(_) => {
-                    // Put car back temporarily so tick() can read its info
-                    cars.insert(car_id, car);
-                    results.push((car_id, CarUpdateResult::Despawn));
-                }
+        // Cars that finished stay in the map so tick() can read their info
+        match result {
+            Ok(CarUpdateResult::Continue) => {}
+            Ok(CarUpdateResult::Despawn) | Err(_) => {
+                results.push((car_id, CarUpdateResult::Despawn));
+            }
+            Ok(CarUpdateResult::ArrivedAtDestination(dest)) => {
+                results.push((car_id, CarUpdateResult::ArrivedAtDestination(dest)));
+            }
+            Ok(CarUpdateResult::LeftRemovedRoad(at)) => {
+                results.push((car_id, CarUpdateResult::LeftRemovedRoad(at)));
             }
         }
     }
@@ -194,18 +246,27 @@ pub fn update_cars(
 
 /// Recalculate paths for all cars that might have invalid paths
 ///
-/// This is called when roads are removed and cars need to find new routes
+/// This is called when roads are removed and cars need to find new routes.
+/// A car with no other way round a closed road keeps its route and waits for
+/// the road to reopen.
+///
+/// Returns the cars left with no way to their destination, for the caller
+/// to despawn.
 pub fn recalculate_car_paths(
-    cars: &mut HashMap<CarId, SimCar>,
-    road_network: &mut SimRoadNetwork,
-    apartments: &mut HashMap<ApartmentId, SimApartment>,
-    factories: &mut HashMap<FactoryId, SimFactory>,
-) {
+    cars: &mut SimMap<CarId, SimCar>,
+    road_network: &SimRoadNetwork,
+) -> Vec<CarId> {
     let car_ids: Vec<CarId> = cars.keys().copied().collect();
     let mut cars_to_despawn = Vec::new();
 
     for car_id in car_ids {
         if let Some(car) = cars.get(&car_id) {
+            // Routed afresh once off the removed road it is on
+            if car.road_exit.is_some() {
+                continue;
+            }
+            let vehicle_type = car.vehicle_type;
+
             // Get the car's final destination
             let destination = match car.path.last() {
                 Some(dest) => *dest,
@@ -219,7 +280,7 @@ pub fn recalculate_car_paths(
             };
 
             // Try to find a new path from current target to destination
-            let new_path = road_network.find_path(current_target, destination);
+            let new_path = road_network.find_path_for(current_target, destination, vehicle_type);
 
             match new_path {
                 Some(path) => {
@@ -228,6 +289,11 @@ pub fn recalculate_car_paths(
                         car.path = std::iter::once(current_target).chain(path).collect();
                     }
                 }
+                None if road_network.route_is_usable_when_open(
+                    current_target,
+                    &car.path[1..],
+                    vehicle_type,
+                ) => {}
                 None => {
                     // No valid path exists - mark for despawn
                     cars_to_despawn.push(car_id);
@@ -236,8 +302,5 @@ pub fn recalculate_car_paths(
         }
     }
 
-    // Despawn cars that can't find a path
-    for car_id in cars_to_despawn {
-        despawn_car(car_id, cars, road_network, apartments, factories);
-    }
+    cars_to_despawn
 }
@@ -0,0 +1,106 @@
+//! Recycling vehicles that finish their trips
+//!
+//! At rush hour thousands of vehicles set off and arrive every few minutes.
+//! Rather than dropping each vehicle that arrives and building the next one
+//! from nothing, the world keeps finished [`SimCar`]s in a [`CarPool`], up
+//! to its capacity, and builds each vehicle that sets off in one of them,
+//! keeping its route buffer. Every field of a recycled vehicle is set
+//! afresh, so runs with and without a pool match each other exactly.
+
+#[cfg(feature = "serialization")]
+use serde::{Deserialize, Serialize};
+
+use super::car::SimCar;
+use super::types::IntersectionId;
+use super::world::SimWorld;
+
+/// Most finished vehicles a world keeps for reuse by default
+pub const CAR_POOL_CAPACITY: usize = 512;
+
+/// Finished vehicles kept for the next ones to set off
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+pub struct CarPool {
+    #[cfg_attr(feature = "serialization", serde(skip))]
+    idle: Vec<SimCar>,
+    capacity: usize,
+    /// Vehicles set off in a recycled one
+    reused: u64,
+    /// Vehicles set off in a new one
+    built: u64,
+}
+
+impl Default for CarPool {
+    fn default() -> Self {
+        Self::with_capacity(CAR_POOL_CAPACITY)
+    }
+}
+
+impl CarPool {
+    /// A pool keeping up to `capacity` finished vehicles; 0 turns it off
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            idle: Vec::new(),
+            capacity,
+            reused: 0,
+            built: 0,
+        }
+    }
+
+    /// Keep a finished vehicle for reuse, if there's room
+    pub(super) fn release(&mut self, car: SimCar) {
+        if self.idle.len() < self.capacity {
+            self.idle.push(car);
+        }
+    }
+
+    /// A vehicle built by `build` around an empty route buffer, in a
+    /// recycled one if any are idle
+    pub(super) fn take(&mut self, build: impl FnOnce(Vec<IntersectionId>) -> SimCar) -> SimCar {
+        match self.idle.pop() {
+            Some(car) => {
+                self.reused += 1;
+                let mut path = car.path;
+                path.clear();
+                build(path)
+            }
+            None => {
+                self.built += 1;
+                build(Vec::new())
+            }
+        }
+    }
+
+    /// Finished vehicles waiting for reuse
+    pub fn idle(&self) -> &[SimCar] {
+        &self.idle
+    }
+
+    /// Most finished vehicles kept
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Vehicles that set off in a recycled one
+    pub fn reused(&self) -> u64 {
+        self.reused
+    }
+
+    /// Vehicles that set off in a new one
+    pub fn built(&self) -> u64 {
+        self.built
+    }
+}
+
+impl SimWorld {
+    /// Keep up to `capacity` finished vehicles for reuse; 0 turns recycling
+    /// off
+    pub fn set_car_pool_capacity(&mut self, capacity: usize) {
+        self.car_pool = CarPool::with_capacity(capacity);
+    }
+
+    /// The finished vehicles kept for reuse and how often they were reused
+    pub fn car_pool(&self) -> &CarPool {
+        &self.car_pool
+    }
+}
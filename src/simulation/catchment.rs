@@ -0,0 +1,205 @@
+//! Shop catchments
+//!
+//! Every delivery a shop receives was made at a factory from the shifts of
+//! workers from particular houses. The shop credits the delivery and the
+//! revenue it earned to the factory that made it, and to the houses whose
+//! workers put in its shifts, in proportion to the shifts each worked. A
+//! shop's catchment is the factories and houses it draws on this way, and a
+//! house no shop draws on is cut off from the shops' custom, so a
+//! neighborhood of them is underserved. Shoppers don't visit shops yet; when
+//! they do, their purchases belong here too.
+
+use std::collections::VecDeque;
+
+use super::diagnostics::map_bytes;
+use super::types::{ApartmentId, FactoryId, IntersectionId, ShopId, SimMap};
+use super::world::SimWorld;
+
+/// What a shop drew from one factory or house
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Attribution {
+    /// Deliveries, shared between houses by the shifts they worked on each
+    pub deliveries: f32,
+    /// Revenue of those deliveries, shared the same way
+    pub revenue: f32,
+}
+
+impl Attribution {
+    fn add(&mut self, share: f32, revenue: i32) {
+        self.deliveries += share;
+        self.revenue += share * revenue as f32;
+    }
+}
+
+/// Where a shop's deliveries came from
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShopCatchment {
+    pub shop_id: ShopId,
+    pub deliveries: u32,
+    /// Revenue the deliveries earned
+    pub revenue: i32,
+    /// In factory id order
+    pub factories: Vec<(FactoryId, Attribution)>,
+    /// In house id order
+    pub apartments: Vec<(ApartmentId, Attribution)>,
+}
+
+impl ShopCatchment {
+    /// The house credited with the most deliveries, if any
+    pub fn main_apartment(&self) -> Option<ApartmentId> {
+        self.apartments
+            .iter()
+            .max_by(|a, b| a.1.deliveries.total_cmp(&b.1.deliveries))
+            .map(|(id, _)| *id)
+    }
+}
+
+/// What each shop drew from where
+#[derive(Debug, Clone, Default)]
+struct ShopTotals {
+    deliveries: u32,
+    revenue: i32,
+    factories: SimMap<FactoryId, Attribution>,
+    apartments: SimMap<ApartmentId, Attribution>,
+}
+
+/// Which houses worked on each delivery, from its first shift until a shop
+/// receives it
+#[derive(Debug, Clone, Default)]
+pub(super) struct CatchmentStats {
+    /// Houses of the workers whose shifts count towards each factory's next
+    /// delivery
+    in_progress: SimMap<FactoryId, Vec<ApartmentId>>,
+    /// Houses behind each factory's finished deliveries, oldest first
+    ready: SimMap<FactoryId, VecDeque<Vec<ApartmentId>>>,
+    /// Houses behind the delivery each factory's truck is carrying
+    on_truck: SimMap<FactoryId, Vec<ApartmentId>>,
+    shops: SimMap<ShopId, ShopTotals>,
+}
+
+impl CatchmentStats {
+    /// Note the shifts workers from `apartments` finished at a factory,
+    /// counting only those its next delivery still needed like the factory
+    /// does, and whether that delivery was finished
+    pub(super) fn record_shifts(
+        &mut self,
+        factory_id: FactoryId,
+        apartments: &[ApartmentId],
+        shifts_per_delivery: u32,
+        finished: bool,
+    ) {
+        let next = self.in_progress.entry(factory_id).or_default();
+        let needed = (shifts_per_delivery as usize).saturating_sub(next.len());
+        next.extend(apartments.iter().take(needed));
+        if finished {
+            let delivery = std::mem::take(next);
+            self.ready.entry(factory_id).or_default().push_back(delivery);
+        }
+    }
+
+    /// Note a factory's truck taking its oldest finished delivery
+    pub(super) fn load_truck(&mut self, factory_id: FactoryId) {
+        let delivery = self
+            .ready
+            .get_mut(&factory_id)
+            .and_then(VecDeque::pop_front)
+            .unwrap_or_default();
+        self.on_truck.insert(factory_id, delivery);
+    }
+
+    /// Entries across its collections
+    pub(super) fn len(&self) -> usize {
+        self.in_progress.len() + self.ready.len() + self.on_truck.len() + self.shops.len()
+    }
+
+    /// Approximate bytes on the heap
+    pub(super) fn memory_bytes(&self) -> usize {
+        let shops: usize = self
+            .shops
+            .values()
+            .map(|shop| map_bytes(&shop.factories) + map_bytes(&shop.apartments))
+            .sum();
+        map_bytes(&self.in_progress)
+            + map_bytes(&self.ready)
+            + map_bytes(&self.on_truck)
+            + map_bytes(&self.shops)
+            + shops
+    }
+}
+
+impl SimWorld {
+    /// Credit a delivery a factory's truck brought to the shop at
+    /// `shop_intersection` to the factory and the houses that worked on it
+    pub(super) fn record_shop_delivery(
+        &mut self,
+        shop_intersection: IntersectionId,
+        factory_id: FactoryId,
+        late: bool,
+        price: f32,
+    ) {
+        let apartments = self
+            .catchment_stats
+            .on_truck
+            .remove(&factory_id)
+            .unwrap_or_default();
+        let Some(shop_id) = self
+            .shops
+            .values()
+            .find(|s| s.intersection_id == shop_intersection)
+            .map(|s| s.id)
+        else {
+            return;
+        };
+        let revenue = self
+            .game_state
+            .as_ref()
+            .map_or(0, |game_state| game_state.priced_shop_delivery_revenue(late, price));
+        let shop = self.catchment_stats.shops.entry(shop_id).or_default();
+        shop.deliveries += 1;
+        shop.revenue += revenue;
+        shop.factories.entry(factory_id).or_default().add(1.0, revenue);
+        let share = 1.0 / apartments.len().max(1) as f32;
+        for apartment_id in apartments {
+            shop.apartments
+                .entry(apartment_id)
+                .or_default()
+                .add(share, revenue);
+        }
+    }
+
+    /// Where each shop's deliveries came from, in shop id order
+    ///
+    /// Shops without any deliveries yet have an empty catchment. Demolished
+    /// factories and houses stay in the catchments they contributed to.
+    pub fn shop_catchments(&self) -> Vec<ShopCatchment> {
+        self.shops
+            .keys()
+            .map(|&shop_id| {
+                let totals = self.catchment_stats.shops.get(&shop_id);
+                let totals = totals.cloned().unwrap_or_default();
+                ShopCatchment {
+                    shop_id,
+                    deliveries: totals.deliveries,
+                    revenue: totals.revenue,
+                    factories: totals.factories.into_iter().collect(),
+                    apartments: totals.apartments.into_iter().collect(),
+                }
+            })
+            .collect()
+    }
+
+    /// Houses no shop has had a delivery from, in house id order
+    pub fn underserved_apartments(&self) -> Vec<ApartmentId> {
+        self.apartments
+            .keys()
+            .filter(|id| {
+                !self
+                    .catchment_stats
+                    .shops
+                    .values()
+                    .any(|shop| shop.apartments.contains_key(id))
+            })
+            .copied()
+            .collect()
+    }
+}
@@ -0,0 +1,396 @@
+//! Build commands
+//!
+//! Every change a player makes to the world goes through a [`BuildCommand`].
+//! Commands describe the player's intent (click positions, building type)
+//! rather than the IDs of what they create, so the same command applied to
+//! identical worlds has identical results. That lets commands be queued, sent over the network
+//! and replayed.
+
+use anyhow::{Context, Result};
+use log::{info, warn};
+#[cfg(feature = "serialization")]
+use serde::{Deserialize, Serialize};
+
+use super::content::BuildingKind;
+use super::contraflow::ContraflowWindow;
+use super::control::IntersectionControl;
+use super::cul_de_sac::{cul_de_sac_lots, CUL_DE_SAC_MAX_LOTS};
+use super::decoration::DecorationKind;
+use super::junction::{junction_layout, JunctionKind};
+use super::signal::SignalPhase;
+use super::special::SpecialKind;
+use super::types::{BusRouteId, IntersectionId, Position, RoadId, RoadKind, RoadRestriction};
+use super::upgrades::UpgradeKind;
+use super::world::SimWorld;
+
+/// A single player action that changes the world
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+#[cfg_attr(
+    feature = "serialization",
+    serde(tag = "type", rename_all = "snake_case")
+)]
+pub enum BuildCommand {
+    /// Build a two-way road between two points, snapping each end to a nearby
+    /// intersection or road
+    Road {
+        start: Position,
+        end: Position,
+        snap_distance: f32,
+    },
+    /// Build a bridge between two points, snapping like [`Self::Road`]
+    Bridge {
+        start: Position,
+        end: Position,
+        snap_distance: f32,
+    },
+    /// Build a ferry crossing over water between two points, snapping like
+    /// [`Self::Road`]
+    Ferry {
+        start: Position,
+        end: Position,
+        snap_distance: f32,
+    },
+    /// Place a building at a point, snapping to a nearby intersection or
+    /// joining the network by a driveway (see [`SimWorld::building_site`])
+    Building {
+        kind: BuildingKind,
+        /// Content pack building type; `None` for the built-in one
+        variant: Option<String>,
+        position: Position,
+        snap_distance: f32,
+    },
+    /// Build a dead-end street with apartments along it; `start` snaps like a
+    /// road end
+    CulDeSac {
+        start: Position,
+        end: Position,
+        lots: usize,
+        snap_distance: f32,
+    },
+    /// Lay out a junction preset centred on a point, its main road running
+    /// towards `toward`; the ends of its arms snap like road ends
+    Junction {
+        kind: JunctionKind,
+        center: Position,
+        toward: Position,
+        snap_distance: f32,
+    },
+    /// Place a school, hospital or depot at a point, snapping like [`Self::Building`]
+    Special {
+        kind: SpecialKind,
+        position: Position,
+        snap_distance: f32,
+    },
+    /// Plant a tree or lay out a park at a point clear of roads
+    Decoration {
+        kind: DecorationKind,
+        position: Position,
+    },
+    /// Add, retime or (with no phases) remove an intersection's traffic signal
+    Signal {
+        intersection: IntersectionId,
+        phases: Option<Vec<SignalPhase>>,
+    },
+    /// Change how an intersection is controlled: uncontrolled, stop sign,
+    /// yield or traffic light
+    SetControl {
+        intersection: IntersectionId,
+        control: IntersectionControl,
+    },
+    /// Limit which vehicles may use a road (both directions if two-way)
+    RestrictRoad {
+        road: RoadId,
+        restriction: RoadRestriction,
+    },
+    /// Close a road to every vehicle for now, or reopen it (both directions if
+    /// two-way)
+    CloseRoad { road: RoadId, closed: bool },
+    /// Put a traffic sensor on a road or take it off (both directions if two-way)
+    SetSensor { road: RoadId, installed: bool },
+    /// Make a road two-way, or one-way in its own direction
+    SetTwoWay { road: RoadId, two_way: bool },
+    /// Turn a one-way road round
+    ReverseRoad { road: RoadId },
+    /// Have a one-way road run the other way during set hours of the day,
+    /// or (with no windows) cancel that
+    ScheduleContraflow {
+        road: RoadId,
+        windows: Vec<ContraflowWindow>,
+    },
+    /// Move an intersection, reshaping the roads that meet there
+    MoveIntersection {
+        intersection: IntersectionId,
+        position: Position,
+    },
+    /// Take out or cancel the insurance policy covering incident cleanup
+    SetInsurance { insured: bool },
+    /// Replace every worn apartment car and factory truck
+    ReplaceWornVehicles,
+    /// Buy the next level of a capacity upgrade
+    BuyUpgrade { upgrade: UpgradeKind },
+    /// Remove the intersections left with no roads and no building
+    CleanupOrphans,
+    /// Open a bus route calling at intersections in order, looping back to
+    /// the first
+    BusRoute { stops: Vec<IntersectionId> },
+    /// Take a bus route out of service
+    RemoveBusRoute { route: BusRouteId },
+}
+
+impl BuildCommand {
+    /// Short description for logs
+    pub fn describe(&self) -> String {
+        match self {
+            BuildCommand::Road { .. } => "road".to_string(),
+            BuildCommand::Bridge { .. } => "bridge".to_string(),
+            BuildCommand::Ferry { .. } => "ferry crossing".to_string(),
+            BuildCommand::Building {
+                variant: Some(variant),
+                ..
+            } => variant.clone(),
+            BuildCommand::Building { kind, .. } => format!("{:?}", kind).to_lowercase(),
+            BuildCommand::CulDeSac { .. } => "cul-de-sac".to_string(),
+            BuildCommand::Junction { kind, .. } => kind.label().to_string(),
+            BuildCommand::Special { kind, .. } => format!("{:?}", kind).to_lowercase(),
+            BuildCommand::Decoration { kind, .. } => kind.label().to_string(),
+            BuildCommand::Signal { .. } => "traffic signal".to_string(),
+            BuildCommand::SetControl { control, .. } => control.label().to_lowercase(),
+            BuildCommand::RestrictRoad { .. } => "road restriction".to_string(),
+            BuildCommand::CloseRoad { closed: true, .. } => "road closure".to_string(),
+            BuildCommand::CloseRoad { closed: false, .. } => "road reopening".to_string(),
+            BuildCommand::SetSensor { installed: true, .. } => "traffic sensor".to_string(),
+            BuildCommand::SetSensor { installed: false, .. } => "sensor removal".to_string(),
+            BuildCommand::SetTwoWay { two_way: true, .. } => "two-way road".to_string(),
+            BuildCommand::SetTwoWay { two_way: false, .. } => "one-way road".to_string(),
+            BuildCommand::ReverseRoad { .. } => "reversed road".to_string(),
+            BuildCommand::ScheduleContraflow { windows, .. } if windows.is_empty() => {
+                "contraflow cancellation".to_string()
+            }
+            BuildCommand::ScheduleContraflow { .. } => "contraflow schedule".to_string(),
+            BuildCommand::MoveIntersection { .. } => "intersection move".to_string(),
+            BuildCommand::SetInsurance { insured: true } => "insurance policy".to_string(),
+            BuildCommand::SetInsurance { insured: false } => "insurance cancellation".to_string(),
+            BuildCommand::ReplaceWornVehicles => "worn vehicle replacement".to_string(),
+            BuildCommand::BuyUpgrade { upgrade } => format!("{} upgrade", upgrade.label()),
+            BuildCommand::CleanupOrphans => "orphan intersection cleanup".to_string(),
+            BuildCommand::BusRoute { .. } => "bus route".to_string(),
+            BuildCommand::RemoveBusRoute { .. } => "bus route closure".to_string(),
+        }
+    }
+
+    /// Points on the map the command builds at, which must be inside the
+    /// world's bounds
+    pub fn positions(&self) -> Vec<Position> {
+        match self {
+            BuildCommand::Road { start, end, .. }
+            | BuildCommand::Bridge { start, end, .. }
+            | BuildCommand::Ferry { start, end, .. } => vec![*start, *end],
+            BuildCommand::CulDeSac {
+                start, end, lots, ..
+            } => {
+                // Past the most lots a street takes, it is refused anyway
+                let lots = cul_de_sac_lots(*start, *end, (*lots).min(CUL_DE_SAC_MAX_LOTS));
+                [*start, *end]
+                    .into_iter()
+                    .chain(lots.into_iter().flat_map(|(driveway, lot)| [driveway, lot]))
+                    .collect()
+            }
+            BuildCommand::Building { position, .. }
+            | BuildCommand::Special { position, .. }
+            | BuildCommand::Decoration { position, .. }
+            | BuildCommand::MoveIntersection { position, .. } => vec![*position],
+            BuildCommand::Junction {
+                kind,
+                center,
+                toward,
+                ..
+            } => junction_layout(*kind, *center, *toward).points,
+            BuildCommand::Signal { .. }
+            | BuildCommand::SetControl { .. }
+            | BuildCommand::RestrictRoad { .. }
+            | BuildCommand::CloseRoad { .. }
+            | BuildCommand::SetSensor { .. }
+            | BuildCommand::SetTwoWay { .. }
+            | BuildCommand::ReverseRoad { .. }
+            | BuildCommand::ScheduleContraflow { .. }
+            | BuildCommand::SetInsurance { .. }
+            | BuildCommand::ReplaceWornVehicles
+            | BuildCommand::BuyUpgrade { .. }
+            | BuildCommand::CleanupOrphans
+            | BuildCommand::BusRoute { .. }
+            | BuildCommand::RemoveBusRoute { .. } => Vec::new(),
+        }
+    }
+}
+
+impl SimWorld {
+    /// Apply a build command, charging the game's economy if there is one
+    ///
+    /// Returns `Ok(false)` if there were insufficient funds, and fails for a
+    /// command building outside the world's bounds.
+    pub fn apply_command(&mut self, command: &BuildCommand) -> Result<bool> {
+        for position in command.positions() {
+            self.ensure_in_bounds(&position)?;
+        }
+        match command {
+            BuildCommand::Road {
+                start,
+                end,
+                snap_distance,
+            } => Ok(self
+                .try_add_road_at_positions(*start, *end, *snap_distance)?
+                .is_some()),
+            BuildCommand::Bridge {
+                start,
+                end,
+                snap_distance,
+            } => Ok(self
+                .try_add_crossing_at_positions(*start, *end, *snap_distance, RoadKind::Bridge)?
+                .is_some()),
+            BuildCommand::Ferry {
+                start,
+                end,
+                snap_distance,
+            } => Ok(self
+                .try_add_crossing_at_positions(*start, *end, *snap_distance, RoadKind::Ferry)?
+                .is_some()),
+            BuildCommand::Building {
+                kind,
+                variant,
+                position,
+                snap_distance,
+            } => {
+                self.place_building(*position, *snap_distance, |world, site| {
+                    if let Some(variant) = variant {
+                        return Ok(world.try_add_building_from_def(variant, site)?.is_some());
+                    }
+                    Ok(match kind {
+                        BuildingKind::Apartment => world.try_add_apartment(site).is_some(),
+                        BuildingKind::Factory => world.try_add_factory(site).is_some(),
+                        BuildingKind::Shop => world.try_add_shop(site).is_some(),
+                    })
+                })
+            }
+            BuildCommand::CulDeSac {
+                start,
+                end,
+                lots,
+                snap_distance,
+            } => Ok(self
+                .try_add_cul_de_sac(*start, *end, *lots, *snap_distance)?
+                .is_some()),
+            BuildCommand::Junction {
+                kind,
+                center,
+                toward,
+                snap_distance,
+            } => Ok(self
+                .try_add_junction(*kind, *center, *toward, *snap_distance)?
+                .is_some()),
+            BuildCommand::Special {
+                kind,
+                position,
+                snap_distance,
+            } => {
+                self.place_building(*position, *snap_distance, |world, site| {
+                    Ok(world.try_add_special_building(site, *kind).is_some())
+                })
+            }
+            BuildCommand::Decoration { kind, position } => {
+                Ok(self.try_add_decoration(*kind, *position)?.is_some())
+            }
+            BuildCommand::Signal {
+                intersection,
+                phases,
+            } => {
+                self.set_signal(*intersection, phases.clone())?;
+                Ok(true)
+            }
+            BuildCommand::SetControl {
+                intersection,
+                control,
+            } => {
+                self.set_intersection_control(*intersection, *control)?;
+                Ok(true)
+            }
+            BuildCommand::RestrictRoad { road, restriction } => {
+                self.set_road_restriction(*road, *restriction)?;
+                Ok(true)
+            }
+            BuildCommand::CloseRoad { road, closed } => {
+                self.set_road_closed(*road, *closed)?;
+                Ok(true)
+            }
+            BuildCommand::SetSensor { road, installed } => {
+                self.try_set_road_sensor(*road, *installed)
+            }
+            BuildCommand::SetTwoWay { road, two_way } => {
+                self.set_road_two_way(*road, *two_way)?;
+                Ok(true)
+            }
+            BuildCommand::ReverseRoad { road } => {
+                self.reverse_one_way_road(*road)?;
+                Ok(true)
+            }
+            BuildCommand::ScheduleContraflow { road, windows } => {
+                self.schedule_contraflow(*road, windows.clone())?;
+                Ok(true)
+            }
+            BuildCommand::MoveIntersection {
+                intersection,
+                position,
+            } => {
+                self.move_intersection(*intersection, *position)?;
+                Ok(true)
+            }
+            BuildCommand::SetInsurance { insured } => {
+                let game_state = self
+                    .game_state
+                    .as_mut()
+                    .context("Insurance needs a game in progress")?;
+                Ok(game_state.set_insured(*insured))
+            }
+            BuildCommand::ReplaceWornVehicles => Ok(self.try_replace_worn_vehicles().is_some()),
+            BuildCommand::BuyUpgrade { upgrade } => self.try_buy_upgrade(*upgrade),
+            BuildCommand::CleanupOrphans => {
+                self.cleanup_orphans()?;
+                Ok(true)
+            }
+            BuildCommand::BusRoute { stops } => {
+                Ok(self.try_add_bus_route(stops.clone())?.is_some())
+            }
+            BuildCommand::RemoveBusRoute { route } => {
+                self.remove_bus_route(*route)?;
+                Ok(true)
+            }
+        }
+    }
+
+    /// Apply commands in order, logging any that fail
+    ///
+    /// A failed command (e.g. for lack of funds) doesn't stop the rest.
+    pub fn apply_commands(&mut self, commands: &[BuildCommand]) {
+        for command in commands {
+            self.apply_command_logged(command);
+        }
+    }
+
+    /// Apply a command, logging the result; returns whether it was applied
+    pub fn apply_command_logged(&mut self, command: &BuildCommand) -> bool {
+        match self.apply_command(command) {
+            Ok(true) => {
+                info!("Built {}", command.describe());
+                true
+            }
+            Ok(false) => {
+                warn!("Insufficient funds to build {}", command.describe());
+                false
+            }
+            Err(e) => {
+                warn!("Failed to build {}: {:#}", command.describe(), e);
+                false
+            }
+        }
+    }
+}
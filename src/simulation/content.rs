@@ -0,0 +1,282 @@
+//! Data-driven content packs for extra building and vehicle types
+//!
+//! A content pack is a TOML file listing building and vehicle definitions.
+//! Each building definition is a variant of one of the built-in behaviours
+//! (apartment, factory or shop) with its own cost, throughput and look, and
+//! may name a vehicle definition for the cars or trucks it sends out.
+//!
+//! ```toml
+//! [[vehicles]]
+//! name = "van"
+//! base = "truck"
+//! min_speed = 3.0
+//! max_speed = 5.0
+//! color = [0.9, 0.9, 0.9]
+//!
+//! [[buildings]]
+//! name = "warehouse"
+//! kind = "factory"
+//! cost = 800
+//! work_time = 3.0
+//! max_deliveries = 4
+//! shifts_per_delivery = 2
+//! vehicle = "van"
+//! ```
+
+use anyhow::{bail, ensure, Context, Result};
+#[cfg(feature = "serialization")]
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "serialization")]
+use std::path::Path;
+
+use super::types::{ApartmentId, FactoryId, ShopId, VehicleType};
+
+/// Built-in behaviour a building definition is based on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serialization", serde(rename_all = "lowercase"))]
+pub enum BuildingKind {
+    Apartment,
+    Factory,
+    Shop,
+}
+
+impl BuildingKind {
+    /// Vehicle type this kind of building sends out, if any
+    pub fn vehicle_type(&self) -> Option<VehicleType> {
+        match self {
+            BuildingKind::Apartment => Some(VehicleType::Car),
+            BuildingKind::Factory => Some(VehicleType::Truck),
+            BuildingKind::Shop => None,
+        }
+    }
+}
+
+/// A building type loaded from a content pack
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serialization", serde(deny_unknown_fields))]
+pub struct BuildingDef {
+    /// Unique name used to refer to this building type
+    pub name: String,
+    /// Built-in behaviour this building uses
+    pub kind: BuildingKind,
+    /// Build cost; defaults to the economy's cost for the kind
+    pub cost: Option<i32>,
+    /// Number of cars an apartment owns
+    pub cars: Option<usize>,
+    /// Seconds a worker spends at a factory
+    pub work_time: Option<f32>,
+    /// Deliveries a factory can stockpile
+    pub max_deliveries: Option<u32>,
+    /// Worker shifts a factory needs to finish one delivery
+    pub shifts_per_delivery: Option<u32>,
+    /// Loads of raw materials each of a factory's deliveries uses, when
+    /// factories need them; 0 for none
+    pub materials_per_delivery: Option<u32>,
+    /// Vehicle definition used for this building's cars or trucks
+    pub vehicle: Option<String>,
+    /// Display color as sRGB components in [0, 1]
+    pub color: Option<[f32; 3]>,
+}
+
+/// A vehicle type loaded from a content pack
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serialization", serde(deny_unknown_fields))]
+pub struct VehicleDef {
+    /// Unique name used to refer to this vehicle type
+    pub name: String,
+    /// Built-in vehicle behaviour (car or truck)
+    pub base: VehicleType,
+    /// Lower bound of the randomly chosen speed
+    pub min_speed: f32,
+    /// Upper bound of the randomly chosen speed
+    pub max_speed: f32,
+    /// Display color as sRGB components in [0, 1]
+    pub color: Option<[f32; 3]>,
+    /// Display size as (width, height, length)
+    pub size: Option<[f32; 3]>,
+}
+
+/// The contents of a single content pack file
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serialization", serde(default, deny_unknown_fields))]
+pub struct ContentPack {
+    pub buildings: Vec<BuildingDef>,
+    pub vehicles: Vec<VehicleDef>,
+}
+
+impl ContentPack {
+    /// Parse a content pack from TOML text
+    #[cfg(feature = "serialization")]
+    pub fn from_toml_str(text: &str) -> Result<Self> {
+        toml::from_str(text).context("Failed to parse content pack TOML")
+    }
+}
+
+/// A building placed from a content definition
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlacedBuilding {
+    Apartment(ApartmentId),
+    Factory(FactoryId),
+    Shop(ShopId),
+}
+
+/// Registry of all building and vehicle definitions loaded from content packs
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+pub struct ContentRegistry {
+    buildings: Vec<BuildingDef>,
+    vehicles: Vec<VehicleDef>,
+}
+
+impl ContentRegistry {
+    /// Register every definition in a pack
+    ///
+    /// The pack is checked as a whole before anything is registered, so a bad
+    /// pack leaves the registry unchanged.
+    pub fn register_pack(&mut self, pack: ContentPack) -> Result<()> {
+        for (index, vehicle) in pack.vehicles.iter().enumerate() {
+            ensure!(
+                self.vehicle(&vehicle.name).is_none()
+                    && !pack.vehicles[..index]
+                        .iter()
+                        .any(|v| v.name == vehicle.name),
+                "Duplicate vehicle definition '{}'",
+                vehicle.name
+            );
+            ensure!(
+                vehicle.min_speed > 0.0 && vehicle.min_speed <= vehicle.max_speed,
+                "Vehicle '{}' needs 0 < min_speed <= max_speed",
+                vehicle.name
+            );
+        }
+
+        for (index, building) in pack.buildings.iter().enumerate() {
+            ensure!(
+                self.building(&building.name).is_none()
+                    && !pack.buildings[..index]
+                        .iter()
+                        .any(|b| b.name == building.name),
+                "Duplicate building definition '{}'",
+                building.name
+            );
+            if let Some(vehicle_name) = &building.vehicle {
+                let vehicle = self
+                    .vehicle(vehicle_name)
+                    .or_else(|| pack.vehicles.iter().find(|v| &v.name == vehicle_name))
+                    .with_context(|| {
+                        format!(
+                            "Building '{}' uses unknown vehicle '{}'",
+                            building.name, vehicle_name
+                        )
+                    })?;
+                if building.kind.vehicle_type() != Some(vehicle.base) {
+                    bail!(
+                        "Building '{}' ({:?}) cannot use {:?} vehicle '{}'",
+                        building.name,
+                        building.kind,
+                        vehicle.base,
+                        vehicle_name
+                    );
+                }
+            }
+            if let Some(cost) = building.cost {
+                ensure!(
+                    cost >= 0,
+                    "Building '{}' can't have a negative cost",
+                    building.name
+                );
+            }
+            if let Some(cars) = building.cars {
+                ensure!(
+                    cars > 0,
+                    "Building '{}' needs at least one car",
+                    building.name
+                );
+            }
+            if let Some(work_time) = building.work_time {
+                ensure!(
+                    work_time > 0.0,
+                    "Building '{}' needs a positive work_time",
+                    building.name
+                );
+            }
+            if let Some(max_deliveries) = building.max_deliveries {
+                ensure!(
+                    max_deliveries > 0,
+                    "Building '{}' needs a positive max_deliveries",
+                    building.name
+                );
+            }
+            if let Some(shifts) = building.shifts_per_delivery {
+                ensure!(
+                    shifts > 0,
+                    "Building '{}' needs a positive shifts_per_delivery",
+                    building.name
+                );
+            }
+        }
+
+        self.vehicles.extend(pack.vehicles);
+        self.buildings.extend(pack.buildings);
+        Ok(())
+    }
+
+    /// Load and register a single content pack file
+    #[cfg(feature = "serialization")]
+    pub fn load_pack_file(&mut self, path: &Path) -> Result<()> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read content pack {}", path.display()))?;
+        let pack = ContentPack::from_toml_str(&text)
+            .with_context(|| format!("Invalid content pack {}", path.display()))?;
+        self.register_pack(pack)
+            .with_context(|| format!("Failed to register content pack {}", path.display()))
+    }
+
+    /// Load every `*.toml` content pack in a directory, in file name order
+    ///
+    /// Returns the number of packs loaded.
+    #[cfg(feature = "serialization")]
+    pub fn load_dir(&mut self, dir: &Path) -> Result<usize> {
+        let mut paths = std::fs::read_dir(dir)
+            .with_context(|| format!("Failed to read content directory {}", dir.display()))?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<std::io::Result<Vec<_>>>()
+            .with_context(|| format!("Failed to list content directory {}", dir.display()))?;
+        paths.retain(|path| path.extension().is_some_and(|ext| ext == "toml"));
+        paths.sort();
+
+        for path in &paths {
+            self.load_pack_file(path)?;
+        }
+        Ok(paths.len())
+    }
+
+    /// Look up a building definition by name
+    pub fn building(&self, name: &str) -> Option<&BuildingDef> {
+        self.buildings.iter().find(|def| def.name == name)
+    }
+
+    /// Look up a vehicle definition by name
+    pub fn vehicle(&self, name: &str) -> Option<&VehicleDef> {
+        self.vehicles.iter().find(|def| def.name == name)
+    }
+
+    /// All building definitions, in registration order
+    pub fn buildings(&self) -> &[BuildingDef] {
+        &self.buildings
+    }
+
+    /// All vehicle definitions, in registration order
+    pub fn vehicles(&self) -> &[VehicleDef] {
+        &self.vehicles
+    }
+
+    /// Building definitions based on the given kind, in registration order
+    pub fn buildings_of_kind(&self, kind: BuildingKind) -> impl Iterator<Item = &BuildingDef> {
+        self.buildings.iter().filter(move |def| def.kind == kind)
+    }
+}
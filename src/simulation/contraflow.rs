@@ -0,0 +1,307 @@
+//! Contraflow: one-way roads that turn round for rush hour
+//!
+//! A one-way road can be given [`ContraflowWindow`]s, hours of the day when
+//! it runs against the direction it was built in, so a road into town in
+//! the morning carries traffic home in the evening. The player schedules a
+//! road from the road menu (see [`BuildCommand::ScheduleContraflow`]) and a
+//! scenario names its roads by their intersections in the economy's
+//! `contraflow` list.
+//!
+//! When a window opens or closes the road doesn't turn round under the cars
+//! on it. It is closed, so no more vehicles route onto it, and turns round
+//! once the ones already on it have finished their way along it; then it
+//! reopens in its new direction and every vehicle's route is checked again.
+//! The road keeps its ID, restriction and sensor whichever way it runs.
+//! Removing the road or making it two-way drops its schedule.
+//!
+//! [`BuildCommand::ScheduleContraflow`]: super::BuildCommand::ScheduleContraflow
+
+use std::collections::BTreeSet;
+
+use anyhow::{ensure, Context, Result};
+#[cfg(feature = "serialization")]
+use serde::{Deserialize, Serialize};
+
+use super::types::{IntersectionId, RoadId, SimMap};
+use super::warnings::SimWarning;
+use super::world::SimWorld;
+
+/// Hours of the day a scheduled road runs against its built direction
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serialization", serde(deny_unknown_fields))]
+pub struct ContraflowWindow {
+    /// Hour of the day (0-24) the road turns round
+    pub start_hour: f32,
+    /// Hour of the day (0-24) it turns back; earlier than `start_hour` for a
+    /// window that runs past midnight
+    pub end_hour: f32,
+}
+
+/// The windows the road menu schedules: the morning and evening rush hours
+pub const RUSH_HOUR_CONTRAFLOW: [ContraflowWindow; 2] = [
+    ContraflowWindow {
+        start_hour: 7.0,
+        end_hour: 9.0,
+    },
+    ContraflowWindow {
+        start_hour: 16.0,
+        end_hour: 18.0,
+    },
+];
+
+impl ContraflowWindow {
+    /// Reject windows that never open
+    pub fn validate(&self) -> Result<()> {
+        for (name, hour) in [("start_hour", self.start_hour), ("end_hour", self.end_hour)] {
+            ensure!(
+                (0.0..24.0).contains(&hour),
+                "contraflow window {} must be at least 0 and less than 24 (got {})",
+                name,
+                hour
+            );
+        }
+        ensure!(
+            self.start_hour != self.end_hour,
+            "contraflow window start_hour and end_hour must differ (both {})",
+            self.start_hour
+        );
+        Ok(())
+    }
+
+    /// Whether the window is open at an hour of the day
+    pub fn contains(&self, hour: f32) -> bool {
+        if self.start_hour < self.end_hour {
+            (self.start_hour..self.end_hour).contains(&hour)
+        } else {
+            hour >= self.start_hour || hour < self.end_hour
+        }
+    }
+}
+
+/// A scenario's contraflow road, named by the intersections it was built
+/// to run between
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serialization", serde(deny_unknown_fields))]
+pub struct ContraflowRoad {
+    pub from: IntersectionId,
+    pub to: IntersectionId,
+    pub windows: Vec<ContraflowWindow>,
+}
+
+impl ContraflowRoad {
+    /// Reject roads with no windows or windows that never open
+    pub fn validate(&self) -> Result<()> {
+        ensure!(
+            !self.windows.is_empty(),
+            "contraflow road from {:?} to {:?} needs at least one window",
+            self.from,
+            self.to
+        );
+        for window in &self.windows {
+            window.validate()?;
+        }
+        Ok(())
+    }
+}
+
+/// Contraflow schedules and the roads turned round by them
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+pub(super) struct Contraflow {
+    /// Windows the player scheduled, by road
+    schedules: SimMap<RoadId, Vec<ContraflowWindow>>,
+    /// Roads running against the direction they were built in
+    reversed: BTreeSet<RoadId>,
+    /// Roads closed until their vehicles have cleared so they can turn
+    /// round, and whether they were open before
+    clearing: SimMap<RoadId, bool>,
+}
+
+impl SimWorld {
+    /// Schedule a one-way road to run the other way during `windows`, or
+    /// (with no windows) cancel its schedule
+    ///
+    /// A road cancelled while turned round turns back once it is clear.
+    pub fn schedule_contraflow(
+        &mut self,
+        road_id: RoadId,
+        windows: Vec<ContraflowWindow>,
+    ) -> Result<()> {
+        self.road_network
+            .get_road(road_id)
+            .with_context(|| format!("Road {:?} not found", road_id))?;
+        if windows.is_empty() {
+            self.contraflow.schedules.remove(&road_id);
+            return Ok(());
+        }
+        ensure!(
+            self.road_network.opposite_road(road_id).is_none(),
+            "Only one-way roads can run contraflow"
+        );
+        for window in &windows {
+            window.validate()?;
+        }
+        self.contraflow.schedules.insert(road_id, windows);
+        Ok(())
+    }
+
+    /// Windows the player scheduled a road to run the other way in
+    pub fn contraflow_windows(&self, road_id: RoadId) -> &[ContraflowWindow] {
+        self.contraflow
+            .schedules
+            .get(&road_id)
+            .map_or(&[], Vec::as_slice)
+    }
+
+    /// Roads the player scheduled, with their windows, in ID order
+    pub fn contraflow_schedules(&self) -> &SimMap<RoadId, Vec<ContraflowWindow>> {
+        &self.contraflow.schedules
+    }
+
+    /// Whether a road is running against the direction it was built in
+    pub fn is_contraflow_reversed(&self, road_id: RoadId) -> bool {
+        self.contraflow.reversed.contains(&road_id)
+    }
+
+    /// Whether a road is closed waiting for its vehicles to clear before it
+    /// turns round
+    pub fn is_contraflow_clearing(&self, road_id: RoadId) -> bool {
+        self.contraflow.clearing.contains_key(&road_id)
+    }
+
+    /// The economy's contraflow roads that exist, with their windows
+    fn scenario_contraflow_roads(&mut self) -> Vec<(RoadId, Vec<ContraflowWindow>)> {
+        let Some(game_state) = &self.game_state else {
+            return Vec::new();
+        };
+        let mut roads = Vec::new();
+        for scenario_road in &game_state.economy.contraflow {
+            let (from, to) = (scenario_road.from, scenario_road.to);
+            // A road turned round runs from `to` to `from`
+            let road_id = [(from, to, false), (to, from, true)]
+                .into_iter()
+                .find_map(|(start, end, reversed)| {
+                    self.road_network
+                        .find_road_between(start, end)
+                        .ok()
+                        .filter(|road_id| self.contraflow.reversed.contains(road_id) == reversed)
+                })
+                .filter(|road_id| self.road_network.opposite_road(*road_id).is_none());
+            match road_id {
+                Some(road_id) => roads.push((road_id, scenario_road.windows.clone())),
+                None => {
+                    self.warnings.warn(SimWarning::Contraflow, self.time, || {
+                        format!("No one-way road from {:?} to {:?} for contraflow", from, to)
+                    });
+                }
+            }
+        }
+        roads
+    }
+
+    /// Turn round the roads whose windows opened or closed, once they are
+    /// clear
+    pub(super) fn update_contraflow(&mut self) {
+        let has_scenario_roads = self
+            .game_state
+            .as_ref()
+            .is_some_and(|game_state| !game_state.economy.contraflow.is_empty());
+        if !has_scenario_roads
+            && self.contraflow.schedules.is_empty()
+            && self.contraflow.reversed.is_empty()
+        {
+            return;
+        }
+
+        // Forget roads that were removed or made two-way
+        let network = &self.road_network;
+        let one_way = |road_id: &RoadId| {
+            network.get_road(*road_id).is_some() && network.opposite_road(*road_id).is_none()
+        };
+        self.contraflow.schedules.retain(|road_id, _| one_way(road_id));
+        self.contraflow.reversed.retain(one_way);
+        self.contraflow.clearing.retain(|road_id, _| one_way(road_id));
+
+        let hour = self.time_of_day();
+        let scenario_roads = self.scenario_contraflow_roads();
+        let wanted: BTreeSet<RoadId> = self
+            .contraflow
+            .schedules
+            .iter()
+            .map(|(road_id, windows)| (*road_id, windows))
+            .chain(scenario_roads.iter().map(|(road_id, windows)| (*road_id, windows)))
+            .filter(|(_, windows)| windows.iter().any(|window| window.contains(hour)))
+            .map(|(road_id, _)| road_id)
+            .collect();
+
+        let due: BTreeSet<RoadId> = wanted
+            .symmetric_difference(&self.contraflow.reversed)
+            .copied()
+            .collect();
+        // A window that closed again before its road cleared
+        let settled: Vec<RoadId> = self
+            .contraflow
+            .clearing
+            .keys()
+            .filter(|road_id| !due.contains(road_id))
+            .copied()
+            .collect();
+        for road_id in settled {
+            if self.contraflow.clearing.remove(&road_id) == Some(true) {
+                self.reopen_contraflow_road(road_id);
+            }
+        }
+        for road_id in due {
+            self.turn_round_when_clear(road_id);
+        }
+    }
+
+    /// Turn a road round if no vehicle is on it, or close it until none is
+    fn turn_round_when_clear(&mut self, road_id: RoadId) {
+        // Vehicles waiting to get onto the road count, since they will
+        let occupied = self
+            .cars
+            .values()
+            .any(|car| car.current_road == road_id && car.awaiting_route.is_none());
+        if occupied {
+            if !self.contraflow.clearing.contains_key(&road_id) {
+                let open = self
+                    .road_network
+                    .get_road(road_id)
+                    .is_some_and(|road| !road.closed);
+                let closed_here = open && self.set_road_closed(road_id, true).is_ok();
+                self.contraflow.clearing.insert(road_id, closed_here);
+            }
+            return;
+        }
+
+        let closed_here = self.contraflow.clearing.remove(&road_id) == Some(true);
+        if let Err(err) = self.road_network.flip_road(road_id) {
+            self.warnings.warn(SimWarning::Contraflow, self.time, || {
+                format!("Couldn't turn road {:?} round: {:#}", road_id, err)
+            });
+            if closed_here {
+                self.reopen_contraflow_road(road_id);
+            }
+            return;
+        }
+        if !self.contraflow.reversed.remove(&road_id) {
+            self.contraflow.reversed.insert(road_id);
+        }
+        if closed_here {
+            let _ = self.road_network.set_road_closed(road_id, false);
+        }
+        self.changes.mark_layout();
+        self.recalculate_car_paths();
+    }
+
+    fn reopen_contraflow_road(&mut self, road_id: RoadId) {
+        if let Err(err) = self.set_road_closed(road_id, false) {
+            self.warnings.warn(SimWarning::Contraflow, self.time, || {
+                format!("Couldn't reopen road {:?}: {:#}", road_id, err)
+            });
+        }
+    }
+}
@@ -0,0 +1,118 @@
+//! Stop signs and yield rules
+//!
+//! Every intersection is controlled one of four ways, set with
+//! [`SimWorld::set_intersection_control`]:
+//!
+//! - **Uncontrolled**: the first vehicle to reach the intersection crosses
+//!   while the others wait for it to clear.
+//! - **Stop**: every vehicle comes to a full stop at the line for
+//!   [`STOP_SIGN_SECS`] before it may go, and vehicles go in the order they
+//!   stopped, whichever road they are on.
+//! - **Yield**: vehicles needn't stop, but only set off across the
+//!   intersection once it has been clear for a gap of [`YIELD_GAP_SECS`]; a
+//!   driver's gap acceptance (see [`super::personality`]) stretches or
+//!   shrinks the gap they wait for.
+//! - **Traffic light**: the intersection's signal (see [`super::signal`])
+//!   decides which roads may enter.
+//!
+//! A stop sign is slow but fair: a queue on a busy road can't starve a
+//! quiet one. A yield keeps traffic moving when it is light, but a steady
+//! stream leaves no gap for anyone else. Ambulances don't stop at stop
+//! signs.
+
+use anyhow::{Context, Result};
+#[cfg(feature = "serialization")]
+use serde::{Deserialize, Serialize};
+
+use super::types::IntersectionId;
+use super::world::SimWorld;
+
+/// Seconds a vehicle stands at a stop sign before it may go
+pub const STOP_SIGN_SECS: f32 = 1.0;
+/// Seconds an intersection must have been clear before a yielding vehicle
+/// sets off across it
+pub const YIELD_GAP_SECS: f32 = 1.5;
+
+/// How an intersection decides who goes next
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serialization", serde(rename_all = "snake_case"))]
+pub enum IntersectionControl {
+    /// First come, first across
+    #[default]
+    Uncontrolled,
+    /// Full stop, then in the order vehicles stopped
+    Stop,
+    /// Set off once the intersection has been clear for a gap
+    Yield,
+    /// A traffic signal's phases
+    TrafficLight,
+}
+
+impl IntersectionControl {
+    /// Every control, in the order [`Self::next`] cycles through them
+    pub const ALL: [IntersectionControl; 4] = [
+        IntersectionControl::Uncontrolled,
+        IntersectionControl::Stop,
+        IntersectionControl::Yield,
+        IntersectionControl::TrafficLight,
+    ];
+
+    /// Name shown to the player
+    pub fn label(self) -> &'static str {
+        match self {
+            IntersectionControl::Uncontrolled => "Uncontrolled",
+            IntersectionControl::Stop => "Stop sign",
+            IntersectionControl::Yield => "Yield",
+            IntersectionControl::TrafficLight => "Traffic light",
+        }
+    }
+
+    /// The control after this one, round to the first after the last
+    pub fn next(self) -> Self {
+        let index = Self::ALL.iter().position(|control| *control == self);
+        Self::ALL[index.map_or(0, |index| (index + 1) % Self::ALL.len())]
+    }
+}
+
+impl SimWorld {
+    /// How an intersection is controlled, if it exists
+    pub fn intersection_control(
+        &self,
+        intersection_id: IntersectionId,
+    ) -> Option<IntersectionControl> {
+        self.intersections
+            .get(&intersection_id)
+            .map(|intersection| intersection.control)
+    }
+
+    /// Change how an intersection is controlled
+    ///
+    /// Switching to a traffic light puts up a signal with the
+    /// [default phases](Self::default_signal_phases), keeping one already
+    /// there; switching away takes the signal down. Vehicles already
+    /// crossing carry on.
+    pub fn set_intersection_control(
+        &mut self,
+        intersection_id: IntersectionId,
+        control: IntersectionControl,
+    ) -> Result<()> {
+        let intersection = self
+            .intersections
+            .get(&intersection_id)
+            .with_context(|| format!("Intersection {:?} not found", intersection_id))?;
+        if control == IntersectionControl::TrafficLight {
+            if intersection.signal.is_none() {
+                let phases = self.default_signal_phases(intersection_id);
+                self.set_signal(intersection_id, Some(phases))?;
+            }
+            return Ok(());
+        }
+        self.set_signal(intersection_id, None)?;
+        if let Some(intersection) = self.intersections.get_mut(&intersection_id) {
+            intersection.control = control;
+            intersection.stop_queue.clear();
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,167 @@
+//! Slowing down for bends
+//!
+//! Roads are straight, so a curved street is a chain of short roads and
+//! vehicles change direction at the intersections between them. Over the
+//! last [`INTERSECTION_APPROACH_DISTANCE`] of a road, a vehicle that turns
+//! at its end slows to the speed it can take the corner at. The corner is
+//! driven as an arc cut across that distance, so the sharper the turn the
+//! tighter the arc, and vehicles keep their sideways acceleration within
+//! [`CurveSpeedLimit::lateral_accel`]. Going straight on needs no slowing.
+//!
+//! The arc itself is a [`TurnArc`]: it starts that distance before the
+//! intersection and ends the same distance along the next road, and a
+//! vehicle is drawn along it, turning gradually, instead of snapping to the
+//! next road's heading at the intersection. The vehicle keeps the
+//! intersection to itself until it is round.
+
+use std::f32::consts::FRAC_PI_2;
+#[cfg(feature = "serialization")]
+use serde::{Deserialize, Serialize};
+
+use super::rules::turn_angle;
+use super::types::{IntersectionId, Position, INTERSECTION_APPROACH_DISTANCE};
+
+/// Lines closer to parallel than this (the sine of the angle between them)
+/// are treated as parallel when finding the corner of a turn
+const PARALLEL_EPSILON: f32 = 1e-3;
+
+/// Sideways acceleration vehicles accept in a bend, in world units per
+/// second squared
+pub const DEFAULT_LATERAL_ACCEL: f32 = 4.0;
+
+/// Slowest speed vehicles take even the sharpest bend at
+pub const DEFAULT_MIN_CORNER_SPEED: f32 = 0.5;
+
+/// How fast vehicles may take a bend of a given curvature
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+pub struct CurveSpeedLimit {
+    /// Sideways acceleration vehicles accept, in world units per second
+    /// squared
+    pub lateral_accel: f32,
+    /// Slowest speed for any bend, so a U-turn still gets round
+    pub min_speed: f32,
+}
+
+impl Default for CurveSpeedLimit {
+    fn default() -> Self {
+        Self {
+            lateral_accel: DEFAULT_LATERAL_ACCEL,
+            min_speed: DEFAULT_MIN_CORNER_SPEED,
+        }
+    }
+}
+
+impl CurveSpeedLimit {
+    /// Curvature (one over the radius) of the arc a vehicle drives round a
+    /// change in heading of `turn_angle` radians; infinite for a U-turn
+    pub fn curvature_of_turn(turn_angle: f32) -> f32 {
+        let half_turn = turn_angle.abs() / 2.0;
+        if half_turn >= FRAC_PI_2 {
+            return f32::INFINITY;
+        }
+        half_turn.tan() / INTERSECTION_APPROACH_DISTANCE
+    }
+
+    /// Fastest speed for a bend of the given curvature, keeping sideways
+    /// acceleration (speed squared times curvature) within the limit
+    pub fn max_speed(&self, curvature: f32) -> f32 {
+        if curvature <= 0.0 {
+            return f32::INFINITY;
+        }
+        (self.lateral_accel / curvature).sqrt().max(self.min_speed)
+    }
+
+    /// Fastest speed for a change in heading of `turn_angle` radians
+    pub fn max_speed_for_turn(&self, turn_angle: f32) -> f32 {
+        self.max_speed(Self::curvature_of_turn(turn_angle))
+    }
+}
+
+/// The curve a vehicle drives through an intersection from one road onto
+/// the next
+///
+/// It is a quadratic curve from the vehicle's lane on the first road to its
+/// lane on the next, cornering where the two lanes meet.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+pub struct TurnArc {
+    /// Intersection the arc goes through
+    pub intersection: IntersectionId,
+    /// Where the arc leaves the first road
+    pub entry: Position,
+    /// Where the two lanes meet, which the curve bends toward
+    pub control: Position,
+    /// Where the arc joins the next road
+    pub exit: Position,
+    /// Distance the arc covers on each road, either side of the
+    /// intersection
+    pub reach: f32,
+    /// Change in heading round the arc (see [`turn_angle`])
+    pub change: f32,
+}
+
+impl TurnArc {
+    /// The arc from `entry`, heading `entry_angle`, to `exit`, heading
+    /// `exit_angle`, round the intersection at `corner`
+    ///
+    /// Headings are in radians as given by [`Position::angle_to`].
+    pub fn new(
+        intersection: IntersectionId,
+        corner: Position,
+        entry: Position,
+        entry_angle: f32,
+        exit: Position,
+        exit_angle: f32,
+        reach: f32,
+    ) -> Self {
+        let (entry_x, entry_z) = entry_angle.sin_cos();
+        let (exit_x, exit_z) = exit_angle.sin_cos();
+        let cross = entry_x * exit_z - entry_z * exit_x;
+        // How far ahead of the entry the line into the arc meets the line
+        // out of it
+        let along = (cross.abs() > PARALLEL_EPSILON)
+            .then(|| ((exit.x - entry.x) * exit_z - (exit.z - entry.z) * exit_x) / cross)
+            .filter(|along| *along > 0.0 && *along < 4.0 * reach);
+        let control = if let Some(along) = along {
+            Position::new(entry.x + entry_x * along, corner.y, entry.z + entry_z * along)
+        } else if entry_x * exit_x + entry_z * exit_z > 0.0 {
+            // Straight on, perhaps changing lanes
+            entry.lerp(&exit, 0.5)
+        } else {
+            // Turning back the way it came
+            corner
+        };
+        Self {
+            intersection,
+            entry,
+            control,
+            exit,
+            reach,
+            change: turn_angle(entry_angle, exit_angle),
+        }
+    }
+
+    /// Position and heading `progress` (0 to 1) of the way round the arc
+    pub fn point(&self, progress: f32) -> (Position, f32) {
+        let t = progress.clamp(0.0, 1.0);
+        let first = self.entry.lerp(&self.control, t);
+        let second = self.control.lerp(&self.exit, t);
+        // The curve heads along the line between the two
+        let heading = if first.distance(&second) > f32::EPSILON {
+            first.angle_to(&second)
+        } else {
+            self.entry.angle_to(&self.exit)
+        };
+        (first.lerp(&second, t), heading)
+    }
+
+    /// Position and heading of a vehicle `distance` from the intersection,
+    /// negative before it
+    pub fn point_at(&self, distance: f32) -> (Position, f32) {
+        if self.reach <= 0.0 {
+            return self.point(if distance < 0.0 { 0.0 } else { 1.0 });
+        }
+        self.point((distance + self.reach) / (2.0 * self.reach))
+    }
+}
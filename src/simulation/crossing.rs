@@ -0,0 +1,77 @@
+//! How long vehicles take to cross intersections
+//!
+//! A vehicle let into an intersection holds it while it crosses, and no
+//! other movement may start through it meanwhile. How long that is depends
+//! on the intersection's size, the vehicle's movement through it and the
+//! vehicle's length: it drives across the intersection along its movement
+//! and on until its tail is clear, at its own speed; slowing for a bend
+//! is already part of its approach (see [`super::cornering`]). A movement
+//! across a wide junction, a turn across the oncoming traffic or a long
+//! truck holds the intersection longer, so a junction's capacity follows
+//! from what uses it.
+//!
+//! An intersection is as wide as the widest road meeting at it, at
+//! [`LANE_WIDTH`] a lane. Going straight on crosses the whole of it. A
+//! turn to the kerbside (right, driving on the right) hugs the near corner
+//! on a quarter circle a quarter of the width across; a turn across the
+//! oncoming traffic swings round the far corner on one three quarters
+//! across; a U-turn goes round a half circle as wide as the intersection.
+
+use std::f32::consts::{FRAC_PI_2, PI};
+
+use super::driving_side::DrivingSide;
+use super::road_network::SimRoadNetwork;
+use super::rules::Turn;
+use super::types::{IntersectionId, VehicleType, CAR_LENGTH};
+
+/// Width of a lane where roads meet
+pub const LANE_WIDTH: f32 = 0.3;
+
+/// Slowest speed a crossing is worked out at, so even a worn-out vehicle
+/// doesn't hold a junction for long
+const MIN_CROSSING_SPEED: f32 = 0.5;
+
+impl VehicleType {
+    /// Length of a vehicle of this type, nose to tail
+    pub fn length(self) -> f32 {
+        match self {
+            VehicleType::Car => CAR_LENGTH,
+            VehicleType::Truck => CAR_LENGTH * 2.0,
+            VehicleType::Ambulance => CAR_LENGTH * 1.2,
+            VehicleType::TowTruck => CAR_LENGTH * 1.5,
+            VehicleType::Van => CAR_LENGTH * 1.3,
+        }
+    }
+}
+
+impl SimRoadNetwork {
+    /// Width of an intersection: the lanes of the widest road meeting at
+    /// it (two for a two-way road, one for a one-way road)
+    pub fn intersection_width(&self, intersection_id: IntersectionId) -> f32 {
+        let lanes = self
+            .get_roads_at_intersection(intersection_id)
+            .into_iter()
+            .filter_map(|road_id| self.get_road(road_id))
+            .map(|road| if road.is_two_way { 2 } else { 1 })
+            .max()
+            .unwrap_or(1);
+        lanes as f32 * LANE_WIDTH
+    }
+}
+
+/// Distance a vehicle drives through an intersection `width` wide, making
+/// `turn` with traffic on `side`
+pub fn crossing_distance(width: f32, turn: Turn, side: DrivingSide) -> f32 {
+    match turn {
+        Turn::Straight => width,
+        Turn::UTurn => PI * width / 2.0,
+        turn if turn == side.kerbside_turn() => FRAC_PI_2 * width / 4.0,
+        _ => FRAC_PI_2 * width * 3.0 / 4.0,
+    }
+}
+
+/// Seconds a vehicle `length` long, driving at `speed`, holds an
+/// intersection `width` wide while making `turn` through it
+pub fn crossing_secs(width: f32, turn: Turn, side: DrivingSide, length: f32, speed: f32) -> f32 {
+    (crossing_distance(width, turn, side) + length) / speed.max(MIN_CROSSING_SPEED)
+}
@@ -0,0 +1,178 @@
+//! Cul-de-sac builder
+//!
+//! Lays out a dead-end residential street in one go: the street itself, a
+//! short driveway for each lot alternating between its sides, and an
+//! apartment at the end of every driveway. The whole street is paid for up
+//! front, so it is either built completely or not at all.
+
+use anyhow::{ensure, Result};
+
+use super::economy::EconomyConfig;
+use super::types::{ApartmentId, IntersectionId, Position, RoadKind};
+use super::world::SimWorld;
+
+/// Most lots one cul-de-sac can have
+pub const CUL_DE_SAC_MAX_LOTS: usize = 12;
+
+/// Length of the street needed per lot
+pub const CUL_DE_SAC_LOT_SPACING: f32 = 3.0;
+
+/// Length of each driveway from the street to its apartment
+pub const CUL_DE_SAC_DRIVEWAY_LENGTH: f32 = 2.5;
+
+/// What a cul-de-sac was built from
+#[derive(Debug, Clone, PartialEq)]
+pub struct CulDeSac {
+    /// Where the street joins the rest of the network
+    pub entrance: IntersectionId,
+    /// The closed end of the street
+    pub dead_end: IntersectionId,
+    /// Apartments from the entrance to the dead end
+    pub apartments: Vec<ApartmentId>,
+}
+
+/// Cost of a cul-de-sac with `lots` apartments on level ground
+///
+/// The same as building it by hand: one road for the street (driveways split
+/// it for free), one per driveway and one apartment per lot. On slopes the
+/// roads cost more; see [`SimWorld::cul_de_sac_slope_cost`].
+pub fn cul_de_sac_cost(economy: &EconomyConfig, lots: usize) -> i32 {
+    let lots = lots as i32;
+    economy.cost_road * (lots + 1) + economy.cost_apartment * lots
+}
+
+/// Where each lot's driveway leaves the street and where its apartment
+/// stands, from the entrance to the dead end
+pub fn cul_de_sac_lots(start: Position, end: Position, lots: usize) -> Vec<(Position, Position)> {
+    (0..lots)
+        .map(|lot| {
+            let along = start.lerp(&end, (lot + 1) as f32 / (lots + 1) as f32);
+            // Alternate lots between the right and left of the street
+            let side = if lot % 2 == 0 { 1.0 } else { -1.0 };
+            let offset = start.perpendicular_offset(&end, CUL_DE_SAC_DRIVEWAY_LENGTH * side);
+            let lot_position = Position::new(along.x + offset.x, along.y, along.z + offset.z);
+            (along, lot_position)
+        })
+        .collect()
+}
+
+impl SimWorld {
+    /// Check a cul-de-sac can be laid out between two points, with its street
+    /// and every driveway on land
+    fn validate_cul_de_sac(
+        &self,
+        start: Position,
+        end: Position,
+        lots: usize,
+        snap_distance: f32,
+    ) -> Result<()> {
+        ensure!(
+            (1..=CUL_DE_SAC_MAX_LOTS).contains(&lots),
+            "A cul-de-sac needs 1-{} lots, not {}",
+            CUL_DE_SAC_MAX_LOTS,
+            lots
+        );
+        let needed = CUL_DE_SAC_LOT_SPACING * (lots + 1) as f32;
+        ensure!(
+            start.distance(&end) >= needed,
+            "A street with {} lots must be at least {} long",
+            lots,
+            needed
+        );
+
+        // The street runs from where its entrance snaps to
+        let mut previous = self.snapped_position(start, snap_distance);
+        for (along, lot_position) in cul_de_sac_lots(previous, end, lots) {
+            self.check_crossing(&previous, &along, RoadKind::Street)?;
+            self.check_crossing(&along, &lot_position, RoadKind::Street)?;
+            previous = along;
+        }
+        self.check_crossing(&previous, &end, RoadKind::Street)
+    }
+
+    /// Cost of a cul-de-sac from `start` to `end` with `lots` apartments
+    ///
+    /// As [`cul_de_sac_cost`], but the street and each driveway cost more on
+    /// steep slopes like any road built by hand; nothing without a game.
+    pub fn cul_de_sac_slope_cost(
+        &self,
+        start: Position,
+        end: Position,
+        lots: usize,
+        snap_distance: f32,
+    ) -> i32 {
+        let Some(game_state) = &self.game_state else {
+            return 0;
+        };
+        let economy = &game_state.economy;
+        let road_cost = |from: &Position, to: &Position| {
+            (economy.cost_road as f32 * self.slope_cost_factor(from, to)).round() as i32
+        };
+        let start = self.snapped_position(start, snap_distance);
+        let driveways: i32 = cul_de_sac_lots(start, end, lots)
+            .iter()
+            .map(|(along, lot_position)| road_cost(along, lot_position))
+            .sum();
+        road_cost(&start, &end) + driveways + economy.cost_apartment * lots as i32
+    }
+
+    /// Build a dead-end street from `start` to `end` with `lots` apartments
+    ///
+    /// `start` snaps to a nearby intersection or road like any road end; the
+    /// dead end at `end` never does, so the street can't join the network
+    /// twice.
+    pub fn add_cul_de_sac(
+        &mut self,
+        start: Position,
+        end: Position,
+        lots: usize,
+        snap_distance: f32,
+    ) -> Result<CulDeSac> {
+        self.validate_cul_de_sac(start, end, lots, snap_distance)?;
+
+        let entrance = self.find_or_create_intersection(start, snap_distance)?;
+        let start = self
+            .road_network
+            .get_intersection_position(entrance)
+            .copied()
+            .unwrap_or(start);
+        let dead_end = self.add_intersection(end);
+
+        let mut apartments = Vec::with_capacity(lots);
+        let mut previous = entrance;
+        for (along, lot_position) in cul_de_sac_lots(start, end, lots) {
+            let junction = self.add_intersection(along);
+            self.add_two_way_road(previous, junction)?;
+            let driveway_end = self.add_intersection(lot_position);
+            self.add_two_way_road(junction, driveway_end)?;
+            apartments.push(self.add_apartment(driveway_end));
+            previous = junction;
+        }
+        self.add_two_way_road(previous, dead_end)?;
+
+        Ok(CulDeSac {
+            entrance,
+            dead_end,
+            apartments,
+        })
+    }
+
+    /// Build a cul-de-sac with game cost checking
+    /// Returns Some(cul_de_sac) if successful, None if insufficient funds
+    pub fn try_add_cul_de_sac(
+        &mut self,
+        start: Position,
+        end: Position,
+        lots: usize,
+        snap_distance: f32,
+    ) -> Result<Option<CulDeSac>> {
+        // Check the layout before charging for it
+        self.validate_cul_de_sac(start, end, lots, snap_distance)?;
+        let cost = self.cul_de_sac_slope_cost(start, end, lots, snap_distance);
+        if !self.spend_for_game(|_| cost) {
+            return Ok(None);
+        }
+        self.add_cul_de_sac(start, end, lots, snap_distance)
+            .map(Some)
+    }
+}
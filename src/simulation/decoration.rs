@@ -0,0 +1,152 @@
+//! Trees and parks
+//!
+//! Decorations are placed anywhere on land clear of roads, for a price.
+//! They carry no traffic; instead each gives the spots around it some
+//! appeal, the simulation's measure of how pleasant a place is to live.
+//! Appeal adds up from every decoration in range, to at most
+//! [`MAX_APPEAL`], less the pollution of nearby traffic hotspots (see
+//! [`super::hotspots`]), to at least `-MAX_APPEAL`. Greenery screens homes
+//! from nearby industry, so the short-commute penalty on a worker's pay
+//! shrinks by up to [`APPEAL_PENALTY_RELIEF`] for a home with full appeal,
+//! and grows by as much for one with none at all.
+
+use anyhow::{ensure, Result};
+#[cfg(feature = "serialization")]
+use serde::{Deserialize, Serialize};
+
+use super::types::{ApartmentId, DecorationId, Position};
+use super::world::SimWorld;
+
+/// Most appeal a spot can have, and most it can fall below nothing
+pub const MAX_APPEAL: f32 = 1.0;
+
+/// Share of the short-commute penalty a home with full appeal is spared
+pub const APPEAL_PENALTY_RELIEF: f32 = 0.5;
+
+/// How far decorations must stay from roads and intersections
+pub const DECORATION_CLEARANCE: f32 = 1.0;
+
+/// Kind of decoration
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serialization", serde(rename_all = "lowercase"))]
+pub enum DecorationKind {
+    Tree,
+    Park,
+}
+
+impl DecorationKind {
+    /// Name shown to players
+    pub fn label(self) -> &'static str {
+        match self {
+            DecorationKind::Tree => "tree",
+            DecorationKind::Park => "park",
+        }
+    }
+
+    /// Distance within which it adds appeal
+    pub fn radius(self) -> f32 {
+        match self {
+            DecorationKind::Tree => 6.0,
+            DecorationKind::Park => 12.0,
+        }
+    }
+
+    /// Appeal it adds to spots in range
+    pub fn appeal(self) -> f32 {
+        match self {
+            DecorationKind::Tree => 0.1,
+            DecorationKind::Park => 0.4,
+        }
+    }
+}
+
+/// A tree or park in the simulation
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+pub struct SimDecoration {
+    pub id: DecorationId,
+    pub kind: DecorationKind,
+    pub position: Position,
+}
+
+impl SimWorld {
+    /// Place a decoration on land clear of roads and intersections
+    pub fn add_decoration(
+        &mut self,
+        kind: DecorationKind,
+        position: Position,
+    ) -> Result<DecorationId> {
+        let position = self.on_ground(position);
+        self.check_on_land(&position)?;
+        let network = &self.road_network;
+        let clear_of_intersections = network
+            .find_closest_intersection(&position)
+            .and_then(|id| network.get_intersection_position(id))
+            .is_none_or(|other| position.distance(other) >= DECORATION_CLEARANCE);
+        let clear_of_roads = network
+            .find_closest_point_on_road(&position)
+            .is_none_or(|(_, point, _, _)| position.distance(&point) >= DECORATION_CLEARANCE);
+        ensure!(
+            clear_of_intersections && clear_of_roads,
+            "A {} must be at least {} from roads",
+            kind.label(),
+            DECORATION_CLEARANCE
+        );
+
+        let id = DecorationId(self.next_sim_id());
+        self.decorations
+            .insert(id, SimDecoration { id, kind, position });
+        self.changes.mark_layout();
+        Ok(id)
+    }
+
+    /// Place a decoration with game cost checking
+    /// Returns Some(id) if successful, None if insufficient funds
+    pub fn try_add_decoration(
+        &mut self,
+        kind: DecorationKind,
+        position: Position,
+    ) -> Result<Option<DecorationId>> {
+        let paid = self.spend_for_game(|economy| match kind {
+            DecorationKind::Tree => economy.cost_tree,
+            DecorationKind::Park => economy.cost_park,
+        });
+        if !paid {
+            return Ok(None);
+        }
+        self.add_decoration(kind, position).map(Some)
+    }
+
+    /// Remove a decoration
+    pub fn remove_decoration(&mut self, id: DecorationId) {
+        self.decorations.remove(&id);
+        self.changes.mark_layout();
+    }
+
+    /// Appeal of a spot from the decorations and hotspots around it,
+    /// `-MAX_APPEAL` to [`MAX_APPEAL`]
+    pub fn appeal_at(&self, position: &Position) -> f32 {
+        let greenery = self
+            .decorations
+            .values()
+            .filter(|decoration| {
+                position.distance(&decoration.position) <= decoration.kind.radius()
+            })
+            .map(|decoration| decoration.kind.appeal())
+            .sum::<f32>()
+            .min(MAX_APPEAL);
+        (greenery - self.pollution_at(position)).max(-MAX_APPEAL)
+    }
+
+    /// Appeal of the spot an apartment stands on
+    pub fn apartment_appeal(&self, apartment_id: ApartmentId) -> f32 {
+        self.apartments
+            .get(&apartment_id)
+            .and_then(|apartment| {
+                self.road_network
+                    .get_intersection_position(apartment.intersection_id)
+            })
+            .map_or(0.0, |position| self.appeal_at(position))
+    }
+}
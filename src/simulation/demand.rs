@@ -0,0 +1,234 @@
+//! Demand that grows over time
+//!
+//! An economy can include a [`DemandRamp`], which turns the game into a
+//! scenario where demand keeps rising and the player has to keep expanding.
+//! The demand level starts low and grows on a linear or exponential curve.
+//! At level 1, each house sends a worker out every `worker_rest_secs`, and
+//! each shop orders `shop_orders_per_minute` deliveries a minute. Higher
+//! levels scale both. Trucks serve the shop with the oldest unclaimed order.
+//! Every order has to arrive within `delivery_sla_secs`, and the first
+//! order that doesn't is recorded as an [`SlaBreach`], so a headless run can
+//! find the demand level a network stops keeping up at.
+//!
+//! Without a ramp, houses send workers out as soon as they are home and
+//! shops don't place orders.
+
+use anyhow::{ensure, Result};
+#[cfg(feature = "serialization")]
+use serde::{Deserialize, Serialize};
+
+use super::types::{IntersectionId, TripType, VehicleType};
+use super::world::SimWorld;
+
+/// How the demand level grows
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serialization", serde(rename_all = "snake_case"))]
+pub enum RampCurve {
+    /// By `growth_per_minute` every minute
+    #[default]
+    Linear,
+    /// By a factor of `1 + growth_per_minute` every minute
+    Exponential,
+}
+
+/// Demand growing over time on a configurable curve
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serialization", serde(default, deny_unknown_fields))]
+pub struct DemandRamp {
+    /// Demand level at the start of the game
+    pub start_level: f32,
+    /// How the level grows
+    pub curve: RampCurve,
+    /// Growth per in-game minute; see [`RampCurve`]
+    pub growth_per_minute: f32,
+    /// Level the ramp stops growing at
+    pub max_level: f32,
+    /// Seconds a worker stays home between shifts at level 1
+    pub worker_rest_secs: f32,
+    /// Deliveries each shop orders per minute at level 1
+    pub shop_orders_per_minute: f32,
+    /// Seconds an order may wait before the delivery SLA counts as broken
+    pub delivery_sla_secs: f32,
+}
+
+impl Default for DemandRamp {
+    fn default() -> Self {
+        Self {
+            start_level: 0.5,
+            curve: RampCurve::Linear,
+            growth_per_minute: 0.25,
+            max_level: 10.0,
+            worker_rest_secs: 20.0,
+            shop_orders_per_minute: 1.0,
+            delivery_sla_secs: 90.0,
+        }
+    }
+}
+
+impl DemandRamp {
+    /// Demand level `time` seconds into the game
+    pub fn level_at(&self, time: f32) -> f32 {
+        let minutes = time.max(0.0) / 60.0;
+        let level = match self.curve {
+            RampCurve::Linear => self.start_level + self.growth_per_minute * minutes,
+            RampCurve::Exponential => {
+                self.start_level * (1.0 + self.growth_per_minute).powf(minutes)
+            }
+        };
+        level.min(self.max_level)
+    }
+
+    /// Reject ramps that would stall or never place orders
+    pub fn validate(&self) -> Result<()> {
+        ensure!(
+            self.start_level > 0.0,
+            "demand_ramp.start_level must be positive (got {})",
+            self.start_level
+        );
+        ensure!(
+            self.max_level >= self.start_level,
+            "demand_ramp.max_level must be at least start_level (got {})",
+            self.max_level
+        );
+        for (name, value) in [
+            ("growth_per_minute", self.growth_per_minute),
+            ("worker_rest_secs", self.worker_rest_secs),
+            ("shop_orders_per_minute", self.shop_orders_per_minute),
+        ] {
+            ensure!(
+                value >= 0.0,
+                "demand_ramp.{} must not be negative (got {})",
+                name,
+                value
+            );
+        }
+        ensure!(
+            self.delivery_sla_secs > 0.0,
+            "demand_ramp.delivery_sla_secs must be positive (got {})",
+            self.delivery_sla_secs
+        );
+        Ok(())
+    }
+}
+
+/// The first shop order that waited longer than the delivery SLA
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+pub struct SlaBreach {
+    /// Simulation time the SLA broke
+    pub time: f32,
+    /// Demand level at that time
+    pub level: f32,
+}
+
+impl SimWorld {
+    /// The demand ramp of the game's economy, if it has one
+    pub fn demand_ramp(&self) -> Option<&DemandRamp> {
+        self.game_state
+            .as_ref()
+            .and_then(|game_state| game_state.economy.demand_ramp.as_ref())
+    }
+
+    /// Current demand level, surges included (1 without a ramp)
+    pub fn demand_level(&self) -> f32 {
+        self.demand_ramp()
+            .map_or(1.0, |ramp| ramp.level_at(self.time) * self.demand_surge())
+    }
+
+    /// Shop orders waiting for a delivery
+    pub fn open_orders(&self) -> usize {
+        self.shops.values().map(|shop| shop.orders.len()).sum()
+    }
+
+    /// When the delivery SLA first broke, if it has
+    pub fn sla_breach(&self) -> Option<SlaBreach> {
+        self.sla_breach
+    }
+
+    /// Seconds a house waits between sending workers out (0 without a ramp)
+    pub(super) fn worker_rest_secs(&self) -> f32 {
+        self.demand_ramp()
+            .map_or(0.0, |ramp| ramp.worker_rest_secs / self.demand_level())
+    }
+
+    /// Place shop orders and check them against the delivery SLA
+    pub(super) fn update_demand(&mut self, delta_secs: f32) {
+        let Some(ramp) = self.demand_ramp().cloned() else {
+            return;
+        };
+        let level = self.demand_level();
+        let orders_per_sec = ramp.shop_orders_per_minute * level / 60.0;
+        let mut oldest_order = None;
+        for shop in self.shops.values_mut() {
+            shop.order_progress += orders_per_sec * delta_secs;
+            while shop.order_progress >= 1.0 {
+                shop.order_progress -= 1.0;
+                shop.orders.push_back(self.time);
+            }
+            if let Some(&placed) = shop.orders.front() {
+                oldest_order = Some(oldest_order.map_or(placed, |oldest: f32| oldest.min(placed)));
+            }
+        }
+
+        let late = oldest_order.is_some_and(|placed| self.time - placed > ramp.delivery_sla_secs);
+        if late && self.sla_breach.is_none() {
+            self.sla_breach = Some(SlaBreach {
+                time: self.time,
+                level,
+            });
+            if let Some(game_state) = &mut self.game_state {
+                game_state.record_sla_breach(level);
+            }
+        }
+    }
+
+    /// Count the trucks on their way to each shop that has open orders
+    pub(super) fn count_orders_en_route(&mut self) {
+        for shop in self.shops.values_mut() {
+            shop.orders_en_route = 0;
+        }
+        if self.open_orders() == 0 {
+            return;
+        }
+        // Trucks waiting for a route have claimed their orders too
+        let waiting = self
+            .pending_trips
+            .iter()
+            .filter(|trip| trip.spawn.vehicle_type == VehicleType::Truck)
+            .map(|trip| trip.spawn.to);
+        let on_the_road = self
+            .cars
+            .values()
+            .filter(|car| {
+                car.vehicle_type == VehicleType::Truck && car.trip_type == TripType::Outbound
+            })
+            .filter_map(|car| car.destination());
+        for destination in on_the_road.chain(waiting) {
+            if let Some(shop) = self
+                .shops
+                .values_mut()
+                .find(|shop| shop.intersection_id == destination)
+            {
+                shop.orders_en_route += 1;
+            }
+        }
+    }
+
+    /// Claim the oldest shop order no truck is on its way to yet, returning
+    /// the shop's intersection
+    pub(super) fn claim_shop_order(&mut self) -> Option<IntersectionId> {
+        let shop = self
+            .shops
+            .values_mut()
+            .filter_map(|shop| {
+                let placed = *shop.orders.get(shop.orders_en_route)?;
+                Some((placed, shop))
+            })
+            .min_by(|(a, _), (b, _)| a.total_cmp(b))
+            .map(|(_, shop)| shop)?;
+        shop.orders_en_route += 1;
+        Some(shop.intersection_id)
+    }
+}
@@ -0,0 +1,315 @@
+//! Memory usage and entity budgets
+//!
+//! [`SimWorld::memory_report`] estimates the memory held by the world's
+//! major collections from their capacities and element sizes, plus the heap
+//! memory elements own directly (such as each car's path). It is a guide
+//! for sizing worlds, not an exact count: allocator overhead isn't included.
+//!
+//! [`EntityBudgets`] caps how many cars, roads and buildings a world should
+//! have. Nothing stops a world going over; a [`BudgetWatch`] reports each
+//! budget as it is exceeded so front-ends can warn the player.
+
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+use std::mem::{size_of, size_of_val};
+
+use super::world::SimWorld;
+
+/// Approximate bytes held by a hash set's table
+pub(super) fn set_bytes<T, S>(set: &HashSet<T, S>) -> usize {
+    set.capacity() * (size_of::<T>() + 1)
+}
+
+/// Approximate bytes held by a B-tree's entries, ignoring the spare room in
+/// its nodes
+pub(super) fn map_bytes<K, V>(map: &BTreeMap<K, V>) -> usize {
+    map.len() * size_of::<(K, V)>()
+}
+
+/// Bytes held by a vector's buffer
+#[allow(clippy::ptr_arg)]
+pub(super) fn vec_bytes<T>(vec: &Vec<T>) -> usize {
+    vec.capacity() * size_of::<T>()
+}
+
+/// Memory held by one category of the world's data
+#[derive(Debug, Clone, PartialEq)]
+pub struct MemoryUsage {
+    /// What the category holds
+    pub name: &'static str,
+    /// Entries across its collections
+    pub entries: usize,
+    /// Approximate bytes on the heap
+    pub bytes: usize,
+}
+
+/// Approximate memory held by the world, by category
+#[derive(Debug, Clone, PartialEq)]
+pub struct MemoryReport {
+    /// Cars, roads, intersections, buildings, caches and stats, in that order
+    pub categories: Vec<MemoryUsage>,
+}
+
+impl MemoryReport {
+    /// Approximate bytes across every category
+    pub fn total_bytes(&self) -> usize {
+        self.categories.iter().map(|category| category.bytes).sum()
+    }
+
+    /// Memory held by a category, by name
+    pub fn category(&self, name: &str) -> Option<&MemoryUsage> {
+        self.categories
+            .iter()
+            .find(|category| category.name == name)
+    }
+}
+
+impl fmt::Display for MemoryReport {
+    /// One line per category, then the total
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for category in &self.categories {
+            writeln!(
+                f,
+                "{:<14} {:>8} entries {:>10}",
+                category.name,
+                category.entries,
+                format_bytes(category.bytes)
+            )?;
+        }
+        write!(
+            f,
+            "{:<14} {:>25}",
+            "total",
+            format_bytes(self.total_bytes())
+        )
+    }
+}
+
+/// A byte count in the largest unit that keeps it at least 1
+pub fn format_bytes(bytes: usize) -> String {
+    const UNITS: [&str; 3] = ["KiB", "MiB", "GiB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", value, UNITS[unit])
+}
+
+/// Most cars, roads and buildings a world should have; `None` is unlimited
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EntityBudgets {
+    pub max_cars: Option<usize>,
+    /// One-way roads, so a two-way road counts twice
+    pub max_roads: Option<usize>,
+    /// Houses, factories, shops, schools, hospitals, depots and terminals
+    pub max_buildings: Option<usize>,
+}
+
+impl EntityBudgets {
+    /// Whether any budget is set
+    pub fn is_limited(&self) -> bool {
+        self.max_cars.is_some() || self.max_roads.is_some() || self.max_buildings.is_some()
+    }
+
+    /// Every budget the world is over
+    pub fn overruns(&self, world: &SimWorld) -> Vec<BudgetOverrun> {
+        [
+            ("cars", world.cars.len(), self.max_cars),
+            ("roads", world.road_network.road_count(), self.max_roads),
+            ("buildings", world.building_count(), self.max_buildings),
+        ]
+        .into_iter()
+        .filter_map(|(entity, count, limit)| {
+            let limit = limit?;
+            (count > limit).then_some(BudgetOverrun {
+                entity,
+                count,
+                limit,
+            })
+        })
+        .collect()
+    }
+}
+
+/// An entity budget the world is over
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BudgetOverrun {
+    /// "cars", "roads" or "buildings"
+    pub entity: &'static str,
+    pub count: usize,
+    pub limit: usize,
+}
+
+impl fmt::Display for BudgetOverrun {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} is over the budget of {}",
+            self.count, self.entity, self.limit
+        )
+    }
+}
+
+/// Watches a world's entity budgets, reporting each overrun once until the
+/// world is back within that budget
+#[derive(Debug, Clone, Default)]
+pub struct BudgetWatch {
+    pub budgets: EntityBudgets,
+    /// Entities over budget at the last check
+    over: Vec<&'static str>,
+}
+
+impl BudgetWatch {
+    pub fn new(budgets: EntityBudgets) -> Self {
+        Self {
+            budgets,
+            over: Vec::new(),
+        }
+    }
+
+    /// Budgets the world has gone over since the last check
+    pub fn check(&mut self, world: &SimWorld) -> Vec<BudgetOverrun> {
+        if !self.budgets.is_limited() {
+            return Vec::new();
+        }
+        let overruns = self.budgets.overruns(world);
+        let new = overruns
+            .iter()
+            .filter(|overrun| !self.over.contains(&overrun.entity))
+            .cloned()
+            .collect();
+        self.over = overruns.iter().map(|overrun| overrun.entity).collect();
+        new
+    }
+
+    /// Whether the world was over any budget at the last check
+    pub fn is_over(&self) -> bool {
+        !self.over.is_empty()
+    }
+}
+
+impl SimWorld {
+    /// Buildings of every kind, not counting trees and parks
+    pub fn building_count(&self) -> usize {
+        self.apartments.len()
+            + self.factories.len()
+            + self.shops.len()
+            + self.special_buildings.len()
+    }
+
+    /// Approximate memory held by the world's major collections
+    pub fn memory_report(&self) -> MemoryReport {
+        let network = self.road_network.memory_usage();
+
+        let idle = self.car_pool.idle();
+        let car_paths: usize = self
+            .cars
+            .values()
+            .chain(idle)
+            .map(|car| vec_bytes(&car.path))
+            .sum();
+        let cars = MemoryUsage {
+            name: "cars",
+            entries: self.cars.len(),
+            bytes: map_bytes(&self.cars)
+                + size_of_val(idle)
+                + car_paths
+                + vec_bytes(&self.awaiting_tow)
+                + vec_bytes(&self.pending_trips),
+        };
+
+        let intersections = MemoryUsage {
+            name: "intersections",
+            entries: self.intersections.len(),
+            bytes: map_bytes(&self.intersections) + network.intersections,
+        };
+
+        let roads = MemoryUsage {
+            name: "roads",
+            entries: self.road_network.road_count(),
+            bytes: network.roads,
+        };
+
+        let apartments: usize = self
+            .apartments
+            .values()
+            .map(|apartment| vec_bytes(&apartment.cars) + vec_bytes(&apartment.vehicles))
+            .sum();
+        let factories: usize = self
+            .factories
+            .values()
+            .map(|factory| vec_bytes(&factory.workers))
+            .sum();
+        let shops: usize = self
+            .shops
+            .values()
+            .map(|shop| shop.orders.capacity() * size_of::<f32>())
+            .sum();
+        let specials: usize = self
+            .special_buildings
+            .values()
+            .map(|building| vec_bytes(&building.profile.peaks))
+            .sum();
+        let ferries: usize = self
+            .ferries
+            .values()
+            .map(|ferry| vec_bytes(&ferry.aboard))
+            .sum();
+        let buildings = MemoryUsage {
+            name: "buildings",
+            entries: self.building_count() + self.decorations.len() + self.ferries.len(),
+            bytes: map_bytes(&self.apartments)
+                + map_bytes(&self.factories)
+                + map_bytes(&self.shops)
+                + map_bytes(&self.special_buildings)
+                + map_bytes(&self.decorations)
+                + map_bytes(&self.ferries)
+                + apartments
+                + factories
+                + shops
+                + specials
+                + ferries,
+        };
+
+        let (change_entries, change_bytes) = self.changes.memory_usage();
+        let (event_entries, event_bytes) = self.sim_events.memory_usage();
+        let (path_entries, path_bytes) = self.path_cache.memory_usage();
+        let caches = MemoryUsage {
+            name: "caches",
+            entries: network.cache_entries + change_entries + event_entries + path_entries,
+            bytes: network.caches + change_bytes + event_bytes + path_bytes,
+        };
+
+        let mut stats = MemoryUsage {
+            name: "stats",
+            entries: network.stats_entries,
+            bytes: network.stats,
+        };
+        if let Some(game_state) = &self.game_state {
+            stats.entries += game_state.events.len();
+            stats.bytes += vec_bytes(&game_state.events);
+        }
+        #[cfg(feature = "stats")]
+        {
+            stats.entries += self.trip_stats.len()
+                + self.approach_waits.len()
+                + self.labor_stats.len()
+                + self.catchment_stats.len()
+                + self.recap_stats.len();
+            stats.bytes += map_bytes(&self.trip_stats)
+                + map_bytes(&self.approach_waits)
+                + self.labor_stats.memory_bytes()
+                + self.catchment_stats.memory_bytes()
+                + self.recap_stats.memory_bytes();
+        }
+
+        MemoryReport {
+            categories: vec![cars, roads, intersections, buildings, caches, stats],
+        }
+    }
+}
@@ -0,0 +1,106 @@
+//! Queues for vehicles setting off from buildings
+//!
+//! A building's vehicles set off from its access point, the intersection
+//! the building sits on. A vehicle whose first road has another vehicle
+//! near its start would appear on top of it, so it waits at the access
+//! point instead and gets onto the road once there's room, behind any
+//! vehicle that was already waiting there. Time spent waiting doesn't count
+//! towards the trip.
+
+use std::collections::VecDeque;
+
+use super::car_manager;
+use super::types::{CarId, IntersectionId, CAR_LENGTH, SAFE_FOLLOWING_MULTIPLIER};
+use super::world::SimWorld;
+#[cfg(feature = "stats")]
+use super::trip_stats::TripStats;
+
+/// Room a vehicle needs at the start of its first road to set off
+pub const SPAWN_CLEARANCE: f32 = CAR_LENGTH * SAFE_FOLLOWING_MULTIPLIER;
+
+impl SimWorld {
+    /// Vehicles waiting at an intersection for room to set off
+    pub fn spawn_queue_len(&self, intersection_id: IntersectionId) -> usize {
+        self.spawn_queues.get(&intersection_id).map_or(0, VecDeque::len)
+    }
+
+    /// Vehicles waiting anywhere for room to set off
+    pub fn vehicles_queued_to_spawn(&self) -> usize {
+        self.spawn_queues.values().map(VecDeque::len).sum()
+    }
+
+    /// Trips that had to wait for room to set off, and how long they waited
+    #[cfg(feature = "stats")]
+    pub fn spawn_delay_stats(&self) -> TripStats {
+        self.spawn_delays
+    }
+
+    /// Put a vehicle that has just set off onto its first road, or queue it
+    /// at its start if the road is busy there or others are already waiting
+    pub(super) fn join_or_queue(&mut self, car_id: CarId) {
+        let Some(car) = self.cars.get(&car_id) else {
+            return;
+        };
+        let start = car.start_intersection;
+        if self.spawn_queue_len(start) == 0 && self.has_room_to_set_off(car_id) {
+            self.join_first_road(car_id);
+        } else {
+            self.spawn_queues
+                .entry(start)
+                .or_default()
+                .push_back((car_id, self.time));
+        }
+    }
+
+    /// Let waiting vehicles onto their first roads, in order at each start,
+    /// until one doesn't have room
+    pub(super) fn release_spawn_queues(&mut self) {
+        let starts: Vec<IntersectionId> = self.spawn_queues.keys().copied().collect();
+        for start in starts {
+            while let Some(&(car_id, queued_at)) =
+                self.spawn_queues.get(&start).and_then(VecDeque::front)
+            {
+                // The vehicle may have been removed while it waited
+                let waiting = self
+                    .cars
+                    .get(&car_id)
+                    .is_some_and(|car| car.queued_at_start);
+                if waiting && !self.has_room_to_set_off(car_id) {
+                    break;
+                }
+                if let Some(queue) = self.spawn_queues.get_mut(&start) {
+                    queue.pop_front();
+                }
+                if waiting {
+                    #[cfg(feature = "stats")]
+                    self.spawn_delays.record(self.time - queued_at);
+                    #[cfg(not(feature = "stats"))]
+                    let _ = queued_at;
+                    self.join_first_road(car_id);
+                }
+            }
+        }
+        self.spawn_queues.retain(|_, queue| !queue.is_empty());
+    }
+
+    /// Whether a queued vehicle's first road has room at its start
+    fn has_room_to_set_off(&self, car_id: CarId) -> bool {
+        self.cars.get(&car_id).is_some_and(|car| {
+            self.road_network
+                .first_car_distance(car.current_road)
+                .is_none_or(|distance| distance >= SPAWN_CLEARANCE)
+        })
+    }
+
+    /// Put a queued vehicle on its first road; one whose road is somehow
+    /// gone gives up its trip
+    fn join_first_road(&mut self, car_id: CarId) {
+        let joined = self
+            .cars
+            .get_mut(&car_id)
+            .is_some_and(|car| car_manager::join_first_road(car, &mut self.road_network).is_ok());
+        if !joined {
+            self.end_trip_unexpectedly(car_id);
+        }
+    }
+}
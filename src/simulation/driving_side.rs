@@ -0,0 +1,61 @@
+//! Which side of the road traffic keeps to
+//!
+//! A world drives on the right or the left, fixed when it is made and kept
+//! in its save. Everything that depends on it asks its [`DrivingSide`]
+//! instead of assuming the right: the lane a vehicle is drawn in on a
+//! two-way road (and so the arcs it takes through intersections), which
+//! turn is the kerbside one that doesn't cross oncoming traffic, and which
+//! way round a roundabout traffic circulates.
+
+#[cfg(feature = "serialization")]
+use serde::{Deserialize, Serialize};
+
+use super::rules::Turn;
+use super::types::Position;
+
+/// The side of the road vehicles drive on
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serialization", serde(rename_all = "lowercase"))]
+pub enum DrivingSide {
+    #[default]
+    Right,
+    Left,
+}
+
+impl DrivingSide {
+    /// Lowercase name, as in saves
+    pub fn label(self) -> &'static str {
+        match self {
+            DrivingSide::Right => "right",
+            DrivingSide::Left => "left",
+        }
+    }
+
+    /// The turn that doesn't cross the oncoming lane
+    pub fn kerbside_turn(self) -> Turn {
+        match self {
+            DrivingSide::Right => Turn::Right,
+            DrivingSide::Left => Turn::Left,
+        }
+    }
+
+    /// Which way traffic goes round a roundabout, as the sign of the change
+    /// in heading (see [`super::turn_angle`]): anticlockwise seen from above
+    /// when driving on the right, clockwise on the left
+    pub fn circulation(self) -> f32 {
+        match self {
+            DrivingSide::Right => 1.0,
+            DrivingSide::Left => -1.0,
+        }
+    }
+
+    /// Offset across a road from `start` to `end`, `distance` towards the
+    /// side traffic going that way keeps to
+    pub fn lane_offset(self, start: &Position, end: &Position, distance: f32) -> Position {
+        match self {
+            DrivingSide::Right => start.perpendicular_offset(end, distance),
+            DrivingSide::Left => start.perpendicular_offset(end, -distance),
+        }
+    }
+}
@@ -0,0 +1,490 @@
+//! Data-driven economy parameters for the management game
+//!
+//! Every cost, revenue and goal the game uses lives in [`EconomyConfig`], so a
+//! custom economy can be loaded from a TOML file without recompiling. The
+//! defaults come from the constants in [`super::game_state`].
+
+#[cfg(feature = "serialization")]
+use anyhow::Context;
+use anyhow::{ensure, Result};
+#[cfg(feature = "serialization")]
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "serialization")]
+use std::path::Path;
+
+use super::alarms::AlarmConfig;
+use super::calibration::DemandWeight;
+use super::contraflow::ContraflowRoad;
+use super::decoration::{APPEAL_PENALTY_RELIEF, MAX_APPEAL};
+use super::demand::DemandRamp;
+use super::personality::DriverMix;
+use super::rules::IntersectionRules;
+use super::scenario_events::EventSchedule;
+use super::speed_goal::SpeedGoal;
+use super::pricing::MarketPricing;
+use super::recap::RecapConfig;
+use super::upgrades::UpgradeConfig;
+use super::game_state::{
+    BREAKDOWNS_PER_VEHICLE_HOUR, COLLISIONS_PER_CONGESTED_ROAD_HOUR, COMMUTE_HEALTHY_DISTANCE,
+    COMMUTE_TIME_ALLOWANCE_SECS, COMMUTE_TIME_PENALTY_PER_SEC, COST_APARTMENT,
+    COST_BREAKDOWN_CLEANUP, COST_BRIDGE, COST_COLLISION_CLEANUP, COST_DEPOT, COST_FACTORY, COST_FERRY,
+    COST_SENSOR, COST_HOSPITAL, COST_PARK, COST_ROAD, COST_TREE, COST_BUS_STOP,
+    COST_SCHOOL, COST_SHOP, COST_TERMINAL, COST_VEHICLE_REPLACEMENT, DELIVERY_DEADLINE_SECS, GOAL_DELIVERIES,
+    GOAL_MONEY, INSURANCE_PERIOD_SECS, INSURANCE_PREMIUM, LATE_DELIVERY_REVENUE_SHARE,
+    MIN_ON_TIME_RATE, QUICK_COMMUTE_BONUS, REVENUE_SHOP_DELIVERY, REVENUE_WORKER_DELIVERY,
+    SHORT_COMMUTE_PENALTY, STARTING_BUDGET,
+};
+
+/// How the short-commute penalty grows as a commute falls short of the
+/// healthy distance
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serialization", serde(rename_all = "snake_case"))]
+pub enum CommuteCurve {
+    /// In proportion to the shortfall
+    #[default]
+    Linear,
+    /// Gently for slightly short commutes, steeply for very short ones
+    Quadratic,
+    /// The full penalty for any commute under the healthy distance
+    Step,
+}
+
+impl CommuteCurve {
+    /// Share of the full penalty for a commute that falls `shortfall` short
+    /// of the healthy distance, as a fraction of it
+    pub fn apply(self, shortfall: f32) -> f32 {
+        let shortfall = shortfall.clamp(0.0, 1.0);
+        match self {
+            CommuteCurve::Linear => shortfall,
+            CommuteCurve::Quadratic => shortfall * shortfall,
+            CommuteCurve::Step if shortfall > 0.0 => 1.0,
+            CommuteCurve::Step => 0.0,
+        }
+    }
+
+    /// The curve as written in a formula, in terms of the shortfall `s`
+    pub fn formula(self) -> &'static str {
+        match self {
+            CommuteCurve::Linear => "s",
+            CommuteCurve::Quadratic => "s²",
+            CommuteCurve::Step => "(1 if s > 0)",
+        }
+    }
+}
+
+/// Economy parameters used by [`super::GameState`]
+///
+/// Missing fields in a TOML file fall back to their defaults, so a file only
+/// needs to list the values it changes. Unknown fields are rejected to catch
+/// typos early.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serialization", serde(default, deny_unknown_fields))]
+pub struct EconomyConfig {
+    /// Money the player starts with
+    pub starting_budget: i32,
+
+    /// Cost of building a two-way road
+    pub cost_road: i32,
+    /// Cost of building a bridge
+    pub cost_bridge: i32,
+    /// Cost of building a ferry crossing, ferry included
+    pub cost_ferry: i32,
+    /// Cost of building an apartment
+    pub cost_apartment: i32,
+    /// Cost of building a factory
+    pub cost_factory: i32,
+    /// Cost of building a shop
+    pub cost_shop: i32,
+    /// Cost of building a school
+    pub cost_school: i32,
+    /// Cost of building a hospital
+    pub cost_hospital: i32,
+    /// Cost of building a tow truck depot
+    pub cost_depot: i32,
+    /// Cost of building a rail freight terminal
+    pub cost_terminal: i32,
+    /// Cost of putting a traffic sensor on a road
+    pub cost_sensor: i32,
+    /// Cost of planting a tree
+    pub cost_tree: i32,
+    /// Cost of laying out a park
+    pub cost_park: i32,
+    /// Cost of each stop on a bus route, the bus included
+    pub cost_bus_stop: i32,
+    /// Whether traffic metrics are only shown for roads with a sensor
+    pub sensors_required: bool,
+    /// Whether factories need raw materials from a rail terminal to produce
+    /// goods
+    pub factory_supply_required: bool,
+
+    /// Revenue when a worker completes their shift and returns home
+    pub revenue_worker_delivery: i32,
+    /// Revenue when a truck delivers goods to a shop and returns
+    pub revenue_shop_delivery: i32,
+    /// Seconds a truck has from leaving its factory to reaching the shop
+    pub delivery_deadline_secs: f32,
+    /// Share of the delivery revenue a late delivery still earns
+    pub late_delivery_revenue_share: f32,
+    /// Share of deliveries that must be on time, below which the game is
+    /// lost once a few have been made (0 turns the check off)
+    pub min_on_time_rate: f32,
+
+    /// Distance (in world units) after which commutes are considered healthy
+    pub commute_healthy_distance: f32,
+    /// Maximum per-trip penalty for unhealthy (too short) commutes
+    pub short_commute_penalty: i32,
+    /// How the short-commute penalty grows with the shortfall
+    pub commute_penalty_curve: CommuteCurve,
+    /// Seconds a worker's drive home may take before it counts as slow
+    pub commute_time_allowance_secs: f32,
+    /// Penalty per second a worker's drive home takes beyond the allowance,
+    /// never more than the base pay
+    pub commute_time_penalty_per_sec: f32,
+    /// Bonus for a worker whose drive home takes no longer than the allowance
+    pub quick_commute_bonus: i32,
+
+    /// Chance of each vehicle breaking down per in-game hour
+    pub breakdowns_per_vehicle_hour: f32,
+    /// Chance of a collision on each congested road per in-game hour
+    pub collisions_per_congested_road_hour: f32,
+    /// Cost of clearing a broken-down vehicle when uninsured
+    pub cost_breakdown_cleanup: i32,
+    /// Cost of clearing a collision when uninsured
+    pub cost_collision_cleanup: i32,
+    /// Cost of replacing one worn apartment car or factory truck
+    pub cost_vehicle_replacement: i32,
+    /// Insurance premium charged every period while insured
+    pub insurance_premium: i32,
+    /// Seconds between insurance premiums
+    pub insurance_period_secs: f32,
+
+    /// Shop deliveries needed to win
+    pub goal_deliveries: usize,
+    /// Money needed to win
+    pub goal_money: i32,
+
+    /// Demand that grows over time, for scenarios that push the player to
+    /// keep expanding
+    pub demand_ramp: Option<DemandRamp>,
+
+    /// Revenue that rises where goods are short and falls where they pile
+    /// up; without it every delivery and shift earns the fixed revenue
+    pub market_pricing: Option<MarketPricing>,
+
+    /// Traffic rules at every intersection
+    pub intersection_rules: IntersectionRules,
+
+    /// Aggressive and cautious drivers among the standard ones; without it
+    /// every driver is standard
+    pub driver_mix: Option<DriverMix>,
+
+    /// Network-average speed to keep up through rush hour, for scenarios
+    /// that judge how well traffic flows
+    pub speed_goal: Option<SpeedGoal>,
+
+    /// A cap on vehicles and the capacity upgrades the player can buy;
+    /// without it there is no cap
+    pub upgrades: Option<UpgradeConfig>,
+
+    /// Road closures, strikes and demand surges the scenario puts the city
+    /// through
+    pub event_schedule: Option<EventSchedule>,
+
+    /// One-way roads that run the other way at set hours of the day
+    pub contraflow: Vec<ContraflowRoad>,
+
+    /// Alarms that go off when a measure of the city stays past a
+    /// threshold, for watching long runs
+    pub alarms: Vec<AlarmConfig>,
+
+    /// How much of their usual demand houses and factories send, as fitted
+    /// to road counts; buildings not listed send all of it
+    pub demand_weights: Vec<DemandWeight>,
+
+    /// How the recap of a finished game is worded and arranged
+    pub recap: RecapConfig,
+}
+
+impl Default for EconomyConfig {
+    fn default() -> Self {
+        Self {
+            starting_budget: STARTING_BUDGET,
+            cost_road: COST_ROAD,
+            cost_bridge: COST_BRIDGE,
+            cost_ferry: COST_FERRY,
+            cost_apartment: COST_APARTMENT,
+            cost_factory: COST_FACTORY,
+            cost_shop: COST_SHOP,
+            cost_school: COST_SCHOOL,
+            cost_hospital: COST_HOSPITAL,
+            cost_depot: COST_DEPOT,
+            cost_terminal: COST_TERMINAL,
+            cost_sensor: COST_SENSOR,
+            cost_tree: COST_TREE,
+            cost_park: COST_PARK,
+            cost_bus_stop: COST_BUS_STOP,
+            sensors_required: true,
+            factory_supply_required: false,
+            revenue_worker_delivery: REVENUE_WORKER_DELIVERY,
+            revenue_shop_delivery: REVENUE_SHOP_DELIVERY,
+            delivery_deadline_secs: DELIVERY_DEADLINE_SECS,
+            late_delivery_revenue_share: LATE_DELIVERY_REVENUE_SHARE,
+            min_on_time_rate: MIN_ON_TIME_RATE,
+            commute_healthy_distance: COMMUTE_HEALTHY_DISTANCE,
+            short_commute_penalty: SHORT_COMMUTE_PENALTY,
+            commute_penalty_curve: CommuteCurve::default(),
+            commute_time_allowance_secs: COMMUTE_TIME_ALLOWANCE_SECS,
+            commute_time_penalty_per_sec: COMMUTE_TIME_PENALTY_PER_SEC,
+            quick_commute_bonus: QUICK_COMMUTE_BONUS,
+            breakdowns_per_vehicle_hour: BREAKDOWNS_PER_VEHICLE_HOUR,
+            collisions_per_congested_road_hour: COLLISIONS_PER_CONGESTED_ROAD_HOUR,
+            cost_breakdown_cleanup: COST_BREAKDOWN_CLEANUP,
+            cost_collision_cleanup: COST_COLLISION_CLEANUP,
+            cost_vehicle_replacement: COST_VEHICLE_REPLACEMENT,
+            insurance_premium: INSURANCE_PREMIUM,
+            insurance_period_secs: INSURANCE_PERIOD_SECS,
+            goal_deliveries: GOAL_DELIVERIES,
+            goal_money: GOAL_MONEY,
+            demand_ramp: None,
+            market_pricing: None,
+            intersection_rules: IntersectionRules::default(),
+            driver_mix: None,
+            speed_goal: None,
+            upgrades: None,
+            event_schedule: None,
+            contraflow: Vec::new(),
+            alarms: Vec::new(),
+            demand_weights: Vec::new(),
+            recap: RecapConfig::default(),
+        }
+    }
+}
+
+impl EconomyConfig {
+    /// Parse and validate an economy from TOML text
+    #[cfg(feature = "serialization")]
+    pub fn from_toml_str(text: &str) -> Result<Self> {
+        let config: Self = toml::from_str(text).context("Failed to parse economy TOML")?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Serialize the economy to TOML text
+    #[cfg(feature = "serialization")]
+    pub fn to_toml_string(&self) -> Result<String> {
+        toml::to_string_pretty(self).context("Failed to serialize economy to TOML")
+    }
+
+    /// Load an economy from a TOML file
+    #[cfg(feature = "serialization")]
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read economy file {}", path.display()))?;
+        Self::from_toml_str(&text)
+            .with_context(|| format!("Invalid economy file {}", path.display()))
+    }
+
+    /// Write the economy to a TOML file
+    #[cfg(feature = "serialization")]
+    pub fn save(&self, path: &Path) -> Result<()> {
+        std::fs::write(path, self.to_toml_string()?)
+            .with_context(|| format!("Failed to write economy file {}", path.display()))
+    }
+
+    /// Penalty for a worker commute between an apartment and a factory this
+    /// far apart
+    ///
+    /// Commutes shorter than `commute_healthy_distance` are penalized up to
+    /// `short_commute_penalty`, following `commute_penalty_curve`,
+    /// representing quality-of-life costs for living too close to industrial
+    /// sites.
+    pub fn short_commute_penalty_for(&self, commute_distance: f32) -> i32 {
+        let healthy_distance = self.commute_healthy_distance;
+        if healthy_distance <= 0.0 {
+            // Invalid configuration; avoid a non-positive denominator by falling back to the maximum penalty.
+            return self.short_commute_penalty;
+        }
+        let shortfall = (healthy_distance - commute_distance).max(0.0) / healthy_distance;
+        (self.commute_penalty_curve.apply(shortfall) * self.short_commute_penalty as f32).round()
+            as i32
+    }
+
+    /// Pay for a worker trip
+    ///
+    /// Base pay, minus the short-commute penalty, minus a penalty for every
+    /// second the drive home took beyond the allowance, or plus the quick
+    /// commute bonus if it took no longer than that.
+    pub fn worker_trip_pay(&self, commute_distance: f32, commute_secs: f32) -> i32 {
+        self.worker_trip_pay_with_appeal(commute_distance, commute_secs, 0.0)
+    }
+
+    /// Pay for a worker trip from a home with some appeal (see
+    /// [`super::decoration`]), which spares it part of the short-commute
+    /// penalty, or adds to it if below nothing
+    pub fn worker_trip_pay_with_appeal(
+        &self,
+        commute_distance: f32,
+        commute_secs: f32,
+        appeal: f32,
+    ) -> i32 {
+        self.priced_worker_trip_pay(commute_distance, commute_secs, appeal, 1.0)
+    }
+
+    /// Pay for a worker trip whose base pay is scaled by `price` (see
+    /// [`MarketPricing`])
+    pub fn priced_worker_trip_pay(
+        &self,
+        commute_distance: f32,
+        commute_secs: f32,
+        appeal: f32,
+        price: f32,
+    ) -> i32 {
+        let base_pay = (self.revenue_worker_delivery as f32 * price).round() as i32;
+        let relief = 1.0 - appeal.clamp(-MAX_APPEAL, MAX_APPEAL) * APPEAL_PENALTY_RELIEF;
+        let short_commute_penalty =
+            (self.short_commute_penalty_for(commute_distance) as f32 * relief).round() as i32;
+        let overtime = commute_secs - self.commute_time_allowance_secs;
+        let time_adjustment = if overtime <= 0.0 {
+            self.quick_commute_bonus
+        } else {
+            let penalty = (overtime * self.commute_time_penalty_per_sec).round() as i32;
+            -penalty.min(self.revenue_worker_delivery.max(0))
+        };
+        base_pay - short_commute_penalty + time_adjustment
+    }
+
+    /// The worker pay formula with this economy's values, one term per line
+    pub fn worker_pay_formula(&self) -> Vec<String> {
+        vec![
+            format!("Base pay: ${}", self.revenue_worker_delivery),
+            format!(
+                "- Short commute: ${} × {} (s = share of the {:.0} healthy distance it falls short; up to {:.0}% less among trees and parks, or more by traffic hotspots)",
+                self.short_commute_penalty,
+                self.commute_penalty_curve.formula(),
+                self.commute_healthy_distance,
+                APPEAL_PENALTY_RELIEF * 100.0
+            ),
+            format!(
+                "- Slow drive home: ${:.2} per second over {:.0}s (at most the base pay)",
+                self.commute_time_penalty_per_sec, self.commute_time_allowance_secs
+            ),
+            format!(
+                "+ Quick drive home: ${} if within {:.0}s",
+                self.quick_commute_bonus, self.commute_time_allowance_secs
+            ),
+        ]
+    }
+
+    /// Reject values that would make the game nonsensical
+    pub fn validate(&self) -> Result<()> {
+        for (name, cost) in [
+            ("cost_road", self.cost_road),
+            ("cost_bridge", self.cost_bridge),
+            ("cost_ferry", self.cost_ferry),
+            ("cost_apartment", self.cost_apartment),
+            ("cost_factory", self.cost_factory),
+            ("cost_shop", self.cost_shop),
+            ("cost_school", self.cost_school),
+            ("cost_hospital", self.cost_hospital),
+            ("cost_depot", self.cost_depot),
+            ("cost_terminal", self.cost_terminal),
+            ("cost_sensor", self.cost_sensor),
+            ("cost_tree", self.cost_tree),
+            ("cost_park", self.cost_park),
+            ("cost_bus_stop", self.cost_bus_stop),
+            ("cost_breakdown_cleanup", self.cost_breakdown_cleanup),
+            ("cost_collision_cleanup", self.cost_collision_cleanup),
+            ("cost_vehicle_replacement", self.cost_vehicle_replacement),
+            ("insurance_premium", self.insurance_premium),
+            ("quick_commute_bonus", self.quick_commute_bonus),
+        ] {
+            ensure!(cost >= 0, "{} must not be negative (got {})", name, cost);
+        }
+        ensure!(
+            self.commute_healthy_distance > 0.0,
+            "commute_healthy_distance must be positive (got {})",
+            self.commute_healthy_distance
+        );
+        ensure!(
+            self.delivery_deadline_secs > 0.0,
+            "delivery_deadline_secs must be positive (got {})",
+            self.delivery_deadline_secs
+        );
+        for (name, share) in [
+            (
+                "late_delivery_revenue_share",
+                self.late_delivery_revenue_share,
+            ),
+            ("min_on_time_rate", self.min_on_time_rate),
+        ] {
+            ensure!(
+                (0.0..=1.0).contains(&share),
+                "{} must be between 0 and 1 (got {})",
+                name,
+                share
+            );
+        }
+        ensure!(
+            self.short_commute_penalty >= 0,
+            "short_commute_penalty must not be negative (got {})",
+            self.short_commute_penalty
+        );
+        for (name, rate) in [
+            ("commute_time_allowance_secs", self.commute_time_allowance_secs),
+            (
+                "commute_time_penalty_per_sec",
+                self.commute_time_penalty_per_sec,
+            ),
+            (
+                "breakdowns_per_vehicle_hour",
+                self.breakdowns_per_vehicle_hour,
+            ),
+            (
+                "collisions_per_congested_road_hour",
+                self.collisions_per_congested_road_hour,
+            ),
+        ] {
+            ensure!(rate >= 0.0, "{} must not be negative (got {})", name, rate);
+        }
+        ensure!(
+            self.insurance_period_secs > 0.0,
+            "insurance_period_secs must be positive (got {})",
+            self.insurance_period_secs
+        );
+        ensure!(
+            self.goal_deliveries > 0,
+            "goal_deliveries must be at least 1"
+        );
+        if let Some(ramp) = &self.demand_ramp {
+            ramp.validate()?;
+        }
+        if let Some(pricing) = &self.market_pricing {
+            pricing.validate()?;
+        }
+        if let Some(mix) = &self.driver_mix {
+            mix.validate()?;
+        }
+        if let Some(goal) = &self.speed_goal {
+            goal.validate()?;
+        }
+        if let Some(upgrades) = &self.upgrades {
+            upgrades.validate()?;
+        }
+        if let Some(schedule) = &self.event_schedule {
+            schedule.validate()?;
+        }
+        for road in &self.contraflow {
+            road.validate()?;
+        }
+        for alarm in &self.alarms {
+            alarm.validate()?;
+        }
+        for weight in &self.demand_weights {
+            weight.validate()?;
+        }
+        self.recap.validate()?;
+        Ok(())
+    }
+}
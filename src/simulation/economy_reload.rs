@@ -0,0 +1,121 @@
+//! Reloading an edited economy into a running game
+//!
+//! Balancing an economy goes quicker when an edit to its TOML file shows up
+//! in the game already running instead of needing a new one. An
+//! [`EconomyWatcher`] notices when the file is saved, and
+//! [`SimWorld::reload_economy`] swaps the new values into the game.
+//!
+//! Costs, revenues, goals, incident rates, the demand ramp (and so how often
+//! houses and shops send vehicles out) and the intersection rules are read as
+//! the game runs, so changes to them take effect at once. A new driver mix
+//! applies to the drivers who set off after it. The starting budget
+//! only matters when a game starts, and an event schedule is worked through
+//! in order from the start, so changes to those wait for the next game.
+
+use anyhow::{Context, Result};
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use super::economy::EconomyConfig;
+use super::world::SimWorld;
+
+/// Economy fields whose changes only take effect in a new game
+pub const NEXT_GAME_ECONOMY_FIELDS: [&str; 2] = ["starting_budget", "event_schedule"];
+
+/// Watches an economy file for saved changes
+pub struct EconomyWatcher {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+}
+
+impl EconomyWatcher {
+    /// Watch the economy file at `path`, taking it as it is now as already
+    /// loaded
+    pub fn new(path: PathBuf) -> Self {
+        let modified = modified_time(&path);
+        Self { path, modified }
+    }
+
+    /// The file being watched
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The economy in the file, if it has been saved since the last check
+    ///
+    /// A file that is missing or part way through being written is skipped
+    /// until it is saved again.
+    pub fn poll(&mut self) -> Option<Result<EconomyConfig>> {
+        let modified = modified_time(&self.path)?;
+        if self.modified == Some(modified) {
+            return None;
+        }
+        self.modified = Some(modified);
+        Some(EconomyConfig::load(&self.path))
+    }
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
+
+/// Which economy fields a reload changed
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EconomyReload {
+    /// Fields whose new values are in effect
+    pub applied: Vec<String>,
+    /// Fields that changed but only take effect in a new game (see
+    /// [`NEXT_GAME_ECONOMY_FIELDS`])
+    pub deferred: Vec<String>,
+}
+
+impl EconomyReload {
+    /// Whether nothing changed
+    pub fn is_empty(&self) -> bool {
+        self.applied.is_empty() && self.deferred.is_empty()
+    }
+}
+
+/// Top-level fields that differ between two economies, in name order
+fn changed_fields(old: &EconomyConfig, new: &EconomyConfig) -> Result<Vec<String>> {
+    let old: toml::Table = toml::from_str(&old.to_toml_string()?)?;
+    let new: toml::Table = toml::from_str(&new.to_toml_string()?)?;
+    // Fields left unset (no demand ramp, say) are missing from one side
+    let names: BTreeSet<&String> = old.keys().chain(new.keys()).collect();
+    Ok(names
+        .into_iter()
+        .filter(|name| old.get(*name) != new.get(*name))
+        .cloned()
+        .collect())
+}
+
+impl SimWorld {
+    /// Put an edited economy into effect in the running game
+    ///
+    /// Changes to the [fields read only at the start of a
+    /// game](NEXT_GAME_ECONOMY_FIELDS) are kept back; the rest apply from the
+    /// next tick.
+    pub fn reload_economy(&mut self, economy: EconomyConfig) -> Result<EconomyReload> {
+        economy.validate()?;
+        let game_state = self
+            .game_state
+            .as_mut()
+            .context("No game is running to reload the economy into")?;
+        let mut reload = EconomyReload::default();
+        for field in changed_fields(&game_state.economy, &economy)? {
+            if NEXT_GAME_ECONOMY_FIELDS.contains(&field.as_str()) {
+                reload.deferred.push(field);
+            } else {
+                reload.applied.push(field);
+            }
+        }
+        let economy = EconomyConfig {
+            starting_budget: game_state.economy.starting_budget,
+            event_schedule: game_state.economy.event_schedule.take(),
+            ..economy
+        };
+        game_state.economy = economy;
+        Ok(reload)
+    }
+}
@@ -0,0 +1,246 @@
+//! Terrain elevation
+//!
+//! A world can have a [`Heightmap`]: a grid of ground heights that new
+//! intersections are placed on. A road's grade is its rise over its run.
+//! Roads cut into slopes steeper than [`STEEP_GRADE`] cost more to build,
+//! and vehicles slow down driving uphill (see [`slope_speed_factor`]), so
+//! routes round a hill can beat routes over it. Worlds without a heightmap
+//! are flat.
+
+#[cfg(feature = "serialization")]
+use anyhow::Context;
+use anyhow::{ensure, Result};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+#[cfg(feature = "serialization")]
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "serialization")]
+use std::path::Path;
+
+use super::types::Position;
+use super::world::SimWorld;
+
+/// Grade (rise over run) above which roads cost more to build
+pub const STEEP_GRADE: f32 = 0.05;
+
+/// Extra share of a road's cost per unit of grade beyond [`STEEP_GRADE`]
+pub const SLOPE_COST_PER_GRADE: f32 = 10.0;
+
+/// Share of its speed a vehicle loses per unit of uphill grade
+pub const UPHILL_SLOWDOWN_PER_GRADE: f32 = 3.0;
+
+/// Least share of its speed a vehicle keeps on the steepest climb
+pub const MIN_UPHILL_SPEED_FACTOR: f32 = 0.4;
+
+/// Spacing of the samples in generated hills
+const HILLS_CELL_SIZE: f32 = 4.0;
+
+/// Share of its cost a road over this steepest grade costs
+pub fn slope_cost_factor(grade: f32) -> f32 {
+    1.0 + (grade.abs() - STEEP_GRADE).max(0.0) * SLOPE_COST_PER_GRADE
+}
+
+/// Share of its speed a vehicle keeps on a road with this grade; only
+/// climbing slows vehicles down
+pub fn slope_speed_factor(grade: f32) -> f32 {
+    (1.0 - grade.max(0.0) * UPHILL_SLOWDOWN_PER_GRADE).max(MIN_UPHILL_SPEED_FACTOR)
+}
+
+/// Ground heights sampled on a square grid, aligned with the world's axes
+///
+/// Between samples the height is interpolated; beyond the edges it is the
+/// height at the nearest edge.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+pub struct Heightmap {
+    /// World X of the first column
+    pub min_x: f32,
+    /// World Z of the first row
+    pub min_z: f32,
+    /// Distance between neighbouring samples
+    pub cell_size: f32,
+    /// Samples along X
+    pub columns: usize,
+    /// Samples along Z
+    pub rows: usize,
+    /// `rows * columns` heights, row by row
+    pub heights: Vec<f32>,
+}
+
+impl Heightmap {
+    /// Rolling hills up to `max_height` high covering a square of side
+    /// `size` from (`min_x`, `min_z`), the same for the same seed
+    pub fn hills(seed: u64, min_x: f32, min_z: f32, size: f32, max_height: f32) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+        // A few overlapping swells running in random directions
+        let waves: Vec<(f32, f32, f32)> = (0..3)
+            .map(|_| {
+                let direction = rng.random_range(0.0..std::f32::consts::TAU);
+                let wavelength = rng.random_range(30.0..80.0);
+                let phase = rng.random_range(0.0..std::f32::consts::TAU);
+                let k = std::f32::consts::TAU / wavelength;
+                (direction.cos() * k, direction.sin() * k, phase)
+            })
+            .collect();
+
+        let samples = (size / HILLS_CELL_SIZE).ceil() as usize + 1;
+        let mut heights = Vec::with_capacity(samples * samples);
+        for row in 0..samples {
+            for col in 0..samples {
+                let x = min_x + col as f32 * HILLS_CELL_SIZE;
+                let z = min_z + row as f32 * HILLS_CELL_SIZE;
+                let swell: f32 = waves
+                    .iter()
+                    .map(|(kx, kz, phase)| (kx * x + kz * z + phase).sin())
+                    .sum();
+                // Average the swells (-1..1) and scale that to 0..max_height
+                heights.push((swell / waves.len() as f32 + 1.0) / 2.0 * max_height);
+            }
+        }
+        Self {
+            min_x,
+            min_z,
+            cell_size: HILLS_CELL_SIZE,
+            columns: samples,
+            rows: samples,
+            heights,
+        }
+    }
+
+    /// Check the grid is complete
+    pub fn validate(&self) -> Result<()> {
+        ensure!(
+            self.columns >= 2 && self.rows >= 2,
+            "A heightmap needs at least 2x2 samples (got {}x{})",
+            self.columns,
+            self.rows
+        );
+        ensure!(
+            self.cell_size > 0.0,
+            "cell_size must be positive (got {})",
+            self.cell_size
+        );
+        ensure!(
+            self.heights.len() == self.columns * self.rows,
+            "A {}x{} heightmap needs {} heights (got {})",
+            self.columns,
+            self.rows,
+            self.columns * self.rows,
+            self.heights.len()
+        );
+        ensure!(
+            self.heights.iter().all(|height| height.is_finite()),
+            "Heights must be finite numbers"
+        );
+        Ok(())
+    }
+
+    /// Parse and validate a heightmap from TOML text
+    #[cfg(feature = "serialization")]
+    pub fn from_toml_str(text: &str) -> Result<Self> {
+        let heightmap: Self = toml::from_str(text).context("Failed to parse heightmap TOML")?;
+        heightmap.validate()?;
+        Ok(heightmap)
+    }
+
+    /// Load a heightmap from a TOML file
+    #[cfg(feature = "serialization")]
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read heightmap file {}", path.display()))?;
+        Self::from_toml_str(&text)
+            .with_context(|| format!("Invalid heightmap file {}", path.display()))
+    }
+
+    /// World X and Z of the last sample
+    pub fn max_corner(&self) -> (f32, f32) {
+        (
+            self.min_x + (self.columns - 1) as f32 * self.cell_size,
+            self.min_z + (self.rows - 1) as f32 * self.cell_size,
+        )
+    }
+
+    /// Height of the sample in a row and column
+    pub fn sample(&self, row: usize, col: usize) -> f32 {
+        self.heights[row * self.columns + col]
+    }
+
+    /// Ground height at a point
+    pub fn height_at(&self, x: f32, z: f32) -> f32 {
+        let col = ((x - self.min_x) / self.cell_size).clamp(0.0, (self.columns - 1) as f32);
+        let row = ((z - self.min_z) / self.cell_size).clamp(0.0, (self.rows - 1) as f32);
+        let (col0, row0) = (col.floor() as usize, row.floor() as usize);
+        let (col1, row1) = (
+            (col0 + 1).min(self.columns - 1),
+            (row0 + 1).min(self.rows - 1),
+        );
+        let (tx, tz) = (col - col0 as f32, row - row0 as f32);
+        let near = self.sample(row0, col0) * (1.0 - tx) + self.sample(row0, col1) * tx;
+        let far = self.sample(row1, col0) * (1.0 - tx) + self.sample(row1, col1) * tx;
+        near * (1.0 - tz) + far * tz
+    }
+
+    /// Steepest grade of the ground under a straight road from `start` to
+    /// `end`, sampled every half cell
+    pub fn steepest_grade(&self, start: &Position, end: &Position) -> f32 {
+        let run = ((end.x - start.x).powi(2) + (end.z - start.z).powi(2)).sqrt();
+        if run <= 0.0 {
+            return 0.0;
+        }
+        let steps = (run / (self.cell_size / 2.0)).ceil().max(1.0) as usize;
+        let step_run = run / steps as f32;
+        let mut previous = self.height_at(start.x, start.z);
+        let mut steepest: f32 = 0.0;
+        for step in 1..=steps {
+            let point = start.lerp(end, step as f32 / steps as f32);
+            let height = self.height_at(point.x, point.z);
+            steepest = steepest.max((height - previous).abs() / step_run);
+            previous = height;
+        }
+        steepest
+    }
+}
+
+impl SimWorld {
+    /// Give the world terrain, moving intersections already built on to it
+    pub fn set_heightmap(&mut self, heightmap: Heightmap) -> Result<()> {
+        heightmap.validate()?;
+        self.heightmap = Some(heightmap);
+        let positions: Vec<_> = self
+            .intersections
+            .values()
+            .map(|intersection| (intersection.id, intersection.position))
+            .collect();
+        for (intersection_id, position) in positions {
+            self.move_intersection(intersection_id, position)?;
+        }
+        self.changes.mark_layout();
+        Ok(())
+    }
+
+    /// Ground height at a point; 0 without terrain
+    pub fn ground_height(&self, x: f32, z: f32) -> f32 {
+        self.heightmap
+            .as_ref()
+            .map_or(0.0, |heightmap| heightmap.height_at(x, z))
+    }
+
+    /// A position moved up or down on to the ground, if the world has terrain
+    pub fn on_ground(&self, position: Position) -> Position {
+        match &self.heightmap {
+            Some(heightmap) => Position {
+                y: heightmap.height_at(position.x, position.z),
+                ..position
+            },
+            None => position,
+        }
+    }
+
+    /// Share of its cost a road from `start` to `end` costs for the slopes
+    /// it cuts into
+    pub fn slope_cost_factor(&self, start: &Position, end: &Position) -> f32 {
+        self.heightmap.as_ref().map_or(1.0, |heightmap| {
+            slope_cost_factor(heightmap.steepest_grade(start, end))
+        })
+    }
+}
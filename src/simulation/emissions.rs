@@ -0,0 +1,198 @@
+//! Fuel use and CO2 emissions
+//!
+//! Every vehicle burns fuel as it drives: a steady amount per distance at
+//! speed, more to speed up again after slowing or stopping, and a trickle
+//! while it idles in a queue or at a light. Stalled vehicles and vehicles
+//! aboard a ferry have their engines off. Each trip's fuel and idle time
+//! are counted with its trip statistics when it completes, which gives a
+//! city's emissions as another measure of how well it is laid out: fewer
+//! stops and shorter routes burn less.
+//!
+//! The city is built at toy scale, so a unit of distance counts as
+//! [`WORLD_UNIT_METRES`] metres and figures come out in litres and
+//! kilograms of CO2 as for real vehicles of each type.
+
+#[cfg(feature = "stats")]
+use std::fmt::Write as _;
+#[cfg(feature = "stats")]
+use std::path::Path;
+
+#[cfg(feature = "stats")]
+use anyhow::{Context, Result};
+
+use super::car::SimCar;
+use super::types::VehicleType;
+#[cfg(feature = "stats")]
+use super::world::SimWorld;
+
+/// Metres a unit of distance in the world counts as
+pub const WORLD_UNIT_METRES: f32 = 5.0;
+
+/// Energy an engine puts into moving the vehicle per litre burned, in
+/// joules (about a quarter of what the fuel holds)
+const USEFUL_JOULES_PER_LITRE: f32 = 8.75e6;
+
+/// Header line of the emissions CSV
+#[cfg(feature = "stats")]
+pub const EMISSIONS_CSV_HEADER: &str =
+    "purpose,vehicle_type,trips,fuel_litres,co2_kg,idle_secs,trip_secs";
+
+/// How a type of vehicle burns fuel
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FuelProfile {
+    /// Litres per kilometre driven at a steady speed
+    pub litres_per_km: f32,
+    /// Litres per hour standing with the engine running
+    pub idle_litres_per_hour: f32,
+    /// Weight to get moving when speeding up
+    pub mass_kg: f32,
+    /// CO2 given off per litre burned: petrol for cars, diesel otherwise
+    pub co2_kg_per_litre: f32,
+}
+
+impl FuelProfile {
+    /// Litres burned driving `distance` units in `secs` seconds, going from
+    /// `from_speed` to `to_speed` units per second
+    pub fn litres(&self, distance: f32, secs: f32, from_speed: f32, to_speed: f32) -> f32 {
+        let km = distance * WORLD_UNIT_METRES / 1000.0;
+        let cruising = km * self.litres_per_km;
+        let idling = if distance <= 0.0 {
+            secs / 3600.0 * self.idle_litres_per_hour
+        } else {
+            0.0
+        };
+        // Speeding up takes the kinetic energy gained; slowing down gives
+        // none back
+        let speed_gain = (to_speed * to_speed - from_speed * from_speed).max(0.0)
+            * WORLD_UNIT_METRES
+            * WORLD_UNIT_METRES;
+        let accelerating = 0.5 * self.mass_kg * speed_gain / USEFUL_JOULES_PER_LITRE;
+        cruising + idling + accelerating
+    }
+}
+
+impl VehicleType {
+    /// How vehicles of this type burn fuel
+    pub fn fuel_profile(self) -> FuelProfile {
+        let (litres_per_km, idle_litres_per_hour, mass_kg, co2_kg_per_litre) = match self {
+            VehicleType::Car => (0.07, 0.8, 1400.0, 2.31),
+            VehicleType::Truck => (0.30, 2.5, 10000.0, 2.68),
+            VehicleType::Ambulance => (0.18, 1.5, 4000.0, 2.68),
+            VehicleType::TowTruck => (0.25, 2.0, 7000.0, 2.68),
+            VehicleType::Van => (0.12, 1.2, 2500.0, 2.68),
+        };
+        FuelProfile {
+            litres_per_km,
+            idle_litres_per_hour,
+            mass_kg,
+            co2_kg_per_litre,
+        }
+    }
+}
+
+impl SimCar {
+    /// Count the fuel burned in an update that moved the vehicle `distance`
+    /// in `secs`, having been going at `from_speed`
+    pub(super) fn burn_fuel(&mut self, distance: f32, secs: f32, from_speed: f32) {
+        if distance <= 0.0 {
+            self.idle_secs += secs;
+        }
+        self.fuel_litres +=
+            self.vehicle_type
+                .fuel_profile()
+                .litres(distance, secs, from_speed, self.current_speed);
+    }
+
+    /// CO2 given off on this trip so far, in kilograms
+    pub fn co2_kg(&self) -> f32 {
+        self.fuel_litres * self.vehicle_type.fuel_profile().co2_kg_per_litre
+    }
+}
+
+/// Fuel and emissions of the city's completed trips
+#[cfg(feature = "stats")]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct CityEmissions {
+    pub trips: u32,
+    pub fuel_litres: f32,
+    pub co2_kg: f32,
+    /// Seconds vehicles stood with their engines running
+    pub idle_secs: f32,
+    /// Seconds the trips took in all
+    pub trip_secs: f32,
+}
+
+#[cfg(feature = "stats")]
+impl CityEmissions {
+    /// Mean CO2 per trip in kilograms (0 with no trips)
+    pub fn co2_per_trip(&self) -> f32 {
+        if self.trips == 0 {
+            0.0
+        } else {
+            self.co2_kg / self.trips as f32
+        }
+    }
+
+    /// Share of the time on the road spent idling, if any trips were made
+    pub fn idle_share(&self) -> Option<f32> {
+        (self.trip_secs > 0.0).then(|| self.idle_secs / self.trip_secs)
+    }
+}
+
+#[cfg(feature = "stats")]
+impl SimWorld {
+    /// Fuel and emissions of every trip completed so far
+    pub fn emissions(&self) -> CityEmissions {
+        let mut city = CityEmissions::default();
+        for stats in self.trip_stats.values() {
+            city.trips += stats.trips;
+            city.fuel_litres += stats.fuel_litres;
+            city.co2_kg += stats.co2_kg;
+            city.idle_secs += stats.idle_secs;
+            city.trip_secs += stats.total_secs;
+        }
+        city
+    }
+
+    /// Emissions as CSV: a row per trip purpose and vehicle type, as in
+    /// [`SimWorld::trip_statistics`], then the city's total
+    pub fn emissions_csv(&self) -> String {
+        let mut csv = format!("{}\n", EMISSIONS_CSV_HEADER);
+        let rows = self.trip_statistics().into_iter().map(|row| {
+            (
+                row.purpose.label().to_lowercase(),
+                format!("{:?}", row.vehicle_type).to_lowercase(),
+                row.stats.trips,
+                row.stats.fuel_litres,
+                row.stats.co2_kg,
+                row.stats.idle_secs,
+                row.stats.total_secs,
+            )
+        });
+        let city = self.emissions();
+        let total = (
+            "total".to_string(),
+            String::new(),
+            city.trips,
+            city.fuel_litres,
+            city.co2_kg,
+            city.idle_secs,
+            city.trip_secs,
+        );
+        for (purpose, vehicle, trips, fuel, co2, idle, secs) in rows.chain([total]) {
+            // Writing to a String can't fail
+            let _ = writeln!(
+                csv,
+                "{},{},{},{:.3},{:.3},{:.1},{:.1}",
+                purpose, vehicle, trips, fuel, co2, idle, secs
+            );
+        }
+        csv
+    }
+
+    /// Write [`SimWorld::emissions_csv`] to a file
+    pub fn save_emissions_csv(&self, path: &Path) -> Result<()> {
+        std::fs::write(path, self.emissions_csv())
+            .with_context(|| format!("Failed to write emissions to {}", path.display()))
+    }
+}
@@ -0,0 +1,577 @@
+//! Gym-style environment for training traffic-management agents
+//!
+//! [`TrafficEnv`] wraps a [`SimWorld`] in the familiar reinforcement-learning
+//! loop: [`TrafficEnv::reset`] starts an episode from a seed and
+//! [`TrafficEnv::step`] applies one action, runs the simulation for a fixed
+//! number of ticks and returns the next observation and the reward.
+//!
+//! Each episode is played on a square grid of intersections with buildings
+//! placed at random grid points. The agent's job is to connect them: actions
+//! build a road between two neighbouring grid points (or, if enabled, place a
+//! building or time a grid point's signal), and the reward is the change in
+//! money, so road costs count against revenue from worker trips and
+//! deliveries.
+//!
+//! Observations are flat `f32` vectors and actions are numbered, so agents
+//! that expect fixed-size spaces can use [`TrafficEnv::observation_size`],
+//! [`TrafficEnv::action_count`] and [`TrafficEnv::step_index`] directly.
+
+use anyhow::{ensure, Result};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+
+use super::autopilot::AUTOPILOT_GRID_SPACING;
+use super::content::BuildingKind;
+use super::economy::EconomyConfig;
+use super::elevation::Heightmap;
+use super::game_state::GameState;
+use super::signal::{MAX_PHASE_SECS, MIN_PHASE_SECS};
+use super::types::{IntersectionId, Position};
+use super::world::SimWorld;
+
+/// Building kinds in the order they appear in observations and actions
+const BUILDING_KINDS: [BuildingKind; 3] = [
+    BuildingKind::Apartment,
+    BuildingKind::Factory,
+    BuildingKind::Shop,
+];
+
+/// Which features make up an observation, in this order
+#[derive(Debug, Clone, PartialEq)]
+pub struct ObservationConfig {
+    /// 1.0 for each grid edge with a road, otherwise 0.0
+    pub roads: bool,
+    /// Traffic density (cars per unit length) of each grid edge's roads
+    pub road_densities: bool,
+    /// One-hot apartment, factory and shop flags for each grid point
+    pub buildings: bool,
+    /// Money held
+    pub money: bool,
+}
+
+impl Default for ObservationConfig {
+    fn default() -> Self {
+        Self {
+            roads: true,
+            road_densities: true,
+            buildings: true,
+            money: true,
+        }
+    }
+}
+
+/// Which actions the agent may take, besides waiting
+#[derive(Debug, Clone, PartialEq)]
+pub struct ActionConfig {
+    /// Build a two-way road along a grid edge
+    pub build_roads: bool,
+    /// Place an apartment, factory or shop on a free grid point
+    pub build_buildings: bool,
+    /// Phase lengths in seconds the agent may give a grid point's signal,
+    /// each a separate action; none leaves signals alone
+    pub signal_timings: Vec<f32>,
+}
+
+impl Default for ActionConfig {
+    fn default() -> Self {
+        Self {
+            build_roads: true,
+            build_buildings: false,
+            signal_timings: Vec::new(),
+        }
+    }
+}
+
+/// Settings for a [`TrafficEnv`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnvConfig {
+    /// Number of grid points along each side of the map
+    pub grid_size: usize,
+    /// Distance between neighbouring grid points
+    pub grid_spacing: f32,
+    /// Height of the rolling hills raised for each episode; 0 keeps the
+    /// ground flat
+    pub hills: f32,
+    /// Apartments, factories and shops placed at random grid points on reset
+    pub starting_buildings: [usize; 3],
+    /// Simulation ticks run per step
+    pub ticks_per_step: u32,
+    /// Time delta per tick in seconds
+    pub delta: f32,
+    /// Steps before an episode is truncated
+    pub max_steps: u32,
+    pub economy: EconomyConfig,
+    pub observation: ObservationConfig,
+    pub actions: ActionConfig,
+}
+
+impl Default for EnvConfig {
+    fn default() -> Self {
+        Self {
+            grid_size: 5,
+            grid_spacing: AUTOPILOT_GRID_SPACING,
+            hills: 0.0,
+            starting_buildings: [4, 2, 2],
+            ticks_per_step: 50,
+            delta: 0.1,
+            max_steps: 240,
+            economy: EconomyConfig::default(),
+            observation: ObservationConfig::default(),
+            actions: ActionConfig::default(),
+        }
+    }
+}
+
+impl EnvConfig {
+    /// Check that the settings describe a playable environment
+    pub fn validate(&self) -> Result<()> {
+        ensure!(
+            self.grid_size >= 2,
+            "grid_size must be at least 2 (got {})",
+            self.grid_size
+        );
+        ensure!(
+            self.grid_spacing > 0.0,
+            "grid_spacing must be positive (got {})",
+            self.grid_spacing
+        );
+        ensure!(
+            self.hills >= 0.0,
+            "hills must not be negative (got {})",
+            self.hills
+        );
+        let buildings: usize = self.starting_buildings.iter().sum();
+        ensure!(
+            buildings <= self.grid_size * self.grid_size,
+            "{} starting buildings don't fit on a {}x{} grid",
+            buildings,
+            self.grid_size,
+            self.grid_size
+        );
+        ensure!(self.ticks_per_step > 0, "ticks_per_step must be at least 1");
+        ensure!(
+            self.delta > 0.0,
+            "delta must be positive (got {})",
+            self.delta
+        );
+        ensure!(self.max_steps > 0, "max_steps must be at least 1");
+        for timing in &self.actions.signal_timings {
+            ensure!(
+                (MIN_PHASE_SECS..=MAX_PHASE_SECS).contains(timing),
+                "signal_timings must be {}-{}s (got {})",
+                MIN_PHASE_SECS,
+                MAX_PHASE_SECS,
+                timing
+            );
+        }
+        self.economy.validate()
+    }
+}
+
+/// An action the agent can take
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    /// Build nothing and let the simulation run
+    Wait,
+    /// Build a two-way road along a grid edge (see [`TrafficEnv::edges`])
+    BuildRoad { edge: usize },
+    /// Place a building on a grid point (numbered row by row)
+    BuildBuilding { point: usize, kind: BuildingKind },
+    /// Give every phase of a grid point's signal one of
+    /// [`ActionConfig::signal_timings`], adding a signal with a phase per
+    /// road in if it has none
+    SetSignalTiming { point: usize, timing: usize },
+}
+
+/// Outcome of one [`TrafficEnv::step`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnvStep {
+    pub observation: Vec<f32>,
+    /// Change in money over the step, including what the action cost
+    pub reward: f32,
+    /// The game was won or lost
+    pub terminated: bool,
+    /// The episode reached [`EnvConfig::max_steps`]
+    pub truncated: bool,
+    /// Whether the action could be carried out; an action that can't (an
+    /// existing road, an occupied grid point, a signal with no roads in,
+    /// lack of funds) acts as a wait
+    pub action_applied: bool,
+}
+
+impl EnvStep {
+    /// Whether the episode is over and the environment needs a reset
+    pub fn done(&self) -> bool {
+        self.terminated || self.truncated
+    }
+}
+
+/// Gym-style reinforcement-learning environment around the simulation
+pub struct TrafficEnv {
+    config: EnvConfig,
+    world: SimWorld,
+    /// Intersection at each grid point, row by row
+    points: Vec<IntersectionId>,
+    /// Pairs of neighbouring grid points, indexing `points`
+    edges: Vec<(usize, usize)>,
+    steps: u32,
+}
+
+impl TrafficEnv {
+    /// Create an environment, ready for its first episode with seed 0
+    pub fn new(config: EnvConfig) -> Result<Self> {
+        config.validate()?;
+        let size = config.grid_size;
+        let mut edges = Vec::new();
+        for row in 0..size {
+            for col in 0..size {
+                let point = row * size + col;
+                if col + 1 < size {
+                    edges.push((point, point + 1));
+                }
+                if row + 1 < size {
+                    edges.push((point, point + size));
+                }
+            }
+        }
+
+        let mut env = Self {
+            config,
+            world: SimWorld::new(),
+            points: Vec::new(),
+            edges,
+            steps: 0,
+        };
+        env.reset(0);
+        Ok(env)
+    }
+
+    /// Start a new episode and return its first observation
+    ///
+    /// The seed decides where the starting buildings go and seeds the
+    /// simulation, so equal seeds and actions give equal episodes.
+    pub fn reset(&mut self, seed: u64) -> Vec<f32> {
+        let mut world = SimWorld::new_with_seed(seed);
+        world.game_state = Some(GameState::with_economy(self.config.economy.clone()));
+
+        let size = self.config.grid_size;
+        if self.config.hills > 0.0 {
+            // Cover the grid with a cell to spare all round
+            let spacing = self.config.grid_spacing;
+            world.heightmap = Some(Heightmap::hills(
+                seed,
+                -spacing,
+                -spacing,
+                (size + 1) as f32 * spacing,
+                self.config.hills,
+            ));
+        }
+        self.points = (0..size * size)
+            .map(|point| {
+                let (row, col) = (point / size, point % size);
+                world.add_intersection(Position::new(
+                    col as f32 * self.config.grid_spacing,
+                    0.0,
+                    row as f32 * self.config.grid_spacing,
+                ))
+            })
+            .collect();
+
+        // Starting buildings are free, like the prebuilt test world
+        let mut free_points: Vec<usize> = (0..self.points.len()).collect();
+        free_points.shuffle(&mut StdRng::seed_from_u64(seed));
+        let mut free_points = free_points.into_iter();
+        for (kind, count) in BUILDING_KINDS
+            .into_iter()
+            .zip(self.config.starting_buildings)
+        {
+            for point in free_points.by_ref().take(count) {
+                let intersection_id = self.points[point];
+                match kind {
+                    BuildingKind::Apartment => {
+                        world.add_apartment(intersection_id);
+                    }
+                    BuildingKind::Factory => {
+                        world.add_factory(intersection_id);
+                    }
+                    BuildingKind::Shop => {
+                        world.add_shop(intersection_id);
+                    }
+                }
+            }
+        }
+
+        self.world = world;
+        self.steps = 0;
+        self.observation()
+    }
+
+    /// Apply an action, run the simulation for a step and report the result
+    pub fn step(&mut self, action: Action) -> EnvStep {
+        let money_before = self.money();
+        let action_applied = self.apply(action);
+        for _ in 0..self.config.ticks_per_step {
+            self.world.tick(self.config.delta);
+        }
+        self.steps += 1;
+
+        let game_state = self.world.game_state.as_ref();
+        EnvStep {
+            observation: self.observation(),
+            reward: (self.money() - money_before) as f32,
+            terminated: game_state
+                .is_some_and(|game_state| game_state.is_won || game_state.is_lost),
+            truncated: self.steps >= self.config.max_steps,
+            action_applied,
+        }
+    }
+
+    /// [`Self::step`] with an action given by its number
+    pub fn step_index(&mut self, index: usize) -> Result<EnvStep> {
+        let action = self.action(index).ok_or_else(|| {
+            anyhow::anyhow!(
+                "Action {} is out of range (there are {})",
+                index,
+                self.action_count()
+            )
+        })?;
+        Ok(self.step(action))
+    }
+
+    /// Number of actions; action 0 is [`Action::Wait`]
+    pub fn action_count(&self) -> usize {
+        let mut count = 1;
+        if self.config.actions.build_roads {
+            count += self.edges.len();
+        }
+        if self.config.actions.build_buildings {
+            count += self.points.len() * BUILDING_KINDS.len();
+        }
+        count += self.points.len() * self.config.actions.signal_timings.len();
+        count
+    }
+
+    /// The action with a given number, if there is one
+    pub fn action(&self, index: usize) -> Option<Action> {
+        if index == 0 {
+            return Some(Action::Wait);
+        }
+        let mut index = index - 1;
+        if self.config.actions.build_roads {
+            if index < self.edges.len() {
+                return Some(Action::BuildRoad { edge: index });
+            }
+            index -= self.edges.len();
+        }
+        if self.config.actions.build_buildings {
+            if index < self.points.len() * BUILDING_KINDS.len() {
+                return Some(Action::BuildBuilding {
+                    point: index / BUILDING_KINDS.len(),
+                    kind: BUILDING_KINDS[index % BUILDING_KINDS.len()],
+                });
+            }
+            index -= self.points.len() * BUILDING_KINDS.len();
+        }
+        let timings = self.config.actions.signal_timings.len();
+        if index < self.points.len() * timings {
+            return Some(Action::SetSignalTiming {
+                point: index / timings,
+                timing: index % timings,
+            });
+        }
+        None
+    }
+
+    /// Length of every observation
+    pub fn observation_size(&self) -> usize {
+        let observation = &self.config.observation;
+        let mut size = 0;
+        if observation.roads {
+            size += self.edges.len();
+        }
+        if observation.road_densities {
+            size += self.edges.len();
+        }
+        if observation.buildings {
+            size += self.points.len() * BUILDING_KINDS.len();
+        }
+        if observation.money {
+            size += 1;
+        }
+        size
+    }
+
+    /// Observation of the current state, laid out as configured
+    pub fn observation(&self) -> Vec<f32> {
+        let config = &self.config.observation;
+        let network = &self.world.road_network;
+        let mut observation = Vec::with_capacity(self.observation_size());
+
+        if config.roads {
+            observation.extend(self.edges.iter().map(|&(a, b)| {
+                let built = network
+                    .find_road_between(self.points[a], self.points[b])
+                    .is_ok();
+                if built {
+                    1.0
+                } else {
+                    0.0
+                }
+            }));
+        }
+        if config.road_densities {
+            observation.extend(self.edges.iter().map(|&(a, b)| {
+                [(a, b), (b, a)]
+                    .into_iter()
+                    .filter_map(|(from, to)| {
+                        network
+                            .find_road_between(self.points[from], self.points[to])
+                            .ok()
+                    })
+                    .map(|road_id| network.calculate_traffic_density(road_id))
+                    .sum::<f32>()
+            }));
+        }
+        if config.buildings {
+            for &intersection_id in &self.points {
+                let building = self.building_at(intersection_id);
+                observation.extend(BUILDING_KINDS.map(|kind| {
+                    if building == Some(kind) {
+                        1.0
+                    } else {
+                        0.0
+                    }
+                }));
+            }
+        }
+        if config.money {
+            observation.push(self.money() as f32);
+        }
+        observation
+    }
+
+    /// Grid edges as pairs of grid points (numbered row by row), in the
+    /// order used by [`Action::BuildRoad`] and observations
+    pub fn edges(&self) -> &[(usize, usize)] {
+        &self.edges
+    }
+
+    pub fn config(&self) -> &EnvConfig {
+        &self.config
+    }
+
+    /// The simulation being played, e.g. for rendering
+    pub fn world(&self) -> &SimWorld {
+        &self.world
+    }
+
+    /// Steps taken this episode
+    pub fn steps(&self) -> u32 {
+        self.steps
+    }
+
+    fn money(&self) -> i32 {
+        self.world
+            .game_state
+            .as_ref()
+            .map_or(0, |game_state| game_state.money)
+    }
+
+    /// Kind of building on an intersection, if any
+    fn building_at(&self, intersection_id: IntersectionId) -> Option<BuildingKind> {
+        if self
+            .world
+            .apartments
+            .values()
+            .any(|a| a.intersection_id == intersection_id)
+        {
+            Some(BuildingKind::Apartment)
+        } else if self
+            .world
+            .factories
+            .values()
+            .any(|f| f.intersection_id == intersection_id)
+        {
+            Some(BuildingKind::Factory)
+        } else if self
+            .world
+            .shops
+            .values()
+            .any(|s| s.intersection_id == intersection_id)
+        {
+            Some(BuildingKind::Shop)
+        } else {
+            None
+        }
+    }
+
+    /// Carry out an action, returning whether it could be
+    fn apply(&mut self, action: Action) -> bool {
+        match action {
+            Action::Wait => true,
+            Action::BuildRoad { edge } => {
+                if !self.config.actions.build_roads {
+                    return false;
+                }
+                let Some(&(a, b)) = self.edges.get(edge) else {
+                    return false;
+                };
+                let (start, end) = (self.points[a], self.points[b]);
+                if self
+                    .world
+                    .road_network
+                    .find_road_between(start, end)
+                    .is_ok()
+                {
+                    return false;
+                }
+                matches!(self.world.try_add_two_way_road(start, end), Ok(Some(_)))
+            }
+            Action::BuildBuilding { point, kind } => {
+                if !self.config.actions.build_buildings {
+                    return false;
+                }
+                let Some(&intersection_id) = self.points.get(point) else {
+                    return false;
+                };
+                if self.building_at(intersection_id).is_some() {
+                    return false;
+                }
+                match kind {
+                    BuildingKind::Apartment => {
+                        self.world.try_add_apartment(intersection_id).is_some()
+                    }
+                    BuildingKind::Factory => self.world.try_add_factory(intersection_id).is_some(),
+                    BuildingKind::Shop => self.world.try_add_shop(intersection_id).is_some(),
+                }
+            }
+            Action::SetSignalTiming { point, timing } => {
+                let (Some(&intersection_id), Some(&duration)) = (
+                    self.points.get(point),
+                    self.config.actions.signal_timings.get(timing),
+                ) else {
+                    return false;
+                };
+                let mut phases = match self
+                    .world
+                    .intersections
+                    .get(&intersection_id)
+                    .and_then(|intersection| intersection.signal.as_ref())
+                {
+                    Some(signal) => signal.phases().to_vec(),
+                    None => self.world.default_signal_phases(intersection_id),
+                };
+                if phases.is_empty() {
+                    return false;
+                }
+                for phase in &mut phases {
+                    phase.duration = duration;
+                }
+                self.world
+                    .set_signal(intersection_id, Some(phases))
+                    .is_ok()
+            }
+        }
+    }
+}
@@ -0,0 +1,133 @@
+//! Travel time estimates
+//!
+//! An estimate drives each road of a route at the vehicle's speed, slowed by
+//! hills and by the traffic on the road now (the same congestion multiplier
+//! pathfinding weighs roads by), and allows [`ETA_INTERSECTION_SECS`] at
+//! each intersection for waiting to get across, longer for vehicles that
+//! wait for longer gaps, plus the time lost getting back up to speed (see
+//! [`super::handling`]). A ferry crossing takes the ferry's speed plus half
+//! a dock's wait.
+//!
+//! Estimates guide dispatch and route previews. They don't foresee traffic
+//! building up on the way, so busy routes tend to take a little longer.
+
+use super::elevation::slope_speed_factor;
+use super::ferry::{FERRY_DOCK_SECS, FERRY_SPEED};
+use super::fleet::wear_speed_factor;
+use super::road_network::SimRoadNetwork;
+use super::types::{CarId, IntersectionId, RoadKind, SimRoad, VehicleType};
+use super::world::SimWorld;
+
+/// Seconds allowed at each intersection on a route for waiting to cross
+pub const ETA_INTERSECTION_SECS: f32 = 1.0;
+
+/// A route found for a preview or a dispatch, with its estimated travel time
+#[derive(Debug, Clone, PartialEq)]
+pub struct RouteEstimate {
+    /// Intersections after the start, as [`SimRoadNetwork::find_path`] gives
+    /// them
+    pub path: Vec<IntersectionId>,
+    /// Length of the route
+    pub distance: f32,
+    /// Estimated seconds to drive it
+    pub secs: f32,
+}
+
+impl SimRoadNetwork {
+    /// Estimated seconds to drive `path` from `start` at `speed` under the
+    /// current traffic, allowing `intersection_secs` at each intersection,
+    /// or `None` if the path doesn't follow roads
+    pub fn estimate_travel_secs(
+        &self,
+        start: IntersectionId,
+        path: &[IntersectionId],
+        speed: f32,
+        intersection_secs: f32,
+    ) -> Option<f32> {
+        let mut from = start;
+        let mut secs = 0.0;
+        for &to in path {
+            let road_id = self.find_road_between(from, to).ok()?;
+            let road = self.get_road(road_id)?;
+            secs += self.road_secs(road, road.length, speed) + intersection_secs;
+            from = to;
+        }
+        Some(secs)
+    }
+
+    /// Estimated seconds to drive `distance` along a road at `speed`
+    fn road_secs(&self, road: &SimRoad, distance: f32, speed: f32) -> f32 {
+        let secs = free_flow_secs(road, distance, speed);
+        if road.kind == RoadKind::Ferry {
+            secs
+        } else {
+            secs * self.congestion_multiplier(road.id)
+        }
+    }
+}
+
+/// Seconds to drive `distance` along a road at `speed` with no traffic
+pub(super) fn free_flow_secs(road: &SimRoad, distance: f32, speed: f32) -> f32 {
+    if road.kind == RoadKind::Ferry {
+        return distance / FERRY_SPEED + FERRY_DOCK_SECS / 2.0;
+    }
+    let speed = (speed * slope_speed_factor(road.grade)).max(f32::EPSILON);
+    distance / speed
+}
+
+impl SimWorld {
+    /// Seconds allowed for a vehicle of `vehicle_type` going at `speed` to
+    /// get across each intersection and back up to speed
+    pub fn intersection_allowance(&self, vehicle_type: VehicleType, speed: f32) -> f32 {
+        let handling = self.handling.get(vehicle_type);
+        ETA_INTERSECTION_SECS * handling.gap_factor + handling.launch_secs(speed)
+    }
+
+    /// Find a route for a vehicle type and estimate how long a vehicle of
+    /// that type typically takes to drive it now
+    pub fn estimate_route(
+        &self,
+        from: IntersectionId,
+        to: IntersectionId,
+        vehicle_type: VehicleType,
+    ) -> Option<RouteEstimate> {
+        let network = &self.road_network;
+        let path = network.find_path_for(from, to, vehicle_type)?;
+        let speed = vehicle_type.typical_speed();
+        let allowance = self.intersection_allowance(vehicle_type, speed);
+        let secs = network.estimate_travel_secs(from, &path, speed, allowance)?;
+        let mut distance = 0.0;
+        let mut at = from;
+        for &next in &path {
+            let road = network.find_road_between(at, next).ok()?;
+            distance += network.get_road(road)?.length;
+            at = next;
+        }
+        Some(RouteEstimate {
+            path,
+            distance,
+            secs,
+        })
+    }
+
+    /// Estimated seconds until a vehicle reaches its destination, from where
+    /// it is now
+    ///
+    /// `None` for a vehicle that is gone or still waiting for its route.
+    pub fn estimate_arrival_secs(&self, car_id: CarId) -> Option<f32> {
+        let car = self.cars.get(&car_id)?;
+        if car.awaiting_route.is_some() {
+            return None;
+        }
+        let network = &self.road_network;
+        let speed = car.speed * wear_speed_factor(car.wear);
+        let (&next, rest) = car.path.split_first()?;
+        let road = network.get_road(car.current_road)?;
+        let left = (road.length - car.distance_along_road.into_inner()).max(0.0);
+        let allowance = self.intersection_allowance(car.vehicle_type, speed);
+        let secs = network.road_secs(road, left, speed)
+            + allowance
+            + network.estimate_travel_secs(next, rest, speed, allowance)?;
+        Some(secs + car.stalled_secs)
+    }
+}
@@ -0,0 +1,177 @@
+//! Events raised as the simulation runs
+//!
+//! Rather than comparing the world's maps from one tick to the next to find
+//! what changed, a caller can hear about it: every vehicle that sets off or
+//! leaves the world, delivery made, worker clocked in or turned away, and
+//! intersection, road or building added or removed is raised as a
+//! [`SimEvent`]. There are two ways to hear them:
+//!
+//! - **Drain**: [`SimWorld::drain_events`] returns the events raised since
+//!   it was last called, oldest first. Nothing is queued until the first
+//!   call, so worlds nobody reads events from don't pile them up, and at
+//!   most [`MAX_SIM_EVENTS`] wait at once, dropping the oldest.
+//! - **Listen**: [`SimWorld::add_event_listener`] registers a closure called
+//!   with each event as it is raised, mid-tick. Listeners aren't saved.
+//!
+//! The front-end's [`RenderSync`](super::RenderSync) follows the same
+//! events: a vehicle that sets off or leaves, or a building added or
+//! removed, marks just its own visual (and a vehicle's home building) for
+//! the next sync. Roads and intersections reshape the visuals around them,
+//! so editing those still has the front-end check everything.
+
+use std::collections::VecDeque;
+
+use super::content::PlacedBuilding;
+use super::render::VisualId;
+use super::types::{ApartmentId, CarId, FactoryId, IntersectionId, RoadId, ShopId, VehicleType};
+use super::world::SimWorld;
+
+/// Most events kept waiting for [`SimWorld::drain_events`]
+pub const MAX_SIM_EVENTS: usize = 10_000;
+
+/// Something that happened in the simulation
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SimEvent {
+    /// A vehicle was spawned; it may wait at its start for room on its
+    /// first road or for a route
+    CarSpawned {
+        car: CarId,
+        vehicle_type: VehicleType,
+    },
+    /// A vehicle left the world: it finished its trip, was stranded, or was
+    /// removed along with its road
+    CarDespawned {
+        car: CarId,
+    },
+    /// A truck unloaded at a shop
+    DeliveryCompleted {
+        shop: ShopId,
+        factory: Option<FactoryId>,
+        late: bool,
+    },
+    /// A worker, driving or on foot, clocked in at a factory
+    WorkerArrived {
+        apartment: ApartmentId,
+        factory: FactoryId,
+    },
+    /// A factory turned a worker away, being on strike, full or without its
+    /// truck
+    FactoryRejectedWorker {
+        apartment: ApartmentId,
+        factory: FactoryId,
+    },
+    IntersectionAdded {
+        intersection: IntersectionId,
+    },
+    IntersectionRemoved {
+        intersection: IntersectionId,
+    },
+    /// A road was added; a two-way road raises one for each direction
+    RoadAdded {
+        road: RoadId,
+    },
+    RoadRemoved {
+        road: RoadId,
+    },
+    /// An apartment, factory or shop was built
+    BuildingAdded {
+        building: PlacedBuilding,
+    },
+    BuildingRemoved {
+        building: PlacedBuilding,
+    },
+}
+
+impl SimEvent {
+    /// Whether the event changed the layout of roads and buildings
+    pub fn is_layout(&self) -> bool {
+        matches!(
+            self,
+            SimEvent::IntersectionAdded { .. }
+                | SimEvent::IntersectionRemoved { .. }
+                | SimEvent::RoadAdded { .. }
+                | SimEvent::RoadRemoved { .. }
+                | SimEvent::BuildingAdded { .. }
+                | SimEvent::BuildingRemoved { .. }
+        )
+    }
+}
+
+impl From<PlacedBuilding> for VisualId {
+    fn from(building: PlacedBuilding) -> Self {
+        match building {
+            PlacedBuilding::Apartment(id) => VisualId::Apartment(id),
+            PlacedBuilding::Factory(id) => VisualId::Factory(id),
+            PlacedBuilding::Shop(id) => VisualId::Shop(id),
+        }
+    }
+}
+
+/// A closure called with each event as it is raised
+type EventListener = Box<dyn FnMut(&SimEvent) + Send + Sync>;
+
+/// Events waiting to be drained, and the listeners to tell
+#[derive(Default)]
+pub(super) struct EventLog {
+    /// Nothing is queued until events are first drained
+    queuing: bool,
+    pending: VecDeque<SimEvent>,
+    listeners: Vec<EventListener>,
+}
+
+impl EventLog {
+    /// Tell the listeners about an event and queue it for draining
+    fn raise(&mut self, event: SimEvent) {
+        for listener in &mut self.listeners {
+            listener(&event);
+        }
+        if self.queuing {
+            if self.pending.len() >= MAX_SIM_EVENTS {
+                self.pending.pop_front();
+            }
+            self.pending.push_back(event);
+        }
+    }
+
+    /// Events queued and the approximate bytes they hold
+    pub(super) fn memory_usage(&self) -> (usize, usize) {
+        (
+            self.pending.len(),
+            self.pending.capacity() * std::mem::size_of::<SimEvent>(),
+        )
+    }
+}
+
+impl SimWorld {
+    /// Take the events raised since the last call, oldest first, and keep
+    /// queuing them
+    ///
+    /// The first call starts the queue, so returns nothing.
+    pub fn drain_events(&mut self) -> Vec<SimEvent> {
+        let log = &mut self.sim_events;
+        log.queuing = true;
+        log.pending.drain(..).collect()
+    }
+
+    /// Call `listener` with every event from now on, as it is raised
+    pub fn add_event_listener(&mut self, listener: impl FnMut(&SimEvent) + Send + Sync + 'static) {
+        self.sim_events.listeners.push(Box::new(listener));
+    }
+
+    /// Raise an event, marking the visuals it changed for the front-end
+    ///
+    /// A departing vehicle's event is raised while it is still in the
+    /// world, so its home building can be found and marked too.
+    pub(super) fn raise(&mut self, event: SimEvent) {
+        match event {
+            SimEvent::CarSpawned { car, .. } | SimEvent::CarDespawned { car } => {
+                self.mark_vehicle_changed(car)
+            }
+            SimEvent::BuildingAdded { building } | SimEvent::BuildingRemoved { building } => {
+                self.changes.mark(building.into())
+            }
+            _ => {}
+        }
+        self.sim_events.raise(event);
+    }
+}
@@ -6,9 +6,15 @@
 use super::building::SimFactory;
 use super::types::ApartmentId;
 
-/// Duration in seconds that a worker spends at the factory before returning home
+/// Default duration in seconds that a worker spends at the factory before returning home
 pub const FACTORY_WORK_TIME: f32 = 5.0;
 
+/// Default number of worker shifts it takes to finish one delivery
+pub const FACTORY_SHIFTS_PER_DELIVERY: u32 = 3;
+
+/// Default loads of raw materials each delivery uses, when factories need them
+pub const FACTORY_MATERIALS_PER_DELIVERY: u32 = 1;
+
 impl SimFactory {
     /// Check if the factory can accept workers
     /// Workers can only be accepted when the truck is available (not out making deliveries)
@@ -22,32 +28,53 @@ impl SimFactory {
         if !self.can_accept_workers() {
             return false;
         }
-        self.workers.push((apartment_id, FACTORY_WORK_TIME));
+        self.workers.push((apartment_id, self.work_time));
         true
     }
 
     /// Update the factory logic
     /// Returns list of apartment_ids for workers whose work is done (they should return home)
-    pub fn update(&mut self, delta_secs: f32) -> Vec<ApartmentId> {
+    /// A delivery is finished once enough shifts have been worked on it and there is room to
+    /// store it; with `needs_materials`, it also uses up the recipe's raw materials
+    pub fn update(&mut self, delta_secs: f32, needs_materials: bool) -> Vec<ApartmentId> {
         // Update worker times and find those done working
         let mut workers_done = Vec::new();
+        let mut shifts_done = 0;
         self.workers.retain_mut(|(apartment_id, time_remaining)| {
             *time_remaining -= delta_secs;
             if *time_remaining <= 0.0 {
                 workers_done.push(*apartment_id);
-                // Add to deliveries when worker finishes
-                if self.deliveries_ready < self.max_deliveries {
-                    self.deliveries_ready += 1;
-                }
+                shifts_done += 1;
                 false
             } else {
                 true
             }
         });
 
+        // Shifts beyond what the next delivery needs are wasted
+        self.shifts_worked = (self.shifts_worked + shifts_done).min(self.shifts_per_delivery);
+        let materials_needed = if needs_materials {
+            self.materials_per_delivery
+        } else {
+            0
+        };
+        if self.shifts_worked >= self.shifts_per_delivery
+            && self.deliveries_ready < self.max_deliveries
+            && self.materials >= materials_needed
+        {
+            self.deliveries_ready += 1;
+            self.materials -= materials_needed;
+            self.shifts_worked = 0;
+        }
+
         workers_done
     }
 
+    /// Fraction of the next delivery's shifts worked so far
+    pub fn production_progress(&self) -> f32 {
+        self.shifts_worked as f32 / self.shifts_per_delivery.max(1) as f32
+    }
+
     /// Try to take one delivery for truck dispatch
     /// Note: This check also verifies truck is home for safety, though callers should ensure this
     pub fn take_delivery(&mut self) -> bool {
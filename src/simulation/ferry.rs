@@ -0,0 +1,247 @@
+//! Car ferries
+//!
+//! A ferry crossing links two landings across water like a two-way road,
+//! but vehicles can't drive it themselves. Each crossing has one ferry,
+//! which waits [`FERRY_DOCK_SECS`] at a landing while up to
+//! [`FERRY_CAPACITY`] vehicles drive on, then carries them over at
+//! [`FERRY_SPEED`] and docks at the other side. Vehicles reaching a landing
+//! while the ferry is away or full queue for the next sailing, so a busy
+//! crossing is worth replacing with a bridge.
+
+use ordered_float::OrderedFloat;
+#[cfg(feature = "serialization")]
+use serde::{Deserialize, Serialize};
+
+use super::car::SimCar;
+use super::road_network::{SimRoadNetwork, TwoWayRoad};
+use super::types::{CarId, IntersectionId, Position, RoadId, CAR_LENGTH};
+use super::warnings::{SimWarning, WarningLog};
+use super::world::SimWorld;
+
+/// Most vehicles a ferry carries
+pub const FERRY_CAPACITY: usize = 4;
+
+/// Seconds a ferry waits at a landing for vehicles to drive on
+pub const FERRY_DOCK_SECS: f32 = 4.0;
+
+/// Speed of a ferry across the water
+pub const FERRY_SPEED: f32 = 3.0;
+
+/// Space each vehicle takes up on deck
+const DECK_SPACING: f32 = CAR_LENGTH * 1.2;
+
+/// What a ferry is doing
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+pub enum FerryState {
+    /// Loading at a landing until it sails
+    Docked {
+        landing: IntersectionId,
+        sails_in: f32,
+    },
+    /// On its way along one direction of the crossing
+    Sailing { road: RoadId, elapsed: f32 },
+}
+
+/// The ferry serving a crossing
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+pub struct SimFerry {
+    /// The crossing's two directions
+    pub route: TwoWayRoad,
+    pub state: FerryState,
+    /// Vehicles on board, in the order they drove on
+    pub aboard: Vec<CarId>,
+    /// Sailings completed
+    pub crossings: u32,
+}
+
+impl SimFerry {
+    /// A ferry docked at `landing`, about to start loading
+    pub fn new(route: TwoWayRoad, landing: IntersectionId) -> Self {
+        Self {
+            route,
+            state: FerryState::Docked {
+                landing,
+                sails_in: FERRY_DOCK_SECS,
+            },
+            aboard: Vec::new(),
+            crossings: 0,
+        }
+    }
+
+    /// The direction of the crossing vehicles are driving on to, while the
+    /// ferry is docked
+    pub fn boarding_road(&self, road_network: &SimRoadNetwork) -> Option<RoadId> {
+        let FerryState::Docked { landing, .. } = self.state else {
+            return None;
+        };
+        self.route.roads().into_iter().find(|road_id| {
+            road_network
+                .get_road(*road_id)
+                .is_some_and(|road| road.start_intersection == landing)
+        })
+    }
+
+    /// Whether a vehicle may drive on to `road_id` now
+    pub fn has_room(&self, road_id: RoadId, road_network: &SimRoadNetwork) -> bool {
+        self.boarding_road(road_network) == Some(road_id)
+            && road_network.get_car_count_on_road(road_id) < FERRY_CAPACITY
+    }
+
+    /// Where the ferry is and which way it faces
+    pub fn position(&self, road_network: &SimRoadNetwork) -> Option<(Position, f32)> {
+        match self.state {
+            FerryState::Docked { landing, .. } => {
+                let road = road_network.get_road(self.boarding_road(road_network)?)?;
+                Some((
+                    *road_network.get_intersection_position(landing)?,
+                    road.angle,
+                ))
+            }
+            FerryState::Sailing { road, elapsed } => {
+                let road = road_network.get_road(road)?;
+                let start = road_network.get_intersection_position(road.start_intersection)?;
+                let end = road_network.get_intersection_position(road.end_intersection)?;
+                let progress = (elapsed * FERRY_SPEED / road.length).min(1.0);
+                Some((start.lerp(end, progress), road.angle))
+            }
+        }
+    }
+}
+
+/// Move a vehicle on deck to `distance` along the crossing
+fn place_on_deck(
+    car: &mut SimCar,
+    road_network: &mut SimRoadNetwork,
+    distance: f32,
+    warnings: &mut WarningLog,
+    now: f32,
+) {
+    let previous = car.distance_along_road;
+    car.distance_along_road = OrderedFloat(distance);
+    if let Err(e) = road_network.update_car_road_position(
+        car.id,
+        car.current_road,
+        car.distance_along_road,
+        false,
+        Some(car.current_road),
+        previous,
+    ) {
+        warnings.warn(SimWarning::FerryDeck, now, || {
+            format!("Couldn't move vehicle {:?} on deck: {:#}", car.id, e)
+        });
+    }
+}
+
+impl SimWorld {
+    /// Put a ferry on a two-way crossing, docked at `landing`
+    pub(super) fn add_ferry(&mut self, route: TwoWayRoad, landing: IntersectionId) {
+        self.ferries
+            .insert(route.forward, SimFerry::new(route, landing));
+    }
+
+    /// Load, sail and unload ferries, carrying the vehicles on board along
+    /// with them
+    pub(super) fn update_ferries(&mut self, delta_secs: f32) {
+        // The crossing may have been removed
+        let road_network = &self.road_network;
+        self.ferries.retain(|_, ferry| {
+            ferry
+                .route
+                .roads()
+                .iter()
+                .all(|road_id| road_network.get_road(*road_id).is_some())
+        });
+
+        for ferry in self.ferries.values_mut() {
+            let (road_id, progress) = match ferry.state {
+                FerryState::Docked { landing, sails_in } => {
+                    let Some(road_id) = ferry.boarding_road(&self.road_network) else {
+                        continue;
+                    };
+                    // First come, first served; the rest wait for the next sailing
+                    let cars = &self.cars;
+                    ferry.aboard.retain(|car_id| {
+                        cars.get(car_id)
+                            .is_some_and(|car| car.current_road == road_id)
+                    });
+                    for car_id in self.road_network.get_cars_on_road(road_id) {
+                        if ferry.aboard.len() >= FERRY_CAPACITY {
+                            break;
+                        }
+                        if !ferry.aboard.contains(&car_id) {
+                            ferry.aboard.push(car_id);
+                        }
+                    }
+                    let sails_in = sails_in - delta_secs;
+                    ferry.state = if sails_in <= 0.0 {
+                        FerryState::Sailing {
+                            road: road_id,
+                            elapsed: 0.0,
+                        }
+                    } else {
+                        FerryState::Docked { landing, sails_in }
+                    };
+                    (road_id, 0.0)
+                }
+                FerryState::Sailing { road, elapsed } => {
+                    let Some(length) = self.road_network.get_road(road).map(|road| road.length)
+                    else {
+                        continue;
+                    };
+                    let elapsed = elapsed + delta_secs;
+                    let progress = elapsed * FERRY_SPEED / length;
+                    if progress >= 1.0 {
+                        // Everyone drives off at the far landing
+                        for (place, car_id) in ferry.aboard.drain(..).enumerate() {
+                            if let Some(car) = self.cars.get_mut(&car_id) {
+                                let distance = length + place as f32 * DECK_SPACING;
+                                place_on_deck(
+                                    car,
+                                    &mut self.road_network,
+                                    distance,
+                                    &mut self.warnings,
+                                    self.time,
+                                );
+                            }
+                        }
+                        ferry.crossings += 1;
+                        if let Some(landing) = self
+                            .road_network
+                            .get_road(road)
+                            .map(|road| road.end_intersection)
+                        {
+                            ferry.state = FerryState::Docked {
+                                landing,
+                                sails_in: FERRY_DOCK_SECS,
+                            };
+                        }
+                        continue;
+                    }
+                    ferry.state = FerryState::Sailing { road, elapsed };
+                    (road, progress)
+                }
+            };
+
+            // Vehicles on board keep their places on deck as it moves
+            let Some(length) = self.road_network.get_road(road_id).map(|road| road.length) else {
+                continue;
+            };
+            let deck_length = (FERRY_CAPACITY as f32 * DECK_SPACING).min(length);
+            let deck_front = deck_length + progress * (length - deck_length);
+            for (place, car_id) in ferry.aboard.iter().enumerate() {
+                if let Some(car) = self.cars.get_mut(car_id) {
+                    let distance = (deck_front - place as f32 * DECK_SPACING).max(0.0);
+                    place_on_deck(
+                        car,
+                        &mut self.road_network,
+                        distance,
+                        &mut self.warnings,
+                        self.time,
+                    );
+                }
+            }
+        }
+    }
+}
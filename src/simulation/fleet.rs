@@ -0,0 +1,176 @@
+//! Vehicle wear and replacement
+//!
+//! Every apartment car and factory truck has a [`VehicleRecord`] kept on the
+//! building that owns it, so its age and mileage outlive the individual trips
+//! it makes. As a vehicle wears out it drives slower and breaks down more
+//! often, until the player pays to replace it.
+
+#[cfg(feature = "serialization")]
+use serde::{Deserialize, Serialize};
+
+use super::types::{CarId, VehicleType};
+use super::world::SimWorld;
+
+/// Mileage at which a vehicle is fully worn out
+pub const VEHICLE_WORN_OUT_MILEAGE: f32 = 2000.0;
+
+/// Age in seconds at which a vehicle is fully worn out (five in-game days)
+pub const VEHICLE_WORN_OUT_AGE_SECS: f32 = 1200.0;
+
+/// Share of its speed a fully worn vehicle loses
+pub const WORN_VEHICLE_SLOWDOWN: f32 = 0.5;
+
+/// How many times more often a fully worn vehicle breaks down
+pub const WORN_VEHICLE_BREAKDOWN_MULTIPLIER: f32 = 5.0;
+
+/// Wear from which a vehicle counts as worn and is due for replacement
+pub const VEHICLE_REPLACEMENT_WEAR: f32 = 0.5;
+
+/// Age and mileage of one vehicle owned by a building
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+pub struct VehicleRecord {
+    /// Simulation time the vehicle was bought
+    pub bought_at: f32,
+    /// Distance driven since it was bought
+    pub mileage: f32,
+}
+
+impl VehicleRecord {
+    pub fn new(bought_at: f32) -> Self {
+        Self {
+            bought_at,
+            mileage: 0.0,
+        }
+    }
+
+    /// Seconds since the vehicle was bought
+    pub fn age(&self, now: f32) -> f32 {
+        (now - self.bought_at).max(0.0)
+    }
+
+    /// How worn the vehicle is, from 0 (new) to 1 (worn out), by whichever
+    /// of its age and mileage is further along
+    pub fn wear(&self, now: f32) -> f32 {
+        (self.mileage / VEHICLE_WORN_OUT_MILEAGE)
+            .max(self.age(now) / VEHICLE_WORN_OUT_AGE_SECS)
+            .clamp(0.0, 1.0)
+    }
+}
+
+/// Multiplier on a vehicle's speed at a given wear
+pub fn wear_speed_factor(wear: f32) -> f32 {
+    1.0 - wear.clamp(0.0, 1.0) * WORN_VEHICLE_SLOWDOWN
+}
+
+/// Multiplier on a vehicle's breakdown chance at a given wear
+pub fn wear_breakdown_factor(wear: f32) -> f32 {
+    1.0 + wear.clamp(0.0, 1.0) * (WORN_VEHICLE_BREAKDOWN_MULTIPLIER - 1.0)
+}
+
+impl SimWorld {
+    /// Every vehicle record in the world
+    fn vehicle_records(&self) -> impl Iterator<Item = &VehicleRecord> {
+        self.apartments
+            .values()
+            .flat_map(|apartment| apartment.vehicles.iter())
+            .chain(self.factories.values().map(|factory| &factory.truck_record))
+    }
+
+    /// Average wear of all apartment cars and factory trucks (0 if there
+    /// are none)
+    pub fn average_fleet_wear(&self) -> f32 {
+        let (total, count) = self
+            .vehicle_records()
+            .fold((0.0, 0), |(total, count), record| {
+                (total + record.wear(self.time), count + 1)
+            });
+        if count == 0 {
+            0.0
+        } else {
+            total / count as f32
+        }
+    }
+
+    /// Number of vehicles due for replacement
+    pub fn worn_vehicle_count(&self) -> usize {
+        self.vehicle_records()
+            .filter(|record| record.wear(self.time) >= VEHICLE_REPLACEMENT_WEAR)
+            .count()
+    }
+
+    /// Replace every worn vehicle with a new one, returning how many were
+    /// replaced
+    ///
+    /// Vehicles out on a trip are swapped for the new ones where they are.
+    pub fn replace_worn_vehicles(&mut self) -> usize {
+        let now = self.time;
+        let mut replaced = 0;
+        let records = self
+            .apartments
+            .values_mut()
+            .flat_map(|apartment| apartment.vehicles.iter_mut())
+            .chain(
+                self.factories
+                    .values_mut()
+                    .map(|factory| &mut factory.truck_record),
+            );
+        for record in records {
+            if record.wear(now) >= VEHICLE_REPLACEMENT_WEAR {
+                *record = VehicleRecord::new(now);
+                replaced += 1;
+            }
+        }
+        replaced
+    }
+
+    /// Replace worn vehicles with game cost checking
+    ///
+    /// All of them are paid for at once. Returns Some(count) if successful,
+    /// None if insufficient funds.
+    pub fn try_replace_worn_vehicles(&mut self) -> Option<usize> {
+        let count = self.worn_vehicle_count();
+        if !self.spend_for_game(|economy| economy.cost_vehicle_replacement * count as i32) {
+            return None;
+        }
+        let replaced = self.replace_worn_vehicles();
+        if let Some(game_state) = &mut self.game_state {
+            game_state.record_vehicles_replaced(replaced);
+        }
+        Some(replaced)
+    }
+
+    /// Add the distance each vehicle has driven to its record and update
+    /// the wear it drives with
+    pub(super) fn update_fleet(&mut self) {
+        let now = self.time;
+        for (car_id, car) in self.cars.iter_mut() {
+            let distance = std::mem::take(&mut car.odometer);
+            let record = match car.vehicle_type {
+                VehicleType::Car => car
+                    .origin_apartment
+                    .and_then(|id| self.apartments.get_mut(&id))
+                    .and_then(|apartment| {
+                        let slot = slot_of(&apartment.cars, *car_id)?;
+                        apartment.vehicles.get_mut(slot)
+                    }),
+                VehicleType::Truck => car
+                    .origin_factory
+                    .and_then(|id| self.factories.get_mut(&id))
+                    .filter(|factory| factory.truck == Some(*car_id))
+                    .map(|factory| &mut factory.truck_record),
+                VehicleType::Ambulance | VehicleType::TowTruck | VehicleType::Van => None,
+            };
+            // Visitors, ambulances, tow trucks and vans aren't part of the fleet
+            car.wear = record.map_or(0.0, |record| {
+                record.mileage += distance;
+                record.wear(now)
+            });
+        }
+    }
+}
+
+/// Which of an apartment's car slots a car is in
+fn slot_of(slots: &[Option<CarId>], car_id: CarId) -> Option<usize> {
+    slots.iter().position(|slot| *slot == Some(car_id))
+}
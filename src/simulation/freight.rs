@@ -0,0 +1,184 @@
+//! Rail freight terminals supplying factories
+//!
+//! A terminal receives a trainload of raw materials every
+//! [`TERMINAL_SHIPMENT_SECS`], up to [`TERMINAL_CAPACITY`] loads. When the
+//! economy requires factories to be supplied, each finished shift uses up a
+//! load of materials at the factory, and a factory without any can't produce
+//! goods. Each terminal keeps one van, which takes up to [`VAN_LOAD`] loads
+//! to the factory with the least materials on hand and drives back, so
+//! where terminals go relative to factories matters. Without the
+//! requirement factories source their materials off the map and vans stay
+//! at their terminal.
+
+
+use super::special::SpecialKind;
+use super::types::{FactoryId, SpecialBuildingId, TripType, VehicleType};
+use super::warnings::SimWarning;
+use super::world::SimWorld;
+
+/// Seconds between trains unloading at a terminal
+pub const TERMINAL_SHIPMENT_SECS: f32 = 30.0;
+
+/// Loads of raw materials each train brings
+pub const TERMINAL_SHIPMENT_SIZE: u32 = 6;
+
+/// Most loads a terminal can hold; trains bring no more once it is full
+pub const TERMINAL_CAPACITY: u32 = 24;
+
+/// Most loads a van carries
+pub const VAN_LOAD: u32 = 3;
+
+/// Loads a factory can stock; vans aren't sent to factories that are full
+pub const FACTORY_MATERIALS_CAPACITY: u32 = 6;
+
+impl SimWorld {
+    /// Whether factories need raw materials from a terminal to produce goods
+    pub fn factory_supply_required(&self) -> bool {
+        self.game_state
+            .as_ref()
+            .is_some_and(|game_state| game_state.economy.factory_supply_required)
+    }
+
+    /// Unload trains at terminals and send idle vans out to the factories
+    /// running lowest on materials
+    pub(super) fn update_terminals(&mut self, delta_secs: f32) {
+        for terminal in self.special_buildings.values_mut() {
+            if terminal.kind != SpecialKind::Terminal {
+                continue;
+            }
+            // The van may have been removed with a road it was on
+            if terminal
+                .van
+                .is_some_and(|van| !self.cars.contains_key(&van))
+            {
+                terminal.van = None;
+                terminal.supplying = None;
+            }
+            terminal.next_shipment_in -= delta_secs;
+            while terminal.next_shipment_in <= 0.0 {
+                terminal.next_shipment_in += TERMINAL_SHIPMENT_SECS;
+                terminal.materials =
+                    (terminal.materials + TERMINAL_SHIPMENT_SIZE).min(TERMINAL_CAPACITY);
+            }
+        }
+
+        if !self.factory_supply_required() {
+            return;
+        }
+        let idle: Vec<SpecialBuildingId> = self
+            .special_buildings
+            .values()
+            .filter(|terminal| {
+                terminal.kind == SpecialKind::Terminal
+                    && terminal.van.is_none()
+                    && terminal.materials > 0
+            })
+            .map(|terminal| terminal.id)
+            .collect();
+        for terminal_id in idle {
+            self.dispatch_van(terminal_id);
+        }
+    }
+
+    /// Send a terminal's van to the factory with the least materials on hand
+    /// or on the way, nearest first among equals
+    fn dispatch_van(&mut self, terminal_id: SpecialBuildingId) {
+        let Some(site) = self
+            .special_buildings
+            .get(&terminal_id)
+            .map(|terminal| terminal.intersection_id)
+        else {
+            return;
+        };
+        let Some(site_position) = self.road_network.get_intersection_position(site) else {
+            return;
+        };
+
+        let mut factories: Vec<(u32, f32, FactoryId)> = self
+            .factories
+            .values()
+            .filter_map(|factory| {
+                let en_route: u32 = self
+                    .special_buildings
+                    .values()
+                    .filter_map(|terminal| terminal.supplying)
+                    .filter(|(id, _)| *id == factory.id)
+                    .map(|(_, load)| load)
+                    .sum();
+                let stock = factory.materials + en_route;
+                if stock >= FACTORY_MATERIALS_CAPACITY {
+                    return None;
+                }
+                let position = self
+                    .road_network
+                    .get_intersection_position(factory.intersection_id)?;
+                Some((stock, position.distance(site_position), factory.id))
+            })
+            .collect();
+        factories.sort_by(|a, b| a.0.cmp(&b.0).then(a.1.total_cmp(&b.1)));
+
+        // Try factories in order, skipping any the van can't reach
+        for (stock, _, factory_id) in factories {
+            let Some(destination) = self
+                .factories
+                .get(&factory_id)
+                .map(|factory| factory.intersection_id)
+            else {
+                continue;
+            };
+            let Some(materials) = self
+                .special_buildings
+                .get(&terminal_id)
+                .map(|terminal| terminal.materials)
+            else {
+                return;
+            };
+            let load = VAN_LOAD
+                .min(materials)
+                .min(FACTORY_MATERIALS_CAPACITY - stock);
+            match self.spawn_vehicle(
+                site,
+                destination,
+                VehicleType::Van,
+                TripType::Outbound,
+                None,
+                None,
+            ) {
+                Ok(van) => {
+                    if let Some(car) = self.cars.get_mut(&van) {
+                        car.origin_special = Some(terminal_id);
+                    }
+                    if let Some(terminal) = self.special_buildings.get_mut(&terminal_id) {
+                        terminal.materials -= load;
+                        terminal.van = Some(van);
+                        terminal.supplying = Some((factory_id, load));
+                    }
+                    return;
+                }
+                Err(e) => {
+                    self.warnings.warn(SimWarning::VanStuck, self.time, || {
+                        format!(
+                            "Van couldn't leave terminal {:?} for factory {:?}: {:#}",
+                            terminal_id, factory_id, e
+                        )
+                    });
+                }
+            }
+        }
+    }
+
+    /// Hand a terminal van's load over to the factory it was taken to
+    pub(super) fn unload_van(&mut self, terminal_id: SpecialBuildingId) {
+        let Some(terminal) = self.special_buildings.get_mut(&terminal_id) else {
+            return;
+        };
+        let Some((factory_id, load)) = terminal.supplying.take() else {
+            return;
+        };
+        terminal.supplies_delivered += 1;
+        // A factory removed while the van was on its way loses the load
+        if let Some(factory) = self.factories.get_mut(&factory_id) {
+            factory.materials += load;
+        }
+    }
+}
@@ -2,12 +2,34 @@
 //!
 //! This module tracks the player's resources, score, and objectives
 //! to turn the traffic simulation into a fun management game.
+//!
+//! The constants below are the default economy; the values actually used
+//! at runtime come from the [`EconomyConfig`] attached to each [`GameState`].
+
+#[cfg(feature = "serialization")]
+use serde::{Deserialize, Serialize};
+
+use super::economy::EconomyConfig;
+use super::incidents::IncidentKind;
+use super::scenario_events::ScenarioEvent;
+use super::speed_goal::SpeedGoalRecord;
+use super::upgrades::{UpgradeKind, UpgradeLevels};
 
 /// Building costs for the game
 pub const COST_ROAD: i32 = 50;
+pub const COST_BRIDGE: i32 = 400;
+pub const COST_FERRY: i32 = 250;
 pub const COST_APARTMENT: i32 = 200;
 pub const COST_FACTORY: i32 = 500;
 pub const COST_SHOP: i32 = 300;
+pub const COST_SCHOOL: i32 = 400;
+pub const COST_HOSPITAL: i32 = 800;
+pub const COST_DEPOT: i32 = 350;
+pub const COST_TERMINAL: i32 = 600;
+pub const COST_SENSOR: i32 = 25;
+pub const COST_TREE: i32 = 20;
+pub const COST_PARK: i32 = 150;
+pub const COST_BUS_STOP: i32 = 40;
 
 /// Revenue from successful operations
 pub const REVENUE_WORKER_DELIVERY: i32 = 10; // Worker completes shift
@@ -16,6 +38,38 @@ pub const REVENUE_SHOP_DELIVERY: i32 = 50; // Truck delivers to shop
 pub const COMMUTE_HEALTHY_DISTANCE: f32 = 15.0;
 /// Maximum per-trip penalty for unhealthy (too short) commutes
 pub const SHORT_COMMUTE_PENALTY: i32 = 20;
+/// Seconds a truck has from leaving its factory to reaching the shop
+pub const DELIVERY_DEADLINE_SECS: f32 = 45.0;
+/// Share of the delivery revenue a late delivery still earns
+pub const LATE_DELIVERY_REVENUE_SHARE: f32 = 0.5;
+/// Share of deliveries that must be on time, below which the game is lost
+/// (0 turns the check off)
+pub const MIN_ON_TIME_RATE: f32 = 0.0;
+/// Shop deliveries needed before the on-time rate can lose the game
+pub const SERVICE_LEVEL_MIN_DELIVERIES: usize = 10;
+/// Seconds a worker's drive home may take before it counts as slow
+pub const COMMUTE_TIME_ALLOWANCE_SECS: f32 = 30.0;
+/// Per-second penalty for a drive home slower than the allowance (off by default)
+pub const COMMUTE_TIME_PENALTY_PER_SEC: f32 = 0.0;
+/// Bonus for a drive home within the allowance (off by default)
+pub const QUICK_COMMUTE_BONUS: i32 = 0;
+
+/// Incident rates (per in-game hour) and cleanup costs
+pub const BREAKDOWNS_PER_VEHICLE_HOUR: f32 = 0.005;
+pub const COLLISIONS_PER_CONGESTED_ROAD_HOUR: f32 = 0.05;
+pub const COST_BREAKDOWN_CLEANUP: i32 = 30;
+pub const COST_COLLISION_CLEANUP: i32 = 100;
+
+/// Cost of replacing one worn vehicle
+pub const COST_VEHICLE_REPLACEMENT: i32 = 40;
+
+/// Insurance premium, charged every period while insured, that covers
+/// incident cleanup
+pub const INSURANCE_PREMIUM: i32 = 20;
+pub const INSURANCE_PERIOD_SECS: f32 = 60.0;
+
+/// Most events kept for the UI before the oldest are dropped
+pub const MAX_GAME_EVENTS: usize = 64;
 
 /// Starting budget for the player
 pub const STARTING_BUDGET: i32 = 2000;
@@ -24,8 +78,44 @@ pub const STARTING_BUDGET: i32 = 2000;
 pub const GOAL_DELIVERIES: usize = 50; // Deliveries needed to win
 pub const GOAL_MONEY: i32 = 5000; // Money target to win
 
+/// Something that happened to the player's finances, for the UI to report
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+#[cfg_attr(
+    feature = "serialization",
+    serde(tag = "type", rename_all = "snake_case")
+)]
+pub enum GameEvent {
+    /// A vehicle broke down or crashed; `cost` is 0 when insurance covered it
+    Incident { kind: IncidentKind, cost: i32 },
+    /// An insurance premium was paid
+    PremiumPaid { amount: i32 },
+    /// A premium couldn't be paid, so the policy ended
+    InsuranceLapsed,
+    /// Worn vehicles were replaced
+    VehiclesReplaced { count: usize },
+    /// A shop order waited longer than the demand ramp's delivery SLA
+    DeliverySlaBroken { level: f32 },
+    /// A scenario event started
+    ScenarioEventStarted { event: ScenarioEvent },
+    /// A scenario event ended
+    ScenarioEventEnded { event: ScenarioEvent },
+    /// Rush hour ended with the network-average speed at or above the goal
+    SpeedGoalMet { average: f32 },
+    /// Rush hour ended with the network-average speed below the goal
+    SpeedGoalMissed { average: f32 },
+    /// A capacity upgrade was bought, reaching `level`
+    UpgradeBought { upgrade: UpgradeKind, level: u32 },
+    /// The economy's alarm at index `alarm` went off with its measure at
+    /// `value`
+    AlarmRaised { alarm: usize, value: f32 },
+    /// The alarm's measure is back on the right side of its threshold
+    AlarmCleared { alarm: usize },
+}
+
 /// Game state that tracks player progress and resources
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
 pub struct GameState {
     /// Player's current money
     pub money: i32,
@@ -36,14 +126,49 @@ pub struct GameState {
     /// Total shop deliveries completed (factory -> shop -> factory)
     pub shop_deliveries_completed: usize,
 
+    /// Sum of the commute distances of all completed worker trips
+    #[cfg_attr(feature = "serialization", serde(default))]
+    pub total_commute_distance: f32,
+
     /// Game time in seconds
     pub time: f32,
 
     /// Whether the game is won
     pub is_won: bool,
 
-    /// Whether the game is lost (bankrupt)
+    /// Whether the game is lost (bankrupt, or too many late deliveries)
     pub is_lost: bool,
+
+    /// Costs, revenues and goals for this game
+    pub economy: EconomyConfig,
+
+    /// Whether an insurance policy covers incident cleanup
+    #[cfg_attr(feature = "serialization", serde(default))]
+    pub insured: bool,
+
+    /// Seconds until the next insurance premium is due
+    #[cfg_attr(feature = "serialization", serde(default))]
+    pub premium_due_in: f32,
+
+    /// Breakdowns and collisions so far
+    #[cfg_attr(feature = "serialization", serde(default))]
+    pub incidents: usize,
+
+    /// Shop deliveries that missed their deadline
+    #[cfg_attr(feature = "serialization", serde(default))]
+    pub late_deliveries: usize,
+
+    /// Events not yet taken with [`GameState::take_events`], oldest first
+    #[cfg_attr(feature = "serialization", serde(default))]
+    pub events: Vec<GameEvent>,
+
+    /// Rush hours the economy's speed goal was met and missed for
+    #[cfg_attr(feature = "serialization", serde(default))]
+    pub speed_goal: SpeedGoalRecord,
+
+    /// Levels of each capacity upgrade bought
+    #[cfg_attr(feature = "serialization", serde(default))]
+    pub upgrades: UpgradeLevels,
 }
 
 impl Default for GameState {
@@ -55,13 +180,27 @@ impl Default for GameState {
 impl GameState {
     /// Create a new game state with starting conditions
     pub fn new() -> Self {
+        Self::with_economy(EconomyConfig::default())
+    }
+
+    /// Create a new game state using a custom economy
+    pub fn with_economy(economy: EconomyConfig) -> Self {
         Self {
-            money: STARTING_BUDGET,
+            money: economy.starting_budget,
             worker_trips_completed: 0,
             shop_deliveries_completed: 0,
+            total_commute_distance: 0.0,
             time: 0.0,
             is_won: false,
             is_lost: false,
+            economy,
+            insured: false,
+            premium_due_in: 0.0,
+            incidents: 0,
+            late_deliveries: 0,
+            events: Vec::new(),
+            speed_goal: SpeedGoalRecord::default(),
+            upgrades: UpgradeLevels::default(),
         }
     }
 
@@ -86,41 +225,236 @@ impl GameState {
         self.money += amount;
     }
 
-    /// Calculate the commute penalty for a worker trip.
+    /// Record a worker trip completion and award revenue
     ///
-    /// Commutes shorter than [`COMMUTE_HEALTHY_DISTANCE`] apply a linear penalty
-    /// up to [`SHORT_COMMUTE_PENALTY`], representing quality-of-life costs for
-    /// living too close to industrial sites.
-    fn compute_commute_penalty(commute_distance: f32) -> i32 {
-        if COMMUTE_HEALTHY_DISTANCE <= 0.0 {
-            // Invalid configuration; avoid a non-positive denominator by falling back to the maximum penalty.
-            return SHORT_COMMUTE_PENALTY;
-        }
-        let distance_penalty_ratio = ((COMMUTE_HEALTHY_DISTANCE - commute_distance).max(0.0)
-            / COMMUTE_HEALTHY_DISTANCE)
-            .clamp(0.0, 1.0);
-        (distance_penalty_ratio * SHORT_COMMUTE_PENALTY as f32).round() as i32
+    /// `commute_distance` is how far the apartment is from the factory and
+    /// `commute_secs` how long the drive home took; the pay follows the
+    /// economy's [worker pay formula](EconomyConfig::worker_trip_pay).
+    pub fn complete_worker_trip(&mut self, commute_distance: f32, commute_secs: f32) {
+        self.complete_worker_trip_with_appeal(commute_distance, commute_secs, 0.0);
     }
 
-    /// Record a worker trip completion and award revenue
-    pub fn complete_worker_trip(&mut self, commute_distance: f32) {
+    /// Record a worker trip completion from a home with some appeal, which
+    /// softens the short-commute penalty
+    pub fn complete_worker_trip_with_appeal(
+        &mut self,
+        commute_distance: f32,
+        commute_secs: f32,
+        appeal: f32,
+    ) {
+        self.complete_priced_worker_trip(commute_distance, commute_secs, appeal, 1.0);
+    }
+
+    /// Record a worker trip completion whose base pay is scaled by `price`
+    /// (see [`super::pricing`])
+    pub fn complete_priced_worker_trip(
+        &mut self,
+        commute_distance: f32,
+        commute_secs: f32,
+        appeal: f32,
+        price: f32,
+    ) {
         self.worker_trips_completed += 1;
-        let penalty = Self::compute_commute_penalty(commute_distance);
-        self.earn(REVENUE_WORKER_DELIVERY - penalty);
+        self.total_commute_distance += commute_distance;
+        self.earn(self.economy.priced_worker_trip_pay(
+            commute_distance,
+            commute_secs,
+            appeal,
+            price,
+        ));
+    }
+
+    /// Revenue a shop delivery earns, of which a late one earns only part
+    pub fn shop_delivery_revenue(&self, late: bool) -> i32 {
+        self.priced_shop_delivery_revenue(late, 1.0)
+    }
+
+    /// Revenue a shop delivery sold at `price` times the usual revenue
+    /// earns (see [`super::pricing`])
+    pub fn priced_shop_delivery_revenue(&self, late: bool, price: f32) -> i32 {
+        let mut revenue = self.economy.revenue_shop_delivery as f32 * price;
+        if late {
+            revenue *= self.economy.late_delivery_revenue_share;
+        }
+        revenue.round() as i32
     }
 
     /// Record a shop delivery completion and award revenue
     pub fn complete_shop_delivery(&mut self) {
+        self.complete_priced_shop_delivery(false, 1.0);
+    }
+
+    /// Record a shop delivery that missed its deadline, which earns only
+    /// part of the revenue
+    pub fn complete_late_shop_delivery(&mut self) {
+        self.complete_priced_shop_delivery(true, 1.0);
+    }
+
+    /// Record a shop delivery sold at `price` times the usual revenue
+    pub fn complete_priced_shop_delivery(&mut self, late: bool, price: f32) {
         self.shop_deliveries_completed += 1;
-        self.earn(REVENUE_SHOP_DELIVERY);
+        if late {
+            self.late_deliveries += 1;
+        }
+        self.earn(self.priced_shop_delivery_revenue(late, price));
+    }
+
+    /// Share of shop deliveries that arrived on time (1 with none yet)
+    pub fn on_time_rate(&self) -> f32 {
+        if self.shop_deliveries_completed == 0 {
+            1.0
+        } else {
+            1.0 - self.late_deliveries as f32 / self.shop_deliveries_completed as f32
+        }
+    }
+
+    /// Whether enough deliveries have been late to lose the game
+    pub fn missed_service_level(&self) -> bool {
+        self.shop_deliveries_completed >= SERVICE_LEVEL_MIN_DELIVERIES
+            && self.on_time_rate() < self.economy.min_on_time_rate
+    }
+
+    /// Record an event, dropping the oldest once too many are waiting
+    fn push_event(&mut self, event: GameEvent) {
+        if self.events.len() >= MAX_GAME_EVENTS {
+            self.events.remove(0);
+        }
+        self.events.push(event);
+    }
+
+    /// Take the events recorded since the last call, oldest first
+    pub fn take_events(&mut self) -> Vec<GameEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+    /// Record an incident and charge its cleanup unless insured
+    ///
+    /// Cleanup can't be declined, so it may push the player into debt.
+    /// Returns what was charged.
+    pub fn record_incident(&mut self, kind: IncidentKind) -> i32 {
+        self.incidents += 1;
+        let cost = if self.insured {
+            0
+        } else {
+            match kind {
+                IncidentKind::Breakdown => self.economy.cost_breakdown_cleanup,
+                IncidentKind::Collision => self.economy.cost_collision_cleanup,
+            }
+        };
+        self.money -= cost;
+        self.push_event(GameEvent::Incident { kind, cost });
+        cost
+    }
+
+    /// Record that the delivery SLA broke at a demand level
+    pub fn record_sla_breach(&mut self, level: f32) {
+        self.push_event(GameEvent::DeliverySlaBroken { level });
+    }
+
+    /// Record that a scenario event started
+    pub fn record_event_started(&mut self, event: ScenarioEvent) {
+        self.push_event(GameEvent::ScenarioEventStarted { event });
+    }
+
+    /// Record that a scenario event ended
+    pub fn record_event_ended(&mut self, event: ScenarioEvent) {
+        self.push_event(GameEvent::ScenarioEventEnded { event });
+    }
+
+    /// Record that an alarm went off
+    pub fn record_alarm_raised(&mut self, alarm: usize, value: f32) {
+        self.push_event(GameEvent::AlarmRaised { alarm, value });
+    }
+
+    /// Record that an alarm cleared
+    pub fn record_alarm_cleared(&mut self, alarm: usize) {
+        self.push_event(GameEvent::AlarmCleared { alarm });
+    }
+
+    /// Record how a rush hour went against the speed goal; missing it loses
+    /// the game
+    pub fn record_speed_goal(&mut self, met: bool, average: f32) {
+        if met {
+            self.speed_goal.met += 1;
+            self.push_event(GameEvent::SpeedGoalMet { average });
+        } else {
+            self.speed_goal.missed += 1;
+            self.is_lost = true;
+            self.push_event(GameEvent::SpeedGoalMissed { average });
+        }
+    }
+
+    /// Whether a rush hour was too slow for the speed goal
+    pub fn missed_speed_goal(&self) -> bool {
+        self.speed_goal.missed > 0
+    }
+
+    /// Whether the speed goal, if there is one, has been met for enough
+    /// rush hours to win
+    pub fn speed_goal_held(&self) -> bool {
+        self.economy
+            .speed_goal
+            .is_none_or(|goal| self.speed_goal.met >= goal.rush_hours)
+    }
+
+    /// Record a capacity upgrade (already paid for)
+    pub fn record_upgrade(&mut self, upgrade: UpgradeKind, level: u32) {
+        self.push_event(GameEvent::UpgradeBought { upgrade, level });
+    }
+
+    /// Record a replacement of worn vehicles (already paid for)
+    pub fn record_vehicles_replaced(&mut self, count: usize) {
+        if count > 0 {
+            self.push_event(GameEvent::VehiclesReplaced { count });
+        }
+    }
+
+    /// Take out or cancel insurance
+    ///
+    /// Taking it out pays the first premium up front. Returns false if that
+    /// premium can't be afforded.
+    pub fn set_insured(&mut self, insured: bool) -> bool {
+        if insured && !self.insured {
+            if !self.pay_premium() {
+                return false;
+            }
+            self.premium_due_in = self.economy.insurance_period_secs;
+        }
+        self.insured = insured;
+        true
+    }
+
+    fn pay_premium(&mut self) -> bool {
+        let amount = self.economy.insurance_premium;
+        let paid = self.spend(amount);
+        if paid {
+            self.push_event(GameEvent::PremiumPaid { amount });
+        }
+        paid
     }
 
     /// Update game time and check win/loss conditions
     pub fn update(&mut self, delta_secs: f32) {
         self.time += delta_secs;
 
+        // Renew insurance, letting it lapse if the premium can't be paid
+        if self.insured {
+            self.premium_due_in -= delta_secs;
+            if self.premium_due_in <= 0.0 {
+                if self.pay_premium() {
+                    self.premium_due_in += self.economy.insurance_period_secs;
+                } else {
+                    self.insured = false;
+                    self.push_event(GameEvent::InsuranceLapsed);
+                }
+            }
+        }
+
         // Check win conditions
-        if self.shop_deliveries_completed >= GOAL_DELIVERIES || self.money >= GOAL_MONEY {
+        if (self.shop_deliveries_completed >= self.economy.goal_deliveries
+            || self.money >= self.economy.goal_money)
+            && self.speed_goal_held()
+        {
             self.is_won = true;
         }
 
@@ -129,6 +463,20 @@ impl GameState {
         if self.money < 0 {
             self.is_lost = true;
         }
+
+        // Scenarios can also be lost by delivering too late too often
+        if self.missed_service_level() {
+            self.is_lost = true;
+        }
+    }
+
+    /// Average commute distance of completed worker trips (0 if there were none)
+    pub fn average_commute_distance(&self) -> f32 {
+        if self.worker_trips_completed == 0 {
+            0.0
+        } else {
+            self.total_commute_distance / self.worker_trips_completed as f32
+        }
     }
 
     /// Get total deliveries (workers + shop)
@@ -146,9 +494,12 @@ impl GameState {
 
     /// Get progress towards goals as a percentage
     pub fn goal_progress(&self) -> (f32, f32) {
-        let delivery_progress =
-            (self.shop_deliveries_completed as f32 / GOAL_DELIVERIES as f32 * 100.0).min(100.0);
-        let money_progress = (self.money as f32 / GOAL_MONEY as f32 * 100.0).min(100.0);
+        let delivery_progress = (self.shop_deliveries_completed as f32
+            / self.economy.goal_deliveries as f32
+            * 100.0)
+            .min(100.0);
+        let money_progress =
+            (self.money as f32 / self.economy.goal_money as f32 * 100.0).min(100.0);
         (delivery_progress, money_progress)
     }
 }
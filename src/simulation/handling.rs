@@ -0,0 +1,137 @@
+//! How each class of vehicle handles intersections
+//!
+//! A junction's capacity depends on what uses it. A truck is long, heavy
+//! and wide: it waits for a longer gap before pulling out at a stop sign
+//! or yield, it gets back up to speed slowly once it has stopped, and it
+//! swings wide round a corner and takes it slower. So a junction busy with
+//! freight lets fewer vehicles through an hour than one of cars.
+//!
+//! Each [`VehicleType`] has a [`VehicleHandling`], held by the world's
+//! [`HandlingConfig`]:
+//!
+//! - **Gaps**: the clear time a vehicle needs before it pulls out at a stop
+//!   sign or yield is [`YIELD_GAP_SECS`](super::YIELD_GAP_SECS) times its
+//!   `gap_factor`.
+//! - **Acceleration**: a vehicle gains at most `launch_accel` of speed a
+//!   second, so one moving off from a stop takes a while to reach its
+//!   speed. Cars get going almost at once.
+//! - **Turns**: a turning vehicle sweeps an arc `turn_width_factor` times
+//!   as wide across the intersection (see [`super::crossing`]), holding it
+//!   longer, and takes the bend at `corner_speed_factor` of the speed
+//!   [`CurveSpeedLimit`](super::CurveSpeedLimit) allows.
+
+#[cfg(feature = "serialization")]
+use serde::{Deserialize, Serialize};
+
+use super::types::VehicleType;
+
+/// How a class of vehicle pulls out, gets going and turns
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+pub struct VehicleHandling {
+    /// Times the usual clear gap it waits for before pulling out
+    pub gap_factor: f32,
+    /// Most speed it gains a second, in world units per second squared
+    pub launch_accel: f32,
+    /// Times the usual width of the arc it sweeps turning across an
+    /// intersection
+    pub turn_width_factor: f32,
+    /// Share of the usual speed it takes bends at
+    pub corner_speed_factor: f32,
+}
+
+impl VehicleHandling {
+    /// The usual handling of a vehicle of `vehicle_type`
+    pub fn of(vehicle_type: VehicleType) -> Self {
+        match vehicle_type {
+            VehicleType::Car => Self {
+                gap_factor: 1.0,
+                launch_accel: 30.0,
+                turn_width_factor: 1.0,
+                corner_speed_factor: 1.0,
+            },
+            VehicleType::Truck => Self {
+                gap_factor: 1.5,
+                launch_accel: 4.0,
+                turn_width_factor: 1.5,
+                corner_speed_factor: 0.7,
+            },
+            VehicleType::Ambulance => Self {
+                gap_factor: 1.0,
+                launch_accel: 20.0,
+                turn_width_factor: 1.1,
+                corner_speed_factor: 1.0,
+            },
+            VehicleType::TowTruck => Self {
+                gap_factor: 1.3,
+                launch_accel: 6.0,
+                turn_width_factor: 1.3,
+                corner_speed_factor: 0.85,
+            },
+            VehicleType::Van => Self {
+                gap_factor: 1.2,
+                launch_accel: 10.0,
+                turn_width_factor: 1.2,
+                corner_speed_factor: 0.9,
+            },
+        }
+    }
+
+    /// Fastest speed this tick for a vehicle that was going at `speed`
+    pub fn max_speed_after(&self, speed: f32, delta_secs: f32) -> f32 {
+        speed + self.launch_accel * delta_secs
+    }
+
+    /// Seconds lost getting up to `speed` from a stop, compared with being
+    /// at it already
+    pub fn launch_secs(&self, speed: f32) -> f32 {
+        speed / (2.0 * self.launch_accel.max(f32::EPSILON))
+    }
+}
+
+/// Handling of every class of vehicle
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+pub struct HandlingConfig {
+    pub car: VehicleHandling,
+    pub truck: VehicleHandling,
+    pub ambulance: VehicleHandling,
+    pub tow_truck: VehicleHandling,
+    pub van: VehicleHandling,
+}
+
+impl Default for HandlingConfig {
+    fn default() -> Self {
+        Self {
+            car: VehicleHandling::of(VehicleType::Car),
+            truck: VehicleHandling::of(VehicleType::Truck),
+            ambulance: VehicleHandling::of(VehicleType::Ambulance),
+            tow_truck: VehicleHandling::of(VehicleType::TowTruck),
+            van: VehicleHandling::of(VehicleType::Van),
+        }
+    }
+}
+
+impl HandlingConfig {
+    /// Handling of vehicles of `vehicle_type`
+    pub fn get(&self, vehicle_type: VehicleType) -> VehicleHandling {
+        match vehicle_type {
+            VehicleType::Car => self.car,
+            VehicleType::Truck => self.truck,
+            VehicleType::Ambulance => self.ambulance,
+            VehicleType::TowTruck => self.tow_truck,
+            VehicleType::Van => self.van,
+        }
+    }
+
+    /// Handling of vehicles of `vehicle_type`, to tune
+    pub fn get_mut(&mut self, vehicle_type: VehicleType) -> &mut VehicleHandling {
+        match vehicle_type {
+            VehicleType::Car => &mut self.car,
+            VehicleType::Truck => &mut self.truck,
+            VehicleType::Ambulance => &mut self.ambulance,
+            VehicleType::TowTruck => &mut self.tow_truck,
+            VehicleType::Van => &mut self.van,
+        }
+    }
+}
@@ -0,0 +1,134 @@
+//! Places in the city worth a look
+//!
+//! [`SimWorld::highlights`] picks out where things are happening from the
+//! simulation's own state: the intersection with the most vehicles on the
+//! roads leading into it, the shop that had the latest delivery, and the
+//! newest road, building or decoration (everything built takes the next ID,
+//! so the newest is the one with the highest). Front-ends use them to point
+//! a camera somewhere interesting, as the UI's cinematic mode does.
+
+use super::special::SpecialKind;
+use super::types::{IntersectionId, Position, SimId, SimMap};
+use super::world::SimWorld;
+
+/// What makes a place worth a look
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HighlightKind {
+    BusiestIntersection,
+    LatestDelivery,
+    NewestConstruction,
+}
+
+/// A place worth a look, with a line saying why
+#[derive(Debug, Clone, PartialEq)]
+pub struct Highlight {
+    pub kind: HighlightKind,
+    pub position: Position,
+    pub caption: String,
+}
+
+impl SimWorld {
+    /// The places worth a look right now, in [`HighlightKind`] order,
+    /// leaving out any there is nothing to show for
+    pub fn highlights(&self) -> Vec<Highlight> {
+        [
+            self.busiest_intersection(),
+            self.latest_delivery(),
+            self.newest_construction(),
+        ]
+        .into_iter()
+        .flatten()
+        .collect()
+    }
+
+    /// The intersection with the most vehicles on the roads into it, if any
+    /// vehicle is on the road
+    fn busiest_intersection(&self) -> Option<Highlight> {
+        let network = &self.road_network;
+        let mut approaching: SimMap<IntersectionId, usize> = SimMap::default();
+        for road in network.roads().values() {
+            *approaching.entry(road.end_intersection).or_default() +=
+                network.get_car_count_on_road(road.id);
+        }
+        // The first of any tied, in ID order
+        let (id, count) = approaching
+            .into_iter()
+            .rev()
+            .max_by_key(|(_, count)| *count)
+            .filter(|(_, count)| *count > 0)?;
+        Some(Highlight {
+            kind: HighlightKind::BusiestIntersection,
+            position: *network.get_intersection_position(id)?,
+            caption: format!("Busiest intersection, with {} heading in", count),
+        })
+    }
+
+    /// The shop that had the latest delivery, if any has had one
+    fn latest_delivery(&self) -> Option<Highlight> {
+        let (shop, time) = self
+            .shops
+            .values()
+            .filter_map(|shop| shop.last_delivery.map(|time| (shop, time)))
+            .max_by(|(_, a), (_, b)| a.total_cmp(b))?;
+        Some(Highlight {
+            kind: HighlightKind::LatestDelivery,
+            position: *self
+                .road_network
+                .get_intersection_position(shop.intersection_id)?,
+            caption: format!(
+                "Latest delivery: shop {} at {:.0}s ({} so far)",
+                shop.id.0 .0, time, shop.cars_received
+            ),
+        })
+    }
+
+    /// The road, building or decoration built last, if anything has been
+    fn newest_construction(&self) -> Option<Highlight> {
+        let network = &self.road_network;
+        let at = |id| network.get_intersection_position(id).copied();
+        let roads = network.roads().values().filter_map(|road| {
+            let start = at(road.start_intersection)?;
+            let end = at(road.end_intersection)?;
+            Some((road.id.0, "road", start.lerp(&end, 0.5)))
+        });
+        let apartments = self.apartments.values().filter_map(|apartment| {
+            Some((apartment.id.0, "house", at(apartment.intersection_id)?))
+        });
+        let factories = self
+            .factories
+            .values()
+            .filter_map(|factory| Some((factory.id.0, "factory", at(factory.intersection_id)?)));
+        let shops = self
+            .shops
+            .values()
+            .filter_map(|shop| Some((shop.id.0, "shop", at(shop.intersection_id)?)));
+        let specials = self.special_buildings.values().filter_map(|building| {
+            let name = match building.kind {
+                SpecialKind::School => "school",
+                SpecialKind::Hospital => "hospital",
+                SpecialKind::Depot => "tow truck depot",
+                SpecialKind::Terminal => "rail terminal",
+            };
+            Some((building.id.0, name, at(building.intersection_id)?))
+        });
+        let decorations = self.decorations.values().map(|decoration| {
+            (
+                decoration.id.0,
+                decoration.kind.label(),
+                decoration.position,
+            )
+        });
+        let (_, name, position): (SimId, &str, Position) = roads
+            .chain(apartments)
+            .chain(factories)
+            .chain(shops)
+            .chain(specials)
+            .chain(decorations)
+            .max_by_key(|(id, _, _)| *id)?;
+        Some(Highlight {
+            kind: HighlightKind::NewestConstruction,
+            position,
+            caption: format!("Newest construction: a {}", name),
+        })
+    }
+}
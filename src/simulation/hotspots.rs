@@ -0,0 +1,128 @@
+//! Noise and emission hotspots at intersections
+//!
+//! Vehicles standing with their engines running are queueing for the
+//! intersection at the end of their road, so each intersection keeps count
+//! of the idling it causes: vehicle-seconds of idling, a measure of the
+//! noise of engines ticking over and pulling away, and the kilograms of
+//! CO2 they give off meanwhile (see [`super::emissions`]). Both fade with a
+//! half-life of [`HOTSPOT_HALF_LIFE_SECS`], so they follow how the junction
+//! has been working lately rather than all day.
+//!
+//! An intersection's severity is the worse of the two, as a share of
+//! [`HOTSPOT_FULL_IDLE_SECS`] and [`HOTSPOT_FULL_CO2_KG`], and it is a
+//! [`Hotspot`] once that reaches [`HOTSPOT_MIN_SEVERITY`]. Homes within
+//! [`HOTSPOT_RADIUS`] of a hotspot lose appeal (see [`super::decoration`])
+//! in proportion, up to [`HOTSPOT_MAX_POLLUTION`] from each, so a badly
+//! timed junction costs its neighbours the greenery around them and then
+//! some: a home whose appeal falls below nothing pays a larger
+//! short-commute penalty than a plain one.
+
+#[cfg(feature = "serialization")]
+use serde::{Deserialize, Serialize};
+
+use super::decoration::MAX_APPEAL;
+use super::types::{IntersectionId, Position, VehicleType};
+use super::world::SimWorld;
+
+/// Seconds for an intersection's idling to fade to half
+pub const HOTSPOT_HALF_LIFE_SECS: f32 = 60.0;
+
+/// Vehicle-seconds of recent idling that make an intersection as noisy as
+/// it gets (about four vehicles always waiting)
+pub const HOTSPOT_FULL_IDLE_SECS: f32 = 360.0;
+
+/// Kilograms of CO2 recently given off idling that make an intersection as
+/// polluted as it gets
+pub const HOTSPOT_FULL_CO2_KG: f32 = 0.2;
+
+/// Severity from which an intersection counts as a hotspot
+pub const HOTSPOT_MIN_SEVERITY: f32 = 0.05;
+
+/// Distance within which a hotspot takes appeal from homes
+pub const HOTSPOT_RADIUS: f32 = 8.0;
+
+/// Appeal taken from homes in range of a hotspot at full severity
+pub const HOTSPOT_MAX_POLLUTION: f32 = 0.5;
+
+/// Recent idling by vehicles waiting for an intersection
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+pub struct IntersectionIdling {
+    /// Vehicle-seconds of idling, fading over time
+    pub idle_secs: f32,
+    /// Kilograms of CO2 given off idling, fading over time
+    pub co2_kg: f32,
+}
+
+impl IntersectionIdling {
+    /// Count a vehicle of `vehicle_type` idling for `secs`
+    pub fn record(&mut self, vehicle_type: VehicleType, secs: f32) {
+        let profile = vehicle_type.fuel_profile();
+        self.idle_secs += secs;
+        self.co2_kg += secs / 3600.0 * profile.idle_litres_per_hour * profile.co2_kg_per_litre;
+    }
+
+    /// Let `secs` of fading pass
+    pub fn fade(&mut self, secs: f32) {
+        let factor = 0.5_f32.powf(secs / HOTSPOT_HALF_LIFE_SECS);
+        self.idle_secs *= factor;
+        self.co2_kg *= factor;
+    }
+
+    /// How bad the idling is, from 0 to 1
+    pub fn severity(&self) -> f32 {
+        (self.idle_secs / HOTSPOT_FULL_IDLE_SECS)
+            .max(self.co2_kg / HOTSPOT_FULL_CO2_KG)
+            .min(1.0)
+    }
+}
+
+/// An intersection whose waiting traffic makes noise and fumes
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Hotspot {
+    pub intersection: IntersectionId,
+    pub position: Position,
+    /// Vehicle-seconds of recent idling
+    pub idle_secs: f32,
+    /// Kilograms of CO2 recently given off idling
+    pub co2_kg: f32,
+    /// How bad it is, from [`HOTSPOT_MIN_SEVERITY`] to 1
+    pub severity: f32,
+}
+
+impl SimWorld {
+    /// The city's hotspots, worst first
+    pub fn hotspots(&self) -> Vec<Hotspot> {
+        let mut hotspots: Vec<Hotspot> = self
+            .intersections
+            .values()
+            .map(|intersection| Hotspot {
+                intersection: intersection.id,
+                position: intersection.position,
+                idle_secs: intersection.idling.idle_secs,
+                co2_kg: intersection.idling.co2_kg,
+                severity: intersection.idling.severity(),
+            })
+            .filter(|hotspot| hotspot.severity >= HOTSPOT_MIN_SEVERITY)
+            .collect();
+        hotspots.sort_by(|a, b| {
+            b.severity
+                .total_cmp(&a.severity)
+                .then_with(|| a.intersection.cmp(&b.intersection))
+        });
+        hotspots
+    }
+
+    /// Appeal taken from a spot by the hotspots around it, 0 to
+    /// [`MAX_APPEAL`]
+    pub fn pollution_at(&self, position: &Position) -> f32 {
+        self.intersections
+            .values()
+            .filter(|intersection| position.distance(&intersection.position) <= HOTSPOT_RADIUS)
+            .map(|intersection| intersection.idling.severity())
+            .filter(|severity| *severity >= HOTSPOT_MIN_SEVERITY)
+            .map(|severity| severity * HOTSPOT_MAX_POLLUTION)
+            .sum::<f32>()
+            .min(MAX_APPEAL)
+    }
+}
@@ -0,0 +1,110 @@
+//! Ticking cheaply while nothing is going on
+//!
+//! A world with no vehicles, walkers, buses or buildings, and no trips or
+//! routes waiting, has nothing for most of a tick to do: an empty map
+//! before the first building goes down, or one the player has cleared. While
+//! [`SimWorld::is_idle`] holds and power saving is on, [`SimWorld::tick`]
+//! only runs the clock, the game, scenario events, contraflow, signals and
+//! ferries, and the run's recap, speed goal and alarms, and skips the rest.
+//! The skipped steps would have done nothing, so a world ticks the same
+//! with power saving on or off.
+//!
+//! The check is made afresh every tick, so the first building, trip or
+//! vehicle wakes the world fully on the next one. Front-ends can also use
+//! it to redraw less often while the world sits idle in the background.
+
+use super::world::SimWorld;
+
+/// Whether the world skips the idle work, and how many ticks it has
+/// skipped it for
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct IdleMode {
+    /// Whether an idle world takes the cheap tick
+    pub power_saving: bool,
+    /// Ticks taken the cheap way
+    pub idle_ticks: u64,
+    /// Ticks that ran in full
+    pub full_ticks: u64,
+}
+
+impl Default for IdleMode {
+    fn default() -> Self {
+        Self {
+            power_saving: true,
+            idle_ticks: 0,
+            full_ticks: 0,
+        }
+    }
+}
+
+impl IdleMode {
+    /// Share of ticks taken the cheap way, or 0 before the first tick
+    pub fn idle_share(&self) -> f32 {
+        let ticks = self.idle_ticks + self.full_ticks;
+        if ticks == 0 {
+            return 0.0;
+        }
+        self.idle_ticks as f32 / ticks as f32
+    }
+}
+
+impl SimWorld {
+    /// Whether the world has nothing to do but keep time: no vehicles,
+    /// walkers, buses or buildings, and no trips, routes or tows waiting
+    pub fn is_idle(&self) -> bool {
+        self.cars.is_empty()
+            && self.walks.is_empty()
+            && self.apartments.is_empty()
+            && self.factories.is_empty()
+            && self.shops.is_empty()
+            && self.special_buildings.is_empty()
+            && self.bus_routes.is_empty()
+            && self.pending_trips.is_empty()
+            && self.awaiting_tow.is_empty()
+            && self.spawn_queues.values().all(|queue| queue.is_empty())
+            && self.vehicles_awaiting_route() == 0
+    }
+
+    /// Whether an idle world takes the cheap tick
+    pub fn power_saving(&self) -> bool {
+        self.idle.power_saving
+    }
+
+    /// Take the cheap tick while idle, or (with `false`) always tick in full
+    pub fn set_power_saving(&mut self, power_saving: bool) {
+        self.idle.power_saving = power_saving;
+    }
+
+    /// Whether power saving is on and how many ticks were taken each way
+    pub fn idle_mode(&self) -> IdleMode {
+        self.idle
+    }
+
+    /// Whether this tick can be the cheap one, counting it either way
+    pub(super) fn take_idle_tick(&mut self) -> bool {
+        let idle = self.idle.power_saving && self.is_idle();
+        if idle {
+            self.idle.idle_ticks += 1;
+        } else {
+            self.idle.full_ticks += 1;
+        }
+        idle
+    }
+
+    /// The part of a tick an idle world still needs: the clock, the game,
+    /// scenario events, contraflow, signals, ferries and the run's records
+    pub(super) fn tick_idle(&mut self, delta_secs: f32) {
+        if let Some(game_state) = &mut self.game_state {
+            game_state.update(delta_secs);
+        }
+        self.update_scenario_events(delta_secs);
+        self.update_contraflow();
+        self.update_intersections(delta_secs);
+        self.update_ferries(delta_secs);
+        #[cfg(feature = "stats")]
+        self.record_recap();
+        self.update_speed_goal(delta_secs);
+        self.update_alarms();
+        self.warnings.flush(self.time);
+    }
+}
@@ -0,0 +1,100 @@
+//! Breakdowns and collisions
+//!
+//! While a game is running, any vehicle can break down and congested roads
+//! can see collisions. Either way the vehicles involved stop where they are
+//! for a while, holding up the traffic behind them, and the player pays for
+//! the cleanup unless they are insured (see [`super::GameState`]). Broken-down
+//! vehicles wait for a tow truck.
+
+#[cfg(feature = "serialization")]
+use serde::{Deserialize, Serialize};
+
+use super::achievements::CONGESTED_ROAD_DENSITY;
+use super::fleet::wear_breakdown_factor;
+use super::render::VisualId;
+use super::types::{CarId, RoadId, SECONDS_PER_DAY};
+use super::world::SimWorld;
+
+/// Seconds a broken-down vehicle stays stopped unless a tow truck clears it
+/// sooner (see [`super::towing`])
+pub const BREAKDOWN_STALL_SECS: f32 = 20.0;
+
+/// Seconds the vehicles in a collision stay stopped
+pub const COLLISION_STALL_SECS: f32 = 10.0;
+
+/// Kind of incident
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serialization", serde(rename_all = "lowercase"))]
+pub enum IncidentKind {
+    Breakdown,
+    Collision,
+}
+
+impl SimWorld {
+    /// Roll for breakdowns and collisions, stopping the vehicles involved and
+    /// charging the game for each incident
+    ///
+    /// Worlds without a game state have no incidents.
+    pub(super) fn update_incidents(&mut self, delta_secs: f32) {
+        let Some(economy) = self
+            .game_state
+            .as_ref()
+            .map(|game_state| &game_state.economy)
+        else {
+            return;
+        };
+        let hours = delta_secs / SECONDS_PER_DAY * 24.0;
+        let breakdown_chance = economy.breakdowns_per_vehicle_hour * hours;
+        let collision_chance = economy.collisions_per_congested_road_hour * hours;
+
+        let car_ids: Vec<CarId> = self.cars.keys().copied().collect();
+        for car_id in car_ids {
+            let rolled = self.random_range(0.0..1.0);
+            let Some(car) = self.cars.get_mut(&car_id) else {
+                continue;
+            };
+            // A stopped vehicle can't break down again, nor can one yet to
+            // set off; worn ones break down more often
+            if car.stalled_secs > 0.0
+                || car.awaiting_route.is_some()
+                || car.queued_at_start
+                || rolled >= breakdown_chance * wear_breakdown_factor(car.wear)
+            {
+                continue;
+            }
+            car.stalled_secs = BREAKDOWN_STALL_SECS;
+            self.changes.mark(VisualId::Vehicle(car_id));
+            self.awaiting_tow.push(car_id);
+            self.record_incident(IncidentKind::Breakdown);
+        }
+
+        let congested: Vec<RoadId> = self
+            .road_network
+            .roads()
+            .keys()
+            .copied()
+            .filter(|road_id| {
+                self.road_network.calculate_traffic_density(*road_id) >= CONGESTED_ROAD_DENSITY
+            })
+            .collect();
+        for road_id in congested {
+            if self.random_range(0.0..1.0) >= collision_chance {
+                continue;
+            }
+            for car_id in self.road_network.get_cars_on_road(road_id) {
+                if let Some(car) = self.cars.get_mut(&car_id) {
+                    car.stalled_secs = car.stalled_secs.max(COLLISION_STALL_SECS);
+                    self.changes.mark(VisualId::Vehicle(car_id));
+                }
+            }
+            self.record_incident(IncidentKind::Collision);
+        }
+    }
+
+    fn record_incident(&mut self, kind: IncidentKind) {
+        if let Some(game_state) = &mut self.game_state {
+            game_state.record_incident(kind);
+        }
+    }
+}
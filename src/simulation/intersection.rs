@@ -2,10 +2,19 @@
 //!
 //! Standalone implementation that doesn't depend on Bevy.
 
-use super::types::{CarId, IntersectionId, Position};
+use std::collections::VecDeque;
+
+#[cfg(feature = "serialization")]
+use serde::{Deserialize, Serialize};
+
+use super::control::IntersectionControl;
+use super::hotspots::IntersectionIdling;
+use super::signal::TrafficSignal;
+use super::types::{CarId, IntersectionId, Position, RoadId};
 
 /// An intersection in the traffic simulation
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
 #[allow(dead_code)]
 pub struct SimIntersection {
     pub id: IntersectionId,
@@ -14,8 +23,19 @@ pub struct SimIntersection {
     pub occupied_by: Option<CarId>,
     /// Timer for how long the current car has been in the intersection
     pub occupation_timer: f32,
-    /// Time it takes for a car to cross through the intersection
-    pub crossing_time: f32,
+    /// Traffic signal controlling which roads may enter, if any
+    pub signal: Option<TrafficSignal>,
+    /// How the intersection decides who goes next; a
+    /// [traffic light](IntersectionControl::TrafficLight) whenever it has a
+    /// signal
+    pub control: IntersectionControl,
+    /// Vehicles stopped at a stop sign, in the order they stopped
+    pub stop_queue: VecDeque<CarId>,
+    /// Seconds since the intersection was last clear of vehicles
+    /// crossing it, or since it was built
+    pub clear_secs: f32,
+    /// Recent idling by vehicles waiting for the intersection
+    pub idling: IntersectionIdling,
 }
 
 impl SimIntersection {
@@ -25,7 +45,11 @@ impl SimIntersection {
             position,
             occupied_by: None,
             occupation_timer: 0.0,
-            crossing_time: 0.25,
+            signal: None,
+            control: IntersectionControl::Uncontrolled,
+            stop_queue: VecDeque::new(),
+            clear_secs: 0.0,
+            idling: IntersectionIdling::default(),
         }
     }
 
@@ -44,10 +68,11 @@ impl SimIntersection {
         self.occupied_by == Some(car_id)
     }
 
-    /// Check if a car can proceed through the intersection
+    /// Check if a car that takes `crossing_time` seconds to cross can
+    /// proceed through the intersection
     /// This handles both acquiring the lock and checking wait time
     /// Returns true if the car can proceed, false if it must wait
-    pub fn can_proceed(&mut self, car_id: CarId) -> bool {
+    pub fn can_proceed(&mut self, car_id: CarId, crossing_time: f32) -> bool {
         match self.occupied_by {
             None => {
                 // Intersection is free, acquire it and start crossing
@@ -57,7 +82,7 @@ impl SimIntersection {
             }
             Some(current_car) if current_car == car_id => {
                 // This car already has the lock, check if crossing time has elapsed
-                self.occupation_timer >= self.crossing_time
+                self.occupation_timer >= crossing_time
             }
             Some(_) => {
                 // Another car has the lock, must wait
@@ -66,10 +91,78 @@ impl SimIntersection {
         }
     }
 
-    /// Update the occupation timer
+    /// Check if a car may go through the intersection under its
+    /// [control](IntersectionControl), acquiring it if so (see
+    /// [`Self::can_proceed`])
+    ///
+    /// `stopped` is whether the car has stood at the line long enough for a
+    /// stop sign, and `yield_gap` the seconds the intersection must have
+    /// been clear before it sets off at a yield. A car already crossing
+    /// carries on whatever the control.
+    pub fn admit(
+        &mut self,
+        car_id: CarId,
+        crossing_time: f32,
+        stopped: bool,
+        yield_gap: f32,
+    ) -> bool {
+        if self.is_held_by(car_id) {
+            return self.can_proceed(car_id, crossing_time);
+        }
+        match self.control {
+            IntersectionControl::Uncontrolled | IntersectionControl::TrafficLight => {
+                self.can_proceed(car_id, crossing_time)
+            }
+            IntersectionControl::Stop => {
+                if !stopped {
+                    return false;
+                }
+                if !self.stop_queue.contains(&car_id) {
+                    self.stop_queue.push_back(car_id);
+                }
+                if self.stop_queue.front() != Some(&car_id) {
+                    return false;
+                }
+                let proceed = self.can_proceed(car_id, crossing_time);
+                if self.is_held_by(car_id) {
+                    self.stop_queue.pop_front();
+                }
+                proceed
+            }
+            IntersectionControl::Yield => {
+                self.occupied_by.is_none()
+                    && self.clear_secs >= yield_gap
+                    && self.can_proceed(car_id, crossing_time)
+            }
+        }
+    }
+
+    /// Update the occupation timer and the signal's cycle
     pub fn update_timer(&mut self, delta_time: f32) {
         if self.occupied_by.is_some() {
             self.occupation_timer += delta_time;
+            self.clear_secs = 0.0;
+        } else {
+            self.clear_secs += delta_time;
         }
+        self.idling.fade(delta_time);
+        if let Some(signal) = &mut self.signal {
+            signal.update(delta_time);
+        }
+    }
+
+    /// Check if the signal lets cars in from a road (always true without a signal)
+    pub fn is_green_for(&self, road_id: RoadId) -> bool {
+        self.signal
+            .as_ref()
+            .is_none_or(|signal| signal.is_green(road_id))
+    }
+
+    /// Have the signal give a road its green early for a late truck,
+    /// returning whether it did (never without a signal)
+    pub fn preempt_signal(&mut self, road_id: RoadId) -> bool {
+        self.signal
+            .as_mut()
+            .is_some_and(|signal| signal.preempt(road_id))
     }
 }
@@ -0,0 +1,148 @@
+//! Consistency checks on the world's state
+//!
+//! [`SimWorld::check_invariants`] checks that the world's collections agree
+//! with each other: the road graph with its lookups, vehicles with the roads
+//! they are on, and buildings with the vehicles they have out. Nothing in a
+//! normal run should ever break one; long soak runs call it every so often
+//! so a slow corruption is caught near where it started.
+
+use std::collections::HashMap;
+
+use anyhow::{bail, Result};
+
+use super::types::CarId;
+use super::world::SimWorld;
+
+/// How far past the end of its road a vehicle may be before it has moved on
+const ROAD_END_TOLERANCE: f32 = 1.0;
+
+impl SimWorld {
+    /// Check the world's state is consistent, failing with every
+    /// inconsistency found
+    pub fn check_invariants(&self) -> Result<()> {
+        let mut errors = self.road_network.consistency_errors();
+
+        for id in self.intersections.keys() {
+            if self.road_network.get_intersection_position(*id).is_none() {
+                errors.push(format!(
+                    "intersection {} isn't in the road network",
+                    id.0 .0
+                ));
+            }
+        }
+        if self.intersections.len() != self.road_network.intersection_count() {
+            errors.push(format!(
+                "{} intersections but {} in the road network",
+                self.intersections.len(),
+                self.road_network.intersection_count()
+            ));
+        }
+
+        for (id, car) in &self.cars {
+            if !car.position.x.is_finite() || !car.position.z.is_finite() {
+                errors.push(format!("car {} is at {:?}", id.0 .0, car.position));
+            }
+            // A vehicle getting off a removed road isn't on one
+            if car.awaiting_route.is_some() || car.road_exit.is_some() {
+                continue;
+            }
+            let Some(road) = self.road_network.get_road(car.current_road) else {
+                errors.push(format!(
+                    "car {} is on missing road {}",
+                    id.0 .0, car.current_road.0 .0
+                ));
+                continue;
+            };
+            let distance = car.distance_along_road.into_inner();
+            if !(0.0..=road.length + ROAD_END_TOLERANCE).contains(&distance) {
+                errors.push(format!(
+                    "car {} is {} along road {} of length {}",
+                    id.0 .0, distance, road.id.0 .0, road.length
+                ));
+            }
+        }
+        for (road_id, distance, car_id) in self.road_network.tracked_cars() {
+            match self.cars.get(&car_id) {
+                None => errors.push(format!(
+                    "missing car {} is tracked on road {}",
+                    car_id.0 .0, road_id.0 .0
+                )),
+                Some(car) if car.current_road != road_id => errors.push(format!(
+                    "car {} is tracked {} along road {} but is on road {}",
+                    car_id.0 .0, distance, road_id.0 .0, car.current_road.0 .0
+                )),
+                Some(_) => {}
+            }
+        }
+
+        // Each vehicle out belongs to at most one building
+        let mut owners: HashMap<CarId, String> = HashMap::new();
+        let mut claim = |car_id: CarId, owner: String, errors: &mut Vec<String>| {
+            if !self.cars.contains_key(&car_id) {
+                errors.push(format!("{} has missing car {} out", owner, car_id.0 .0));
+            }
+            if let Some(other) = owners.insert(car_id, owner.clone()) {
+                errors.push(format!(
+                    "car {} is out for both {} and {}",
+                    car_id.0 .0, other, owner
+                ));
+            }
+        };
+        for (id, apartment) in &self.apartments {
+            for car_id in apartment.cars.iter().flatten() {
+                claim(*car_id, format!("apartment {}", id.0 .0), &mut errors);
+            }
+        }
+        for (id, factory) in &self.factories {
+            if let Some(car_id) = factory.truck {
+                claim(car_id, format!("factory {}", id.0 .0), &mut errors);
+            }
+        }
+        for (id, building) in &self.special_buildings {
+            for car_id in [building.ambulance, building.tow_truck, building.van]
+                .into_iter()
+                .flatten()
+            {
+                claim(car_id, format!("special building {}", id.0 .0), &mut errors);
+            }
+        }
+
+        let building_sites = self
+            .apartments
+            .values()
+            .map(|apartment| (apartment.intersection_id, "apartment"))
+            .chain(
+                self.factories
+                    .values()
+                    .map(|factory| (factory.intersection_id, "factory")),
+            )
+            .chain(
+                self.shops
+                    .values()
+                    .map(|shop| (shop.intersection_id, "shop")),
+            )
+            .chain(
+                self.special_buildings
+                    .values()
+                    .map(|building| (building.intersection_id, "special building")),
+            );
+        for (intersection_id, kind) in building_sites {
+            if !self.intersections.contains_key(&intersection_id) {
+                errors.push(format!(
+                    "{} at missing intersection {}",
+                    kind, intersection_id.0 .0
+                ));
+            }
+        }
+
+        if !errors.is_empty() {
+            bail!(
+                "{} invariant(s) broken at {:.1}s:\n  {}",
+                errors.len(),
+                self.time,
+                errors.join("\n  ")
+            );
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,298 @@
+//! Junction presets
+//!
+//! Stamps a common junction pattern down in one go: a T-junction, a
+//! crossroads with a traffic signal, or a diamond interchange where a main
+//! road bridges over a cross road and four ramps join the two. A junction
+//! is placed by its centre and the direction its main road runs; the ends
+//! of its arms snap onto nearby intersections and roads like road ends, so
+//! it can be dropped into an existing network. Like a cul-de-sac, the whole
+//! junction is paid for up front, so it is either built completely or not
+//! at all.
+
+use anyhow::{ensure, Result};
+#[cfg(feature = "serialization")]
+use serde::{Deserialize, Serialize};
+
+use super::economy::EconomyConfig;
+use super::signal::{SignalPhase, DEFAULT_PHASE_SECS};
+use super::types::{IntersectionId, Position, RoadId, RoadKind, MIN_ROAD_LENGTH};
+use super::world::SimWorld;
+
+/// Length of each arm from the centre of a junction to its end
+pub const JUNCTION_ARM_LENGTH: f32 = 8.0;
+
+/// How far from the centre of a diamond interchange its ramps meet the
+/// main road and the cross road
+pub const DIAMOND_RAMP_OFFSET: f32 = 4.0;
+
+/// Kind of junction preset
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serialization", serde(rename_all = "snake_case"))]
+pub enum JunctionKind {
+    /// A side road joining the main road from the right
+    #[default]
+    Tee,
+    /// Two roads crossing, with a signal giving each its turn
+    Cross,
+    /// The main road bridging over a cross road, joined to it by four ramps
+    Diamond,
+}
+
+impl JunctionKind {
+    /// Every kind, in the order the build tool cycles through them
+    pub const ALL: [JunctionKind; 3] = [
+        JunctionKind::Tee,
+        JunctionKind::Cross,
+        JunctionKind::Diamond,
+    ];
+
+    /// Name shown to players
+    pub fn label(self) -> &'static str {
+        match self {
+            JunctionKind::Tee => "T-junction",
+            JunctionKind::Cross => "crossroads",
+            JunctionKind::Diamond => "diamond interchange",
+        }
+    }
+}
+
+/// Where a junction's intersections go and which roads join them
+#[derive(Debug, Clone, PartialEq)]
+pub struct JunctionLayout {
+    /// Intersection positions, the ends of the arms first
+    pub points: Vec<Position>,
+    /// How many of the points are arm ends, which snap onto the network
+    pub arm_ends: usize,
+    /// Two-way roads as the indices of the points they join, and their kind
+    pub roads: Vec<(usize, usize, RoadKind)>,
+    /// The point given a signal, and for each of its phases the points whose
+    /// roads into it have a green light
+    pub signal: Option<(usize, Vec<Vec<usize>>)>,
+}
+
+/// What a junction was built from
+#[derive(Debug, Clone, PartialEq)]
+pub struct Junction {
+    /// Intersections in the order of [`JunctionLayout::points`]
+    pub intersections: Vec<IntersectionId>,
+    /// The two roads of each of [`JunctionLayout::roads`], the first running
+    /// from its first point to its second
+    pub roads: Vec<(RoadId, RoadId)>,
+}
+
+/// Lay out a junction centred on `center` with its main road running
+/// towards `toward`
+pub fn junction_layout(kind: JunctionKind, center: Position, toward: Position) -> JunctionLayout {
+    let along = |distance: f32| {
+        let dx = toward.x - center.x;
+        let dz = toward.z - center.z;
+        let len = (dx * dx + dz * dz).sqrt().max(f32::EPSILON);
+        Position::new(
+            center.x + dx / len * distance,
+            center.y,
+            center.z + dz / len * distance,
+        )
+    };
+    let across = |distance: f32| {
+        let offset = center.perpendicular_offset(&toward, distance);
+        Position::new(center.x + offset.x, center.y, center.z + offset.z)
+    };
+    let arm = JUNCTION_ARM_LENGTH;
+    let street = RoadKind::Street;
+    match kind {
+        JunctionKind::Tee => JunctionLayout {
+            points: vec![along(-arm), along(arm), across(arm), center],
+            arm_ends: 3,
+            roads: vec![(0, 3, street), (3, 1, street), (3, 2, street)],
+            signal: None,
+        },
+        JunctionKind::Cross => JunctionLayout {
+            points: vec![along(-arm), along(arm), across(arm), across(-arm), center],
+            arm_ends: 4,
+            roads: vec![
+                (0, 4, street),
+                (4, 1, street),
+                (2, 4, street),
+                (4, 3, street),
+            ],
+            // The main road's traffic goes both ways at once, then the
+            // cross road's
+            signal: Some((4, vec![vec![0, 1], vec![2, 3]])),
+        },
+        JunctionKind::Diamond => {
+            let ramp = DIAMOND_RAMP_OFFSET;
+            JunctionLayout {
+                points: vec![
+                    along(-arm),
+                    along(arm),
+                    across(arm),
+                    across(-arm),
+                    // Where the ramps leave the main road, either side of
+                    // the bridge
+                    along(-ramp),
+                    along(ramp),
+                    // Where they meet the cross road, either side of it
+                    across(ramp),
+                    across(-ramp),
+                ],
+                arm_ends: 4,
+                roads: vec![
+                    (0, 4, street),
+                    (4, 5, RoadKind::Bridge),
+                    (5, 1, street),
+                    (2, 6, street),
+                    (6, 7, street),
+                    (7, 3, street),
+                    (4, 6, street),
+                    (6, 5, street),
+                    (5, 7, street),
+                    (7, 4, street),
+                ],
+                signal: None,
+            }
+        }
+    }
+}
+
+/// Cost of a junction
+///
+/// The same as building it by hand: a road or bridge for each of its roads.
+/// Signals are free.
+pub fn junction_cost(economy: &EconomyConfig, kind: JunctionKind) -> i32 {
+    let layout = junction_layout(
+        kind,
+        Position::new(0.0, 0.0, 0.0),
+        Position::new(0.0, 0.0, 1.0),
+    );
+    layout
+        .roads
+        .iter()
+        .map(|(_, _, road_kind)| match road_kind {
+            RoadKind::Street => economy.cost_road,
+            RoadKind::Bridge => economy.cost_bridge,
+            RoadKind::Ferry => economy.cost_ferry,
+        })
+        .sum()
+}
+
+impl SimWorld {
+    /// Where a junction's points would end up: the arm ends where they snap
+    /// to, the rest where the layout puts them
+    fn junction_points(&self, layout: &JunctionLayout, snap_distance: f32) -> Vec<Position> {
+        layout
+            .points
+            .iter()
+            .enumerate()
+            .map(|(index, point)| {
+                if index < layout.arm_ends {
+                    self.snapped_position(*point, snap_distance)
+                } else {
+                    *point
+                }
+            })
+            .collect()
+    }
+
+    /// Check a junction can be laid out, with every road on land (bar
+    /// bridges) and none too short
+    fn validate_junction(
+        &self,
+        center: Position,
+        toward: Position,
+        layout: &JunctionLayout,
+        snap_distance: f32,
+    ) -> Result<()> {
+        ensure!(
+            center.distance(&toward) > 0.0,
+            "Pick a direction for the junction's main road"
+        );
+        let points = self.junction_points(layout, snap_distance);
+        for (start, end, kind) in &layout.roads {
+            let (start, end) = (&points[*start], &points[*end]);
+            ensure!(
+                start.distance(end) >= MIN_ROAD_LENGTH,
+                "The junction's arms would meet"
+            );
+            self.check_crossing(start, end, *kind)?;
+        }
+        Ok(())
+    }
+
+    /// Build a junction of `kind` centred on `center`, with its main road
+    /// running towards `toward`
+    pub fn add_junction(
+        &mut self,
+        kind: JunctionKind,
+        center: Position,
+        toward: Position,
+        snap_distance: f32,
+    ) -> Result<Junction> {
+        let layout = junction_layout(kind, center, toward);
+        self.validate_junction(center, toward, &layout, snap_distance)?;
+
+        let mut intersections = Vec::with_capacity(layout.points.len());
+        for (index, point) in layout.points.iter().enumerate() {
+            intersections.push(if index < layout.arm_ends {
+                self.find_or_create_intersection(*point, snap_distance)?
+            } else {
+                self.add_intersection(*point)
+            });
+        }
+        let mut roads = Vec::with_capacity(layout.roads.len());
+        for (start, end, road_kind) in &layout.roads {
+            let (forward, backward) =
+                self.add_two_way_road(intersections[*start], intersections[*end])?;
+            self.road_network.set_road_kind(forward, *road_kind)?;
+            self.road_network.set_road_kind(backward, *road_kind)?;
+            roads.push((forward, backward));
+        }
+        if let Some((signalled, phases)) = &layout.signal {
+            let phases = phases
+                .iter()
+                .map(|from| SignalPhase {
+                    green: layout
+                        .roads
+                        .iter()
+                        .zip(&roads)
+                        .filter_map(|((start, end, _), (forward, backward))| {
+                            if end == signalled && from.contains(start) {
+                                Some(*forward)
+                            } else if start == signalled && from.contains(end) {
+                                Some(*backward)
+                            } else {
+                                None
+                            }
+                        })
+                        .collect(),
+                    duration: DEFAULT_PHASE_SECS,
+                })
+                .collect();
+            self.set_signal(intersections[*signalled], Some(phases))?;
+        }
+
+        Ok(Junction {
+            intersections,
+            roads,
+        })
+    }
+
+    /// Build a junction with game cost checking
+    /// Returns Some(junction) if successful, None if insufficient funds
+    pub fn try_add_junction(
+        &mut self,
+        kind: JunctionKind,
+        center: Position,
+        toward: Position,
+        snap_distance: f32,
+    ) -> Result<Option<Junction>> {
+        // Check the layout before charging for it
+        let layout = junction_layout(kind, center, toward);
+        self.validate_junction(center, toward, &layout, snap_distance)?;
+        if !self.spend_for_game(|economy| junction_cost(economy, kind)) {
+            return Ok(None);
+        }
+        self.add_junction(kind, center, toward, snap_distance)
+            .map(Some)
+    }
+}
@@ -0,0 +1,213 @@
+//! Labor market statistics
+//!
+//! Every worker a house sends to a factory is a shift requested. The shift
+//! is fulfilled if the factory takes the worker on when they arrive, and
+//! rejected if it turns them away (its truck is out or it is on strike).
+//! Workers who never get there, such as when a road is removed under them,
+//! count as requested only. The counts are kept per in-game day and per
+//! factory and house, so a layout where labor can't reach industry shows
+//! up as a low employment rate at the buildings it cuts off.
+
+use std::fmt::Write as _;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+
+use super::diagnostics::{map_bytes, vec_bytes};
+use super::types::{ApartmentId, FactoryId, IntersectionId, SimMap, SECONDS_PER_DAY};
+use super::world::SimWorld;
+
+/// Header row of the labor stats CSV
+pub const LABOR_STATS_CSV_HEADER: &str = "scope,id,requested,fulfilled,rejected,employment_rate";
+
+/// What became of a worker sent to a factory
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum ShiftOutcome {
+    /// The worker set off
+    Requested,
+    /// The factory took the worker on
+    Fulfilled,
+    /// The factory turned the worker away
+    Rejected,
+}
+
+/// Worker shifts requested, fulfilled and rejected
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LaborCounts {
+    pub requested: u32,
+    pub fulfilled: u32,
+    pub rejected: u32,
+}
+
+impl LaborCounts {
+    fn record(&mut self, outcome: ShiftOutcome) {
+        match outcome {
+            ShiftOutcome::Requested => self.requested += 1,
+            ShiftOutcome::Fulfilled => self.fulfilled += 1,
+            ShiftOutcome::Rejected => self.rejected += 1,
+        }
+    }
+
+    /// Share of requested shifts that were worked, or `None` before any
+    /// were requested
+    pub fn employment_rate(&self) -> Option<f32> {
+        (self.requested > 0).then(|| self.fulfilled as f32 / self.requested as f32)
+    }
+}
+
+/// Worker shifts of one in-game day
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LaborDay {
+    /// Days since the run began, from 0
+    pub day: u32,
+    pub counts: LaborCounts,
+}
+
+/// Worker shifts by day, factory and house
+#[derive(Debug, Clone, Default)]
+pub(super) struct LaborStats {
+    /// In day order, skipping days without any shifts
+    days: Vec<LaborDay>,
+    factories: SimMap<FactoryId, LaborCounts>,
+    apartments: SimMap<ApartmentId, LaborCounts>,
+}
+
+impl LaborStats {
+    /// Entries across its collections
+    pub(super) fn len(&self) -> usize {
+        self.days.len() + self.factories.len() + self.apartments.len()
+    }
+
+    /// Approximate bytes on the heap
+    pub(super) fn memory_bytes(&self) -> usize {
+        vec_bytes(&self.days) + map_bytes(&self.factories) + map_bytes(&self.apartments)
+    }
+}
+
+/// Counts in building id order
+fn sorted<K: Copy>(counts: &SimMap<K, LaborCounts>) -> Vec<(K, LaborCounts)> {
+    counts.iter().map(|(id, c)| (*id, *c)).collect()
+}
+
+impl SimWorld {
+    /// Count a worker shift at the house the worker lives in and the factory
+    /// they were sent to
+    pub(super) fn record_shift(
+        &mut self,
+        apartment_id: ApartmentId,
+        factory_id: FactoryId,
+        outcome: ShiftOutcome,
+    ) {
+        let day = (self.time / SECONDS_PER_DAY) as u32;
+        let stats = &mut self.labor_stats;
+        if stats.days.last().is_none_or(|last| last.day != day) {
+            stats.days.push(LaborDay {
+                day,
+                counts: LaborCounts::default(),
+            });
+        }
+        if let Some(today) = stats.days.last_mut() {
+            today.counts.record(outcome);
+        }
+        stats.factories.entry(factory_id).or_default().record(outcome);
+        stats
+            .apartments
+            .entry(apartment_id)
+            .or_default()
+            .record(outcome);
+    }
+
+    /// Count a worker's arrival at the factory at `factory`, whether it took
+    /// them on or turned them away
+    pub(super) fn record_worker_arrival(
+        &mut self,
+        apartment_id: Option<ApartmentId>,
+        factory: IntersectionId,
+        accepted: bool,
+    ) {
+        let factory_id = self
+            .factories
+            .values()
+            .find(|f| f.intersection_id == factory)
+            .map(|f| f.id);
+        if let (Some(apartment_id), Some(factory_id)) = (apartment_id, factory_id) {
+            let outcome = if accepted {
+                ShiftOutcome::Fulfilled
+            } else {
+                ShiftOutcome::Rejected
+            };
+            self.record_shift(apartment_id, factory_id, outcome);
+        }
+    }
+
+    /// Worker shifts by in-game day, in day order
+    ///
+    /// Days without any shifts are left out.
+    pub fn labor_by_day(&self) -> &[LaborDay] {
+        &self.labor_stats.days
+    }
+
+    /// Worker shifts over the whole run
+    pub fn labor_totals(&self) -> LaborCounts {
+        self.labor_stats
+            .days
+            .iter()
+            .fold(LaborCounts::default(), |total, day| LaborCounts {
+                requested: total.requested + day.counts.requested,
+                fulfilled: total.fulfilled + day.counts.fulfilled,
+                rejected: total.rejected + day.counts.rejected,
+            })
+    }
+
+    /// Worker shifts sent to each factory, in factory id order
+    ///
+    /// Factories that have since been demolished are included.
+    pub fn labor_by_factory(&self) -> Vec<(FactoryId, LaborCounts)> {
+        sorted(&self.labor_stats.factories)
+    }
+
+    /// Worker shifts from each house, in house id order
+    ///
+    /// Houses that have since been demolished are included.
+    pub fn labor_by_apartment(&self) -> Vec<(ApartmentId, LaborCounts)> {
+        sorted(&self.labor_stats.apartments)
+    }
+
+    /// Labor statistics as CSV: a row per day, then per factory, then per
+    /// house
+    ///
+    /// The employment rate is left empty where no shifts were requested.
+    pub fn labor_stats_csv(&self) -> String {
+        let mut csv = format!("{}\n", LABOR_STATS_CSV_HEADER);
+        let days = self
+            .labor_by_day()
+            .iter()
+            .map(|day| ("day", day.day as usize, day.counts));
+        let factories = self
+            .labor_by_factory()
+            .into_iter()
+            .map(|(id, counts)| ("factory", id.0 .0, counts));
+        let apartments = self
+            .labor_by_apartment()
+            .into_iter()
+            .map(|(id, counts)| ("apartment", id.0 .0, counts));
+        for (scope, id, counts) in days.chain(factories).chain(apartments) {
+            let rate = counts
+                .employment_rate()
+                .map_or(String::new(), |rate| format!("{:.3}", rate));
+            // Writing to a String can't fail
+            let _ = writeln!(
+                csv,
+                "{},{},{},{},{},{}",
+                scope, id, counts.requested, counts.fulfilled, counts.rejected, rate
+            );
+        }
+        csv
+    }
+
+    /// Write the labor statistics to a CSV file
+    pub fn save_labor_stats_csv(&self, path: &Path) -> Result<()> {
+        std::fs::write(path, self.labor_stats_csv())
+            .with_context(|| format!("Failed to write labor stats {}", path.display()))
+    }
+}
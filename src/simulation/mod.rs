@@ -4,37 +4,353 @@
 //! independently of the Bevy game engine. It can be tested via console
 //! without needing to boot up the full game.
 
+mod achievements;
+mod alarms;
+#[cfg(feature = "stats")]
+mod anomalies;
+mod arrivals;
+mod assignment;
+mod audit;
+#[cfg(feature = "generator")]
+mod autopilot;
+mod bounds;
 mod building;
+mod calibration;
+#[cfg(feature = "serialization")]
+mod camera_tour;
 mod car;
 mod car_manager;
+mod car_pool;
+#[cfg(feature = "stats")]
+mod catchment;
+mod command;
+mod content;
+mod contraflow;
+mod control;
+mod cornering;
+mod crossing;
+mod cul_de_sac;
+mod decoration;
+mod demand;
+mod diagnostics;
+mod driveways;
+mod driving_side;
+mod economy;
+#[cfg(feature = "serialization")]
+mod economy_reload;
+mod elevation;
+mod emissions;
+#[cfg(feature = "generator")]
+mod environment;
+mod eta;
+mod events;
 mod factory;
+mod ferry;
+mod fleet;
+mod freight;
 mod game_state;
+mod handling;
+mod highlights;
+mod hotspots;
+mod idle;
+mod incidents;
 mod intersection;
+mod invariants;
+mod junction;
+#[cfg(feature = "stats")]
+mod labor_stats;
+mod network_diff;
+mod path_cache;
+mod render;
+#[cfg(feature = "stats")]
+mod report;
+mod pending_trips;
+mod personality;
+mod placement;
+mod pricing;
+mod raster;
+mod recap;
+mod region;
+mod rng;
 mod road_network;
+mod road_removal;
+#[cfg(feature = "stats")]
+mod road_stats;
+mod routing;
+mod rules;
+mod scenario_events;
+#[cfg(feature = "serialization")]
+mod save;
+mod score;
+mod sensors;
+mod signal;
+#[cfg(feature = "stats")]
+mod signal_advice;
+#[cfg(feature = "serialization")]
+mod snapshot;
+mod spawning;
+mod special;
+mod speed_goal;
+mod terrain;
+mod towing;
+mod transit;
+#[cfg(feature = "stats")]
+mod trip_stats;
 mod types;
+mod upgrades;
+mod walking;
+mod warnings;
 mod world;
 
 // Re-export public types for external use
 // These may not be used within this crate but are part of the public API
 #[allow(unused_imports)]
+pub use achievements::{
+    Achievement, AchievementTracker, ACHIEVEMENT_DELIVERY_COUNT, ACHIEVEMENT_FREE_FLOW_CARS,
+    ACHIEVEMENT_LOW_FUNDS, ACHIEVEMENT_LOW_FUNDS_SECS, CONGESTED_ROAD_DENSITY,
+};
+#[allow(unused_imports)]
+pub use alarms::{AlarmConfig, AlarmMetric, AlarmReport};
+#[cfg(feature = "stats")]
+#[allow(unused_imports)]
+pub use anomalies::{Anomaly, AnomalyConfig, HealthReport, ANOMALY_MAX_TRIP_SECS};
+#[allow(unused_imports)]
+pub use assignment::{
+    Assignment, AssignmentConfig, OdDemand, RoadFlow, ASSIGNMENT_GAP_TOLERANCE,
+};
+#[allow(unused_imports)]
+pub use calibration::{
+    load_road_counts, parse_road_counts, CalibratedRoad, Calibration, CalibrationConfig,
+    DemandWeight, RoadCount, ROAD_COUNTS_CSV_HEADER,
+};
+#[allow(unused_imports)]
+pub use audit::{AuditTrip, MissingLink, NetworkAudit, UnreachablePair};
+#[cfg(feature = "generator")]
+#[allow(unused_imports)]
+pub use autopilot::{Autopilot, AUTOPILOT_DECISION_SECS, AUTOPILOT_GRID_SPACING};
+#[allow(unused_imports)]
+pub use bounds::{Equirectangular, MapProjection, WorldBounds, DEFAULT_WORLD_SIZE};
+#[allow(unused_imports)]
 pub use building::{SimFactory, SimApartment, SimShop};
+#[cfg(feature = "serialization")]
+#[allow(unused_imports)]
+pub use camera_tour::{CameraKeyframe, CameraPose, CameraTour};
 #[allow(unused_imports)]
 pub use car::{CarUpdateResult, SimCar};
 #[allow(unused_imports)]
-pub use factory::FACTORY_WORK_TIME;
+pub use car_pool::{CarPool, CAR_POOL_CAPACITY};
+#[cfg(feature = "stats")]
+#[allow(unused_imports)]
+pub use catchment::{Attribution, ShopCatchment};
+#[allow(unused_imports)]
+pub use command::BuildCommand;
+#[allow(unused_imports)]
+pub use contraflow::{ContraflowRoad, ContraflowWindow, RUSH_HOUR_CONTRAFLOW};
+#[allow(unused_imports)]
+pub use content::{
+    BuildingDef, BuildingKind, ContentPack, ContentRegistry, PlacedBuilding, VehicleDef,
+};
+#[allow(unused_imports)]
+pub use control::{IntersectionControl, STOP_SIGN_SECS, YIELD_GAP_SECS};
+#[allow(unused_imports)]
+pub use cornering::{CurveSpeedLimit, TurnArc, DEFAULT_LATERAL_ACCEL, DEFAULT_MIN_CORNER_SPEED};
+#[allow(unused_imports)]
+pub use crossing::{crossing_distance, crossing_secs, LANE_WIDTH};
+#[allow(unused_imports)]
+pub use cul_de_sac::{
+    cul_de_sac_cost, cul_de_sac_lots, CulDeSac, CUL_DE_SAC_DRIVEWAY_LENGTH, CUL_DE_SAC_LOT_SPACING,
+    CUL_DE_SAC_MAX_LOTS,
+};
+#[allow(unused_imports)]
+pub use decoration::{
+    DecorationKind, SimDecoration, APPEAL_PENALTY_RELIEF, DECORATION_CLEARANCE, MAX_APPEAL,
+};
+#[allow(unused_imports)]
+pub use demand::{DemandRamp, RampCurve, SlaBreach};
+#[allow(unused_imports)]
+pub use diagnostics::{
+    format_bytes, BudgetOverrun, BudgetWatch, EntityBudgets, MemoryReport, MemoryUsage,
+};
+#[allow(unused_imports)]
+pub use driveways::SPAWN_CLEARANCE;
+#[allow(unused_imports)]
+pub use driving_side::DrivingSide;
+#[allow(unused_imports)]
+pub use economy::{CommuteCurve, EconomyConfig};
+#[allow(unused_imports)]
+pub use elevation::{
+    slope_cost_factor, slope_speed_factor, Heightmap, MIN_UPHILL_SPEED_FACTOR,
+    SLOPE_COST_PER_GRADE, STEEP_GRADE, UPHILL_SLOWDOWN_PER_GRADE,
+};
+#[allow(unused_imports)]
+pub use emissions::{FuelProfile, WORLD_UNIT_METRES};
+#[cfg(feature = "stats")]
+#[allow(unused_imports)]
+pub use emissions::{CityEmissions, EMISSIONS_CSV_HEADER};
+#[cfg(feature = "generator")]
+#[allow(unused_imports)]
+pub use environment::{Action, ActionConfig, EnvConfig, EnvStep, ObservationConfig, TrafficEnv};
+#[allow(unused_imports)]
+pub use eta::{RouteEstimate, ETA_INTERSECTION_SECS};
+#[allow(unused_imports)]
+pub use events::{SimEvent, MAX_SIM_EVENTS};
+#[allow(unused_imports)]
+pub use factory::{
+    FACTORY_MATERIALS_PER_DELIVERY, FACTORY_SHIFTS_PER_DELIVERY, FACTORY_WORK_TIME,
+};
+#[allow(unused_imports)]
+pub use ferry::{FerryState, SimFerry, FERRY_CAPACITY, FERRY_DOCK_SECS, FERRY_SPEED};
+#[allow(unused_imports)]
+pub use fleet::{
+    wear_breakdown_factor, wear_speed_factor, VehicleRecord, VEHICLE_REPLACEMENT_WEAR,
+    VEHICLE_WORN_OUT_AGE_SECS, VEHICLE_WORN_OUT_MILEAGE, WORN_VEHICLE_BREAKDOWN_MULTIPLIER,
+    WORN_VEHICLE_SLOWDOWN,
+};
+#[allow(unused_imports)]
+pub use freight::{
+    FACTORY_MATERIALS_CAPACITY, TERMINAL_CAPACITY, TERMINAL_SHIPMENT_SECS,
+    TERMINAL_SHIPMENT_SIZE, VAN_LOAD,
+};
 #[allow(unused_imports)]
 pub use game_state::{
-    GameState, COMMUTE_HEALTHY_DISTANCE, COST_FACTORY, COST_APARTMENT, COST_ROAD, COST_SHOP,
-    GOAL_DELIVERIES, GOAL_MONEY, REVENUE_SHOP_DELIVERY, REVENUE_WORKER_DELIVERY,
-    SHORT_COMMUTE_PENALTY, STARTING_BUDGET,
+    GameEvent, GameState, BREAKDOWNS_PER_VEHICLE_HOUR, COLLISIONS_PER_CONGESTED_ROAD_HOUR,
+    COMMUTE_HEALTHY_DISTANCE, COMMUTE_TIME_ALLOWANCE_SECS, COMMUTE_TIME_PENALTY_PER_SEC,
+    COST_BREAKDOWN_CLEANUP, COST_COLLISION_CLEANUP, COST_DEPOT, COST_FACTORY, COST_SENSOR, DELIVERY_DEADLINE_SECS,
+    COST_APARTMENT, COST_BRIDGE, COST_BUS_STOP, COST_FERRY, COST_HOSPITAL, COST_PARK, COST_ROAD, COST_SCHOOL, COST_SHOP, COST_TERMINAL, COST_TREE, COST_VEHICLE_REPLACEMENT,
+    GOAL_DELIVERIES, GOAL_MONEY, INSURANCE_PERIOD_SECS, INSURANCE_PREMIUM, LATE_DELIVERY_REVENUE_SHARE, MAX_GAME_EVENTS, MIN_ON_TIME_RATE,
+    QUICK_COMMUTE_BONUS, REVENUE_SHOP_DELIVERY, REVENUE_WORKER_DELIVERY, SERVICE_LEVEL_MIN_DELIVERIES, SHORT_COMMUTE_PENALTY, STARTING_BUDGET,
+};
+#[allow(unused_imports)]
+pub use handling::{HandlingConfig, VehicleHandling};
+#[allow(unused_imports)]
+pub use highlights::{Highlight, HighlightKind};
+#[allow(unused_imports)]
+pub use hotspots::{
+    Hotspot, IntersectionIdling, HOTSPOT_FULL_CO2_KG, HOTSPOT_FULL_IDLE_SECS,
+    HOTSPOT_HALF_LIFE_SECS, HOTSPOT_MAX_POLLUTION, HOTSPOT_MIN_SEVERITY, HOTSPOT_RADIUS,
 };
 #[allow(unused_imports)]
+pub use idle::IdleMode;
+#[allow(unused_imports)]
+pub use incidents::{IncidentKind, BREAKDOWN_STALL_SECS, COLLISION_STALL_SECS};
+#[allow(unused_imports)]
 pub use intersection::SimIntersection;
 #[allow(unused_imports)]
-pub use road_network::SimRoadNetwork;
+pub use junction::{
+    junction_cost, junction_layout, Junction, JunctionKind, JunctionLayout, DIAMOND_RAMP_OFFSET,
+    JUNCTION_ARM_LENGTH,
+};
+#[cfg(feature = "stats")]
+#[allow(unused_imports)]
+pub use labor_stats::{LaborCounts, LaborDay, LABOR_STATS_CSV_HEADER};
+#[allow(unused_imports)]
+pub use network_diff::{IntersectionChange, NetworkDiff, RoadChange, RoadField};
+#[cfg(feature = "stats")]
+#[allow(unused_imports)]
+pub use report::{ReportFormat, REPORT_CONGESTED_ROADS, REPORT_MONEY_ROWS};
+#[allow(unused_imports)]
+pub use render::{RenderSync, SimRenderer, Visual, VisualId, VisualKind, WorldChanges};
+#[allow(unused_imports)]
+pub use path_cache::{
+    PathCache, PathCacheConfig, PathCacheEviction, PathCacheStats, PATH_CACHE_CAPACITY,
+    PATH_CACHE_MAX_AGE_SECS,
+};
+#[allow(unused_imports)]
+pub use pending_trips::PendingTrip;
+#[allow(unused_imports)]
+pub use personality::{DriverKind, DriverMix, DriverProfile, Personality};
+#[cfg(feature = "stats")]
+#[allow(unused_imports)]
+pub use personality::DriverSummary;
+#[allow(unused_imports)]
+pub use placement::{
+    BuildingSite, DrivewayJoin, MAX_DRIVEWAY_LENGTH, THROUGH_ROAD_MAX_BEND_DEGREES,
+};
+#[allow(unused_imports)]
+pub use pricing::MarketPricing;
+#[allow(unused_imports)]
+pub use recap::{
+    RecapConfig, RecapOutcome, RecapSection, RECAP_MONEY_INTERVAL_SECS, RECAP_MONEY_SAMPLES,
+};
+#[cfg(feature = "stats")]
+#[allow(unused_imports)]
+pub use recap::{MvpFactory, Recap, TrafficJam};
+#[allow(unused_imports)]
+pub use road_network::{ShortestPathTree, SimRoadNetwork, TwoWayRoad};
+#[allow(unused_imports)]
+pub use road_removal::{RoadExit, REVERSING_SPEED_FACTOR};
+#[cfg(feature = "stats")]
+#[allow(unused_imports)]
+pub use road_stats::{RoadStats, ROAD_STATS_CSV_HEADER};
+#[allow(unused_imports)]
+pub use routing::{default_route_workers, MAX_DEFAULT_ROUTE_WORKERS};
+#[allow(unused_imports)]
+pub use rules::{
+    turn_angle, IntersectionRules, Turn, PEDESTRIAN_CROSSING_MIN_ROADS, PEDESTRIAN_YIELD_SECS,
+    PREEMPTION_WINDOW_SECS,
+};
+#[allow(unused_imports)]
+pub use region::{CarFilter, Region};
+#[allow(unused_imports)]
+pub use scenario_events::{ActiveEvent, EventSchedule, RandomEvents, ScenarioEvent, ScheduledEvent};
+#[cfg(feature = "serialization")]
+#[allow(unused_imports)]
+pub use economy_reload::{EconomyReload, EconomyWatcher, NEXT_GAME_ECONOMY_FIELDS};
+#[cfg(feature = "serialization")]
+#[allow(unused_imports)]
+pub use save::{
+    write_atomically, Autosaver, RunningMarker, SaveFile, SaveMetadata, SavedCommand,
+    SAVE_VERSION, THUMBNAIL_WIDTH,
+};
+#[allow(unused_imports)]
+pub use score::{
+    BestScores, RunSummary, SCORE_FORMULA_VERSION, SCORE_MONEY_DIVISOR, SCORE_PAR_TIME_SECS,
+    SCORE_PER_SECOND_UNDER_PAR, SCORE_PER_SHOP_DELIVERY, SCORE_PER_WORKER_TRIP,
+};
+#[allow(unused_imports)]
+pub use sensors::RoadMetrics;
+#[allow(unused_imports)]
+pub use signal::{
+    copy_timings, validate_phases, SignalPhase, SignalPreemptions, TrafficSignal,
+    DEFAULT_PHASE_SECS, MAX_PHASE_SECS, MIN_PHASE_SECS,
+};
+#[cfg(feature = "stats")]
+#[allow(unused_imports)]
+pub use signal_advice::{
+    SignalSuggestion, Treatment, MIN_SUGGESTION_WAIT_SECS, ROUNDABOUT_DELAY_REDUCTION,
+    SIGNAL_DELAY_REDUCTION, SIGNAL_MIN_VEHICLES_PER_MINUTE,
+};
+#[cfg(feature = "serialization")]
+#[allow(unused_imports)]
+pub use snapshot::SNAPSHOT_VERSION;
+#[allow(unused_imports)]
+pub use spawning::VehicleSpawn;
+#[allow(unused_imports)]
+pub use special::{SimSpecialBuilding, SpecialKind, TripPeak, TripProfile};
+#[allow(unused_imports)]
+pub use speed_goal::{SpeedGoal, SpeedGoalProgress, SpeedGoalRecord, WindowedMean};
+#[allow(unused_imports)]
+pub use terrain::{WaterRegion, MAX_BRIDGE_LENGTH, RIVER};
+#[allow(unused_imports)]
+pub use transit::{
+    BusRider, BusRoute, BusState, SimBus, BUS_CAPACITY, BUS_DWELL_SECS, BUS_SPEED, MIN_BUS_STOPS,
+};
+#[cfg(feature = "stats")]
+#[allow(unused_imports)]
+pub use trip_stats::{TripBreakdown, TripPurpose, TripStats};
 #[allow(unused_imports)]
 pub use types::{
-    CarId, FactoryId, ApartmentId, IntersectionId, Position, RoadId, ShopId, SimId, SimRoad, TripType,
-    VehicleType, CAR_LENGTH, INTERSECTION_APPROACH_DISTANCE, SAFE_FOLLOWING_MULTIPLIER,
+    BusRouteId, CarId, DecorationId, FactoryId, ApartmentId, IntersectionId, Position, RoadId, RoadKind, RoadRestriction, ShopId, SimId, SimMap, SimRoad, SpecialBuildingId, TripType,
+    VehicleType, CAR_LENGTH, DAY_START_HOUR, INTERSECTION_APPROACH_DISTANCE, MIN_ROAD_LENGTH,
+    SAFE_FOLLOWING_MULTIPLIER, SECONDS_PER_DAY,
 };
+#[allow(unused_imports)]
+pub use upgrades::{UpgradeConfig, UpgradeKind, UpgradeLevels, UpgradeTrack};
+#[allow(unused_imports)]
+pub use walking::{Walk, MIN_WALK_SECS, WALKING_DISTANCE, WALKING_SPEED};
+#[cfg(feature = "stats")]
+#[allow(unused_imports)]
+pub use walking::CommuteModes;
+#[allow(unused_imports)]
+pub use warnings::{SimWarning, WarningLog, WARNING_INTERVAL_SECS};
 pub use world::SimWorld;
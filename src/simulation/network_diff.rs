@@ -0,0 +1,262 @@
+//! Comparing two road networks
+//!
+//! [`SimRoadNetwork::diff`] lists what changed between one network and
+//! another: intersections added, removed or moved, and roads added, removed
+//! or modified, matched by ID. A modified road says which of its
+//! [`RoadField`]s differ, so a test can check an operation touched exactly
+//! what it should, a what-if tool can show what a layout would change and a
+//! co-op peer can see where its roads drifted from the host's. Each
+//! direction of a two-way road is compared separately, as the network
+//! stores them.
+
+use std::collections::BTreeSet;
+use std::fmt;
+
+use super::road_network::SimRoadNetwork;
+use super::types::{IntersectionId, Position, RoadId, SimRoad};
+
+/// A part of a road that can differ between two networks
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum RoadField {
+    /// The intersections it runs between
+    Ends,
+    /// Its length, heading or grade, as when an end is moved
+    Shape,
+    /// Whether it is one direction of a two-way road
+    TwoWay,
+    /// Vehicles allowed on it
+    Restriction,
+    /// Whether a traffic sensor reports on it
+    Sensor,
+    /// Street, bridge or ferry crossing
+    Kind,
+    /// Whether it is closed
+    Closed,
+}
+
+impl RoadField {
+    /// Name for display
+    pub fn label(self) -> &'static str {
+        match self {
+            RoadField::Ends => "ends",
+            RoadField::Shape => "shape",
+            RoadField::TwoWay => "two-way",
+            RoadField::Restriction => "restriction",
+            RoadField::Sensor => "sensor",
+            RoadField::Kind => "kind",
+            RoadField::Closed => "closed",
+        }
+    }
+
+    /// The fields that differ between two versions of a road
+    fn compare(before: &SimRoad, after: &SimRoad) -> Vec<RoadField> {
+        let checks = [
+            (
+                RoadField::Ends,
+                (before.start_intersection, before.end_intersection)
+                    != (after.start_intersection, after.end_intersection),
+            ),
+            (
+                RoadField::Shape,
+                (before.length, before.angle, before.grade)
+                    != (after.length, after.angle, after.grade),
+            ),
+            (RoadField::TwoWay, before.is_two_way != after.is_two_way),
+            (RoadField::Restriction, before.restriction != after.restriction),
+            (RoadField::Sensor, before.sensor != after.sensor),
+            (RoadField::Kind, before.kind != after.kind),
+            (RoadField::Closed, before.closed != after.closed),
+        ];
+        checks
+            .into_iter()
+            .filter(|(_, differs)| *differs)
+            .map(|(field, _)| field)
+            .collect()
+    }
+}
+
+/// How an intersection differs between two networks
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum IntersectionChange {
+    Added { id: IntersectionId, position: Position },
+    Removed { id: IntersectionId, position: Position },
+    Moved {
+        id: IntersectionId,
+        from: Position,
+        to: Position,
+    },
+}
+
+impl IntersectionChange {
+    /// The intersection that changed
+    pub fn id(&self) -> IntersectionId {
+        match *self {
+            IntersectionChange::Added { id, .. }
+            | IntersectionChange::Removed { id, .. }
+            | IntersectionChange::Moved { id, .. } => id,
+        }
+    }
+}
+
+/// How a road differs between two networks
+#[derive(Debug, Clone, PartialEq)]
+pub enum RoadChange {
+    Added(SimRoad),
+    Removed(SimRoad),
+    Modified {
+        before: SimRoad,
+        after: SimRoad,
+        /// What differs, in [`RoadField`] order
+        fields: Vec<RoadField>,
+    },
+}
+
+impl RoadChange {
+    /// The road that changed
+    pub fn id(&self) -> RoadId {
+        match self {
+            RoadChange::Added(road) | RoadChange::Removed(road) => road.id,
+            RoadChange::Modified { after, .. } => after.id,
+        }
+    }
+}
+
+/// Everything that differs between two road networks, in ID order
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct NetworkDiff {
+    pub intersections: Vec<IntersectionChange>,
+    pub roads: Vec<RoadChange>,
+}
+
+impl NetworkDiff {
+    /// Whether the networks have the same intersections and roads
+    pub fn is_empty(&self) -> bool {
+        self.intersections.is_empty() && self.roads.is_empty()
+    }
+
+    /// Intersections added, removed or moved
+    pub fn touched_intersections(&self) -> BTreeSet<IntersectionId> {
+        self.intersections.iter().map(IntersectionChange::id).collect()
+    }
+
+    /// Roads added, removed or modified
+    pub fn touched_roads(&self) -> BTreeSet<RoadId> {
+        self.roads.iter().map(RoadChange::id).collect()
+    }
+
+    /// The fields of a road that were modified, if it was
+    pub fn road_fields(&self, road_id: RoadId) -> Option<&[RoadField]> {
+        self.roads.iter().find_map(|change| match change {
+            RoadChange::Modified { after, fields, .. } if after.id == road_id => {
+                Some(fields.as_slice())
+            }
+            _ => None,
+        })
+    }
+}
+
+fn describe_road(road: &SimRoad) -> String {
+    format!(
+        "{} -> {}, {:.1} long, {}, {}, {}{}{}",
+        road.start_intersection.0 .0,
+        road.end_intersection.0 .0,
+        road.length,
+        if road.is_two_way { "two-way" } else { "one-way" },
+        road.kind.label(),
+        road.restriction.label(),
+        if road.sensor { ", sensor" } else { "" },
+        if road.closed { ", closed" } else { "" }
+    )
+}
+
+impl fmt::Display for NetworkDiff {
+    /// One line per change: `+` added, `-` removed, `~` moved or modified
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return writeln!(f, "No differences");
+        }
+        let at = |position: &Position| format!("({:.1}, {:.1})", position.x, position.z);
+        for change in &self.intersections {
+            match change {
+                IntersectionChange::Added { id, position } => {
+                    writeln!(f, "+ intersection {} at {}", id.0 .0, at(position))?
+                }
+                IntersectionChange::Removed { id, position } => {
+                    writeln!(f, "- intersection {} at {}", id.0 .0, at(position))?
+                }
+                IntersectionChange::Moved { id, from, to } => writeln!(
+                    f,
+                    "~ intersection {} moved {} -> {}",
+                    id.0 .0,
+                    at(from),
+                    at(to)
+                )?,
+            }
+        }
+        for change in &self.roads {
+            match change {
+                RoadChange::Added(road) => {
+                    writeln!(f, "+ road {}: {}", road.id.0 .0, describe_road(road))?
+                }
+                RoadChange::Removed(road) => {
+                    writeln!(f, "- road {}: {}", road.id.0 .0, describe_road(road))?
+                }
+                RoadChange::Modified { after, fields, .. } => {
+                    let fields: Vec<&str> = fields.iter().map(|field| field.label()).collect();
+                    writeln!(
+                        f,
+                        "~ road {} ({}): {}",
+                        after.id.0 .0,
+                        fields.join(", "),
+                        describe_road(after)
+                    )?
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl SimRoadNetwork {
+    /// What differs going from this network to `other`, matching
+    /// intersections and roads by ID
+    pub fn diff(&self, other: &SimRoadNetwork) -> NetworkDiff {
+        let (before, after) = (self.intersection_positions(), other.intersection_positions());
+        let ids: BTreeSet<IntersectionId> = before.keys().chain(after.keys()).copied().collect();
+        let intersections = ids
+            .into_iter()
+            .filter_map(|id| match (before.get(&id), after.get(&id)) {
+                (None, Some(&position)) => Some(IntersectionChange::Added { id, position }),
+                (Some(&position), None) => Some(IntersectionChange::Removed { id, position }),
+                (Some(&from), Some(&to)) if from != to => {
+                    Some(IntersectionChange::Moved { id, from, to })
+                }
+                _ => None,
+            })
+            .collect();
+
+        let (before, after) = (self.roads(), other.roads());
+        let ids: BTreeSet<RoadId> = before.keys().chain(after.keys()).copied().collect();
+        let roads = ids
+            .into_iter()
+            .filter_map(|id| match (before.get(&id), after.get(&id)) {
+                (None, Some(road)) => Some(RoadChange::Added(road.clone())),
+                (Some(road), None) => Some(RoadChange::Removed(road.clone())),
+                (Some(before), Some(after)) => {
+                    let fields = RoadField::compare(before, after);
+                    (!fields.is_empty()).then(|| RoadChange::Modified {
+                        before: before.clone(),
+                        after: after.clone(),
+                        fields,
+                    })
+                }
+                (None, None) => None,
+            })
+            .collect();
+
+        NetworkDiff {
+            intersections,
+            roads,
+        }
+    }
+}
@@ -0,0 +1,443 @@
+//! Reusing routes found recently
+//!
+//! Finding a route is the costliest part of sending a vehicle off, and at
+//! rush hour many vehicles leave the same place for the same destination
+//! within seconds of each other. A world given a [`PathCacheConfig`] with
+//! some capacity keeps the routes it finds in a [`PathCache`], keyed by
+//! start, destination and vehicle type, and hands out a cached route
+//! instead of searching again while it is fresh.
+//!
+//! The cache is off by default: a cached route was traffic-aware when found
+//! but not when handed out, so turning it on trades some routing quality for
+//! speed. [`PathCacheConfig::enabled`] is a sensible size to start from.
+//!
+//! Routes weigh the traffic on the roads when they are found, so a cached
+//! one is only used for [`PathCacheConfig::max_age_secs`] of simulation
+//! time, after which the next vehicle searches afresh. Any change to the
+//! roads (see [`SimRoadNetwork::layout_version`]) throws the whole cache
+//! away. The cache holds at most [`PathCacheConfig::capacity`] routes and
+//! makes room for a new one by dropping the least recently used route or
+//! the oldest, as its [`PathCacheEviction`] says, so a large world's cache
+//! doesn't grow without bound. The routes are also indexed in that order,
+//! so finding the one to drop doesn't mean looking through them all.
+//!
+//! [`SimWorld::path_cache_stats`] reports how well the cache is doing: how
+//! often a route was found in it, how full it is and how often it has been
+//! thrown away lately. Background routing threads work on a snapshot of the
+//! roads and don't use the cache. Snapshots of the world keep it, so a
+//! loaded world hands out the same routes.
+//!
+//! [`SimRoadNetwork::layout_version`]: super::road_network::SimRoadNetwork::layout_version
+
+use std::collections::VecDeque;
+
+#[cfg(feature = "serialization")]
+use serde::{Deserialize, Serialize};
+
+use super::diagnostics::{map_bytes, vec_bytes};
+use super::types::{IntersectionId, SimMap, VehicleType};
+use super::world::SimWorld;
+
+/// Most routes a cache turned on with [`PathCacheConfig::enabled`] holds
+pub const PATH_CACHE_CAPACITY: usize = 2048;
+
+/// Seconds of simulation time a cached route is used for unless configured
+/// otherwise
+pub const PATH_CACHE_MAX_AGE_SECS: f32 = 5.0;
+
+/// Seconds over which [`PathCacheStats::invalidations_per_minute`] counts
+const INVALIDATION_WINDOW_SECS: f32 = 60.0;
+
+/// Which route a full cache drops to make room
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serialization", serde(rename_all = "snake_case"))]
+pub enum PathCacheEviction {
+    /// The route handed out longest ago
+    #[default]
+    Lru,
+    /// The route found longest ago, however often it was used since
+    Oldest,
+}
+
+/// How many routes the cache keeps, for how long and which go first
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+pub struct PathCacheConfig {
+    /// Most routes kept; 0 turns the cache off
+    pub capacity: usize,
+    /// Seconds of simulation time a route is used for after it was found
+    pub max_age_secs: f32,
+    pub eviction: PathCacheEviction,
+}
+
+impl Default for PathCacheConfig {
+    /// The cache turned off, so every route is searched for
+    fn default() -> Self {
+        Self {
+            capacity: 0,
+            max_age_secs: PATH_CACHE_MAX_AGE_SECS,
+            eviction: PathCacheEviction::default(),
+        }
+    }
+}
+
+impl PathCacheConfig {
+    /// The cache turned on with room for [`PATH_CACHE_CAPACITY`] routes
+    pub fn enabled() -> Self {
+        Self {
+            capacity: PATH_CACHE_CAPACITY,
+            ..Self::default()
+        }
+    }
+}
+
+/// How well the path cache is doing
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PathCacheStats {
+    /// Routes handed out from the cache
+    pub hits: u64,
+    /// Routes searched for, not being cached or having gone stale
+    pub misses: u64,
+    /// Routes held now
+    pub entries: usize,
+    pub capacity: usize,
+    /// Routes dropped to make room
+    pub evictions: u64,
+    /// Routes dropped for being too old to use
+    pub expirations: u64,
+    /// Times the whole cache was thrown away for a change to the roads
+    pub invalidations: u64,
+    /// Of those, how many were in the last minute of simulation time
+    pub invalidations_per_minute: usize,
+}
+
+impl PathCacheStats {
+    /// Share of routes handed out from the cache, from 0 to 1
+    pub fn hit_rate(&self) -> f32 {
+        let lookups = self.hits + self.misses;
+        if lookups == 0 {
+            0.0
+        } else {
+            self.hits as f32 / lookups as f32
+        }
+    }
+}
+
+/// Vehicle type, start and destination of a cached route
+type RouteKey = (VehicleType, IntersectionId, IntersectionId);
+
+/// A route in the cache
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+struct CachedPath {
+    path: Vec<IntersectionId>,
+    /// Simulation time it was found at
+    found_at: f32,
+    /// Order it was cached in
+    inserted: u64,
+    /// Order it was last handed out in
+    used: u64,
+}
+
+impl CachedPath {
+    /// Where the route comes in the order `eviction` drops routes in
+    fn order(&self, eviction: PathCacheEviction) -> u64 {
+        match eviction {
+            PathCacheEviction::Lru => self.used,
+            PathCacheEviction::Oldest => self.inserted,
+        }
+    }
+}
+
+/// Routes found recently, by vehicle type, start and destination
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+pub struct PathCache {
+    config: PathCacheConfig,
+    paths: SimMap<VehicleType, SimMap<IntersectionId, SimMap<IntersectionId, CachedPath>>>,
+    len: usize,
+    /// The cached routes in the order the eviction policy drops them, by
+    /// the time they were last used or cached
+    eviction_order: SimMap<u64, RouteKey>,
+    /// Road layout the routes were found on
+    layout_version: u64,
+    /// Counts lookups and insertions, ordering them for eviction
+    clock: u64,
+    hits: u64,
+    misses: u64,
+    evictions: u64,
+    expirations: u64,
+    invalidations: u64,
+    /// Simulation times of the invalidations in the last minute
+    recent_invalidations: VecDeque<f32>,
+}
+
+impl PathCache {
+    /// An empty cache
+    pub fn new(config: PathCacheConfig) -> Self {
+        Self {
+            config,
+            ..Self::default()
+        }
+    }
+
+    pub fn config(&self) -> PathCacheConfig {
+        self.config
+    }
+
+    /// Throw the routes away if the roads have changed since they were found
+    fn check_layout(&mut self, layout_version: u64, now: f32) {
+        if layout_version == self.layout_version {
+            return;
+        }
+        self.layout_version = layout_version;
+        if self.len == 0 {
+            return;
+        }
+        self.paths.clear();
+        self.eviction_order.clear();
+        self.len = 0;
+        self.invalidations += 1;
+        self.recent_invalidations.push_back(now);
+    }
+
+    /// A fresh cached route from `from` to `to` for `vehicle_type`,
+    /// counting a hit or a miss
+    fn get(
+        &mut self,
+        from: IntersectionId,
+        to: IntersectionId,
+        vehicle_type: VehicleType,
+        layout_version: u64,
+        now: f32,
+    ) -> Option<Vec<IntersectionId>> {
+        if self.config.capacity == 0 {
+            return None;
+        }
+        self.check_layout(layout_version, now);
+        self.clock += 1;
+        let max_age = self.config.max_age_secs;
+        let eviction = self.config.eviction;
+        let ends = self
+            .paths
+            .get_mut(&vehicle_type)
+            .and_then(|starts| starts.get_mut(&from));
+        let Some(ends) = ends else {
+            self.misses += 1;
+            return None;
+        };
+        match ends.get_mut(&to) {
+            Some(cached) if now - cached.found_at <= max_age => {
+                if eviction == PathCacheEviction::Lru {
+                    self.eviction_order.remove(&cached.used);
+                    self.eviction_order
+                        .insert(self.clock, (vehicle_type, from, to));
+                }
+                cached.used = self.clock;
+                self.hits += 1;
+                Some(cached.path.clone())
+            }
+            Some(cached) => {
+                let order = cached.order(eviction);
+                ends.remove(&to);
+                self.eviction_order.remove(&order);
+                self.len -= 1;
+                self.expirations += 1;
+                self.misses += 1;
+                None
+            }
+            None => {
+                self.misses += 1;
+                None
+            }
+        }
+    }
+
+    /// Keep a route just found, making room for it if the cache is full
+    fn insert(
+        &mut self,
+        from: IntersectionId,
+        to: IntersectionId,
+        vehicle_type: VehicleType,
+        path: &[IntersectionId],
+        now: f32,
+    ) {
+        if self.config.capacity == 0 {
+            return;
+        }
+        while self.len >= self.config.capacity {
+            self.evict();
+        }
+        self.clock += 1;
+        let cached = CachedPath {
+            path: path.to_vec(),
+            found_at: now,
+            inserted: self.clock,
+            used: self.clock,
+        };
+        self.eviction_order
+            .insert(self.clock, (vehicle_type, from, to));
+        let previous = self
+            .paths
+            .entry(vehicle_type)
+            .or_default()
+            .entry(from)
+            .or_default()
+            .insert(to, cached);
+        match previous {
+            Some(previous) => {
+                self.eviction_order
+                    .remove(&previous.order(self.config.eviction));
+            }
+            None => self.len += 1,
+        }
+    }
+
+    /// Drop the route the eviction policy picks: the first in its order
+    fn evict(&mut self) {
+        let Some((_, (vehicle_type, from, to))) = self.eviction_order.pop_first() else {
+            return;
+        };
+        let starts = self.paths.get_mut(&vehicle_type).expect("victim's type");
+        let ends = starts.get_mut(&from).expect("victim's start");
+        ends.remove(&to);
+        if ends.is_empty() {
+            starts.remove(&from);
+        }
+        if starts.is_empty() {
+            self.paths.remove(&vehicle_type);
+        }
+        self.len -= 1;
+        self.evictions += 1;
+    }
+
+    /// How well the cache is doing as of simulation time `now`
+    pub fn stats(&self, now: f32) -> PathCacheStats {
+        PathCacheStats {
+            hits: self.hits,
+            misses: self.misses,
+            entries: self.len,
+            capacity: self.config.capacity,
+            evictions: self.evictions,
+            expirations: self.expirations,
+            invalidations: self.invalidations,
+            invalidations_per_minute: self
+                .recent_invalidations
+                .iter()
+                .filter(|at| now - **at <= INVALIDATION_WINDOW_SECS)
+                .count(),
+        }
+    }
+
+    /// Forget invalidations older than a minute
+    fn prune(&mut self, now: f32) {
+        while self
+            .recent_invalidations
+            .front()
+            .is_some_and(|at| now - *at > INVALIDATION_WINDOW_SECS)
+        {
+            self.recent_invalidations.pop_front();
+        }
+    }
+
+    /// Routes held and the approximate bytes they take
+    pub(super) fn memory_usage(&self) -> (usize, usize) {
+        let bytes = map_bytes(&self.paths)
+            + map_bytes(&self.eviction_order)
+            + self
+                .paths
+                .values()
+                .map(|starts| {
+                    map_bytes(starts)
+                        + starts
+                            .values()
+                            .map(|ends| {
+                                map_bytes(ends)
+                                    + ends
+                                        .values()
+                                        .map(|cached| vec_bytes(&cached.path))
+                                        .sum::<usize>()
+                            })
+                            .sum::<usize>()
+                })
+                .sum::<usize>();
+        (self.len, bytes)
+    }
+}
+
+impl SimWorld {
+    /// Change how many routes are cached, for how long and which go first,
+    /// emptying the cache
+    pub fn set_path_cache_config(&mut self, config: PathCacheConfig) {
+        self.path_cache = PathCache::new(config);
+    }
+
+    /// How well the path cache is doing
+    pub fn path_cache_stats(&self) -> PathCacheStats {
+        self.path_cache.stats(self.time)
+    }
+
+    /// Find a traffic-aware route for a vehicle, from the cache if a fresh
+    /// one is there
+    pub(super) fn find_route(
+        &mut self,
+        from: IntersectionId,
+        to: IntersectionId,
+        vehicle_type: VehicleType,
+    ) -> Option<Vec<IntersectionId>> {
+        let layout_version = self.road_network.layout_version();
+        let cache = &mut self.path_cache;
+        cache.prune(self.time);
+        if let Some(path) = cache.get(from, to, vehicle_type, layout_version, self.time) {
+            return Some(path);
+        }
+        let path = self.road_network.find_path_for(from, to, vehicle_type)?;
+        self.path_cache
+            .insert(from, to, vehicle_type, &path, self.time);
+        Some(path)
+    }
+
+    /// Routes for vehicles of `vehicle_type` from `from` to each of `ends`,
+    /// from the cache where fresh ones are there and one search for the rest
+    pub(super) fn find_routes_from(
+        &mut self,
+        from: IntersectionId,
+        ends: &[IntersectionId],
+        vehicle_type: VehicleType,
+    ) -> Vec<Option<Vec<IntersectionId>>> {
+        let layout_version = self.road_network.layout_version();
+        let now = self.time;
+        self.path_cache.prune(now);
+        let mut routes: Vec<Option<Vec<IntersectionId>>> = ends
+            .iter()
+            .map(|to| {
+                self.path_cache
+                    .get(from, *to, vehicle_type, layout_version, now)
+            })
+            .collect();
+        let missing: Vec<IntersectionId> = ends
+            .iter()
+            .zip(&routes)
+            .filter(|(_, route)| route.is_none())
+            .map(|(to, _)| *to)
+            .collect();
+        if missing.is_empty() {
+            return routes;
+        }
+        let mut found = self
+            .road_network
+            .find_paths_from(from, &missing, vehicle_type)
+            .into_iter();
+        for (to, route) in ends.iter().zip(&mut routes) {
+            if route.is_some() {
+                continue;
+            }
+            *route = found.next().flatten();
+            if let Some(path) = route {
+                self.path_cache.insert(from, *to, vehicle_type, path, now);
+            }
+        }
+        routes
+    }
+}
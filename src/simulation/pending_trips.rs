@@ -0,0 +1,163 @@
+//! Trips waiting for a road connection
+//!
+//! A commuter, a worker heading home or a delivery truck with no route to
+//! its destination, such as from a building the player hasn't connected
+//! yet, doesn't give up its trip. The trip waits here and is tried again
+//! whenever the roads change, and its building sends nobody else out in the
+//! meantime. A trip is dropped if the building it belongs to is removed.
+
+use anyhow::Result;
+#[cfg(feature = "serialization")]
+use serde::{Deserialize, Serialize};
+
+use super::render::VisualId;
+use super::spawning::VehicleSpawn;
+use super::types::{ApartmentId, CarId, FactoryId, TripType, VehicleType};
+use super::world::SimWorld;
+
+/// A trip with no route yet
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+pub struct PendingTrip {
+    pub spawn: VehicleSpawn,
+    /// Simulation time the trip first failed to find a route
+    pub since: f32,
+    /// Road layout the trip last failed to find a route on
+    layout_version: u64,
+}
+
+/// Whether a trip is a commute, a worker's trip home or a delivery, the
+/// trips that wait for a connection
+pub(super) fn can_wait_for_connection(spawn: &VehicleSpawn) -> bool {
+    match (spawn.vehicle_type, spawn.trip_type) {
+        (VehicleType::Car, _) => spawn.origin_apartment.is_some(),
+        (VehicleType::Truck, TripType::Outbound) => spawn.origin_factory.is_some(),
+        _ => false,
+    }
+}
+
+impl SimWorld {
+    /// Trips waiting for the roads to connect them, oldest first
+    pub fn pending_trips(&self) -> &[PendingTrip] {
+        &self.pending_trips
+    }
+
+    /// Number of trips waiting for the roads to connect them
+    pub fn trips_waiting_for_connection(&self) -> usize {
+        self.pending_trips.len()
+    }
+
+    /// Whether an apartment has a worker waiting for a route
+    pub(super) fn apartment_has_pending_trip(&self, apartment_id: ApartmentId) -> bool {
+        self.pending_trips
+            .iter()
+            .any(|trip| trip.spawn.origin_apartment == Some(apartment_id))
+    }
+
+    /// Whether a factory has a truck waiting for a route
+    pub(super) fn factory_has_pending_trip(&self, factory_id: FactoryId) -> bool {
+        self.pending_trips.iter().any(|trip| {
+            trip.spawn.vehicle_type == VehicleType::Truck
+                && trip.spawn.origin_factory == Some(factory_id)
+        })
+    }
+
+    /// Spawn the vehicle for a commute, a worker's trip home or a delivery,
+    /// and record it with the building it belongs to
+    pub(super) fn start_trip(&mut self, spawn: &VehicleSpawn) -> Result<CarId> {
+        let car_id = self.spawn_vehicle(
+            spawn.from,
+            spawn.to,
+            spawn.vehicle_type,
+            spawn.trip_type,
+            spawn.origin_apartment,
+            spawn.origin_factory,
+        )?;
+        match (spawn.vehicle_type, spawn.trip_type) {
+            (VehicleType::Truck, _) => {
+                if let Some(factory) = spawn
+                    .origin_factory
+                    .and_then(|factory_id| self.factories.get_mut(&factory_id))
+                {
+                    factory.truck = Some(car_id);
+                }
+                let deadline = self
+                    .game_state
+                    .as_ref()
+                    .map(|game_state| game_state.economy.delivery_deadline_secs);
+                if let Some(truck) = self.cars.get_mut(&car_id) {
+                    truck.delivery_deadline = deadline;
+                }
+            }
+            (_, trip_type) => {
+                // Under a demand ramp, workers rest at home between shifts
+                let rest_secs = self.worker_rest_secs();
+                if let Some(apartment) = spawn
+                    .origin_apartment
+                    .and_then(|apartment_id| self.apartments.get_mut(&apartment_id))
+                {
+                    if let Some(car_slot) = apartment.cars.iter_mut().find(|slot| slot.is_none()) {
+                        *car_slot = Some(car_id);
+                    }
+                    if trip_type == TripType::Outbound {
+                        apartment.next_departure = self.time + rest_secs;
+                    }
+                }
+            }
+        }
+        Ok(car_id)
+    }
+
+    /// Hold a trip with no route until the roads change
+    pub(super) fn defer_trip(&mut self, spawn: VehicleSpawn) {
+        if let Some(factory_id) = spawn.origin_factory {
+            self.changes.mark(VisualId::Factory(factory_id));
+        }
+        self.pending_trips.push(PendingTrip {
+            spawn,
+            since: self.time,
+            layout_version: self.road_network.layout_version(),
+        });
+    }
+
+    /// Try the waiting trips again if the roads have changed since they
+    /// last failed, in the order they started waiting
+    pub(super) fn retry_pending_trips(&mut self) {
+        let layout_version = self.road_network.layout_version();
+        if self
+            .pending_trips
+            .iter()
+            .all(|trip| trip.layout_version == layout_version)
+        {
+            return;
+        }
+        for mut trip in std::mem::take(&mut self.pending_trips) {
+            if trip.layout_version != layout_version {
+                if !self.trip_still_wanted(&trip.spawn) {
+                    continue;
+                }
+                if self.start_trip(&trip.spawn).is_ok() {
+                    continue;
+                }
+                trip.layout_version = layout_version;
+            }
+            self.pending_trips.push(trip);
+        }
+    }
+
+    /// Whether the building a waiting trip belongs to and both its ends are
+    /// still there
+    fn trip_still_wanted(&self, spawn: &VehicleSpawn) -> bool {
+        let owner_exists = match spawn.vehicle_type {
+            VehicleType::Truck => spawn
+                .origin_factory
+                .is_some_and(|factory_id| self.factories.contains_key(&factory_id)),
+            _ => spawn
+                .origin_apartment
+                .is_some_and(|apartment_id| self.apartments.contains_key(&apartment_id)),
+        };
+        owner_exists
+            && self.road_network.get_intersection_position(spawn.from).is_some()
+            && self.road_network.get_intersection_position(spawn.to).is_some()
+    }
+}
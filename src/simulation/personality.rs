@@ -0,0 +1,273 @@
+//! Driver personalities
+//!
+//! Without a [`DriverMix`] every driver behaves the same. An economy can
+//! include one to give each vehicle's driver a personality as it sets off,
+//! drawn from the world's RNG: aggressive, standard or cautious, each with a
+//! [`DriverProfile`] of how fast they like to drive, how close they follow
+//! and how long they take to commit to crossing an intersection. Each value
+//! is varied by up to `spread` either way, so no two drivers are quite
+//! alike. Aggressive drivers bunch up and squeeze through; cautious ones
+//! leave gaps that cost the road capacity.
+
+use anyhow::{ensure, Result};
+#[cfg(feature = "serialization")]
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "stats")]
+use super::types::SimMap;
+use super::world::SimWorld;
+
+/// How a driver behaves, as factors on the standard driver's behaviour
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serialization", serde(default, deny_unknown_fields))]
+pub struct DriverProfile {
+    /// Factor on the vehicle's speed
+    pub speed_factor: f32,
+    /// Factor on the gap kept to the vehicle ahead
+    pub following_gap: f32,
+    /// Factor on the time taken to cross an intersection once it is clear,
+    /// the gap left to traffic coming through after
+    pub gap_acceptance: f32,
+}
+
+impl Default for DriverProfile {
+    fn default() -> Self {
+        Self::STANDARD
+    }
+}
+
+impl DriverProfile {
+    /// The standard driver, who every driver is without a [`DriverMix`]
+    pub const STANDARD: Self = Self {
+        speed_factor: 1.0,
+        following_gap: 1.0,
+        gap_acceptance: 1.0,
+    };
+
+    fn validate(&self, name: &str) -> Result<()> {
+        for (field, value) in [
+            ("speed_factor", self.speed_factor),
+            ("following_gap", self.following_gap),
+            ("gap_acceptance", self.gap_acceptance),
+        ] {
+            ensure!(
+                value > 0.0,
+                "driver_mix.{}.{} must be positive (got {})",
+                name,
+                field,
+                value
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Kinds of driver
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serialization", serde(rename_all = "lowercase"))]
+pub enum DriverKind {
+    Aggressive,
+    #[default]
+    Standard,
+    Cautious,
+}
+
+impl DriverKind {
+    /// Name for display
+    pub fn label(self) -> &'static str {
+        match self {
+            DriverKind::Aggressive => "aggressive",
+            DriverKind::Standard => "standard",
+            DriverKind::Cautious => "cautious",
+        }
+    }
+}
+
+/// A vehicle's driver
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+pub struct Personality {
+    pub kind: DriverKind,
+    /// The kind's profile as varied for this driver
+    pub profile: DriverProfile,
+}
+
+/// Shares of aggressive and cautious drivers and how they behave; the rest
+/// are standard drivers
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serialization", serde(default, deny_unknown_fields))]
+pub struct DriverMix {
+    /// Share of drivers who are aggressive
+    pub aggressive_share: f32,
+    /// Share of drivers who are cautious
+    pub cautious_share: f32,
+    /// How aggressive drivers behave
+    pub aggressive: DriverProfile,
+    /// How cautious drivers behave
+    pub cautious: DriverProfile,
+    /// Most each driver's values vary from their kind's, as a share of them
+    pub spread: f32,
+}
+
+impl Default for DriverMix {
+    fn default() -> Self {
+        Self {
+            aggressive_share: 0.2,
+            cautious_share: 0.2,
+            aggressive: DriverProfile {
+                speed_factor: 1.2,
+                following_gap: 0.6,
+                gap_acceptance: 0.6,
+            },
+            cautious: DriverProfile {
+                speed_factor: 0.85,
+                following_gap: 1.5,
+                gap_acceptance: 1.6,
+            },
+            spread: 0.1,
+        }
+    }
+}
+
+impl DriverMix {
+    /// Reject shares that don't add up and profiles that would stop traffic
+    pub fn validate(&self) -> Result<()> {
+        for (name, share) in [
+            ("aggressive_share", self.aggressive_share),
+            ("cautious_share", self.cautious_share),
+        ] {
+            ensure!(
+                (0.0..=1.0).contains(&share),
+                "driver_mix.{} must be between 0 and 1 (got {})",
+                name,
+                share
+            );
+        }
+        ensure!(
+            self.aggressive_share + self.cautious_share <= 1.0,
+            "driver_mix.aggressive_share and cautious_share add up to more than 1 ({})",
+            self.aggressive_share + self.cautious_share
+        );
+        self.aggressive.validate("aggressive")?;
+        self.cautious.validate("cautious")?;
+        ensure!(
+            (0.0..1.0).contains(&self.spread),
+            "driver_mix.spread must be at least 0 and less than 1 (got {})",
+            self.spread
+        );
+        Ok(())
+    }
+
+    /// The profile of a kind of driver
+    pub fn profile(&self, kind: DriverKind) -> DriverProfile {
+        match kind {
+            DriverKind::Aggressive => self.aggressive,
+            DriverKind::Standard => DriverProfile::STANDARD,
+            DriverKind::Cautious => self.cautious,
+        }
+    }
+}
+
+/// Drivers of one kind that have set off, with their values summed
+#[cfg(feature = "stats")]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+struct DriverTally {
+    drivers: u32,
+    speed_factor: f32,
+    following_gap: f32,
+    gap_acceptance: f32,
+}
+
+/// How many drivers of a kind have set off and their average values
+#[cfg(feature = "stats")]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DriverSummary {
+    pub kind: DriverKind,
+    pub drivers: u32,
+    /// Average values across these drivers
+    pub average: DriverProfile,
+}
+
+/// The personalities drivers have set off with
+#[cfg(feature = "stats")]
+#[derive(Debug, Clone, Default)]
+pub(super) struct DriverStats {
+    tallies: SimMap<DriverKind, DriverTally>,
+}
+
+#[cfg(feature = "stats")]
+impl DriverStats {
+    fn record(&mut self, personality: &Personality) {
+        let tally = self.tallies.entry(personality.kind).or_default();
+        tally.drivers += 1;
+        tally.speed_factor += personality.profile.speed_factor;
+        tally.following_gap += personality.profile.following_gap;
+        tally.gap_acceptance += personality.profile.gap_acceptance;
+    }
+}
+
+impl SimWorld {
+    /// The driver mix of the game's economy, if it has one
+    pub fn driver_mix(&self) -> Option<&DriverMix> {
+        self.game_state
+            .as_ref()
+            .and_then(|game_state| game_state.economy.driver_mix.as_ref())
+    }
+
+    /// Pick a personality for a new vehicle's driver
+    ///
+    /// Without a driver mix every driver is standard and no random numbers
+    /// are drawn, so worlds without one run as they always have.
+    pub(super) fn roll_personality(&mut self) -> Personality {
+        let Some(mix) = self.driver_mix().copied() else {
+            return Personality::default();
+        };
+        let roll = self.random_range(0.0..1.0);
+        let kind = if roll < mix.aggressive_share {
+            DriverKind::Aggressive
+        } else if roll < mix.aggressive_share + mix.cautious_share {
+            DriverKind::Cautious
+        } else {
+            DriverKind::Standard
+        };
+        let mut profile = mix.profile(kind);
+        if mix.spread > 0.0 {
+            for value in [
+                &mut profile.speed_factor,
+                &mut profile.following_gap,
+                &mut profile.gap_acceptance,
+            ] {
+                *value *= 1.0 + self.random_range(-mix.spread..mix.spread);
+            }
+        }
+        let personality = Personality { kind, profile };
+        #[cfg(feature = "stats")]
+        self.driver_stats.record(&personality);
+        personality
+    }
+
+    /// How many drivers of each kind have set off and their average values,
+    /// for the kinds that have
+    #[cfg(feature = "stats")]
+    pub fn driver_summaries(&self) -> Vec<DriverSummary> {
+        self.driver_stats
+            .tallies
+            .iter()
+            .map(|(kind, tally)| {
+                let drivers = tally.drivers.max(1) as f32;
+                DriverSummary {
+                    kind: *kind,
+                    drivers: tally.drivers,
+                    average: DriverProfile {
+                        speed_factor: tally.speed_factor / drivers,
+                        following_gap: tally.following_gap / drivers,
+                        gap_acceptance: tally.gap_acceptance / drivers,
+                    },
+                }
+            })
+            .collect()
+    }
+}
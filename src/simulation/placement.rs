@@ -0,0 +1,253 @@
+//! Building placement rules
+//!
+//! Every building stands on an intersection of its own, which its vehicles
+//! set off from. A building placed near an intersection snaps to it like
+//! before, but only one building may stand on an intersection, and none where
+//! a road runs straight through, since vehicles pulling in and out would hold
+//! up the traffic passing by. A building placed beside the network gets a
+//! driveway of up to [`MAX_DRIVEWAY_LENGTH`] to the nearest intersection or
+//! road, built in the same operation and paid for like any road along with
+//! the building. Buildings can't go in the middle of a road, or any further
+//! from one.
+
+use anyhow::{bail, ensure, Result};
+
+use super::types::{IntersectionId, Position, RoadId, RoadKind};
+use super::world::SimWorld;
+
+/// Longest driveway a building can be joined to the network by
+pub const MAX_DRIVEWAY_LENGTH: f32 = 5.0;
+
+/// Largest bend, in degrees, at which two roads meeting at an intersection
+/// count as one road running straight through it
+pub const THROUGH_ROAD_MAX_BEND_DEGREES: f32 = 30.0;
+
+/// Where a driveway meets the network
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DrivewayJoin {
+    /// At an existing intersection
+    Intersection(IntersectionId),
+    /// Partway along a road, which is split there
+    Road(RoadId),
+}
+
+/// Where a building would go
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BuildingSite {
+    /// On an existing intersection
+    Intersection {
+        id: IntersectionId,
+        position: Position,
+    },
+    /// On a new intersection joined to the network by a driveway
+    Driveway {
+        position: Position,
+        /// Where the driveway meets the network
+        join: Position,
+        onto: DrivewayJoin,
+    },
+}
+
+impl BuildingSite {
+    /// Where the building would stand
+    pub fn position(&self) -> Position {
+        match self {
+            BuildingSite::Intersection { position, .. }
+            | BuildingSite::Driveway { position, .. } => *position,
+        }
+    }
+}
+
+impl SimWorld {
+    /// Whether a building of any kind stands on an intersection
+    pub fn has_building(&self, intersection_id: IntersectionId) -> bool {
+        self.apartments
+            .values()
+            .any(|a| a.intersection_id == intersection_id)
+            || self
+                .factories
+                .values()
+                .any(|f| f.intersection_id == intersection_id)
+            || self
+                .shops
+                .values()
+                .any(|s| s.intersection_id == intersection_id)
+            || self
+                .special_buildings
+                .values()
+                .any(|b| b.intersection_id == intersection_id)
+    }
+
+    /// Whether two of the roads at an intersection run straight through it
+    pub fn has_through_road(&self, intersection_id: IntersectionId) -> bool {
+        let network = &self.road_network;
+        let Some(centre) = network.get_intersection_position(intersection_id) else {
+            return false;
+        };
+        let mut directions: Vec<(f32, f32)> = Vec::new();
+        for road_id in network.get_roads_at_intersection(intersection_id) {
+            let Some(road) = network.get_road(road_id) else {
+                continue;
+            };
+            let other = if road.start_intersection == intersection_id {
+                road.end_intersection
+            } else {
+                road.start_intersection
+            };
+            let Some(other) = network.get_intersection_position(other) else {
+                continue;
+            };
+            let (dx, dz) = (other.x - centre.x, other.z - centre.z);
+            let length = (dx * dx + dz * dz).sqrt();
+            if length > 0.0 {
+                directions.push((dx / length, dz / length));
+            }
+        }
+        // Roads heading off in nearly opposite directions form one road
+        let straight = (180.0 - THROUGH_ROAD_MAX_BEND_DEGREES).to_radians().cos();
+        directions.iter().enumerate().any(|(i, a)| {
+            directions[i + 1..]
+                .iter()
+                .any(|b| a.0 * b.0 + a.1 * b.1 <= straight)
+        })
+    }
+
+    /// Where a building placed at `position` would go, snapping to an
+    /// intersection within `snap_distance`, or why it can't go there
+    pub fn building_site(&self, position: Position, snap_distance: f32) -> Result<BuildingSite> {
+        let network = &self.road_network;
+        let nearest_intersection = network
+            .find_closest_intersection(&position)
+            .and_then(|id| Some((id, *network.get_intersection_position(id)?)));
+        if let Some((id, at)) = nearest_intersection {
+            if position.distance(&at) <= snap_distance {
+                self.check_building_intersection(id)?;
+                return Ok(BuildingSite::Intersection { id, position: at });
+            }
+        }
+
+        let nearest_road = network.find_closest_point_on_road(&position);
+        if let Some((_, point, _, _)) = nearest_road {
+            ensure!(
+                position.distance(&point) > snap_distance,
+                "Buildings can't go in the middle of a road; place it beside the road"
+            );
+        }
+
+        // Join the network wherever it is nearest
+        let (join, onto) = match (nearest_intersection, nearest_road) {
+            (Some((_, at)), Some((road_id, point, _, _)))
+                if position.distance(&point) < position.distance(&at) =>
+            {
+                (point, DrivewayJoin::Road(road_id))
+            }
+            (Some((id, at)), _) => (at, DrivewayJoin::Intersection(id)),
+            _ => bail!("Buildings must be within {} of a road", MAX_DRIVEWAY_LENGTH),
+        };
+        ensure!(
+            position.distance(&join) <= MAX_DRIVEWAY_LENGTH,
+            "Buildings must be within {} of a road",
+            MAX_DRIVEWAY_LENGTH
+        );
+        match onto {
+            DrivewayJoin::Intersection(id) => {
+                ensure!(
+                    network.intersection_has_roads(id),
+                    "Buildings must be within {} of a road",
+                    MAX_DRIVEWAY_LENGTH
+                );
+                ensure!(
+                    !self.has_building(id),
+                    "A driveway can't join another building's lot"
+                );
+            }
+            DrivewayJoin::Road(road_id) => {
+                if let Some(road) = network.get_road(road_id) {
+                    ensure!(
+                        road.kind == RoadKind::Street,
+                        "A driveway can't join a {}",
+                        road.kind.label()
+                    );
+                }
+            }
+        }
+        self.check_crossing(&position, &join, RoadKind::Street)?;
+        Ok(BuildingSite::Driveway {
+            position,
+            join,
+            onto,
+        })
+    }
+
+    /// Cost of the driveway a building on `site` needs, like any road of its
+    /// length; nothing for a site on an existing intersection or without a
+    /// game
+    pub fn driveway_cost(&self, site: &BuildingSite) -> i32 {
+        let (BuildingSite::Driveway { position, join, .. }, Some(game_state)) =
+            (site, &self.game_state)
+        else {
+            return 0;
+        };
+        let slope = self.slope_cost_factor(position, join);
+        (game_state.economy.cost_road as f32 * slope).round() as i32
+    }
+
+    /// Check a building can stand on an existing intersection
+    fn check_building_intersection(&self, intersection_id: IntersectionId) -> Result<()> {
+        ensure!(
+            !self.has_building(intersection_id),
+            "There's already a building here"
+        );
+        ensure!(
+            self.road_network.intersection_has_roads(intersection_id),
+            "Connect this spot to a road first"
+        );
+        ensure!(
+            !self.has_through_road(intersection_id),
+            "A road runs straight through here; place the building beside it"
+        );
+        Ok(())
+    }
+
+    /// Place a building at `position` by the placement rules, with `place`
+    /// putting it on its intersection and returning whether it was
+    ///
+    /// The building and its driveway are paid for together: if the money
+    /// runs out for either, neither is built and nothing is charged.
+    pub(super) fn place_building(
+        &mut self,
+        position: Position,
+        snap_distance: f32,
+        place: impl FnOnce(&mut Self, IntersectionId) -> Result<bool>,
+    ) -> Result<bool> {
+        let site = self.building_site(position, snap_distance)?;
+        match site {
+            BuildingSite::Intersection { id, .. } => place(self, id),
+            BuildingSite::Driveway {
+                position,
+                join,
+                onto,
+            } => {
+                let driveway_cost = self.driveway_cost(&site);
+                if !self.spend_for_game(|_| driveway_cost) {
+                    return Ok(false);
+                }
+                let lot = self.add_intersection(position);
+                let placed = place(self, lot);
+                if !matches!(placed, Ok(true)) {
+                    if let Some(game_state) = &mut self.game_state {
+                        game_state.earn(driveway_cost);
+                    }
+                    self.remove_intersection(lot)?;
+                    return placed;
+                }
+                let access = match onto {
+                    DrivewayJoin::Intersection(id) => id,
+                    DrivewayJoin::Road(road_id) => self.split_road_at_position(road_id, join)?.0,
+                };
+                self.add_two_way_road(access, lot)?;
+                Ok(true)
+            }
+        }
+    }
+}
@@ -0,0 +1,154 @@
+//! Revenue that follows supply and demand
+//!
+//! An economy can include [`MarketPricing`], which prices each delivery by
+//! how much its shop needs it, so a network that keeps one shop topped up
+//! from a short route earns less for it than one that serves every shop.
+//! Each shop keeps a stock of goods: every delivery adds one, and
+//! `sales_per_minute` sell off. The stock is what's left of the shop's
+//! recent deliveries, so a shop that has gone a while without one has
+//! empty shelves and a shop a nearby factory keeps flooding has full ones.
+//!
+//! A delivery to a shop holding `target_stock` earns the economy's usual
+//! revenue. One to an empty shop earns up to `max_premium` more, and one to
+//! a shop holding twice the target or more up to `max_discount` less, in
+//! proportion between. The price is set when the truck unloads and paid
+//! when it gets home, so a later delivery doesn't change it.
+//!
+//! Worker pay follows the same curve for the factory the worker came home
+//! from: one with no finished deliveries waiting pays the premium, one
+//! whose store is full because its truck can't get them away pays less.
+//! Only the base pay is repriced; the commute penalties and bonus stay as
+//! they are.
+//!
+//! Without it, every delivery and shift earns the economy's fixed revenue.
+
+use anyhow::{ensure, Result};
+#[cfg(feature = "serialization")]
+use serde::{Deserialize, Serialize};
+
+use super::types::{FactoryId, IntersectionId};
+use super::world::SimWorld;
+
+/// Delivery and worker revenue that rises where goods are short and falls
+/// where they pile up
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serialization", serde(default, deny_unknown_fields))]
+pub struct MarketPricing {
+    /// Goods a shop can hold before its deliveries are worth less than
+    /// the usual revenue
+    pub target_stock: f32,
+    /// Goods each shop sells a minute
+    pub sales_per_minute: f32,
+    /// Share of the usual revenue added for goods nobody has
+    pub max_premium: f32,
+    /// Share of the usual revenue taken off for goods nobody needs
+    pub max_discount: f32,
+}
+
+impl Default for MarketPricing {
+    fn default() -> Self {
+        Self {
+            target_stock: 2.0,
+            sales_per_minute: 1.0,
+            max_premium: 0.5,
+            max_discount: 0.5,
+        }
+    }
+}
+
+impl MarketPricing {
+    /// Share of the usual revenue paid at `need`, from -1 (nobody needs the
+    /// goods) through 0 (as needed as usual) to 1 (nobody has them)
+    pub fn price_for_need(&self, need: f32) -> f32 {
+        let need = need.clamp(-1.0, 1.0);
+        if need >= 0.0 {
+            1.0 + need * self.max_premium
+        } else {
+            1.0 + need * self.max_discount
+        }
+    }
+
+    /// Share of the usual revenue a delivery to a shop holding `stock` earns
+    pub fn shop_price(&self, stock: f32) -> f32 {
+        self.price_for_need(1.0 - stock / self.target_stock)
+    }
+
+    /// Share of the usual pay for a shift at a factory with `ready` of its
+    /// `capacity` deliveries waiting for its truck
+    pub fn factory_price(&self, ready: u32, capacity: u32) -> f32 {
+        let fill = ready as f32 / capacity.max(1) as f32;
+        self.price_for_need(1.0 - 2.0 * fill)
+    }
+
+    /// Reject pricing that never moves or pays below nothing
+    pub fn validate(&self) -> Result<()> {
+        ensure!(
+            self.target_stock > 0.0,
+            "market_pricing.target_stock must be positive (got {})",
+            self.target_stock
+        );
+        for (name, value) in [
+            ("sales_per_minute", self.sales_per_minute),
+            ("max_premium", self.max_premium),
+        ] {
+            ensure!(
+                value >= 0.0,
+                "market_pricing.{} must not be negative (got {})",
+                name,
+                value
+            );
+        }
+        ensure!(
+            (0.0..=1.0).contains(&self.max_discount),
+            "market_pricing.max_discount must be between 0 and 1 (got {})",
+            self.max_discount
+        );
+        Ok(())
+    }
+}
+
+impl SimWorld {
+    /// The economy's market pricing, if the game has one
+    pub fn market_pricing(&self) -> Option<&MarketPricing> {
+        self.game_state.as_ref()?.economy.market_pricing.as_ref()
+    }
+
+    /// Share of the usual revenue a delivery to the shop at `shop` would
+    /// earn now (1 without market pricing)
+    pub fn shop_delivery_price(&self, shop: IntersectionId) -> f32 {
+        let Some(pricing) = self.market_pricing() else {
+            return 1.0;
+        };
+        self.shops
+            .values()
+            .find(|s| s.intersection_id == shop)
+            .map_or(1.0, |s| pricing.shop_price(s.stock))
+    }
+
+    /// Share of the usual pay a shift at `factory` would earn now (1 without
+    /// market pricing)
+    pub fn worker_shift_price(&self, factory: Option<FactoryId>) -> f32 {
+        let Some(pricing) = self.market_pricing() else {
+            return 1.0;
+        };
+        factory
+            .and_then(|id| self.factories.get(&id))
+            .map_or(1.0, |f| {
+                pricing.factory_price(f.deliveries_ready, f.max_deliveries)
+            })
+    }
+
+    /// Sell off each shop's stock for `delta_secs`, at the default rate
+    /// without market pricing
+    pub(super) fn sell_shop_stock(&mut self, delta_secs: f32) {
+        let sales_per_minute = self.market_pricing().map_or(
+            MarketPricing::default().sales_per_minute,
+            |pricing| pricing.sales_per_minute,
+        );
+        let sold = sales_per_minute * delta_secs / 60.0;
+        for shop in self.shops.values_mut() {
+            shop.stock = (shop.stock - sold).max(0.0);
+        }
+    }
+}
@@ -0,0 +1,327 @@
+//! Maps as PNG images
+//!
+//! [`SimWorld::map_png`] draws the city from above into a PNG image, with
+//! no front-end and no GPU: water, roads (red where congested) and a dot
+//! for each building, in the colours of the report's map. It is what saves
+//! use for their thumbnails, so a save written by a headless run gets one
+//! just the same as a save from the UI.
+//!
+//! The image has a colour palette and is stored uncompressed, which keeps
+//! the encoder small and is compact enough for the few colours of a map.
+
+#[cfg(feature = "serialization")]
+use anyhow::{bail, Result};
+
+use super::achievements::CONGESTED_ROAD_DENSITY;
+use super::types::Position;
+use super::world::SimWorld;
+
+/// Space around the city on a map, in world units
+const MAP_MARGIN: f32 = 5.0;
+
+/// Tallest a map can be, as a multiple of its width
+const MAX_ASPECT: f32 = 4.0;
+
+/// Radius of the dot for a building, in world units
+const BUILDING_RADIUS: f32 = 1.2;
+
+/// A map's colours, indexed by the values of [`Canvas::pixels`]
+const PALETTE: [[u8; 3]; 8] = [
+    [0xf4, 0xf1, 0xe8],
+    [0x8f, 0xc1, 0xe3],
+    [0x77, 0x77, 0x77],
+    [0xdd, 0x33, 0x33],
+    [0x33, 0xaa, 0x33],
+    [0xee, 0x88, 0x00],
+    [0x33, 0x66, 0xcc],
+    [0x99, 0x33, 0xcc],
+];
+const WATER: u8 = 1;
+const ROAD: u8 = 2;
+const CONGESTED_ROAD: u8 = 3;
+const APARTMENT: u8 = 4;
+const FACTORY: u8 = 5;
+const SHOP: u8 = 6;
+const SPECIAL: u8 = 7;
+
+/// Largest block of uncompressed data in a zlib stream
+const MAX_STORED_BLOCK: usize = 65_535;
+
+/// The part of the world a map shows: its top-left corner and size
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) struct MapBounds {
+    pub min_x: f32,
+    pub min_z: f32,
+    pub width: f32,
+    pub depth: f32,
+}
+
+/// Pixels being drawn, as indices into [`PALETTE`]
+struct Canvas {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+    bounds: MapBounds,
+    /// Pixels per world unit
+    scale: f32,
+}
+
+impl Canvas {
+    /// A blank canvas `width` pixels across showing `bounds`
+    fn new(width: u32, bounds: MapBounds) -> Self {
+        let width = width.max(1);
+        let scale = width as f32 / bounds.width;
+        let height = (bounds.depth * scale)
+            .round()
+            .clamp(1.0, width as f32 * MAX_ASPECT) as u32;
+        Self {
+            width,
+            height,
+            pixels: vec![0; width as usize * height as usize],
+            bounds,
+            scale,
+        }
+    }
+
+    /// Where a point of the world falls on the canvas
+    fn project(&self, x: f32, z: f32) -> (f32, f32) {
+        (
+            (x - self.bounds.min_x) * self.scale,
+            (z - self.bounds.min_z) * self.scale,
+        )
+    }
+
+    /// Colour a pixel, if it is on the canvas
+    fn plot(&mut self, x: i64, y: i64, color: u8) {
+        if (0..self.width as i64).contains(&x) && (0..self.height as i64).contains(&y) {
+            self.pixels[y as usize * self.width as usize + x as usize] = color;
+        }
+    }
+
+    /// Fill the rectangle of the world between two corners
+    fn fill_rect(&mut self, min: (f32, f32), max: (f32, f32), color: u8) {
+        let (left, top) = self.project(min.0, min.1);
+        let (right, bottom) = self.project(max.0, max.1);
+        for y in top.floor() as i64..bottom.ceil() as i64 {
+            for x in left.floor() as i64..right.ceil() as i64 {
+                self.plot(x, y, color);
+            }
+        }
+    }
+
+    /// Draw a line a pixel wide between two points of the world
+    fn line(&mut self, start: &Position, end: &Position, color: u8) {
+        let (x0, y0) = self.project(start.x, start.z);
+        let (x1, y1) = self.project(end.x, end.z);
+        let steps = (x1 - x0).abs().max((y1 - y0).abs()).ceil().max(1.0) as u32;
+        for step in 0..=steps {
+            let t = step as f32 / steps as f32;
+            let x = x0 + (x1 - x0) * t;
+            let y = y0 + (y1 - y0) * t;
+            self.plot(x.floor() as i64, y.floor() as i64, color);
+        }
+    }
+
+    /// Draw a dot around a point of the world, at least a pixel across
+    fn dot(&mut self, at: &Position, radius: f32, color: u8) {
+        let (cx, cy) = self.project(at.x, at.z);
+        let radius = (radius * self.scale).max(0.5);
+        for y in (cy - radius).floor() as i64..=(cy + radius).floor() as i64 {
+            for x in (cx - radius).floor() as i64..=(cx + radius).floor() as i64 {
+                let (dx, dy) = (x as f32 + 0.5 - cx, y as f32 + 0.5 - cy);
+                if dx * dx + dy * dy <= radius * radius {
+                    self.plot(x, y, color);
+                }
+            }
+        }
+        self.plot(cx.floor() as i64, cy.floor() as i64, color);
+    }
+
+    /// Encode the canvas as a PNG image
+    fn to_png(&self) -> Vec<u8> {
+        let mut header = Vec::with_capacity(13);
+        header.extend_from_slice(&self.width.to_be_bytes());
+        header.extend_from_slice(&self.height.to_be_bytes());
+        // Eight bits per pixel, paletted, no interlacing
+        header.extend_from_slice(&[8, 3, 0, 0, 0]);
+
+        // Each row starts with the filter it was encoded with: none
+        let row = self.width as usize;
+        let mut raw = Vec::with_capacity((row + 1) * self.height as usize);
+        for pixels in self.pixels.chunks(row) {
+            raw.push(0);
+            raw.extend_from_slice(pixels);
+        }
+
+        let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+        write_chunk(&mut png, b"IHDR", &header);
+        write_chunk(&mut png, b"PLTE", PALETTE.as_flattened());
+        write_chunk(&mut png, b"IDAT", &zlib_stored(&raw));
+        write_chunk(&mut png, b"IEND", &[]);
+        png
+    }
+}
+
+/// Append a PNG chunk, with its length and checksum
+fn write_chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = png.len();
+    png.extend_from_slice(kind);
+    png.extend_from_slice(data);
+    let crc = crc32(&png[start..]);
+    png.extend_from_slice(&crc.to_be_bytes());
+}
+
+/// Wrap data in a zlib stream without compressing it
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let blocks = data.len().div_ceil(MAX_STORED_BLOCK).max(1);
+    let mut stream = Vec::with_capacity(data.len() + blocks * 5 + 6);
+    stream.extend_from_slice(&[0x78, 0x01]);
+    let mut chunks = data.chunks(MAX_STORED_BLOCK).peekable();
+    while let Some(chunk) = chunks.next() {
+        stream.push(u8::from(chunks.peek().is_none()));
+        let len = chunk.len() as u16;
+        stream.extend_from_slice(&len.to_le_bytes());
+        stream.extend_from_slice(&(!len).to_le_bytes());
+        stream.extend_from_slice(chunk);
+    }
+    stream.extend_from_slice(&adler32(data).to_be_bytes());
+    stream
+}
+
+/// The CRC-32 checksum PNG chunks end with
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+/// The Adler-32 checksum zlib streams end with
+fn adler32(bytes: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for &byte in bytes {
+        a = (a + byte as u32) % 65_521;
+        b = (b + a) % 65_521;
+    }
+    (b << 16) | a
+}
+
+#[cfg(any(feature = "stats", feature = "serialization"))]
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Encode bytes as base64, for embedding images in text
+#[cfg(any(feature = "stats", feature = "serialization"))]
+pub(super) fn base64(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let b = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let n = (b[0] as usize) << 16 | (b[1] as usize) << 8 | b[2] as usize;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(BASE64_ALPHABET[(n >> (18 - 6 * i)) & 63] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+/// Decode base64 written by [`base64`]
+#[cfg(feature = "serialization")]
+pub(super) fn from_base64(text: &str) -> Result<Vec<u8>> {
+    let text = text.trim_end_matches('=');
+    let mut bytes = Vec::with_capacity(text.len() * 3 / 4);
+    let mut bits = 0u32;
+    let mut count = 0;
+    for c in text.bytes() {
+        let Some(value) = BASE64_ALPHABET.iter().position(|&a| a == c) else {
+            bail!("'{}' isn't a base64 character", c as char);
+        };
+        bits = (bits << 6) | value as u32;
+        count += 6;
+        if count >= 8 {
+            count -= 8;
+            bytes.push((bits >> count) as u8);
+        }
+    }
+    Ok(bytes)
+}
+
+impl SimWorld {
+    /// The part of the world a map of the city shows: every intersection
+    /// and all the water, with a margin around them
+    pub(super) fn map_bounds(&self) -> MapBounds {
+        let network = &self.road_network;
+        let positions = self
+            .intersections
+            .keys()
+            .filter_map(|&id| network.get_intersection_position(id));
+        let mut corners = positions
+            .map(|p| (p.x, p.z, p.x, p.z))
+            .chain(self.water.iter().map(|w| (w.min_x, w.min_z, w.max_x, w.max_z)));
+        let (min_x, min_z, max_x, max_z) = corners
+            .next()
+            .map(|first| {
+                corners.fold(first, |(a, b, c, d), (e, f, g, h)| {
+                    (a.min(e), b.min(f), c.max(g), d.max(h))
+                })
+            })
+            .unwrap_or((0.0, 0.0, 0.0, 0.0));
+        MapBounds {
+            min_x: min_x - MAP_MARGIN,
+            min_z: min_z - MAP_MARGIN,
+            width: max_x - min_x + 2.0 * MAP_MARGIN,
+            depth: max_z - min_z + 2.0 * MAP_MARGIN,
+        }
+    }
+
+    /// Map of the city as a PNG image `width` pixels across, as tall as the
+    /// city's shape needs: water in blue, roads in grey (red where
+    /// congested), houses green, factories orange, shops blue and schools
+    /// and hospitals purple
+    pub fn map_png(&self, width: u32) -> Vec<u8> {
+        let mut canvas = Canvas::new(width, self.map_bounds());
+        for water in &self.water {
+            canvas.fill_rect((water.min_x, water.min_z), (water.max_x, water.max_z), WATER);
+        }
+        let network = &self.road_network;
+        for road in network.roads().values() {
+            let (Some(start), Some(end)) = (
+                network.get_intersection_position(road.start_intersection),
+                network.get_intersection_position(road.end_intersection),
+            ) else {
+                continue;
+            };
+            let congested = network.calculate_traffic_density(road.id) >= CONGESTED_ROAD_DENSITY;
+            canvas.line(start, end, if congested { CONGESTED_ROAD } else { ROAD });
+        }
+        let buildings = self
+            .apartments
+            .values()
+            .map(|a| (a.intersection_id, APARTMENT))
+            .chain(self.factories.values().map(|f| (f.intersection_id, FACTORY)))
+            .chain(self.shops.values().map(|s| (s.intersection_id, SHOP)))
+            .chain(
+                self.special_buildings
+                    .values()
+                    .map(|b| (b.intersection_id, SPECIAL)),
+            );
+        for (intersection_id, color) in buildings {
+            if let Some(position) = network.get_intersection_position(intersection_id) {
+                canvas.dot(position, BUILDING_RADIUS, color);
+            }
+        }
+        canvas.to_png()
+    }
+}
@@ -0,0 +1,357 @@
+//! The recap shown when a game ends
+//!
+//! When a game is won or lost the front-end shows a recap of the run: how
+//! the money went over time, the biggest traffic jam, the distance every
+//! vehicle drove between them and the MVP factory, whose goods made the
+//! most shop deliveries. [`SimWorld::recap`] builds it from the statistics
+//! the world collects as it runs. The money is sampled every
+//! [`RECAP_MONEY_INTERVAL_SECS`], and once there are more than
+//! [`RECAP_MONEY_SAMPLES`] every other sample is dropped and the rest are
+//! taken half as often, so a long game keeps a timeline of the whole run in
+//! a fixed space. The biggest jam is the most vehicles seen stopped on one
+//! road at once, looked at every second.
+//!
+//! Each scenario can word and arrange its own recap with the economy's
+//! [`RecapConfig`]: the headings for a win and a loss, a closing message,
+//! which sections are shown in what order, and whether the player may keep
+//! playing the city as a sandbox once the game is over.
+
+use anyhow::{ensure, Result};
+#[cfg(feature = "serialization")]
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "stats")]
+use super::emissions::WORLD_UNIT_METRES;
+#[cfg(feature = "stats")]
+use super::types::{FactoryId, RoadId, SimMap};
+#[cfg(feature = "stats")]
+use super::world::SimWorld;
+
+/// Seconds between samples of the money at the start of a game
+pub const RECAP_MONEY_INTERVAL_SECS: f32 = 5.0;
+
+/// Most samples of the money kept before they are thinned out
+pub const RECAP_MONEY_SAMPLES: usize = 200;
+
+/// Seconds between looks for the biggest traffic jam
+#[cfg(feature = "stats")]
+const JAM_CHECK_SECS: f32 = 1.0;
+
+/// A part of the recap
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serialization", serde(rename_all = "snake_case"))]
+pub enum RecapSection {
+    /// The money over the run
+    Money,
+    BiggestJam,
+    DistanceDriven,
+    MvpFactory,
+}
+
+impl RecapSection {
+    pub const ALL: [RecapSection; 4] = [
+        RecapSection::Money,
+        RecapSection::BiggestJam,
+        RecapSection::DistanceDriven,
+        RecapSection::MvpFactory,
+    ];
+
+    /// Heading of the section
+    pub fn label(self) -> &'static str {
+        match self {
+            RecapSection::Money => "Money",
+            RecapSection::BiggestJam => "Biggest traffic jam",
+            RecapSection::DistanceDriven => "Distance driven",
+            RecapSection::MvpFactory => "MVP factory",
+        }
+    }
+}
+
+/// How a scenario words and arranges the recap of a finished game
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serialization", serde(default, deny_unknown_fields))]
+pub struct RecapConfig {
+    /// Heading when the game is won
+    pub victory_title: String,
+    /// Heading when the game is lost
+    pub defeat_title: String,
+    /// Text under the heading, such as how the scenario's story ends
+    pub message: String,
+    /// Sections shown, in order
+    pub sections: Vec<RecapSection>,
+    /// Whether the player may keep playing the city once the game is over
+    pub sandbox: bool,
+}
+
+impl Default for RecapConfig {
+    fn default() -> Self {
+        Self {
+            victory_title: "Victory!".to_string(),
+            defeat_title: "Game Over".to_string(),
+            message: String::new(),
+            sections: RecapSection::ALL.to_vec(),
+            sandbox: true,
+        }
+    }
+}
+
+impl RecapConfig {
+    /// Reject a section listed twice
+    pub fn validate(&self) -> Result<()> {
+        for (i, section) in self.sections.iter().enumerate() {
+            ensure!(
+                !self.sections[..i].contains(section),
+                "recap section {:?} is listed twice",
+                section
+            );
+        }
+        Ok(())
+    }
+}
+
+/// How the game stands in a recap
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecapOutcome {
+    /// Still being played, or not a game
+    Playing,
+    Won,
+    Lost,
+}
+
+/// The most vehicles stopped on one road at once
+#[cfg(feature = "stats")]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TrafficJam {
+    pub road_id: RoadId,
+    pub vehicles: usize,
+    /// Simulation time it was seen at
+    pub time: f32,
+}
+
+/// The factory whose goods made the most shop deliveries
+#[cfg(feature = "stats")]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MvpFactory {
+    pub factory_id: FactoryId,
+    /// Shop deliveries of its goods
+    pub deliveries: f32,
+    /// Revenue those deliveries earned
+    pub revenue: f32,
+    /// Worker shifts worked there
+    pub shifts: u32,
+}
+
+/// What the recap screen shows of a run
+#[cfg(feature = "stats")]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Recap {
+    pub outcome: RecapOutcome,
+    /// The scenario's heading for the outcome
+    pub title: String,
+    pub message: String,
+    pub sections: Vec<RecapSection>,
+    /// Whether the player may keep playing the city
+    pub sandbox: bool,
+    /// Simulation time of the recap
+    pub time: f32,
+    /// The money at each sample time, oldest first, ending with the money
+    /// now; empty without a game
+    pub money: Vec<(f32, i32)>,
+    pub biggest_jam: Option<TrafficJam>,
+    /// World units driven by every vehicle
+    pub distance_driven: f32,
+    pub mvp_factory: Option<MvpFactory>,
+}
+
+#[cfg(feature = "stats")]
+impl Recap {
+    /// Kilometres driven by every vehicle
+    pub fn distance_driven_km(&self) -> f32 {
+        self.distance_driven * WORLD_UNIT_METRES / 1000.0
+    }
+
+    /// Lowest and highest money over the run
+    pub fn money_range(&self) -> Option<(i32, i32)> {
+        let min = self.money.iter().map(|(_, money)| *money).min()?;
+        let max = self.money.iter().map(|(_, money)| *money).max()?;
+        Some((min, max))
+    }
+}
+
+/// Statistics kept as the world runs for its recap
+#[cfg(feature = "stats")]
+#[derive(Debug, Clone)]
+pub(super) struct RecapStats {
+    money: Vec<(f32, i32)>,
+    money_interval: f32,
+    next_money_at: f32,
+    next_jam_check_at: f32,
+    biggest_jam: Option<TrafficJam>,
+    distance: f32,
+}
+
+#[cfg(feature = "stats")]
+impl Default for RecapStats {
+    fn default() -> Self {
+        Self {
+            money: Vec::new(),
+            money_interval: RECAP_MONEY_INTERVAL_SECS,
+            next_money_at: 0.0,
+            next_jam_check_at: 0.0,
+            biggest_jam: None,
+            distance: 0.0,
+        }
+    }
+}
+
+#[cfg(feature = "stats")]
+impl RecapStats {
+    /// Sample the money if it's time to, thinning the samples out once
+    /// there are too many
+    fn sample_money(&mut self, now: f32, money: i32) {
+        if now < self.next_money_at {
+            return;
+        }
+        self.money.push((now, money));
+        self.next_money_at = now + self.money_interval;
+        if self.money.len() > RECAP_MONEY_SAMPLES {
+            let mut index = 0;
+            self.money.retain(|_| {
+                index += 1;
+                index % 2 == 1
+            });
+            self.money_interval *= 2.0;
+        }
+    }
+
+    /// Samples of the money kept
+    pub(super) fn len(&self) -> usize {
+        self.money.len()
+    }
+
+    /// Approximate bytes the samples take
+    pub(super) fn memory_bytes(&self) -> usize {
+        self.money.capacity() * std::mem::size_of::<(f32, i32)>()
+    }
+}
+
+#[cfg(feature = "stats")]
+impl SimWorld {
+    /// Add up this tick's driving, sample the money and look for the
+    /// biggest jam
+    pub(super) fn record_recap(&mut self) {
+        let now = self.time;
+        let stats = &mut self.recap_stats;
+        stats.distance += self.cars.values().map(|car| car.odometer).sum::<f32>();
+        if let Some(game_state) = &self.game_state {
+            stats.sample_money(now, game_state.money);
+        }
+        if now < stats.next_jam_check_at {
+            return;
+        }
+        stats.next_jam_check_at = now + JAM_CHECK_SECS;
+        let mut stopped: SimMap<RoadId, usize> = SimMap::new();
+        for car in self.cars.values() {
+            if car.current_speed > 0.0
+                || car.stalled_secs > 0.0
+                || car.path.is_empty()
+                || car.queued_at_start
+            {
+                continue;
+            }
+            *stopped.entry(car.current_road).or_default() += 1;
+        }
+        let Some((road_id, vehicles)) = stopped.into_iter().max_by_key(|(_, count)| *count) else {
+            return;
+        };
+        if stats.biggest_jam.is_none_or(|jam| vehicles > jam.vehicles) {
+            stats.biggest_jam = Some(TrafficJam {
+                road_id,
+                vehicles,
+                time: now,
+            });
+        }
+    }
+
+    /// The factory whose goods made the most shop deliveries, or failing
+    /// any deliveries the one most shifts were worked at
+    fn mvp_factory(&self) -> Option<MvpFactory> {
+        let mut factories: SimMap<FactoryId, MvpFactory> = SimMap::new();
+        let blank = |factory_id| MvpFactory {
+            factory_id,
+            deliveries: 0.0,
+            revenue: 0.0,
+            shifts: 0,
+        };
+        for catchment in self.shop_catchments() {
+            for (factory_id, attribution) in catchment.factories {
+                let factory = factories
+                    .entry(factory_id)
+                    .or_insert_with(|| blank(factory_id));
+                factory.deliveries += attribution.deliveries;
+                factory.revenue += attribution.revenue;
+            }
+        }
+        for (factory_id, labor) in self.labor_by_factory() {
+            factories
+                .entry(factory_id)
+                .or_insert_with(|| blank(factory_id))
+                .shifts = labor.fulfilled;
+        }
+        factories
+            .into_values()
+            .filter(|factory| factory.deliveries > 0.0 || factory.shifts > 0)
+            .max_by(|a, b| {
+                a.deliveries
+                    .total_cmp(&b.deliveries)
+                    .then(a.shifts.cmp(&b.shifts))
+                    // The older factory on a tie
+                    .then(b.factory_id.cmp(&a.factory_id))
+            })
+    }
+
+    /// Recap of the run so far, worded as the economy's [`RecapConfig`]
+    /// says
+    pub fn recap(&self) -> Recap {
+        let default_config = RecapConfig::default();
+        let (outcome, config) = match &self.game_state {
+            Some(game_state) => {
+                let outcome = if game_state.is_won {
+                    RecapOutcome::Won
+                } else if game_state.is_lost {
+                    RecapOutcome::Lost
+                } else {
+                    RecapOutcome::Playing
+                };
+                (outcome, &game_state.economy.recap)
+            }
+            None => (RecapOutcome::Playing, &default_config),
+        };
+        let title = match outcome {
+            RecapOutcome::Won => config.victory_title.clone(),
+            RecapOutcome::Lost => config.defeat_title.clone(),
+            RecapOutcome::Playing => "Run so far".to_string(),
+        };
+        let stats = &self.recap_stats;
+        let mut money = stats.money.clone();
+        if let Some(game_state) = &self.game_state {
+            if money.last().is_none_or(|(time, _)| *time < self.time) {
+                money.push((self.time, game_state.money));
+            }
+        }
+        Recap {
+            outcome,
+            title,
+            message: config.message.clone(),
+            sections: config.sections.clone(),
+            sandbox: config.sandbox,
+            time: self.time,
+            money,
+            biggest_jam: stats.biggest_jam,
+            distance_driven: stats.distance,
+            mvp_factory: self.mvp_factory(),
+        }
+    }
+}
@@ -0,0 +1,108 @@
+//! Finding vehicles by where they are
+//!
+//! [`SimWorld::cars_in_region`] lists the vehicles in a rectangle of the map
+//! that match a [`CarFilter`], for overlays, statistics over part of the
+//! city and selecting vehicles by dragging out a box. Rather than check
+//! every vehicle, it uses the road network's index of the vehicles on each
+//! road: only roads that come near the rectangle are looked at, so a small
+//! region of a big city is quick to search. Vehicles still waiting at their
+//! start for a route or to pull out aren't on a road yet, so aren't found.
+
+use super::car::SimCar;
+use super::types::{Position, SimRoad, TripType, VehicleType};
+use super::world::SimWorld;
+
+/// How far a vehicle on a road can be from the line between the road's
+/// ends: its lane, or the arc it takes through a turn at either end
+const ROAD_MARGIN: f32 = 2.0;
+
+/// A rectangle of the map, aligned with the world's axes
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Region {
+    pub min_x: f32,
+    pub min_z: f32,
+    pub max_x: f32,
+    pub max_z: f32,
+}
+
+impl Region {
+    /// The rectangle between two opposite corners
+    pub fn new(corner: Position, opposite: Position) -> Self {
+        Self {
+            min_x: corner.x.min(opposite.x),
+            min_z: corner.z.min(opposite.z),
+            max_x: corner.x.max(opposite.x),
+            max_z: corner.z.max(opposite.z),
+        }
+    }
+
+    /// The square reaching `radius` either way from `center`
+    pub fn around(center: Position, radius: f32) -> Self {
+        Self {
+            min_x: center.x - radius,
+            min_z: center.z - radius,
+            max_x: center.x + radius,
+            max_z: center.z + radius,
+        }
+    }
+
+    /// Whether a point is in the rectangle, whatever its height
+    pub fn contains(&self, position: &Position) -> bool {
+        (self.min_x..=self.max_x).contains(&position.x)
+            && (self.min_z..=self.max_z).contains(&position.z)
+    }
+
+    /// Whether a vehicle on a road from `start` to `end` could be in the
+    /// rectangle
+    fn near_road(&self, start: &Position, end: &Position) -> bool {
+        start.x.min(end.x) - ROAD_MARGIN <= self.max_x
+            && start.x.max(end.x) + ROAD_MARGIN >= self.min_x
+            && start.z.min(end.z) - ROAD_MARGIN <= self.max_z
+            && start.z.max(end.z) + ROAD_MARGIN >= self.min_z
+    }
+}
+
+/// Which vehicles to find; each condition left as `None` matches any vehicle
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct CarFilter {
+    pub vehicle_type: Option<VehicleType>,
+    pub trip_type: Option<TripType>,
+    /// Only vehicles going slower than this, in distance per second over
+    /// their last update
+    pub slower_than: Option<f32>,
+}
+
+impl CarFilter {
+    /// Whether a vehicle meets every condition
+    pub fn matches(&self, car: &SimCar) -> bool {
+        self.vehicle_type
+            .is_none_or(|kind| car.vehicle_type == kind)
+            && self.trip_type.is_none_or(|trip| car.trip_type == trip)
+            && self
+                .slower_than
+                .is_none_or(|speed| car.current_speed < speed)
+    }
+}
+
+impl SimWorld {
+    /// Vehicles on the roads in `region` that match `filter`, in ID order
+    pub fn cars_in_region(&self, region: &Region, filter: &CarFilter) -> Vec<&SimCar> {
+        let network = &self.road_network;
+        let near = |road: &&SimRoad| {
+            let at = |id| network.get_intersection_position(id);
+            at(road.start_intersection)
+                .zip(at(road.end_intersection))
+                .is_some_and(|(start, end)| region.near_road(start, end))
+        };
+        let mut cars: Vec<&SimCar> = network
+            .roads()
+            .values()
+            .filter(near)
+            .flat_map(|road| network.get_cars_on_road(road.id))
+            .filter_map(|car_id| self.cars.get(&car_id))
+            .filter(|car| region.contains(&car.position) && filter.matches(car))
+            .collect();
+        cars.sort_by_key(|car| car.id);
+        cars
+    }
+}
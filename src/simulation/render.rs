@@ -0,0 +1,524 @@
+//! Front-end agnostic rendering interface
+//!
+//! The simulation describes everything a front-end needs to draw as a flat
+//! list of [`Visual`]s. A front-end implements [`SimRenderer`] and lets a
+//! [`RenderSync`] work out which visuals to spawn, update and despawn each
+//! frame, so it never has to look inside apartments, factories or cars to
+//! decide what to show. The Bevy UI is one such front-end; a terminal view or
+//! an image exporter could be another.
+//!
+//! Once a front-end has caught up, the world records which visuals each tick
+//! touches (vehicles that moved, appeared or left, and the buildings they
+//! belong to), so syncing a large, mostly idle world only revisits those.
+//! Editing the layout (intersections, roads and buildings) marks the whole
+//! world as changed.
+//!
+//! A front-end can draw several worlds in turn with one [`RenderSync`],
+//! calling [`RenderSync::resync`] whenever it switches between them.
+//!
+//! A front-end can give its [`RenderSync`] a spawn budget, so that catching
+//! up with a big world (a loaded save, say) spawns its visuals over several
+//! frames rather than stalling on one.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use super::decoration::DecorationKind;
+use super::diagnostics::set_bytes;
+use super::driving_side::DrivingSide;
+use super::special::SpecialKind;
+use super::types::{
+    ApartmentId, CarId, DecorationId, FactoryId, IntersectionId, Position, RoadId, RoadKind,
+    ShopId, SpecialBuildingId, VehicleType,
+};
+use super::world::SimWorld;
+
+/// Identifies the simulation object a visual stands for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum VisualId {
+    Intersection(IntersectionId),
+    /// A road; two-way roads have one visual, for their canonical direction
+    Road(RoadId),
+    Apartment(ApartmentId),
+    Factory(FactoryId),
+    Shop(ShopId),
+    Special(SpecialBuildingId),
+    Decoration(DecorationId),
+    Vehicle(CarId),
+}
+
+/// What a visual is, with the state a front-end shows for it
+#[derive(Debug, Clone, PartialEq)]
+pub enum VisualKind {
+    Intersection,
+    Road {
+        start: Position,
+        end: Position,
+        two_way: bool,
+        kind: RoadKind,
+        /// Side of a two-way road each direction's lane is on
+        driving_side: DrivingSide,
+    },
+    Apartment {
+        /// Whether any of its cars is out
+        busy: bool,
+        /// Healthy commute distance, shown as a ring around it
+        commute_radius: f32,
+    },
+    Factory {
+        /// Whether its truck is out
+        busy: bool,
+        deliveries_ready: u32,
+        max_deliveries: u32,
+        /// Fraction of the next delivery's shifts worked so far
+        production_progress: f32,
+    },
+    Shop,
+    Special(SpecialKind),
+    Decoration(DecorationKind),
+    Vehicle {
+        vehicle_type: VehicleType,
+        /// Stopped after a breakdown or collision
+        stalled: bool,
+        /// A delivery truck past its deadline
+        overdue: bool,
+    },
+}
+
+/// Everything a front-end needs to draw one simulation object
+#[derive(Debug, Clone, PartialEq)]
+pub struct Visual {
+    pub id: VisualId,
+    pub kind: VisualKind,
+    /// Where it stands; the start of a road
+    pub position: Position,
+    /// Heading in radians around the vertical axis (vehicles only)
+    pub angle: f32,
+    /// Display color from a content pack, as sRGB components in [0, 1]
+    pub color: Option<[f32; 3]>,
+    /// Display size from a content pack, as (width, height, length)
+    pub size: Option<[f32; 3]>,
+}
+
+impl Visual {
+    /// Whether a front-end draws an overlay over this visual every frame
+    pub fn has_overlay(&self) -> bool {
+        matches!(
+            self.kind,
+            VisualKind::Vehicle { stalled: true, .. } | VisualKind::Vehicle { overdue: true, .. }
+        )
+    }
+
+    /// Whether a front-end draws this the same way as `other` apart from
+    /// state it updates in place, so it needn't be spawned afresh; only
+    /// differs for the same id in different worlds
+    fn same_look(&self, other: &Visual) -> bool {
+        let looks_alike = match (&self.kind, &other.kind) {
+            (VisualKind::Road { kind: a, .. }, VisualKind::Road { kind: b, .. }) => a == b,
+            (VisualKind::Special(a), VisualKind::Special(b)) => a == b,
+            (VisualKind::Decoration(a), VisualKind::Decoration(b)) => a == b,
+            (
+                VisualKind::Vehicle { vehicle_type: a, .. },
+                VisualKind::Vehicle { vehicle_type: b, .. },
+            ) => a == b,
+            (a, b) => std::mem::discriminant(a) == std::mem::discriminant(b),
+        };
+        looks_alike && self.color == other.color && self.size == other.size
+    }
+
+    fn new(id: VisualId, kind: VisualKind, position: Position) -> Self {
+        Self {
+            id,
+            kind,
+            position,
+            angle: 0.0,
+            color: None,
+            size: None,
+        }
+    }
+}
+
+/// A front-end that draws the simulation
+pub trait SimRenderer {
+    /// Start drawing a visual that has appeared
+    fn spawn_visual(&mut self, visual: &Visual);
+
+    /// Redraw a visual whose state has changed since it was last drawn
+    fn update_visual(&mut self, visual: &Visual);
+
+    /// Stop drawing a visual whose simulation object is gone
+    fn despawn_visual(&mut self, id: VisualId);
+
+    /// Draw per-frame extras over a visual, such as a hazard ring on a
+    /// stopped vehicle; called on every sync for each visual that
+    /// [has an overlay](Visual::has_overlay)
+    fn draw_overlay(&mut self, _visual: &Visual) {}
+}
+
+/// Visuals that changed since a front-end last caught up
+#[derive(Debug, Default)]
+pub struct WorldChanges {
+    /// Anything may have changed: the layout was edited, or no front-end
+    /// had caught up yet
+    pub full: bool,
+    /// Visuals that appeared, changed or disappeared
+    pub visuals: HashSet<VisualId>,
+}
+
+/// Changes the world has recorded for its front-end
+#[derive(Debug, Default)]
+pub(super) struct ChangeLog {
+    /// Nothing is recorded until a front-end first takes the changes, so
+    /// headless runs don't pile them up
+    tracking: bool,
+    pending: WorldChanges,
+}
+
+impl ChangeLog {
+    /// Record that a visual appeared, changed or disappeared
+    pub(super) fn mark(&mut self, id: VisualId) {
+        if self.tracking && !self.pending.full {
+            self.pending.visuals.insert(id);
+        }
+    }
+
+    /// Record that the layout changed, so everything needs checking
+    pub(super) fn mark_layout(&mut self) {
+        self.pending.full = true;
+        self.pending.visuals.clear();
+    }
+
+    /// Visuals recorded and the approximate bytes they hold
+    pub(super) fn memory_usage(&self) -> (usize, usize) {
+        (
+            self.pending.visuals.len(),
+            set_bytes(&self.pending.visuals),
+        )
+    }
+}
+
+/// Tracks what a front-end is showing and brings it up to date with the
+/// simulation
+#[derive(Debug, Default)]
+pub struct RenderSync {
+    shown: HashMap<VisualId, Visual>,
+    /// Shown visuals that draw an overlay
+    overlaid: HashSet<VisualId>,
+    /// Whether this has synced with a world before
+    caught_up: bool,
+    /// Most visuals to spawn per sync; `None` spawns everything at once
+    spawn_budget: Option<usize>,
+    /// Spawns left in the current sync
+    spawns_left: usize,
+    /// Visuals left for a later sync to spawn, oldest first; may hold
+    /// visuals that have since been spawned or gone
+    queue: VecDeque<VisualId>,
+    /// Visuals still waiting to be spawned
+    waiting: HashSet<VisualId>,
+    /// Visuals spawned or queued since none were last waiting
+    batch_size: usize,
+}
+
+impl RenderSync {
+    /// Spawn at most `per_sync` visuals per sync, leaving the rest for later
+    /// syncs
+    pub fn with_spawn_budget(per_sync: usize) -> Self {
+        Self {
+            spawn_budget: Some(per_sync.max(1)),
+            ..Self::default()
+        }
+    }
+
+    /// Visuals waiting for a later sync to spawn them
+    pub fn pending_spawns(&self) -> usize {
+        self.waiting.len()
+    }
+
+    /// Share of the visuals to spawn since none were last waiting that have
+    /// been spawned, while any are still waiting
+    pub fn loading_progress(&self) -> Option<f32> {
+        (!self.waiting.is_empty())
+            .then(|| 1.0 - self.waiting.len() as f32 / self.batch_size as f32)
+    }
+
+    /// Number of visuals the front-end is showing
+    pub fn len(&self) -> usize {
+        self.shown.len()
+    }
+
+    /// Whether the front-end is showing nothing
+    pub fn is_empty(&self) -> bool {
+        self.shown.is_empty()
+    }
+
+    /// Check every visual on the next sync, as after switching to drawing
+    /// another world
+    ///
+    /// Visuals with the same id in both worlds, such as the layout of a
+    /// world copied from the other, are updated in place rather than
+    /// respawned, unless they look different there, like a car in one world
+    /// whose id is a truck's in the other.
+    pub fn resync(&mut self) {
+        self.caught_up = false;
+    }
+
+    /// Spawn, update and despawn visuals so the renderer matches the world
+    ///
+    /// Only the visuals the world recorded as changed are looked at, unless
+    /// the layout changed or this is the first sync. With a spawn budget,
+    /// visuals queued by earlier syncs are spawned first.
+    pub fn sync(&mut self, world: &mut SimWorld, renderer: &mut impl SimRenderer) {
+        self.spawns_left = self.spawn_budget.unwrap_or(usize::MAX);
+        while self.spawns_left > 0 {
+            let Some(id) = self.queue.pop_front() else {
+                break;
+            };
+            if self.waiting.contains(&id) {
+                self.show(id, world.visual(id), renderer);
+            }
+        }
+
+        let changes = world.take_changes();
+        if changes.full || !self.caught_up {
+            self.caught_up = true;
+            let mut seen = HashSet::with_capacity(self.shown.len());
+            for visual in world.visuals() {
+                seen.insert(visual.id);
+                self.show(visual.id, Some(visual), renderer);
+            }
+            let gone: Vec<VisualId> = self
+                .shown
+                .keys()
+                .filter(|id| !seen.contains(id))
+                .copied()
+                .collect();
+            for id in gone {
+                self.show(id, None, renderer);
+            }
+        } else {
+            for id in changes.visuals {
+                self.show(id, world.visual(id), renderer);
+            }
+        }
+
+        if self.waiting.is_empty() {
+            self.queue.clear();
+            self.batch_size = 0;
+        }
+
+        for id in &self.overlaid {
+            if let Some(visual) = self.shown.get(id) {
+                renderer.draw_overlay(visual);
+            }
+        }
+    }
+
+    /// Bring one visual up to date; `None` if it no longer exists
+    fn show(&mut self, id: VisualId, visual: Option<Visual>, renderer: &mut impl SimRenderer) {
+        match (visual, self.shown.get(&id)) {
+            (None, None) => {
+                self.waiting.remove(&id);
+            }
+            (None, Some(_)) => {
+                renderer.despawn_visual(id);
+                self.shown.remove(&id);
+                self.overlaid.remove(&id);
+            }
+            (Some(visual), shown) => {
+                match shown {
+                    None if self.spawns_left == 0 => {
+                        if self.waiting.insert(id) {
+                            self.queue.push_back(id);
+                            self.batch_size += 1;
+                        }
+                        return;
+                    }
+                    None => {
+                        self.spawns_left -= 1;
+                        if !self.waiting.remove(&id) {
+                            self.batch_size += 1;
+                        }
+                        renderer.spawn_visual(&visual)
+                    }
+                    Some(shown) if !shown.same_look(&visual) => {
+                        renderer.despawn_visual(id);
+                        renderer.spawn_visual(&visual)
+                    }
+                    Some(shown) if *shown != visual => renderer.update_visual(&visual),
+                    Some(_) => return,
+                }
+                if visual.has_overlay() {
+                    self.overlaid.insert(id);
+                } else {
+                    self.overlaid.remove(&id);
+                }
+                self.shown.insert(id, visual);
+            }
+        }
+    }
+}
+
+impl SimWorld {
+    /// Take the changes recorded since the last call, and keep recording
+    pub fn take_changes(&mut self) -> WorldChanges {
+        let log = &mut self.changes;
+        if !log.tracking {
+            log.tracking = true;
+            log.pending = WorldChanges::default();
+            return WorldChanges {
+                full: true,
+                ..WorldChanges::default()
+            };
+        }
+        std::mem::take(&mut log.pending)
+    }
+
+    /// Record that a vehicle changed, along with the apartment or factory
+    /// it belongs to
+    pub(super) fn mark_vehicle_changed(&mut self, car_id: CarId) {
+        self.changes.mark(VisualId::Vehicle(car_id));
+        let Some(car) = self.cars.get(&car_id) else {
+            return;
+        };
+        let owners = [
+            car.origin_apartment.map(VisualId::Apartment),
+            car.origin_factory.map(VisualId::Factory),
+        ];
+        for owner in owners.into_iter().flatten() {
+            self.changes.mark(owner);
+        }
+    }
+
+    /// Every visual in the world: intersections, roads, buildings,
+    /// decorations and then vehicles
+    pub fn visuals(&self) -> Vec<Visual> {
+        let network = &self.road_network;
+        let ids = self
+            .intersections
+            .keys()
+            .map(|id| VisualId::Intersection(*id))
+            .chain(network.get_all_roads().map(|(id, _)| VisualId::Road(*id)))
+            .chain(self.apartments.keys().map(|id| VisualId::Apartment(*id)))
+            .chain(self.factories.keys().map(|id| VisualId::Factory(*id)))
+            .chain(self.shops.keys().map(|id| VisualId::Shop(*id)))
+            .chain(
+                self.special_buildings
+                    .keys()
+                    .map(|id| VisualId::Special(*id)),
+            )
+            .chain(self.decorations.keys().map(|id| VisualId::Decoration(*id)))
+            .chain(self.cars.keys().map(|id| VisualId::Vehicle(*id)));
+        ids.filter_map(|id| self.visual(id)).collect()
+    }
+
+    /// The visual for one simulation object, if it exists and is drawn
+    pub fn visual(&self, id: VisualId) -> Option<Visual> {
+        let network = &self.road_network;
+        let position_of = |id: IntersectionId| network.get_intersection_position(id).copied();
+        let building_color = |variant: Option<&str>| {
+            variant
+                .and_then(|name| self.content.building(name))
+                .and_then(|def| def.color)
+        };
+
+        let visual = match id {
+            VisualId::Intersection(intersection_id) => Visual::new(
+                id,
+                VisualKind::Intersection,
+                self.intersections.get(&intersection_id)?.position,
+            ),
+            VisualId::Road(road_id) => {
+                // Two-way roads are drawn for their canonical direction only
+                let road = network
+                    .get_road(road_id)
+                    .filter(|_| network.canonical_road(road_id) == road_id)?;
+                let start = position_of(road.start_intersection)?;
+                let end = position_of(road.end_intersection)?;
+                Visual::new(
+                    id,
+                    VisualKind::Road {
+                        start,
+                        end,
+                        two_way: road.is_two_way,
+                        kind: road.kind,
+                        driving_side: self.driving_side,
+                    },
+                    start,
+                )
+            }
+            VisualId::Apartment(apartment_id) => {
+                let apartment = self.apartments.get(&apartment_id)?;
+                let mut visual = Visual::new(
+                    id,
+                    VisualKind::Apartment {
+                        busy: apartment.cars.iter().any(|car| car.is_some()),
+                        commute_radius: self.commute_healthy_distance(),
+                    },
+                    position_of(apartment.intersection_id)?,
+                );
+                visual.color = building_color(apartment.variant.as_deref());
+                visual
+            }
+            VisualId::Factory(factory_id) => {
+                let factory = self.factories.get(&factory_id)?;
+                let mut visual = Visual::new(
+                    id,
+                    VisualKind::Factory {
+                        busy: factory.truck.is_some(),
+                        deliveries_ready: factory.deliveries_ready,
+                        max_deliveries: factory.max_deliveries,
+                        production_progress: factory.production_progress(),
+                    },
+                    position_of(factory.intersection_id)?,
+                );
+                visual.color = building_color(factory.variant.as_deref());
+                visual
+            }
+            VisualId::Shop(shop_id) => {
+                let shop = self.shops.get(&shop_id)?;
+                let mut visual =
+                    Visual::new(id, VisualKind::Shop, position_of(shop.intersection_id)?);
+                visual.color = building_color(shop.variant.as_deref());
+                visual
+            }
+            VisualId::Special(special_id) => {
+                let building = self.special_buildings.get(&special_id)?;
+                Visual::new(
+                    id,
+                    VisualKind::Special(building.kind),
+                    position_of(building.intersection_id)?,
+                )
+            }
+            VisualId::Decoration(decoration_id) => {
+                let decoration = self.decorations.get(&decoration_id)?;
+                Visual::new(
+                    id,
+                    VisualKind::Decoration(decoration.kind),
+                    decoration.position,
+                )
+            }
+            VisualId::Vehicle(car_id) => {
+                let car = self.cars.get(&car_id)?;
+                let mut visual = Visual::new(
+                    id,
+                    VisualKind::Vehicle {
+                        vehicle_type: car.vehicle_type,
+                        stalled: car.stalled_secs > 0.0,
+                        overdue: car.is_overdue(),
+                    },
+                    car.position,
+                );
+                visual.angle = car.angle;
+                if let Some(def) = car
+                    .variant
+                    .as_deref()
+                    .and_then(|name| self.content.vehicle(name))
+                {
+                    visual.color = def.color;
+                    visual.size = def.size;
+                }
+                visual
+            }
+        };
+        Some(visual)
+    }
+}
@@ -0,0 +1,413 @@
+//! End-of-run reports
+//!
+//! A report documents a run in one file: a map of the city as it ended, the
+//! game's summary statistics with the highlights of its recap (see
+//! [`super::recap`]), the money over time, a table for each kind of
+//! building and the most congested roads. It is written as Markdown, or as
+//! HTML for a path ending in `.html` or `.htm`. The map is an SVG drawing,
+//! inline in HTML and embedded as a data URI image in Markdown, so the
+//! report needs no other files.
+
+use std::fmt::Write as _;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+
+use super::achievements::CONGESTED_ROAD_DENSITY;
+use super::raster::{base64, MapBounds};
+use super::score::RunSummary;
+use super::types::{IntersectionId, Position, SimMap};
+use super::world::SimWorld;
+
+/// Roads listed in the congested roads table
+pub const REPORT_CONGESTED_ROADS: usize = 10;
+
+/// Most rows of the money over time table
+pub const REPORT_MONEY_ROWS: usize = 20;
+
+/// Width of the map drawing in pixels
+const MAP_WIDTH: f32 = 600.0;
+
+/// How a report is written
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportFormat {
+    Markdown,
+    Html,
+}
+
+impl ReportFormat {
+    /// The format for a report written to `path`: HTML for `.html` or
+    /// `.htm`, Markdown otherwise
+    pub fn for_path(path: &Path) -> Self {
+        let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+        if extension.eq_ignore_ascii_case("html") || extension.eq_ignore_ascii_case("htm") {
+            ReportFormat::Html
+        } else {
+            ReportFormat::Markdown
+        }
+    }
+}
+
+/// A table of a report
+struct Table {
+    heading: &'static str,
+    columns: &'static [&'static str],
+    rows: Vec<Vec<String>>,
+}
+
+/// Escape text for HTML
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// A share as a percentage, or a dash without one
+fn percent(share: Option<f32>) -> String {
+    share.map_or_else(|| "-".to_string(), |share| format!("{:.0}%", share * 100.0))
+}
+
+/// A position on the ground plane
+fn at(position: Option<&Position>) -> String {
+    position.map_or_else(String::new, |p| format!("({:.0}, {:.0})", p.x, p.z))
+}
+
+impl SimWorld {
+    /// Map of the city as an SVG drawing: water in blue, roads in grey
+    /// (red where congested), houses green, factories orange, shops blue and
+    /// schools and hospitals purple
+    pub fn map_svg(&self) -> String {
+        let network = &self.road_network;
+        let MapBounds {
+            min_x,
+            min_z,
+            width,
+            depth,
+        } = self.map_bounds();
+        let height = MAP_WIDTH * depth / width;
+
+        // Writing to a String can't fail
+        let mut svg = String::new();
+        let _ = writeln!(
+            svg,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{:.0}" height="{:.0}" viewBox="{:.2} {:.2} {:.2} {:.2}">"#,
+            MAP_WIDTH, height, min_x, min_z, width, depth
+        );
+        let _ = writeln!(
+            svg,
+            r##"<rect x="{:.2}" y="{:.2}" width="{:.2}" height="{:.2}" fill="#f4f1e8"/>"##,
+            min_x, min_z, width, depth
+        );
+        for water in &self.water {
+            let _ = writeln!(
+                svg,
+                r##"<rect x="{:.2}" y="{:.2}" width="{:.2}" height="{:.2}" fill="#8fc1e3"/>"##,
+                water.min_x,
+                water.min_z,
+                water.max_x - water.min_x,
+                water.max_z - water.min_z
+            );
+        }
+        for road in network.roads().values() {
+            let (Some(start), Some(end)) = (
+                network.get_intersection_position(road.start_intersection),
+                network.get_intersection_position(road.end_intersection),
+            ) else {
+                continue;
+            };
+            let congested = network.calculate_traffic_density(road.id) >= CONGESTED_ROAD_DENSITY;
+            let color = if congested { "#d33" } else { "#777" };
+            let _ = writeln!(
+                svg,
+                r#"<line x1="{:.2}" y1="{:.2}" x2="{:.2}" y2="{:.2}" stroke="{}" stroke-width="0.6"/>"#,
+                start.x, start.z, end.x, end.z, color
+            );
+        }
+        let buildings = self
+            .apartments
+            .values()
+            .map(|a| (a.intersection_id, "#3a3"))
+            .chain(self.factories.values().map(|f| (f.intersection_id, "#e80")))
+            .chain(self.shops.values().map(|s| (s.intersection_id, "#36c")))
+            .chain(
+                self.special_buildings
+                    .values()
+                    .map(|b| (b.intersection_id, "#93c")),
+            );
+        for (intersection_id, color) in buildings {
+            if let Some(p) = network.get_intersection_position(intersection_id) {
+                let _ = writeln!(
+                    svg,
+                    r#"<circle cx="{:.2}" cy="{:.2}" r="1.2" fill="{}"/>"#,
+                    p.x, p.z, color
+                );
+            }
+        }
+        svg.push_str("</svg>\n");
+        svg
+    }
+
+    /// Tables of the run's statistics, buildings and congested roads
+    fn report_tables(&self) -> Vec<Table> {
+        let network = &self.road_network;
+        let position = |id: IntersectionId| at(network.get_intersection_position(id));
+
+        let mut summary = vec![
+            ("Simulated time", format!("{:.1}s", self.time)),
+            ("Vehicles on the road", self.cars.len().to_string()),
+            ("Roads", network.roads().len().to_string()),
+            ("Houses", self.apartments.len().to_string()),
+            ("Factories", self.factories.len().to_string()),
+            ("Shops", self.shops.len().to_string()),
+            ("Employment", percent(self.labor_totals().employment_rate())),
+        ];
+        let emissions = self.emissions();
+        summary.extend([
+            ("Fuel used", format!("{:.1} L", emissions.fuel_litres)),
+            ("CO2", format!("{:.1} kg", emissions.co2_kg)),
+            ("CO2 per trip", format!("{:.2} kg", emissions.co2_per_trip())),
+            ("Time idling", percent(emissions.idle_share())),
+        ]);
+        let recap = self.recap();
+        summary.push((
+            "Distance driven",
+            format!("{:.1} km", recap.distance_driven_km()),
+        ));
+        if let Some(jam) = recap.biggest_jam {
+            summary.push((
+                "Biggest jam",
+                format!(
+                    "{} vehicles on road {} at {:.0}s",
+                    jam.vehicles, jam.road_id.0 .0, jam.time
+                ),
+            ));
+        }
+        if let Some(mvp) = recap.mvp_factory {
+            summary.push((
+                "MVP factory",
+                format!(
+                    "{} ({:.0} deliveries, {} shifts)",
+                    mvp.factory_id.0 .0, mvp.deliveries, mvp.shifts
+                ),
+            ));
+        }
+        if let Some(game_state) = &self.game_state {
+            let run = RunSummary::from_game_state("report", game_state);
+            summary.extend([
+                ("Money", format!("${}", run.money)),
+                ("Worker trips", run.worker_trips.to_string()),
+                ("Average commute", format!("{:.1}", run.average_commute)),
+                ("Shop deliveries", run.shop_deliveries.to_string()),
+                ("On time", percent(Some(game_state.on_time_rate()))),
+                ("Score", run.score.to_string()),
+            ]);
+        }
+        let summary = Table {
+            heading: "Summary",
+            columns: &["Statistic", "Value"],
+            rows: summary
+                .into_iter()
+                .map(|(name, value)| vec![name.to_string(), value])
+                .collect(),
+        };
+
+        let catchments = self.shop_catchments();
+        let mut apartment_deliveries: SimMap<_, f32> = SimMap::new();
+        let mut factory_deliveries: SimMap<_, f32> = SimMap::new();
+        for catchment in &catchments {
+            for (id, attribution) in &catchment.apartments {
+                *apartment_deliveries.entry(*id).or_default() += attribution.deliveries;
+            }
+            for (id, attribution) in &catchment.factories {
+                *factory_deliveries.entry(*id).or_default() += attribution.deliveries;
+            }
+        }
+        let apartment_labor: SimMap<_, _> = self.labor_by_apartment().into_iter().collect();
+        let factory_labor: SimMap<_, _> = self.labor_by_factory().into_iter().collect();
+
+        let houses = Table {
+            heading: "Houses",
+            columns: &["House", "Position", "Shifts worked", "Employment", "Shop deliveries"],
+            rows: self
+                .apartments
+                .values()
+                .map(|apartment| {
+                    let labor = apartment_labor.get(&apartment.id).copied().unwrap_or_default();
+                    let deliveries = apartment_deliveries.get(&apartment.id).copied();
+                    vec![
+                        apartment.id.0 .0.to_string(),
+                        position(apartment.intersection_id),
+                        labor.fulfilled.to_string(),
+                        percent(labor.employment_rate()),
+                        format!("{:.1}", deliveries.unwrap_or(0.0)),
+                    ]
+                })
+                .collect(),
+        };
+        let factories = Table {
+            heading: "Factories",
+            columns: &[
+                "Factory",
+                "Position",
+                "Deliveries ready",
+                "Shifts worked",
+                "Employment",
+                "Shop deliveries",
+            ],
+            rows: self
+                .factories
+                .values()
+                .map(|factory| {
+                    let labor = factory_labor.get(&factory.id).copied().unwrap_or_default();
+                    let deliveries = factory_deliveries.get(&factory.id).copied();
+                    vec![
+                        factory.id.0 .0.to_string(),
+                        position(factory.intersection_id),
+                        factory.deliveries_ready.to_string(),
+                        labor.fulfilled.to_string(),
+                        percent(labor.employment_rate()),
+                        format!("{:.0}", deliveries.unwrap_or(0.0)),
+                    ]
+                })
+                .collect(),
+        };
+        let shops = Table {
+            heading: "Shops",
+            columns: &["Shop", "Position", "Deliveries", "Revenue", "Factories", "Houses"],
+            rows: catchments
+                .iter()
+                .map(|catchment| {
+                    let intersection = self.shops.get(&catchment.shop_id).map(|s| s.intersection_id);
+                    vec![
+                        catchment.shop_id.0 .0.to_string(),
+                        intersection.map_or_else(String::new, position),
+                        catchment.deliveries.to_string(),
+                        format!("${}", catchment.revenue),
+                        catchment.factories.len().to_string(),
+                        catchment.apartments.len().to_string(),
+                    ]
+                })
+                .collect(),
+        };
+
+        // Fullest roads now, then the busiest over the run
+        let mut roads: Vec<_> = self
+            .road_stats()
+            .into_iter()
+            .filter(|road| road.vehicles_now > 0 || road.traffic_volume > 0)
+            .map(|road| (network.calculate_traffic_density(road.road_id), road))
+            .collect();
+        roads.sort_by(|(a_density, a), (b_density, b)| {
+            b_density
+                .total_cmp(a_density)
+                .then(b.traffic_volume.cmp(&a.traffic_volume))
+        });
+        let roads = Table {
+            heading: "Most congested roads",
+            columns: &["Road", "From", "To", "Length", "Vehicles now", "Density", "Vehicles in all"],
+            rows: roads
+                .into_iter()
+                .take(REPORT_CONGESTED_ROADS)
+                .map(|(density, road)| {
+                    vec![
+                        road.road_id.0 .0.to_string(),
+                        position(road.start_intersection),
+                        position(road.end_intersection),
+                        format!("{:.1}", road.length),
+                        road.vehicles_now.to_string(),
+                        format!("{:.2}", density),
+                        road.traffic_volume.to_string(),
+                    ]
+                })
+                .collect(),
+        };
+
+        // Evenly spread samples, always with the last
+        let step = recap.money.len().div_ceil(REPORT_MONEY_ROWS).max(1);
+        let last = recap.money.len().saturating_sub(1);
+        let money = Table {
+            heading: "Money over time",
+            columns: &["Time", "Money"],
+            rows: recap
+                .money
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| (last - i).is_multiple_of(step))
+                .map(|(_, (time, money))| vec![format!("{:.0}s", time), format!("${}", money)])
+                .collect(),
+        };
+
+        vec![summary, money, houses, factories, shops, roads]
+    }
+
+    /// Report of the run so far under `title`
+    pub fn run_report(&self, title: &str, format: ReportFormat) -> String {
+        let map = self.map_svg();
+        let tables = self.report_tables();
+        // Writing to a String can't fail
+        let mut report = String::new();
+        match format {
+            ReportFormat::Markdown => {
+                let _ = writeln!(report, "# {}\n", title);
+                let _ = writeln!(
+                    report,
+                    "![Map of the city](data:image/svg+xml;base64,{})\n",
+                    base64(map.as_bytes())
+                );
+                for table in tables {
+                    let _ = writeln!(report, "## {}\n", table.heading);
+                    if table.rows.is_empty() {
+                        report.push_str("None\n\n");
+                        continue;
+                    }
+                    let _ = writeln!(report, "| {} |", table.columns.join(" | "));
+                    let _ = writeln!(report, "|{}", "---|".repeat(table.columns.len()));
+                    for row in table.rows {
+                        let _ = writeln!(report, "| {} |", row.join(" | "));
+                    }
+                    report.push('\n');
+                }
+            }
+            ReportFormat::Html => {
+                let title = escape_html(title);
+                let _ = writeln!(
+                    report,
+                    "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n</head>\n<body>\n<h1>{}</h1>",
+                    title, title
+                );
+                report.push_str(&map);
+                for table in tables {
+                    let _ = writeln!(report, "<h2>{}</h2>", table.heading);
+                    if table.rows.is_empty() {
+                        report.push_str("<p>None</p>\n");
+                        continue;
+                    }
+                    report.push_str("<table>\n<tr>");
+                    for column in table.columns {
+                        let _ = write!(report, "<th>{}</th>", column);
+                    }
+                    report.push_str("</tr>\n");
+                    for row in table.rows {
+                        report.push_str("<tr>");
+                        for cell in row {
+                            let _ = write!(report, "<td>{}</td>", escape_html(&cell));
+                        }
+                        report.push_str("</tr>\n");
+                    }
+                    report.push_str("</table>\n");
+                }
+                report.push_str("</body>\n</html>\n");
+            }
+        }
+        report
+    }
+
+    /// Write a report of the run so far to a file, in the format its
+    /// extension calls for
+    pub fn save_run_report(&self, path: &Path, title: &str) -> Result<()> {
+        let report = self.run_report(title, ReportFormat::for_path(path));
+        std::fs::write(path, report)
+            .with_context(|| format!("Failed to write report {}", path.display()))
+    }
+}
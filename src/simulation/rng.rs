@@ -0,0 +1,60 @@
+//! The world's seeded random numbers
+//!
+//! A seeded world draws from a [`StdRng`], whose state can't be serialized.
+//! [`SimRng`] wraps it and counts the 32-bit words drawn from it instead:
+//! every draw takes whole words from the generator's stream in order, so a
+//! generator made from the same seed that skips that many words is in the
+//! same place. A snapshot keeps the seed and the count, and taking one
+//! leaves the world's RNG as it was.
+
+use rand::rngs::StdRng;
+use rand::{RngCore, SeedableRng};
+
+/// A seeded [`StdRng`] that knows how far along its stream it is
+#[derive(Debug, Clone)]
+pub(super) struct SimRng {
+    seed: u64,
+    /// 32-bit words drawn since seeding
+    words: u64,
+    rng: StdRng,
+}
+
+impl SimRng {
+    /// A generator at the start of `seed`'s stream
+    pub fn new(seed: u64) -> Self {
+        Self::resume(seed, 0)
+    }
+
+    /// A generator `words` words along `seed`'s stream
+    pub fn resume(seed: u64, words: u64) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+        for _ in 0..words {
+            rng.next_u32();
+        }
+        Self { seed, words, rng }
+    }
+
+    /// The seed and how many words have been drawn, to [`Self::resume`] from
+    #[cfg_attr(not(feature = "serialization"), allow(dead_code))]
+    pub fn position(&self) -> (u64, u64) {
+        (self.seed, self.words)
+    }
+}
+
+impl RngCore for SimRng {
+    fn next_u32(&mut self) -> u32 {
+        self.words += 1;
+        self.rng.next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.words += 2;
+        self.rng.next_u64()
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        // Part of a word left over at the end is thrown away
+        self.words += dest.len().div_ceil(4) as u64;
+        self.rng.fill_bytes(dest);
+    }
+}
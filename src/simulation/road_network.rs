@@ -2,16 +2,37 @@
 //!
 //! Standalone implementation that doesn't depend on Bevy.
 
-use anyhow::{Context, Result};
+use anyhow::{bail, ensure, Context, Result};
 use ordered_float::OrderedFloat;
-use petgraph::algo::astar;
-use petgraph::graph::{DiGraph, NodeIndex};
-use petgraph::visit::EdgeRef;
+use petgraph::algo::{astar, has_path_connecting};
+use petgraph::graph::{DiGraph, Edge, Node, NodeIndex};
+use petgraph::visit::{EdgeFiltered, EdgeRef};
+use std::cmp::Reverse;
 use std::collections::BTreeMap;
-use std::collections::HashMap;
-use std::ops::Bound;
-
-use super::types::{CarId, IntersectionId, Position, RoadId, SimRoad};
+use std::collections::BinaryHeap;
+use std::collections::{HashMap, HashSet};
+use std::ops::{Add, Bound};
+#[cfg(feature = "serialization")]
+use serde::{Deserialize, Serialize};
+
+use super::diagnostics::map_bytes;
+use super::elevation::slope_speed_factor;
+use super::types::{
+    CarId, IntersectionId, Position, RoadId, RoadKind, RoadRestriction, SimMap, SimRoad,
+    VehicleType,
+};
+
+/// Approximate bytes held by a road network, by what they are for
+pub(super) struct NetworkMemory {
+    pub roads: usize,
+    pub intersections: usize,
+    /// The index of vehicles on each road
+    pub cache_entries: usize,
+    pub caches: usize,
+    /// Traffic volumes
+    pub stats_entries: usize,
+    pub stats: usize,
+}
 
 /// Weight multiplier applied per car on a road for traffic-aware pathfinding.
 /// Higher values make congested roads less attractive.
@@ -24,6 +45,7 @@ const MAX_TRAFFIC_MULTIPLIER: f32 = 3.0;
 
 /// Edge data for the road network graph
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
 pub struct RoadEdge {
     pub road_id: RoadId,
     pub weight: u32, // Road length scaled for integer weights
@@ -32,7 +54,8 @@ pub struct RoadEdge {
 impl RoadEdge {
     pub fn from_road(road: &SimRoad) -> Self {
         // Convert road length to integer weight (scaled by 100 to preserve precision)
-        let weight = (road.length * 100.0) as u32;
+        // Climbs take longer, so they weigh more
+        let weight = (road.length / slope_speed_factor(road.grade) * 100.0) as u32;
         Self {
             road_id: road.id,
             weight: weight.max(1), // Ensure minimum weight of 1
@@ -40,37 +63,105 @@ impl RoadEdge {
     }
 }
 
+/// Shortest routes from one intersection to every intersection reachable
+/// from it, from [`SimRoadNetwork::shortest_paths_from`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShortestPathTree {
+    pub origin: IntersectionId,
+    /// Road distance to each reachable intersection, including the origin
+    pub distances: SimMap<IntersectionId, f32>,
+    /// The intersection before each reachable one on its shortest route
+    pub predecessors: SimMap<IntersectionId, IntersectionId>,
+}
+
+impl ShortestPathTree {
+    /// Road distance to an intersection, if it can be reached
+    pub fn distance_to(&self, intersection: IntersectionId) -> Option<f32> {
+        self.distances.get(&intersection).copied()
+    }
+
+    /// Shortest route to an intersection, leaving out the origin as
+    /// [`SimRoadNetwork::find_path`] does
+    pub fn path_to(&self, intersection: IntersectionId) -> Option<Vec<IntersectionId>> {
+        self.distances.get(&intersection)?;
+        let mut path = Vec::new();
+        let mut at = intersection;
+        while at != self.origin {
+            path.push(at);
+            at = *self.predecessors.get(&at)?;
+        }
+        path.reverse();
+        Some(path)
+    }
+}
+
+/// The two directions of a two-way road
+///
+/// Each direction is its own [`SimRoad`] so cars and pathfinding treat them
+/// separately, but building, splitting and removing act on both together.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+pub struct TwoWayRoad {
+    /// The direction built first; the pair's canonical segment
+    pub forward: RoadId,
+    /// The opposite direction
+    pub backward: RoadId,
+}
+
+impl TwoWayRoad {
+    pub fn roads(self) -> [RoadId; 2] {
+        [self.forward, self.backward]
+    }
+
+    /// The direction opposite `road_id`, if it belongs to this pair
+    pub fn other(self, road_id: RoadId) -> Option<RoadId> {
+        if road_id == self.forward {
+            Some(self.backward)
+        } else if road_id == self.backward {
+            Some(self.forward)
+        } else {
+            None
+        }
+    }
+}
+
 /// Standalone road network graph for pathfinding
 /// This doesn't depend on Bevy's ECS system
-#[derive(Default)]
+#[derive(Default, Clone)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
 #[allow(dead_code)]
 pub struct SimRoadNetwork {
     /// The underlying petgraph directed graph (one-way roads)
     graph: DiGraph<IntersectionId, RoadEdge>,
 
     /// Maps intersection IDs to their node indices in the graph
-    intersection_to_node: HashMap<IntersectionId, NodeIndex>,
+    intersection_to_node: SimMap<IntersectionId, NodeIndex>,
 
     /// Maps node indices back to intersection IDs
-    node_to_intersection: HashMap<NodeIndex, IntersectionId>,
-
-    /// Path cache - currently unused since traffic-aware pathfinding doesn't cache
-    /// results because traffic conditions change frequently. The cache is cleared
-    /// when the road network structure changes to maintain consistency.
-    path_cache: HashMap<IntersectionId, HashMap<IntersectionId, Vec<IntersectionId>>>,
+    node_to_intersection: SimMap<NodeIndex, IntersectionId>,
 
     /// Maps road IDs to their base weight (road length * 100) for efficient lookup
     /// during traffic-aware pathfinding
-    road_base_weights: HashMap<RoadId, u32>,
+    road_base_weights: SimMap<RoadId, u32>,
 
     /// Maps road IDs to lists of (distance, car_id) tuples for traffic detection
-    cars_on_roads: HashMap<RoadId, BTreeMap<OrderedFloat<f32>, CarId>>,
+    cars_on_roads: SimMap<RoadId, BTreeMap<OrderedFloat<f32>, CarId>>,
+
+    /// Number of vehicles that have entered each road since the run began
+    traffic_volumes: SimMap<RoadId, u32>,
 
     /// Storage for road data
-    roads: HashMap<RoadId, SimRoad>,
+    roads: SimMap<RoadId, SimRoad>,
+
+    /// Two-way road pairs, keyed by both of their roads
+    two_way_pairs: SimMap<RoadId, TwoWayRoad>,
 
     /// Storage for intersection positions
-    intersection_positions: HashMap<IntersectionId, Position>,
+    intersection_positions: SimMap<IntersectionId, Position>,
+
+    /// Bumped whenever roads or intersections are added, removed, reshaped
+    /// or restricted
+    layout_version: u64,
 }
 
 impl SimRoadNetwork {
@@ -78,6 +169,18 @@ impl SimRoadNetwork {
         Self::default()
     }
 
+    /// Changes each time the roads vehicles can use change, so a route that
+    /// wasn't found may be worth looking for again
+    pub fn layout_version(&self) -> u64 {
+        self.layout_version
+    }
+
+    /// Note a change to the roads, so routes found before it are looked for
+    /// again (see [`super::path_cache`])
+    fn layout_changed(&mut self) {
+        self.layout_version += 1;
+    }
+
     /// Calculate traffic-aware weight for a road.
     ///
     /// The weight combines the base road length with a traffic penalty based on
@@ -86,19 +189,11 @@ impl SimRoadNetwork {
     ///
     /// Formula: base_weight * min(1 + (car_count * TRAFFIC_CONGESTION_FACTOR), MAX_TRAFFIC_MULTIPLIER)
     pub fn calculate_traffic_weight(&self, road_id: RoadId, base_weight: u32) -> u32 {
-        let car_count = self
-            .cars_on_roads
-            .get(&road_id)
-            .map(|cars| cars.len())
-            .unwrap_or(0);
-
-        if car_count == 0 {
+        let traffic_multiplier = self.congestion_multiplier(road_id);
+        if traffic_multiplier == 1.0 {
             return base_weight;
         }
 
-        let traffic_multiplier =
-            (1.0 + car_count as f32 * TRAFFIC_CONGESTION_FACTOR).min(MAX_TRAFFIC_MULTIPLIER);
-
         let traffic_weight = (base_weight as f32 * traffic_multiplier) as u32;
         // Ensure minimum weight of 1 to prevent zero-weight edges, which could cause
         // the pathfinding algorithm to prefer very short congested roads over longer
@@ -106,6 +201,25 @@ impl SimRoadNetwork {
         traffic_weight.max(1)
     }
 
+    /// How many times longer a road takes to cross with the traffic on it now
+    ///
+    /// Formula: min(1 + (car_count * TRAFFIC_CONGESTION_FACTOR), MAX_TRAFFIC_MULTIPLIER)
+    pub fn congestion_multiplier(&self, road_id: RoadId) -> f32 {
+        let car_count = self.get_car_count_on_road(road_id);
+        (1.0 + car_count as f32 * TRAFFIC_CONGESTION_FACTOR).min(MAX_TRAFFIC_MULTIPLIER)
+    }
+
+    /// Number of intersections joined to this one by a road either way
+    pub fn neighbour_count(&self, intersection_id: IntersectionId) -> usize {
+        let Some(&node) = self.intersection_to_node.get(&intersection_id) else {
+            return 0;
+        };
+        let mut neighbours: Vec<NodeIndex> = self.graph.neighbors_undirected(node).collect();
+        neighbours.sort_unstable();
+        neighbours.dedup();
+        neighbours.len()
+    }
+
     /// Get the number of cars currently on a specific road
     pub fn get_car_count_on_road(&self, road_id: RoadId) -> usize {
         self.cars_on_roads
@@ -146,7 +260,65 @@ impl SimRoadNetwork {
             .insert(node_index, intersection_id);
         self.intersection_positions
             .insert(intersection_id, position);
-        self.path_cache.clear();
+        self.layout_changed();
+    }
+
+    /// Move an intersection, reshaping the roads that meet there
+    /// Returns each reshaped road with the factor its length changed by
+    ///
+    /// Road lengths, angles and pathfinding weights follow the new position,
+    /// and cars tracked on a reshaped road keep their share of the way along
+    /// it.
+    pub fn move_intersection(
+        &mut self,
+        intersection_id: IntersectionId,
+        position: Position,
+    ) -> Result<Vec<(RoadId, f32)>> {
+        *self
+            .intersection_positions
+            .get_mut(&intersection_id)
+            .context("Intersection not found")? = position;
+
+        let mut reshaped = Vec::new();
+        for road in self.roads.values_mut().filter(|road| {
+            road.start_intersection == intersection_id || road.end_intersection == intersection_id
+        }) {
+            let (Some(start), Some(end)) = (
+                self.intersection_positions.get(&road.start_intersection),
+                self.intersection_positions.get(&road.end_intersection),
+            ) else {
+                continue;
+            };
+            let length = start.distance(end);
+            let scale = if road.length > 0.0 {
+                length / road.length
+            } else {
+                1.0
+            };
+            road.length = length;
+            road.angle = start.angle_to(end);
+            road.grade = start.grade_to(end);
+
+            let edge = RoadEdge::from_road(road);
+            self.road_base_weights.insert(road.id, edge.weight);
+            if let Some(weight) = self
+                .graph
+                .edge_weights_mut()
+                .find(|weight| weight.road_id == road.id)
+            {
+                *weight = edge;
+            }
+            if let Some(cars) = self.cars_on_roads.get_mut(&road.id) {
+                *cars = cars
+                    .iter()
+                    .map(|(distance, car_id)| (OrderedFloat(distance.into_inner() * scale), *car_id))
+                    .collect();
+            }
+            reshaped.push((road.id, scale));
+        }
+
+        self.layout_changed();
+        Ok(reshaped)
     }
 
     /// Gets the position of an intersection
@@ -177,9 +349,102 @@ impl SimRoadNetwork {
         self.graph.add_edge(start_node, end_node, edge_data);
 
         // Store the road
+        let is_two_way = road.is_two_way;
         self.roads.insert(road_id, road);
 
-        self.path_cache.clear();
+        // A two-way road pairs up with an unpaired two-way road going back
+        if is_two_way {
+            let reverse = self
+                .graph
+                .edges(end_node)
+                .map(|edge| edge.weight().road_id)
+                .find(|reverse_id| {
+                    *reverse_id != road_id
+                        && !self.two_way_pairs.contains_key(reverse_id)
+                        && self.roads.get(reverse_id).is_some_and(|reverse| {
+                            reverse.end_intersection == start_id && reverse.is_two_way
+                        })
+                });
+            if let Some(reverse_id) = reverse {
+                self.insert_pair(TwoWayRoad {
+                    forward: reverse_id,
+                    backward: road_id,
+                });
+            }
+        }
+
+        self.layout_changed();
+    }
+
+    /// Pair two roads running between the same intersections in opposite
+    /// directions into a two-way road
+    pub fn pair_roads(&mut self, forward: RoadId, backward: RoadId) -> Result<TwoWayRoad> {
+        let forward_road = self.roads.get(&forward).context("Road not found")?;
+        let backward_road = self.roads.get(&backward).context("Road not found")?;
+        ensure!(
+            forward_road.start_intersection == backward_road.end_intersection
+                && forward_road.end_intersection == backward_road.start_intersection,
+            "Roads {:?} and {:?} don't run in opposite directions",
+            forward,
+            backward
+        );
+        ensure!(
+            !self.two_way_pairs.contains_key(&forward)
+                && !self.two_way_pairs.contains_key(&backward),
+            "Road is already part of a two-way road"
+        );
+        let pair = TwoWayRoad { forward, backward };
+        self.insert_pair(pair);
+        Ok(pair)
+    }
+
+    fn insert_pair(&mut self, pair: TwoWayRoad) {
+        for road_id in pair.roads() {
+            if let Some(road) = self.roads.get_mut(&road_id) {
+                road.is_two_way = true;
+            }
+            self.two_way_pairs.insert(road_id, pair);
+        }
+    }
+
+    /// Forget the pair a road belongs to; the other direction becomes a
+    /// one-way road
+    fn unpair(&mut self, road_id: RoadId) {
+        let Some(pair) = self.two_way_pairs.remove(&road_id) else {
+            return;
+        };
+        if let Some(other) = pair.other(road_id) {
+            self.two_way_pairs.remove(&other);
+            if let Some(road) = self.roads.get_mut(&other) {
+                road.is_two_way = false;
+            }
+        }
+    }
+
+    /// The two-way road a road is one direction of
+    pub fn two_way_road(&self, road_id: RoadId) -> Option<TwoWayRoad> {
+        self.two_way_pairs.get(&road_id).copied()
+    }
+
+    /// The other direction of a two-way road
+    pub fn opposite_road(&self, road_id: RoadId) -> Option<RoadId> {
+        self.two_way_road(road_id)
+            .and_then(|pair| pair.other(road_id))
+    }
+
+    /// The road standing for a road's whole segment: the forward direction of
+    /// a two-way road, or the road itself if it's one-way
+    pub fn canonical_road(&self, road_id: RoadId) -> RoadId {
+        self.two_way_road(road_id)
+            .map_or(road_id, |pair| pair.forward)
+    }
+
+    /// All two-way roads, once each
+    pub fn two_way_roads(&self) -> impl Iterator<Item = TwoWayRoad> + '_ {
+        self.two_way_pairs
+            .iter()
+            .filter(|(road_id, pair)| **road_id == pair.forward)
+            .map(|(_, pair)| *pair)
     }
 
     /// Gets a road by ID
@@ -187,6 +452,92 @@ impl SimRoadNetwork {
         self.roads.get(&road_id)
     }
 
+    /// Sets which vehicles may use a road
+    pub fn set_road_restriction(
+        &mut self,
+        road_id: RoadId,
+        restriction: RoadRestriction,
+    ) -> Result<()> {
+        self.roads
+            .get_mut(&road_id)
+            .context("Road not found")?
+            .restriction = restriction;
+        self.layout_changed();
+        Ok(())
+    }
+
+    /// Closes a road to every vehicle, or reopens it
+    pub fn set_road_closed(&mut self, road_id: RoadId, closed: bool) -> Result<()> {
+        self.roads.get_mut(&road_id).context("Road not found")?.closed = closed;
+        self.layout_changed();
+        Ok(())
+    }
+
+    /// Puts a traffic sensor on a road or takes it off
+    pub fn set_road_sensor(&mut self, road_id: RoadId, installed: bool) -> Result<()> {
+        self.roads
+            .get_mut(&road_id)
+            .context("Road not found")?
+            .sensor = installed;
+        Ok(())
+    }
+
+    /// Makes a road a bridge or ferry crossing, or an ordinary road
+    pub fn set_road_kind(&mut self, road_id: RoadId, kind: RoadKind) -> Result<()> {
+        self.roads.get_mut(&road_id).context("Road not found")?.kind = kind;
+        Ok(())
+    }
+
+    /// Turns an empty one-way road round in place, keeping its ID and
+    /// settings
+    pub fn flip_road(&mut self, road_id: RoadId) -> Result<()> {
+        ensure!(
+            !self.two_way_pairs.contains_key(&road_id),
+            "Road {:?} is two-way",
+            road_id
+        );
+        ensure!(
+            self.get_car_count_on_road(road_id) == 0,
+            "Road {:?} has vehicles on it",
+            road_id
+        );
+        let road = self.roads.get(&road_id).context("Road not found")?;
+        let (start, end) = (road.start_intersection, road.end_intersection);
+        ensure!(
+            self.find_road_between(end, start).is_err(),
+            "A road already runs the other way"
+        );
+        let (Some(&start_node), Some(&end_node), Some(&start_pos), Some(&end_pos)) = (
+            self.intersection_to_node.get(&start),
+            self.intersection_to_node.get(&end),
+            self.intersection_positions.get(&start),
+            self.intersection_positions.get(&end),
+        ) else {
+            bail!("Road {:?} has a missing intersection", road_id);
+        };
+
+        let edge_id = self
+            .graph
+            .edges(start_node)
+            .find(|edge| edge.weight().road_id == road_id)
+            .map(|edge| edge.id())
+            .context("Road not in the graph")?;
+        self.graph.remove_edge(edge_id);
+
+        let road = self.roads.get_mut(&road_id).context("Road not found")?;
+        road.start_intersection = end;
+        road.end_intersection = start;
+        road.angle = end_pos.angle_to(&start_pos);
+        road.grade = end_pos.grade_to(&start_pos);
+        let edge = RoadEdge::from_road(road);
+        self.road_base_weights.insert(road_id, edge.weight);
+        self.graph.add_edge(end_node, start_node, edge);
+        self.cars_on_roads.remove(&road_id);
+
+        self.layout_changed();
+        Ok(())
+    }
+
     /// Finds the road connecting two intersections
     pub fn find_road_between(
         &self,
@@ -227,6 +578,75 @@ impl SimRoadNetwork {
         &mut self,
         start: IntersectionId,
         end: IntersectionId,
+    ) -> Option<Vec<IntersectionId>> {
+        self.find_path_where(start, end, |_| true)
+    }
+
+    /// Finds a traffic-aware path for a vehicle, using only the roads its
+    /// type is allowed on
+    pub fn find_path_for(
+        &self,
+        start: IntersectionId,
+        end: IntersectionId,
+        vehicle_type: VehicleType,
+    ) -> Option<Vec<IntersectionId>> {
+        self.find_path_where(start, end, |road| road.allows(vehicle_type))
+    }
+
+    /// Finds a traffic-aware path for a route's bus, using bus lanes and
+    /// every other road but those for trucks only
+    pub fn find_bus_path(
+        &self,
+        start: IntersectionId,
+        end: IntersectionId,
+    ) -> Option<Vec<IntersectionId>> {
+        self.find_path_where(start, end, SimRoad::allows_buses)
+    }
+
+    /// Checks that a route from `start` still follows roads the vehicle may use
+    pub fn route_is_usable(
+        &self,
+        start: IntersectionId,
+        path: &[IntersectionId],
+        vehicle_type: VehicleType,
+    ) -> bool {
+        self.route_follows(start, path, |road| road.allows(vehicle_type))
+    }
+
+    /// Checks that a route from `start` follows roads the vehicle may use
+    /// once the closed ones on it reopen
+    pub fn route_is_usable_when_open(
+        &self,
+        start: IntersectionId,
+        path: &[IntersectionId],
+        vehicle_type: VehicleType,
+    ) -> bool {
+        self.route_follows(start, path, |road| road.restriction.allows(vehicle_type))
+    }
+
+    fn route_follows(
+        &self,
+        start: IntersectionId,
+        path: &[IntersectionId],
+        usable: impl Fn(&SimRoad) -> bool,
+    ) -> bool {
+        let mut from = start;
+        path.iter().all(|&to| {
+            let follows = self
+                .find_road_between(from, to)
+                .ok()
+                .and_then(|road_id| self.roads.get(&road_id))
+                .is_some_and(&usable);
+            from = to;
+            follows
+        })
+    }
+
+    fn find_path_where(
+        &self,
+        start: IntersectionId,
+        end: IntersectionId,
+        usable: impl Fn(&SimRoad) -> bool,
     ) -> Option<Vec<IntersectionId>> {
         if start == end {
             return Some(vec![]);
@@ -235,19 +655,15 @@ impl SimRoadNetwork {
         let start_node = self.intersection_to_node.get(&start)?;
         let end_node = self.intersection_to_node.get(&end)?;
 
-        // Pre-compute traffic weights for all roads using the cached base weights
-        // This is O(n) where n is the number of roads, avoiding the previous O(n²) lookup
-        let traffic_weights: HashMap<RoadId, u32> = self
-            .road_base_weights
-            .iter()
-            .map(|(&road_id, &base_weight)| {
-                let traffic_weight = self.calculate_traffic_weight(road_id, base_weight);
-                (road_id, traffic_weight)
-            })
-            .collect();
+        let traffic_weights = self.traffic_weights();
 
+        let graph = EdgeFiltered::from_fn(&self.graph, |edge| {
+            self.roads
+                .get(&edge.weight().road_id)
+                .is_some_and(&usable)
+        });
         let result = astar(
-            &self.graph,
+            &graph,
             *start_node,
             |node| node == *end_node,
             |edge| {
@@ -272,6 +688,211 @@ impl SimRoadNetwork {
         Some(path)
     }
 
+    /// Finds traffic-aware paths for a vehicle from one intersection to each
+    /// of `ends`, sharing a single search between them
+    ///
+    /// Returns a path (or `None`) per end, in order. Cheaper than calling
+    /// [`Self::find_path_for`] for each end when several vehicles leave the
+    /// same place.
+    pub fn find_paths_from(
+        &self,
+        start: IntersectionId,
+        ends: &[IntersectionId],
+        vehicle_type: VehicleType,
+    ) -> Vec<Option<Vec<IntersectionId>>> {
+        let Some(&start_node) = self.intersection_to_node.get(&start) else {
+            return vec![None; ends.len()];
+        };
+        let traffic_weights = self.traffic_weights();
+        let targets = ends
+            .iter()
+            .filter_map(|end| self.intersection_to_node.get(end).copied())
+            .collect();
+        let (costs, previous) = self.shortest_tree(
+            &[start_node],
+            |edge| {
+                let road_id = edge.road_id;
+                self.roads
+                    .get(&road_id)
+                    .is_some_and(|road| road.allows(vehicle_type))
+                    .then(|| *traffic_weights.get(&road_id).unwrap_or(&edge.weight))
+            },
+            Some(targets),
+        );
+
+        ends.iter()
+            .map(|end| {
+                let end_node = *self.intersection_to_node.get(end)?;
+                costs.get(&end_node)?;
+                // Walk back to the start, which the path leaves out
+                let mut path = Vec::new();
+                let mut node = end_node;
+                while node != start_node {
+                    path.push(*self.node_to_intersection.get(&node)?);
+                    node = *previous.get(&node)?;
+                }
+                path.reverse();
+                Some(path)
+            })
+            .collect()
+    }
+
+    /// Shortest routes by road length from one intersection to every
+    /// intersection that can be reached from it, ignoring traffic
+    ///
+    /// One search answers how far, and which way, every destination is, so
+    /// prefer it to a [`Self::find_path`] per destination.
+    pub fn shortest_paths_from(&self, origin: IntersectionId) -> ShortestPathTree {
+        self.cheapest_paths_from(origin, |road| Some(road.length))
+    }
+
+    /// Cheapest routes from one intersection to every intersection that can
+    /// be reached from it, with `cost` giving the cost of each road that may
+    /// be driven (`None` for one that may not)
+    ///
+    /// The tree's distances are then the costs of the cheapest routes.
+    pub fn cheapest_paths_from(
+        &self,
+        origin: IntersectionId,
+        cost: impl Fn(&SimRoad) -> Option<f32>,
+    ) -> ShortestPathTree {
+        let mut tree = ShortestPathTree {
+            origin,
+            distances: SimMap::default(),
+            predecessors: SimMap::default(),
+        };
+        let Some(&origin_node) = self.intersection_to_node.get(&origin) else {
+            return tree;
+        };
+        let (distances, previous) = self.shortest_tree(
+            &[origin_node],
+            |edge| {
+                self.roads
+                    .get(&edge.road_id)
+                    .and_then(&cost)
+                    .map(OrderedFloat)
+            },
+            None,
+        );
+        let intersection = |node: NodeIndex| self.node_to_intersection.get(&node).copied();
+        tree.distances = distances
+            .into_iter()
+            .filter_map(|(node, distance)| Some((intersection(node)?, distance.into_inner())))
+            .collect();
+        tree.predecessors = previous
+            .into_iter()
+            .filter_map(|(node, before)| Some((intersection(node)?, intersection(before)?)))
+            .collect();
+        tree
+    }
+
+    /// Dijkstra from the nearest of `sources` across the roads `weight`
+    /// gives a cost for, returning the cost of reaching each node and the
+    /// node before it on the cheapest route
+    ///
+    /// With `targets`, stops once all of them are settled.
+    fn shortest_tree<W>(
+        &self,
+        sources: &[NodeIndex],
+        weight: impl Fn(&RoadEdge) -> Option<W>,
+        mut targets: Option<HashSet<NodeIndex>>,
+    ) -> (SimMap<NodeIndex, W>, SimMap<NodeIndex, NodeIndex>)
+    where
+        W: Copy + Ord + Default + Add<Output = W>,
+    {
+        let mut costs: SimMap<NodeIndex, W> = SimMap::default();
+        let mut previous: SimMap<NodeIndex, NodeIndex> = SimMap::default();
+        let mut queue = BinaryHeap::new();
+        for &source in sources {
+            costs.insert(source, W::default());
+            queue.push(Reverse((W::default(), source)));
+        }
+
+        while let Some(Reverse((cost, node))) = queue.pop() {
+            if costs.get(&node).is_some_and(|&best| cost > best) {
+                continue;
+            }
+            if let Some(targets) = &mut targets {
+                targets.remove(&node);
+                if targets.is_empty() {
+                    break;
+                }
+            }
+            for edge in self.graph.edges(node) {
+                let Some(edge_cost) = weight(edge.weight()) else {
+                    continue;
+                };
+                let next = edge.target();
+                let next_cost = cost + edge_cost;
+                if costs.get(&next).is_none_or(|&best| next_cost < best) {
+                    costs.insert(next, next_cost);
+                    previous.insert(next, node);
+                    queue.push(Reverse((next_cost, next)));
+                }
+            }
+        }
+        (costs, previous)
+    }
+
+    /// Current traffic-aware weight of every road
+    ///
+    /// This is O(n) where n is the number of roads, using the cached base
+    /// weights.
+    fn traffic_weights(&self) -> HashMap<RoadId, u32> {
+        self.road_base_weights
+            .iter()
+            .map(|(&road_id, &base_weight)| {
+                let traffic_weight = self.calculate_traffic_weight(road_id, base_weight);
+                (road_id, traffic_weight)
+            })
+            .collect()
+    }
+
+    /// Checks whether any route leads from one intersection to another
+    ///
+    /// Unlike [`Self::find_path`] this ignores traffic and only needs shared access.
+    pub fn is_reachable(&self, from: IntersectionId, to: IntersectionId) -> bool {
+        match (
+            self.intersection_to_node.get(&from),
+            self.intersection_to_node.get(&to),
+        ) {
+            (Some(from_node), Some(to_node)) => {
+                has_path_connecting(&self.graph, *from_node, *to_node, None)
+            }
+            _ => false,
+        }
+    }
+
+    /// Shortest travel distance from the nearest of `sources` to every
+    /// intersection that can be reached from them
+    ///
+    /// Distances follow road lengths and ignore traffic. Useful for judging
+    /// how accessible a site is, e.g. how far a worker would commute.
+    pub fn travel_distances(&self, sources: &[IntersectionId]) -> SimMap<IntersectionId, f32> {
+        let sources: Vec<NodeIndex> = sources
+            .iter()
+            .filter_map(|source| self.intersection_to_node.get(source).copied())
+            .collect();
+        let (distances, _) = self.shortest_tree(
+            &sources,
+            |edge| {
+                self.roads
+                    .get(&edge.road_id)
+                    .map(|road| OrderedFloat(road.length))
+            },
+            None,
+        );
+
+        distances
+            .into_iter()
+            .filter_map(|(node, distance)| {
+                self.node_to_intersection
+                    .get(&node)
+                    .map(|intersection_id| (*intersection_id, distance.into_inner()))
+            })
+            .collect()
+    }
+
     /// Gets all intersection IDs in the network
     pub fn get_all_intersections(&self) -> Vec<IntersectionId> {
         self.intersection_to_node.keys().copied().collect()
@@ -323,6 +944,10 @@ impl SimRoadNetwork {
             // Insert at new position
             let car_map = self.cars_on_roads.entry(road_id).or_default();
             car_map.insert(distance, car_id);
+
+            if prev_road_id != Some(road_id) {
+                *self.traffic_volumes.entry(road_id).or_default() += 1;
+            }
         }
 
         Ok(())
@@ -345,6 +970,103 @@ impl SimRoadNetwork {
             .map(|(distance, car)| (distance, *car)))
     }
 
+    /// Distance along a road of the car nearest its start, if any car is on
+    /// it
+    pub fn first_car_distance(&self, road_id: RoadId) -> Option<f32> {
+        self.cars_on_roads
+            .get(&road_id)
+            .and_then(|car_map| car_map.keys().next())
+            .map(|distance| distance.into_inner())
+    }
+
+    /// Approximate memory held by the graph, lookups, caches and traffic
+    /// counts
+    pub(super) fn memory_usage(&self) -> NetworkMemory {
+        let (node_capacity, edge_capacity) = self.graph.capacity();
+        let cars_on_roads_entries: usize = self.cars_on_roads.values().map(BTreeMap::len).sum();
+        let cars_on_roads_bytes: usize = self.cars_on_roads.values().map(map_bytes).sum();
+        NetworkMemory {
+            roads: edge_capacity * std::mem::size_of::<Edge<RoadEdge>>()
+                + map_bytes(&self.road_base_weights)
+                + map_bytes(&self.roads)
+                + map_bytes(&self.two_way_pairs),
+            intersections: node_capacity * std::mem::size_of::<Node<IntersectionId>>()
+                + map_bytes(&self.intersection_to_node)
+                + map_bytes(&self.node_to_intersection)
+                + map_bytes(&self.intersection_positions),
+            cache_entries: cars_on_roads_entries,
+            caches: map_bytes(&self.cars_on_roads) + cars_on_roads_bytes,
+            stats_entries: self.traffic_volumes.len(),
+            stats: map_bytes(&self.traffic_volumes),
+        }
+    }
+
+    /// Ways the graph, its lookups and the vehicles tracked on roads
+    /// disagree with each other
+    pub(super) fn consistency_errors(&self) -> Vec<String> {
+        let mut errors = Vec::new();
+        if self.graph.node_count() != self.intersection_to_node.len()
+            || self.node_to_intersection.len() != self.intersection_to_node.len()
+        {
+            errors.push(format!(
+                "graph has {} nodes for {} intersections ({} reverse lookups)",
+                self.graph.node_count(),
+                self.intersection_to_node.len(),
+                self.node_to_intersection.len()
+            ));
+        }
+        for (&intersection_id, &node) in &self.intersection_to_node {
+            if self.graph.node_weight(node) != Some(&intersection_id)
+                || self.node_to_intersection.get(&node) != Some(&intersection_id)
+            {
+                errors.push(format!(
+                    "intersection {} maps to node {} which isn't it",
+                    intersection_id.0 .0,
+                    node.index()
+                ));
+            }
+            if !self.intersection_positions.contains_key(&intersection_id) {
+                errors.push(format!(
+                    "intersection {} has no position",
+                    intersection_id.0 .0
+                ));
+            }
+        }
+        if self.graph.edge_count() != self.roads.len() {
+            errors.push(format!(
+                "graph has {} edges for {} roads",
+                self.graph.edge_count(),
+                self.roads.len()
+            ));
+        }
+        for (road_id, road) in &self.roads {
+            for end in [road.start_intersection, road.end_intersection] {
+                if !self.intersection_to_node.contains_key(&end) {
+                    errors.push(format!(
+                        "road {} ends at missing intersection {}",
+                        road_id.0 .0, end.0 .0
+                    ));
+                }
+            }
+        }
+        for road_id in self.cars_on_roads.keys() {
+            if !self.roads.contains_key(road_id) {
+                errors.push(format!("vehicles tracked on missing road {}", road_id.0 .0));
+            }
+        }
+        errors
+    }
+
+    /// Every vehicle tracked on a road, with the road and its distance along
+    /// it
+    pub fn tracked_cars(&self) -> impl Iterator<Item = (RoadId, f32, CarId)> + '_ {
+        self.cars_on_roads.iter().flat_map(|(&road_id, car_map)| {
+            car_map
+                .iter()
+                .map(move |(distance, &car_id)| (road_id, distance.into_inner(), car_id))
+        })
+    }
+
     /// Get number of roads
     pub fn road_count(&self) -> usize {
         self.roads.len()
@@ -362,22 +1084,39 @@ impl SimRoadNetwork {
     }
 
     /// Get all roads
-    pub fn roads(&self) -> &HashMap<RoadId, SimRoad> {
+    pub fn roads(&self) -> &SimMap<RoadId, SimRoad> {
         &self.roads
     }
 
     /// Get all intersection positions
-    pub fn intersection_positions(&self) -> &HashMap<IntersectionId, Position> {
+    pub fn intersection_positions(&self) -> &SimMap<IntersectionId, Position> {
         &self.intersection_positions
     }
 
+    /// Remove both directions of a two-way road, or just the road if it's
+    /// one-way
+    /// Returns the cars that were on the removed roads
+    pub fn remove_two_way_road(&mut self, road_id: RoadId) -> Result<Vec<CarId>> {
+        let opposite = self.opposite_road(road_id);
+        let mut cars = self.remove_road(road_id)?;
+        if let Some(opposite) = opposite {
+            cars.extend(self.remove_road(opposite)?);
+        }
+        Ok(cars)
+    }
+
     /// Remove a road from the network
     /// Returns the cars that were on the road
+    ///
+    /// Removing one direction of a two-way road leaves the other as a one-way
+    /// road.
     pub fn remove_road(&mut self, road_id: RoadId) -> Result<Vec<CarId>> {
+        self.unpair(road_id);
         let road = self.roads.remove(&road_id).context("Road not found")?;
 
         // Remove base weight cache entry
         self.road_base_weights.remove(&road_id);
+        self.traffic_volumes.remove(&road_id);
 
         let start_node = self
             .intersection_to_node
@@ -407,7 +1146,7 @@ impl SimRoadNetwork {
             .map(|car_map| car_map.values().copied().collect())
             .unwrap_or_default();
 
-        self.path_cache.clear();
+        self.layout_changed();
 
         Ok(cars)
     }
@@ -440,17 +1179,27 @@ impl SimRoadNetwork {
         // Remove roads and collect affected cars
         let mut affected_cars = Vec::new();
         for road_id in &roads_to_remove {
+            self.unpair(*road_id);
             self.roads.remove(road_id);
             self.road_base_weights.remove(road_id);
+            self.traffic_volumes.remove(road_id);
             if let Some(car_map) = self.cars_on_roads.remove(road_id) {
                 affected_cars.extend(car_map.values().copied());
             }
         }
 
-        // Remove the node from the graph (this also removes all edges)
+        // Remove the node from the graph (this also removes all edges). The
+        // graph moves its last node into the freed index, so follow it there
+        let last_node = NodeIndex::new(self.graph.node_count() - 1);
         self.graph.remove_node(node_index);
+        if last_node != node_index {
+            if let Some(moved) = self.node_to_intersection.remove(&last_node) {
+                self.node_to_intersection.insert(node_index, moved);
+                self.intersection_to_node.insert(moved, node_index);
+            }
+        }
 
-        self.path_cache.clear();
+        self.layout_changed();
 
         Ok((roads_to_remove, affected_cars))
     }
@@ -475,6 +1224,20 @@ impl SimRoadNetwork {
             .unwrap_or_default()
     }
 
+    /// Number of vehicles that have entered a road since the run began
+    ///
+    /// Unlike [`get_cars_on_road`](Self::get_cars_on_road), this keeps
+    /// counting after the vehicles have left, so it shows which roads carry
+    /// the load over time.
+    pub fn traffic_volume(&self, road_id: RoadId) -> u32 {
+        self.traffic_volumes.get(&road_id).copied().unwrap_or(0)
+    }
+
+    /// Highest traffic volume of any road
+    pub fn max_traffic_volume(&self) -> u32 {
+        self.traffic_volumes.values().copied().max().unwrap_or(0)
+    }
+
     /// Remove a car from road tracking
     pub fn remove_car_from_tracking(&mut self, car_id: CarId) {
         for car_map in self.cars_on_roads.values_mut() {
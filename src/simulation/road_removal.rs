@@ -0,0 +1,257 @@
+//! Getting vehicles off a road that is removed under them
+//!
+//! Removing a road doesn't make the vehicles on it vanish. A vehicle that
+//! hadn't got halfway along backs up to the intersection it came from, at
+//! [`REVERSING_SPEED_FACTOR`] of its speed, and one past halfway drives on
+//! to the end. Off the road network it isn't in traffic: it neither queues
+//! behind others nor holds anybody up, and gives up any intersection it
+//! held. Once at the intersection it finds a new route to where it was going
+//! and waits there for room on the first road like a vehicle setting off.
+//! A vehicle still waiting to set off on a removed road finds a new route
+//! from where it waits, keeping its place in the queue, and one only routed
+//! over the road further on finds another way from the intersection it is
+//! making for.
+//!
+//! Only a vehicle with no route at all from there is despawned, as is one
+//! on a ferry crossing, or one both of whose ends were removed with the road.
+
+#[cfg(feature = "serialization")]
+use serde::{Deserialize, Serialize};
+
+use super::car::{CarUpdateResult, SimCar};
+use super::fleet::wear_speed_factor;
+use super::types::{CarId, IntersectionId, Position, RoadId, RoadKind, SimMap};
+use super::world::SimWorld;
+
+/// Share of its speed a vehicle backs up to the start of a removed road at
+pub const REVERSING_SPEED_FACTOR: f32 = 0.3;
+
+/// Where a vehicle is getting off a road that was removed under it
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+pub struct RoadExit {
+    /// Intersection it is making for
+    pub to: IntersectionId,
+    /// Where that intersection is
+    pub target: Position,
+    /// Whether it is backing up to the start of the road rather than
+    /// driving on to the end
+    pub reversing: bool,
+}
+
+impl SimCar {
+    /// Move toward `exit`, reporting once there
+    pub(super) fn drive_to_exit(&mut self, exit: RoadExit, delta_secs: f32) -> CarUpdateResult {
+        let mut speed = self.speed * wear_speed_factor(self.wear);
+        if exit.reversing {
+            speed *= REVERSING_SPEED_FACTOR;
+        }
+        // A broken-down vehicle stays put, its engine off, until cleared
+        let engine_running = self.stalled_secs <= 0.0;
+        if !engine_running {
+            self.stalled_secs = (self.stalled_secs - delta_secs).max(0.0);
+            speed = 0.0;
+        }
+        let remaining = self.position.distance(&exit.target);
+        let distance = (speed * delta_secs).min(remaining);
+        if remaining > 0.0 {
+            self.position = self.position.lerp(&exit.target, distance / remaining);
+        }
+        self.odometer += distance;
+        let previous_speed = self.current_speed;
+        if delta_secs > 0.0 {
+            self.current_speed = distance / delta_secs;
+        }
+        if distance > 0.0 {
+            self.stopped_secs = 0.0;
+        } else {
+            self.stopped_secs += delta_secs;
+        }
+        self.trip_secs += delta_secs;
+        if engine_running {
+            self.burn_fuel(distance, delta_secs, previous_speed);
+        }
+        if distance >= remaining {
+            CarUpdateResult::LeftRemovedRoad(exit.to)
+        } else {
+            CarUpdateResult::Continue
+        }
+    }
+}
+
+impl SimWorld {
+    /// Where each vehicle driving on `roads` would get off them, skipping
+    /// ferry crossings and `removed`, an intersection going with the roads
+    ///
+    /// Found before the roads are removed, while their ends are known.
+    pub(super) fn road_exits(
+        &self,
+        roads: &[RoadId],
+        removed: Option<IntersectionId>,
+    ) -> SimMap<CarId, RoadExit> {
+        let network = &self.road_network;
+        let mut exits = SimMap::new();
+        for (car_id, car) in &self.cars {
+            if car.awaiting_route.is_some() || car.queued_at_start || car.road_exit.is_some() {
+                continue;
+            }
+            if !roads.contains(&car.current_road) {
+                continue;
+            }
+            let Some(road) = network.get_road(car.current_road) else {
+                continue;
+            };
+            if road.kind == RoadKind::Ferry {
+                continue;
+            }
+            let short_of_halfway = car.distance_along_road.into_inner() < road.length / 2.0;
+            let ends = [
+                (road.start_intersection, true),
+                (road.end_intersection, false),
+            ];
+            let preferred = if short_of_halfway { ends } else { [ends[1], ends[0]] };
+            let exit = preferred
+                .into_iter()
+                .filter(|(to, _)| Some(*to) != removed)
+                .find_map(|(to, reversing)| {
+                    let target = *network.get_intersection_position(to)?;
+                    Some(RoadExit {
+                        to,
+                        target,
+                        reversing,
+                    })
+                });
+            if let Some(exit) = exit {
+                exits.insert(*car_id, exit);
+            }
+        }
+        exits
+    }
+
+    /// Send the vehicles that were on removed roads off them by the exits
+    /// found beforehand, despawning those without one, and reroute those
+    /// waiting to set off on them
+    pub(super) fn leave_removed_roads(
+        &mut self,
+        cars_on_roads: Vec<CarId>,
+        mut exits: SimMap<CarId, RoadExit>,
+    ) {
+        for car_id in cars_on_roads {
+            let Some(exit) = exits.remove(&car_id) else {
+                self.despawn_car(car_id);
+                continue;
+            };
+            let Some(car) = self.cars.get_mut(&car_id) else {
+                continue;
+            };
+            car.road_exit = Some(exit);
+            let held = car.turn.take().map(|turn| turn.intersection);
+            let next = car.path.first().copied();
+            for intersection in held.into_iter().chain(next) {
+                if let Some(intersection) = self.intersections.get_mut(&intersection) {
+                    intersection.release(car_id);
+                }
+            }
+            self.mark_vehicle_changed(car_id);
+        }
+        self.reroute_queued_vehicles();
+    }
+
+    /// Give each vehicle waiting to set off on a road that no longer exists
+    /// a new route from its start, ending the trips of those with none
+    fn reroute_queued_vehicles(&mut self) {
+        let stranded: Vec<CarId> = self
+            .cars
+            .iter()
+            .filter(|(_, car)| {
+                car.queued_at_start
+                    && car.awaiting_route.is_none()
+                    && self.road_network.get_road(car.current_road).is_none()
+            })
+            .map(|(car_id, _)| *car_id)
+            .collect();
+        for car_id in stranded {
+            let start = self.cars[&car_id].start_intersection;
+            if !self.route_from(car_id, start) {
+                self.end_trip_unexpectedly(car_id);
+            }
+        }
+    }
+
+    /// Route the vehicles that got off a removed road this tick on from
+    /// where they got off, passing the other update results through
+    ///
+    /// A vehicle that got off at its destination has arrived, and one with
+    /// no route from there is stranded.
+    pub(super) fn reroute_off_removed_roads(
+        &mut self,
+        car_results: Vec<(CarId, CarUpdateResult)>,
+    ) -> Vec<(CarId, CarUpdateResult)> {
+        car_results
+            .into_iter()
+            .filter_map(|(car_id, result)| match result {
+                CarUpdateResult::LeftRemovedRoad(at) => {
+                    self.rejoin_roads(car_id, at).map(|result| (car_id, result))
+                }
+                result => Some((car_id, result)),
+            })
+            .collect()
+    }
+
+    /// Queue a vehicle that got off a removed road at `at` to set off on a
+    /// new route; `None` once queued, otherwise what became of its trip
+    fn rejoin_roads(&mut self, car_id: CarId, at: IntersectionId) -> Option<CarUpdateResult> {
+        let car = self.cars.get_mut(&car_id)?;
+        car.road_exit = None;
+        let Some(destination) = car.path.last().copied() else {
+            return Some(CarUpdateResult::Despawn);
+        };
+        if destination == at {
+            return Some(CarUpdateResult::ArrivedAtDestination(destination));
+        }
+        if !self.route_from(car_id, at) {
+            return Some(CarUpdateResult::Despawn);
+        }
+        self.spawn_queues
+            .entry(at)
+            .or_default()
+            .push_back((car_id, self.time));
+        None
+    }
+
+    /// Set a vehicle to set off from `at` on a new route to its
+    /// destination, if there is one
+    fn route_from(&mut self, car_id: CarId, at: IntersectionId) -> bool {
+        let Some(car) = self.cars.get(&car_id) else {
+            return false;
+        };
+        let vehicle_type = car.vehicle_type;
+        let Some(destination) = car.path.last().copied() else {
+            return false;
+        };
+        let first_road = self
+            .find_route(at, destination, vehicle_type)
+            .and_then(|path| {
+                let road_id = self
+                    .road_network
+                    .find_road_between(at, *path.first()?)
+                    .ok()?;
+                Some((path, road_id))
+            });
+        let Some((path, road_id)) = first_road else {
+            return false;
+        };
+        let angle = self.road_network.get_road(road_id).map(|road| road.angle);
+        let Some(car) = self.cars.get_mut(&car_id) else {
+            return false;
+        };
+        car.start_intersection = at;
+        car.current_road = road_id;
+        car.distance_along_road = 0.0.into();
+        car.angle = angle.unwrap_or(car.angle);
+        car.path = path;
+        car.queued_at_start = true;
+        self.mark_vehicle_changed(car_id);
+        true
+    }
+}
@@ -0,0 +1,79 @@
+//! Per-road traffic statistics for offline analysis
+//!
+//! Every road reports how many vehicles are on it right now and how many
+//! have driven onto it since the run began. The totals show which roads
+//! carry the load over a whole run, which the instantaneous density can't.
+
+use std::fmt::Write as _;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+
+use super::types::{IntersectionId, RoadId};
+use super::world::SimWorld;
+
+/// Header row of the stats CSV
+pub const ROAD_STATS_CSV_HEADER: &str =
+    "road_id,start_intersection,end_intersection,length,two_way,vehicles_now,traffic_volume";
+
+/// Traffic statistics for one road
+#[derive(Debug, Clone, PartialEq)]
+pub struct RoadStats {
+    pub road_id: RoadId,
+    pub start_intersection: IntersectionId,
+    pub end_intersection: IntersectionId,
+    pub length: f32,
+    pub two_way: bool,
+    /// Vehicles on the road right now
+    pub vehicles_now: usize,
+    /// Vehicles that have entered the road since the run began
+    pub traffic_volume: u32,
+}
+
+impl SimWorld {
+    /// Statistics for every road, in road id order
+    ///
+    /// Each direction of a two-way road has its own row.
+    pub fn road_stats(&self) -> Vec<RoadStats> {
+        let network = &self.road_network;
+        network
+            .roads()
+            .values()
+            .map(|road| RoadStats {
+                road_id: road.id,
+                start_intersection: road.start_intersection,
+                end_intersection: road.end_intersection,
+                length: road.length,
+                two_way: road.is_two_way,
+                vehicles_now: network.get_cars_on_road(road.id).len(),
+                traffic_volume: network.traffic_volume(road.id),
+            })
+            .collect()
+    }
+
+    /// Road statistics as CSV, one row per road
+    pub fn road_stats_csv(&self) -> String {
+        let mut csv = format!("{}\n", ROAD_STATS_CSV_HEADER);
+        for road in self.road_stats() {
+            // Writing to a String can't fail
+            let _ = writeln!(
+                csv,
+                "{},{},{},{:.2},{},{},{}",
+                road.road_id.0 .0,
+                road.start_intersection.0 .0,
+                road.end_intersection.0 .0,
+                road.length,
+                road.two_way,
+                road.vehicles_now,
+                road.traffic_volume
+            );
+        }
+        csv
+    }
+
+    /// Write the road statistics to a CSV file
+    pub fn save_road_stats_csv(&self, path: &Path) -> Result<()> {
+        std::fs::write(path, self.road_stats_csv())
+            .with_context(|| format!("Failed to write road stats {}", path.display()))
+    }
+}
@@ -0,0 +1,338 @@
+//! Background pathfinding
+//!
+//! Finding a route is the costliest part of spawning a vehicle, so a tick
+//! in which many workers set off at once can stall. A world with a
+//! [`RoutePool`] spawns commuters and delivery trucks waiting at their
+//! starting intersection instead. At the end of the tick their route
+//! requests go to the pool's worker threads along with a snapshot of the
+//! road network, and at the start of the next tick the routes are collected
+//! and the vehicles set off. A route found on a snapshot the player has
+//! since built over is found again on the current network.
+//!
+//! Every route is found on the snapshot taken at the end of the tick it was
+//! requested in and applied in request order, so runs with any number of
+//! workers match each other. They don't match runs that route inline,
+//! because vehicles set off a tick later.
+
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+
+#[cfg(feature = "serialization")]
+use serde::{Deserialize, Serialize};
+
+use super::car_manager;
+use super::road_network::SimRoadNetwork;
+use super::pending_trips::can_wait_for_connection;
+use super::spawning::VehicleSpawn;
+use super::types::{CarId, IntersectionId, VehicleType};
+use super::world::SimWorld;
+
+/// Most worker threads [`default_route_workers`] picks
+pub const MAX_DEFAULT_ROUTE_WORKERS: usize = 4;
+
+/// Worker threads to use on this machine: one per spare core, up to
+/// [`MAX_DEFAULT_ROUTE_WORKERS`]
+pub fn default_route_workers() -> usize {
+    std::thread::available_parallelism()
+        .map_or(1, |cores| cores.get().saturating_sub(1))
+        .clamp(1, MAX_DEFAULT_ROUTE_WORKERS)
+}
+
+/// A vehicle's request for a route
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+pub(super) struct RouteRequest {
+    pub car_id: CarId,
+    pub from: IntersectionId,
+    pub to: IntersectionId,
+    pub vehicle_type: VehicleType,
+}
+
+/// A request on its way to a worker, with the network to route it on
+struct RouteJob {
+    /// Position of the request in its batch
+    index: usize,
+    network: Arc<SimRoadNetwork>,
+    request: RouteRequest,
+}
+
+/// A route found by a worker, or `None` if there is none
+type RouteResult = (usize, Option<Vec<IntersectionId>>);
+
+/// Worker threads finding routes for newly spawned vehicles
+pub struct RoutePool {
+    /// Closed on drop, which stops the workers
+    jobs: Option<Sender<RouteJob>>,
+    /// Only read through `&mut self`; the lock just makes the pool `Sync`
+    results: Mutex<Receiver<RouteResult>>,
+    workers: Vec<JoinHandle<()>>,
+    /// Requests made this tick
+    queued: Vec<RouteRequest>,
+    /// Requests the workers are routing
+    in_flight: Vec<RouteRequest>,
+    /// Road layout of the snapshot they are routing on
+    in_flight_layout: u64,
+}
+
+impl RoutePool {
+    /// Start `workers` threads (at least one)
+    pub fn new(workers: usize) -> Self {
+        let (jobs, job_receiver) = mpsc::channel::<RouteJob>();
+        let (result_sender, results) = mpsc::channel();
+        let job_receiver = Arc::new(Mutex::new(job_receiver));
+        let workers = (0..workers.max(1))
+            .map(|_| {
+                let job_receiver = Arc::clone(&job_receiver);
+                let result_sender = result_sender.clone();
+                std::thread::spawn(move || loop {
+                    // The lock is only held while waiting for the next job
+                    let job = match job_receiver.lock() {
+                        Ok(receiver) => receiver.recv(),
+                        Err(_) => return,
+                    };
+                    let Ok(job) = job else {
+                        return;
+                    };
+                    let RouteRequest {
+                        from,
+                        to,
+                        vehicle_type,
+                        ..
+                    } = job.request;
+                    // A panic loses the route, not the worker or the batch
+                    let route =
+                        catch_unwind(AssertUnwindSafe(|| {
+                            job.network.find_path_for(from, to, vehicle_type)
+                        }))
+                        .ok()
+                        .flatten();
+                    if result_sender.send((job.index, route)).is_err() {
+                        return;
+                    }
+                })
+            })
+            .collect();
+        Self {
+            jobs: Some(jobs),
+            results: Mutex::new(results),
+            workers,
+            queued: Vec::new(),
+            in_flight: Vec::new(),
+            in_flight_layout: 0,
+        }
+    }
+
+    /// Number of worker threads
+    pub fn workers(&self) -> usize {
+        self.workers.len()
+    }
+
+    /// Vehicles waiting for a route
+    pub fn waiting(&self) -> usize {
+        self.queued.len() + self.in_flight.len()
+    }
+
+    /// Requests the workers are routing, and those made since
+    #[cfg(feature = "serialization")]
+    pub(super) fn requests(&self) -> (&[RouteRequest], &[RouteRequest]) {
+        (&self.in_flight, &self.queued)
+    }
+
+    /// Ask for a route, to be sent to the workers at the end of the tick
+    pub(super) fn request(&mut self, request: RouteRequest) {
+        self.queued.push(request);
+    }
+
+    /// Send this tick's requests to the workers with a snapshot of the
+    /// network
+    ///
+    /// Routes sent earlier must have been collected first.
+    pub(super) fn dispatch(&mut self, network: &SimRoadNetwork) {
+        debug_assert!(self.in_flight.is_empty(), "routes dispatched twice");
+        if self.queued.is_empty() {
+            return;
+        }
+        let snapshot = Arc::new(network.clone());
+        self.in_flight = std::mem::take(&mut self.queued);
+        self.in_flight_layout = network.layout_version();
+        for (index, request) in self.in_flight.iter().enumerate() {
+            let job = RouteJob {
+                index,
+                network: Arc::clone(&snapshot),
+                request: *request,
+            };
+            if let Some(jobs) = &self.jobs {
+                // The workers only stop when the pool is dropped
+                let _ = jobs.send(job);
+            }
+        }
+    }
+
+    /// Wait for the routes sent by the last [`dispatch`](Self::dispatch),
+    /// in the order they were requested
+    ///
+    /// A route the workers couldn't deliver comes back as `None` along
+    /// with a flag saying so, for the caller to find inline.
+    pub(super) fn collect(&mut self) -> Vec<(RouteRequest, Option<Vec<IntersectionId>>, bool)> {
+        let requests = std::mem::take(&mut self.in_flight);
+        let mut routes: Vec<Option<Option<Vec<IntersectionId>>>> = vec![None; requests.len()];
+        let results = self
+            .results
+            .get_mut()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        for _ in 0..requests.len() {
+            match results.recv() {
+                Ok((index, route)) => routes[index] = Some(route),
+                // Every worker is gone
+                Err(_) => break,
+            }
+        }
+        requests
+            .into_iter()
+            .zip(routes)
+            .map(|(request, route)| match route {
+                Some(route) => (request, route, true),
+                None => (request, None, false),
+            })
+            .collect()
+    }
+}
+
+impl Drop for RoutePool {
+    fn drop(&mut self) {
+        self.jobs = None;
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+impl SimWorld {
+    /// Find routes for new commuters and trucks on `workers` background
+    /// threads, or inline if `workers` is 0
+    ///
+    /// Vehicles already waiting for a route set off before the pool is
+    /// replaced.
+    pub fn set_route_workers(&mut self, workers: usize) {
+        self.set_off_routed_vehicles();
+        self.dispatch_route_requests();
+        self.set_off_routed_vehicles();
+        self.route_pool = (workers > 0).then(|| RoutePool::new(workers));
+    }
+
+    /// Route requests the background threads are working on, and those
+    /// made since, in the order they were made
+    #[cfg(feature = "serialization")]
+    pub(super) fn route_requests(&self) -> (&[RouteRequest], &[RouteRequest]) {
+        self.route_pool.as_ref().map_or((&[], &[]), RoutePool::requests)
+    }
+
+    /// Start `workers` threads routing requests left over from another
+    /// world, as [`Self::route_requests`] returned them
+    ///
+    /// The requests being worked on are sent again with the current
+    /// network, so their vehicles set off when they would have in the world
+    /// the requests came from.
+    #[cfg(feature = "serialization")]
+    pub(super) fn resume_route_requests(
+        &mut self,
+        workers: usize,
+        in_flight: Vec<RouteRequest>,
+        queued: Vec<RouteRequest>,
+    ) {
+        let mut pool = RoutePool::new(workers);
+        pool.queued = in_flight;
+        pool.dispatch(&self.road_network);
+        pool.queued = queued;
+        self.route_pool = Some(pool);
+    }
+
+    /// Number of background routing threads (0 when routing inline)
+    pub fn route_workers(&self) -> usize {
+        self.route_pool.as_ref().map_or(0, RoutePool::workers)
+    }
+
+    /// Vehicles waiting at their start for a route
+    pub fn vehicles_awaiting_route(&self) -> usize {
+        self.route_pool.as_ref().map_or(0, RoutePool::waiting)
+    }
+
+    /// Send the route requests made this tick to the route pool
+    pub(super) fn dispatch_route_requests(&mut self) {
+        if let Some(pool) = &mut self.route_pool {
+            pool.dispatch(&self.road_network);
+        }
+    }
+
+    /// Start the vehicles whose routes have come back along them, in the
+    /// order they were requested
+    ///
+    /// A route the network no longer allows, or one the workers couldn't
+    /// deliver or didn't find on a network that has since changed, is looked
+    /// for again. A vehicle with no route gives up its trip, as it would have
+    /// if spawning had failed.
+    pub(super) fn set_off_routed_vehicles(&mut self) {
+        let Some(pool) = &mut self.route_pool else {
+            return;
+        };
+        let layout_unchanged = pool.in_flight_layout == self.road_network.layout_version();
+        for (request, route, delivered) in pool.collect() {
+            // The vehicle may have been removed while it waited
+            if self
+                .cars
+                .get(&request.car_id)
+                .is_none_or(|car| car.awaiting_route != Some(request.to))
+            {
+                continue;
+            }
+            let route = match route {
+                Some(path)
+                    if self.road_network.route_is_usable(
+                        request.from,
+                        &path,
+                        request.vehicle_type,
+                    ) =>
+                {
+                    Some(path)
+                }
+                None if delivered && layout_unchanged => None,
+                _ => self.find_route(request.from, request.to, request.vehicle_type),
+            };
+            let set_off = match (route, self.cars.get_mut(&request.car_id)) {
+                (Some(path), Some(car)) => {
+                    car_manager::set_off(car, path, &self.road_network).is_ok()
+                }
+                _ => false,
+            };
+            if set_off {
+                self.mark_vehicle_changed(request.car_id);
+                self.join_or_queue(request.car_id);
+            } else {
+                self.abandon_trip(request);
+            }
+        }
+    }
+
+    /// Give up the trip of a vehicle that has no route
+    ///
+    /// Commutes, workers' trips home and deliveries wait for the roads to
+    /// connect them instead.
+    fn abandon_trip(&mut self, request: RouteRequest) {
+        let pending = self
+            .cars
+            .get(&request.car_id)
+            .filter(|car| car.origin_special.is_none())
+            .map(|car| VehicleSpawn {
+                origin_apartment: car.origin_apartment,
+                origin_factory: car.origin_factory,
+                ..VehicleSpawn::new(request.from, request.to, car.vehicle_type, car.trip_type)
+            })
+            .filter(can_wait_for_connection);
+        self.end_trip_unexpectedly(request.car_id);
+        if let Some(spawn) = pending {
+            self.defer_trip(spawn);
+        }
+    }
+}
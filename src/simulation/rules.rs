@@ -0,0 +1,106 @@
+//! Traffic rules at intersections
+//!
+//! [`IntersectionRules`] are policies that hold at every intersection in the
+//! world, so scenarios can compare regulatory regimes by how much traffic
+//! gets through. They are part of the economy, so an economy file sets them
+//! for a scenario; a world without a game uses the defaults.
+//!
+//! - **Right turn on red**: a vehicle turning right may go against a red
+//!   light once it has come to a stop, as long as the intersection is clear.
+//!   Where traffic drives on the left, it is the left turn instead.
+//! - **Yield to pedestrians**: a vehicle turning at a junction of three or
+//!   more roads waits for people crossing, taking [`PEDESTRIAN_YIELD_SECS`]
+//!   longer to clear the intersection. Ambulances don't wait.
+//! - **Late truck preemption**: a delivery truck within
+//!   [`PREEMPTION_WINDOW_SECS`] of its deadline, or past it, that reaches a
+//!   red light switches the signal to its road's phase. Each signal gives way
+//!   at most once a cycle, so the roads it cuts short still get their turn.
+
+use std::f32::consts::{FRAC_PI_4, PI};
+
+#[cfg(feature = "serialization")]
+use serde::{Deserialize, Serialize};
+
+use super::world::SimWorld;
+
+/// Extra seconds a turning vehicle takes to cross a junction while yielding
+/// to pedestrians
+pub const PEDESTRIAN_YIELD_SECS: f32 = 0.5;
+
+/// Fewest roads meeting at an intersection for it to have pedestrian
+/// crossings
+pub const PEDESTRIAN_CROSSING_MIN_ROADS: usize = 3;
+
+/// Seconds before its delivery deadline from which a truck may preempt
+/// signals
+pub const PREEMPTION_WINDOW_SECS: f32 = 10.0;
+
+/// Policies that apply at every intersection
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serialization", serde(default, deny_unknown_fields))]
+pub struct IntersectionRules {
+    /// Whether vehicles may turn right (left, driving on the left) at a red
+    /// light after stopping
+    pub right_turn_on_red: bool,
+    /// Whether turning vehicles yield to pedestrians crossing at junctions
+    pub yield_to_pedestrians: bool,
+    /// Whether trucks near their delivery deadline preempt signals
+    pub late_truck_preemption: bool,
+}
+
+/// Which way a vehicle turns from one road onto the next
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Turn {
+    Straight,
+    Left,
+    Right,
+    /// Back the way it came
+    UTurn,
+}
+
+/// Change in heading from a road heading at `from_angle` onto one heading at
+/// `to_angle` (see [`super::Position::angle_to`]), between -π and π
+///
+/// A right turn, clockwise seen from above, is negative, whichever side
+/// vehicles drive on.
+pub fn turn_angle(from_angle: f32, to_angle: f32) -> f32 {
+    (to_angle - from_angle + PI).rem_euclid(2.0 * PI) - PI
+}
+
+impl Turn {
+    /// The turn from a road heading at `from_angle` onto one heading at
+    /// `to_angle`
+    pub fn between(from_angle: f32, to_angle: f32) -> Self {
+        Self::of_angle(turn_angle(from_angle, to_angle))
+    }
+
+    /// The turn for a change in heading given by [`turn_angle`]
+    pub fn of_angle(change: f32) -> Self {
+        if change.abs() < FRAC_PI_4 {
+            Turn::Straight
+        } else if change.abs() > 3.0 * FRAC_PI_4 {
+            Turn::UTurn
+        } else if change < 0.0 {
+            Turn::Right
+        } else {
+            Turn::Left
+        }
+    }
+
+    /// Whether the vehicle changes direction
+    pub fn is_turn(self) -> bool {
+        self != Turn::Straight
+    }
+}
+
+impl SimWorld {
+    /// The rules in force at intersections
+    pub fn intersection_rules(&self) -> IntersectionRules {
+        self.game_state
+            .as_ref()
+            .map_or_else(IntersectionRules::default, |game_state| {
+                game_state.economy.intersection_rules
+            })
+    }
+}
@@ -0,0 +1,622 @@
+//! Saved cities
+//!
+//! A save doesn't serialize the [`SimWorld`] itself, as a snapshot does
+//! (see [`SimWorld::save_to_file`]). Instead a [`SaveFile`] holds
+//! what the starting world was made from (seed, economy, content packs,
+//! water, terrain, bounds and the side of the road traffic drives on) and the [`BuildCommand`]s the city was built with, in
+//! order. Since commands have the same result on identical worlds, replaying
+//! them on a fresh world rebuilds the same city. Each command keeps the ID
+//! the world was about to give out when it was applied, so what it builds
+//! gets the same IDs on replay even though the vehicles spawned in between
+//! aren't saved, and the save keeps the ID the world was about to give out
+//! when it was saved, so a loaded world carries on from there. Traffic
+//! starts over when a save is loaded. [`SimWorld::state_hash`] covers what
+//! a save keeps, so it is the same for a world and the world loaded from its
+//! save.
+//!
+//! Every save records the [`SAVE_VERSION`] it was written with. An older
+//! save is upgraded as JSON before it is parsed, one version at a time:
+//! each migration defaults the fields its version added and maps anything
+//! it renamed, so saves keep loading as the simulation changes. Changing
+//! the format means adding a migration to `MIGRATIONS`, which bumps
+//! [`SAVE_VERSION`], and a sample save of the new version to `tests/saves`.
+//!
+//! Version 0 is the unversioned format of the `save_load` example: just the
+//! seed, economy and commands, paid for from the starting budget.
+//!
+//! Each save also keeps a little [`SaveMetadata`] for listing it: when it
+//! was written, how long the game has run, the money and deliveries it was
+//! up to and a small map of the city, drawn by [`SimWorld::map_png`].
+//!
+//! Saves are written atomically, so a crash mid-write leaves the previous
+//! save intact. An [`Autosaver`] writes them on a background thread, and a
+//! [`RunningMarker`] next to the save tells the next session whether the
+//! last one exited cleanly.
+
+use anyhow::{anyhow, bail, ensure, Context, Result};
+use log::warn;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::fs::File;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::thread::JoinHandle;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::command::BuildCommand;
+use super::content::ContentPack;
+use super::driving_side::DrivingSide;
+use super::economy::EconomyConfig;
+use super::bounds::WorldBounds;
+use super::elevation::Heightmap;
+use super::game_state::GameState;
+use super::raster::{base64, from_base64};
+use super::terrain::WaterRegion;
+use super::types::Position;
+use super::world::SimWorld;
+
+/// Upgrades a save's JSON from the version at its index in `MIGRATIONS` to
+/// the next
+type Migration = fn(&mut Map<String, Value>) -> Result<()>;
+
+/// Every change to the format so far, oldest first
+const MIGRATIONS: &[Migration] = &[
+    migrate_v0_to_v1,
+    migrate_v1_to_v2,
+    migrate_v2_to_v3,
+    migrate_v3_to_v4,
+    migrate_v4_to_v5,
+    migrate_v5_to_v6,
+];
+
+/// Version of the format saves are written in
+pub const SAVE_VERSION: u32 = MIGRATIONS.len() as u32;
+
+/// Width of a save's map thumbnail, in pixels
+pub const THUMBNAIL_WIDTH: u32 = 128;
+
+/// Funds a game has while its commands are replayed, so none run short
+const REPLAY_FUNDS: i32 = i32::MAX / 2;
+
+/// A command in a save
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SavedCommand {
+    /// ID the world was about to give out when the command was applied;
+    /// `None` in saves that predate it
+    pub first_id: Option<usize>,
+    pub command: BuildCommand,
+}
+
+/// What a save was of, for listing it without rebuilding the city
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SaveMetadata {
+    /// When the save was written, in seconds since the Unix epoch
+    pub saved_at: u64,
+    /// Seconds of simulation time the game has run for
+    pub play_time: f32,
+    /// Funds, or `None` for a sandbox world
+    pub money: Option<i32>,
+    /// Deliveries made to shops
+    pub deliveries: usize,
+    /// Map of the city as a base64 PNG image [`THUMBNAIL_WIDTH`] pixels across
+    pub thumbnail: String,
+}
+
+impl SaveMetadata {
+    /// Describe the world as it is now
+    pub fn of_world(world: &SimWorld) -> Self {
+        Self {
+            saved_at: unix_time(),
+            play_time: world.time,
+            money: world.game_state.as_ref().map(|game_state| game_state.money),
+            deliveries: world
+                .game_state
+                .as_ref()
+                .map_or(0, |game_state| game_state.shop_deliveries_completed),
+            thumbnail: base64(&world.map_png(THUMBNAIL_WIDTH)),
+        }
+    }
+
+    /// The thumbnail as a PNG image
+    pub fn thumbnail_png(&self) -> Result<Vec<u8>> {
+        from_base64(&self.thumbnail).context("Invalid save thumbnail")
+    }
+
+    /// One line describing the save: how long ago it was written, time
+    /// played, money and deliveries
+    pub fn summary(&self) -> String {
+        let ago = unix_time().saturating_sub(self.saved_at);
+        let ago = match ago {
+            0..60 => "just now".to_string(),
+            60..3600 => format!("{} min ago", ago / 60),
+            3600..86_400 => format!("{} h ago", ago / 3600),
+            _ => format!("{} days ago", ago / 86_400),
+        };
+        let played = self.play_time.max(0.0) as u64;
+        let mut summary = format!(
+            "saved {}, {}:{:02} played",
+            ago,
+            played / 60,
+            played % 60
+        );
+        if let Some(money) = self.money {
+            summary.push_str(&format!(", ${}", money));
+        }
+        summary.push_str(&format!(", {} deliveries", self.deliveries));
+        summary
+    }
+}
+
+/// Seconds since the Unix epoch
+fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs())
+}
+
+/// Everything needed to rebuild a city
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SaveFile {
+    /// Format version the save was written in
+    pub version: u32,
+    /// Seed for the simulation's RNG
+    pub seed: u64,
+    pub economy: EconomyConfig,
+    /// Content pack definitions the commands may use
+    pub content: ContentPack,
+    /// Water on the map
+    pub water: Vec<WaterRegion>,
+    /// Ground heights, if the map isn't flat
+    pub heightmap: Option<Heightmap>,
+    /// The rectangle the city is built in; `None` to derive it as the world
+    /// goes (see [`SimWorld::bounds`])
+    pub bounds: Option<WorldBounds>,
+    /// Side of the road traffic drives on
+    pub driving_side: DrivingSide,
+    /// Commands that built the city, in the order they were applied
+    pub commands: Vec<SavedCommand>,
+    /// The game's progress when it was saved; if `None`, the commands are
+    /// paid for from the starting budget as they are replayed
+    pub game_state: Option<GameState>,
+    /// ID the world was about to give out when it was saved; `None` in saves
+    /// that predate it
+    pub next_id: Option<usize>,
+    /// What the save was of when it was written; `None` in saves that
+    /// predate it or were never written with the game's progress
+    pub metadata: Option<SaveMetadata>,
+}
+
+impl SaveFile {
+    /// An empty city on a flat map
+    pub fn new(seed: u64, economy: EconomyConfig) -> Self {
+        Self {
+            version: SAVE_VERSION,
+            seed,
+            economy,
+            content: ContentPack::default(),
+            water: Vec::new(),
+            heightmap: None,
+            bounds: None,
+            driving_side: DrivingSide::default(),
+            commands: Vec::new(),
+            game_state: None,
+            next_id: None,
+            metadata: None,
+        }
+    }
+
+    /// A save of a world about to be built on, keeping its economy, content
+    /// packs, water, terrain, bounds and driving side
+    pub fn of_world(seed: u64, world: &SimWorld) -> Self {
+        let economy = world
+            .game_state
+            .as_ref()
+            .map(|game_state| game_state.economy.clone())
+            .unwrap_or_default();
+        Self {
+            content: ContentPack {
+                buildings: world.content.buildings().to_vec(),
+                vehicles: world.content.vehicles().to_vec(),
+            },
+            water: world.water.clone(),
+            heightmap: world.heightmap.clone(),
+            bounds: world.configured_bounds(),
+            driving_side: world.driving_side,
+            ..Self::new(seed, economy)
+        }
+    }
+
+    /// Apply commands to the world the save is of, logging any that fail
+    /// like [`SimWorld::apply_commands`] and keeping the rest
+    pub fn apply_commands(&mut self, world: &mut SimWorld, commands: &[BuildCommand]) {
+        for command in commands {
+            let first_id = world.peek_next_id();
+            if world.apply_command_logged(command) {
+                self.commands.push(SavedCommand {
+                    first_id: Some(first_id),
+                    command: command.clone(),
+                });
+            }
+        }
+    }
+
+    /// Keep the game's progress, so loading restores it instead of paying
+    /// for the commands again, the ID the world is up to and the save's
+    /// metadata
+    pub fn record_game_state(&mut self, world: &SimWorld) {
+        self.next_id = Some(world.peek_next_id());
+        self.metadata = Some(SaveMetadata::of_world(world));
+        self.game_state = world.game_state.clone().map(|mut game_state| {
+            // Already reported
+            game_state.events.clear();
+            game_state
+        });
+    }
+
+    /// Parse a save of any version so far, upgrading it to [`SAVE_VERSION`]
+    pub fn from_json_str(text: &str) -> Result<Self> {
+        let value: Value = serde_json::from_str(text).context("Failed to parse save JSON")?;
+        let Value::Object(mut save) = value else {
+            bail!("A save must be a JSON object");
+        };
+        // Version 0 predates the version field
+        let version = match save.get("version") {
+            Some(version) => version
+                .as_u64()
+                .and_then(|version| u32::try_from(version).ok())
+                .context("The save's version must be a whole number")?,
+            None => 0,
+        };
+        ensure!(
+            version <= SAVE_VERSION,
+            "The save is version {} but this build reads up to version {}",
+            version,
+            SAVE_VERSION
+        );
+        for (from, migrate) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+            migrate(&mut save)
+                .with_context(|| format!("Failed to upgrade the save from version {}", from))?;
+            save.insert("version".to_string(), Value::from(from + 1));
+        }
+        let save: Self = serde_json::from_value(Value::Object(save)).context("Invalid save")?;
+        save.economy.validate()?;
+        if let Some(heightmap) = &save.heightmap {
+            heightmap.validate()?;
+        }
+        Ok(save)
+    }
+
+    /// Serialize the save to JSON text
+    pub fn to_json_string(&self) -> Result<String> {
+        serde_json::to_string_pretty(self).context("Failed to serialize save")
+    }
+
+    /// Load a save from a JSON file
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read save file {}", path.display()))?;
+        Self::from_json_str(&text).with_context(|| format!("Invalid save file {}", path.display()))
+    }
+
+    /// Write the save to a JSON file, atomically
+    pub fn save(&self, path: &Path) -> Result<()> {
+        write_atomically(path, &self.to_json_string()?)
+            .with_context(|| format!("Failed to write save file {}", path.display()))
+    }
+
+    /// Rebuild the city on a fresh world
+    pub fn create_world(&self) -> Result<SimWorld> {
+        let mut world = SimWorld::new_with_seed(self.seed);
+        world.game_state = Some(GameState::with_economy(self.economy.clone()));
+        world
+            .content
+            .register_pack(self.content.clone())
+            .context("Failed to register the save's content packs")?;
+        if let Some(heightmap) = &self.heightmap {
+            world.set_heightmap(heightmap.clone())?;
+        }
+        for region in &self.water {
+            world.add_water(*region);
+        }
+        world.set_bounds(self.bounds)?;
+        world.driving_side = self.driving_side;
+
+        // With the game's progress saved, what the commands cost doesn't
+        // matter; it is put back afterwards
+        if self.game_state.is_some() {
+            if let Some(game_state) = &mut world.game_state {
+                game_state.money = REPLAY_FUNDS;
+            }
+        }
+        for (index, saved) in self.commands.iter().enumerate() {
+            let applied = saved
+                .first_id
+                .map_or(Ok(()), |first_id| world.skip_ids_to(first_id))
+                .and_then(|()| world.apply_command(&saved.command))
+                .with_context(|| format!("Failed to replay command {}", index + 1))?;
+            ensure!(
+                applied,
+                "Couldn't afford command {} ({})",
+                index + 1,
+                saved.command.describe()
+            );
+        }
+        if let Some(game_state) = &self.game_state {
+            world.game_state = Some(game_state.clone());
+        }
+        // Commands applied after the ID was recorded have taken it further
+        if let Some(next_id) = self.next_id.filter(|&id| id > world.peek_next_id()) {
+            world.skip_ids_to(next_id)?;
+        }
+        Ok(world)
+    }
+}
+
+impl SimWorld {
+    /// Hash of everything a save keeps: the layout, buildings, signal
+    /// timings, bus routes, the game's progress, the ID the world is up to,
+    /// the side traffic drives on and any bounds the world was given
+    ///
+    /// Everything is hashed in ID order, so a world and the world loaded
+    /// from its save have the same hash whatever was built and demolished
+    /// before. Traffic and anything else that starts over on loading is left
+    /// out.
+    pub fn state_hash(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.peek_next_id().hash(&mut hasher);
+        self.driving_side.hash(&mut hasher);
+        if let Some(bounds) = self.configured_bounds() {
+            for edge in [bounds.min_x, bounds.min_z, bounds.max_x, bounds.max_z] {
+                edge.to_bits().hash(&mut hasher);
+            }
+        }
+        let hash_position = |position: &Position, hasher: &mut DefaultHasher| {
+            for coordinate in [position.x, position.y, position.z] {
+                coordinate.to_bits().hash(hasher);
+            }
+        };
+        for intersection in self.intersections.values() {
+            intersection.id.hash(&mut hasher);
+            hash_position(&intersection.position, &mut hasher);
+            let phases = intersection.signal.as_ref().map(|signal| signal.phases());
+            for phase in phases.into_iter().flatten() {
+                phase.green.hash(&mut hasher);
+                phase.duration.to_bits().hash(&mut hasher);
+            }
+        }
+        for road in self.road_network.roads().values() {
+            // The direction it was built in, which is what loading restores
+            let ends = (road.start_intersection, road.end_intersection);
+            let (start, end) = if self.is_contraflow_reversed(road.id) {
+                (ends.1, ends.0)
+            } else {
+                ends
+            };
+            (road.id, start, end).hash(&mut hasher);
+            (road.is_two_way, road.restriction, road.sensor, road.kind).hash(&mut hasher);
+            road.closed.hash(&mut hasher);
+        }
+        for (road_id, windows) in self.contraflow_schedules() {
+            road_id.hash(&mut hasher);
+            for window in windows {
+                (window.start_hour.to_bits(), window.end_hour.to_bits()).hash(&mut hasher);
+            }
+        }
+        for apartment in self.apartments.values() {
+            (apartment.id, apartment.intersection_id).hash(&mut hasher);
+        }
+        for factory in self.factories.values() {
+            (factory.id, factory.intersection_id).hash(&mut hasher);
+        }
+        for shop in self.shops.values() {
+            (shop.id, shop.intersection_id, &shop.variant).hash(&mut hasher);
+        }
+        for building in self.special_buildings.values() {
+            (building.id, building.intersection_id, building.kind).hash(&mut hasher);
+        }
+        for decoration in self.decorations.values() {
+            (decoration.id, decoration.kind).hash(&mut hasher);
+            hash_position(&decoration.position, &mut hasher);
+        }
+        for route in self.bus_routes().values() {
+            (route.id, &route.stops).hash(&mut hasher);
+        }
+        if let Some(game_state) = &self.game_state {
+            game_state.money.hash(&mut hasher);
+            game_state.worker_trips_completed.hash(&mut hasher);
+            game_state.shop_deliveries_completed.hash(&mut hasher);
+            game_state.incidents.hash(&mut hasher);
+            game_state.time.to_bits().hash(&mut hasher);
+            (game_state.is_won, game_state.is_lost, game_state.insured).hash(&mut hasher);
+        }
+        hasher.finish()
+    }
+}
+
+/// Write a file so that it holds either its old or its new contents, even
+/// if the process dies part way through
+pub fn write_atomically(path: &Path, contents: &str) -> Result<()> {
+    let mut temp_name = path.file_name().context("No file name")?.to_os_string();
+    temp_name.push(".tmp");
+    let temp_path = path.with_file_name(temp_name);
+    let mut file = File::create(&temp_path)
+        .with_context(|| format!("Failed to create {}", temp_path.display()))?;
+    file.write_all(contents.as_bytes())
+        .and_then(|()| file.sync_all())
+        .with_context(|| format!("Failed to write {}", temp_path.display()))?;
+    std::fs::rename(&temp_path, path)
+        .with_context(|| format!("Failed to replace {}", path.display()))
+}
+
+/// Writes saves to a file on a background thread, one at a time
+pub struct Autosaver {
+    path: PathBuf,
+    writer: Option<JoinHandle<Result<()>>>,
+}
+
+impl Autosaver {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path, writer: None }
+    }
+
+    /// File the saves are written to
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Start writing a copy of a save, unless the last one is still being
+    /// written
+    ///
+    /// Returns whether it started, or the error the last write ended with.
+    pub fn autosave(&mut self, save: &SaveFile) -> Result<bool> {
+        if self
+            .writer
+            .as_ref()
+            .is_some_and(|writer| !writer.is_finished())
+        {
+            return Ok(false);
+        }
+        self.finish()?;
+        let save = save.clone();
+        let path = self.path.clone();
+        self.writer = Some(std::thread::spawn(move || save.save(&path)));
+        Ok(true)
+    }
+
+    /// Wait for the save being written, if any
+    pub fn finish(&mut self) -> Result<()> {
+        match self.writer.take() {
+            Some(writer) => writer
+                .join()
+                .map_err(|_| anyhow!("The autosave thread panicked"))?,
+            None => Ok(()),
+        }
+    }
+}
+
+/// Marks a save as belonging to a session that is still running
+///
+/// The marker is a file next to the save, removed by [`RunningMarker::clear`]
+/// when the session ends cleanly. It isn't removed on drop, so a session
+/// that panics or is killed leaves it behind.
+#[derive(Debug)]
+pub struct RunningMarker {
+    path: PathBuf,
+}
+
+impl RunningMarker {
+    /// Marker file for a save
+    fn path_for(save_path: &Path) -> PathBuf {
+        let mut name = save_path.as_os_str().to_os_string();
+        name.push(".running");
+        PathBuf::from(name)
+    }
+
+    /// Whether a session saving to `save_path` ended without clearing its
+    /// marker
+    pub fn left_behind(save_path: &Path) -> bool {
+        Self::path_for(save_path).exists()
+    }
+
+    /// Mark a session saving to `save_path` as running
+    pub fn create(save_path: &Path) -> Result<Self> {
+        let path = Self::path_for(save_path);
+        std::fs::write(&path, std::process::id().to_string())
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        Ok(Self { path })
+    }
+
+    /// Remove the marker as the session ends cleanly
+    pub fn clear(self) -> Result<()> {
+        match std::fs::remove_file(&self.path) {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                warn!("{} was already gone", self.path.display());
+                Ok(())
+            }
+            Err(e) => Err(e).with_context(|| format!("Failed to remove {}", self.path.display())),
+        }
+    }
+}
+
+/// Version 1 added the version, content packs, water, terrain and funds
+fn migrate_v0_to_v1(save: &mut Map<String, Value>) -> Result<()> {
+    for field in ["seed", "economy", "commands"] {
+        ensure!(save.contains_key(field), "The save has no {}", field);
+    }
+    save.insert("content".to_string(), serde_json::json!({}));
+    save.insert("water".to_string(), Value::Array(Vec::new()));
+    save.insert("heightmap".to_string(), Value::Null);
+    save.insert("money".to_string(), Value::Null);
+    Ok(())
+}
+
+/// Version 2 gave each command the ID it started from and replaced the
+/// funds with the whole game state
+fn migrate_v1_to_v2(save: &mut Map<String, Value>) -> Result<()> {
+    let commands = save
+        .get_mut("commands")
+        .and_then(Value::as_array_mut)
+        .context("The save's commands must be a list")?;
+    for command in commands.iter_mut() {
+        *command = serde_json::json!({ "first_id": null, "command": command.take() });
+    }
+    let money = save.remove("money").unwrap_or(Value::Null);
+    let game_state = if money.is_null() {
+        Value::Null
+    } else {
+        // Nothing else about the game's progress was saved
+        let economy = save.get("economy").cloned().unwrap_or_default();
+        serde_json::json!({
+            "money": money,
+            "worker_trips_completed": 0,
+            "shop_deliveries_completed": 0,
+            "time": 0.0,
+            "is_won": false,
+            "is_lost": false,
+            "economy": economy,
+        })
+    };
+    save.insert("game_state".to_string(), game_state);
+    Ok(())
+}
+
+/// Version 3 kept the ID the world was up to
+fn migrate_v2_to_v3(save: &mut Map<String, Value>) -> Result<()> {
+    save.insert("next_id".to_string(), Value::Null);
+    Ok(())
+}
+
+/// Version 4 kept the side of the road traffic drives on, which was always
+/// the right before
+fn migrate_v3_to_v4(save: &mut Map<String, Value>) -> Result<()> {
+    save.insert("driving_side".to_string(), Value::from("right"));
+    Ok(())
+}
+
+/// Version 5 kept metadata for listing saves
+fn migrate_v4_to_v5(save: &mut Map<String, Value>) -> Result<()> {
+    save.insert("metadata".to_string(), Value::Null);
+    Ok(())
+}
+
+/// Version 6 kept the world's bounds. Commands weren't held to any before,
+/// so older saves get bounds covering everything their commands built at.
+fn migrate_v5_to_v6(save: &mut Map<String, Value>) -> Result<()> {
+    let commands: Vec<SavedCommand> =
+        serde_json::from_value(save.get("commands").cloned().unwrap_or_default())
+            .context("Invalid commands")?;
+    let heightmap: Option<Heightmap> =
+        serde_json::from_value(save.get("heightmap").cloned().unwrap_or_default())
+            .context("Invalid heightmap")?;
+    let bounds = WorldBounds::covering(
+        heightmap.as_ref(),
+        commands.iter().flat_map(|saved| saved.command.positions()),
+    );
+    save.insert("bounds".to_string(), serde_json::to_value(bounds)?);
+    Ok(())
+}
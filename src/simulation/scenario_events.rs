@@ -0,0 +1,336 @@
+//! Scripted and random scenario events
+//!
+//! An economy can include an [`EventSchedule`] that puts the city through
+//! disruptions, so a scenario (or a headless test) can check how well a
+//! network copes with them. Events listed in the schedule start at set
+//! times; with [`RandomEvents`], more start at random, each picking a road
+//! or factory of the city at random. Every event lasts a set time:
+//!
+//! - **Road closure**: a road is closed like the player closes one (see
+//!   [`SimWorld::set_road_closed`]), e.g. for a parade, and reopened when
+//!   the event ends. A road that is already closed is left to whoever
+//!   closed it.
+//! - **Factory strike**: the factory turns workers away, so it stops
+//!   producing goods. Workers already on shift finish it.
+//! - **Demand surge**: the demand ramp's level is multiplied while it lasts.
+//!   Without a ramp it has no effect.
+//!
+//! Starting and ending events are reported as [`GameEvent`]s. Events
+//! restart from the beginning of the schedule when a save is loaded, like
+//! traffic.
+//!
+//! [`GameEvent`]: super::GameEvent
+
+use anyhow::{ensure, Result};
+#[cfg(feature = "serialization")]
+use serde::{Deserialize, Serialize};
+
+use super::types::{IntersectionId, SECONDS_PER_DAY};
+use super::warnings::SimWarning;
+use super::world::SimWorld;
+
+/// Something that happens to the city for a while
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+#[cfg_attr(
+    feature = "serialization",
+    serde(tag = "type", rename_all = "snake_case")
+)]
+pub enum ScenarioEvent {
+    /// The road between two intersections is closed both ways
+    RoadClosure {
+        from: IntersectionId,
+        to: IntersectionId,
+    },
+    /// The factory at an intersection turns its workers away
+    FactoryStrike { factory: IntersectionId },
+    /// Demand is multiplied by `multiplier`
+    DemandSurge { multiplier: f32 },
+}
+
+impl ScenarioEvent {
+    /// Name for display
+    pub fn label(&self) -> String {
+        match self {
+            ScenarioEvent::RoadClosure { .. } => "Road closure".to_string(),
+            ScenarioEvent::FactoryStrike { .. } => "Factory strike".to_string(),
+            ScenarioEvent::DemandSurge { multiplier } => {
+                format!("Demand surge x{:.1}", multiplier)
+            }
+        }
+    }
+}
+
+/// An event the schedule starts at a set time
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serialization", serde(deny_unknown_fields))]
+pub struct ScheduledEvent {
+    /// Simulation time the event starts
+    pub at_secs: f32,
+    /// Seconds the event lasts
+    pub duration_secs: f32,
+    pub event: ScenarioEvent,
+}
+
+/// Events that start at random, on top of the scheduled ones
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serialization", serde(default, deny_unknown_fields))]
+pub struct RandomEvents {
+    /// Chance of an event starting per in-game hour
+    pub events_per_hour: f32,
+    /// Seconds each random event lasts
+    pub duration_secs: f32,
+    /// Whether random events may close roads
+    pub road_closures: bool,
+    /// Whether random events may be factory strikes
+    pub factory_strikes: bool,
+    /// Whether random events may be demand surges
+    pub demand_surges: bool,
+    /// How much a random demand surge multiplies demand by
+    pub surge_multiplier: f32,
+}
+
+impl Default for RandomEvents {
+    fn default() -> Self {
+        Self {
+            events_per_hour: 0.1,
+            duration_secs: 60.0,
+            road_closures: true,
+            factory_strikes: true,
+            demand_surges: true,
+            surge_multiplier: 2.0,
+        }
+    }
+}
+
+/// The events a scenario puts the city through
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serialization", serde(default, deny_unknown_fields))]
+pub struct EventSchedule {
+    /// Events at set times, in the order they start
+    pub events: Vec<ScheduledEvent>,
+    /// Events at random times, if any
+    pub random: Option<RandomEvents>,
+}
+
+impl EventSchedule {
+    /// Reject schedules with events that never end or happen out of order
+    pub fn validate(&self) -> Result<()> {
+        let mut previous_start = 0.0;
+        for scheduled in &self.events {
+            ensure!(
+                scheduled.at_secs >= previous_start,
+                "event_schedule.events must be in the order they start (got {} after {})",
+                scheduled.at_secs,
+                previous_start
+            );
+            previous_start = scheduled.at_secs;
+            ensure!(
+                scheduled.duration_secs > 0.0,
+                "event_schedule.events duration_secs must be positive (got {})",
+                scheduled.duration_secs
+            );
+            if let ScenarioEvent::DemandSurge { multiplier } = scheduled.event {
+                ensure!(
+                    multiplier > 0.0,
+                    "event_schedule.events multiplier must be positive (got {})",
+                    multiplier
+                );
+            }
+        }
+        if let Some(random) = &self.random {
+            ensure!(
+                random.events_per_hour >= 0.0,
+                "event_schedule.random.events_per_hour must not be negative (got {})",
+                random.events_per_hour
+            );
+            ensure!(
+                random.duration_secs > 0.0,
+                "event_schedule.random.duration_secs must be positive (got {})",
+                random.duration_secs
+            );
+            ensure!(
+                random.surge_multiplier > 0.0,
+                "event_schedule.random.surge_multiplier must be positive (got {})",
+                random.surge_multiplier
+            );
+            ensure!(
+                random.road_closures || random.factory_strikes || random.demand_surges,
+                "event_schedule.random must allow at least one kind of event"
+            );
+        }
+        Ok(())
+    }
+}
+
+/// An event under way
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+pub struct ActiveEvent {
+    pub event: ScenarioEvent,
+    /// Simulation time the event ends
+    pub ends_at: f32,
+}
+
+impl SimWorld {
+    /// The event schedule of the game's economy, if it has one
+    pub fn event_schedule(&self) -> Option<&EventSchedule> {
+        self.game_state
+            .as_ref()
+            .and_then(|game_state| game_state.economy.event_schedule.as_ref())
+    }
+
+    /// Events under way, in the order they started
+    pub fn active_events(&self) -> &[ActiveEvent] {
+        &self.active_events
+    }
+
+    /// Whether the factory at an intersection is on strike
+    pub fn factory_on_strike(&self, factory: IntersectionId) -> bool {
+        self.active_events.iter().any(|active| {
+            matches!(active.event, ScenarioEvent::FactoryStrike { factory: striking }
+                if striking == factory)
+        })
+    }
+
+    /// Product of the multipliers of the demand surges under way
+    pub(super) fn demand_surge(&self) -> f32 {
+        self.active_events
+            .iter()
+            .filter_map(|active| match active.event {
+                ScenarioEvent::DemandSurge { multiplier } => Some(multiplier),
+                _ => None,
+            })
+            .product()
+    }
+
+    /// Start the events due by now and end the ones that are over
+    pub(super) fn update_scenario_events(&mut self, delta_secs: f32) {
+        let Some(schedule) = self.event_schedule().cloned() else {
+            return;
+        };
+
+        let mut index = 0;
+        while index < self.active_events.len() {
+            if self.active_events[index].ends_at <= self.time {
+                let ended = self.active_events.remove(index);
+                self.end_event(ended);
+            } else {
+                index += 1;
+            }
+        }
+
+        while let Some(scheduled) = schedule.events.get(self.scheduled_events_started) {
+            if scheduled.at_secs > self.time {
+                break;
+            }
+            self.scheduled_events_started += 1;
+            self.start_event(scheduled.event, scheduled.duration_secs);
+        }
+
+        if let Some(random) = &schedule.random {
+            let hours = delta_secs / SECONDS_PER_DAY * 24.0;
+            if self.random_range(0.0..1.0) < random.events_per_hour * hours {
+                if let Some(event) = self.random_event(random) {
+                    self.start_event(event, random.duration_secs);
+                }
+            }
+        }
+    }
+
+    /// Pick an event of a kind the random events allow, on a road or
+    /// factory that isn't already affected
+    fn random_event(&mut self, random: &RandomEvents) -> Option<ScenarioEvent> {
+        let mut candidates = Vec::new();
+        if random.road_closures {
+            let open: Vec<(IntersectionId, IntersectionId)> = self
+                .road_network
+                .two_way_roads()
+                .filter_map(|pair| self.road_network.get_road(pair.forward))
+                .filter(|road| !road.closed)
+                .map(|road| (road.start_intersection, road.end_intersection))
+                .collect();
+            if let Some(&(from, to)) = self.choose_random(&open) {
+                candidates.push(ScenarioEvent::RoadClosure { from, to });
+            }
+        }
+        if random.factory_strikes {
+            let working: Vec<IntersectionId> = self
+                .factories
+                .values()
+                .map(|factory| factory.intersection_id)
+                .filter(|&factory| !self.factory_on_strike(factory))
+                .collect();
+            if let Some(&factory) = self.choose_random(&working) {
+                candidates.push(ScenarioEvent::FactoryStrike { factory });
+            }
+        }
+        if random.demand_surges {
+            candidates.push(ScenarioEvent::DemandSurge {
+                multiplier: random.surge_multiplier,
+            });
+        }
+        self.choose_random(&candidates).copied()
+    }
+
+    fn start_event(&mut self, event: ScenarioEvent, duration_secs: f32) {
+        if let ScenarioEvent::RoadClosure { from, to } = event {
+            let road = self
+                .road_network
+                .find_road_between(from, to)
+                .ok()
+                .and_then(|road_id| self.road_network.get_road(road_id))
+                .map(|road| (road.id, road.closed));
+            let Some((road_id, closed)) = road else {
+                self.warnings.warn(SimWarning::RoadClosure, self.time, || {
+                    format!("No road from {:?} to {:?} to close", from, to)
+                });
+                return;
+            };
+            if closed {
+                return;
+            }
+            if let Err(err) = self.set_road_closed(road_id, true) {
+                self.warnings.warn(SimWarning::RoadClosure, self.time, || {
+                    format!("Couldn't close road {:?}: {:#}", road_id, err)
+                });
+                return;
+            }
+        }
+        self.active_events.push(ActiveEvent {
+            event,
+            ends_at: self.time + duration_secs,
+        });
+        if let Some(game_state) = &mut self.game_state {
+            game_state.record_event_started(event);
+        }
+    }
+
+    fn end_event(&mut self, active: ActiveEvent) {
+        if let ScenarioEvent::RoadClosure { from, to } = active.event {
+            // The road may have been removed or reopened in the meantime
+            let still_closed = self
+                .road_network
+                .find_road_between(from, to)
+                .ok()
+                .filter(|road_id| {
+                    self.road_network
+                        .get_road(*road_id)
+                        .is_some_and(|road| road.closed)
+                });
+            if let Some(road_id) = still_closed {
+                if let Err(err) = self.set_road_closed(road_id, false) {
+                    self.warnings.warn(SimWarning::RoadClosure, self.time, || {
+                        format!("Couldn't reopen road {:?}: {:#}", road_id, err)
+                    });
+                }
+            }
+        }
+        if let Some(game_state) = &mut self.game_state {
+            game_state.record_event_ended(active.event);
+        }
+    }
+}
@@ -0,0 +1,182 @@
+//! Run scoring and end-of-run summaries
+//!
+//! The score is a pure function of the statistics recorded in a
+//! [`RunSummary`], using integer arithmetic wherever possible, so anyone
+//! holding a summary (or a replay that reproduces it) can recompute and verify
+//! the score. [`SCORE_FORMULA_VERSION`] changes whenever the formula does, and
+//! best scores are only compared within the same version.
+
+#[cfg(feature = "serialization")]
+use anyhow::{Context, Result};
+#[cfg(feature = "serialization")]
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+#[cfg(feature = "serialization")]
+use std::path::Path;
+
+use super::game_state::GameState;
+
+/// Version of the scoring formula in [`RunSummary::compute_score`]
+pub const SCORE_FORMULA_VERSION: u32 = 1;
+
+/// Points per completed shop delivery
+pub const SCORE_PER_SHOP_DELIVERY: i64 = 100;
+/// Points per completed worker trip
+pub const SCORE_PER_WORKER_TRIP: i64 = 10;
+/// Money is worth one point per this many dollars
+pub const SCORE_MONEY_DIVISOR: i64 = 10;
+/// Runs that win faster than this many seconds earn a time bonus
+pub const SCORE_PAR_TIME_SECS: i64 = 600;
+/// Points per second a winning run beats [`SCORE_PAR_TIME_SECS`] by
+pub const SCORE_PER_SECOND_UNDER_PAR: i64 = 5;
+
+/// Statistics describing a finished (or stopped) run, plus its score
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+pub struct RunSummary {
+    /// Scenario the run was played on
+    pub scenario: String,
+    /// Version of the scoring formula used for `score`
+    pub formula_version: u32,
+    /// Shop deliveries completed
+    pub shop_deliveries: usize,
+    /// Worker trips completed
+    pub worker_trips: usize,
+    /// Average worker commute distance
+    pub average_commute: f32,
+    /// Healthy commute distance of the economy the run was played with
+    pub commute_healthy_distance: f32,
+    /// Money at the end of the run
+    pub money: i32,
+    /// Game time at the end of the run, in seconds
+    pub time_secs: f32,
+    /// Whether the run reached a win condition
+    pub won: bool,
+    /// Whether the run went bankrupt
+    pub lost: bool,
+    /// Final score
+    pub score: i64,
+}
+
+impl RunSummary {
+    /// Summarize a game state and compute its score
+    pub fn from_game_state(scenario: &str, game_state: &GameState) -> Self {
+        let mut summary = Self {
+            scenario: scenario.to_string(),
+            formula_version: SCORE_FORMULA_VERSION,
+            shop_deliveries: game_state.shop_deliveries_completed,
+            worker_trips: game_state.worker_trips_completed,
+            average_commute: game_state.average_commute_distance(),
+            commute_healthy_distance: game_state.economy.commute_healthy_distance,
+            money: game_state.money,
+            time_secs: game_state.time,
+            won: game_state.is_won,
+            lost: game_state.is_lost,
+            score: 0,
+        };
+        summary.score = summary.compute_score();
+        summary
+    }
+
+    /// Compute the score from the recorded statistics
+    ///
+    /// - 100 points per shop delivery and 10 per worker trip, scaled between
+    ///   50% and 100% by how close the average commute is to healthy
+    /// - 1 point per $10 held at the end; debt scores no money points
+    /// - 5 points per second a winning run finishes under a 600s par
+    pub fn compute_score(&self) -> i64 {
+        let trips = self.shop_deliveries as i64 * SCORE_PER_SHOP_DELIVERY
+            + self.worker_trips as i64 * SCORE_PER_WORKER_TRIP;
+
+        // Commute quality in whole percent, so rounding is identical everywhere
+        let commute_percent = if self.worker_trips == 0 || self.commute_healthy_distance <= 0.0 {
+            100
+        } else {
+            ((self.average_commute / self.commute_healthy_distance).clamp(0.0, 1.0) * 100.0).round()
+                as i64
+        };
+        let trip_points = trips * (50 + commute_percent / 2) / 100;
+
+        let money_points = i64::from(self.money.max(0)) / SCORE_MONEY_DIVISOR;
+
+        let time_points = if self.won {
+            (SCORE_PAR_TIME_SECS - self.time_secs.floor() as i64).max(0)
+                * SCORE_PER_SECOND_UNDER_PAR
+        } else {
+            0
+        };
+
+        trip_points + money_points + time_points
+    }
+
+    /// Check that the recorded score matches the recorded statistics
+    pub fn verify(&self) -> bool {
+        self.formula_version == SCORE_FORMULA_VERSION && self.score == self.compute_score()
+    }
+
+    /// Serialize the summary as pretty-printed JSON
+    #[cfg(feature = "serialization")]
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string_pretty(self).context("Failed to serialize run summary")
+    }
+
+    /// Write the summary to a JSON file
+    #[cfg(feature = "serialization")]
+    pub fn save_json(&self, path: &Path) -> Result<()> {
+        std::fs::write(path, self.to_json()?)
+            .with_context(|| format!("Failed to write run summary {}", path.display()))
+    }
+}
+
+/// Best run per scenario, kept on disk between runs
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+pub struct BestScores {
+    pub scenarios: BTreeMap<String, RunSummary>,
+}
+
+impl BestScores {
+    /// Load best scores, starting fresh if the file doesn't exist
+    #[cfg(feature = "serialization")]
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read best scores {}", path.display()))?;
+        serde_json::from_str(&text)
+            .with_context(|| format!("Invalid best scores file {}", path.display()))
+    }
+
+    /// Save best scores
+    #[cfg(feature = "serialization")]
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let text = serde_json::to_string_pretty(self).context("Failed to serialize best scores")?;
+        std::fs::write(path, text)
+            .with_context(|| format!("Failed to write best scores {}", path.display()))
+    }
+
+    /// Best verified run for a scenario under the current formula
+    pub fn best(&self, scenario: &str) -> Option<&RunSummary> {
+        self.scenarios
+            .get(scenario)
+            .filter(|summary| summary.verify())
+    }
+
+    /// Record a run, returning `true` if it is a new best for its scenario
+    ///
+    /// Runs whose score doesn't verify are never recorded.
+    pub fn record(&mut self, summary: &RunSummary) -> bool {
+        if !summary.verify() {
+            return false;
+        }
+        let is_best = self
+            .best(&summary.scenario)
+            .is_none_or(|best| summary.score > best.score);
+        if is_best {
+            self.scenarios
+                .insert(summary.scenario.clone(), summary.clone());
+        }
+        is_best
+    }
+}
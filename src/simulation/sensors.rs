@@ -0,0 +1,98 @@
+//! Traffic sensors
+//!
+//! In a game whose economy requires sensors, detailed traffic metrics are
+//! only known for roads the player has put a sensor on, so deciding where to
+//! measure is part of the game. A sensor covers both directions of a two-way
+//! road and stays with the road when it is split, reversed or made one- or
+//! two-way. Sandbox games and worlds without a game see every road.
+
+use anyhow::{Context, Result};
+
+use super::types::RoadId;
+use super::world::SimWorld;
+
+/// What a sensor reports about a road right now
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RoadMetrics {
+    /// Vehicles on the road
+    pub vehicles: usize,
+    /// Vehicles on the road that didn't move last tick
+    pub queued: usize,
+    /// Mean speed of the vehicles on the road (0 when it is empty)
+    pub average_speed: f32,
+    /// Vehicles that have driven onto the road since the run began
+    pub volume: u32,
+}
+
+impl SimWorld {
+    /// Whether the game only shows traffic metrics for roads with a sensor
+    pub fn sensors_required(&self) -> bool {
+        self.game_state
+            .as_ref()
+            .is_some_and(|game_state| game_state.economy.sensors_required)
+    }
+
+    /// Whether the player can see a road's traffic metrics
+    pub fn road_is_monitored(&self, road_id: RoadId) -> bool {
+        !self.sensors_required()
+            || self
+                .road_network
+                .get_road(road_id)
+                .is_some_and(|road| road.sensor)
+    }
+
+    /// Traffic metrics for a road, if it is monitored
+    pub fn road_metrics(&self, road_id: RoadId) -> Option<RoadMetrics> {
+        if !self.road_is_monitored(road_id) {
+            return None;
+        }
+        self.road_network.get_road(road_id)?;
+        let speeds: Vec<f32> = self
+            .road_network
+            .get_cars_on_road(road_id)
+            .into_iter()
+            .filter_map(|car_id| self.cars.get(&car_id))
+            .map(|car| car.current_speed)
+            .collect();
+        let average_speed = if speeds.is_empty() {
+            0.0
+        } else {
+            speeds.iter().sum::<f32>() / speeds.len() as f32
+        };
+        Some(RoadMetrics {
+            vehicles: speeds.len(),
+            queued: speeds.iter().filter(|speed| **speed == 0.0).count(),
+            average_speed,
+            volume: self.road_network.traffic_volume(road_id),
+        })
+    }
+
+    /// Put a sensor on a road or take it off, in both directions if the road
+    /// is two-way
+    pub fn set_road_sensor(&mut self, road_id: RoadId, installed: bool) -> Result<()> {
+        let reverse = self.road_network.opposite_road(road_id);
+        self.road_network
+            .set_road_sensor(road_id, installed)
+            .with_context(|| format!("Road {:?} not found", road_id))?;
+        if let Some(reverse) = reverse {
+            self.road_network.set_road_sensor(reverse, installed)?;
+        }
+        Ok(())
+    }
+
+    /// Put a sensor on a road or take it off, charging the game for a new one
+    ///
+    /// Returns `Ok(false)` if there were insufficient funds. Taking a sensor
+    /// off is free and nothing is charged for a road that already has one.
+    pub fn try_set_road_sensor(&mut self, road_id: RoadId, installed: bool) -> Result<bool> {
+        let road = self
+            .road_network
+            .get_road(road_id)
+            .with_context(|| format!("Road {:?} not found", road_id))?;
+        if installed && !road.sensor && !self.spend_for_game(|economy| economy.cost_sensor) {
+            return Ok(false);
+        }
+        self.set_road_sensor(road_id, installed)?;
+        Ok(true)
+    }
+}
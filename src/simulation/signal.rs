@@ -0,0 +1,249 @@
+//! Traffic signals
+//!
+//! A signal cycles through phases. Each phase gives a green light to some of
+//! the roads leading into its intersection for a fixed time; cars on the
+//! other incoming roads wait at the intersection until their phase comes
+//! round. Roads that no phase lists, such as ones built after the signal,
+//! aren't controlled by it.
+//!
+//! Under late truck preemption (see [`super::IntersectionRules`]) a signal
+//! can jump to the phase of a road a late truck is waiting on, cutting the
+//! current phase short. It then can't be preempted again for a whole cycle.
+//! Every signal counts its preemptions and the green time they cut from
+//! other roads, which is what preemption costs the rest of the traffic.
+
+use anyhow::{ensure, Context, Result};
+#[cfg(feature = "serialization")]
+use serde::{Deserialize, Serialize};
+
+use super::control::IntersectionControl;
+use super::types::{IntersectionId, RoadId};
+use super::world::SimWorld;
+
+/// Length of each phase of a new signal in seconds
+pub const DEFAULT_PHASE_SECS: f32 = 10.0;
+/// Shortest allowed phase in seconds
+pub const MIN_PHASE_SECS: f32 = 2.0;
+/// Longest allowed phase in seconds
+pub const MAX_PHASE_SECS: f32 = 120.0;
+
+/// One step of a signal's cycle
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+pub struct SignalPhase {
+    /// Incoming roads with a green light during this phase
+    pub green: Vec<RoadId>,
+    /// How long the phase lasts in seconds
+    pub duration: f32,
+}
+
+/// How often signals were preempted and what it cost other roads
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+pub struct SignalPreemptions {
+    /// Times a signal switched to a late truck's phase
+    pub count: u32,
+    /// Seconds of green cut short for the roads whose phase was running
+    pub green_cut_secs: f32,
+}
+
+/// A traffic signal at an intersection
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+pub struct TrafficSignal {
+    phases: Vec<SignalPhase>,
+    /// Index of the phase that is green now
+    current: usize,
+    /// Seconds the current phase has been green
+    elapsed: f32,
+    /// Seconds until it may be preempted again
+    preemption_cooldown: f32,
+    preemptions: SignalPreemptions,
+}
+
+impl TrafficSignal {
+    /// Create a signal starting at its first phase
+    pub fn new(phases: Vec<SignalPhase>) -> Result<Self> {
+        validate_phases(&phases)?;
+        Ok(Self {
+            phases,
+            current: 0,
+            elapsed: 0.0,
+            preemption_cooldown: 0.0,
+            preemptions: SignalPreemptions::default(),
+        })
+    }
+
+    pub fn phases(&self) -> &[SignalPhase] {
+        &self.phases
+    }
+
+    /// Index of the phase that is green now
+    pub fn current_phase(&self) -> usize {
+        self.current
+    }
+
+    /// Seconds until the next phase
+    pub fn time_left(&self) -> f32 {
+        (self.phases[self.current].duration - self.elapsed).max(0.0)
+    }
+
+    /// Whether cars coming from a road may enter the intersection
+    pub fn is_green(&self, road_id: RoadId) -> bool {
+        self.phases[self.current].green.contains(&road_id)
+            || !self
+                .phases
+                .iter()
+                .any(|phase| phase.green.contains(&road_id))
+    }
+
+    /// Seconds a whole cycle of the phases takes
+    pub fn cycle_secs(&self) -> f32 {
+        self.phases.iter().map(|phase| phase.duration).sum()
+    }
+
+    /// Preemptions so far and the green they cut short
+    pub fn preemptions(&self) -> SignalPreemptions {
+        self.preemptions
+    }
+
+    /// Switch straight to the phase giving a road its green, for a late truck
+    /// waiting on it, returning whether it switched
+    ///
+    /// A road that is green already or that no phase lists needs no switch,
+    /// and a signal preempted less than a cycle ago won't switch again.
+    pub fn preempt(&mut self, road_id: RoadId) -> bool {
+        if self.is_green(road_id) || self.preemption_cooldown > 0.0 {
+            return false;
+        }
+        let Some(phase) = self
+            .phases
+            .iter()
+            .position(|phase| phase.green.contains(&road_id))
+        else {
+            return false;
+        };
+        self.preemptions.count += 1;
+        self.preemptions.green_cut_secs += self.time_left();
+        self.current = phase;
+        self.elapsed = 0.0;
+        self.preemption_cooldown = self.cycle_secs();
+        true
+    }
+
+    /// Replace the phases, keeping the cycle's place where possible
+    pub fn set_phases(&mut self, phases: Vec<SignalPhase>) -> Result<()> {
+        validate_phases(&phases)?;
+        self.phases = phases;
+        if self.current >= self.phases.len() {
+            self.current = 0;
+            self.elapsed = 0.0;
+        }
+        self.elapsed = self.elapsed.min(self.phases[self.current].duration);
+        Ok(())
+    }
+
+    /// Advance the cycle
+    pub fn update(&mut self, delta_secs: f32) {
+        self.preemption_cooldown = (self.preemption_cooldown - delta_secs).max(0.0);
+        self.elapsed += delta_secs;
+        while self.elapsed >= self.phases[self.current].duration {
+            self.elapsed -= self.phases[self.current].duration;
+            self.current = (self.current + 1) % self.phases.len();
+        }
+    }
+}
+
+/// Check that phases make a usable signal cycle
+pub fn validate_phases(phases: &[SignalPhase]) -> Result<()> {
+    ensure!(!phases.is_empty(), "A signal needs at least one phase");
+    for (index, phase) in phases.iter().enumerate() {
+        ensure!(
+            (MIN_PHASE_SECS..=MAX_PHASE_SECS).contains(&phase.duration),
+            "Phase {} lasts {}s; phases must last {}-{}s",
+            index + 1,
+            phase.duration,
+            MIN_PHASE_SECS,
+            MAX_PHASE_SECS
+        );
+    }
+    Ok(())
+}
+
+/// Apply the phase durations of `from` to `to`, matching phases by position
+///
+/// Lets timings be copied between intersections whose roads differ. Extra
+/// phases in `to` take the duration of the last phase in `from`.
+pub fn copy_timings(from: &[SignalPhase], to: &[SignalPhase]) -> Vec<SignalPhase> {
+    to.iter()
+        .enumerate()
+        .map(|(index, phase)| SignalPhase {
+            green: phase.green.clone(),
+            duration: from
+                .get(index)
+                .or(from.last())
+                .map_or(phase.duration, |source| source.duration),
+        })
+        .collect()
+}
+
+impl SimWorld {
+    /// Phases for a new signal: one per road leading into the intersection,
+    /// in order of the direction they arrive from
+    pub fn default_signal_phases(&self, intersection_id: IntersectionId) -> Vec<SignalPhase> {
+        let mut incoming: Vec<_> = self
+            .road_network
+            .roads()
+            .values()
+            .filter(|road| road.end_intersection == intersection_id)
+            .map(|road| (road.angle, road.id))
+            .collect();
+        incoming.sort_by(|(a, _), (b, _)| a.total_cmp(b));
+        incoming
+            .into_iter()
+            .map(|(_, road_id)| SignalPhase {
+                green: vec![road_id],
+                duration: DEFAULT_PHASE_SECS,
+            })
+            .collect()
+    }
+
+    /// Preemptions for late trucks across all signals
+    pub fn signal_preemptions(&self) -> SignalPreemptions {
+        self.intersections
+            .values()
+            .filter_map(|intersection| intersection.signal.as_ref())
+            .fold(SignalPreemptions::default(), |total, signal| {
+                let preemptions = signal.preemptions();
+                SignalPreemptions {
+                    count: total.count + preemptions.count,
+                    green_cut_secs: total.green_cut_secs + preemptions.green_cut_secs,
+                }
+            })
+    }
+
+    /// Add, change or (with `None`) remove the signal at an intersection,
+    /// making it (or no longer) a [traffic light](IntersectionControl::TrafficLight)
+    pub fn set_signal(
+        &mut self,
+        intersection_id: IntersectionId,
+        phases: Option<Vec<SignalPhase>>,
+    ) -> Result<()> {
+        let intersection = self
+            .intersections
+            .get_mut(&intersection_id)
+            .with_context(|| format!("Intersection {:?} not found", intersection_id))?;
+        match (phases, &mut intersection.signal) {
+            (None, signal) => *signal = None,
+            (Some(phases), Some(signal)) => signal.set_phases(phases)?,
+            (Some(phases), signal) => *signal = Some(TrafficSignal::new(phases)?),
+        }
+        // The signal is the intersection's control while it has one
+        if intersection.signal.is_some() {
+            intersection.control = IntersectionControl::TrafficLight;
+        } else if intersection.control == IntersectionControl::TrafficLight {
+            intersection.control = IntersectionControl::Uncontrolled;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,158 @@
+//! Suggestions for where to put traffic signals or roundabouts
+//!
+//! Every second a vehicle spends stopped on a road, other than broken down
+//! or in a collision, is counted as waiting at the intersection the road
+//! leads into. Intersections without a signal where vehicles from at least
+//! two roads have waited are ranked by how much waiting a signal or
+//! roundabout could save. Busy intersections with three or more roads
+//! leading in are suggested a signal; quieter ones a roundabout. The saving
+//! is a rough estimate: a fixed share of the waiting measured so far, per
+//! minute of simulation.
+
+use super::types::{IntersectionId, Position, RoadId};
+use super::world::SimWorld;
+
+/// Share of the waiting a signal is expected to save
+pub const SIGNAL_DELAY_REDUCTION: f32 = 0.4;
+
+/// Share of the waiting a roundabout is expected to save
+pub const ROUNDABOUT_DELAY_REDUCTION: f32 = 0.25;
+
+/// Vehicles per minute arriving at an intersection above which a signal
+/// handles it better than a roundabout
+pub const SIGNAL_MIN_VEHICLES_PER_MINUTE: f32 = 20.0;
+
+/// Seconds of waiting an intersection needs before it is suggested
+pub const MIN_SUGGESTION_WAIT_SECS: f32 = 5.0;
+
+/// What to put at an intersection
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Treatment {
+    Signal,
+    Roundabout,
+}
+
+impl Treatment {
+    pub fn label(self) -> &'static str {
+        match self {
+            Treatment::Signal => "signal",
+            Treatment::Roundabout => "roundabout",
+        }
+    }
+
+    /// Share of the waiting this treatment is expected to save
+    pub fn delay_reduction(self) -> f32 {
+        match self {
+            Treatment::Signal => SIGNAL_DELAY_REDUCTION,
+            Treatment::Roundabout => ROUNDABOUT_DELAY_REDUCTION,
+        }
+    }
+}
+
+/// An intersection that would benefit from a signal or roundabout
+#[derive(Debug, Clone, PartialEq)]
+pub struct SignalSuggestion {
+    pub intersection: IntersectionId,
+    pub position: Position,
+    pub treatment: Treatment,
+    /// Roads leading into the intersection
+    pub approaches: usize,
+    /// Seconds vehicles have spent waiting to get in, all together
+    pub wait_secs: f32,
+    /// Vehicles that have driven onto the roads leading in
+    pub vehicles: u32,
+    /// Estimated seconds of waiting saved per minute of simulation
+    pub projected_savings_per_minute: f32,
+}
+
+impl SignalSuggestion {
+    /// Mean wait of a vehicle arriving at the intersection
+    pub fn delay_per_vehicle(&self) -> f32 {
+        self.wait_secs / self.vehicles.max(1) as f32
+    }
+}
+
+impl SimWorld {
+    /// Count the time stopped vehicles spend waiting on each road
+    pub(super) fn record_approach_waits(&mut self, delta_secs: f32) {
+        for car in self.cars.values() {
+            if car.current_speed > 0.0
+                || car.stalled_secs > 0.0
+                || car.path.is_empty()
+                || car.queued_at_start
+            {
+                continue;
+            }
+            *self.approach_waits.entry(car.current_road).or_default() += delta_secs;
+        }
+    }
+
+    /// Seconds vehicles have spent waiting on a road to get into the
+    /// intersection at its end
+    pub fn approach_wait_secs(&self, road_id: RoadId) -> f32 {
+        self.approach_waits.get(&road_id).copied().unwrap_or(0.0)
+    }
+
+    /// Intersections without a signal, ranked by the waiting a signal or
+    /// roundabout is expected to save, best first
+    pub fn signal_suggestions(&self, limit: usize) -> Vec<SignalSuggestion> {
+        let minutes = self.time / 60.0;
+        if minutes <= 0.0 {
+            return Vec::new();
+        }
+        let mut suggestions: Vec<SignalSuggestion> = self
+            .intersections
+            .values()
+            .filter(|intersection| intersection.signal.is_none())
+            .filter_map(|intersection| {
+                let approaches: Vec<RoadId> = self
+                    .road_network
+                    .roads()
+                    .values()
+                    .filter(|road| road.end_intersection == intersection.id)
+                    .map(|road| road.id)
+                    .collect();
+                let waited = approaches
+                    .iter()
+                    .filter(|road_id| self.approach_wait_secs(**road_id) > 0.0)
+                    .count();
+                let wait_secs: f32 = approaches
+                    .iter()
+                    .map(|road_id| self.approach_wait_secs(*road_id))
+                    .sum();
+                // A single stream of traffic has nothing to give way to
+                if waited < 2 || wait_secs < MIN_SUGGESTION_WAIT_SECS {
+                    return None;
+                }
+                let vehicles: u32 = approaches
+                    .iter()
+                    .map(|road_id| self.road_network.traffic_volume(*road_id))
+                    .sum();
+                let treatment = if approaches.len() >= 3
+                    && vehicles as f32 / minutes >= SIGNAL_MIN_VEHICLES_PER_MINUTE
+                {
+                    Treatment::Signal
+                } else {
+                    Treatment::Roundabout
+                };
+                Some(SignalSuggestion {
+                    intersection: intersection.id,
+                    position: intersection.position,
+                    treatment,
+                    approaches: approaches.len(),
+                    wait_secs,
+                    vehicles,
+                    projected_savings_per_minute: wait_secs / minutes
+                        * treatment.delay_reduction(),
+                })
+            })
+            .collect();
+        suggestions.sort_by(|a, b| {
+            b.projected_savings_per_minute
+                .total_cmp(&a.projected_savings_per_minute)
+                .then(a.intersection.0 .0.cmp(&b.intersection.0 .0))
+        });
+        suggestions.truncate(limit);
+        suggestions
+    }
+}
@@ -0,0 +1,115 @@
+//! Snapshots of a running world
+//!
+//! A save (see [`super::SaveFile`]) keeps how a city was built and starts
+//! its traffic over. A snapshot keeps the whole world instead, serialized
+//! as it is: the roads, intersections and their signals, buildings and what
+//! they are waiting for, vehicles part way along their routes, workers on
+//! foot, queued and waiting trips, scenario events under way, the game's
+//! progress and the simulation time. Loading one carries on exactly where
+//! the world left off, so a long game can be paused and resumed later.
+//!
+//! Taking a snapshot leaves the world as it was. Background routing threads
+//! can't be saved, but the routes asked of them are: a loaded world starts
+//! as many threads and asks them again, so its vehicles set off when they
+//! would have. The RNG's state can't be serialized either, so the snapshot
+//! keeps its seed and how far along its stream it is (see [`SimRng`]), and
+//! the world and the world loaded from its snapshot go on to make the same
+//! random choices. Statistics, logged warnings and the finished vehicles
+//! kept for reuse start over.
+//!
+//! A snapshot is the simulation's internals, which change from one version
+//! to the next, so unlike saves it isn't upgraded: a snapshot only loads in
+//! a build with the same [`SNAPSHOT_VERSION`].
+
+use std::path::Path;
+
+use anyhow::{ensure, Context, Result};
+use serde::{Deserialize, Serialize};
+
+use super::rng::SimRng;
+use super::routing::RouteRequest;
+use super::save::write_atomically;
+use super::world::SimWorld;
+
+/// Version of the format snapshots are written in
+pub const SNAPSHOT_VERSION: u32 = 12;
+
+/// A snapshot being written
+#[derive(Serialize)]
+struct SnapshotRef<'a> {
+    version: u32,
+    /// Seed of the world's RNG and the words drawn from it, if it has one
+    rng: Option<(u64, u64)>,
+    /// Background routing threads (0 when routing inline)
+    route_workers: usize,
+    /// Routes the threads were finding
+    routes_in_flight: &'a [RouteRequest],
+    /// Routes asked for since, to be sent to the threads next
+    routes_queued: &'a [RouteRequest],
+    world: &'a SimWorld,
+}
+
+/// A snapshot being read
+#[derive(Deserialize)]
+struct Snapshot {
+    version: u32,
+    rng: Option<(u64, u64)>,
+    route_workers: usize,
+    routes_in_flight: Vec<RouteRequest>,
+    routes_queued: Vec<RouteRequest>,
+    world: SimWorld,
+}
+
+impl SimWorld {
+    /// Write the world's whole state to a JSON file, atomically
+    pub fn save_to_file(&self, path: &Path) -> Result<()> {
+        let text = self.to_snapshot_json()?;
+        write_atomically(path, &text)
+            .with_context(|| format!("Failed to write snapshot {}", path.display()))
+    }
+
+    /// Load a world from a snapshot written by [`Self::save_to_file`]
+    pub fn load_from_file(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read snapshot {}", path.display()))?;
+        Self::from_snapshot_json(&text)
+            .with_context(|| format!("Invalid snapshot {}", path.display()))
+    }
+
+    /// Serialize the world's whole state to JSON text
+    pub fn to_snapshot_json(&self) -> Result<String> {
+        let (routes_in_flight, routes_queued) = self.route_requests();
+        serde_json::to_string(&SnapshotRef {
+            version: SNAPSHOT_VERSION,
+            rng: self.rng.as_ref().map(SimRng::position),
+            route_workers: self.route_workers(),
+            routes_in_flight,
+            routes_queued,
+            world: self,
+        })
+        .context("Failed to serialize snapshot")
+    }
+
+    /// Parse a snapshot's JSON text
+    pub fn from_snapshot_json(text: &str) -> Result<Self> {
+        let snapshot: Snapshot = serde_json::from_str(text).context("Failed to parse snapshot")?;
+        ensure!(
+            snapshot.version == SNAPSHOT_VERSION,
+            "The snapshot is version {} but this build reads version {}",
+            snapshot.version,
+            SNAPSHOT_VERSION
+        );
+        let mut world = snapshot.world;
+        world.rng = snapshot
+            .rng
+            .map(|(seed, words)| SimRng::resume(seed, words));
+        if snapshot.route_workers > 0 {
+            world.resume_route_requests(
+                snapshot.route_workers,
+                snapshot.routes_in_flight,
+                snapshot.routes_queued,
+            );
+        }
+        Ok(world)
+    }
+}
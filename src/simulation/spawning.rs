@@ -0,0 +1,130 @@
+//! Spawning vehicles in batches
+//!
+//! [`SimWorld::spawn_vehicles`] spawns many vehicles in one pass. Vehicles
+//! of the same type leaving the same intersection share one search for
+//! their routes, and every route is found against the traffic on the roads
+//! before the batch, so a burst of spawns costs little more than its
+//! searches.
+
+use anyhow::{Context, Result};
+#[cfg(feature = "serialization")]
+use serde::{Deserialize, Serialize};
+
+use super::car_manager;
+use super::events::SimEvent;
+use super::types::{ApartmentId, CarId, FactoryId, IntersectionId, TripType, VehicleType};
+use super::world::SimWorld;
+
+/// A vehicle to spawn with [`SimWorld::spawn_vehicles`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+pub struct VehicleSpawn {
+    pub from: IntersectionId,
+    pub to: IntersectionId,
+    pub vehicle_type: VehicleType,
+    pub trip_type: TripType,
+    /// The apartment the vehicle belongs to (for cars)
+    pub origin_apartment: Option<ApartmentId>,
+    /// The factory the vehicle belongs to (for trucks)
+    pub origin_factory: Option<FactoryId>,
+}
+
+impl VehicleSpawn {
+    /// A vehicle that doesn't belong to any building
+    pub fn new(
+        from: IntersectionId,
+        to: IntersectionId,
+        vehicle_type: VehicleType,
+        trip_type: TripType,
+    ) -> Self {
+        Self {
+            from,
+            to,
+            vehicle_type,
+            trip_type,
+            origin_apartment: None,
+            origin_factory: None,
+        }
+    }
+}
+
+impl SimWorld {
+    /// Spawn a batch of vehicles, returning each one's ID or the reason it
+    /// couldn't spawn, in order
+    ///
+    /// Vehicles set off straight away, even when routes are otherwise found
+    /// in the background.
+    pub fn spawn_vehicles(&mut self, batch: &[VehicleSpawn]) -> Vec<Result<CarId>> {
+        // One search per origin and vehicle type, in the order they first
+        // appear
+        let mut searches: Vec<(IntersectionId, VehicleType, Vec<IntersectionId>)> = Vec::new();
+        let mut search_of = Vec::with_capacity(batch.len());
+        for spawn in batch {
+            let index = searches
+                .iter()
+                .position(|(from, vehicle_type, _)| {
+                    *from == spawn.from && *vehicle_type == spawn.vehicle_type
+                })
+                .unwrap_or_else(|| {
+                    searches.push((spawn.from, spawn.vehicle_type, Vec::new()));
+                    searches.len() - 1
+                });
+            search_of.push((index, searches[index].2.len()));
+            searches[index].2.push(spawn.to);
+        }
+        let mut routes: Vec<Vec<Option<Vec<IntersectionId>>>> = searches
+            .iter()
+            .map(|(from, vehicle_type, ends)| self.find_routes_from(*from, ends, *vehicle_type))
+            .collect();
+
+        batch
+            .iter()
+            .zip(search_of)
+            .map(|(spawn, (search, end))| {
+                let route = routes[search][end].take();
+                self.spawn_routed_vehicle(spawn, route)
+            })
+            .collect()
+    }
+
+    /// Spawn a vehicle along a route already found for it
+    fn spawn_routed_vehicle(
+        &mut self,
+        spawn: &VehicleSpawn,
+        route: Option<Vec<IntersectionId>>,
+    ) -> Result<CarId> {
+        let (speed, variant) = self.roll_vehicle_speed(
+            spawn.vehicle_type,
+            spawn.origin_apartment,
+            spawn.origin_factory,
+        );
+        let personality = self.roll_personality();
+        let speed = speed * personality.profile.speed_factor;
+        let car_id = CarId(self.next_sim_id());
+
+        let mut car = car_manager::spawn_waiting_vehicle(
+            car_id,
+            spawn.from,
+            spawn.to,
+            spawn.vehicle_type,
+            spawn.trip_type,
+            spawn.origin_apartment,
+            spawn.origin_factory,
+            &self.road_network,
+            &mut self.car_pool,
+            speed,
+        )?;
+        let path = route.context("No path found to destination")?;
+        car_manager::set_off(&mut car, path, &self.road_network)?;
+        car.variant = variant;
+        car.personality = personality;
+
+        self.cars.insert(car_id, car);
+        self.raise(SimEvent::CarSpawned {
+            car: car_id,
+            vehicle_type: spawn.vehicle_type,
+        });
+        self.join_or_queue(car_id);
+        Ok(car_id)
+    }
+}
@@ -0,0 +1,442 @@
+//! Special buildings: schools, hospitals, depots and rail terminals
+//!
+//! Special buildings don't employ workers or take deliveries. Instead each
+//! generates its own traffic following a [`TripProfile`] over the simulated
+//! day. Schools draw parents from apartments in sharp morning and afternoon
+//! peaks; hospitals draw a steady trickle of visitors and now and then send
+//! an ambulance out to an apartment and back. Ambulances run red lights.
+//! Depots draw no visitors and send their tow truck out to breakdowns (see
+//! [`super::towing`]). Rail terminals draw no visitors either; their van
+//! carries raw materials to factories (see [`super::freight`]).
+
+use anyhow::Context;
+#[cfg(feature = "serialization")]
+use serde::{Deserialize, Serialize};
+
+use super::arrivals::ReturnTrip;
+use super::freight::TERMINAL_SHIPMENT_SECS;
+use super::types::{
+    ApartmentId, CarId, FactoryId, IntersectionId, SpecialBuildingId, TripType, VehicleType,
+    SECONDS_PER_DAY,
+};
+use super::warnings::SimWarning;
+use super::world::SimWorld;
+
+/// Kind of special building
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serialization", serde(rename_all = "lowercase"))]
+pub enum SpecialKind {
+    School,
+    Hospital,
+    /// Tow truck depot
+    Depot,
+    /// Rail freight terminal supplying factories
+    Terminal,
+}
+
+impl SpecialKind {
+    /// Trip profile a new building of this kind starts with
+    pub fn profile(self) -> TripProfile {
+        match self {
+            SpecialKind::School => TripProfile::school(),
+            SpecialKind::Hospital => TripProfile::hospital(),
+            SpecialKind::Depot => TripProfile::depot(),
+            SpecialKind::Terminal => TripProfile::terminal(),
+        }
+    }
+}
+
+/// A busy period in a trip profile
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+pub struct TripPeak {
+    /// Hour of the day the peak starts
+    pub start_hour: f32,
+    /// Hour of the day the peak ends; must be after `start_hour`
+    pub end_hour: f32,
+    /// Extra trips per in-game hour during the peak
+    pub trips_per_hour: f32,
+}
+
+/// How many trips a special building generates at each time of day
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+pub struct TripProfile {
+    /// Trips per in-game hour at any time of day
+    pub base_trips_per_hour: f32,
+    /// Busy periods adding to the base rate
+    pub peaks: Vec<TripPeak>,
+    /// Ambulance runs per in-game hour
+    pub emergencies_per_hour: f32,
+}
+
+impl TripProfile {
+    /// Drop-offs before school and pick-ups after it, and nothing in between
+    pub fn school() -> Self {
+        Self {
+            base_trips_per_hour: 0.0,
+            peaks: vec![
+                TripPeak {
+                    start_hour: 7.5,
+                    end_hour: 9.0,
+                    trips_per_hour: 24.0,
+                },
+                TripPeak {
+                    start_hour: 15.0,
+                    end_hour: 16.5,
+                    trips_per_hour: 24.0,
+                },
+            ],
+            emergencies_per_hour: 0.0,
+        }
+    }
+
+    /// Visitors around the clock plus the odd emergency
+    pub fn hospital() -> Self {
+        Self {
+            base_trips_per_hour: 3.0,
+            peaks: Vec::new(),
+            emergencies_per_hour: 0.5,
+        }
+    }
+
+    /// No visitors; the tow truck only goes out to breakdowns
+    pub fn depot() -> Self {
+        Self {
+            base_trips_per_hour: 0.0,
+            peaks: Vec::new(),
+            emergencies_per_hour: 0.0,
+        }
+    }
+
+    /// No visitors; the van only goes out to factories
+    pub fn terminal() -> Self {
+        Self::depot()
+    }
+
+    /// Visitor trips per in-game hour at an hour of the day
+    pub fn trips_per_hour(&self, hour: f32) -> f32 {
+        self.base_trips_per_hour
+            + self
+                .peaks
+                .iter()
+                .filter(|peak| (peak.start_hour..peak.end_hour).contains(&hour))
+                .map(|peak| peak.trips_per_hour)
+                .sum::<f32>()
+    }
+}
+
+/// A school, hospital, depot or rail terminal in the simulation
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+pub struct SimSpecialBuilding {
+    pub id: SpecialBuildingId,
+    pub intersection_id: IntersectionId,
+    pub kind: SpecialKind,
+    pub profile: TripProfile,
+    /// Visitor trips due but not yet started
+    trip_backlog: f32,
+    /// Share of the next emergency that has built up
+    emergency_backlog: f32,
+    /// The hospital's ambulance, while it is out
+    pub ambulance: Option<CarId>,
+    /// Visitors who have been and gone home
+    pub visits_completed: usize,
+    /// Ambulance runs back at the hospital
+    pub emergency_runs_completed: usize,
+    /// The depot's tow truck, while it is out
+    pub tow_truck: Option<CarId>,
+    /// Broken-down vehicle the tow truck is on its way to
+    pub towing: Option<CarId>,
+    /// Breakdowns the depot has cleared
+    pub tows_completed: usize,
+    /// Loads of raw materials waiting at the terminal
+    pub materials: u32,
+    /// Seconds until the next train unloads at the terminal
+    pub next_shipment_in: f32,
+    /// The terminal's van, while it is out
+    pub van: Option<CarId>,
+    /// Factory the van is taking materials to, and how many loads
+    pub supplying: Option<(FactoryId, u32)>,
+    /// Van loads the terminal has delivered to factories
+    pub supplies_delivered: usize,
+}
+
+impl SimSpecialBuilding {
+    pub fn new(id: SpecialBuildingId, intersection_id: IntersectionId, kind: SpecialKind) -> Self {
+        Self {
+            id,
+            intersection_id,
+            kind,
+            profile: kind.profile(),
+            trip_backlog: 0.0,
+            emergency_backlog: 0.0,
+            ambulance: None,
+            visits_completed: 0,
+            emergency_runs_completed: 0,
+            tow_truck: None,
+            towing: None,
+            tows_completed: 0,
+            materials: 0,
+            next_shipment_in: TERMINAL_SHIPMENT_SECS,
+            van: None,
+            supplying: None,
+            supplies_delivered: 0,
+        }
+    }
+}
+
+impl SimWorld {
+    /// Add a special building at an intersection
+    pub fn add_special_building(
+        &mut self,
+        intersection_id: IntersectionId,
+        kind: SpecialKind,
+    ) -> SpecialBuildingId {
+        let id = SpecialBuildingId(self.next_sim_id());
+        let building = SimSpecialBuilding::new(id, intersection_id, kind);
+        self.special_buildings.insert(id, building);
+        self.changes.mark_layout();
+        id
+    }
+
+    /// Add a special building with game cost checking
+    /// Returns Some(id) if successful, None if insufficient funds
+    pub fn try_add_special_building(
+        &mut self,
+        intersection_id: IntersectionId,
+        kind: SpecialKind,
+    ) -> Option<SpecialBuildingId> {
+        let paid = self.spend_for_game(|economy| match kind {
+            SpecialKind::School => economy.cost_school,
+            SpecialKind::Hospital => economy.cost_hospital,
+            SpecialKind::Depot => economy.cost_depot,
+            SpecialKind::Terminal => economy.cost_terminal,
+        });
+        if !paid {
+            return None;
+        }
+        Some(self.add_special_building(intersection_id, kind))
+    }
+
+    /// Remove a special building; its vehicles finish their trips
+    pub fn remove_special_building(&mut self, id: SpecialBuildingId) {
+        self.special_buildings.remove(&id);
+        self.changes.mark_layout();
+    }
+
+    /// Start the visitor trips and ambulance runs that have come due
+    pub(super) fn update_special_buildings(&mut self, delta_secs: f32) {
+        let hour = self.time_of_day();
+        let hours = delta_secs / SECONDS_PER_DAY * 24.0;
+        let homes: Vec<IntersectionId> = self
+            .apartments
+            .values()
+            .map(|apartment| apartment.intersection_id)
+            .collect();
+        let ids: Vec<SpecialBuildingId> = self.special_buildings.keys().copied().collect();
+
+        for id in ids {
+            let Some(building) = self.special_buildings.get_mut(&id) else {
+                continue;
+            };
+            // The ambulance may have been removed with a road it was on
+            if building
+                .ambulance
+                .is_some_and(|ambulance| !self.cars.contains_key(&ambulance))
+            {
+                building.ambulance = None;
+            }
+            building.trip_backlog += building.profile.trips_per_hour(hour) * hours;
+            // Emergencies wait for the ambulance rather than queueing up
+            building.emergency_backlog =
+                (building.emergency_backlog + building.profile.emergencies_per_hour * hours)
+                    .min(1.0);
+            let mut trips = 0;
+            while building.trip_backlog >= 1.0 {
+                building.trip_backlog -= 1.0;
+                trips += 1;
+            }
+            let emergency = building.emergency_backlog >= 1.0 && building.ambulance.is_none();
+            if emergency {
+                building.emergency_backlog = 0.0;
+            }
+            let site = building.intersection_id;
+
+            for _ in 0..trips {
+                let Some(&home) = self.choose_random(&homes) else {
+                    break;
+                };
+                let apartment = self
+                    .apartments
+                    .values()
+                    .find(|apartment| apartment.intersection_id == home)
+                    .map(|apartment| apartment.id);
+                // Unreachable visitors stay home
+                if let Ok(car_id) = self.spawn_vehicle(
+                    home,
+                    site,
+                    VehicleType::Car,
+                    TripType::Outbound,
+                    apartment,
+                    None,
+                ) {
+                    self.tag_special_vehicle(car_id, id);
+                }
+            }
+
+            if emergency {
+                let Some(&scene) = self.choose_random(&homes) else {
+                    continue;
+                };
+                match self.spawn_vehicle(
+                    site,
+                    scene,
+                    VehicleType::Ambulance,
+                    TripType::Outbound,
+                    None,
+                    None,
+                ) {
+                    Ok(ambulance) => {
+                        self.tag_special_vehicle(ambulance, id);
+                        if let Some(building) = self.special_buildings.get_mut(&id) {
+                            building.ambulance = Some(ambulance);
+                        }
+                    }
+                    Err(e) => {
+                        self.warnings.warn(SimWarning::AmbulanceStuck, self.time, || {
+                            format!("Ambulance couldn't leave hospital {:?}: {:#}", id, e)
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    fn tag_special_vehicle(&mut self, car_id: CarId, id: SpecialBuildingId) {
+        if let Some(car) = self.cars.get_mut(&car_id) {
+            car.origin_special = Some(id);
+        }
+    }
+
+    /// Handle a special building's vehicle reaching its destination
+    ///
+    /// Outbound vehicles head back where they came from, a tow truck after
+    /// clearing its breakdown and a van after unloading, once every arrival
+    /// has been resolved; returning ones are counted and removed.
+    pub(super) fn resolve_special_arrival(
+        &mut self,
+        car_id: CarId,
+        dest: IntersectionId,
+    ) -> Option<ReturnTrip> {
+        let car = self.cars.get(&car_id)?;
+        let (origin_special, trip_type, vehicle_type, origin_apartment) = (
+            car.origin_special,
+            car.trip_type,
+            car.vehicle_type,
+            car.origin_apartment,
+        );
+        self.remove_car(car_id);
+        let id = origin_special?;
+
+        match trip_type {
+            TripType::Outbound => {
+                if vehicle_type == VehicleType::TowTruck {
+                    let towing = self
+                        .special_buildings
+                        .get(&id)
+                        .and_then(|depot| depot.towing);
+                    if let Some(towing) = towing {
+                        self.clear_breakdown(id, towing);
+                    }
+                }
+                if vehicle_type == VehicleType::Van {
+                    self.unload_van(id);
+                }
+                let home = match vehicle_type {
+                    VehicleType::Ambulance | VehicleType::TowTruck | VehicleType::Van => self
+                        .special_buildings
+                        .get(&id)
+                        .map(|building| building.intersection_id),
+                    _ => origin_apartment
+                        .and_then(|apartment_id| self.apartments.get(&apartment_id))
+                        .map(|apartment| apartment.intersection_id),
+                };
+                Some(ReturnTrip::Special {
+                    arrived: car_id,
+                    from: dest,
+                    home,
+                    building: id,
+                    vehicle_type,
+                    apartment: origin_apartment,
+                })
+            }
+            TripType::Return => {
+                if let Some(building) = self.special_buildings.get_mut(&id) {
+                    match vehicle_type {
+                        VehicleType::Ambulance => {
+                            building.ambulance = None;
+                            building.emergency_runs_completed += 1;
+                        }
+                        VehicleType::TowTruck => building.tow_truck = None,
+                        VehicleType::Van => building.van = None,
+                        _ => building.visits_completed += 1,
+                    }
+                }
+                None
+            }
+        }
+    }
+
+    /// Send a special building's vehicle back from where it arrived,
+    /// taking over the building's reference to it
+    pub(super) fn set_off_special_return(
+        &mut self,
+        arrived: CarId,
+        from: IntersectionId,
+        home: Option<IntersectionId>,
+        id: SpecialBuildingId,
+        vehicle_type: VehicleType,
+        origin_apartment: Option<ApartmentId>,
+    ) {
+        let returning = home.context("Nowhere to return to").and_then(|home| {
+            self.spawn_vehicle(
+                from,
+                home,
+                vehicle_type,
+                TripType::Return,
+                origin_apartment,
+                None,
+            )
+        });
+        match returning {
+            Ok(new_car_id) => {
+                self.tag_special_vehicle(new_car_id, id);
+                if let Some(building) = self.special_buildings.get_mut(&id) {
+                    match vehicle_type {
+                        VehicleType::Ambulance => building.ambulance = Some(new_car_id),
+                        VehicleType::TowTruck => building.tow_truck = Some(new_car_id),
+                        VehicleType::Van => building.van = Some(new_car_id),
+                        _ => {}
+                    }
+                }
+            }
+            // The trip ends here; an ambulance, tow truck or van is freed for
+            // the next call
+            Err(_) => {
+                if let Some(building) = self.special_buildings.get_mut(&id) {
+                    if building.ambulance == Some(arrived) {
+                        building.ambulance = None;
+                    }
+                    if building.tow_truck == Some(arrived) {
+                        building.tow_truck = None;
+                    }
+                    if building.van == Some(arrived) {
+                        building.van = None;
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,200 @@
+//! Keeping traffic moving through rush hour
+//!
+//! An economy can include a [`SpeedGoal`], which asks the player to keep the
+//! network-average speed of the vehicles on the roads at or above
+//! `min_average_speed` between `start_hour` and `end_hour` of every in-game
+//! day. The average over each window is kept in a [`WindowedMean`], weighted
+//! by time, and judged when the window closes: a rush hour that was kept
+//! moving counts towards the `rush_hours` the goal has to be met for before
+//! the game can be won, and one that wasn't loses the game. Ticks with no
+//! vehicle driving a road don't count towards the average, and a window in
+//! which none drove at all isn't judged.
+
+use anyhow::{ensure, Result};
+#[cfg(feature = "serialization")]
+use serde::{Deserialize, Serialize};
+
+use super::world::SimWorld;
+
+/// Average of a value over the time it was sampled for
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+pub struct WindowedMean {
+    /// Sum of the samples, each times the seconds it held for
+    total: f32,
+    /// Seconds sampled
+    secs: f32,
+}
+
+impl WindowedMean {
+    /// Add a value that held for `secs`
+    pub fn add(&mut self, value: f32, secs: f32) {
+        self.total += value * secs;
+        self.secs += secs;
+    }
+
+    /// Seconds sampled so far
+    pub fn secs(&self) -> f32 {
+        self.secs
+    }
+
+    /// The time-weighted average, if anything was sampled
+    pub fn mean(&self) -> Option<f32> {
+        (self.secs > 0.0).then(|| self.total / self.secs)
+    }
+}
+
+/// A network-average speed to keep up through rush hour
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serialization", serde(default, deny_unknown_fields))]
+pub struct SpeedGoal {
+    /// Hour of the day (0-24) the window opens
+    pub start_hour: f32,
+    /// Hour of the day (0-24) the window closes; earlier than `start_hour`
+    /// for a window that runs past midnight
+    pub end_hour: f32,
+    /// Lowest average speed, in world units per second, that meets the goal
+    pub min_average_speed: f32,
+    /// Rush hours the goal has to be met for before the game can be won
+    pub rush_hours: u32,
+}
+
+impl Default for SpeedGoal {
+    fn default() -> Self {
+        Self {
+            start_hour: 8.0,
+            end_hour: 9.0,
+            min_average_speed: 2.0,
+            rush_hours: 1,
+        }
+    }
+}
+
+impl SpeedGoal {
+    /// Reject windows that never open and speeds that can't be judged
+    pub fn validate(&self) -> Result<()> {
+        for (name, hour) in [("start_hour", self.start_hour), ("end_hour", self.end_hour)] {
+            ensure!(
+                (0.0..24.0).contains(&hour),
+                "speed_goal.{} must be at least 0 and less than 24 (got {})",
+                name,
+                hour
+            );
+        }
+        ensure!(
+            self.start_hour != self.end_hour,
+            "speed_goal.start_hour and end_hour must differ (both {})",
+            self.start_hour
+        );
+        ensure!(
+            self.min_average_speed > 0.0,
+            "speed_goal.min_average_speed must be positive (got {})",
+            self.min_average_speed
+        );
+        ensure!(
+            self.rush_hours > 0,
+            "speed_goal.rush_hours must be at least 1"
+        );
+        Ok(())
+    }
+
+    /// Whether the window is open at an hour of the day
+    pub fn window_contains(&self, hour: f32) -> bool {
+        if self.start_hour < self.end_hour {
+            (self.start_hour..self.end_hour).contains(&hour)
+        } else {
+            hour >= self.start_hour || hour < self.end_hour
+        }
+    }
+
+    /// Length of the window in hours
+    pub fn window_hours(&self) -> f32 {
+        (self.end_hour - self.start_hour).rem_euclid(24.0)
+    }
+}
+
+/// How the player has done at the speed goal so far
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+pub struct SpeedGoalRecord {
+    /// Average speed so far in the window that is open, if one is
+    pub window: Option<WindowedMean>,
+    /// Rush hours the goal was met for
+    pub met: u32,
+    /// Rush hours the goal was missed for
+    pub missed: u32,
+}
+
+/// The speed goal's window as it stands, for showing while it is open
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpeedGoalProgress {
+    pub goal: SpeedGoal,
+    /// Average speed so far, if any vehicle has driven yet
+    pub average: Option<f32>,
+    /// Share of the window gone, from 0 to 1
+    pub elapsed: f32,
+}
+
+impl SimWorld {
+    /// The speed goal of the game's economy, if it has one
+    pub fn speed_goal(&self) -> Option<&SpeedGoal> {
+        self.game_state
+            .as_ref()
+            .and_then(|game_state| game_state.economy.speed_goal.as_ref())
+    }
+
+    /// Average speed of the vehicles driving a road, if any are
+    pub fn network_average_speed(&self) -> Option<f32> {
+        let speeds: Vec<f32> = self
+            .cars
+            .values()
+            .filter(|car| car.speed_share(&self.road_network).is_some())
+            .map(|car| car.current_speed)
+            .collect();
+        (!speeds.is_empty()).then(|| speeds.iter().sum::<f32>() / speeds.len() as f32)
+    }
+
+    /// The speed goal's window, while it is open
+    pub fn speed_goal_progress(&self) -> Option<SpeedGoalProgress> {
+        let goal = *self.speed_goal()?;
+        let window = self.game_state.as_ref()?.speed_goal.window?;
+        let elapsed = (self.time_of_day() - goal.start_hour).rem_euclid(24.0);
+        Some(SpeedGoalProgress {
+            goal,
+            average: window.mean(),
+            elapsed: (elapsed / goal.window_hours()).min(1.0),
+        })
+    }
+
+    /// Sample the network-average speed while the goal's window is open,
+    /// and judge the window once it closes
+    pub(super) fn update_speed_goal(&mut self, delta_secs: f32) {
+        let Some(goal) = self.speed_goal().copied() else {
+            return;
+        };
+        let open = goal.window_contains(self.time_of_day());
+        let speed = if open {
+            self.network_average_speed()
+        } else {
+            None
+        };
+        let Some(game_state) = &mut self.game_state else {
+            return;
+        };
+        // Once the game is over, later rush hours don't change the outcome
+        if game_state.is_won || game_state.is_lost {
+            return;
+        }
+        if open {
+            let window = game_state.speed_goal.window.get_or_insert_default();
+            if let Some(speed) = speed {
+                window.add(speed, delta_secs);
+            }
+        } else if let Some(window) = game_state.speed_goal.window.take() {
+            if let Some(average) = window.mean() {
+                game_state.record_speed_goal(average >= goal.min_average_speed, average);
+            }
+        }
+    }
+}
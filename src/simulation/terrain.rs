@@ -0,0 +1,136 @@
+//! Water and what may be built over it
+//!
+//! A world can have water: rectangles of river or lake that roads can't
+//! cross and nothing can be built on. Only a bridge, up to
+//! [`MAX_BRIDGE_LENGTH`] long, or a ferry crossing (see [`super::ferry`]) gets
+//! traffic over it. Worlds without water build as they always have.
+
+use anyhow::{ensure, Result};
+#[cfg(feature = "serialization")]
+use serde::{Deserialize, Serialize};
+
+use super::types::{Position, RoadKind};
+use super::world::SimWorld;
+
+/// Longest span a bridge can have
+pub const MAX_BRIDGE_LENGTH: f32 = 16.0;
+
+/// The river `--river` lays down the east side of the map
+pub const RIVER: WaterRegion = WaterRegion {
+    min_x: 40.0,
+    min_z: -100.0,
+    max_x: 52.0,
+    max_z: 100.0,
+};
+
+/// A rectangle of water, aligned with the world's axes
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+pub struct WaterRegion {
+    pub min_x: f32,
+    pub min_z: f32,
+    pub max_x: f32,
+    pub max_z: f32,
+}
+
+impl WaterRegion {
+    /// The water between two opposite corners
+    pub fn new(corner: Position, opposite: Position) -> Self {
+        Self {
+            min_x: corner.x.min(opposite.x),
+            min_z: corner.z.min(opposite.z),
+            max_x: corner.x.max(opposite.x),
+            max_z: corner.z.max(opposite.z),
+        }
+    }
+
+    /// Whether a point is in the water
+    pub fn contains(&self, position: &Position) -> bool {
+        (self.min_x..=self.max_x).contains(&position.x)
+            && (self.min_z..=self.max_z).contains(&position.z)
+    }
+
+    /// Whether a straight road from `start` to `end` touches the water
+    pub fn crosses(&self, start: &Position, end: &Position) -> bool {
+        // Clip the segment against each pair of sides in turn
+        let (mut enter, mut leave) = (0.0_f32, 1.0_f32);
+        for (from, delta, min, max) in [
+            (start.x, end.x - start.x, self.min_x, self.max_x),
+            (start.z, end.z - start.z, self.min_z, self.max_z),
+        ] {
+            if delta == 0.0 {
+                if from < min || from > max {
+                    return false;
+                }
+                continue;
+            }
+            let (a, b) = ((min - from) / delta, (max - from) / delta);
+            enter = enter.max(a.min(b));
+            leave = leave.min(a.max(b));
+        }
+        enter <= leave
+    }
+
+    /// Middle of the water
+    pub fn center(&self) -> Position {
+        Position::new(
+            (self.min_x + self.max_x) / 2.0,
+            0.0,
+            (self.min_z + self.max_z) / 2.0,
+        )
+    }
+}
+
+impl SimWorld {
+    /// Add water that roads can only cross by bridge or ferry
+    pub fn add_water(&mut self, region: WaterRegion) {
+        self.water.push(region);
+        self.changes.mark_layout();
+    }
+
+    /// Whether a point is in the water
+    pub fn is_water(&self, position: &Position) -> bool {
+        self.water.iter().any(|region| region.contains(position))
+    }
+
+    /// Whether a straight road from `start` to `end` crosses water
+    pub fn crosses_water(&self, start: &Position, end: &Position) -> bool {
+        self.water.iter().any(|region| region.crosses(start, end))
+    }
+
+    /// Check something can be built at a point
+    pub(super) fn check_on_land(&self, position: &Position) -> Result<()> {
+        ensure!(
+            !self.is_water(position),
+            "Can't build on water at ({:.0}, {:.0})",
+            position.x,
+            position.z
+        );
+        Ok(())
+    }
+
+    /// Check a road of the given kind can run from `start` to `end`
+    pub(super) fn check_crossing(
+        &self,
+        start: &Position,
+        end: &Position,
+        kind: RoadKind,
+    ) -> Result<()> {
+        self.check_on_land(start)?;
+        self.check_on_land(end)?;
+        let crosses = self.crosses_water(start, end);
+        match kind {
+            RoadKind::Street => ensure!(
+                !crosses,
+                "Roads can't cross water; build a bridge or ferry instead"
+            ),
+            RoadKind::Bridge => ensure!(
+                start.distance(end) <= MAX_BRIDGE_LENGTH,
+                "Bridges can span at most {}",
+                MAX_BRIDGE_LENGTH
+            ),
+            RoadKind::Ferry => ensure!(crosses, "A ferry needs water to cross"),
+        }
+        Ok(())
+    }
+}
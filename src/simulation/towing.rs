@@ -0,0 +1,126 @@
+//! Tow trucks clearing breakdowns
+//!
+//! A broken-down vehicle waits for a tow. Each depot keeps one tow truck,
+//! and every breakdown is sent the truck, of the depots that have theirs
+//! in, estimated to get there soonest through the current traffic. Once the tow truck reaches the intersection behind the breakdown it
+//! clears the road, so the stopped vehicle and the traffic behind it can
+//! move again. Breakdowns no tow truck can reach clear by themselves after
+//! [`super::BREAKDOWN_STALL_SECS`], so building more depots shortens the
+//! wait.
+
+
+use super::render::VisualId;
+use super::special::SpecialKind;
+use super::types::{CarId, SpecialBuildingId, TripType, VehicleType};
+use super::warnings::SimWarning;
+use super::world::SimWorld;
+
+impl SimWorld {
+    /// Broken-down vehicles still waiting for a tow truck to reach them
+    pub fn breakdowns_awaiting_tow(&self) -> &[CarId] {
+        &self.awaiting_tow
+    }
+
+    /// Send idle tow trucks out to breakdowns that don't have one on the way
+    pub(super) fn dispatch_tow_trucks(&mut self) {
+        // Drop breakdowns that cleared by themselves or whose vehicle is gone
+        let cars = &self.cars;
+        self.awaiting_tow
+            .retain(|car_id| cars.get(car_id).is_some_and(|car| car.stalled_secs > 0.0));
+
+        for depot in self.special_buildings.values_mut() {
+            // The tow truck may have been removed with a road it was on
+            if depot
+                .tow_truck
+                .is_some_and(|tow_truck| !self.cars.contains_key(&tow_truck))
+            {
+                depot.tow_truck = None;
+                depot.towing = None;
+            }
+        }
+
+        for car_id in self.awaiting_tow.clone() {
+            if self
+                .special_buildings
+                .values()
+                .any(|depot| depot.towing == Some(car_id))
+            {
+                continue;
+            }
+            let Some(car) = self.cars.get(&car_id) else {
+                continue;
+            };
+            let scene = car.start_intersection;
+
+            // Depots that can reach the scene, soonest first
+            let mut depots: Vec<(f32, SpecialBuildingId)> = self
+                .special_buildings
+                .values()
+                .filter(|depot| depot.kind == SpecialKind::Depot && depot.tow_truck.is_none())
+                .filter_map(|depot| {
+                    let estimate =
+                        self.estimate_route(depot.intersection_id, scene, VehicleType::TowTruck)?;
+                    Some((estimate.secs, depot.id))
+                })
+                .collect();
+            depots.sort_by(|(a, _), (b, _)| a.total_cmp(b));
+
+            for (_, depot_id) in depots {
+                let Some(site) = self
+                    .special_buildings
+                    .get(&depot_id)
+                    .map(|depot| depot.intersection_id)
+                else {
+                    continue;
+                };
+                if site == scene {
+                    self.clear_breakdown(depot_id, car_id);
+                    break;
+                }
+                match self.spawn_vehicle(
+                    site,
+                    scene,
+                    VehicleType::TowTruck,
+                    TripType::Outbound,
+                    None,
+                    None,
+                ) {
+                    Ok(tow_truck) => {
+                        if let Some(car) = self.cars.get_mut(&tow_truck) {
+                            car.origin_special = Some(depot_id);
+                        }
+                        if let Some(depot) = self.special_buildings.get_mut(&depot_id) {
+                            depot.tow_truck = Some(tow_truck);
+                            depot.towing = Some(car_id);
+                        }
+                        break;
+                    }
+                    Err(e) => {
+                        self.warnings.warn(SimWarning::TowTruckStuck, self.time, || {
+                            format!("Tow truck couldn't leave depot {:?}: {:#}", depot_id, e)
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    /// Get a broken-down vehicle moving again once a depot's tow truck
+    /// reaches it
+    ///
+    /// Only counts as a tow if the vehicle hadn't got going by itself first.
+    pub(super) fn clear_breakdown(&mut self, depot_id: SpecialBuildingId, car_id: CarId) {
+        let waiting = self.awaiting_tow.contains(&car_id);
+        self.awaiting_tow.retain(|id| *id != car_id);
+        if let Some(depot) = self.special_buildings.get_mut(&depot_id) {
+            depot.towing = None;
+            if waiting {
+                depot.tows_completed += 1;
+            }
+        }
+        if let Some(car) = self.cars.get_mut(&car_id).filter(|_| waiting) {
+            car.stalled_secs = 0.0;
+            self.changes.mark(VisualId::Vehicle(car_id));
+        }
+    }
+}
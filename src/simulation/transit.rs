@@ -0,0 +1,459 @@
+//! Bus routes
+//!
+//! A bus route is a loop of stops, each an intersection, that one bus drives
+//! round in order, going from the last stop back to the first. A worker
+//! whose home and factory are both stops on a route rides the bus instead
+//! of driving: they wait at the stop by their home, ride to the one by their
+//! factory, and ride back after their shift. Riders take no car, so every
+//! worker on the bus is a car fewer on the roads. Like walkers, they hold
+//! their place at home while they are out and are paid for their commute
+//! the same way as drivers once home.
+//!
+//! Between stops the bus takes the quickest way it may use: bus lanes
+//! ([`RoadRestriction::BusOnly`]) and every other road but those for trucks
+//! only. It runs in a lane of its own, so it neither queues in traffic nor
+//! holds anybody up. It waits
+//! [`BUS_DWELL_SECS`] at each stop and carries at most [`BUS_CAPACITY`]
+//! riders; the rest wait for it to come round again. A bus with no way to
+//! its next stop waits where it is until the roads give it one. Removing an
+//! intersection a route stops at takes the route out of service, and its
+//! riders give up their trips.
+//!
+//! [`RoadRestriction::BusOnly`]: super::types::RoadRestriction::BusOnly
+
+use anyhow::{bail, ensure, Context, Result};
+#[cfg(feature = "serialization")]
+use serde::{Deserialize, Serialize};
+
+use super::events::SimEvent;
+use super::types::{
+    ApartmentId, BusRouteId, FactoryId, IntersectionId, Position, SimMap, TripType,
+};
+use super::world::SimWorld;
+
+/// Most riders a bus carries at once
+pub const BUS_CAPACITY: usize = 12;
+
+/// How fast a bus drives between stops, in distance per second
+pub const BUS_SPEED: f32 = 4.0;
+
+/// Seconds a bus waits at each stop
+pub const BUS_DWELL_SECS: f32 = 3.0;
+
+/// Fewest stops a route can have
+pub const MIN_BUS_STOPS: usize = 2;
+
+/// A worker waiting for or riding a bus between their home and a factory
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+pub struct BusRider {
+    pub apartment: ApartmentId,
+    pub factory: FactoryId,
+    /// Outbound to the factory, or returning home
+    pub trip_type: TripType,
+    /// Stop they get on at
+    pub from: IntersectionId,
+    /// Stop they get off at
+    pub to: IntersectionId,
+    /// When they got to the stop
+    pub started: f32,
+    /// Distance ridden so far
+    pub distance: f32,
+}
+
+/// Where a route's bus is
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+pub enum BusState {
+    /// Waiting at a stop until `departs`
+    AtStop { stop: IntersectionId, departs: f32 },
+    /// On its way to a stop through the intersections still ahead, the stop
+    /// last
+    Driving {
+        to: IntersectionId,
+        waypoints: Vec<IntersectionId>,
+    },
+}
+
+/// The bus running a route
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+pub struct SimBus {
+    pub position: Position,
+    pub state: BusState,
+    /// Workers on board, in the order they got on
+    pub riders: Vec<BusRider>,
+}
+
+/// A loop of bus stops and the bus running round it
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+pub struct BusRoute {
+    pub id: BusRouteId,
+    /// Stops in the order the bus calls at them
+    pub stops: Vec<IntersectionId>,
+    pub bus: SimBus,
+    /// Workers waiting at the stops, in the order they got there
+    pub waiting: Vec<BusRider>,
+    /// Workers who have got on the bus so far
+    pub boardings: u32,
+}
+
+impl BusRoute {
+    /// Whether the route stops at both of two different intersections
+    pub fn serves(&self, from: IntersectionId, to: IntersectionId) -> bool {
+        from != to && self.stops.contains(&from) && self.stops.contains(&to)
+    }
+
+    /// The stop the bus calls at after `stop`
+    fn stop_after(&self, stop: IntersectionId) -> IntersectionId {
+        let index = self.stops.iter().position(|s| *s == stop).unwrap_or(0);
+        self.stops[(index + 1) % self.stops.len()]
+    }
+}
+
+impl SimWorld {
+    /// Bus routes by ID
+    pub fn bus_routes(&self) -> &SimMap<BusRouteId, BusRoute> {
+        &self.bus_routes
+    }
+
+    /// Workers who have got on a bus so far, across every route
+    pub fn transit_ridership(&self) -> u32 {
+        self.bus_routes.values().map(|route| route.boardings).sum()
+    }
+
+    /// Open a bus route calling at `stops` in order and back to the first,
+    /// its bus waiting at the first stop
+    ///
+    /// There must be at least [`MIN_BUS_STOPS`] different stops, and a way
+    /// from each to the next.
+    pub fn add_bus_route(&mut self, stops: Vec<IntersectionId>) -> Result<BusRouteId> {
+        self.check_bus_route(&stops)?;
+        let first = stops[0];
+        let position = *self
+            .road_network
+            .get_intersection_position(first)
+            .context("First stop not found")?;
+        let id = BusRouteId(self.next_sim_id());
+        let bus = SimBus {
+            position,
+            state: BusState::AtStop {
+                stop: first,
+                departs: self.time + BUS_DWELL_SECS,
+            },
+            riders: Vec::new(),
+        };
+        self.bus_routes.insert(
+            id,
+            BusRoute {
+                id,
+                stops,
+                bus,
+                waiting: Vec::new(),
+                boardings: 0,
+            },
+        );
+        self.changes.mark_layout();
+        Ok(id)
+    }
+
+    /// Open a bus route with game cost checking, charged per stop
+    /// Returns Some(id) if successful, None if insufficient funds
+    pub fn try_add_bus_route(&mut self, stops: Vec<IntersectionId>) -> Result<Option<BusRouteId>> {
+        self.check_bus_route(&stops)?;
+        let count = stops.len() as i32;
+        if !self.spend_for_game(|economy| economy.cost_bus_stop * count) {
+            return Ok(None);
+        }
+        self.add_bus_route(stops).map(Some)
+    }
+
+    /// Take a bus route out of service; its riders give up their trips
+    pub fn remove_bus_route(&mut self, id: BusRouteId) -> Result<()> {
+        self.bus_routes
+            .remove(&id)
+            .with_context(|| format!("Bus route {:?} not found", id))?;
+        self.changes.mark_layout();
+        Ok(())
+    }
+
+    /// Fail unless a bus could run round `stops`
+    fn check_bus_route(&self, stops: &[IntersectionId]) -> Result<()> {
+        ensure!(
+            stops.len() >= MIN_BUS_STOPS,
+            "A bus route needs at least {} stops",
+            MIN_BUS_STOPS
+        );
+        for (index, &stop) in stops.iter().enumerate() {
+            ensure!(
+                self.intersections.contains_key(&stop),
+                "Stop {:?} is not an intersection",
+                stop
+            );
+            let next = stops[(index + 1) % stops.len()];
+            if next == stop {
+                bail!("The bus would call at {:?} twice in a row", stop);
+            }
+            ensure!(
+                self.road_network.find_bus_path(stop, next).is_some(),
+                "No way for the bus from {:?} to {:?}",
+                stop,
+                next
+            );
+        }
+        Ok(())
+    }
+
+    /// The first route stopping at both of two intersections
+    pub(super) fn bus_route_between(
+        &self,
+        from: IntersectionId,
+        to: IntersectionId,
+    ) -> Option<BusRouteId> {
+        self.bus_routes
+            .values()
+            .find(|route| route.serves(from, to))
+            .map(|route| route.id)
+    }
+
+    /// Workers from a house waiting for or riding a bus, either way
+    pub(super) fn riders_from(&self, apartment_id: ApartmentId) -> usize {
+        self.bus_routes
+            .values()
+            .flat_map(|route| route.waiting.iter().chain(&route.bus.riders))
+            .filter(|rider| rider.apartment == apartment_id)
+            .count()
+    }
+
+    /// Send a worker to the stop to ride a route between their home and a
+    /// factory
+    pub(super) fn start_bus_ride(
+        &mut self,
+        route_id: BusRouteId,
+        apartment_id: ApartmentId,
+        factory_id: FactoryId,
+        trip_type: TripType,
+    ) {
+        let (Some(apartment), Some(factory)) = (
+            self.apartments.get(&apartment_id),
+            self.factories.get(&factory_id),
+        ) else {
+            return;
+        };
+        let (home, work) = (apartment.intersection_id, factory.intersection_id);
+        let (from, to) = match trip_type {
+            TripType::Outbound => (home, work),
+            TripType::Return => (work, home),
+        };
+        if trip_type == TripType::Outbound {
+            // Under a demand ramp, workers rest at home between shifts
+            let rest_secs = self.worker_rest_secs();
+            if let Some(apartment) = self.apartments.get_mut(&apartment_id) {
+                apartment.next_departure = self.time + rest_secs;
+            }
+        }
+        self.wait_for_bus(
+            route_id,
+            BusRider {
+                apartment: apartment_id,
+                factory: factory_id,
+                trip_type,
+                from,
+                to,
+                started: self.time,
+                distance: 0.0,
+            },
+        );
+    }
+
+    /// Put a rider at their stop on a route
+    fn wait_for_bus(&mut self, route_id: BusRouteId, rider: BusRider) {
+        if let Some(route) = self.bus_routes.get_mut(&route_id) {
+            route.waiting.push(rider);
+        }
+    }
+
+    /// Take every route that stops at an intersection being removed out of
+    /// service
+    pub(super) fn close_bus_stops(&mut self, intersection_id: IntersectionId) {
+        let routes = self.bus_routes.len();
+        self.bus_routes
+            .retain(|_, route| !route.stops.contains(&intersection_id));
+        if self.bus_routes.len() != routes {
+            self.changes.mark_layout();
+        }
+    }
+
+    /// Drive each bus on, letting riders off and on at the stops it calls at
+    pub(super) fn update_transit(&mut self, delta_secs: f32) {
+        let route_ids: Vec<BusRouteId> = self.bus_routes.keys().copied().collect();
+        for route_id in route_ids {
+            let Some(route) = self.bus_routes.get(&route_id) else {
+                continue;
+            };
+            let BusState::AtStop { stop, departs } = route.bus.state else {
+                if let Some(stop) = self.drive_bus(route_id, delta_secs) {
+                    self.call_at_stop(route_id, stop);
+                }
+                continue;
+            };
+            let next = route.stop_after(stop);
+            self.board_bus(route_id, stop);
+            if self.time < departs {
+                continue;
+            }
+            let state = match self.road_network.find_bus_path(stop, next) {
+                Some(waypoints) => BusState::Driving {
+                    to: next,
+                    waypoints,
+                },
+                // Try again once the bus has waited another spell
+                None => BusState::AtStop {
+                    stop,
+                    departs: self.time + BUS_DWELL_SECS,
+                },
+            };
+            if let Some(route) = self.bus_routes.get_mut(&route_id) {
+                route.bus.state = state;
+            }
+        }
+    }
+
+    /// Move a bus along its waypoints, returning the stop it reached, if it
+    /// did
+    fn drive_bus(&mut self, route_id: BusRouteId, delta_secs: f32) -> Option<IntersectionId> {
+        let network = &self.road_network;
+        let bus = &mut self.bus_routes.get_mut(&route_id)?.bus;
+        let BusState::Driving { to, waypoints } = &mut bus.state else {
+            return None;
+        };
+        let to = *to;
+        let mut travel = BUS_SPEED * delta_secs;
+        let mut driven = 0.0;
+        while travel > 0.0 {
+            let Some(&next) = waypoints.first() else {
+                break;
+            };
+            // An intersection moved since setting off is driven to where it
+            // is now, and one removed is skipped
+            let Some(target) = network.get_intersection_position(next) else {
+                waypoints.remove(0);
+                continue;
+            };
+            let remaining = bus.position.distance(target);
+            if remaining > travel {
+                bus.position = bus.position.lerp(target, travel / remaining);
+                driven += travel;
+                break;
+            }
+            bus.position = *target;
+            driven += remaining;
+            travel -= remaining;
+            waypoints.remove(0);
+        }
+        for rider in &mut bus.riders {
+            rider.distance += driven;
+        }
+        waypoints.is_empty().then_some(to)
+    }
+
+    /// Stop a bus at a stop: riders for there get off, then the waiting
+    /// ones get on
+    fn call_at_stop(&mut self, route_id: BusRouteId, stop: IntersectionId) {
+        let Some(route) = self.bus_routes.get_mut(&route_id) else {
+            return;
+        };
+        route.bus.state = BusState::AtStop {
+            stop,
+            departs: self.time + BUS_DWELL_SECS,
+        };
+        let (alighting, riding) = std::mem::take(&mut route.bus.riders)
+            .into_iter()
+            .partition(|rider| rider.to == stop);
+        route.bus.riders = riding;
+        for rider in alighting {
+            #[cfg(feature = "stats")]
+            {
+                self.commute_modes.rode += 1;
+            }
+            if rider.trip_type == TripType::Outbound {
+                self.ride_in(route_id, rider);
+            } else {
+                self.ride_home(rider);
+            }
+        }
+        self.board_bus(route_id, stop);
+    }
+
+    /// Let the riders waiting at a stop onto the bus standing there, while
+    /// there's room
+    fn board_bus(&mut self, route_id: BusRouteId, stop: IntersectionId) {
+        let Some(route) = self.bus_routes.get_mut(&route_id) else {
+            return;
+        };
+        let BusRoute {
+            bus,
+            waiting,
+            boardings,
+            ..
+        } = route;
+        waiting.retain(|rider| {
+            if rider.from != stop || bus.riders.len() >= BUS_CAPACITY {
+                return true;
+            }
+            bus.riders.push(*rider);
+            *boardings += 1;
+            false
+        });
+    }
+
+    /// Clock a rider in at the factory, or send them back home on the bus
+    /// if it's on strike or can't take them
+    fn ride_in(&mut self, route_id: BusRouteId, rider: BusRider) {
+        let factory_at = self
+            .factories
+            .get(&rider.factory)
+            .map(|factory| factory.intersection_id);
+        let accepted = factory_at.is_some_and(|at| !self.factory_on_strike(at))
+            && self
+                .factories
+                .get_mut(&rider.factory)
+                .is_some_and(|factory| factory.receive_worker(rider.apartment));
+        #[cfg(feature = "stats")]
+        if let Some(factory_at) = factory_at {
+            self.record_worker_arrival(Some(rider.apartment), factory_at, accepted);
+        }
+        if factory_at.is_some() {
+            let (apartment, factory) = (rider.apartment, rider.factory);
+            self.raise(if accepted {
+                SimEvent::WorkerArrived { apartment, factory }
+            } else {
+                SimEvent::FactoryRejectedWorker { apartment, factory }
+            });
+        }
+        if !accepted {
+            let rider = BusRider {
+                trip_type: TripType::Return,
+                from: rider.to,
+                to: rider.from,
+                started: self.time,
+                distance: 0.0,
+                ..rider
+            };
+            self.wait_for_bus(route_id, rider);
+        }
+    }
+
+    /// Count a rider's commute once they are home
+    fn ride_home(&mut self, rider: BusRider) {
+        if !self.apartments.contains_key(&rider.apartment) {
+            return;
+        }
+        let appeal = self.apartment_appeal(rider.apartment);
+        let commute_secs = self.time - rider.started;
+        if let Some(game_state) = &mut self.game_state {
+            game_state.complete_worker_trip_with_appeal(rider.distance, commute_secs, appeal);
+        }
+    }
+}
@@ -0,0 +1,150 @@
+//! Trip statistics broken down by purpose and vehicle type
+//!
+//! Every trip that reaches its destination is counted under its purpose and
+//! vehicle type along with how long it took, so a change to the spawning
+//! rules can be judged by how many trips of each kind it produces and how
+//! long they take, and the fuel they burned (see [`super::emissions`]).
+//! Trips cut short, such as by a road being removed under them, aren't
+//! counted. Shops only receive freight, so there are no shopping trips.
+
+use super::car::SimCar;
+use super::types::{CarId, TripType, VehicleType};
+use super::world::SimWorld;
+
+/// Why a vehicle is on the road
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum TripPurpose {
+    /// A worker driving from home to a factory
+    Commute,
+    /// Any vehicle heading back where it came from
+    Return,
+    /// A truck delivering goods to a shop
+    Freight,
+    /// A resident driving to a school or hospital
+    Visit,
+    /// An ambulance on its way to an emergency
+    Emergency,
+    /// A tow truck on its way to a breakdown
+    Recovery,
+    /// A van taking raw materials from a rail terminal to a factory
+    Supply,
+}
+
+impl TripPurpose {
+    /// Every purpose, in display order
+    pub const ALL: [TripPurpose; 7] = [
+        TripPurpose::Commute,
+        TripPurpose::Return,
+        TripPurpose::Freight,
+        TripPurpose::Visit,
+        TripPurpose::Emergency,
+        TripPurpose::Recovery,
+        TripPurpose::Supply,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            TripPurpose::Commute => "Commute",
+            TripPurpose::Return => "Return",
+            TripPurpose::Freight => "Freight",
+            TripPurpose::Visit => "Visit",
+            TripPurpose::Emergency => "Emergency",
+            TripPurpose::Recovery => "Recovery",
+            TripPurpose::Supply => "Supply",
+        }
+    }
+}
+
+/// Completed trips of one purpose and vehicle type
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct TripStats {
+    pub trips: u32,
+    /// Travel time of all the trips together, in seconds
+    pub total_secs: f32,
+    /// Travel time of the slowest trip, in seconds
+    pub longest_secs: f32,
+    /// Fuel burned on all the trips together, in litres
+    pub fuel_litres: f32,
+    /// CO2 given off on all the trips together, in kilograms
+    pub co2_kg: f32,
+    /// Seconds spent standing with the engine running on all the trips
+    pub idle_secs: f32,
+}
+
+impl TripStats {
+    pub(super) fn record(&mut self, secs: f32) {
+        self.trips += 1;
+        self.total_secs += secs;
+        self.longest_secs = self.longest_secs.max(secs);
+    }
+
+    /// Count a vehicle's completed trip with the fuel it burned
+    fn record_trip(&mut self, car: &SimCar) {
+        self.record(car.trip_secs);
+        self.fuel_litres += car.fuel_litres;
+        self.co2_kg += car.co2_kg();
+        self.idle_secs += car.idle_secs;
+    }
+
+    /// Mean travel time in seconds (0 with no trips)
+    pub fn average_secs(&self) -> f32 {
+        if self.trips == 0 {
+            0.0
+        } else {
+            self.total_secs / self.trips as f32
+        }
+    }
+}
+
+/// One row of [`SimWorld::trip_statistics`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct TripBreakdown {
+    pub purpose: TripPurpose,
+    pub vehicle_type: VehicleType,
+    pub stats: TripStats,
+}
+
+impl SimCar {
+    /// Why this vehicle is on the road
+    pub fn purpose(&self) -> TripPurpose {
+        match (self.trip_type, self.vehicle_type) {
+            (TripType::Return, _) => TripPurpose::Return,
+            (TripType::Outbound, VehicleType::Ambulance) => TripPurpose::Emergency,
+            (TripType::Outbound, VehicleType::TowTruck) => TripPurpose::Recovery,
+            (TripType::Outbound, VehicleType::Van) => TripPurpose::Supply,
+            (TripType::Outbound, _) if self.origin_special.is_some() => TripPurpose::Visit,
+            (TripType::Outbound, VehicleType::Car) => TripPurpose::Commute,
+            (TripType::Outbound, VehicleType::Truck) => TripPurpose::Freight,
+        }
+    }
+}
+
+impl SimWorld {
+    /// Count a vehicle's trip as completed
+    pub(super) fn record_trip(&mut self, car_id: CarId) {
+        let Some(car) = self.cars.get(&car_id) else {
+            return;
+        };
+        self.trip_stats
+            .entry((car.purpose(), car.vehicle_type))
+            .or_default()
+            .record_trip(car);
+    }
+
+    /// Completed trips by purpose and vehicle type, in purpose order
+    ///
+    /// Only combinations with at least one completed trip are listed.
+    pub fn trip_statistics(&self) -> Vec<TripBreakdown> {
+        let mut rows: Vec<TripBreakdown> = self
+            .trip_stats
+            .iter()
+            .map(|((purpose, vehicle_type), stats)| TripBreakdown {
+                purpose: *purpose,
+                vehicle_type: *vehicle_type,
+                stats: *stats,
+            })
+            .collect();
+        rows.sort_by_key(|row| (row.purpose, row.vehicle_type as u8));
+        rows
+    }
+}
@@ -2,22 +2,149 @@
 //!
 //! These are standalone types that don't depend on Bevy.
 
+#[cfg(feature = "serialization")]
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Map used for simulation state
+///
+/// Iterates in key order, so cars, buildings and roads are always visited
+/// in ID order whatever was inserted and removed before. Two processes
+/// running the same seed and the same commands therefore stay in sync, and
+/// a city rebuilt from a save visits everything in the same order as the
+/// one that was saved.
+pub type SimMap<K, V> = BTreeMap<K, V>;
+
 /// A unique identifier for simulation entities
 /// This is a simple wrapper around a usize for type safety
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
 pub struct SimId(pub usize);
 
 /// Type of vehicle in the simulation
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serialization", serde(rename_all = "lowercase"))]
 pub enum VehicleType {
     /// Regular car from a house
     Car,
     /// Delivery truck from a factory
     Truck,
+    /// Emergency vehicle from a hospital; runs red lights
+    Ambulance,
+    /// Recovery vehicle from a depot that clears breakdowns
+    TowTruck,
+    /// Van from a rail terminal carrying raw materials to a factory
+    Van,
+}
+
+impl VehicleType {
+    /// Speeds vehicles of this type are given, unless a content pack sets
+    /// them (trucks are faster)
+    pub fn speed_range(self) -> std::ops::Range<f32> {
+        match self {
+            VehicleType::Car => 2.0..6.0,
+            VehicleType::Truck => 4.0..8.0,
+            VehicleType::Ambulance => 6.0..9.0,
+            VehicleType::TowTruck => 5.0..8.0,
+            VehicleType::Van => 4.0..7.0,
+        }
+    }
+
+    /// Average speed of a vehicle of this type
+    pub fn typical_speed(self) -> f32 {
+        let range = self.speed_range();
+        (range.start + range.end) / 2.0
+    }
+}
+
+/// Which vehicles may drive on a road
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Hash)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serialization", serde(rename_all = "snake_case"))]
+pub enum RoadRestriction {
+    /// Open to every vehicle
+    #[default]
+    All,
+    /// Closed to trucks, e.g. a residential street
+    NoTrucks,
+    /// Only trucks, e.g. a delivery lane
+    TrucksOnly,
+    /// Only buses, e.g. a bus lane
+    BusOnly,
+}
+
+impl RoadRestriction {
+    /// Whether a vehicle type may use the road
+    ///
+    /// Ambulances and tow trucks may use every road.
+    pub fn allows(self, vehicle_type: VehicleType) -> bool {
+        if matches!(vehicle_type, VehicleType::Ambulance | VehicleType::TowTruck) {
+            return true;
+        }
+        match self {
+            RoadRestriction::All => true,
+            RoadRestriction::NoTrucks => vehicle_type != VehicleType::Truck,
+            RoadRestriction::TrucksOnly => vehicle_type == VehicleType::Truck,
+            RoadRestriction::BusOnly => false,
+        }
+    }
+
+    /// Whether the buses of bus routes may use the road
+    pub fn allows_buses(self) -> bool {
+        self != RoadRestriction::TrucksOnly
+    }
+
+    /// The restriction after this one, for toggling through them
+    pub fn next(self) -> Self {
+        match self {
+            RoadRestriction::All => RoadRestriction::NoTrucks,
+            RoadRestriction::NoTrucks => RoadRestriction::TrucksOnly,
+            RoadRestriction::TrucksOnly => RoadRestriction::BusOnly,
+            RoadRestriction::BusOnly => RoadRestriction::All,
+        }
+    }
+
+    /// Name for display
+    pub fn label(self) -> &'static str {
+        match self {
+            RoadRestriction::All => "All vehicles",
+            RoadRestriction::NoTrucks => "No trucks",
+            RoadRestriction::TrucksOnly => "Trucks only",
+            RoadRestriction::BusOnly => "Buses only",
+        }
+    }
+}
+
+/// What carries a road over the ground beneath it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Hash)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serialization", serde(rename_all = "lowercase"))]
+pub enum RoadKind {
+    /// An ordinary road, which can't cross water
+    #[default]
+    Street,
+    /// A bridge, which may span water
+    Bridge,
+    /// A ferry crossing; vehicles wait for the ferry to carry them over
+    Ferry,
+}
+
+impl RoadKind {
+    /// Name for display
+    pub fn label(self) -> &'static str {
+        match self {
+            RoadKind::Street => "road",
+            RoadKind::Bridge => "bridge",
+            RoadKind::Ferry => "ferry crossing",
+        }
+    }
 }
 
 /// The type of trip a vehicle is making
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serialization", serde(rename_all = "lowercase"))]
 pub enum TripType {
     /// Going to destination (work for cars, delivery for trucks)
     Outbound,
@@ -26,31 +153,53 @@ pub enum TripType {
 }
 
 /// A wrapper type for intersection IDs
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
 pub struct IntersectionId(pub SimId);
 
 /// A wrapper type for road IDs
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
 pub struct RoadId(pub SimId);
 
 /// A wrapper type for car IDs
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
 pub struct CarId(pub SimId);
 
 /// A wrapper type for apartment IDs
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
 pub struct ApartmentId(pub SimId);
 
 /// A wrapper type for factory IDs
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
 pub struct FactoryId(pub SimId);
 
 /// A wrapper type for shop IDs
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
 pub struct ShopId(pub SimId);
 
+/// A wrapper type for special building IDs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+pub struct SpecialBuildingId(pub SimId);
+
+/// A wrapper type for decoration IDs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+pub struct DecorationId(pub SimId);
+
+/// A wrapper type for bus route IDs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+pub struct BusRouteId(pub SimId);
+
 /// A 3D position in the simulation
 #[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
 pub struct Position {
     pub x: f32,
     pub y: f32,
@@ -89,6 +238,19 @@ impl Position {
         }
     }
 
+    /// Rise over horizontal run from this position to another; uphill is
+    /// positive
+    pub fn grade_to(&self, other: &Position) -> f32 {
+        let dx = other.x - self.x;
+        let dz = other.z - self.z;
+        let run = (dx * dx + dz * dz).sqrt();
+        if run > 0.0 {
+            (other.y - self.y) / run
+        } else {
+            0.0
+        }
+    }
+
     /// Calculate perpendicular offset (right side of direction)
     pub fn perpendicular_offset(&self, other: &Position, offset: f32) -> Position {
         let dx = other.x - self.x;
@@ -118,14 +280,26 @@ impl Default for Position {
 }
 
 /// A road segment connecting two intersections
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
 pub struct SimRoad {
     pub id: RoadId,
     pub start_intersection: IntersectionId,
     pub end_intersection: IntersectionId,
     pub length: f32,
     pub angle: f32,
+    /// Rise over run from start to end; uphill is positive
+    pub grade: f32,
     pub is_two_way: bool,
+    /// Vehicles allowed on the road
+    pub restriction: RoadRestriction,
+    /// Whether a traffic sensor reports on the road
+    pub sensor: bool,
+    /// Whether the road is a bridge or ferry crossing
+    pub kind: RoadKind,
+    /// Whether the road is closed to every vehicle for now, keeping its
+    /// other settings for when it reopens
+    pub closed: bool,
 }
 
 impl SimRoad {
@@ -139,6 +313,7 @@ impl SimRoad {
     ) -> Self {
         let length = start_pos.distance(end_pos);
         let angle = start_pos.angle_to(end_pos);
+        let grade = start_pos.grade_to(end_pos);
 
         Self {
             id,
@@ -146,16 +321,40 @@ impl SimRoad {
             end_intersection,
             length,
             angle,
+            grade,
             is_two_way,
+            restriction: RoadRestriction::All,
+            sensor: false,
+            kind: RoadKind::Street,
+            closed: false,
         }
     }
+
+    /// Whether a vehicle may drive onto the road
+    pub fn allows(&self, vehicle_type: VehicleType) -> bool {
+        !self.closed && self.restriction.allows(vehicle_type)
+    }
+
+    /// Whether a bus may drive onto the road
+    pub fn allows_buses(&self) -> bool {
+        !self.closed && self.restriction.allows_buses()
+    }
 }
 
 /// Length of a car in world units
 pub const CAR_LENGTH: f32 = 0.5;
 
+/// Shortest road an intersection can be moved to leave
+pub const MIN_ROAD_LENGTH: f32 = 1.0;
+
 /// Distance from intersection to start checking for lock
 pub const INTERSECTION_APPROACH_DISTANCE: f32 = 1.0;
 
 /// Safe following distance multiplier for CAR_LENGTH
 pub const SAFE_FOLLOWING_MULTIPLIER: f32 = 1.5;
+
+/// Simulated seconds that make up one in-game day
+pub const SECONDS_PER_DAY: f32 = 240.0;
+
+/// Hour of the day (0-24) at which a new simulation starts
+pub const DAY_START_HOUR: f32 = 8.0;
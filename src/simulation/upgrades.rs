@@ -0,0 +1,278 @@
+//! Capacity upgrades bought with the game's money
+//!
+//! An economy can include an [`UpgradeConfig`], which caps how many vehicles
+//! may be on the network at once and lets the player buy upgrades that
+//! raise that cap, give every house more cars and let every factory store
+//! more finished deliveries. Each [`UpgradeKind`] has its own
+//! [`UpgradeTrack`]: the first level costs `cost`, each level after costs
+//! `cost_growth` times the one before, and a track can be bought `levels`
+//! times. Upgrades apply to the buildings already placed and to those built
+//! after. Upgrades are bought with [`BuildCommand::BuyUpgrade`], so they
+//! replay from saves and reach co-op peers.
+//!
+//! While the network is at its vehicle cap, houses keep their workers at
+//! home; trucks, workers heading home and service vehicles still set off.
+//! Without an upgrade config there is no cap and nothing to buy.
+//!
+//! [`BuildCommand::BuyUpgrade`]: super::command::BuildCommand::BuyUpgrade
+
+use anyhow::{ensure, Context, Result};
+#[cfg(feature = "serialization")]
+use serde::{Deserialize, Serialize};
+
+use super::world::SimWorld;
+
+/// Kinds of capacity upgrade
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serialization", serde(rename_all = "snake_case"))]
+pub enum UpgradeKind {
+    /// More vehicles allowed on the network at once
+    CarCap,
+    /// More cars at every house
+    ApartmentCars,
+    /// More finished deliveries stored at every factory
+    FactoryStorage,
+}
+
+impl UpgradeKind {
+    /// Every kind, in the order the upgrade panel lists them
+    pub const ALL: [UpgradeKind; 3] = [
+        UpgradeKind::CarCap,
+        UpgradeKind::ApartmentCars,
+        UpgradeKind::FactoryStorage,
+    ];
+
+    /// Name for display
+    pub fn label(self) -> &'static str {
+        match self {
+            UpgradeKind::CarCap => "vehicle cap",
+            UpgradeKind::ApartmentCars => "house cars",
+            UpgradeKind::FactoryStorage => "factory storage",
+        }
+    }
+}
+
+/// What one kind of upgrade costs and does
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serialization", serde(deny_unknown_fields))]
+pub struct UpgradeTrack {
+    /// Cost of the first level
+    pub cost: i32,
+    /// Factor on the cost of each level after the first
+    pub cost_growth: f32,
+    /// Vehicles, cars or deliveries each level adds
+    pub step: u32,
+    /// Times the upgrade can be bought
+    pub levels: u32,
+}
+
+impl UpgradeTrack {
+    /// Cost of buying the upgrade with `level` levels already bought
+    pub fn cost_at(&self, level: u32) -> i32 {
+        (self.cost as f32 * self.cost_growth.powi(level as i32)).round() as i32
+    }
+
+    fn validate(&self, name: &str) -> Result<()> {
+        ensure!(
+            self.cost >= 0,
+            "upgrades.{}.cost must not be negative (got {})",
+            name,
+            self.cost
+        );
+        ensure!(
+            self.cost_growth >= 1.0,
+            "upgrades.{}.cost_growth must be at least 1 (got {})",
+            name,
+            self.cost_growth
+        );
+        ensure!(self.step > 0, "upgrades.{}.step must be at least 1", name);
+        Ok(())
+    }
+}
+
+/// The vehicle cap and the upgrades that can be bought
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serialization", serde(default, deny_unknown_fields))]
+pub struct UpgradeConfig {
+    /// Vehicles allowed on the network at once before any upgrade
+    pub max_cars: usize,
+    /// Raises the vehicle cap by `step` vehicles a level
+    pub car_cap: UpgradeTrack,
+    /// Gives every house `step` more cars a level
+    pub apartment_cars: UpgradeTrack,
+    /// Lets every factory store `step` more deliveries a level
+    pub factory_storage: UpgradeTrack,
+}
+
+impl Default for UpgradeConfig {
+    fn default() -> Self {
+        Self {
+            max_cars: 100,
+            car_cap: UpgradeTrack {
+                cost: 300,
+                cost_growth: 1.5,
+                step: 25,
+                levels: 4,
+            },
+            apartment_cars: UpgradeTrack {
+                cost: 400,
+                cost_growth: 1.5,
+                step: 2,
+                levels: 3,
+            },
+            factory_storage: UpgradeTrack {
+                cost: 500,
+                cost_growth: 1.5,
+                step: 1,
+                levels: 3,
+            },
+        }
+    }
+}
+
+impl UpgradeConfig {
+    /// Reject caps that stop all traffic and tracks that can't be bought
+    pub fn validate(&self) -> Result<()> {
+        ensure!(self.max_cars > 0, "upgrades.max_cars must be at least 1");
+        self.car_cap.validate("car_cap")?;
+        self.apartment_cars.validate("apartment_cars")?;
+        self.factory_storage.validate("factory_storage")?;
+        Ok(())
+    }
+
+    /// The track of a kind of upgrade
+    pub fn track(&self, kind: UpgradeKind) -> &UpgradeTrack {
+        match kind {
+            UpgradeKind::CarCap => &self.car_cap,
+            UpgradeKind::ApartmentCars => &self.apartment_cars,
+            UpgradeKind::FactoryStorage => &self.factory_storage,
+        }
+    }
+}
+
+/// Levels of each upgrade bought so far
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+pub struct UpgradeLevels {
+    pub car_cap: u32,
+    pub apartment_cars: u32,
+    pub factory_storage: u32,
+}
+
+impl UpgradeLevels {
+    /// Levels bought of a kind of upgrade
+    pub fn level(&self, kind: UpgradeKind) -> u32 {
+        match kind {
+            UpgradeKind::CarCap => self.car_cap,
+            UpgradeKind::ApartmentCars => self.apartment_cars,
+            UpgradeKind::FactoryStorage => self.factory_storage,
+        }
+    }
+
+    fn level_mut(&mut self, kind: UpgradeKind) -> &mut u32 {
+        match kind {
+            UpgradeKind::CarCap => &mut self.car_cap,
+            UpgradeKind::ApartmentCars => &mut self.apartment_cars,
+            UpgradeKind::FactoryStorage => &mut self.factory_storage,
+        }
+    }
+}
+
+impl SimWorld {
+    /// The upgrades of the game's economy, if it has any
+    pub fn upgrades(&self) -> Option<&UpgradeConfig> {
+        self.game_state
+            .as_ref()
+            .and_then(|game_state| game_state.economy.upgrades.as_ref())
+    }
+
+    /// Levels of a kind of upgrade bought so far
+    pub fn upgrade_level(&self, kind: UpgradeKind) -> u32 {
+        self.game_state
+            .as_ref()
+            .map_or(0, |game_state| game_state.upgrades.level(kind))
+    }
+
+    /// Cost of the next level of an upgrade, if it can still be bought
+    pub fn upgrade_cost(&self, kind: UpgradeKind) -> Option<i32> {
+        let track = self.upgrades()?.track(kind);
+        let level = self.upgrade_level(kind);
+        (level < track.levels).then(|| track.cost_at(level))
+    }
+
+    /// What the upgrades bought so far add for a kind
+    fn upgrade_bonus(&self, kind: UpgradeKind) -> u32 {
+        self.upgrades()
+            .map_or(0, |upgrades| upgrades.track(kind).step * self.upgrade_level(kind))
+    }
+
+    /// Vehicles allowed on the network at once, if there is a cap
+    pub fn max_cars(&self) -> Option<usize> {
+        let upgrades = self.upgrades()?;
+        Some(upgrades.max_cars + self.upgrade_bonus(UpgradeKind::CarCap) as usize)
+    }
+
+    /// Whether the network is at its vehicle cap
+    pub fn at_car_cap(&self) -> bool {
+        self.max_cars().is_some_and(|max_cars| self.cars.len() >= max_cars)
+    }
+
+    /// Cars the upgrades bought so far add to every house
+    pub fn extra_apartment_cars(&self) -> usize {
+        self.upgrade_bonus(UpgradeKind::ApartmentCars) as usize
+    }
+
+    /// Deliveries the upgrades bought so far let every factory store
+    pub fn extra_factory_storage(&self) -> u32 {
+        self.upgrade_bonus(UpgradeKind::FactoryStorage)
+    }
+
+    /// Buy the next level of an upgrade and apply it to the buildings
+    /// already placed
+    ///
+    /// Returns `Ok(false)` if it can't be afforded.
+    pub fn try_buy_upgrade(&mut self, kind: UpgradeKind) -> Result<bool> {
+        let now = self.time;
+        let game_state = self
+            .game_state
+            .as_mut()
+            .context("Upgrades need a game in progress")?;
+        let track = *game_state
+            .economy
+            .upgrades
+            .as_ref()
+            .context("This economy has no upgrades to buy")?
+            .track(kind);
+        let level = game_state.upgrades.level(kind);
+        ensure!(
+            level < track.levels,
+            "The {} is fully upgraded",
+            kind.label()
+        );
+        if !game_state.spend(track.cost_at(level)) {
+            return Ok(false);
+        }
+        *game_state.upgrades.level_mut(kind) += 1;
+        game_state.record_upgrade(kind, level + 1);
+
+        match kind {
+            UpgradeKind::CarCap => {}
+            UpgradeKind::ApartmentCars => {
+                for apartment in self.apartments.values_mut() {
+                    apartment.add_cars(track.step as usize, now);
+                }
+            }
+            UpgradeKind::FactoryStorage => {
+                for factory in self.factories.values_mut() {
+                    factory.max_deliveries += track.step;
+                }
+            }
+        }
+        // Houses and factories show their cars and storage
+        self.changes.mark_layout();
+        Ok(true)
+    }
+}
@@ -0,0 +1,209 @@
+//! Workers who walk to work
+//!
+//! A worker whose factory shares their home's intersection, or is only a
+//! few steps away, walks there instead of driving. A walk takes no car and
+//! no road: it is slower than driving, but needs no route, can't be held up
+//! by traffic and doesn't count towards the vehicle cap. Walkers still take
+//! up a place at home while they are out, so a house sends no more workers
+//! at once on foot than it could by car, and they are paid for their
+//! commute the same way as drivers when they get home.
+
+#[cfg(feature = "serialization")]
+use serde::{Deserialize, Serialize};
+
+use super::events::SimEvent;
+use super::types::{ApartmentId, FactoryId, TripType};
+use super::world::SimWorld;
+
+/// Furthest apart a house and factory can be for their workers to walk
+pub const WALKING_DISTANCE: f32 = 6.0;
+
+/// How fast a worker walks, in distance per second
+pub const WALKING_SPEED: f32 = 1.0;
+
+/// Shortest walk, even between a house and factory at the same intersection
+pub const MIN_WALK_SECS: f32 = 2.0;
+
+/// A worker on foot between their home and a factory
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+pub struct Walk {
+    pub apartment: ApartmentId,
+    pub factory: FactoryId,
+    /// Outbound to the factory, or returning home
+    pub trip_type: TripType,
+    /// Distance between the house and the factory
+    pub distance: f32,
+    /// When the walk began
+    pub started: f32,
+    /// When the worker gets there
+    pub arrives: f32,
+}
+
+/// Worker trips by how they were made, each way counting as one
+#[cfg(feature = "stats")]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct CommuteModes {
+    pub driven: u32,
+    pub walked: u32,
+    /// Rode the bus
+    pub rode: u32,
+    /// Seconds spent on the walks
+    pub walking_secs: f32,
+}
+
+#[cfg(feature = "stats")]
+impl CommuteModes {
+    /// Share of worker trips made on foot, or `None` before any were made
+    pub fn walk_share(&self) -> Option<f32> {
+        let trips = self.driven + self.walked + self.rode;
+        (trips > 0).then(|| self.walked as f32 / trips as f32)
+    }
+
+    /// Share of worker trips made by bus, or `None` before any were made
+    pub fn ride_share(&self) -> Option<f32> {
+        let trips = self.driven + self.walked + self.rode;
+        (trips > 0).then(|| self.rode as f32 / trips as f32)
+    }
+
+    /// Average seconds a walk took
+    pub fn average_walk_secs(&self) -> f32 {
+        if self.walked == 0 {
+            0.0
+        } else {
+            self.walking_secs / self.walked as f32
+        }
+    }
+}
+
+impl SimWorld {
+    /// Workers on foot, in the order they set off
+    pub fn walks(&self) -> &[Walk] {
+        &self.walks
+    }
+
+    /// Worker trips driven and walked so far
+    #[cfg(feature = "stats")]
+    pub fn commute_modes(&self) -> CommuteModes {
+        self.commute_modes
+    }
+
+    /// Distance between a house and a factory if it is close enough to
+    /// walk, or `None` if the worker has to drive
+    pub(super) fn walking_distance(
+        &self,
+        apartment_id: ApartmentId,
+        factory_id: FactoryId,
+    ) -> Option<f32> {
+        let home = self.apartments.get(&apartment_id)?.intersection_id;
+        let work = self.factories.get(&factory_id)?.intersection_id;
+        if home == work {
+            return Some(0.0);
+        }
+        let network = &self.road_network;
+        let distance = network
+            .get_intersection_position(home)?
+            .distance(network.get_intersection_position(work)?);
+        (distance <= WALKING_DISTANCE).then_some(distance)
+    }
+
+    /// Workers from a house out on foot, either way
+    pub(super) fn walkers_from(&self, apartment_id: ApartmentId) -> usize {
+        self.walks
+            .iter()
+            .filter(|walk| walk.apartment == apartment_id)
+            .count()
+    }
+
+    /// Send a worker on foot between their home and a factory
+    pub(super) fn start_walk(
+        &mut self,
+        apartment_id: ApartmentId,
+        factory_id: FactoryId,
+        trip_type: TripType,
+        distance: f32,
+    ) {
+        if trip_type == TripType::Outbound {
+            // Under a demand ramp, workers rest at home between shifts
+            let rest_secs = self.worker_rest_secs();
+            if let Some(apartment) = self.apartments.get_mut(&apartment_id) {
+                apartment.next_departure = self.time + rest_secs;
+            }
+        }
+        self.walks.push(Walk {
+            apartment: apartment_id,
+            factory: factory_id,
+            trip_type,
+            distance,
+            started: self.time,
+            arrives: self.time + (distance / WALKING_SPEED).max(MIN_WALK_SECS),
+        });
+    }
+
+    /// Clock in the walkers who reached their factory and pay for the
+    /// commutes of those who got home
+    pub(super) fn update_walks(&mut self) {
+        let now = self.time;
+        let (arrived, walking) = std::mem::take(&mut self.walks)
+            .into_iter()
+            .partition(|walk| walk.arrives <= now);
+        self.walks = walking;
+        for walk in arrived {
+            #[cfg(feature = "stats")]
+            {
+                self.commute_modes.walked += 1;
+                self.commute_modes.walking_secs += now - walk.started;
+            }
+            if walk.trip_type == TripType::Outbound {
+                self.walk_in(walk);
+            } else {
+                self.walk_home(walk, now - walk.started);
+            }
+        }
+    }
+
+    /// Clock a walker in at the factory, or turn them away home if it's on
+    /// strike or can't take them
+    fn walk_in(&mut self, walk: Walk) {
+        let factory_at = self
+            .factories
+            .get(&walk.factory)
+            .map(|factory| factory.intersection_id);
+        let accepted = factory_at.is_some_and(|at| !self.factory_on_strike(at))
+            && self
+                .factories
+                .get_mut(&walk.factory)
+                .is_some_and(|factory| factory.receive_worker(walk.apartment));
+        #[cfg(feature = "stats")]
+        if let Some(factory_at) = factory_at {
+            self.record_worker_arrival(Some(walk.apartment), factory_at, accepted);
+        }
+        if factory_at.is_some() {
+            let (apartment, factory) = (walk.apartment, walk.factory);
+            self.raise(if accepted {
+                SimEvent::WorkerArrived { apartment, factory }
+            } else {
+                SimEvent::FactoryRejectedWorker { apartment, factory }
+            });
+        }
+        if !accepted {
+            self.start_walk(
+                walk.apartment,
+                walk.factory,
+                TripType::Return,
+                walk.distance,
+            );
+        }
+    }
+
+    /// Count a walker's commute once they are home
+    fn walk_home(&mut self, walk: Walk, commute_secs: f32) {
+        if !self.apartments.contains_key(&walk.apartment) {
+            return;
+        }
+        let appeal = self.apartment_appeal(walk.apartment);
+        if let Some(game_state) = &mut self.game_state {
+            game_state.complete_worker_trip_with_appeal(walk.distance, commute_secs, appeal);
+        }
+    }
+}
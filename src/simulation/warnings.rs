@@ -0,0 +1,137 @@
+//! Rate-limited logging of recurring simulation warnings
+//!
+//! Some warnings can come up every tick for as long as their cause lasts,
+//! such as a commute whose buildings are gone or a tow truck with no way out
+//! of its depot, and a long run would log thousands of identical lines. The
+//! [`WarningLog`] logs the first of each [`SimWarning`] kind, then counts the
+//! rest and logs how many there were every [`WARNING_INTERVAL_SECS`] of
+//! simulation time. The totals are kept for diagnostics.
+
+use log::warn;
+use std::collections::BTreeMap;
+
+use super::world::SimWorld;
+
+/// Simulation seconds between log lines for one kind of warning
+pub const WARNING_INTERVAL_SECS: f32 = 60.0;
+
+/// Kinds of recurring warning the simulation logs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SimWarning {
+    /// A worker got home but their house or factory has no position
+    CommuteWithoutBuildings,
+    /// A worker got home without a house or factory to pay them for
+    CommuteWithoutWorker,
+    /// A vehicle on a ferry couldn't be moved along the crossing
+    FerryDeck,
+    /// A terminal's van couldn't set off with materials
+    VanStuck,
+    /// A hospital's ambulance couldn't set off
+    AmbulanceStuck,
+    /// A depot's tow truck couldn't set off
+    TowTruckStuck,
+    /// A scenario's road closure couldn't be made or lifted
+    RoadClosure,
+    /// A contraflow road couldn't be found or turned round
+    Contraflow,
+    /// The autopilot couldn't build what it planned
+    AutopilotBuild,
+}
+
+impl SimWarning {
+    /// Short description for diagnostics
+    pub fn label(self) -> &'static str {
+        match self {
+            SimWarning::CommuteWithoutBuildings => "commute without buildings",
+            SimWarning::CommuteWithoutWorker => "commute without worker",
+            SimWarning::FerryDeck => "ferry deck",
+            SimWarning::VanStuck => "van stuck",
+            SimWarning::AmbulanceStuck => "ambulance stuck",
+            SimWarning::TowTruckStuck => "tow truck stuck",
+            SimWarning::RoadClosure => "road closure",
+            SimWarning::Contraflow => "contraflow",
+            SimWarning::AutopilotBuild => "autopilot build",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct WarningCount {
+    total: u64,
+    /// Warnings since the last one logged
+    unlogged: u64,
+    /// Simulation time the last one was logged
+    logged_at: f32,
+}
+
+/// Recurring warnings seen so far, logged at most once per kind every
+/// [`WARNING_INTERVAL_SECS`]
+#[derive(Debug, Clone, Default)]
+pub struct WarningLog {
+    counts: BTreeMap<SimWarning, WarningCount>,
+}
+
+impl WarningLog {
+    /// Count a warning at simulation time `now`, logging `message` unless
+    /// one of its kind was logged less than [`WARNING_INTERVAL_SECS`] ago;
+    /// returns whether it was logged
+    pub fn warn(
+        &mut self,
+        kind: SimWarning,
+        now: f32,
+        message: impl FnOnce() -> String,
+    ) -> bool {
+        let count = self.counts.entry(kind).or_default();
+        count.total += 1;
+        if count.total > 1 && now - count.logged_at < WARNING_INTERVAL_SECS {
+            count.unlogged += 1;
+            return false;
+        }
+        if count.unlogged > 0 {
+            warn!("{} (and {} more like it)", message(), count.unlogged);
+        } else {
+            warn!("{}", message());
+        }
+        count.unlogged = 0;
+        count.logged_at = now;
+        true
+    }
+
+    /// Log how many of each kind went unlogged over the last interval
+    pub(super) fn flush(&mut self, now: f32) {
+        for (kind, count) in &mut self.counts {
+            if count.unlogged > 0 && now - count.logged_at >= WARNING_INTERVAL_SECS {
+                warn!(
+                    "{} more {} warnings in the last {:.0}s",
+                    count.unlogged,
+                    kind.label(),
+                    now - count.logged_at
+                );
+                count.unlogged = 0;
+                count.logged_at = now;
+            }
+        }
+    }
+
+    /// How many warnings of a kind there have been, logged or not
+    pub fn count(&self, kind: SimWarning) -> u64 {
+        self.counts.get(&kind).map_or(0, |count| count.total)
+    }
+
+    /// Warnings there have been of each kind, for the kinds seen
+    pub fn counts(&self) -> impl Iterator<Item = (SimWarning, u64)> + '_ {
+        self.counts.iter().map(|(kind, count)| (*kind, count.total))
+    }
+
+    /// Warnings of every kind
+    pub fn total(&self) -> u64 {
+        self.counts.values().map(|count| count.total).sum()
+    }
+}
+
+impl SimWorld {
+    /// Recurring warnings the world has raised so far
+    pub fn warnings(&self) -> &WarningLog {
+        &self.warnings
+    }
+}
@@ -3,23 +3,64 @@
 //! This is the entry point for running the traffic simulation
 //! without any Bevy dependencies.
 
-use anyhow::{Context, Result};
-use log::warn;
-use rand::rngs::StdRng;
+use std::collections::VecDeque;
+
+use anyhow::{ensure, Context, Result};
+use ordered_float::OrderedFloat;
 use rand::seq::IndexedRandom;
 use rand::Rng;
-use rand::SeedableRng;
-use std::collections::HashMap;
+#[cfg(feature = "serialization")]
+use serde::{Deserialize, Serialize};
 
+use super::alarms::AlarmMonitor;
+use super::bounds::WorldBounds;
 use super::building::{SimApartment, SimFactory, SimShop};
 use super::car::{CarUpdateResult, SimCar};
 use super::car_manager;
-use super::game_state::{GameState, COST_APARTMENT, COST_FACTORY, COST_ROAD, COST_SHOP};
+use super::car_pool::CarPool;
+use super::contraflow::Contraflow;
+#[cfg(feature = "stats")]
+use super::catchment::CatchmentStats;
+use super::cornering::CurveSpeedLimit;
+use super::content::{BuildingKind, ContentRegistry, PlacedBuilding};
+use super::decoration::SimDecoration;
+use super::demand::SlaBreach;
+use super::driving_side::DrivingSide;
+use super::economy::EconomyConfig;
+use super::elevation::Heightmap;
+use super::events::{EventLog, SimEvent};
+use super::ferry::SimFerry;
+use super::game_state::{GameState, COMMUTE_HEALTHY_DISTANCE};
+use super::handling::HandlingConfig;
+use super::idle::IdleMode;
 use super::intersection::SimIntersection;
+#[cfg(feature = "stats")]
+use super::labor_stats::{LaborStats, ShiftOutcome};
+use super::render::{ChangeLog, VisualId};
+use super::path_cache::PathCache;
+use super::pending_trips::PendingTrip;
+#[cfg(feature = "stats")]
+use super::recap::RecapStats;
+#[cfg(feature = "stats")]
+use super::personality::DriverStats;
 use super::road_network::SimRoadNetwork;
+use super::rng::SimRng;
+use super::scenario_events::ActiveEvent;
+use super::routing::{RoutePool, RouteRequest};
+use super::spawning::VehicleSpawn;
+use super::special::{SimSpecialBuilding, SpecialKind};
+use super::terrain::WaterRegion;
+use super::transit::BusRoute;
+#[cfg(feature = "stats")]
+use super::walking::CommuteModes;
+use super::walking::Walk;
+use super::warnings::WarningLog;
+#[cfg(feature = "stats")]
+use super::trip_stats::{TripPurpose, TripStats};
 use super::types::{
-    ApartmentId, CarId, FactoryId, IntersectionId, Position, RoadId, ShopId, SimId, SimRoad,
-    TripType, VehicleType,
+    ApartmentId, BusRouteId, CarId, DecorationId, FactoryId, IntersectionId, Position, RoadId, RoadKind,
+    RoadRestriction, ShopId, SimId, SimMap, SimRoad, SpecialBuildingId, TripType, VehicleType,
+    DAY_START_HOUR, MIN_ROAD_LENGTH, SECONDS_PER_DAY,
 };
 
 /// Global demand metrics for the simulation
@@ -51,24 +92,50 @@ type WorkersDone = Vec<(FactoryId, ApartmentId)>;
 type TrucksToDispatch = Vec<(FactoryId, IntersectionId)>;
 
 /// The main simulation world
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
 pub struct SimWorld {
     /// Road network for pathfinding
     pub road_network: SimRoadNetwork,
 
     /// All intersections
-    pub intersections: HashMap<IntersectionId, SimIntersection>,
+    pub intersections: SimMap<IntersectionId, SimIntersection>,
 
     /// All cars
-    pub cars: HashMap<CarId, SimCar>,
+    pub cars: SimMap<CarId, SimCar>,
+
+    /// Finished cars kept for the next ones to set off in
+    pub(super) car_pool: CarPool,
+
+    /// Routes found recently, handed out again while fresh
+    pub(super) path_cache: PathCache,
 
     /// All apartments
-    pub apartments: HashMap<ApartmentId, SimApartment>,
+    pub apartments: SimMap<ApartmentId, SimApartment>,
 
     /// All factories
-    pub factories: HashMap<FactoryId, SimFactory>,
+    pub factories: SimMap<FactoryId, SimFactory>,
 
     /// All shops
-    pub shops: HashMap<ShopId, SimShop>,
+    pub shops: SimMap<ShopId, SimShop>,
+
+    /// All schools and hospitals
+    pub special_buildings: SimMap<SpecialBuildingId, SimSpecialBuilding>,
+
+    /// Trees and parks
+    pub decorations: SimMap<DecorationId, SimDecoration>,
+
+    /// Ferries, keyed by the forward direction of their crossing
+    pub ferries: SimMap<RoadId, SimFerry>,
+
+    /// Water that roads can only cross by bridge or ferry
+    pub water: Vec<WaterRegion>,
+
+    /// Ground heights, if the world isn't flat
+    pub heightmap: Option<Heightmap>,
+
+    /// The rectangle the city is built in, if the map sets one (see
+    /// [`SimWorld::bounds`])
+    pub(super) bounds: Option<WorldBounds>,
 
     /// Next ID to assign
     next_id: usize,
@@ -77,10 +144,117 @@ pub struct SimWorld {
     pub time: f32,
 
     /// Optional seeded RNG for reproducible simulations
-    rng: Option<StdRng>,
+    #[cfg_attr(feature = "serialization", serde(skip))]
+    pub(super) rng: Option<SimRng>,
 
     /// Game state tracking (optional - only used when playing as a game)
     pub game_state: Option<GameState>,
+
+    /// Building and vehicle types loaded from content packs
+    pub content: ContentRegistry,
+
+    /// How fast vehicles may take the bends between roads
+    pub curve_speed: CurveSpeedLimit,
+
+    /// How each class of vehicle pulls out, gets going and turns
+    pub handling: HandlingConfig,
+
+    /// Which side of the road traffic keeps to
+    pub driving_side: DrivingSide,
+
+    /// What changed since the front-end last caught up
+    #[cfg_attr(feature = "serialization", serde(skip))]
+    pub(super) changes: ChangeLog,
+
+    /// Events waiting to be drained, and the listeners to tell
+    #[cfg_attr(feature = "serialization", serde(skip))]
+    pub(super) sim_events: EventLog,
+
+    /// Completed trips by purpose and vehicle type
+    #[cfg(feature = "stats")]
+    #[cfg_attr(feature = "serialization", serde(skip))]
+    pub(super) trip_stats: SimMap<(TripPurpose, VehicleType), TripStats>,
+
+    /// Worker shifts requested, fulfilled and rejected
+    #[cfg(feature = "stats")]
+    #[cfg_attr(feature = "serialization", serde(skip))]
+    pub(super) labor_stats: LaborStats,
+
+    /// Which houses and factories each shop's deliveries came from
+    #[cfg(feature = "stats")]
+    #[cfg_attr(feature = "serialization", serde(skip))]
+    pub(super) catchment_stats: CatchmentStats,
+
+    /// The personalities drivers have set off with
+    #[cfg(feature = "stats")]
+    #[cfg_attr(feature = "serialization", serde(skip))]
+    pub(super) driver_stats: DriverStats,
+
+    /// When the demand ramp's delivery SLA first broke
+    pub(super) sla_breach: Option<SlaBreach>,
+
+    /// Broken-down vehicles waiting for a tow truck, oldest first
+    pub(super) awaiting_tow: Vec<CarId>,
+
+    /// Seconds vehicles have spent stopped on each road, waiting to get into
+    /// the intersection at its end
+    #[cfg(feature = "stats")]
+    #[cfg_attr(feature = "serialization", serde(skip))]
+    pub(super) approach_waits: SimMap<RoadId, f32>,
+
+    /// Money over time, the biggest jam and the distance driven, for the
+    /// recap of the run
+    #[cfg(feature = "stats")]
+    #[cfg_attr(feature = "serialization", serde(skip))]
+    pub(super) recap_stats: RecapStats,
+
+    /// Worker threads finding routes for new commuters and trucks, if
+    /// routing is done in the background
+    #[cfg_attr(feature = "serialization", serde(skip))]
+    pub(super) route_pool: Option<RoutePool>,
+
+    /// Vehicles waiting at each starting intersection for room on their
+    /// first road, in the order they got there, with the time they did
+    pub(super) spawn_queues: SimMap<IntersectionId, VecDeque<(CarId, f32)>>,
+
+    /// Trips with no route to their destination, oldest first
+    pub(super) pending_trips: Vec<PendingTrip>,
+
+    /// Vehicles that had to wait to set off, and for how long
+    #[cfg(feature = "stats")]
+    #[cfg_attr(feature = "serialization", serde(skip))]
+    pub(super) spawn_delays: TripStats,
+
+    /// Workers on foot, in the order they set off
+    pub(super) walks: Vec<Walk>,
+
+    /// Bus routes and the workers riding them
+    pub(super) bus_routes: SimMap<BusRouteId, BusRoute>,
+
+    /// Worker trips driven and walked
+    #[cfg(feature = "stats")]
+    #[cfg_attr(feature = "serialization", serde(skip))]
+    pub(super) commute_modes: CommuteModes,
+
+    /// Scenario events under way, in the order they started
+    pub(super) active_events: Vec<ActiveEvent>,
+
+    /// How many of the event schedule's events have started
+    pub(super) scheduled_events_started: usize,
+
+    /// Roads scheduled to run contraflow and the ones turned round now
+    pub(super) contraflow: Contraflow,
+
+    /// The economy's alarms and when they went off
+    pub(super) alarms: AlarmMonitor,
+
+    /// Recurring warnings, counted and logged at a limited rate
+    #[cfg_attr(feature = "serialization", serde(skip))]
+    pub(super) warnings: WarningLog,
+
+    /// Whether an idle world ticks the cheap way, and how often it has
+    #[cfg_attr(feature = "serialization", serde(skip))]
+    pub(super) idle: IdleMode,
 }
 
 impl Default for SimWorld {
@@ -90,18 +264,61 @@ impl Default for SimWorld {
 }
 
 impl SimWorld {
-    fn new_internal(rng: Option<StdRng>, game_state: Option<GameState>) -> Self {
+    fn new_internal(rng: Option<SimRng>, game_state: Option<GameState>) -> Self {
         Self {
             road_network: SimRoadNetwork::new(),
-            intersections: HashMap::new(),
-            cars: HashMap::new(),
-            apartments: HashMap::new(),
-            factories: HashMap::new(),
-            shops: HashMap::new(),
+            intersections: SimMap::default(),
+            cars: SimMap::default(),
+            car_pool: CarPool::default(),
+            path_cache: PathCache::default(),
+            apartments: SimMap::default(),
+            factories: SimMap::default(),
+            shops: SimMap::default(),
+            special_buildings: SimMap::default(),
+            decorations: SimMap::default(),
+            ferries: SimMap::default(),
+            water: Vec::new(),
+            heightmap: None,
+            bounds: None,
             next_id: 0,
             time: 0.0,
             rng,
             game_state,
+            content: ContentRegistry::default(),
+            curve_speed: CurveSpeedLimit::default(),
+            handling: HandlingConfig::default(),
+            driving_side: DrivingSide::default(),
+            changes: ChangeLog::default(),
+            sim_events: EventLog::default(),
+            #[cfg(feature = "stats")]
+            trip_stats: SimMap::default(),
+            #[cfg(feature = "stats")]
+            labor_stats: LaborStats::default(),
+            #[cfg(feature = "stats")]
+            catchment_stats: CatchmentStats::default(),
+            #[cfg(feature = "stats")]
+            driver_stats: DriverStats::default(),
+            sla_breach: None,
+            awaiting_tow: Vec::new(),
+            #[cfg(feature = "stats")]
+            approach_waits: SimMap::default(),
+            #[cfg(feature = "stats")]
+            recap_stats: RecapStats::default(),
+            route_pool: None,
+            spawn_queues: SimMap::default(),
+            pending_trips: Vec::new(),
+            #[cfg(feature = "stats")]
+            spawn_delays: TripStats::default(),
+            walks: Vec::new(),
+            bus_routes: SimMap::new(),
+            #[cfg(feature = "stats")]
+            commute_modes: CommuteModes::default(),
+            active_events: Vec::new(),
+            scheduled_events_started: 0,
+            contraflow: Contraflow::default(),
+            alarms: AlarmMonitor::default(),
+            warnings: WarningLog::default(),
+            idle: IdleMode::default(),
         }
     }
 
@@ -111,16 +328,21 @@ impl SimWorld {
 
     /// Create a new SimWorld with a seeded RNG for reproducible simulations
     pub fn new_with_seed(seed: u64) -> Self {
-        Self::new_internal(Some(StdRng::seed_from_u64(seed)), None)
+        Self::new_internal(Some(SimRng::new(seed)), None)
     }
 
     /// Create a new SimWorld with game state enabled (for playing as a game)
     pub fn new_with_game() -> Self {
-        Self::new_internal(None, Some(GameState::new()))
+        Self::new_with_economy(EconomyConfig::default())
+    }
+
+    /// Create a new SimWorld with game state using a custom economy
+    pub fn new_with_economy(economy: EconomyConfig) -> Self {
+        Self::new_internal(None, Some(GameState::with_economy(economy)))
     }
 
     /// Get a random value in the given range, using seeded RNG if available
-    fn random_range(&mut self, range: std::ops::Range<f32>) -> f32 {
+    pub(super) fn random_range(&mut self, range: std::ops::Range<f32>) -> f32 {
         match &mut self.rng {
             Some(rng) => rng.random_range(range),
             None => rand::rng().random_range(range),
@@ -128,7 +350,7 @@ impl SimWorld {
     }
 
     /// Choose a random element from a slice, using seeded RNG if available
-    fn choose_random<'a, T>(&mut self, slice: &'a [T]) -> Option<&'a T> {
+    pub(super) fn choose_random<'a, T>(&mut self, slice: &'a [T]) -> Option<&'a T> {
         if slice.is_empty() {
             return None;
         }
@@ -138,32 +360,80 @@ impl SimWorld {
         }
     }
 
-    fn next_sim_id(&mut self) -> SimId {
+    /// Current hour of the simulated day in the range [0, 24)
+    ///
+    /// Derived from the simulation time, starting at [`DAY_START_HOUR`] and
+    /// wrapping every [`SECONDS_PER_DAY`] simulated seconds.
+    pub fn time_of_day(&self) -> f32 {
+        (DAY_START_HOUR + self.time / SECONDS_PER_DAY * 24.0).rem_euclid(24.0)
+    }
+
+    /// Healthy commute distance from the game's economy, or the default when
+    /// no game state is attached
+    pub fn commute_healthy_distance(&self) -> f32 {
+        self.game_state
+            .as_ref()
+            .map_or(COMMUTE_HEALTHY_DISTANCE, |game_state| {
+                game_state.economy.commute_healthy_distance
+            })
+    }
+
+    pub(super) fn next_sim_id(&mut self) -> SimId {
         let id = SimId(self.next_id);
         self.next_id += 1;
         id
     }
 
-    /// Attempts to charge the given cost from the game state if one exists.
-    /// Returns `true` when no game state is attached so headless simulations
-    /// can operate without budget constraints.
-    fn spend_for_game(&mut self, cost: i32) -> bool {
+    /// ID the next thing added to the world will get
+    #[cfg(feature = "serialization")]
+    pub(super) fn peek_next_id(&self) -> usize {
+        self.next_id
+    }
+
+    /// Skip IDs up to `next_id`, as if the things in between had been added
+    #[cfg(feature = "serialization")]
+    pub(super) fn skip_ids_to(&mut self, next_id: usize) -> Result<()> {
+        ensure!(
+            next_id >= self.next_id,
+            "ID {} has already been given out (next is {})",
+            next_id,
+            self.next_id
+        );
+        self.next_id = next_id;
+        Ok(())
+    }
+
+    /// Attempts to charge a cost picked from the game's economy if a game state
+    /// exists. Returns `true` when no game state is attached so headless
+    /// simulations can operate without budget constraints.
+    pub(super) fn spend_for_game(&mut self, cost: impl Fn(&EconomyConfig) -> i32) -> bool {
         match &mut self.game_state {
-            Some(game_state) => game_state.spend(cost),
+            Some(game_state) => {
+                let cost = cost(&game_state.economy);
+                game_state.spend(cost)
+            }
             None => true,
         }
     }
 
     /// Add an intersection to the world
+    ///
+    /// On terrain the intersection is placed on the ground.
     pub fn add_intersection(&mut self, position: Position) -> IntersectionId {
+        let position = self.on_ground(position);
         let id = IntersectionId(self.next_sim_id());
         let intersection = SimIntersection::new(id, position);
         self.intersections.insert(id, intersection);
         self.road_network.add_intersection(id, position);
+        self.changes.mark_layout();
+        self.raise(SimEvent::IntersectionAdded { intersection: id });
         id
     }
 
     /// Add a road between two intersections (one-way)
+    ///
+    /// A road marked two-way pairs up with an unpaired two-way road already
+    /// running back the other way; see [`Self::add_two_way_road`].
     pub fn add_road(
         &mut self,
         start: IntersectionId,
@@ -183,10 +453,13 @@ impl SimWorld {
         let id = RoadId(self.next_sim_id());
         let road = SimRoad::new(id, start, end, &start_pos, &end_pos, is_two_way);
         self.road_network.add_road(road);
+        self.changes.mark_layout();
+        self.raise(SimEvent::RoadAdded { road: id });
         Ok(id)
     }
 
-    /// Add a two-way road between intersections (creates two logical roads)
+    /// Add a two-way road between intersections (creates two logical roads,
+    /// paired in the road network)
     pub fn add_two_way_road(
         &mut self,
         start: IntersectionId,
@@ -200,16 +473,24 @@ impl SimWorld {
     /// Add an apartment at an intersection
     pub fn add_apartment(&mut self, intersection_id: IntersectionId) -> ApartmentId {
         let id = ApartmentId(self.next_sim_id());
-        let apartment = SimApartment::new(id, intersection_id);
+        let mut apartment = SimApartment::new(id, intersection_id, self.time);
+        apartment.add_cars(self.extra_apartment_cars(), self.time);
         self.apartments.insert(id, apartment);
+        self.raise(SimEvent::BuildingAdded {
+            building: PlacedBuilding::Apartment(id),
+        });
         id
     }
 
     /// Add a factory at an intersection
     pub fn add_factory(&mut self, intersection_id: IntersectionId) -> FactoryId {
         let id = FactoryId(self.next_sim_id());
-        let factory = SimFactory::new(id, intersection_id);
+        let mut factory = SimFactory::new(id, intersection_id, self.time);
+        factory.max_deliveries += self.extra_factory_storage();
         self.factories.insert(id, factory);
+        self.raise(SimEvent::BuildingAdded {
+            building: PlacedBuilding::Factory(id),
+        });
         id
     }
 
@@ -218,13 +499,16 @@ impl SimWorld {
         let id = ShopId(self.next_sim_id());
         let shop = SimShop::new(id, intersection_id);
         self.shops.insert(id, shop);
+        self.raise(SimEvent::BuildingAdded {
+            building: PlacedBuilding::Shop(id),
+        });
         id
     }
 
     /// Add an apartment with game cost checking
     /// Returns Some(apartment_id) if successful, None if insufficient funds
     pub fn try_add_apartment(&mut self, intersection_id: IntersectionId) -> Option<ApartmentId> {
-        if !self.spend_for_game(COST_APARTMENT) {
+        if !self.spend_for_game(|economy| economy.cost_apartment) {
             return None;
         }
         Some(self.add_apartment(intersection_id))
@@ -233,7 +517,7 @@ impl SimWorld {
     /// Add a factory with game cost checking
     /// Returns Some(factory_id) if successful, None if insufficient funds
     pub fn try_add_factory(&mut self, intersection_id: IntersectionId) -> Option<FactoryId> {
-        if !self.spend_for_game(COST_FACTORY) {
+        if !self.spend_for_game(|economy| economy.cost_factory) {
             return None;
         }
         Some(self.add_factory(intersection_id))
@@ -242,7 +526,7 @@ impl SimWorld {
     /// Add a shop with game cost checking
     /// Returns Some(shop_id) if successful, None if insufficient funds
     pub fn try_add_shop(&mut self, intersection_id: IntersectionId) -> Option<ShopId> {
-        if !self.spend_for_game(COST_SHOP) {
+        if !self.spend_for_game(|economy| economy.cost_shop) {
             return None;
         }
         Some(self.add_shop(intersection_id))
@@ -255,7 +539,14 @@ impl SimWorld {
         start: IntersectionId,
         end: IntersectionId,
     ) -> Result<Option<(RoadId, RoadId)>> {
-        if !self.spend_for_game(COST_ROAD) {
+        let slope = match (
+            self.road_network.get_intersection_position(start),
+            self.road_network.get_intersection_position(end),
+        ) {
+            (Some(start), Some(end)) => self.slope_cost_factor(start, end),
+            _ => 1.0,
+        };
+        if !self.spend_for_game(|economy| (economy.cost_road as f32 * slope).round() as i32) {
             return Ok(None);
         }
         self.add_two_way_road(start, end).map(Some)
@@ -269,13 +560,119 @@ impl SimWorld {
         end_pos: Position,
         snap_distance: f32,
     ) -> Result<Option<(IntersectionId, IntersectionId, RoadId, RoadId)>> {
-        if !self.spend_for_game(COST_ROAD) {
+        self.try_add_crossing_at_positions(start_pos, end_pos, snap_distance, RoadKind::Street)
+    }
+
+    /// Add a road, bridge or ferry crossing at positions with game cost
+    /// checking
+    /// Returns Some(...) if successful, None if insufficient funds
+    pub fn try_add_crossing_at_positions(
+        &mut self,
+        start_pos: Position,
+        end_pos: Position,
+        snap_distance: f32,
+        kind: RoadKind,
+    ) -> Result<Option<(IntersectionId, IntersectionId, RoadId, RoadId)>> {
+        // Check the crossing before charging for it
+        let (start, end) = (
+            self.snapped_position(start_pos, snap_distance),
+            self.snapped_position(end_pos, snap_distance),
+        );
+        self.check_crossing(&start, &end, kind)?;
+        // Roads cut into steep slopes cost more
+        let slope = self.slope_cost_factor(&start, &end);
+        if !self.spend_for_game(|economy| match kind {
+            RoadKind::Street => (economy.cost_road as f32 * slope).round() as i32,
+            RoadKind::Bridge => economy.cost_bridge,
+            RoadKind::Ferry => economy.cost_ferry,
+        }) {
             return Ok(None);
         }
-        self.add_road_at_positions(start_pos, end_pos, snap_distance)
+        self.add_crossing_at_positions(start_pos, end_pos, snap_distance, kind)
             .map(Some)
     }
 
+    /// Add a building of a content pack type at an intersection
+    pub fn add_building_from_def(
+        &mut self,
+        name: &str,
+        intersection_id: IntersectionId,
+    ) -> Result<PlacedBuilding> {
+        let def = self
+            .content
+            .building(name)
+            .with_context(|| format!("Unknown building type '{}'", name))?
+            .clone();
+
+        let placed = match def.kind {
+            BuildingKind::Apartment => {
+                let id = self.add_apartment(intersection_id);
+                let extra_cars = self.extra_apartment_cars();
+                if let Some(apartment) = self.apartments.get_mut(&id) {
+                    if let Some(cars) = def.cars {
+                        apartment.set_car_count(cars + extra_cars, self.time);
+                    }
+                    apartment.variant = Some(def.name);
+                }
+                PlacedBuilding::Apartment(id)
+            }
+            BuildingKind::Factory => {
+                let id = self.add_factory(intersection_id);
+                let extra_storage = self.extra_factory_storage();
+                if let Some(factory) = self.factories.get_mut(&id) {
+                    if let Some(work_time) = def.work_time {
+                        factory.work_time = work_time;
+                    }
+                    if let Some(max_deliveries) = def.max_deliveries {
+                        factory.max_deliveries = max_deliveries + extra_storage;
+                    }
+                    if let Some(shifts) = def.shifts_per_delivery {
+                        factory.shifts_per_delivery = shifts;
+                    }
+                    if let Some(materials) = def.materials_per_delivery {
+                        factory.materials_per_delivery = materials;
+                    }
+                    factory.variant = Some(def.name);
+                }
+                PlacedBuilding::Factory(id)
+            }
+            BuildingKind::Shop => {
+                let id = self.add_shop(intersection_id);
+                if let Some(shop) = self.shops.get_mut(&id) {
+                    shop.variant = Some(def.name);
+                }
+                PlacedBuilding::Shop(id)
+            }
+        };
+        Ok(placed)
+    }
+
+    /// Add a building of a content pack type with game cost checking
+    /// Returns Ok(None) if there are insufficient funds
+    pub fn try_add_building_from_def(
+        &mut self,
+        name: &str,
+        intersection_id: IntersectionId,
+    ) -> Result<Option<PlacedBuilding>> {
+        let def = self
+            .content
+            .building(name)
+            .with_context(|| format!("Unknown building type '{}'", name))?;
+        let (kind, custom_cost) = (def.kind, def.cost);
+
+        let paid = self.spend_for_game(|economy| {
+            custom_cost.unwrap_or(match kind {
+                BuildingKind::Apartment => economy.cost_apartment,
+                BuildingKind::Factory => economy.cost_factory,
+                BuildingKind::Shop => economy.cost_shop,
+            })
+        });
+        if !paid {
+            return Ok(None);
+        }
+        self.add_building_from_def(name, intersection_id).map(Some)
+    }
+
     /// Remove an apartment from the world
     /// Returns the cars that were associated with the apartment (if any)
     pub fn remove_apartment(&mut self, apartment_id: ApartmentId) -> Vec<CarId> {
@@ -283,36 +680,118 @@ impl SimWorld {
             Some(a) => a,
             None => return Vec::new(),
         };
+        self.raise(SimEvent::BuildingRemoved {
+            building: PlacedBuilding::Apartment(apartment_id),
+        });
         apartment.cars.into_iter().flatten().collect()
     }
 
     /// Remove a factory from the world
     pub fn remove_factory(&mut self, factory_id: FactoryId) {
-        self.factories.remove(&factory_id);
+        if self.factories.remove(&factory_id).is_some() {
+            self.raise(SimEvent::BuildingRemoved {
+                building: PlacedBuilding::Factory(factory_id),
+            });
+        }
     }
 
     /// Remove a shop from the world
     pub fn remove_shop(&mut self, shop_id: ShopId) {
-        self.shops.remove(&shop_id);
+        if self.shops.remove(&shop_id).is_some() {
+            self.raise(SimEvent::BuildingRemoved {
+                building: PlacedBuilding::Shop(shop_id),
+            });
+        }
     }
 
     /// Remove a road from the world
-    /// Cars on the road will be despawned
+    /// Cars on the road get off it and find a new route, and cars routed
+    /// over it find another way (see [`super::road_removal`])
     pub fn remove_road(&mut self, road_id: RoadId) -> Result<()> {
-        let cars_on_road = self.road_network.remove_road(road_id)?;
+        // A ferry crossing can't run one way, so both directions go together
+        let mut removed = vec![road_id];
+        let ferry = self
+            .road_network
+            .get_road(road_id)
+            .is_some_and(|road| road.kind == RoadKind::Ferry);
+        if ferry {
+            removed.extend(self.road_network.opposite_road(road_id));
+        }
+        let exits = self.road_exits(&removed, None);
+        let cars_on_road = if ferry {
+            self.road_network.remove_two_way_road(road_id)?
+        } else {
+            self.road_network.remove_road(road_id)?
+        };
+        self.changes.mark_layout();
+        for road in removed {
+            self.raise(SimEvent::RoadRemoved { road });
+        }
 
-        // Despawn all cars that were on the removed road
-        for car_id in cars_on_road {
-            self.despawn_car(car_id);
+        self.leave_removed_roads(cars_on_road, exits);
+        self.recalculate_car_paths();
+
+        Ok(())
+    }
+
+    /// Move an intersection, reshaping the roads that meet there
+    ///
+    /// Buildings at the intersection move with it. Cars on the reshaped roads
+    /// keep their share of the way along them instead of being despawned.
+    pub fn move_intersection(
+        &mut self,
+        intersection_id: IntersectionId,
+        position: Position,
+    ) -> Result<()> {
+        ensure!(
+            self.intersections.contains_key(&intersection_id),
+            "Intersection {:?} not found",
+            intersection_id
+        );
+        let position = self.on_ground(position);
+        self.check_on_land(&position)?;
+        for road in self.road_network.roads().values() {
+            let other = if road.start_intersection == intersection_id {
+                road.end_intersection
+            } else if road.end_intersection == intersection_id {
+                road.start_intersection
+            } else {
+                continue;
+            };
+            if let Some(other_position) = self.road_network.get_intersection_position(other) {
+                ensure!(
+                    position.distance(other_position) >= MIN_ROAD_LENGTH,
+                    "Roads must be at least {} long",
+                    MIN_ROAD_LENGTH
+                );
+                self.check_crossing(&position, other_position, road.kind)?;
+            }
         }
 
+        let reshaped: SimMap<RoadId, f32> = self
+            .road_network
+            .move_intersection(intersection_id, position)?
+            .into_iter()
+            .collect();
+        if let Some(intersection) = self.intersections.get_mut(&intersection_id) {
+            intersection.position = position;
+        }
+        self.changes.mark_layout();
+        for car in self.cars.values_mut() {
+            if let Some(&scale) = reshaped.get(&car.current_road) {
+                car.follow_reshaped_road(scale, &self.road_network, self.driving_side);
+            }
+        }
         Ok(())
     }
 
     /// Remove an intersection and all connected roads
-    /// Cars on affected roads will be despawned
+    /// Cars on affected roads get off them at their other ends and find a
+    /// new route
     /// Buildings at the intersection will be removed
     pub fn remove_intersection(&mut self, intersection_id: IntersectionId) -> Result<()> {
+        self.close_bus_stops(intersection_id);
+
         // Remove any buildings at this intersection
         let apartments_to_remove: Vec<ApartmentId> = self
             .apartments
@@ -347,16 +826,33 @@ impl SimWorld {
             self.remove_shop(shop_id);
         }
 
+        let specials_to_remove: Vec<SpecialBuildingId> = self
+            .special_buildings
+            .iter()
+            .filter(|(_, b)| b.intersection_id == intersection_id)
+            .map(|(id, _)| *id)
+            .collect();
+
+        for special_id in specials_to_remove {
+            self.remove_special_building(special_id);
+        }
+
         // Remove the intersection from intersections collection
         self.intersections.remove(&intersection_id);
 
         // Remove intersection and roads from road network
-        let (_, cars_on_roads) = self.road_network.remove_intersection(intersection_id)?;
-
-        // Despawn all cars that were on removed roads
-        for car_id in cars_on_roads {
-            self.despawn_car(car_id);
+        let connected = self.road_network.get_roads_at_intersection(intersection_id);
+        let exits = self.road_exits(&connected, Some(intersection_id));
+        let (roads, cars_on_roads) = self.road_network.remove_intersection(intersection_id)?;
+        self.changes.mark_layout();
+        for road in roads {
+            self.raise(SimEvent::RoadRemoved { road });
         }
+        self.raise(SimEvent::IntersectionRemoved {
+            intersection: intersection_id,
+        });
+
+        self.leave_removed_roads(cars_on_roads, exits);
 
         // Recalculate paths for remaining cars that might have been using deleted roads
         self.recalculate_car_paths();
@@ -364,36 +860,195 @@ impl SimWorld {
         Ok(())
     }
 
+    /// Whether an intersection has no roads and no building, so nothing
+    /// would miss it
+    pub fn is_orphan_intersection(&self, intersection_id: IntersectionId) -> bool {
+        let at = |id: IntersectionId| id == intersection_id;
+        self.intersections.contains_key(&intersection_id)
+            && self
+                .road_network
+                .get_roads_at_intersection(intersection_id)
+                .is_empty()
+            && !self.apartments.values().any(|a| at(a.intersection_id))
+            && !self.factories.values().any(|f| at(f.intersection_id))
+            && !self.shops.values().any(|s| at(s.intersection_id))
+            && !self.special_buildings.values().any(|b| at(b.intersection_id))
+    }
+
+    /// Remove every intersection left with no roads and no building, e.g.
+    /// after the roads to it were removed
+    /// Returns the intersections removed, in ID order
+    pub fn cleanup_orphans(&mut self) -> Result<Vec<IntersectionId>> {
+        let orphans: Vec<IntersectionId> = self
+            .intersections
+            .keys()
+            .copied()
+            .filter(|id| self.is_orphan_intersection(*id))
+            .collect();
+        for &intersection_id in &orphans {
+            self.intersections.remove(&intersection_id);
+            self.road_network.remove_intersection(intersection_id)?;
+            self.raise(SimEvent::IntersectionRemoved {
+                intersection: intersection_id,
+            });
+        }
+        if !orphans.is_empty() {
+            self.changes.mark_layout();
+        }
+        Ok(orphans)
+    }
+
     /// Remove a two-way road (both directions)
-    /// Cars on either direction will be despawned
+    /// Cars on either direction get off it and find a new route
     pub fn remove_two_way_road(
         &mut self,
         intersection_a: IntersectionId,
         intersection_b: IntersectionId,
     ) -> Result<()> {
-        // Find and remove both directions of the road
-        if let Ok(forward_road) = self
+        // Two one-way roads may also join the intersections, one each way
+        let directions = [
+            (intersection_a, intersection_b),
+            (intersection_b, intersection_a),
+        ];
+        for (from, to) in directions {
+            if let Ok(road_id) = self.road_network.find_road_between(from, to) {
+                let opposite = self.road_network.opposite_road(road_id);
+                let removed: Vec<RoadId> = std::iter::once(road_id).chain(opposite).collect();
+                let exits = self.road_exits(&removed, None);
+                let cars_on_roads = self.road_network.remove_two_way_road(road_id)?;
+                self.changes.mark_layout();
+                for road in removed {
+                    self.raise(SimEvent::RoadRemoved { road });
+                }
+                self.leave_removed_roads(cars_on_roads, exits);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Make a road two-way by adding the opposite direction, or one-way in
+    /// its own direction by removing the opposite one
+    ///
+    /// Cars on a removed direction get off it and find a new route, and cars
+    /// routed over it find another way (see [`Self::remove_road`]).
+    pub fn set_road_two_way(&mut self, road_id: RoadId, two_way: bool) -> Result<()> {
+        let road = self
             .road_network
-            .find_road_between(intersection_a, intersection_b)
-        {
-            self.remove_road(forward_road)?;
+            .get_road(road_id)
+            .with_context(|| format!("Road {:?} not found", road_id))?;
+        let (start, end) = (road.start_intersection, road.end_intersection);
+        let (restriction, sensor, kind, closed) =
+            (road.restriction, road.sensor, road.kind, road.closed);
+        ensure!(
+            two_way || kind != RoadKind::Ferry,
+            "Ferry crossings run both ways"
+        );
+
+        match (two_way, self.road_network.opposite_road(road_id)) {
+            (true, Some(_)) | (false, None) => return Ok(()),
+            (true, None) => {
+                // Pair up with a one-way road already running back
+                let backward = match self.road_network.find_road_between(end, start) {
+                    Ok(backward) => backward,
+                    Err(_) => {
+                        let backward = self.add_road(end, start, false)?;
+                        self.road_network
+                            .set_road_restriction(backward, restriction)?;
+                        self.road_network.set_road_sensor(backward, sensor)?;
+                        self.road_network.set_road_kind(backward, kind)?;
+                        self.road_network.set_road_closed(backward, closed)?;
+                        backward
+                    }
+                };
+                self.road_network.pair_roads(road_id, backward)?;
+                self.changes.mark_layout();
+            }
+            (false, Some(opposite)) => self.remove_road(opposite)?,
         }
+        self.recalculate_car_paths();
+        Ok(())
+    }
 
-        if let Ok(backward_road) = self
+    /// Turn a one-way road round
+    /// Returns the ID of the road in its new direction
+    ///
+    /// Cars on the road get off it and find a new route, and cars routed
+    /// over it find another way (see [`Self::remove_road`]).
+    pub fn reverse_one_way_road(&mut self, road_id: RoadId) -> Result<RoadId> {
+        let road = self
             .road_network
-            .find_road_between(intersection_b, intersection_a)
-        {
-            self.remove_road(backward_road)?;
+            .get_road(road_id)
+            .with_context(|| format!("Road {:?} not found", road_id))?;
+        ensure!(
+            self.road_network.opposite_road(road_id).is_none(),
+            "Road {:?} is two-way",
+            road_id
+        );
+        let (start, end) = (road.start_intersection, road.end_intersection);
+        let (restriction, sensor, kind, closed) =
+            (road.restriction, road.sensor, road.kind, road.closed);
+        ensure!(
+            self.road_network.find_road_between(end, start).is_err(),
+            "A road already runs the other way"
+        );
+
+        self.remove_road(road_id)?;
+        let reversed = self.add_road(end, start, false)?;
+        self.road_network
+            .set_road_restriction(reversed, restriction)?;
+        self.road_network.set_road_sensor(reversed, sensor)?;
+        self.road_network.set_road_kind(reversed, kind)?;
+        self.road_network.set_road_closed(reversed, closed)?;
+        self.recalculate_car_paths();
+        Ok(reversed)
+    }
+
+    /// Set which vehicles may use a road, in both directions if it's two-way
+    ///
+    /// Cars whose route used the road find a new one; cars already on it
+    /// carry on.
+    pub fn set_road_restriction(
+        &mut self,
+        road_id: RoadId,
+        restriction: RoadRestriction,
+    ) -> Result<()> {
+        let reverse = self.road_network.opposite_road(road_id);
+        self.road_network
+            .set_road_restriction(road_id, restriction)
+            .with_context(|| format!("Road {:?} not found", road_id))?;
+        if let Some(reverse) = reverse {
+            self.road_network.set_road_restriction(reverse, restriction)?;
         }
+        self.recalculate_car_paths();
+        Ok(())
+    }
 
+    /// Close a road to every vehicle without removing it, or reopen it, in
+    /// both directions if it's two-way
+    ///
+    /// Cars whose route used the road find a new one, or wait at the
+    /// barricade if there is none; cars already on it carry on. Reopening
+    /// keeps the road's restriction, sensor and direction.
+    pub fn set_road_closed(&mut self, road_id: RoadId, closed: bool) -> Result<()> {
+        let reverse = self.road_network.opposite_road(road_id);
+        self.road_network
+            .set_road_closed(road_id, closed)
+            .with_context(|| format!("Road {:?} not found", road_id))?;
+        if let Some(reverse) = reverse {
+            self.road_network.set_road_closed(reverse, closed)?;
+        }
+        self.recalculate_car_paths();
         Ok(())
     }
 
     /// Despawn a car and clean up references
-    fn despawn_car(&mut self, car_id: CarId) {
+    pub(super) fn despawn_car(&mut self, car_id: CarId) {
+        self.raise(SimEvent::CarDespawned { car: car_id });
         car_manager::despawn_car(
             car_id,
             &mut self.cars,
+            &mut self.car_pool,
             &mut self.road_network,
             &mut self.apartments,
             &mut self.factories,
@@ -401,17 +1056,30 @@ impl SimWorld {
     }
 
     /// Recalculate paths for all cars that might have invalid paths
-    fn recalculate_car_paths(&mut self) {
-        car_manager::recalculate_car_paths(
-            &mut self.cars,
-            &mut self.road_network,
-            &mut self.apartments,
-            &mut self.factories,
-        );
+    pub(super) fn recalculate_car_paths(&mut self) {
+        // Cars left without a route are gone, along with their slots
+        for car_id in car_manager::recalculate_car_paths(&mut self.cars, &self.road_network) {
+            self.despawn_car(car_id);
+        }
+    }
+
+    /// Remove a car that has finished its trip
+    ///
+    /// The apartment or factory it belongs to is left for the caller to
+    /// update.
+    pub(super) fn remove_car(&mut self, car_id: CarId) {
+        self.raise(SimEvent::CarDespawned { car: car_id });
+        self.road_network.remove_car_from_tracking(car_id);
+        if let Some(car) = self.cars.remove(&car_id) {
+            self.car_pool.release(car);
+        }
     }
 
     /// Split a road at a given position to create a new intersection
     /// Returns the new intersection ID and the IDs of the new roads
+    ///
+    /// Cars on the road carry on along the half they were on, and routes over
+    /// it run through the new intersection.
     pub fn split_road_at_position(
         &mut self,
         road_id: RoadId,
@@ -423,43 +1091,157 @@ impl SimWorld {
             .context("Road not found")?
             .clone();
 
+        ensure!(
+            road.kind == RoadKind::Street,
+            "A {} can't be split",
+            road.kind.label()
+        );
+
         let start_intersection = road.start_intersection;
         let end_intersection = road.end_intersection;
-        let is_two_way = road.is_two_way;
-
-        // Get cars that were on this road before removing it
-        let cars_on_road = self.road_network.get_cars_on_road(road_id);
-
-        // Remove the original road
-        self.road_network.remove_road(road_id)?;
+        let (restriction, sensor, closed) = (road.restriction, road.sensor, road.closed);
+        let length = road.length;
+        let is_two_way = self.road_network.two_way_road(road_id).is_some();
+
+        // Remove the original road, and its other direction if it's two-way
+        let opposite = self.road_network.opposite_road(road_id);
+        let cars_on_road = self.road_network.remove_two_way_road(road_id)?;
+        for road in std::iter::once(road_id).chain(opposite) {
+            self.raise(SimEvent::RoadRemoved { road });
+        }
 
         // Create new intersection at split position
         let new_intersection = self.add_intersection(split_position);
 
-        // Create new roads
-        let first_road = self.add_road(start_intersection, new_intersection, is_two_way)?;
-        let second_road = self.add_road(new_intersection, end_intersection, is_two_way)?;
+        // Create new roads, paired again if the original was two-way
+        let (first_road, second_road, reverse) = if is_two_way {
+            let (first_road, first_reverse) =
+                self.add_two_way_road(start_intersection, new_intersection)?;
+            let (second_road, second_reverse) =
+                self.add_two_way_road(new_intersection, end_intersection)?;
+            self.road_network
+                .set_road_restriction(first_reverse, restriction)?;
+            self.road_network
+                .set_road_restriction(second_reverse, restriction)?;
+            self.road_network.set_road_sensor(first_reverse, sensor)?;
+            self.road_network.set_road_sensor(second_reverse, sensor)?;
+            self.road_network.set_road_closed(first_reverse, closed)?;
+            self.road_network.set_road_closed(second_reverse, closed)?;
+            (first_road, second_road, Some((second_reverse, first_reverse)))
+        } else {
+            (
+                self.add_road(start_intersection, new_intersection, false)?,
+                self.add_road(new_intersection, end_intersection, false)?,
+                None,
+            )
+        };
+        self.road_network
+            .set_road_restriction(first_road, restriction)?;
+        self.road_network
+            .set_road_restriction(second_road, restriction)?;
+        self.road_network.set_road_sensor(first_road, sensor)?;
+        self.road_network.set_road_sensor(second_road, sensor)?;
+        self.road_network.set_road_closed(first_road, closed)?;
+        self.road_network.set_road_closed(second_road, closed)?;
+
+        let mut halves = vec![(road_id, length, first_road, second_road)];
+        if let (Some(opposite), Some((first_reverse, second_reverse))) = (opposite, reverse) {
+            halves.push((opposite, length, first_reverse, second_reverse));
+        }
+        self.carry_cars_across_split(cars_on_road, new_intersection, &halves)?;
 
-        // If two-way, also create reverse roads
-        if is_two_way {
-            // Remove the reverse road if it exists
-            if let Ok(reverse_road) = self
-                .road_network
-                .find_road_between(end_intersection, start_intersection)
-            {
-                self.road_network.remove_road(reverse_road)?;
-            }
+        Ok((new_intersection, first_road, second_road))
+    }
 
-            self.add_road(new_intersection, start_intersection, is_two_way)?;
-            self.add_road(end_intersection, new_intersection, is_two_way)?;
+    /// Move the cars on a split road, and those waiting to set off on it,
+    /// onto the half of it they were on, and send every route between its
+    /// ends through the intersection it was split at
+    ///
+    /// `halves` holds each direction of the road that was split, its length
+    /// and the halves it was split into, in the order they are driven.
+    fn carry_cars_across_split(
+        &mut self,
+        cars_on_road: Vec<CarId>,
+        split: IntersectionId,
+        halves: &[(RoadId, f32, RoadId, RoadId)],
+    ) -> Result<()> {
+        let half_length = |network: &SimRoadNetwork, road_id| {
+            network.get_road(road_id).map_or(0.0, |road| road.length)
+        };
+        for car_id in self.cars.keys().copied().collect::<Vec<_>>() {
+            // Cars already getting off a removed road carry on doing so
+            let Some(car) = self.cars.get(&car_id).filter(|car| car.road_exit.is_none()) else {
+                continue;
+            };
+            let Some(&(_, length, first, second)) = halves
+                .iter()
+                .find(|(road_id, ..)| *road_id == car.current_road)
+            else {
+                continue;
+            };
+            let (first_length, second_length) = (
+                half_length(&self.road_network, first),
+                half_length(&self.road_network, second),
+            );
+            let distance = if length > 0.0 {
+                car.distance_along_road.into_inner() * (first_length + second_length) / length
+            } else {
+                0.0
+            };
+            let on_road = cars_on_road.contains(&car_id);
+            let (road_id, distance) = if distance < first_length {
+                (first, distance)
+            } else {
+                (second, distance - first_length)
+            };
+            let car = self.cars.get_mut(&car_id).context("Car not found")?;
+            if road_id == first {
+                // Now heading for the split, with the end still to come
+                if let Some(intersection) = car
+                    .path
+                    .first()
+                    .and_then(|target| self.intersections.get_mut(target))
+                {
+                    intersection.release(car_id);
+                }
+                car.path.insert(0, split);
+            }
+            car.current_road = road_id;
+            car.distance_along_road = OrderedFloat(distance);
+            if on_road {
+                self.road_network.update_car_road_position(
+                    car_id,
+                    road_id,
+                    OrderedFloat(distance),
+                    false,
+                    None,
+                    OrderedFloat(0.0),
+                )?;
+            }
+            self.mark_vehicle_changed(car_id);
         }
 
-        // Despawn cars that were on the split road (they need to recalculate)
-        for car_id in cars_on_road {
-            self.despawn_car(car_id);
+        // Routes further on that ran between the ends now stop at the split,
+        // unless another road still joins them
+        let ends: Vec<(IntersectionId, IntersectionId)> = halves
+            .iter()
+            .filter_map(|&(_, _, first, second)| {
+                let start = self.road_network.get_road(first)?.start_intersection;
+                let end = self.road_network.get_road(second)?.end_intersection;
+                Some((start, end))
+            })
+            .filter(|&(start, end)| self.road_network.find_road_between(start, end).is_err())
+            .collect();
+        for car in self.cars.values_mut() {
+            let mut index = 1;
+            while index < car.path.len() {
+                if ends.contains(&(car.path[index - 1], car.path[index])) {
+                    car.path.insert(index, split);
+                }
+                index += 1;
+            }
         }
-
-        Ok((new_intersection, first_road, second_road))
+        Ok(())
     }
 
     /// Dynamically add a two-way road between two positions
@@ -471,6 +1253,28 @@ impl SimWorld {
         end_pos: Position,
         snap_distance: f32,
     ) -> Result<(IntersectionId, IntersectionId, RoadId, RoadId)> {
+        self.add_crossing_at_positions(start_pos, end_pos, snap_distance, RoadKind::Street)
+    }
+
+    /// Add a two-way road, bridge or ferry crossing between two positions,
+    /// snapping each end like [`Self::add_road_at_positions`]
+    ///
+    /// Ordinary roads can't cross water and ferries must; a ferry crossing
+    /// gets a ferry docked at its start.
+    pub fn add_crossing_at_positions(
+        &mut self,
+        start_pos: Position,
+        end_pos: Position,
+        snap_distance: f32,
+        kind: RoadKind,
+    ) -> Result<(IntersectionId, IntersectionId, RoadId, RoadId)> {
+        // Check where the ends will snap to before building anything
+        self.check_crossing(
+            &self.snapped_position(start_pos, snap_distance),
+            &self.snapped_position(end_pos, snap_distance),
+            kind,
+        )?;
+
         // Find or create start intersection
         let start_intersection = self.find_or_create_intersection(start_pos, snap_distance)?;
 
@@ -488,13 +1292,42 @@ impl SimWorld {
 
         // Create the two-way road
         let (forward, backward) = self.add_two_way_road(start_intersection, end_intersection)?;
+        self.road_network.set_road_kind(forward, kind)?;
+        self.road_network.set_road_kind(backward, kind)?;
+        if kind == RoadKind::Ferry {
+            if let Some(route) = self.road_network.two_way_road(forward) {
+                self.add_ferry(route, start_intersection);
+            }
+        }
 
         Ok((start_intersection, end_intersection, forward, backward))
     }
 
+    /// Where a position snaps to: a nearby intersection, else a nearby
+    /// point on a road, else the position itself
+    pub(super) fn snapped_position(&self, position: Position, snap_distance: f32) -> Position {
+        if let Some(intersection_pos) = self
+            .road_network
+            .find_closest_intersection(&position)
+            .and_then(|closest| self.road_network.get_intersection_position(closest))
+        {
+            if position.distance(intersection_pos) <= snap_distance {
+                return *intersection_pos;
+            }
+        }
+        if let Some((_, closest_point, _, _)) =
+            self.road_network.find_closest_point_on_road(&position)
+        {
+            if position.distance(&closest_point) <= snap_distance {
+                return closest_point;
+            }
+        }
+        position
+    }
+
     /// Find an existing intersection near a position, or create a new one
     /// If the position is near an existing road, split that road
-    fn find_or_create_intersection(
+    pub(super) fn find_or_create_intersection(
         &mut self,
         position: Position,
         snap_distance: f32,
@@ -524,6 +1357,7 @@ impl SimWorld {
         }
 
         // No nearby intersection or road - create new intersection
+        self.check_on_land(&position)?;
         Ok(self.add_intersection(position))
     }
 
@@ -537,51 +1371,145 @@ impl SimWorld {
         origin_apartment: Option<ApartmentId>,
         origin_factory: Option<FactoryId>,
     ) -> Result<CarId> {
-        // Generate random speed (trucks are faster)
-        let speed = match vehicle_type {
-            VehicleType::Car => self.random_range(2.0..6.0),
-            VehicleType::Truck => self.random_range(4.0..8.0),
-        };
+        let (speed, variant) =
+            self.roll_vehicle_speed(vehicle_type, origin_apartment, origin_factory);
+        let personality = self.roll_personality();
+        let speed = speed * personality.profile.speed_factor;
 
         // Generate the car ID using the world's ID generator
         let car_id = CarId(self.next_sim_id());
 
-        let car = car_manager::spawn_vehicle(
-            car_id,
-            from_intersection,
-            to_intersection,
-            vehicle_type,
-            trip_type,
-            origin_apartment,
-            origin_factory,
-            &mut self.road_network,
-            speed,
-        )?;
+        // With a route pool, commuters and trucks wait for their route to be
+        // found in the background
+        let mut car = match &mut self.route_pool {
+            Some(pool) if matches!(vehicle_type, VehicleType::Car | VehicleType::Truck) => {
+                let car = car_manager::spawn_waiting_vehicle(
+                    car_id,
+                    from_intersection,
+                    to_intersection,
+                    vehicle_type,
+                    trip_type,
+                    origin_apartment,
+                    origin_factory,
+                    &self.road_network,
+                    &mut self.car_pool,
+                    speed,
+                )?;
+                pool.request(RouteRequest {
+                    car_id,
+                    from: from_intersection,
+                    to: to_intersection,
+                    vehicle_type,
+                });
+                car
+            }
+            _ => {
+                let mut car = car_manager::spawn_waiting_vehicle(
+                    car_id,
+                    from_intersection,
+                    to_intersection,
+                    vehicle_type,
+                    trip_type,
+                    origin_apartment,
+                    origin_factory,
+                    &self.road_network,
+                    &mut self.car_pool,
+                    speed,
+                )?;
+                // Find a path on roads the vehicle is allowed to use
+                let path = self
+                    .find_route(from_intersection, to_intersection, vehicle_type)
+                    .context("No path found to destination")?;
+                car_manager::set_off(&mut car, path, &self.road_network)?;
+                car
+            }
+        };
+        car.variant = variant;
+        car.personality = personality;
 
         self.cars.insert(car_id, car);
+        self.raise(SimEvent::CarSpawned {
+            car: car_id,
+            vehicle_type,
+        });
+        if self.cars[&car_id].queued_at_start {
+            self.join_or_queue(car_id);
+        }
         Ok(car_id)
     }
 
+    /// Roll a new vehicle's speed, along with the content pack type it
+    /// inherits from the building that owns it
+    pub(super) fn roll_vehicle_speed(
+        &mut self,
+        vehicle_type: VehicleType,
+        origin_apartment: Option<ApartmentId>,
+        origin_factory: Option<FactoryId>,
+    ) -> (f32, Option<String>) {
+        // Vehicles inherit the content pack type of the building that owns them
+        let owner_variant = match vehicle_type {
+            VehicleType::Car => origin_apartment
+                .and_then(|id| self.apartments.get(&id))
+                .and_then(|apartment| apartment.variant.as_deref()),
+            VehicleType::Truck => origin_factory
+                .and_then(|id| self.factories.get(&id))
+                .and_then(|factory| factory.variant.as_deref()),
+            VehicleType::Ambulance | VehicleType::TowTruck | VehicleType::Van => None,
+        };
+        let vehicle_def = owner_variant
+            .and_then(|name| self.content.building(name))
+            .and_then(|building| building.vehicle.as_deref())
+            .and_then(|name| self.content.vehicle(name))
+            .map(|def| (def.name.clone(), def.min_speed, def.max_speed));
+
+        // Generate random speed
+        let speed = match &vehicle_def {
+            Some((_, min_speed, max_speed)) if min_speed < max_speed => {
+                self.random_range(*min_speed..*max_speed)
+            }
+            Some((_, min_speed, _)) => *min_speed,
+            None => self.random_range(vehicle_type.speed_range()),
+        };
+        (speed, vehicle_def.map(|(name, _, _)| name))
+    }
+
     /// Update all cars in the simulation
     fn update_cars(&mut self, delta_secs: f32) -> Vec<(CarId, CarUpdateResult)> {
+        let rules = self.intersection_rules();
         car_manager::update_cars(
             delta_secs,
             &mut self.cars,
             &mut self.road_network,
             &mut self.intersections,
+            &self.ferries,
+            rules,
+            self.curve_speed,
+            &self.handling,
+            self.driving_side,
+            &mut self.changes,
         )
     }
 
     /// Update all intersections
-    fn update_intersections(&mut self, delta_secs: f32) {
+    pub(super) fn update_intersections(&mut self, delta_secs: f32) {
         for intersection in self.intersections.values_mut() {
+            // Vehicles removed mid-crossing, such as with their road, leave
+            // the intersection free
+            if let Some(car_id) = intersection.occupied_by {
+                if !self.cars.contains_key(&car_id) {
+                    intersection.release(car_id);
+                }
+            }
+            intersection
+                .stop_queue
+                .retain(|car_id| self.cars.contains_key(car_id));
             intersection.update_timer(delta_secs);
         }
     }
 
     /// Update all shops
-    fn update_shops(&mut self, _delta_secs: f32) {
-        // Shops no longer have demand that increases over time
+    fn update_shops(&mut self, delta_secs: f32) {
+        self.sell_shop_stock(delta_secs);
     }
 
     /// Update all factories
@@ -596,15 +1524,26 @@ impl SimWorld {
 
         // Collect factory IDs to avoid borrow issues
         let factory_ids: Vec<FactoryId> = self.factories.keys().copied().collect();
+        let needs_materials = self.factory_supply_required();
 
         for factory_id in factory_ids {
+            let truck_waiting_for_route = self.factory_has_pending_trip(factory_id);
             let factory = match self.factories.get_mut(&factory_id) {
                 Some(f) => f,
                 None => continue,
             };
 
             // Update factory and get apartment_ids of workers who finished their shift
-            let finished_apartment_ids = factory.update(delta_secs);
+            let deliveries_ready = factory.deliveries_ready;
+            let shifts_worked = factory.shifts_worked;
+            let finished_apartment_ids = factory.update(delta_secs, needs_materials);
+            #[cfg(feature = "stats")]
+            self.catchment_stats.record_shifts(
+                factory_id,
+                &finished_apartment_ids,
+                factory.shifts_per_delivery,
+                factory.deliveries_ready > deliveries_ready,
+            );
 
             // Record which apartments have workers done
             for apartment_id in finished_apartment_ids {
@@ -613,17 +1552,36 @@ impl SimWorld {
 
             // If truck is available and there are deliveries ready and shops exist
             if factory.truck_available()
+                && !truck_waiting_for_route
                 && factory.deliveries_ready > 0
                 && !shop_intersections.is_empty()
             {
                 // Take a delivery for dispatch
                 if factory.take_delivery() {
+                    #[cfg(feature = "stats")]
+                    self.catchment_stats.load_truck(factory_id);
                     // Pick a random shop (use index based on factory id for determinism)
                     let shop_index = factory_id.0 .0 % shop_intersections.len();
                     let shop_intersection = shop_intersections[shop_index];
                     trucks_to_dispatch.push((factory_id, shop_intersection));
                 }
             }
+
+            if factory.deliveries_ready != deliveries_ready
+                || factory.shifts_worked != shifts_worked
+            {
+                self.changes.mark(VisualId::Factory(factory_id));
+            }
+        }
+
+        // Under a demand ramp, trucks serve the oldest open orders first
+        if !trucks_to_dispatch.is_empty() {
+            self.count_orders_en_route();
+        }
+        for (_, shop_intersection) in &mut trucks_to_dispatch {
+            if let Some(ordering_shop) = self.claim_shop_order() {
+                *shop_intersection = ordering_shop;
+            }
         }
 
         (workers_done, trucks_to_dispatch)
@@ -631,11 +1589,11 @@ impl SimWorld {
 
     /// Spawn workers from apartments to factories
     fn spawn_workers(&mut self) {
-        // Get all factories that can accept workers (truck is home)
+        // Get all factories that can accept workers (truck is home, no strike)
         let factories_accepting: Vec<(FactoryId, IntersectionId)> = self
             .factories
             .values()
-            .filter(|f| f.can_accept_workers())
+            .filter(|f| f.can_accept_workers() && !self.factory_on_strike(f.intersection_id))
             .map(|f| (f.id, f.intersection_id))
             .collect();
 
@@ -648,54 +1606,108 @@ impl SimWorld {
         
         for (apartment_id, apartment) in &self.apartments {
             let apartment_intersection = apartment.intersection_id;
+            // Under a demand ramp, workers rest at home between shifts
+            if self.time < apartment.next_departure {
+                continue;
+            }
+            // A house with a worker waiting for a route sends nobody else
+            if self.apartment_has_pending_trip(*apartment_id) {
+                continue;
+            }
             
-            // Find the first empty slot - only spawn ONE car per apartment per tick
-            for (slot_index, car_slot) in apartment.cars.iter().enumerate() {
-                // Only spawn if this slot doesn't have a car out
-                if car_slot.is_none() {
-                    apartment_slots_to_spawn.push((*apartment_id, slot_index, apartment_intersection));
-                    break; // Only spawn one car per apartment per tick
-                }
+            // Only spawn if a slot doesn't have a car or walker out - only
+            // spawn ONE car per apartment per tick
+            let free_slots = apartment.cars.iter().filter(|slot| slot.is_none()).count();
+            if free_slots > self.walkers_from(*apartment_id) + self.riders_from(*apartment_id) {
+                apartment_slots_to_spawn.push((*apartment_id, apartment_intersection));
             }
         }
 
-        // Now spawn one car per apartment (if they have an empty slot)
-        for (apartment_id, slot_index, apartment_intersection) in apartment_slots_to_spawn {
+        // Now spawn one car per apartment (if they have an empty slot), while
+        // the network is under its vehicle cap
+        for (apartment_id, apartment_intersection) in apartment_slots_to_spawn {
             // Choose random factory
-            let (_factory_id, factory_intersection) = match self.choose_random(&factories_accepting)
+            let (factory_id, factory_intersection) = match self.choose_random(&factories_accepting)
             {
                 Some(&(fid, fi)) => (fid, fi),
                 None => continue,
             };
 
-            // Spawn car going to work
-            match self.spawn_vehicle(
-                apartment_intersection,
-                factory_intersection,
-                VehicleType::Car,
-                TripType::Outbound,
-                Some(apartment_id),
-                None,
-            ) {
-                Ok(car_id) => {
-                    if let Some(apartment) = self.apartments.get_mut(&apartment_id) {
-                        apartment.cars[slot_index] = Some(car_id);
-                    }
-                }
-                Err(_) => continue,
+            // Walk there if it's close enough, which needs no room on the
+            // roads
+            if let Some(distance) = self.walking_distance(apartment_id, factory_id) {
+                #[cfg(feature = "stats")]
+                self.record_shift(apartment_id, factory_id, ShiftOutcome::Requested);
+                self.start_walk(apartment_id, factory_id, TripType::Outbound, distance);
+                continue;
+            }
+            // Likewise take the bus if a route runs between home and work
+            if let Some(route_id) =
+                self.bus_route_between(apartment_intersection, factory_intersection)
+            {
+                #[cfg(feature = "stats")]
+                self.record_shift(apartment_id, factory_id, ShiftOutcome::Requested);
+                self.start_bus_ride(route_id, apartment_id, factory_id, TripType::Outbound);
+                continue;
+            }
+            if self.at_car_cap() {
+                break;
+            }
+            #[cfg(feature = "stats")]
+            self.record_shift(apartment_id, factory_id, ShiftOutcome::Requested);
+
+            // Spawn car going to work, or wait for a route there
+            let spawn = VehicleSpawn {
+                origin_apartment: Some(apartment_id),
+                ..VehicleSpawn::new(
+                    apartment_intersection,
+                    factory_intersection,
+                    VehicleType::Car,
+                    TripType::Outbound,
+                )
+            };
+            if self.start_trip(&spawn).is_err() {
+                self.defer_trip(spawn);
             }
         }
     }
 
     /// Main simulation tick
+    ///
+    /// A tick runs in a fixed order: vehicles whose routes were found set
+    /// off; the game, scenario events, contraflow roads, intersections and
+    /// buildings update, and the trips they start are queued or spawned;
+    /// vehicles wear, break down and are towed; then every vehicle moves.
+    /// Only after all of them have moved are their arrivals handled, in
+    /// three phases (see [`Self::process_arrivals`]), so the vehicles sent
+    /// back by arrivals set off next tick. Alarms and route requests come
+    /// last.
+    ///
+    /// An idle world skips the steps with nothing to do (see
+    /// [`Self::is_idle`]) unless power saving is turned off.
     pub fn tick(&mut self, delta_secs: f32) {
         self.time += delta_secs;
+        if self.take_idle_tick() {
+            self.tick_idle(delta_secs);
+            return;
+        }
+
+        // Set off the vehicles whose routes were found since the last tick,
+        // and try again the trips that had no route if the roads have changed
+        self.set_off_routed_vehicles();
+        self.retry_pending_trips();
 
         // Update game state if enabled
         if let Some(game_state) = &mut self.game_state {
             game_state.update(delta_secs);
         }
 
+        // Start and end the scenario's road closures, strikes and surges
+        self.update_scenario_events(delta_secs);
+
+        // Turn contraflow roads round as their windows open and close
+        self.update_contraflow();
+
         // Update intersections
         self.update_intersections(delta_secs);
 
@@ -719,30 +1731,34 @@ impl SimWorld {
                 None => continue,
             };
 
-            // Spawn car returning home
-            match self.spawn_vehicle(
-                factory_intersection,
-                apartment_intersection,
-                VehicleType::Car,
-                TripType::Return,
-                Some(apartment_id),
-                Some(factory_id),
-            ) {
-                Ok(return_car_id) => {
-                    // Set apartment slot with the returning car's ID
-                    if let Some(apartment) = self.apartments.get_mut(&apartment_id) {
-                        // Find first empty slot and assign the return car
-                        for car_slot in &mut apartment.cars {
-                            if car_slot.is_none() {
-                                *car_slot = Some(return_car_id);
-                                break;
-                            }
-                        }
-                    }
-                }
-                Err(_) => {
-                    // Failed to spawn return car - slot remains empty for next spawn attempt
-                }
+            // Walk home if it's close enough
+            if let Some(distance) = self.walking_distance(apartment_id, factory_id) {
+                self.start_walk(apartment_id, factory_id, TripType::Return, distance);
+                continue;
+            }
+
+            // Ride the bus home if a route runs between the two
+            if let Some(route_id) =
+                self.bus_route_between(factory_intersection, apartment_intersection)
+            {
+                self.start_bus_ride(route_id, apartment_id, factory_id, TripType::Return);
+                continue;
+            }
+
+            // Spawn car returning home, taking up a slot at the apartment, or
+            // wait for a route there
+            let spawn = VehicleSpawn {
+                origin_apartment: Some(apartment_id),
+                origin_factory: Some(factory_id),
+                ..VehicleSpawn::new(
+                    factory_intersection,
+                    apartment_intersection,
+                    VehicleType::Car,
+                    TripType::Return,
+                )
+            };
+            if self.start_trip(&spawn).is_err() {
+                self.defer_trip(spawn);
             }
         }
 
@@ -753,273 +1769,89 @@ impl SimWorld {
                 None => continue,
             };
 
-            // Spawn truck for delivery
-            match self.spawn_vehicle(
-                factory_intersection,
-                shop_intersection,
-                VehicleType::Truck,
-                TripType::Outbound,
-                None,
-                Some(factory_id),
-            ) {
-                Ok(truck_id) => {
-                    if let Some(factory) = self.factories.get_mut(&factory_id) {
-                        factory.truck = Some(truck_id);
-                    }
-                }
-                Err(_) => {
-                    // Failed to spawn truck, return delivery to ready
-                    if let Some(factory) = self.factories.get_mut(&factory_id) {
-                        factory.deliveries_ready += 1;
-                    }
-                }
+            // Spawn truck for delivery, or hold the delivery until there's a
+            // route to the shop
+            let spawn = VehicleSpawn {
+                origin_factory: Some(factory_id),
+                ..VehicleSpawn::new(
+                    factory_intersection,
+                    shop_intersection,
+                    VehicleType::Truck,
+                    TripType::Outbound,
+                )
+            };
+            if self.start_trip(&spawn).is_err() {
+                self.defer_trip(spawn);
             }
         }
 
-        // Spawn workers from apartments
+        // Clock in and pay the workers on foot or on the bus who got there,
+        // then spawn workers from apartments and place shop orders
+        self.update_walks();
+        self.update_transit(delta_secs);
         self.spawn_workers();
-
-        // Update cars and process results
+        self.update_demand(delta_secs);
+
+        // Send out school and hospital traffic, and vans with raw materials
+        self.update_special_buildings(delta_secs);
+        self.update_terminals(delta_secs);
+
+        // Wear vehicles by the distance they drove, then stop any that
+        // break down or crash and send tow trucks to the breakdowns
+        self.update_fleet();
+        self.update_incidents(delta_secs);
+        self.dispatch_tow_trucks();
+
+        // Let vehicles waiting at their start onto the road where there's
+        // room, carry vehicles across on the ferries, then update cars and
+        // process results
+        self.release_spawn_queues();
+        self.update_ferries(delta_secs);
         let car_results = self.update_cars(delta_secs);
+        let car_results = self.reroute_off_removed_roads(car_results);
+        #[cfg(feature = "stats")]
+        self.record_approach_waits(delta_secs);
+        #[cfg(feature = "stats")]
+        self.record_recap();
+        self.update_speed_goal(delta_secs);
 
-        // Process car arrivals
-        for (car_id, result) in car_results {
-            match result {
-                CarUpdateResult::ArrivedAtDestination(dest) => {
-                    // Get car info before processing
-                    let car_info = self.cars.get(&car_id).map(|c| {
-                        (
-                            c.vehicle_type,
-                            c.trip_type,
-                            c.origin_apartment,
-                            c.origin_factory,
-                        )
-                    });
-
-                    if let Some((vehicle_type, trip_type, origin_apartment, origin_factory)) = car_info
-                    {
-                        match (vehicle_type, trip_type) {
-                            (VehicleType::Car, TripType::Outbound) => {
-                                // Worker arrived at factory - try to register them with their apartment_id
-                                let mut worker_accepted = false;
-                                let mut destination_factory: Option<FactoryId> = None;
-                                if let Some(apartment_id) = origin_apartment {
-                                    if let Some((factory_id, factory)) = self
-                                        .factories
-                                        .iter_mut()
-                                        .find(|(_, f)| f.intersection_id == dest)
-                                    {
-                                        worker_accepted = factory.receive_worker(apartment_id);
-                                        destination_factory = Some(*factory_id);
-                                    }
-                                }
-
-                                if worker_accepted {
-                                    // Clear apartment slot since worker is at factory (will be set when return car spawns)
-                                    if let Some(apartment_id) = origin_apartment {
-                                        if let Some(apartment) = self.apartments.get_mut(&apartment_id) {
-                                            for car_slot in &mut apartment.cars {
-                                                if *car_slot == Some(car_id) {
-                                                    *car_slot = None;
-                                                    break;
-                                                }
-                                            }
-                                        }
-                                    }
-                                    // Remove car from tracking while at work (will respawn when returning home)
-                                    self.road_network.remove_car_from_tracking(car_id);
-                                    self.cars.remove(&car_id);
-                                } else {
-                                    // Factory rejected worker (truck out or full), send them back home
-                                    if let Some(apartment_id) = origin_apartment {
-                                        let apartment_intersection =
-                                            self.apartments.get(&apartment_id).map(|a| a.intersection_id);
-                                        if let Some(apartment_intersection) = apartment_intersection {
-                                            // Spawn car returning home
-                                            match self.spawn_vehicle(
-                                                dest,
-                                                apartment_intersection,
-                                                VehicleType::Car,
-                                                TripType::Return,
-                                                Some(apartment_id),
-                                                destination_factory,
-                                            ) {
-                                                Ok(new_car_id) => {
-                                                    // Update apartment slot with new car_id
-                                                    if let Some(apartment) = self.apartments.get_mut(&apartment_id) {
-                                                        for car_slot in &mut apartment.cars {
-                                                            if *car_slot == Some(car_id) {
-                                                                *car_slot = Some(new_car_id);
-                                                                break;
-                                                            }
-                                                        }
-                                                    }
-                                                }
-                                                Err(_) => {
-                                                    // Failed to spawn return car, just clear the slot
-                                                    if let Some(apartment) = self.apartments.get_mut(&apartment_id) {
-                                                        for car_slot in &mut apartment.cars {
-                                                            if *car_slot == Some(car_id) {
-                                                                *car_slot = None;
-                                                                break;
-                                                            }
-                                                        }
-                                                    }
-                                                }
-                                            }
-                                        }
-                                    }
-                                    // Despawn the current car
-                                    self.road_network.remove_car_from_tracking(car_id);
-                                    self.cars.remove(&car_id);
-                                }
-                            }
-                            (VehicleType::Car, TripType::Return) => {
-                                let commute_distance = match (origin_apartment, origin_factory) {
-                                    (Some(apartment_id), Some(factory_id)) => {
-                                        let apartment_position = self
-                                            .apartments
-                                            .get(&apartment_id)
-                                            .and_then(|apartment| {
-                                                self.road_network.get_intersection_position(
-                                                    apartment.intersection_id,
-                                                )
-                                            })
-                                            .copied();
-                                        let factory_position = self
-                                            .factories
-                                            .get(&factory_id)
-                                            .and_then(|factory| {
-                                                self.road_network.get_intersection_position(
-                                                    factory.intersection_id,
-                                                )
-                                            })
-                                            .copied();
-
-                                        match (apartment_position, factory_position) {
-                                            (Some(apartment_pos), Some(factory_pos)) => {
-                                                apartment_pos.distance(&factory_pos)
-                                            }
-                                            _ => {
-                                                warn!(
-                                                    "Missing apartment or factory position for worker commute; defaulting to a zero-distance commute, which applies the maximum commute penalty"
-                                                );
-                                                0.0
-                                            }
-                                        }
-                                    }
-                                    _ => {
-                                        warn!(
-                                            "Missing worker identifiers for commute penalty; defaulting to a zero-distance commute, which applies the maximum commute penalty"
-                                        );
-                                        0.0
-                                    }
-                                };
-                                // Worker returned home - clear car reference and despawn
-                                if let Some(apartment_id) = origin_apartment {
-                                    if let Some(apartment) = self.apartments.get_mut(&apartment_id) {
-                                        // Find and clear the car slot
-                                        for car_slot in &mut apartment.cars {
-                                            if *car_slot == Some(car_id) {
-                                                *car_slot = None;
-                                                break;
-                                            }
-                                        }
-                                    }
-                                }
-                                // Track worker trip completion in game state
-                                if let Some(game_state) = &mut self.game_state {
-                                    game_state.complete_worker_trip(commute_distance);
-                                }
-                                self.road_network.remove_car_from_tracking(car_id);
-                                self.cars.remove(&car_id);
-                            }
-                            (VehicleType::Truck, TripType::Outbound) => {
-                                // Truck delivered to shop
-                                if let Some(shop) =
-                                    self.shops.values_mut().find(|s| s.intersection_id == dest)
-                                {
-                                    shop.receive_delivery();
-                                }
-                                // Now spawn truck returning to factory
-                                if let Some(factory_id) = origin_factory {
-                                    let factory_intersection =
-                                        self.factories.get(&factory_id).map(|f| f.intersection_id);
-                                    if let Some(factory_intersection) = factory_intersection {
-                                        // Spawn truck returning
-                                        match self.spawn_vehicle(
-                                            dest,
-                                            factory_intersection,
-                                            VehicleType::Truck,
-                                            TripType::Return,
-                                            None,
-                                            Some(factory_id),
-                                        ) {
-                                            Ok(new_truck_id) => {
-                                                if let Some(factory) =
-                                                    self.factories.get_mut(&factory_id)
-                                                {
-                                                    factory.truck = Some(new_truck_id);
-                                                }
-                                            }
-                                            Err(_) => {
-                                                // Truck can't return, just clear reference
-                                                if let Some(factory) =
-                                                    self.factories.get_mut(&factory_id)
-                                                {
-                                                    factory.truck = None;
-                                                }
-                                            }
-                                        }
-                                    }
-                                }
-                                // Despawn old truck entity
-                                self.road_network.remove_car_from_tracking(car_id);
-                                self.cars.remove(&car_id);
-                            }
-                            (VehicleType::Truck, TripType::Return) => {
-                                // Truck returned to factory - clear reference and despawn
-                                if let Some(factory_id) = origin_factory {
-                                    if let Some(factory) = self.factories.get_mut(&factory_id) {
-                                        factory.truck = None;
-                                    }
-                                }
-                                // Track shop delivery completion in game state
-                                if let Some(game_state) = &mut self.game_state {
-                                    game_state.complete_shop_delivery();
-                                }
-                                self.road_network.remove_car_from_tracking(car_id);
-                                self.cars.remove(&car_id);
-                            }
+        // Handle the vehicles that arrived, sending back the ones with a trip
+        // home to make once every arrival has taken effect
+        self.process_arrivals(car_results);
+
+        // Raise and clear the economy's alarms
+        self.update_alarms();
+
+        // Hand this tick's route requests to the route pool
+        self.dispatch_route_requests();
+
+        self.warnings.flush(self.time);
+    }
+
+    /// Remove a vehicle whose trip ended unexpectedly, clearing the
+    /// references its apartment or factory hold to it
+    pub(super) fn end_trip_unexpectedly(&mut self, car_id: CarId) {
+        if let Some(car) = self.cars.get(&car_id) {
+            if let Some(apartment_id) = car.origin_apartment {
+                if let Some(apartment) = self.apartments.get_mut(&apartment_id) {
+                    // Find and clear the car slot
+                    for car_slot in &mut apartment.cars {
+                        if *car_slot == Some(car_id) {
+                            *car_slot = None;
+                            break;
                         }
                     }
                 }
-                CarUpdateResult::Despawn => {
-                    // Clean up references for unexpectedly despawned vehicles
-                    if let Some(car) = self.cars.get(&car_id) {
-                        if let Some(apartment_id) = car.origin_apartment {
-                            if let Some(apartment) = self.apartments.get_mut(&apartment_id) {
-                                // Find and clear the car slot
-                                for car_slot in &mut apartment.cars {
-                                    if *car_slot == Some(car_id) {
-                                        *car_slot = None;
-                                        break;
-                                    }
-                                }
-                            }
-                        }
-                        if let Some(factory_id) = car.origin_factory {
-                            if let Some(factory) = self.factories.get_mut(&factory_id) {
-                                factory.truck = None;
-                            }
-                        }
+            }
+            if let Some(factory_id) = car.origin_factory {
+                if let Some(factory) = self.factories.get_mut(&factory_id) {
+                    if factory.truck == Some(car_id) {
+                        factory.truck = None;
                     }
-                    self.road_network.remove_car_from_tracking(car_id);
-                    self.cars.remove(&car_id);
                 }
-                CarUpdateResult::Continue => {}
             }
         }
+        self.remove_car(car_id);
     }
 
     /// Create a default test world with some roads and buildings
@@ -1125,10 +1957,12 @@ impl SimWorld {
         println!("--- Factories ---");
         for factory in self.factories.values() {
             println!(
-                "  Factory {:?}: deliveries={}/{}, workers={}, truck={}",
+                "  Factory {:?}: deliveries={}/{}, shifts={}/{}, workers={}, truck={}",
                 factory.id.0,
                 factory.deliveries_ready,
                 factory.max_deliveries,
+                factory.shifts_worked,
+                factory.shifts_per_delivery,
                 factory.workers.len(),
                 if factory.truck.is_some() {
                     "out"
@@ -1137,6 +1971,25 @@ impl SimWorld {
                 }
             );
         }
+        if self.factory_supply_required() {
+            println!("--- Raw Materials ---");
+            for factory in self.factories.values() {
+                println!(
+                    "  Factory {:?}: materials={}",
+                    factory.id.0, factory.materials
+                );
+            }
+            for terminal in self
+                .special_buildings
+                .values()
+                .filter(|building| building.kind == SpecialKind::Terminal)
+            {
+                println!(
+                    "  Terminal {:?}: materials={}, van loads delivered={}",
+                    terminal.id.0, terminal.materials, terminal.supplies_delivered
+                );
+            }
+        }
 
         // Shop status
         println!("--- Shops ---");
@@ -1174,6 +2027,92 @@ impl SimWorld {
             "  Apartments waiting: {}/{}",
             demand.apartments_waiting, demand.total_apartments
         );
+        if !self.pending_trips.is_empty() {
+            println!(
+                "  Trips waiting for connection: {}",
+                self.pending_trips.len()
+            );
+        }
+
+        #[cfg(feature = "stats")]
+        self.print_statistics();
+    }
+
+    /// Print the trip and intersection statistics part of the summary
+    #[cfg(feature = "stats")]
+    fn print_statistics(&self) {
+        // Completed trips
+        println!("--- Completed Trips ---");
+        for row in self.trip_statistics() {
+            println!(
+                "  {} ({:?}): trips={}, avg={:.1}s, longest={:.1}s, fuel={:.2}L, co2={:.2}kg",
+                row.purpose.label(),
+                row.vehicle_type,
+                row.stats.trips,
+                row.stats.average_secs(),
+                row.stats.longest_secs,
+                row.stats.fuel_litres,
+                row.stats.co2_kg
+            );
+        }
+        let emissions = self.emissions();
+        if emissions.trips > 0 {
+            println!(
+                "  Emissions: {:.1}kg CO2 from {:.1}L of fuel, {:.2}kg per trip, {:.0}% idling",
+                emissions.co2_kg,
+                emissions.fuel_litres,
+                emissions.co2_per_trip(),
+                emissions.idle_share().unwrap_or(0.0) * 100.0
+            );
+        }
+        let modes = self.commute_modes();
+        if let Some(walk_share) = modes.walk_share() {
+            println!(
+                "  Worker trips: {} driven, {} walked ({:.0}%), avg walk {:.1}s",
+                modes.driven,
+                modes.walked,
+                walk_share * 100.0,
+                modes.average_walk_secs()
+            );
+        }
+        if !self.bus_routes.is_empty() {
+            println!(
+                "  Bus routes: {}, {} boardings, {} worker trips by bus ({:.0}%)",
+                self.bus_routes.len(),
+                self.transit_ridership(),
+                modes.rode,
+                modes.ride_share().unwrap_or(0.0) * 100.0
+            );
+        }
+
+        // Vehicles that had to wait at their start for room on the road
+        let delays = self.spawn_delay_stats();
+        if delays.trips > 0 {
+            println!(
+                "  Delayed spawns: {}, avg wait={:.1}s, longest={:.1}s",
+                delays.trips,
+                delays.average_secs(),
+                delays.longest_secs
+            );
+        }
+
+        // Where signals or roundabouts would help most
+        let suggestions = self.signal_suggestions(5);
+        if !suggestions.is_empty() {
+            println!("--- Signal Suggestions ---");
+            for (rank, suggestion) in suggestions.iter().enumerate() {
+                println!(
+                    "  {}. {} at ({:.1}, {:.1}): {} roads in, {:.1}s wait/vehicle, saves ~{:.1}s/min",
+                    rank + 1,
+                    suggestion.treatment.label(),
+                    suggestion.position.x,
+                    suggestion.position.z,
+                    suggestion.approaches,
+                    suggestion.delay_per_vehicle(),
+                    suggestion.projected_savings_per_minute
+                );
+            }
+        }
     }
 
     /// Calculate global demand metrics
@@ -0,0 +1,240 @@
+//! Achievement panel and unlock toasts
+//!
+//! Evaluates the simulation's [`AchievementTracker`] every frame, saves new
+//! unlocks to disk and pops up a short toast. Press H to show the full list.
+//! Other notices share the toast stack through [`spawn_toast`].
+
+use bevy::prelude::*;
+use std::path::PathBuf;
+
+use super::components::SimWorldResource;
+use super::worlds::WorldSlots;
+use crate::simulation::{Achievement, AchievementTracker};
+
+/// How long an unlock toast stays on screen, in real seconds
+const TOAST_SECS: f32 = 4.0;
+
+/// Unlocked achievements and where they are saved
+#[derive(Resource, Default)]
+pub struct Achievements {
+    pub tracker: AchievementTracker,
+    /// File unlocks are saved to; `None` keeps them in memory only
+    pub path: Option<PathBuf>,
+}
+
+impl Achievements {
+    /// Load achievements from a file, falling back to none unlocked on error
+    pub fn load(path: PathBuf) -> Self {
+        let tracker = AchievementTracker::load(&path).unwrap_or_else(|e| {
+            warn!("{:#}", e);
+            AchievementTracker::default()
+        });
+        Self {
+            tracker,
+            path: Some(path),
+        }
+    }
+}
+
+/// Marker for the achievement list panel
+#[derive(Component)]
+pub struct AchievementPanel;
+
+/// Marker for the achievement list text
+#[derive(Component)]
+pub struct AchievementListText;
+
+/// A toast announcing an unlock, despawned after [`TOAST_SECS`]
+#[derive(Component)]
+pub struct AchievementToast {
+    age: f32,
+}
+
+/// Container the toasts stack inside
+#[derive(Component)]
+pub struct AchievementToastStack;
+
+/// System to create the (hidden) achievement panel and the toast stack
+pub fn setup_achievement_ui(mut commands: Commands) {
+    commands
+        .spawn((
+            AchievementPanel,
+            Node {
+                position_type: PositionType::Absolute,
+                top: Val::Px(150.0),
+                left: Val::Px(10.0),
+                padding: UiRect::all(Val::Px(10.0)),
+                ..default()
+            },
+            BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.8)),
+            Visibility::Hidden,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                AchievementListText,
+                Text::new(""),
+                TextFont {
+                    font_size: 14.0,
+                    ..default()
+                },
+                TextColor(Color::WHITE),
+            ));
+        });
+
+    commands.spawn((
+        AchievementToastStack,
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(60.0),
+            right: Val::Px(10.0),
+            flex_direction: FlexDirection::Column,
+            row_gap: Val::Px(6.0),
+            ..default()
+        },
+    ));
+}
+
+/// System to evaluate achievements, save unlocks and show a toast for each
+pub fn evaluate_achievements(
+    mut commands: Commands,
+    sim_world: Res<SimWorldResource>,
+    slots: Res<WorldSlots>,
+    mut achievements: ResMut<Achievements>,
+    stack_query: Query<Entity, With<AchievementToastStack>>,
+) {
+    // Evaluating updates internal bookkeeping every frame; only flag the
+    // resource as changed when something was actually unlocked
+    let unlocked = achievements
+        .bypass_change_detection()
+        .tracker
+        .evaluate(slots.live(&sim_world.0));
+    if unlocked.is_empty() {
+        return;
+    }
+    achievements.set_changed();
+
+    if let Some(path) = &achievements.path {
+        if let Err(e) = achievements.tracker.save(path) {
+            warn!("{:#}", e);
+        }
+    }
+
+    let Ok(stack) = stack_query.single() else {
+        return;
+    };
+    for achievement in unlocked {
+        info!("Achievement unlocked: {}", achievement.title());
+        spawn_toast(
+            &mut commands,
+            stack,
+            &format!("🏆 {}", achievement.title()),
+            &achievement.description(),
+            Color::srgb(1.0, 0.8, 0.2),
+            Color::srgba(0.15, 0.1, 0.0, 0.9),
+        );
+    }
+}
+
+/// Add a toast with a title and description to the stack
+pub fn spawn_toast(
+    commands: &mut Commands,
+    stack: Entity,
+    title: &str,
+    description: &str,
+    accent: Color,
+    background: Color,
+) {
+    let toast = commands
+        .spawn((
+            AchievementToast { age: 0.0 },
+            Node {
+                padding: UiRect::all(Val::Px(10.0)),
+                border: UiRect::all(Val::Px(2.0)),
+                flex_direction: FlexDirection::Column,
+                ..default()
+            },
+            BackgroundColor(background),
+            BorderColor::all(accent),
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Text::new(title),
+                TextFont {
+                    font_size: 16.0,
+                    ..default()
+                },
+                TextColor(accent),
+            ));
+            parent.spawn((
+                Text::new(description),
+                TextFont {
+                    font_size: 12.0,
+                    ..default()
+                },
+                TextColor(Color::srgb(0.9, 0.9, 0.9)),
+            ));
+        })
+        .id();
+    commands.entity(stack).add_child(toast);
+}
+
+/// System to fade out and remove old toasts
+pub fn update_achievement_toasts(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut toast_query: Query<(Entity, &mut AchievementToast, &mut BackgroundColor)>,
+) {
+    for (entity, mut toast, mut background) in toast_query.iter_mut() {
+        toast.age += time.delta_secs();
+        if toast.age >= TOAST_SECS {
+            commands.entity(entity).despawn();
+        } else {
+            // Fade over the last second
+            let alpha = (TOAST_SECS - toast.age).min(1.0) * 0.9;
+            background.0.set_alpha(alpha);
+        }
+    }
+}
+
+/// System to toggle the achievement panel (H) and keep its list current
+pub fn update_achievement_panel(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    achievements: Res<Achievements>,
+    mut panel_query: Query<&mut Visibility, With<AchievementPanel>>,
+    mut text_query: Query<&mut Text, With<AchievementListText>>,
+) {
+    if keyboard.just_pressed(KeyCode::KeyH) {
+        for mut visibility in panel_query.iter_mut() {
+            *visibility = match *visibility {
+                Visibility::Hidden => Visibility::Inherited,
+                _ => Visibility::Hidden,
+            };
+        }
+    }
+
+    if !achievements.is_changed() {
+        return;
+    }
+    let unlocked_count = achievements.tracker.unlocked.len();
+    let mut list = format!(
+        "Achievements ({}/{})",
+        unlocked_count,
+        Achievement::ALL.len()
+    );
+    for achievement in Achievement::ALL {
+        let mark = if achievements.tracker.is_unlocked(achievement) {
+            "✔"
+        } else {
+            "✘"
+        };
+        list.push_str(&format!(
+            "\n{} {} - {}",
+            mark,
+            achievement.title(),
+            achievement.description()
+        ));
+    }
+    for mut text in text_query.iter_mut() {
+        **text = list.clone();
+    }
+}
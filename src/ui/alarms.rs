@@ -0,0 +1,152 @@
+//! Alarm banner
+//!
+//! While any of the economy's alarms is going off a red banner across the
+//! top of the screen lists them with their values. Jump to moves the camera
+//! over the road traffic was densest on when the newest of them went off.
+
+use bevy::prelude::*;
+
+use super::components::{MainCamera, SimWorldResource};
+use crate::simulation::{AlarmReport, Position};
+
+/// Marker for the alarm banner
+#[derive(Component)]
+pub struct AlarmBanner;
+
+/// Text listing the alarms going off
+#[derive(Component)]
+pub struct AlarmBannerText;
+
+/// Button moving the camera to the newest alarm
+#[derive(Component)]
+pub struct AlarmJumpButton;
+
+/// System to create the (hidden) alarm banner
+pub fn setup_alarm_ui(mut commands: Commands) {
+    commands
+        .spawn((
+            AlarmBanner,
+            Node {
+                position_type: PositionType::Absolute,
+                top: Val::Px(10.0),
+                left: Val::Percent(30.0),
+                width: Val::Percent(40.0),
+                padding: UiRect::all(Val::Px(10.0)),
+                border: UiRect::all(Val::Px(2.0)),
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::SpaceBetween,
+                column_gap: Val::Px(10.0),
+                ..default()
+            },
+            BorderColor::all(Color::srgb(1.0, 0.3, 0.3)),
+            BackgroundColor(Color::srgba(0.6, 0.0, 0.0, 0.9)),
+            Visibility::Hidden,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                AlarmBannerText,
+                Text::new(""),
+                TextFont {
+                    font_size: 18.0,
+                    ..default()
+                },
+                TextColor(Color::WHITE),
+            ));
+            parent
+                .spawn((
+                    AlarmJumpButton,
+                    Button,
+                    Node {
+                        padding: UiRect::axes(Val::Px(8.0), Val::Px(4.0)),
+                        border: UiRect::all(Val::Px(1.0)),
+                        ..default()
+                    },
+                    BorderColor::all(Color::WHITE),
+                    BackgroundColor(Color::srgb(0.35, 0.05, 0.05)),
+                ))
+                .with_children(|button| {
+                    button.spawn((
+                        Text::new("Jump to"),
+                        TextFont {
+                            font_size: 14.0,
+                            ..default()
+                        },
+                        TextColor(Color::WHITE),
+                    ));
+                });
+        });
+}
+
+/// A line for an alarm going off
+fn alarm_line(report: &AlarmReport) -> String {
+    format!(
+        "🚨 {}: {} {:.2} since {:.0}s",
+        report.name,
+        report.metric.label(),
+        report.value,
+        report.time
+    )
+}
+
+/// System to show the banner while alarms are going off
+pub fn update_alarm_banner(
+    sim_world: Res<SimWorldResource>,
+    mut banner_query: Query<&mut Visibility, With<AlarmBanner>>,
+    mut text_query: Query<&mut Text, With<AlarmBannerText>>,
+) {
+    let lines: Vec<String> = sim_world.0.active_alarms().map(alarm_line).collect();
+    let wanted = if lines.is_empty() {
+        Visibility::Hidden
+    } else {
+        Visibility::Inherited
+    };
+    for mut visibility in banner_query.iter_mut() {
+        if *visibility != wanted {
+            *visibility = wanted;
+        }
+    }
+    let description = lines.join("\n");
+    for mut text in text_query.iter_mut() {
+        if **text != description {
+            **text = description.clone();
+        }
+    }
+}
+
+/// Where the newest alarm going off happened, if it has a location
+fn newest_alarm_location(reports: impl Iterator<Item = AlarmReport>) -> Option<Position> {
+    reports
+        .filter(|report| report.location.is_some())
+        .max_by(|a, b| a.time.total_cmp(&b.time))
+        .and_then(|report| report.location)
+}
+
+/// System to move the camera to the newest alarm when Jump to is clicked
+pub fn handle_alarm_jump(
+    sim_world: Res<SimWorldResource>,
+    interaction_query: Query<&Interaction, (Changed<Interaction>, With<AlarmJumpButton>)>,
+    mut camera_query: Query<&mut Transform, With<MainCamera>>,
+) {
+    if !interaction_query
+        .iter()
+        .any(|interaction| *interaction == Interaction::Pressed)
+    {
+        return;
+    }
+    let Some(target) = newest_alarm_location(sim_world.0.active_alarms().cloned()) else {
+        return;
+    };
+    let Ok(mut transform) = camera_query.single_mut() else {
+        return;
+    };
+    // Slide the camera so the ground it looks at is the alarm's road,
+    // keeping its height and angle
+    let forward = transform.forward();
+    let pivot = if forward.y < -f32::EPSILON {
+        transform.translation + forward * (-transform.translation.y / forward.y)
+    } else {
+        Vec3::new(transform.translation.x, 0.0, transform.translation.z)
+    };
+    transform.translation.x += target.x - pivot.x;
+    transform.translation.z += target.z - pivot.z;
+}
@@ -0,0 +1,168 @@
+//! Macroscopic traffic mode
+//!
+//! Press F4 to switch from simulating each vehicle to a traffic assignment:
+//! the city's trips settled on its roads as steady flows (see
+//! [`SimWorld::assign_traffic`]). Each road is drawn from green when empty
+//! through yellow to red at capacity, and the assignment is solved again
+//! whenever a road or building changes, so a layout can be judged as soon
+//! as it is built. Offline, the vehicles stand still until F4 switches
+//! back; a multiplayer session carries on simulating for everyone.
+//!
+//! [`SimWorld::assign_traffic`]: crate::simulation::SimWorld::assign_traffic
+
+use bevy::prelude::*;
+
+use super::components::SimWorldResource;
+use super::multiplayer::MultiplayerSession;
+use crate::simulation::{Assignment, AssignmentConfig, SimWorld};
+
+/// Height of the flows above the road surface
+const OVERLAY_HEIGHT: f32 = 0.2;
+
+/// How far a two-way road's lanes are drawn from its center line
+const LANE_OFFSET: f32 = 0.5;
+
+/// Which traffic model is shown, and the last assignment solved
+#[derive(Resource, Default)]
+pub struct TrafficModel {
+    /// Whether flows are shown instead of vehicles being simulated
+    pub macroscopic: bool,
+    assignment: Option<Assignment>,
+    /// Roads and buildings the assignment was solved for
+    solved_for: Option<LayoutKey>,
+}
+
+/// What an assignment depends on, cheap enough to compare every frame
+type LayoutKey = (u64, usize, usize, usize, u32);
+
+fn layout_key(world: &SimWorld) -> LayoutKey {
+    (
+        world.road_network.layout_version(),
+        world.apartments.len(),
+        world.factories.len(),
+        world.shops.len(),
+        world.demand_level().to_bits(),
+    )
+}
+
+/// Marker for the assignment summary text
+#[derive(Component)]
+pub struct AssignmentText;
+
+/// System to create the (hidden) assignment summary
+pub fn setup_assignment_ui(mut commands: Commands) {
+    commands.spawn((
+        AssignmentText,
+        Text::new(""),
+        TextFont {
+            font_size: 16.0,
+            ..default()
+        },
+        TextColor(Color::WHITE),
+        Node {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(50.0),
+            left: Val::Px(10.0),
+            ..default()
+        },
+        Visibility::Hidden,
+    ));
+}
+
+/// System to switch between the microscopic and macroscopic models (F4)
+pub fn handle_assignment_keyboard(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    session: Res<MultiplayerSession>,
+    mut model: ResMut<TrafficModel>,
+    mut text_query: Query<&mut Visibility, With<AssignmentText>>,
+) {
+    if !keyboard.just_pressed(KeyCode::F4) {
+        return;
+    }
+    model.macroscopic = !model.macroscopic;
+    model.solved_for = None;
+    if model.macroscopic && !matches!(*session, MultiplayerSession::Offline) {
+        info!("Macroscopic traffic shown; the session keeps simulating vehicles");
+    } else {
+        info!(
+            "{} traffic model",
+            if model.macroscopic { "Macroscopic" } else { "Microscopic" }
+        );
+    }
+    for mut visibility in text_query.iter_mut() {
+        *visibility = if model.macroscopic {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+    }
+}
+
+/// System to solve the assignment again when the layout changes
+pub fn update_assignment(
+    sim_world: Res<SimWorldResource>,
+    mut model: ResMut<TrafficModel>,
+    mut text_query: Query<&mut Text, With<AssignmentText>>,
+) {
+    if !model.macroscopic {
+        return;
+    }
+    let key = layout_key(&sim_world.0);
+    if model.solved_for == Some(key) {
+        return;
+    }
+    let assignment = sim_world.0.assign_traffic(&AssignmentConfig::default());
+    let mut summary = format!(
+        "Macroscopic traffic (F4): {:.1} vehicles/min, average trip {:.1}s",
+        assignment.routed_per_minute,
+        assignment.average_trip_secs()
+    );
+    if assignment.unrouted_per_minute > 0.0 {
+        summary.push_str(&format!(
+            ", {:.1}/min with no route",
+            assignment.unrouted_per_minute
+        ));
+    }
+    for mut text in text_query.iter_mut() {
+        **text = summary.clone();
+    }
+    model.assignment = Some(assignment);
+    model.solved_for = Some(key);
+}
+
+/// Color for a road carrying `load` times its capacity
+fn load_color(load: f32) -> Color {
+    let load = load.clamp(0.0, 1.0);
+    // Green through yellow to red
+    Color::srgb((2.0 * load).min(1.0), (2.0 * (1.0 - load)).min(1.0), 0.1)
+}
+
+/// System to draw each road colored by its assigned flow
+pub fn draw_assignment(
+    model: Res<TrafficModel>,
+    sim_world: Res<SimWorldResource>,
+    mut gizmos: Gizmos,
+) {
+    let Some(assignment) = model.assignment.as_ref().filter(|_| model.macroscopic) else {
+        return;
+    };
+    let network = &sim_world.0.road_network;
+    for flow in assignment.roads.values() {
+        let Some(road) = network.get_road(flow.road_id) else {
+            continue;
+        };
+        let (Some(start), Some(end)) = (
+            network.get_intersection_position(road.start_intersection),
+            network.get_intersection_position(road.end_intersection),
+        ) else {
+            continue;
+        };
+        let lane = if road.is_two_way { LANE_OFFSET } else { 0.0 };
+        let offset = sim_world.0.driving_side.lane_offset(start, end, lane);
+        gizmos.line(
+            Vec3::new(start.x + offset.x, OVERLAY_HEIGHT, start.z + offset.z),
+            Vec3::new(end.x + offset.x, OVERLAY_HEIGHT, end.z + offset.z),
+            load_color(flow.volume_to_capacity),
+        );
+    }
+}
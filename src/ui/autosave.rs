@@ -0,0 +1,103 @@
+//! Autosaving the city
+//!
+//! Offline games keep a [`SaveFile`] of every command that was applied.
+//! Every [`AUTOSAVE_INTERVAL_SECS`] it is written out in the background with
+//! the game's progress. A final save is written when the app exits. A
+//! [`RunningMarker`] next to the save tells the next launch whether this one
+//! exited cleanly.
+
+use anyhow::Result;
+use bevy::prelude::*;
+use std::path::PathBuf;
+
+use super::components::SimWorldResource;
+use super::worlds::WorldSlots;
+use crate::simulation::{Autosaver, RunningMarker, SaveFile};
+
+/// Seconds of play between autosaves
+pub const AUTOSAVE_INTERVAL_SECS: f32 = 30.0;
+
+/// The city's save and where it is autosaved to
+#[derive(Resource)]
+pub struct Autosave {
+    /// Save of everything built so far
+    pub save: SaveFile,
+    autosaver: Autosaver,
+    marker: Option<RunningMarker>,
+    since_save: f32,
+}
+
+impl Autosave {
+    /// Start autosaving a city to `path`, marking the session as running
+    pub fn start(save: SaveFile, path: PathBuf) -> Result<Self> {
+        let marker = RunningMarker::create(&path)?;
+        Ok(Self {
+            save,
+            autosaver: Autosaver::new(path),
+            marker: Some(marker),
+            since_save: 0.0,
+        })
+    }
+}
+
+/// System to write the save in the background every
+/// [`AUTOSAVE_INTERVAL_SECS`]
+pub fn autosave_city(
+    time: Res<Time>,
+    sim_world: Res<SimWorldResource>,
+    slots: Res<WorldSlots>,
+    autosave: Option<ResMut<Autosave>>,
+) {
+    let Some(mut autosave) = autosave else {
+        return;
+    };
+    autosave.since_save += time.delta_secs();
+    if autosave.since_save < AUTOSAVE_INTERVAL_SECS {
+        return;
+    }
+    let autosave = &mut *autosave;
+    autosave.save.record_game_state(slots.live(&sim_world.0));
+    match autosave.autosaver.autosave(&autosave.save) {
+        Ok(true) => autosave.since_save = 0.0,
+        // Try again next frame
+        Ok(false) => {}
+        Err(e) => {
+            warn!("Autosave failed: {:#}", e);
+            autosave.since_save = 0.0;
+        }
+    }
+}
+
+/// System to write a last save and clear the running marker as the app exits
+pub fn finish_autosave(
+    mut exits: MessageReader<AppExit>,
+    sim_world: Res<SimWorldResource>,
+    slots: Res<WorldSlots>,
+    autosave: Option<ResMut<Autosave>>,
+) {
+    if exits.read().count() == 0 {
+        return;
+    }
+    let Some(mut autosave) = autosave else {
+        return;
+    };
+    let autosave = &mut *autosave;
+    let Some(marker) = autosave.marker.take() else {
+        return;
+    };
+    autosave.save.record_game_state(slots.live(&sim_world.0));
+    let saved = autosave
+        .autosaver
+        .finish()
+        .and_then(|()| autosave.save.save(autosave.autosaver.path()));
+    match saved {
+        Ok(()) => {
+            info!("Saved the city to {}", autosave.autosaver.path().display());
+            if let Err(e) = marker.clear() {
+                warn!("{:#}", e);
+            }
+        }
+        // Leave the marker so the next launch offers the last autosave
+        Err(e) => warn!("Final save failed: {:#}", e),
+    }
+}
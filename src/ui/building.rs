@@ -4,15 +4,16 @@ use bevy::ecs::hierarchy::ChildSpawnerCommands;
 use bevy::prelude::*;
 
 use super::components::{
-    BuildModeButton, BuildingMode, BuildingState, EntityMappings, GhostPreview, MainCamera,
-    SimWorldResource,
+    BuildModeButton, BuildingMode, BuildingState, GhostPreview, MainCamera, PendingCommands,
+    PlacementHint, SimWorldResource,
 };
-use super::spawner::{
-    spawn_factory_visual, spawn_apartment_visual, spawn_intersection_visual, spawn_road_visual,
-    spawn_shop_visual, ApartmentVisualAssets,
+use crate::simulation::{
+    cul_de_sac_cost, cul_de_sac_lots, junction_cost, junction_layout, BuildCommand, BuildingKind,
+    BuildingSite, ContentRegistry, DecorationKind, JunctionKind, Position, RoadKind, SpecialKind,
+    CUL_DE_SAC_MAX_LOTS,
 };
-use crate::simulation::Position;
 use crate::ui::components::GlobalDemandText;
+use crate::ui::multiplayer::MultiplayerSession;
 
 /// System to setup the building mode UI
 pub fn setup_building_ui(mut commands: Commands) {
@@ -44,6 +45,28 @@ pub fn setup_building_ui(mut commands: Commands) {
                 GlobalDemandText::Money,
             ));
 
+            // Insurance status
+            parent.spawn((
+                Text::new("Insurance: off [I]"),
+                TextFont {
+                    font_size: 14.0,
+                    ..default()
+                },
+                TextColor(Color::srgb(1.0, 0.7, 0.4)),
+                GlobalDemandText::Insurance,
+            ));
+
+            // Fleet wear
+            parent.spawn((
+                Text::new("Fleet Wear: 0%"),
+                TextFont {
+                    font_size: 14.0,
+                    ..default()
+                },
+                TextColor(Color::srgb(0.7, 0.8, 1.0)),
+                GlobalDemandText::Fleet,
+            ));
+
             // Worker trips
             parent.spawn((
                 Text::new("Worker Trips: 0"),
@@ -167,7 +190,110 @@ pub fn setup_building_ui(mut commands: Commands) {
                 "Shop [4] - $300",
                 Color::srgb(0.8, 0.4, 0.6),
             );
+            // Signal editor button
+            spawn_build_button(
+                parent,
+                BuildingMode::Signal,
+                "Signals [5]",
+                Color::srgb(0.6, 0.55, 0.2),
+            );
+            // School button
+            spawn_build_button(
+                parent,
+                BuildingMode::School,
+                "School [6] - $400",
+                Color::srgb(0.75, 0.6, 0.15),
+            );
+            // Hospital button
+            spawn_build_button(
+                parent,
+                BuildingMode::Hospital,
+                "Hospital [7] - $800",
+                Color::srgb(0.7, 0.25, 0.25),
+            );
+            // Cul-de-sac button
+            spawn_build_button(
+                parent,
+                BuildingMode::CulDeSac,
+                "Cul-de-sac [8]",
+                Color::srgb(0.55, 0.5, 0.35),
+            );
+            // Junction preset button
+            spawn_build_button(
+                parent,
+                BuildingMode::Junction,
+                "Junction [F8]",
+                Color::srgb(0.4, 0.4, 0.5),
+            );
+            // Depot button
+            spawn_build_button(
+                parent,
+                BuildingMode::Depot,
+                "Depot [9] - $350",
+                Color::srgb(0.75, 0.45, 0.15),
+            );
+            // Rail terminal button
+            spawn_build_button(
+                parent,
+                BuildingMode::Terminal,
+                "Rail Terminal [0] - $600",
+                Color::srgb(0.45, 0.35, 0.3),
+            );
+            // Bridge button
+            spawn_build_button(
+                parent,
+                BuildingMode::Bridge,
+                "Bridge [B] - $400",
+                Color::srgb(0.5, 0.45, 0.4),
+            );
+            // Ferry button
+            spawn_build_button(
+                parent,
+                BuildingMode::Ferry,
+                "Ferry [F] - $250",
+                Color::srgb(0.15, 0.3, 0.55),
+            );
+            // Tree button
+            spawn_build_button(
+                parent,
+                BuildingMode::Tree,
+                "Tree [O] - $20",
+                Color::srgb(0.15, 0.45, 0.2),
+            );
+            // Park button
+            spawn_build_button(
+                parent,
+                BuildingMode::Park,
+                "Park [P] - $150",
+                Color::srgb(0.35, 0.6, 0.3),
+            );
+            // Bus route button
+            spawn_build_button(
+                parent,
+                BuildingMode::BusRoute,
+                "Bus Route [;] - $40/stop",
+                Color::srgb(0.85, 0.5, 0.1),
+            );
         });
+
+    // Why a building can't go at the cursor, or what its driveway costs,
+    // shown beside it
+    commands.spawn((
+        PlacementHint,
+        Text::new(""),
+        TextFont {
+            font_size: 14.0,
+            ..default()
+        },
+        TextColor(Color::srgb(1.0, 0.4, 0.4)),
+        Node {
+            position_type: PositionType::Absolute,
+            padding: UiRect::all(Val::Px(4.0)),
+            ..default()
+        },
+        BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.7)),
+        Visibility::Hidden,
+    ));
 }
 
 fn spawn_demand_text(
@@ -257,9 +383,21 @@ pub fn handle_build_buttons(
         // Update background to show selected state
         let base_color = match button.0 {
             BuildingMode::Road => Color::srgb(0.3, 0.3, 0.3),
+            BuildingMode::Bridge => Color::srgb(0.5, 0.45, 0.4),
+            BuildingMode::Ferry => Color::srgb(0.15, 0.3, 0.55),
             BuildingMode::Apartment => Color::srgb(0.7, 0.6, 0.4),
             BuildingMode::Factory => Color::srgb(0.5, 0.5, 0.7),
             BuildingMode::Shop => Color::srgb(0.8, 0.4, 0.6),
+            BuildingMode::Signal => Color::srgb(0.6, 0.55, 0.2),
+            BuildingMode::School => Color::srgb(0.75, 0.6, 0.15),
+            BuildingMode::Hospital => Color::srgb(0.7, 0.25, 0.25),
+            BuildingMode::CulDeSac => Color::srgb(0.55, 0.5, 0.35),
+            BuildingMode::Junction => Color::srgb(0.4, 0.4, 0.5),
+            BuildingMode::Depot => Color::srgb(0.75, 0.45, 0.15),
+            BuildingMode::Terminal => Color::srgb(0.45, 0.35, 0.3),
+            BuildingMode::Tree => Color::srgb(0.15, 0.45, 0.2),
+            BuildingMode::Park => Color::srgb(0.35, 0.6, 0.3),
+            BuildingMode::BusRoute => Color::srgb(0.85, 0.5, 0.1),
             BuildingMode::None => Color::srgb(0.5, 0.5, 0.5),
         };
 
@@ -281,6 +419,7 @@ pub fn handle_build_buttons(
 pub fn handle_build_keyboard(
     keyboard: Res<ButtonInput<KeyCode>>,
     mut building_state: ResMut<BuildingState>,
+    sim_world: Res<SimWorldResource>,
 ) {
     if keyboard.just_pressed(KeyCode::Digit1) {
         building_state.mode = if building_state.mode == BuildingMode::Road {
@@ -314,6 +453,246 @@ pub fn handle_build_keyboard(
         };
         building_state.road_start = None;
     }
+    if keyboard.just_pressed(KeyCode::Digit5) {
+        building_state.mode = if building_state.mode == BuildingMode::Signal {
+            BuildingMode::None
+        } else {
+            BuildingMode::Signal
+        };
+        building_state.road_start = None;
+    }
+    if keyboard.just_pressed(KeyCode::Digit6) {
+        building_state.mode = if building_state.mode == BuildingMode::School {
+            BuildingMode::None
+        } else {
+            BuildingMode::School
+        };
+        building_state.road_start = None;
+    }
+    if keyboard.just_pressed(KeyCode::Digit7) {
+        building_state.mode = if building_state.mode == BuildingMode::Hospital {
+            BuildingMode::None
+        } else {
+            BuildingMode::Hospital
+        };
+        building_state.road_start = None;
+    }
+    if keyboard.just_pressed(KeyCode::Digit8) {
+        building_state.mode = if building_state.mode == BuildingMode::CulDeSac {
+            BuildingMode::None
+        } else {
+            BuildingMode::CulDeSac
+        };
+        building_state.road_start = None;
+    }
+    if keyboard.just_pressed(KeyCode::F8) {
+        building_state.mode = if building_state.mode == BuildingMode::Junction {
+            BuildingMode::None
+        } else {
+            BuildingMode::Junction
+        };
+        building_state.road_start = None;
+    }
+    if keyboard.just_pressed(KeyCode::Digit9) {
+        building_state.mode = if building_state.mode == BuildingMode::Depot {
+            BuildingMode::None
+        } else {
+            BuildingMode::Depot
+        };
+        building_state.road_start = None;
+    }
+    if keyboard.just_pressed(KeyCode::Digit0) {
+        building_state.mode = if building_state.mode == BuildingMode::Terminal {
+            BuildingMode::None
+        } else {
+            BuildingMode::Terminal
+        };
+        building_state.road_start = None;
+    }
+    if keyboard.just_pressed(KeyCode::KeyB) {
+        building_state.mode = if building_state.mode == BuildingMode::Bridge {
+            BuildingMode::None
+        } else {
+            BuildingMode::Bridge
+        };
+        building_state.road_start = None;
+    }
+    if keyboard.just_pressed(KeyCode::KeyF) {
+        building_state.mode = if building_state.mode == BuildingMode::Ferry {
+            BuildingMode::None
+        } else {
+            BuildingMode::Ferry
+        };
+        building_state.road_start = None;
+    }
+    if keyboard.just_pressed(KeyCode::KeyO) {
+        building_state.mode = if building_state.mode == BuildingMode::Tree {
+            BuildingMode::None
+        } else {
+            BuildingMode::Tree
+        };
+        building_state.road_start = None;
+    }
+    if keyboard.just_pressed(KeyCode::KeyP) {
+        building_state.mode = if building_state.mode == BuildingMode::Park {
+            BuildingMode::None
+        } else {
+            BuildingMode::Park
+        };
+        building_state.road_start = None;
+    }
+    if keyboard.just_pressed(KeyCode::Semicolon) {
+        building_state.mode = if building_state.mode == BuildingMode::BusRoute {
+            BuildingMode::None
+        } else {
+            BuildingMode::BusRoute
+        };
+        building_state.road_start = None;
+    }
+    if keyboard.just_pressed(KeyCode::KeyV) {
+        cycle_building_variant(&mut building_state, &sim_world.0.content);
+    }
+    // Lots on the next cul-de-sac
+    if building_state.mode == BuildingMode::CulDeSac {
+        let lots = building_state.cul_de_sac_lots;
+        if keyboard.just_pressed(KeyCode::BracketLeft) {
+            building_state.cul_de_sac_lots = lots.saturating_sub(1).max(1);
+        }
+        if keyboard.just_pressed(KeyCode::BracketRight) {
+            building_state.cul_de_sac_lots = (lots + 1).min(CUL_DE_SAC_MAX_LOTS);
+        }
+        if building_state.cul_de_sac_lots != lots {
+            let lots = building_state.cul_de_sac_lots;
+            let cost = sim_world
+                .0
+                .game_state
+                .as_ref()
+                .map_or(0, |game_state| cul_de_sac_cost(&game_state.economy, lots));
+            bevy::log::info!("Cul-de-sac: {} lots, ${} on level ground", lots, cost);
+        }
+    }
+    // Preset of the next junction
+    if building_state.mode == BuildingMode::Junction {
+        let kinds = JunctionKind::ALL;
+        let index = kinds
+            .iter()
+            .position(|kind| *kind == building_state.junction_kind)
+            .unwrap_or(0);
+        let step = if keyboard.just_pressed(KeyCode::BracketLeft) {
+            kinds.len() - 1
+        } else if keyboard.just_pressed(KeyCode::BracketRight) {
+            1
+        } else {
+            0
+        };
+        if step != 0 {
+            let kind = kinds[(index + step) % kinds.len()];
+            building_state.junction_kind = kind;
+            let cost = sim_world
+                .0
+                .game_state
+                .as_ref()
+                .map_or(0, |game_state| junction_cost(&game_state.economy, kind));
+            bevy::log::info!("Junction: {}, ${}", kind.label(), cost);
+        }
+    }
+}
+
+/// System to remove intersections left with no roads or buildings (F5)
+pub fn handle_cleanup_keyboard(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    session: Res<MultiplayerSession>,
+    mut pending: ResMut<PendingCommands>,
+) {
+    if keyboard.just_pressed(KeyCode::F5) && !session.is_spectating() {
+        pending.0.push(BuildCommand::CleanupOrphans);
+    }
+}
+
+/// Content pack building kind placed by a building mode
+fn building_kind(mode: BuildingMode) -> Option<BuildingKind> {
+    match mode {
+        BuildingMode::Apartment => Some(BuildingKind::Apartment),
+        BuildingMode::Factory => Some(BuildingKind::Factory),
+        BuildingMode::Shop => Some(BuildingKind::Shop),
+        BuildingMode::None
+        | BuildingMode::Road
+        | BuildingMode::Bridge
+        | BuildingMode::Ferry
+        | BuildingMode::Signal
+        | BuildingMode::School
+        | BuildingMode::Hospital
+        | BuildingMode::Depot
+        | BuildingMode::Terminal
+        | BuildingMode::CulDeSac
+        | BuildingMode::Junction
+        | BuildingMode::Tree
+        | BuildingMode::Park
+        | BuildingMode::BusRoute => None,
+    }
+}
+
+/// Kind of road laid by a building mode
+fn road_kind(mode: BuildingMode) -> Option<RoadKind> {
+    match mode {
+        BuildingMode::Road => Some(RoadKind::Street),
+        BuildingMode::Bridge => Some(RoadKind::Bridge),
+        BuildingMode::Ferry => Some(RoadKind::Ferry),
+        _ => None,
+    }
+}
+
+/// Special building kind placed by a building mode
+fn special_kind(mode: BuildingMode) -> Option<SpecialKind> {
+    match mode {
+        BuildingMode::School => Some(SpecialKind::School),
+        BuildingMode::Hospital => Some(SpecialKind::Hospital),
+        BuildingMode::Depot => Some(SpecialKind::Depot),
+        BuildingMode::Terminal => Some(SpecialKind::Terminal),
+        _ => None,
+    }
+}
+
+/// Decoration placed by a building mode
+fn decoration_kind(mode: BuildingMode) -> Option<DecorationKind> {
+    match mode {
+        BuildingMode::Tree => Some(DecorationKind::Tree),
+        BuildingMode::Park => Some(DecorationKind::Park),
+        _ => None,
+    }
+}
+
+/// Content pack variant selected for the current mode, if it matches the mode
+fn active_variant<'a>(building_state: &'a BuildingState, content: &ContentRegistry) -> Option<&'a str> {
+    let kind = building_kind(building_state.mode)?;
+    building_state
+        .variant
+        .as_deref()
+        .filter(|name| content.building(name).is_some_and(|def| def.kind == kind))
+}
+
+/// Step through the built-in building and each content pack variant of the current mode
+fn cycle_building_variant(building_state: &mut BuildingState, content: &ContentRegistry) {
+    let Some(kind) = building_kind(building_state.mode) else {
+        return;
+    };
+    let names: Vec<&str> = content
+        .buildings_of_kind(kind)
+        .map(|def| def.name.as_str())
+        .collect();
+
+    let next = match active_variant(building_state, content) {
+        Some(current) => names
+            .iter()
+            .position(|name| *name == current)
+            .and_then(|index| names.get(index + 1)),
+        None => names.first(),
+    };
+    building_state.variant = next.map(|name| name.to_string());
+    bevy::log::info!(
+        "Building type: {}",
+        building_state.variant.as_deref().unwrap_or("standard")
+    );
 }
 
 /// System to update cursor position on ground plane
@@ -334,6 +713,7 @@ pub fn update_cursor_position(
     let Some(cursor_position) = window.cursor_position() else {
         building_state.cursor_position = None;
         building_state.snapped_position = None;
+        building_state.building_site = None;
         return;
     };
 
@@ -355,6 +735,16 @@ pub fn update_cursor_position(
     // Check for snapping
     let snap_distance = building_state.snap_distance;
 
+    // Check where a building would go by the placement rules
+    let mode = building_state.mode;
+    building_state.building_site = (building_kind(mode).is_some() || special_kind(mode).is_some())
+        .then(|| {
+            sim_world
+                .0
+                .building_site(position, snap_distance)
+                .map_err(|err| err.to_string())
+        });
+
     // First check for nearby intersection
     if let Some(closest_intersection) = sim_world
         .0
@@ -416,8 +806,37 @@ pub fn update_ghost_preview(
 
     let ghost_color = Color::srgba(1.0, 1.0, 1.0, 0.5);
 
+    // Buildings show where the placement rules put them, with their
+    // driveway, and turn red where they can't go
+    let site = building_state.building_site.as_ref();
+    let pos = site
+        .and_then(|site| site.as_ref().ok())
+        .map_or(pos, BuildingSite::position);
+    let blocked = matches!(site, Some(Err(_)));
+    let tint = |color: Color| {
+        if blocked {
+            Color::srgba(1.0, 0.2, 0.2, 0.5)
+        } else {
+            color
+        }
+    };
+    if let Some(Ok(BuildingSite::Driveway { position, join, .. })) = site {
+        let midpoint = position.lerp(join, 0.5);
+        commands.spawn((
+            GhostPreview,
+            Mesh3d(meshes.add(Cuboid::new(0.6, 0.02, position.distance(join)))),
+            MeshMaterial3d(materials.add(StandardMaterial {
+                base_color: ghost_color,
+                alpha_mode: AlphaMode::Blend,
+                ..default()
+            })),
+            Transform::from_translation(Vec3::new(midpoint.x, 0.01, midpoint.z))
+                .with_rotation(Quat::from_rotation_y(join.angle_to(position))),
+        ));
+    }
+
     match building_state.mode {
-        BuildingMode::Road => {
+        BuildingMode::Road | BuildingMode::Bridge | BuildingMode::Ferry => {
             // Show intersection preview at current position
             commands.spawn((
                 GhostPreview,
@@ -469,7 +888,7 @@ pub fn update_ghost_preview(
                 GhostPreview,
                 Mesh3d(meshes.add(Cuboid::new(1.0, 1.0, 1.0))),
                 MeshMaterial3d(materials.add(StandardMaterial {
-                    base_color: Color::srgba(0.7, 0.6, 0.4, 0.5),
+                    base_color: tint(Color::srgba(0.7, 0.6, 0.4, 0.5)),
                     alpha_mode: AlphaMode::Blend,
                     ..default()
                 })),
@@ -481,7 +900,7 @@ pub fn update_ghost_preview(
                 GhostPreview,
                 Mesh3d(meshes.add(Cuboid::new(1.5, 1.5, 1.5))),
                 MeshMaterial3d(materials.add(StandardMaterial {
-                    base_color: Color::srgba(0.5, 0.5, 0.7, 0.5),
+                    base_color: tint(Color::srgba(0.5, 0.5, 0.7, 0.5)),
                     alpha_mode: AlphaMode::Blend,
                     ..default()
                 })),
@@ -493,27 +912,258 @@ pub fn update_ghost_preview(
                 GhostPreview,
                 Mesh3d(meshes.add(Cuboid::new(1.2, 1.2, 1.2))),
                 MeshMaterial3d(materials.add(StandardMaterial {
-                    base_color: Color::srgba(0.8, 0.4, 0.6, 0.5),
+                    base_color: tint(Color::srgba(0.8, 0.4, 0.6, 0.5)),
                     alpha_mode: AlphaMode::Blend,
                     ..default()
                 })),
                 Transform::from_translation(Vec3::new(pos.x, 0.6, pos.z)),
             ));
         }
+        BuildingMode::School => {
+            commands.spawn((
+                GhostPreview,
+                Mesh3d(meshes.add(Cuboid::new(2.0, 1.0, 1.4))),
+                MeshMaterial3d(materials.add(StandardMaterial {
+                    base_color: tint(Color::srgba(0.9, 0.75, 0.2, 0.5)),
+                    alpha_mode: AlphaMode::Blend,
+                    ..default()
+                })),
+                Transform::from_translation(Vec3::new(pos.x, 0.5, pos.z)),
+            ));
+        }
+        BuildingMode::Hospital => {
+            commands.spawn((
+                GhostPreview,
+                Mesh3d(meshes.add(Cuboid::new(1.6, 1.8, 1.6))),
+                MeshMaterial3d(materials.add(StandardMaterial {
+                    base_color: tint(Color::srgba(0.95, 0.95, 0.95, 0.5)),
+                    alpha_mode: AlphaMode::Blend,
+                    ..default()
+                })),
+                Transform::from_translation(Vec3::new(pos.x, 0.9, pos.z)),
+            ));
+        }
+        BuildingMode::Depot => {
+            commands.spawn((
+                GhostPreview,
+                Mesh3d(meshes.add(Cuboid::new(1.8, 0.8, 1.8))),
+                MeshMaterial3d(materials.add(StandardMaterial {
+                    base_color: tint(Color::srgba(0.85, 0.5, 0.15, 0.5)),
+                    alpha_mode: AlphaMode::Blend,
+                    ..default()
+                })),
+                Transform::from_translation(Vec3::new(pos.x, 0.4, pos.z)),
+            ));
+        }
+        BuildingMode::Terminal => {
+            commands.spawn((
+                GhostPreview,
+                Mesh3d(meshes.add(Cuboid::new(3.0, 0.6, 1.4))),
+                MeshMaterial3d(materials.add(StandardMaterial {
+                    base_color: tint(Color::srgba(0.45, 0.35, 0.3, 0.5)),
+                    alpha_mode: AlphaMode::Blend,
+                    ..default()
+                })),
+                Transform::from_translation(Vec3::new(pos.x, 0.3, pos.z)),
+            ));
+        }
+        BuildingMode::CulDeSac => {
+            // Show the entrance until it is placed, then the whole street
+            let Some(start) = building_state.road_start else {
+                commands.spawn((
+                    GhostPreview,
+                    Mesh3d(meshes.add(Sphere::new(0.3))),
+                    MeshMaterial3d(materials.add(StandardMaterial {
+                        base_color: Color::srgba(0.0, 1.0, 0.0, 0.7),
+                        alpha_mode: AlphaMode::Blend,
+                        ..default()
+                    })),
+                    Transform::from_translation(Vec3::new(pos.x, 0.3, pos.z)),
+                ));
+                return;
+            };
+            // The dead end doesn't snap
+            let end = building_state.cursor_position.unwrap_or(pos);
+            let ghost_material = materials.add(StandardMaterial {
+                base_color: ghost_color,
+                alpha_mode: AlphaMode::Blend,
+                ..default()
+            });
+            let lot_material = materials.add(StandardMaterial {
+                base_color: Color::srgba(0.7, 0.6, 0.4, 0.5),
+                alpha_mode: AlphaMode::Blend,
+                ..default()
+            });
+            let mut spawn_road = |from: Position, to: Position| {
+                let length = from.distance(&to);
+                if length > 0.1 {
+                    let midpoint = from.lerp(&to, 0.5);
+                    commands.spawn((
+                        GhostPreview,
+                        Mesh3d(meshes.add(Cuboid::new(0.6, 0.02, length))),
+                        MeshMaterial3d(ghost_material.clone()),
+                        Transform::from_translation(Vec3::new(midpoint.x, 0.01, midpoint.z))
+                            .with_rotation(Quat::from_rotation_y(from.angle_to(&to))),
+                    ));
+                }
+            };
+            spawn_road(start, end);
+            let lots = cul_de_sac_lots(start, end, building_state.cul_de_sac_lots);
+            for (along, lot) in &lots {
+                spawn_road(*along, *lot);
+            }
+            for (_, lot) in lots {
+                commands.spawn((
+                    GhostPreview,
+                    Mesh3d(meshes.add(Cuboid::new(1.0, 1.0, 1.0))),
+                    MeshMaterial3d(lot_material.clone()),
+                    Transform::from_translation(Vec3::new(lot.x, 0.5, lot.z)),
+                ));
+            }
+        }
+        BuildingMode::Junction => {
+            // Show the centre until it is placed, then the whole junction
+            // with its main road running towards the cursor
+            let Some(center) = building_state.road_start else {
+                let pos = building_state.cursor_position.unwrap_or(pos);
+                commands.spawn((
+                    GhostPreview,
+                    Mesh3d(meshes.add(Sphere::new(0.3))),
+                    MeshMaterial3d(materials.add(StandardMaterial {
+                        base_color: Color::srgba(0.0, 1.0, 0.0, 0.7),
+                        alpha_mode: AlphaMode::Blend,
+                        ..default()
+                    })),
+                    Transform::from_translation(Vec3::new(pos.x, 0.3, pos.z)),
+                ));
+                return;
+            };
+            let toward = building_state.cursor_position.unwrap_or(pos);
+            if center.distance(&toward) < 0.1 {
+                return;
+            }
+            let street_material = materials.add(StandardMaterial {
+                base_color: ghost_color,
+                alpha_mode: AlphaMode::Blend,
+                ..default()
+            });
+            let bridge_material = materials.add(StandardMaterial {
+                base_color: Color::srgba(0.6, 0.5, 0.4, 0.6),
+                alpha_mode: AlphaMode::Blend,
+                ..default()
+            });
+            let layout = junction_layout(building_state.junction_kind, center, toward);
+            for (start, end, kind) in &layout.roads {
+                let (from, to) = (layout.points[*start], layout.points[*end]);
+                let midpoint = from.lerp(&to, 0.5);
+                let (material, height) = match kind {
+                    RoadKind::Bridge => (bridge_material.clone(), 0.3),
+                    _ => (street_material.clone(), 0.01),
+                };
+                commands.spawn((
+                    GhostPreview,
+                    Mesh3d(meshes.add(Cuboid::new(0.6, 0.02, from.distance(&to)))),
+                    MeshMaterial3d(material),
+                    Transform::from_translation(Vec3::new(midpoint.x, height, midpoint.z))
+                        .with_rotation(Quat::from_rotation_y(from.angle_to(&to))),
+                ));
+            }
+            if let Some((signalled, _)) = layout.signal {
+                let at = layout.points[signalled];
+                commands.spawn((
+                    GhostPreview,
+                    Mesh3d(meshes.add(Sphere::new(0.4))),
+                    MeshMaterial3d(materials.add(StandardMaterial {
+                        base_color: Color::srgba(1.0, 0.9, 0.2, 0.6),
+                        alpha_mode: AlphaMode::Blend,
+                        ..default()
+                    })),
+                    Transform::from_translation(Vec3::new(at.x, 0.3, at.z)),
+                ));
+            }
+        }
+        BuildingMode::Tree | BuildingMode::Park => {
+            // Decorations go where the cursor is, away from the roads
+            let pos = building_state.cursor_position.unwrap_or(pos);
+            let radius = if building_state.mode == BuildingMode::Park {
+                2.5
+            } else {
+                0.5
+            };
+            commands.spawn((
+                GhostPreview,
+                Mesh3d(meshes.add(Cylinder::new(radius, 0.1))),
+                MeshMaterial3d(materials.add(StandardMaterial {
+                    base_color: Color::srgba(0.2, 0.6, 0.25, 0.5),
+                    alpha_mode: AlphaMode::Blend,
+                    ..default()
+                })),
+                Transform::from_translation(Vec3::new(pos.x, 0.05, pos.z)),
+            ));
+        }
+        BuildingMode::Signal | BuildingMode::BusRoute => {
+            // Only intersections can take a signal or be a stop
+            if building_state.snapped_position.is_some() {
+                commands.spawn((
+                    GhostPreview,
+                    Mesh3d(meshes.add(Sphere::new(0.4))),
+                    MeshMaterial3d(materials.add(StandardMaterial {
+                        base_color: Color::srgba(1.0, 0.9, 0.2, 0.6),
+                        alpha_mode: AlphaMode::Blend,
+                        ..default()
+                    })),
+                    Transform::from_translation(Vec3::new(pos.x, 0.3, pos.z)),
+                ));
+            }
+        }
         BuildingMode::None => {}
     }
 }
 
+/// System to show why a building can't go at the cursor, or what its
+/// driveway adds to the cost, next to the cursor
+pub fn update_placement_hint(
+    windows: Query<&Window>,
+    building_state: Res<BuildingState>,
+    sim_world: Res<SimWorldResource>,
+    mut hint_query: Query<
+        (&mut Text, &mut TextColor, &mut Node, &mut Visibility),
+        With<PlacementHint>,
+    >,
+) {
+    let hint = match &building_state.building_site {
+        Some(Err(reason)) => Some((reason.clone(), Color::srgb(1.0, 0.4, 0.4))),
+        Some(Ok(site @ BuildingSite::Driveway { .. })) => Some((
+            format!("+ driveway ${}", sim_world.0.driveway_cost(site)),
+            Color::srgb(0.9, 0.9, 0.9),
+        )),
+        _ => None,
+    };
+    let screen = windows
+        .single()
+        .ok()
+        .and_then(|window| window.cursor_position());
+    for (mut text, mut color, mut node, mut visibility) in hint_query.iter_mut() {
+        let (Some((hint, hint_color)), Some(screen)) = (&hint, screen) else {
+            *visibility = Visibility::Hidden;
+            continue;
+        };
+        if text.0 != *hint {
+            text.0 = hint.clone();
+        }
+        color.0 = *hint_color;
+        node.left = Val::Px(screen.x + 16.0);
+        node.top = Val::Px(screen.y + 16.0);
+        *visibility = Visibility::Inherited;
+    }
+}
+
 /// System to handle placement clicks
 pub fn handle_placement_click(
     mouse_button: Res<ButtonInput<MouseButton>>,
     mut building_state: ResMut<BuildingState>,
-    mut sim_world: ResMut<SimWorldResource>,
-    mut commands: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<StandardMaterial>>,
-    mut mappings: ResMut<EntityMappings>,
-    mut apartment_assets: ResMut<ApartmentVisualAssets>,
+    sim_world: Res<SimWorldResource>,
+    mut pending: ResMut<PendingCommands>,
     // Check if mouse is over UI
     interaction_query: Query<&Interaction, With<Button>>,
 ) {
@@ -528,7 +1178,12 @@ pub fn handle_placement_click(
         return;
     }
 
-    if building_state.mode == BuildingMode::None {
+    // Signal and bus route mode clicks are handled by the signal editor and
+    // the route drawing tool
+    if matches!(
+        building_state.mode,
+        BuildingMode::None | BuildingMode::Signal | BuildingMode::BusRoute
+    ) {
         return;
     }
 
@@ -540,219 +1195,83 @@ pub fn handle_placement_click(
         return;
     };
 
-    let world = &mut sim_world.0;
-
-    match building_state.mode {
-        BuildingMode::Road => {
-            if let Some(start) = building_state.road_start {
-                // Second click - create the road
-                let snap_distance = building_state.snap_distance;
-
-                // Try to add road with game cost checking
-                let result = if world.game_state.is_some() {
-                    world.try_add_road_at_positions(start, pos, snap_distance)
-                } else {
-                    world
-                        .add_road_at_positions(start, pos, snap_distance)
-                        .map(Some)
-                };
-
-                match result {
-                    Ok(Some((start_id, end_id, forward_road, _))) => {
-                        // Spawn visuals for new intersection(s) if they don't exist
-                        if !mappings.intersections.contains_key(&start_id) {
-                            if let Some(intersection) = world.intersections.get(&start_id) {
-                                spawn_intersection_visual(
-                                    &mut commands,
-                                    &mut meshes,
-                                    &mut materials,
-                                    start_id,
-                                    &intersection.position,
-                                    &mut mappings,
-                                );
-                            }
-                        }
-                        if !mappings.intersections.contains_key(&end_id) {
-                            if let Some(intersection) = world.intersections.get(&end_id) {
-                                spawn_intersection_visual(
-                                    &mut commands,
-                                    &mut meshes,
-                                    &mut materials,
-                                    end_id,
-                                    &intersection.position,
-                                    &mut mappings,
-                                );
-                            }
-                        }
-
-                        // Spawn road visual
-                        if let Some(road) = world.road_network.get_road(forward_road) {
-                            spawn_road_visual(
-                                &mut commands,
-                                &mut meshes,
-                                &mut materials,
-                                &world.road_network,
-                                forward_road,
-                                road,
-                                &mut mappings,
-                            );
-                        }
-
-                        bevy::log::info!("Created road between {:?} and {:?}", start_id, end_id);
-                    }
-                    Ok(None) => {
-                        bevy::log::warn!("Insufficient funds to create road");
-                    }
-                    Err(e) => {
-                        bevy::log::warn!("Failed to create road: {}", e);
-                    }
-                }
-                building_state.road_start = None;
-            } else {
-                // First click - set start position
-                building_state.road_start = Some(pos);
-            }
-        }
-        BuildingMode::Apartment | BuildingMode::Factory | BuildingMode::Shop => {
-            // For buildings, find or create an intersection at this position
+    // Visuals for whatever the command builds are spawned by the sync systems
+    // once it has been applied
+    if let Some(kind) = road_kind(building_state.mode) {
+        if let Some(start) = building_state.road_start.take() {
+            // Second click - create the road
             let snap_distance = building_state.snap_distance;
-            let intersection_id =
-                match find_or_create_building_intersection(world, pos, snap_distance) {
-                    Ok(id) => id,
-                    Err(e) => {
-                        bevy::log::warn!("Failed to create intersection for building: {}", e);
-                        return;
-                    }
-                };
-
-            // Spawn intersection visual if new
-            if !mappings.intersections.contains_key(&intersection_id) {
-                if let Some(intersection) = world.intersections.get(&intersection_id) {
-                    spawn_intersection_visual(
-                        &mut commands,
-                        &mut meshes,
-                        &mut materials,
-                        intersection_id,
-                        &intersection.position,
-                        &mut mappings,
-                    );
-                }
-            }
-
-            // Spawn the building using the helper
-            spawn_building_at_intersection(
-                building_state.mode,
-                intersection_id,
-                world,
-                &mut commands,
-                &mut meshes,
-                &mut materials,
-                &mut mappings,
-                &mut apartment_assets,
-            );
-        }
-        BuildingMode::None => {}
-    }
-}
-
-/// Helper to spawn a building at an intersection with its visual
-fn spawn_building_at_intersection(
-    building_mode: BuildingMode,
-    intersection_id: crate::simulation::IntersectionId,
-    world: &mut crate::simulation::SimWorld,
-    commands: &mut Commands,
-    meshes: &mut ResMut<Assets<Mesh>>,
-    materials: &mut ResMut<Assets<StandardMaterial>>,
-    mappings: &mut ResMut<EntityMappings>,
-    apartment_assets: &mut ApartmentVisualAssets,
-) {
-    let position = match world.intersections.get(&intersection_id) {
-        Some(intersection) => intersection.position,
-        None => return,
-    };
-
-    match building_mode {
-        BuildingMode::Apartment => {
-            let maybe_apartment_id = if world.game_state.is_some() {
-                world.try_add_apartment(intersection_id)
-            } else {
-                Some(world.add_apartment(intersection_id))
-            };
-
-            if let Some(apartment_id) = maybe_apartment_id {
-                spawn_apartment_visual(
-                    commands,
-                    meshes,
-                    materials,
-                    apartment_id,
-                    &position,
-                    mappings,
-                    apartment_assets,
-                );
-                bevy::log::info!("Created apartment at {:?}", intersection_id);
-            } else {
-                bevy::log::warn!("Insufficient funds to create apartment");
-            }
-        }
-        BuildingMode::Factory => {
-            let maybe_factory_id = if world.game_state.is_some() {
-                world.try_add_factory(intersection_id)
-            } else {
-                Some(world.add_factory(intersection_id))
-            };
-
-            if let Some(factory_id) = maybe_factory_id {
-                spawn_factory_visual(commands, meshes, materials, factory_id, &position, mappings);
-                bevy::log::info!("Created factory at {:?}", intersection_id);
-            } else {
-                bevy::log::warn!("Insufficient funds to create factory");
-            }
-        }
-        BuildingMode::Shop => {
-            let maybe_shop_id = if world.game_state.is_some() {
-                world.try_add_shop(intersection_id)
-            } else {
-                Some(world.add_shop(intersection_id))
-            };
-
-            if let Some(shop_id) = maybe_shop_id {
-                spawn_shop_visual(commands, meshes, materials, shop_id, &position, mappings);
-                bevy::log::info!("Created shop at {:?}", intersection_id);
-            } else {
-                bevy::log::warn!("Insufficient funds to create shop");
-            }
+            pending.0.push(match kind {
+                RoadKind::Street => BuildCommand::Road {
+                    start,
+                    end: pos,
+                    snap_distance,
+                },
+                RoadKind::Bridge => BuildCommand::Bridge {
+                    start,
+                    end: pos,
+                    snap_distance,
+                },
+                RoadKind::Ferry => BuildCommand::Ferry {
+                    start,
+                    end: pos,
+                    snap_distance,
+                },
+            });
+        } else {
+            // First click - set start position
+            building_state.road_start = Some(pos);
         }
-        _ => {}
-    }
-}
-
-/// Helper to find or create an intersection for building placement
-fn find_or_create_building_intersection(
-    world: &mut crate::simulation::SimWorld,
-    position: Position,
-    snap_distance: f32,
-) -> anyhow::Result<crate::simulation::IntersectionId> {
-    // First check for existing intersection nearby
-    if let Some(closest_id) = world.road_network.find_closest_intersection(&position) {
-        if let Some(intersection_pos) = world.road_network.get_intersection_position(closest_id) {
-            if position.distance(intersection_pos) <= snap_distance {
-                return Ok(closest_id);
-            }
+    } else if building_state.mode == BuildingMode::CulDeSac {
+        if let Some(start) = building_state.road_start.take() {
+            // Second click - lay out the street, ending where the cursor is
+            pending.0.push(BuildCommand::CulDeSac {
+                start,
+                end: building_state.cursor_position.unwrap_or(pos),
+                lots: building_state.cul_de_sac_lots,
+                snap_distance: building_state.snap_distance,
+            });
+        } else {
+            // First click - set the entrance
+            building_state.road_start = Some(pos);
         }
-    }
-
-    // Check if we're near a road that can be split
-    if let Some((road_id, closest_point, _, _)) =
-        world.road_network.find_closest_point_on_road(&position)
-    {
-        if position.distance(&closest_point) <= snap_distance {
-            let (new_intersection, _, _) = world.split_road_at_position(road_id, closest_point)?;
-            return Ok(new_intersection);
+    } else if building_state.mode == BuildingMode::Junction {
+        // The centre and direction don't snap; the ends of the arms do
+        let cursor = building_state.cursor_position.unwrap_or(pos);
+        if let Some(center) = building_state.road_start.take() {
+            // Second click - stamp the junction, its main road running
+            // towards the cursor
+            pending.0.push(BuildCommand::Junction {
+                kind: building_state.junction_kind,
+                center,
+                toward: cursor,
+                snap_distance: building_state.snap_distance,
+            });
+        } else {
+            // First click - set the centre
+            building_state.road_start = Some(cursor);
         }
+    } else if let Some(kind) = building_kind(building_state.mode) {
+        let variant = active_variant(&building_state, &sim_world.0.content).map(str::to_string);
+        pending.0.push(BuildCommand::Building {
+            kind,
+            variant,
+            position: pos,
+            snap_distance: building_state.snap_distance,
+        });
+    } else if let Some(kind) = special_kind(building_state.mode) {
+        pending.0.push(BuildCommand::Special {
+            kind,
+            position: pos,
+            snap_distance: building_state.snap_distance,
+        });
+    } else if let Some(kind) = decoration_kind(building_state.mode) {
+        // Decorations don't snap
+        pending.0.push(BuildCommand::Decoration {
+            kind,
+            position: building_state.cursor_position.unwrap_or(pos),
+        });
     }
-
-    // Create new intersection at position
-    Ok(world.add_intersection(position))
 }
 
 /// Update button border colors to show current selection
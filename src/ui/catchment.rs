@@ -0,0 +1,185 @@
+//! Shop catchment overlay
+//!
+//! Press N to list where each shop's deliveries came from, and color the map
+//! by catchment: each shop gets a color, and a ring of it goes round the
+//! shop and every factory and house it draws on the most. Houses no shop
+//! draws on get a grey ring, so underserved neighborhoods stand out.
+
+use bevy::prelude::*;
+
+use super::components::SimWorldResource;
+use crate::simulation::{ApartmentId, FactoryId, Position, ShopCatchment, SimMap};
+
+/// Colors of the shops' catchments, reused when there are more shops
+const PALETTE: [Color; 6] = [
+    Color::srgb(1.0, 0.4, 0.3),
+    Color::srgb(0.3, 0.7, 1.0),
+    Color::srgb(0.4, 0.9, 0.4),
+    Color::srgb(1.0, 0.8, 0.2),
+    Color::srgb(0.8, 0.4, 1.0),
+    Color::srgb(0.2, 0.9, 0.8),
+];
+
+/// Color of the rings round houses no shop draws on
+const UNDERSERVED: Color = Color::srgb(0.5, 0.5, 0.5);
+
+/// Height of the rings above the buildings' intersections
+const RING_HEIGHT: f32 = 0.3;
+
+/// Radius of the rings round shops; factories and houses get smaller ones
+const SHOP_RING_RADIUS: f32 = 2.5;
+
+/// Marker for the catchment panel
+#[derive(Component)]
+pub struct CatchmentPanel;
+
+/// Marker for the catchment panel's text
+#[derive(Component)]
+pub struct CatchmentText;
+
+/// System to create the (hidden) catchment panel
+pub fn setup_catchment_ui(mut commands: Commands) {
+    commands
+        .spawn((
+            CatchmentPanel,
+            Node {
+                position_type: PositionType::Absolute,
+                top: Val::Px(60.0),
+                right: Val::Px(10.0),
+                padding: UiRect::all(Val::Px(10.0)),
+                ..default()
+            },
+            BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.8)),
+            Visibility::Hidden,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                CatchmentText,
+                Text::new(""),
+                TextFont {
+                    font_size: 14.0,
+                    ..default()
+                },
+                TextColor(Color::WHITE),
+            ));
+        });
+}
+
+/// The shop, by its index in `catchments`, that draws the most deliveries
+/// from each factory or house
+fn main_shops<K: Ord + Copy>(
+    catchments: &[ShopCatchment],
+    sources: impl Fn(&ShopCatchment) -> Vec<(K, f32)>,
+) -> SimMap<K, usize> {
+    let mut best: SimMap<K, (usize, f32)> = SimMap::new();
+    for (index, catchment) in catchments.iter().enumerate() {
+        for (id, deliveries) in sources(catchment) {
+            let entry = best.entry(id).or_insert((index, deliveries));
+            if deliveries > entry.1 {
+                *entry = (index, deliveries);
+            }
+        }
+    }
+    best.into_iter().map(|(id, (index, _))| (id, index)).collect()
+}
+
+/// System to toggle the catchment panel (N), and keep it and the rings
+/// marking the catchments current while it is shown
+pub fn update_catchments(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    sim_world: Res<SimWorldResource>,
+    mut panel_query: Query<&mut Visibility, With<CatchmentPanel>>,
+    mut text_query: Query<&mut Text, With<CatchmentText>>,
+    mut gizmos: Gizmos,
+) {
+    let mut shown = false;
+    for mut visibility in panel_query.iter_mut() {
+        if keyboard.just_pressed(KeyCode::KeyN) {
+            *visibility = match *visibility {
+                Visibility::Hidden => Visibility::Inherited,
+                _ => Visibility::Hidden,
+            };
+        }
+        shown |= *visibility != Visibility::Hidden;
+    }
+    if !shown {
+        return;
+    }
+
+    let world = &sim_world.0;
+    let network = &world.road_network;
+    let mut ring = |intersection_id, radius: f32, color: Color| {
+        let Some(&Position { x, z, .. }) = network.get_intersection_position(intersection_id)
+        else {
+            return;
+        };
+        gizmos.circle(
+            Isometry3d::new(
+                Vec3::new(x, RING_HEIGHT, z),
+                Quat::from_rotation_arc(Vec3::Z, Vec3::Y),
+            ),
+            radius,
+            color,
+        );
+    };
+
+    let catchments = world.shop_catchments();
+    let mut description = String::from("Shop catchments");
+    if catchments.is_empty() {
+        description.push_str("\nNo shops yet");
+    }
+    for (index, catchment) in catchments.iter().enumerate() {
+        description.push_str(&format!(
+            "\nShop {}: {} deliveries, ${} from {} factories, {} houses",
+            catchment.shop_id.0 .0,
+            catchment.deliveries,
+            catchment.revenue,
+            catchment.factories.len(),
+            catchment.apartments.len()
+        ));
+        if let Some(shop) = world.shops.get(&catchment.shop_id) {
+            ring(shop.intersection_id, SHOP_RING_RADIUS, PALETTE[index % PALETTE.len()]);
+        }
+    }
+
+    let factories = main_shops::<FactoryId>(&catchments, |catchment| {
+        catchment
+            .factories
+            .iter()
+            .map(|(id, attribution)| (*id, attribution.deliveries))
+            .collect()
+    });
+    for (factory_id, index) in factories {
+        if let Some(factory) = world.factories.get(&factory_id) {
+            ring(factory.intersection_id, 1.8, PALETTE[index % PALETTE.len()]);
+        }
+    }
+    let apartments = main_shops::<ApartmentId>(&catchments, |catchment| {
+        catchment
+            .apartments
+            .iter()
+            .map(|(id, attribution)| (*id, attribution.deliveries))
+            .collect()
+    });
+    for (apartment_id, index) in apartments {
+        if let Some(apartment) = world.apartments.get(&apartment_id) {
+            ring(apartment.intersection_id, 1.2, PALETTE[index % PALETTE.len()]);
+        }
+    }
+
+    let underserved = world.underserved_apartments();
+    if !underserved.is_empty() {
+        description.push_str(&format!("\n{} houses no shop draws on", underserved.len()));
+    }
+    for apartment_id in underserved {
+        if let Some(apartment) = world.apartments.get(&apartment_id) {
+            ring(apartment.intersection_id, 1.2, UNDERSERVED);
+        }
+    }
+
+    for mut text in text_query.iter_mut() {
+        if **text != description {
+            **text = description.clone();
+        }
+    }
+}
@@ -0,0 +1,229 @@
+//! Cinematic camera
+//!
+//! Press F7 (or start with `--cinematic`) to have the camera tour the city
+//! on its own, for demos and attract screens. It visits the places
+//! [`SimWorld::highlights`](crate::simulation::SimWorld::highlights) picks
+//! out in turn: the busiest intersection, the latest delivery and the newest
+//! construction. Each shot glides there, then circles slowly while a caption
+//! says what is there. Press F7 again to take the camera back.
+//!
+//! Started with `--camera-tour`, the camera follows a scripted
+//! [`CameraTour`] instead, in step with the simulation's clock, so the same
+//! tour of the same city films the same way every time. F7 stops it, and
+//! pressing it again plays the tour from the start.
+
+use std::f32::consts::PI;
+
+use bevy::prelude::*;
+
+use super::components::{MainCamera, SimWorldResource};
+use crate::simulation::CameraTour;
+
+/// Seconds the camera takes to glide from one place to the next
+const TRANSITION_SECS: f32 = 3.0;
+
+/// Seconds the camera stays at each place
+const HOLD_SECS: f32 = 6.0;
+
+/// Where the camera looks at a place from: above and off to one side
+const VIEW_OFFSET: Vec3 = Vec3::new(0.0, 22.0, -18.0);
+
+/// Radians a second the camera circles a place
+const ORBIT_SPEED: f32 = PI / 24.0;
+
+/// The camera's tour
+#[derive(Resource, Default)]
+pub struct Cinematic {
+    /// Whether the camera is touring
+    pub active: bool,
+    /// The shot in progress
+    shot: Option<Shot>,
+    /// Index of the next highlight to visit
+    next: usize,
+    /// Scripted tour followed instead of the highlights, if any
+    tour: Option<CameraTour>,
+    /// Simulation time the scripted tour began at
+    tour_start: Option<f32>,
+}
+
+impl Cinematic {
+    /// A tour that starts immediately
+    pub fn started() -> Self {
+        Self {
+            active: true,
+            ..default()
+        }
+    }
+
+    /// A scripted tour that starts immediately
+    pub fn scripted(tour: CameraTour) -> Self {
+        Self {
+            active: true,
+            tour: Some(tour),
+            ..default()
+        }
+    }
+}
+
+/// A glide to a place and the hold there
+struct Shot {
+    from: Transform,
+    target: Vec3,
+    caption: String,
+    /// Seconds since the shot began
+    elapsed: f32,
+}
+
+impl Shot {
+    /// Camera transform `elapsed` seconds into the shot
+    fn transform(&self) -> Transform {
+        let orbit = Quat::from_rotation_y((self.elapsed - TRANSITION_SECS).max(0.0) * ORBIT_SPEED);
+        let to = Transform::from_translation(self.target + orbit * VIEW_OFFSET)
+            .looking_at(self.target, Vec3::Y);
+        let t = (self.elapsed / TRANSITION_SECS).clamp(0.0, 1.0);
+        // Ease in and out
+        let t = t * t * (3.0 - 2.0 * t);
+        Transform {
+            translation: self.from.translation.lerp(to.translation, t),
+            rotation: self.from.rotation.slerp(to.rotation, t),
+            ..to
+        }
+    }
+}
+
+/// Marker for the caption shown during the tour
+#[derive(Component)]
+pub struct CinematicCaption;
+
+/// Marker for the caption's text
+#[derive(Component)]
+pub struct CinematicCaptionText;
+
+/// System to create the (hidden) caption
+pub fn setup_cinematic_ui(mut commands: Commands) {
+    commands
+        .spawn((
+            CinematicCaption,
+            Node {
+                width: Val::Percent(100.0),
+                position_type: PositionType::Absolute,
+                top: Val::Px(60.0),
+                justify_content: JustifyContent::Center,
+                ..default()
+            },
+            Visibility::Hidden,
+        ))
+        .with_children(|parent| {
+            parent
+                .spawn((
+                    Node {
+                        padding: UiRect::axes(Val::Px(16.0), Val::Px(8.0)),
+                        ..default()
+                    },
+                    BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.6)),
+                ))
+                .with_children(|parent| {
+                    parent.spawn((
+                        CinematicCaptionText,
+                        Text::new(""),
+                        TextFont {
+                            font_size: 22.0,
+                            ..default()
+                        },
+                        TextColor(Color::WHITE),
+                    ));
+                });
+        });
+}
+
+/// System to start and stop the tour (F7) and move the camera along it
+pub fn update_cinematic(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    time: Res<Time>,
+    sim_world: Res<SimWorldResource>,
+    mut cinematic: ResMut<Cinematic>,
+    mut camera_query: Query<&mut Transform, With<MainCamera>>,
+    mut caption_query: Query<&mut Visibility, With<CinematicCaption>>,
+    mut text_query: Query<&mut Text, With<CinematicCaptionText>>,
+) {
+    if keyboard.just_pressed(KeyCode::F7) {
+        cinematic.active = !cinematic.active;
+        cinematic.shot = None;
+        cinematic.tour_start = None;
+    }
+    let Ok(mut transform) = camera_query.single_mut() else {
+        return;
+    };
+
+    let cinematic = &mut *cinematic;
+    if let Some(tour) = cinematic.tour.as_ref().filter(|_| cinematic.active) {
+        let now = sim_world.0.time;
+        let start = *cinematic.tour_start.get_or_insert(now);
+        let pose = tour.pose_at(now - start);
+        let target = Vec3::new(pose.position.x, pose.position.y, pose.position.z);
+        *transform = Transform::from_translation(target + VIEW_OFFSET / pose.zoom)
+            .looking_at(target, Vec3::Y);
+        show_caption(pose.caption, &mut caption_query, &mut text_query);
+        return;
+    }
+
+    if cinematic.active {
+        if let Some(shot) = cinematic.shot.as_mut() {
+            shot.elapsed += time.delta_secs();
+        }
+        let finished = cinematic
+            .shot
+            .as_ref()
+            .is_none_or(|shot| shot.elapsed >= TRANSITION_SECS + HOLD_SECS);
+        if finished {
+            // Choose the next place afresh, as the city has moved on
+            let highlights = sim_world.0.highlights();
+            cinematic.shot = (!highlights.is_empty()).then(|| {
+                let highlight = &highlights[cinematic.next % highlights.len()];
+                cinematic.next = cinematic.next.wrapping_add(1);
+                let position = highlight.position;
+                Shot {
+                    from: *transform,
+                    target: Vec3::new(position.x, position.y, position.z),
+                    caption: highlight.caption.clone(),
+                    elapsed: 0.0,
+                }
+            });
+        }
+        if let Some(shot) = &cinematic.shot {
+            *transform = shot.transform();
+        }
+    }
+
+    let caption = cinematic
+        .shot
+        .as_ref()
+        .filter(|_| cinematic.active)
+        .map(|shot| shot.caption.as_str());
+    show_caption(caption, &mut caption_query, &mut text_query);
+}
+
+/// Show a caption, or hide the caption bar without one
+fn show_caption(
+    caption: Option<&str>,
+    caption_query: &mut Query<&mut Visibility, With<CinematicCaption>>,
+    text_query: &mut Query<&mut Text, With<CinematicCaptionText>>,
+) {
+    for mut visibility in caption_query.iter_mut() {
+        let wanted = if caption.is_some() {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+        if *visibility != wanted {
+            *visibility = wanted;
+        }
+    }
+    if let Some(caption) = caption {
+        for mut text in text_query.iter_mut() {
+            if **text != *caption {
+                **text = caption.to_string();
+            }
+        }
+    }
+}
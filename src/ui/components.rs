@@ -4,7 +4,8 @@ use bevy::prelude::*;
 use std::collections::HashMap;
 
 use crate::simulation::{
-    CarId, FactoryId, ApartmentId, IntersectionId, Position, RoadId, ShopId, SimWorld,
+    BuildCommand, BuildingSite, CarId, DecorationId, DrivingSide, EconomyConfig, FactoryId, ApartmentId, IntersectionId, JunctionKind, Position, RoadId, ShopId,
+    SimWorld, SpecialBuildingId, VehicleType, VisualId,
 };
 
 /// Starting budget for the interactive UI sandbox
@@ -16,21 +17,36 @@ pub struct SimWorldResource(pub SimWorld);
 
 impl Default for SimWorldResource {
     fn default() -> Self {
-        // Create a game world with a starting setup
-        let mut world = SimWorld::new_with_game();
         // UI mode starts as a blank sandbox (no prebuilt roads/buildings)
-        if let Some(game_state) = world.game_state.as_mut() {
-            game_state.money = UI_STARTING_BUDGET;
-        }
+        // with a generous budget and every road's traffic in view
+        Self::with_economy(EconomyConfig {
+            starting_budget: UI_STARTING_BUDGET,
+            sensors_required: false,
+            ..EconomyConfig::default()
+        })
+    }
+}
 
-        Self(world)
+impl SimWorldResource {
+    /// Create a blank game world using a custom economy
+    pub fn with_economy(economy: EconomyConfig) -> Self {
+        Self(SimWorld::new_with_economy(economy))
     }
 }
 
+/// Build commands issued by the player, applied on the next simulation step
+#[derive(Resource, Default)]
+pub struct PendingCommands(pub Vec<BuildCommand>);
+
 /// Marker component for ground plane
 #[derive(Component)]
 pub struct Ground;
 
+/// Ground height a building or intersection was drawn on, so it can be
+/// moved up or down with its intersection
+#[derive(Component)]
+pub struct GroundLevel(pub f32);
+
 /// Marker component for the main camera
 #[derive(Component)]
 pub struct MainCamera;
@@ -57,29 +73,61 @@ impl Default for CameraSettings {
 #[derive(Component)]
 pub struct SimSynced;
 
-/// Links a Bevy entity to a simulation intersection
+/// Links a Bevy entity to the simulation object it draws
 #[derive(Component)]
-pub struct IntersectionLink(pub IntersectionId);
+pub struct VisualLink(pub VisualId);
 
-/// Links a Bevy entity to a simulation road
+/// Geometry a road visual was last built from
+///
+/// Kept so the arrow mesh can be rebuilt at a new spacing when the camera
+/// zooms, without going back to the simulation.
 #[derive(Component)]
-pub struct RoadLink(pub RoadId);
+pub struct RoadVisual {
+    pub start: Position,
+    pub end: Position,
+    pub two_way: bool,
+    pub driving_side: DrivingSide,
+    pub arrow_spacing: f32,
+    pub arrow_mesh: Handle<Mesh>,
+}
 
-/// Links a Bevy entity to a simulation car
-#[derive(Component)]
-pub struct CarLink(pub CarId);
+/// Current spacing between road direction arrows, driven by camera zoom
+#[derive(Resource)]
+pub struct RoadArrowDensity {
+    pub spacing: f32,
+}
 
-/// Links a Bevy entity to a simulation apartment
-#[derive(Component)]
-pub struct ApartmentLink(pub ApartmentId);
+impl Default for RoadArrowDensity {
+    fn default() -> Self {
+        Self { spacing: 2.0 }
+    }
+}
 
-/// Links a Bevy entity to a simulation factory
+/// Length of a vehicle's body, for fitting lights to it
 #[derive(Component)]
-pub struct FactoryLink(pub FactoryId);
+pub struct VehicleBody {
+    pub length: f32,
+}
 
-/// Links a Bevy entity to a simulation shop
-#[derive(Component)]
-pub struct ShopLink(pub ShopId);
+/// How a vehicle's entity was built: its type and any content pack tint
+/// and size (as bits, so looks can be compared). Hidden vehicle entities
+/// are only reused for vehicles with the same look.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct VehicleLook {
+    pub vehicle_type: VehicleType,
+    pub tint: Option<[u32; 3]>,
+    pub size: Option<[u32; 3]>,
+}
+
+impl VehicleLook {
+    pub fn new(vehicle_type: VehicleType, tint: Option<[f32; 3]>, size: Option<[f32; 3]>) -> Self {
+        Self {
+            vehicle_type,
+            tint: tint.map(|tint| tint.map(f32::to_bits)),
+            size: size.map(|size| size.map(f32::to_bits)),
+        }
+    }
+}
 
 /// Component to mark the visual demand indicator entity
 #[derive(Component)]
@@ -89,6 +137,10 @@ pub struct DemandIndicator;
 #[derive(Component)]
 pub struct DeliveryIndicator;
 
+/// Component to mark the bar showing progress toward a factory's next delivery
+#[derive(Component)]
+pub struct ProductionBar;
+
 /// Resource to track Bevy entities mapped to simulation entities
 #[derive(Resource, Default)]
 pub struct EntityMappings {
@@ -98,6 +150,8 @@ pub struct EntityMappings {
     pub apartments: HashMap<ApartmentId, Entity>,
     pub factories: HashMap<FactoryId, Entity>,
     pub shops: HashMap<ShopId, Entity>,
+    pub special_buildings: HashMap<SpecialBuildingId, Entity>,
+    pub decorations: HashMap<DecorationId, Entity>,
 }
 
 /// Building mode types
@@ -106,9 +160,31 @@ pub enum BuildingMode {
     #[default]
     None,
     Road,
+    /// Bridge over water
+    Bridge,
+    /// Ferry crossing over water
+    Ferry,
     Apartment,
     Factory,
     Shop,
+    /// Select intersections to edit their traffic signals
+    Signal,
+    School,
+    Hospital,
+    /// Tow truck depot
+    Depot,
+    /// Rail freight terminal
+    Terminal,
+    /// Lay out a dead-end street with apartments along it
+    CulDeSac,
+    /// Stamp down a junction preset
+    Junction,
+    /// Plant a tree clear of the roads
+    Tree,
+    /// Lay out a park clear of the roads
+    Park,
+    /// Lay out the stops of a bus route
+    BusRoute,
 }
 
 /// State for the building system
@@ -116,14 +192,24 @@ pub enum BuildingMode {
 pub struct BuildingState {
     /// Current building mode
     pub mode: BuildingMode,
-    /// First point for road placement (when in Road mode)
+    /// First point for road placement (when in Road, Bridge, Ferry or
+    /// CulDeSac mode), or the centre of a junction (in Junction mode)
     pub road_start: Option<Position>,
+    /// Apartments on the next cul-de-sac
+    pub cul_de_sac_lots: usize,
+    /// Preset the next junction is stamped from
+    pub junction_kind: JunctionKind,
     /// Current mouse position on ground plane
     pub cursor_position: Option<Position>,
     /// Snapped position (if near an intersection or road)
     pub snapped_position: Option<Position>,
+    /// Where a building would go at the cursor, or why it can't go there, in
+    /// modes that place buildings
+    pub building_site: Option<Result<BuildingSite, String>>,
     /// Distance threshold for snapping
     pub snap_distance: f32,
+    /// Content pack building type to place instead of the built-in one
+    pub variant: Option<String>,
 }
 
 impl Default for BuildingState {
@@ -131,9 +217,13 @@ impl Default for BuildingState {
         Self {
             mode: BuildingMode::None,
             road_start: None,
+            cul_de_sac_lots: 4,
+            junction_kind: JunctionKind::default(),
             cursor_position: None,
             snapped_position: None,
+            building_site: None,
             snap_distance: 2.0,
+            variant: None,
         }
     }
 }
@@ -142,6 +232,10 @@ impl Default for BuildingState {
 #[derive(Component)]
 pub struct GhostPreview;
 
+/// Marker for the text saying why a building can't go at the cursor
+#[derive(Component)]
+pub struct PlacementHint;
+
 /// Marker for UI buttons
 #[derive(Component)]
 pub struct BuildModeButton(pub BuildingMode);
@@ -157,6 +251,10 @@ pub enum GlobalDemandText {
     ApartmentsWaiting,
     /// Player's money
     Money,
+    /// Insurance policy status
+    Insurance,
+    /// Wear of the apartment cars and factory trucks
+    Fleet,
     /// Worker trips completed
     WorkerTrips,
     /// Shop deliveries completed
@@ -0,0 +1,184 @@
+//! Memory panel and entity budget warnings
+//!
+//! Press K to show roughly how much memory the world's collections hold and
+//! how many of each recurring simulation warning there have been, along with
+//! how many vehicles set off in recycled ones and how often the path cache
+//! saved a route search.
+//! When the world goes over an entity budget set on the command line, a
+//! warning stays on screen until it is back within its budgets.
+
+use bevy::prelude::*;
+
+use super::components::SimWorldResource;
+use super::renderer::VehicleEntityPool;
+use crate::simulation::BudgetWatch;
+
+/// Seconds between refreshes of the memory panel
+const MEMORY_REFRESH_SECS: f32 = 1.0;
+
+/// Entity budgets the world is checked against
+#[derive(Resource, Default)]
+pub struct EntityBudgetWatch(pub BudgetWatch);
+
+/// Marker for the memory panel
+#[derive(Component)]
+pub struct MemoryPanel;
+
+/// Marker for the memory panel's text
+#[derive(Component)]
+pub struct MemoryText;
+
+/// Marker for the budget warning text
+#[derive(Component)]
+pub struct BudgetWarningText;
+
+/// System to create the (hidden) memory panel and budget warning
+pub fn setup_diagnostics_ui(mut commands: Commands) {
+    commands
+        .spawn((
+            MemoryPanel,
+            Node {
+                position_type: PositionType::Absolute,
+                bottom: Val::Px(10.0),
+                right: Val::Px(10.0),
+                padding: UiRect::all(Val::Px(10.0)),
+                ..default()
+            },
+            BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.8)),
+            Visibility::Hidden,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                MemoryText,
+                Text::new(""),
+                TextFont {
+                    font_size: 14.0,
+                    ..default()
+                },
+                TextColor(Color::WHITE),
+            ));
+        });
+
+    commands.spawn((
+        BudgetWarningText,
+        Text::new(""),
+        TextFont {
+            font_size: 16.0,
+            ..default()
+        },
+        TextColor(Color::srgb(1.0, 0.4, 0.3)),
+        Node {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(10.0),
+            left: Val::Px(10.0),
+            ..default()
+        },
+        Visibility::Hidden,
+    ));
+}
+
+/// System to check the entity budgets, logging each one the world goes over
+/// and showing a warning while it is over any
+pub fn check_entity_budgets(
+    sim_world: Res<SimWorldResource>,
+    mut watch: ResMut<EntityBudgetWatch>,
+    mut warning_query: Query<(&mut Text, &mut Visibility), With<BudgetWarningText>>,
+) {
+    if !watch.0.budgets.is_limited() {
+        return;
+    }
+    for overrun in watch.0.check(&sim_world.0) {
+        warn!("{}", overrun);
+    }
+    let overruns = watch.0.budgets.overruns(&sim_world.0);
+    let warning = overruns
+        .iter()
+        .map(|overrun| format!("⚠ {}", overrun))
+        .collect::<Vec<_>>()
+        .join("\n");
+    for (mut text, mut visibility) in warning_query.iter_mut() {
+        if **text != warning {
+            **text = warning.clone();
+        }
+        visibility.set_if_neq(if overruns.is_empty() {
+            Visibility::Hidden
+        } else {
+            Visibility::Inherited
+        });
+    }
+}
+
+/// System to toggle the memory panel (K), and refresh it every
+/// [`MEMORY_REFRESH_SECS`] while it is shown
+#[allow(clippy::too_many_arguments)]
+pub fn update_memory_panel(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    time: Res<Time>,
+    sim_world: Res<SimWorldResource>,
+    watch: Res<EntityBudgetWatch>,
+    vehicle_pool: Res<VehicleEntityPool>,
+    mut since_refresh: Local<f32>,
+    mut panel_query: Query<&mut Visibility, With<MemoryPanel>>,
+    mut text_query: Query<&mut Text, With<MemoryText>>,
+) {
+    let mut opened = false;
+    let mut shown = false;
+    for mut visibility in panel_query.iter_mut() {
+        if keyboard.just_pressed(KeyCode::KeyK) {
+            *visibility = match *visibility {
+                Visibility::Hidden => Visibility::Inherited,
+                _ => Visibility::Hidden,
+            };
+            opened = *visibility != Visibility::Hidden;
+        }
+        shown |= *visibility != Visibility::Hidden;
+    }
+    if !shown {
+        return;
+    }
+    *since_refresh += time.delta_secs();
+    if !opened && *since_refresh < MEMORY_REFRESH_SECS {
+        return;
+    }
+    *since_refresh = 0.0;
+
+    let world = &sim_world.0;
+    let budgets = &watch.0.budgets;
+    let limit = |limit: Option<usize>| limit.map_or_else(|| "-".to_string(), |n| n.to_string());
+    let mut description = format!(
+        "Memory (approximate)\n{}\n\nBudgets\ncars {} / {}\nroads {} / {}\nbuildings {} / {}",
+        world.memory_report(),
+        world.cars.len(),
+        limit(budgets.max_cars),
+        world.road_network.road_count(),
+        limit(budgets.max_roads),
+        world.building_count(),
+        limit(budgets.max_buildings)
+    );
+    let car_pool = world.car_pool();
+    description.push_str(&format!(
+        "\n\nVehicle reuse\nrecycled {} / {} set off\nhidden entities {}",
+        car_pool.reused(),
+        car_pool.reused() + car_pool.built(),
+        vehicle_pool.idle()
+    ));
+    let path_cache = world.path_cache_stats();
+    description.push_str(&format!(
+        "\n\nPath cache\nhit rate {:.0}% of {}\nroutes {} / {}\ninvalidations {} in the last minute",
+        path_cache.hit_rate() * 100.0,
+        path_cache.hits + path_cache.misses,
+        path_cache.entries,
+        path_cache.capacity,
+        path_cache.invalidations_per_minute
+    ));
+    description.push_str("\n\nWarnings");
+    if world.warnings().total() == 0 {
+        description.push_str("\nnone");
+    }
+    for (kind, count) in world.warnings().counts() {
+        description.push_str(&format!("\n{} {}", kind.label(), count));
+    }
+    for mut text in text_query.iter_mut() {
+        **text = description.clone();
+    }
+}
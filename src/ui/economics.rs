@@ -0,0 +1,90 @@
+//! Economics help panel
+//!
+//! Press M to show how a worker trip is paid under the active economy:
+//! the base pay and each penalty and bonus, with the values and the commute
+//! penalty curve loaded from the economy file.
+
+use bevy::prelude::*;
+
+use super::components::SimWorldResource;
+
+/// Marker for the economics panel
+#[derive(Component)]
+pub struct EconomicsPanel;
+
+/// Marker for the economics panel's text
+#[derive(Component)]
+pub struct EconomicsText;
+
+/// System to create the (hidden) economics panel
+pub fn setup_economics_ui(mut commands: Commands) {
+    commands
+        .spawn((
+            EconomicsPanel,
+            Node {
+                position_type: PositionType::Absolute,
+                bottom: Val::Px(120.0),
+                left: Val::Px(10.0),
+                padding: UiRect::all(Val::Px(10.0)),
+                ..default()
+            },
+            BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.8)),
+            Visibility::Hidden,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                EconomicsText,
+                Text::new(""),
+                TextFont {
+                    font_size: 14.0,
+                    ..default()
+                },
+                TextColor(Color::WHITE),
+            ));
+        });
+}
+
+/// System to toggle the economics panel (M) and fill in the active formula
+pub fn update_economics_panel(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    sim_world: Res<SimWorldResource>,
+    mut panel_query: Query<&mut Visibility, With<EconomicsPanel>>,
+    mut text_query: Query<&mut Text, With<EconomicsText>>,
+) {
+    if !keyboard.just_pressed(KeyCode::KeyM) {
+        return;
+    }
+    let mut shown = false;
+    for mut visibility in panel_query.iter_mut() {
+        *visibility = match *visibility {
+            Visibility::Hidden => Visibility::Inherited,
+            _ => Visibility::Hidden,
+        };
+        shown = *visibility != Visibility::Hidden;
+    }
+    if !shown {
+        return;
+    }
+
+    // The text is filled in when the panel opens, so a reloaded economy
+    // shows the next time it is opened
+    let Some(game_state) = &sim_world.0.game_state else {
+        return;
+    };
+    let economy = &game_state.economy;
+    let mut description = String::from("Worker pay per trip");
+    for term in economy.worker_pay_formula() {
+        description.push_str(&format!("\n{}", term));
+    }
+    description.push_str(&format!(
+        "\n\nShop delivery: ${}\nBuild: road ${}, house ${}, factory ${}, shop ${}",
+        economy.revenue_shop_delivery,
+        economy.cost_road,
+        economy.cost_apartment,
+        economy.cost_factory,
+        economy.cost_shop
+    ));
+    for mut text in text_query.iter_mut() {
+        **text = description.clone();
+    }
+}
@@ -0,0 +1,82 @@
+//! Reloading the economy file while the game runs
+//!
+//! Given `--watch-economy`, the economy file is checked every
+//! [`WATCH_INTERVAL_SECS`], and when it has been saved its new values are put
+//! into effect in both the live and experimental worlds (see
+//! [`crate::simulation::EconomyWatcher`]). A file that doesn't parse or
+//! validate is reported and the economy in effect is kept.
+
+use bevy::prelude::*;
+
+use super::components::SimWorldResource;
+use super::worlds::WorldSlots;
+use crate::simulation::{EconomyReload, EconomyWatcher};
+
+/// Seconds between checks of the economy file
+pub const WATCH_INTERVAL_SECS: f32 = 1.0;
+
+/// The economy file being watched for changes
+#[derive(Resource)]
+pub struct EconomyReloader {
+    watcher: EconomyWatcher,
+    since_check: f32,
+}
+
+impl EconomyReloader {
+    /// Watch an economy file that has just been loaded
+    pub fn new(watcher: EconomyWatcher) -> Self {
+        Self {
+            watcher,
+            since_check: 0.0,
+        }
+    }
+}
+
+/// System to put the economy file's changes into effect once it is saved
+pub fn reload_economy(
+    time: Res<Time>,
+    mut sim_world: ResMut<SimWorldResource>,
+    mut slots: ResMut<WorldSlots>,
+    reloader: Option<ResMut<EconomyReloader>>,
+) {
+    let Some(mut reloader) = reloader else {
+        return;
+    };
+    reloader.since_check += time.delta_secs();
+    if reloader.since_check < WATCH_INTERVAL_SECS {
+        return;
+    }
+    reloader.since_check = 0.0;
+    let economy = match reloader.watcher.poll() {
+        None => return,
+        Some(Ok(economy)) => economy,
+        Some(Err(e)) => {
+            warn!("Kept the economy in effect: {:#}", e);
+            return;
+        }
+    };
+
+    let mut reload = EconomyReload::default();
+    let hidden = slots.hidden_mut();
+    for world in std::iter::once(&mut sim_world.0).chain(hidden) {
+        match world.reload_economy(economy.clone()) {
+            Ok(changes) => reload = changes,
+            Err(e) => warn!("Kept the economy in effect: {:#}", e),
+        }
+    }
+    let path = reloader.watcher.path().display();
+    if reload.is_empty() {
+        info!("Reloaded {} with no changes", path);
+        return;
+    }
+    if !reload.applied.is_empty() {
+        info!("Reloaded {}: {}", path, reload.applied.join(", "));
+    }
+    if !reload.deferred.is_empty() {
+        info!(
+            "Changes to {} in {} take effect in a new game",
+            reload.deferred.join(", "),
+            path
+        );
+    }
+}
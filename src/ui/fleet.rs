@@ -0,0 +1,20 @@
+//! Fleet maintenance
+//!
+//! Press R to replace every worn apartment car and factory truck at once.
+
+use bevy::prelude::*;
+
+use super::components::PendingCommands;
+use super::multiplayer::MultiplayerSession;
+use crate::simulation::BuildCommand;
+
+/// System to replace worn vehicles (R)
+pub fn handle_fleet_keyboard(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    session: Res<MultiplayerSession>,
+    mut pending: ResMut<PendingCommands>,
+) {
+    if keyboard.just_pressed(KeyCode::KeyR) && !session.is_spectating() {
+        pending.0.push(BuildCommand::ReplaceWornVehicles);
+    }
+}
@@ -0,0 +1,86 @@
+//! Noise and emission hotspot overlay
+//!
+//! Marks every intersection whose waiting traffic has been idling (see
+//! [`crate::simulation::Hotspot`]) with a glowing ring, larger and redder
+//! the worse it is, pulsing so the worst junctions catch the eye. A faint
+//! circle shows how far the hotspot takes appeal from homes around it.
+
+use bevy::prelude::*;
+
+use super::components::SimWorldResource;
+use crate::simulation::HOTSPOT_RADIUS;
+
+/// Radius of the ring at the lowest severity
+const MIN_RING_RADIUS: f32 = 0.6;
+
+/// Radius of the ring at full severity
+const MAX_RING_RADIUS: f32 = 2.5;
+
+/// Rings drawn inside each other to make the glow
+const GLOW_RINGS: usize = 4;
+
+/// Pulses a second
+const PULSE_RATE: f32 = 1.5;
+
+/// Settings for the hotspot overlay
+#[derive(Resource, Default)]
+pub struct HotspotOverlay {
+    /// Whether the overlay is drawn
+    pub enabled: bool,
+}
+
+/// System to toggle the hotspot overlay (F9)
+pub fn handle_hotspot_keyboard(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut overlay: ResMut<HotspotOverlay>,
+) {
+    if keyboard.just_pressed(KeyCode::F9) {
+        overlay.enabled = !overlay.enabled;
+        info!(
+            "Hotspot overlay {}",
+            if overlay.enabled { "on" } else { "off" }
+        );
+    }
+}
+
+/// Color for a hotspot of `severity`, `alpha` opaque
+fn hotspot_color(severity: f32, alpha: f32) -> Color {
+    let severity = severity.clamp(0.0, 1.0);
+    // Amber through to red
+    Color::srgba(1.0, 0.75 * (1.0 - severity), 0.1, alpha)
+}
+
+/// System to draw a glowing marker on each hotspot
+pub fn draw_hotspots(
+    overlay: Res<HotspotOverlay>,
+    sim_world: Res<SimWorldResource>,
+    time: Res<Time>,
+    mut gizmos: Gizmos,
+) {
+    if !overlay.enabled {
+        return;
+    }
+    let flat = Quat::from_rotation_x(std::f32::consts::FRAC_PI_2);
+    let pulse = 0.5 + 0.5 * (time.elapsed_secs() * PULSE_RATE * std::f32::consts::TAU).sin();
+    for hotspot in sim_world.0.hotspots() {
+        let center = Vec3::new(hotspot.position.x, 0.15, hotspot.position.z);
+        let radius = MIN_RING_RADIUS + (MAX_RING_RADIUS - MIN_RING_RADIUS) * hotspot.severity;
+        // Rings fading outwards, swelling with the pulse the worse it is
+        let swell = 1.0 + 0.15 * pulse * hotspot.severity;
+        for ring in 0..GLOW_RINGS {
+            let share = (ring + 1) as f32 / GLOW_RINGS as f32;
+            gizmos.circle(
+                Isometry3d::new(center, flat),
+                radius * share * swell,
+                hotspot_color(hotspot.severity, 1.0 - share * 0.75),
+            );
+        }
+        gizmos
+            .circle(
+                Isometry3d::new(center, flat),
+                HOTSPOT_RADIUS,
+                hotspot_color(hotspot.severity, 0.2),
+            )
+            .resolution(48);
+    }
+}
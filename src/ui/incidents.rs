@@ -0,0 +1,126 @@
+//! Incident notices and the insurance toggle
+//!
+//! Press I to take out or cancel insurance. Each breakdown, collision and
+//! premium the game records pops up a toast. The hazard ring over stopped
+//! vehicles is drawn by the renderer.
+
+use bevy::prelude::*;
+
+use super::achievements::{spawn_toast, AchievementToastStack};
+use super::components::{PendingCommands, SimWorldResource};
+use super::multiplayer::MultiplayerSession;
+use crate::simulation::{AlarmConfig, BuildCommand, GameEvent, IncidentKind};
+
+/// System to toggle insurance (I)
+pub fn handle_insurance_keyboard(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    sim_world: Res<SimWorldResource>,
+    session: Res<MultiplayerSession>,
+    mut pending: ResMut<PendingCommands>,
+) {
+    if !keyboard.just_pressed(KeyCode::KeyI) || session.is_spectating() {
+        return;
+    }
+    if let Some(game_state) = &sim_world.0.game_state {
+        pending.0.push(BuildCommand::SetInsurance {
+            insured: !game_state.insured,
+        });
+    }
+}
+
+/// Name of the economy's alarm at an index, which a reload may have removed
+fn alarm_name(alarms: &[AlarmConfig], alarm: usize) -> &str {
+    alarms
+        .get(alarm)
+        .map_or("An alarm", |config| config.name.as_str())
+}
+
+/// System to show a toast for each event the game has recorded
+pub fn report_game_events(
+    mut commands: Commands,
+    mut sim_world: ResMut<SimWorldResource>,
+    stack_query: Query<Entity, With<AchievementToastStack>>,
+) {
+    // Only touch the resource mutably when there is something to take
+    let has_events = sim_world
+        .0
+        .game_state
+        .as_ref()
+        .is_some_and(|game_state| !game_state.events.is_empty());
+    if !has_events {
+        return;
+    }
+    let Some(game_state) = &mut sim_world.0.game_state else {
+        return;
+    };
+    let events = game_state.take_events();
+    let alarms = &game_state.economy.alarms;
+    let Ok(stack) = stack_query.single() else {
+        return;
+    };
+
+    for event in events {
+        let (title, description) = match event {
+            GameEvent::Incident { kind, cost } => {
+                let title = match kind {
+                    IncidentKind::Breakdown => "⚠ Breakdown",
+                    IncidentKind::Collision => "⚠ Collision",
+                };
+                let description = if cost > 0 {
+                    format!("Cleanup cost ${}", cost)
+                } else {
+                    "Covered by insurance".to_string()
+                };
+                (title, description)
+            }
+            GameEvent::PremiumPaid { amount } => {
+                ("🛡 Insurance", format!("Premium of ${} paid", amount))
+            }
+            GameEvent::VehiclesReplaced { count } => {
+                ("🔧 Fleet", format!("Replaced {} worn vehicles", count))
+            }
+            GameEvent::InsuranceLapsed => (
+                "🛡 Insurance lapsed",
+                "Couldn't afford the premium".to_string(),
+            ),
+            GameEvent::DeliverySlaBroken { level } => (
+                "📦 Deliveries late",
+                format!("A shop order missed its deadline at demand x{:.2}", level),
+            ),
+            GameEvent::ScenarioEventStarted { event } => {
+                ("📣 Scenario event", format!("{} started", event.label()))
+            }
+            GameEvent::ScenarioEventEnded { event } => {
+                ("📣 Scenario event", format!("{} is over", event.label()))
+            }
+            GameEvent::SpeedGoalMet { average } => (
+                "🚦 Rush hour",
+                format!("Traffic kept moving at {:.1} on average", average),
+            ),
+            GameEvent::SpeedGoalMissed { average } => (
+                "🚦 Rush hour gridlock",
+                format!("Traffic slowed to {:.1} on average", average),
+            ),
+            GameEvent::UpgradeBought { upgrade, level } => (
+                "⬆ Upgrade",
+                format!("Bought {} level {}", upgrade.label(), level),
+            ),
+            GameEvent::AlarmRaised { alarm, value } => (
+                "🚨 Alarm",
+                format!("{} at {:.2}", alarm_name(alarms, alarm), value),
+            ),
+            GameEvent::AlarmCleared { alarm } => (
+                "✅ Alarm cleared",
+                format!("{} is back to normal", alarm_name(alarms, alarm)),
+            ),
+        };
+        spawn_toast(
+            &mut commands,
+            stack,
+            title,
+            &description,
+            Color::srgb(1.0, 0.5, 0.2),
+            Color::srgba(0.15, 0.05, 0.0, 0.9),
+        );
+    }
+}
@@ -3,7 +3,8 @@
 use bevy::input::mouse::MouseMotion;
 use bevy::prelude::*;
 
-use super::components::{CameraSettings, MainCamera};
+use super::components::{CameraSettings, MainCamera, SimWorldResource};
+use super::intersection_drag::IntersectionDrag;
 
 /// Handle basic keyboard input
 pub fn handle_input(keyboard: Res<ButtonInput<KeyCode>>, mut exit: MessageWriter<AppExit>) {
@@ -16,8 +17,10 @@ pub fn handle_input(keyboard: Res<ButtonInput<KeyCode>>, mut exit: MessageWriter
 ///
 /// Controls:
 /// - Click and drag: Orbit camera around the point where camera looks at the ground
+///   (unless an intersection is being dragged)
 pub fn handle_camera_mouse(
     mouse_button: Res<ButtonInput<MouseButton>>,
+    drag: Res<IntersectionDrag>,
     mut mouse_motion: MessageReader<MouseMotion>,
     mut camera_query: Query<&mut Transform, With<MainCamera>>,
 ) {
@@ -25,6 +28,12 @@ pub fn handle_camera_mouse(
         return;
     };
 
+    // Dragging an intersection uses the mouse instead
+    if drag.intersection.is_some() {
+        mouse_motion.clear();
+        return;
+    }
+
     // Only rotate when left mouse button is pressed
     if !mouse_button.pressed(MouseButton::Left) {
         return;
@@ -97,10 +106,14 @@ pub fn handle_camera_mouse(
 /// - WASD: Move camera horizontally
 /// - Q/E: Rotate camera around the center
 /// - Z/X: Zoom in/out (move camera up/down)
+///
+/// The camera stays over the world's bounds, and can't rise higher than the
+/// world is wide.
 pub fn handle_camera_movement(
     keyboard: Res<ButtonInput<KeyCode>>,
     time: Res<Time>,
     settings: Res<CameraSettings>,
+    sim_world: Res<SimWorldResource>,
     mut camera_query: Query<&mut Transform, With<MainCamera>>,
 ) {
     let Ok(mut transform) = camera_query.single_mut() else {
@@ -108,6 +121,8 @@ pub fn handle_camera_movement(
     };
 
     let delta = time.delta_secs();
+    let bounds = sim_world.0.bounds();
+    let max_height = bounds.width().max(bounds.depth());
 
     // Calculate movement direction (in the camera's local space)
     let mut movement = Vec3::ZERO;
@@ -143,7 +158,7 @@ pub fn handle_camera_movement(
     if keyboard.pressed(KeyCode::KeyX) {
         transform.translation.y += settings.zoom_speed * delta;
         // Clamp maximum height
-        transform.translation.y = transform.translation.y.min(200.0);
+        transform.translation.y = transform.translation.y.min(max_height);
     }
 
     // Rotation around center (Q/E)
@@ -159,4 +174,8 @@ pub fn handle_camera_movement(
         transform.translation = rotation * transform.translation;
         transform.rotation = rotation * transform.rotation;
     }
+
+    // Stay over the world
+    transform.translation.x = transform.translation.x.clamp(bounds.min_x, bounds.max_x);
+    transform.translation.z = transform.translation.z.clamp(bounds.min_z, bounds.max_z);
 }
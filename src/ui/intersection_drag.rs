@@ -0,0 +1,113 @@
+//! Dragging intersections to reshape roads
+//!
+//! With no build mode selected, pressing on an intersection picks it up
+//! instead of orbiting the camera. While the button is held the reshaped
+//! roads are previewed; releasing sends a [`BuildCommand::MoveIntersection`]
+//! so the move reaches co-op peers.
+
+use bevy::prelude::*;
+
+use super::components::{BuildingMode, BuildingState, PendingCommands, SimWorldResource};
+use super::multiplayer::MultiplayerSession;
+use crate::simulation::{BuildCommand, IntersectionId, MIN_ROAD_LENGTH};
+
+/// Intersection being dragged, if any
+#[derive(Resource, Default)]
+pub struct IntersectionDrag {
+    pub intersection: Option<IntersectionId>,
+}
+
+/// System to pick up an intersection under the cursor and put it down where
+/// the button is released
+pub fn drag_intersections(
+    mouse_button: Res<ButtonInput<MouseButton>>,
+    building_state: Res<BuildingState>,
+    sim_world: Res<SimWorldResource>,
+    session: Res<MultiplayerSession>,
+    mut drag: ResMut<IntersectionDrag>,
+    mut pending: ResMut<PendingCommands>,
+    interaction_query: Query<&Interaction, With<Button>>,
+) {
+    if building_state.mode != BuildingMode::None || session.is_spectating() {
+        drag.intersection = None;
+        return;
+    }
+    let Some(cursor) = building_state.cursor_position else {
+        return;
+    };
+    let network = &sim_world.0.road_network;
+
+    if mouse_button.just_pressed(MouseButton::Left)
+        && interaction_query
+            .iter()
+            .all(|interaction| *interaction == Interaction::None)
+    {
+        drag.intersection = network.find_closest_intersection(&cursor).filter(|id| {
+            network
+                .get_intersection_position(*id)
+                .is_some_and(|position| position.distance(&cursor) <= building_state.snap_distance)
+        });
+    }
+
+    if !mouse_button.just_released(MouseButton::Left) {
+        return;
+    }
+    let Some(intersection_id) = drag.intersection.take() else {
+        return;
+    };
+    // A click without a drag leaves the intersection where it is
+    let moved = network
+        .get_intersection_position(intersection_id)
+        .is_some_and(|position| position.distance(&cursor) > 0.1);
+    if moved {
+        pending.0.push(BuildCommand::MoveIntersection {
+            intersection: intersection_id,
+            position: cursor,
+        });
+    }
+}
+
+/// System to preview the roads of the intersection being dragged
+///
+/// Roads that would be too short are drawn red.
+pub fn draw_intersection_drag(
+    drag: Res<IntersectionDrag>,
+    building_state: Res<BuildingState>,
+    sim_world: Res<SimWorldResource>,
+    mut gizmos: Gizmos,
+) {
+    let (Some(intersection_id), Some(cursor)) = (drag.intersection, building_state.cursor_position)
+    else {
+        return;
+    };
+    let network = &sim_world.0.road_network;
+    let target = Vec3::new(cursor.x, 0.1, cursor.z);
+
+    gizmos.circle(
+        Isometry3d::new(target, Quat::from_rotation_arc(Vec3::Z, Vec3::Y)),
+        0.4,
+        Color::WHITE,
+    );
+    for road in network.roads().values() {
+        let other = if road.start_intersection == intersection_id {
+            road.end_intersection
+        } else if road.end_intersection == intersection_id {
+            road.start_intersection
+        } else {
+            continue;
+        };
+        let Some(other_position) = network.get_intersection_position(other) else {
+            continue;
+        };
+        let color = if cursor.distance(other_position) < MIN_ROAD_LENGTH {
+            Color::srgb(1.0, 0.2, 0.2)
+        } else {
+            Color::WHITE
+        };
+        gizmos.line(
+            Vec3::new(other_position.x, 0.1, other_position.z),
+            target,
+            color,
+        );
+    }
+}
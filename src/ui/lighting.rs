@@ -0,0 +1,213 @@
+//! Day/night lighting driven by the simulated clock
+//!
+//! Purely visual: dims the sun as the simulated day turns to night, switches on
+//! car headlights and makes shop signs glow. Nothing here feeds back into the
+//! simulation.
+
+use bevy::prelude::*;
+
+use super::components::{SimWorldResource, VehicleBody, VisualLink};
+use crate::simulation::VisualId;
+
+/// Sun illuminance at midday
+const DAY_ILLUMINANCE: f32 = 10000.0;
+/// Sun illuminance in the middle of the night
+const NIGHT_ILLUMINANCE: f32 = 300.0;
+/// Ambient brightness at midday
+const DAY_AMBIENT: f32 = 80.0;
+/// Ambient brightness in the middle of the night
+const NIGHT_AMBIENT: f32 = 15.0;
+
+/// Length of the translucent headlight beam in front of a vehicle
+const HEADLIGHT_BEAM_LENGTH: f32 = 1.2;
+/// Radius of the headlight beam where it meets the road
+const HEADLIGHT_BEAM_RADIUS: f32 = 0.35;
+/// Maximum opacity of the headlight beam at full darkness
+const HEADLIGHT_BEAM_MAX_ALPHA: f32 = 0.35;
+
+/// Shared meshes and materials for night-time effects
+///
+/// Materials are shared by every car/shop so the lighting update only has to
+/// touch a handful of assets per frame, regardless of how many vehicles exist.
+#[derive(Resource, Default)]
+pub struct NightLightingAssets {
+    headlight_mesh: Option<Handle<Mesh>>,
+    headlight_material: Option<Handle<StandardMaterial>>,
+    beam_mesh: Option<Handle<Mesh>>,
+    beam_material: Option<Handle<StandardMaterial>>,
+    sign_mesh: Option<Handle<Mesh>>,
+    sign_material: Option<Handle<StandardMaterial>>,
+}
+
+/// Marker for the glowing sign on top of a shop
+#[derive(Component)]
+pub struct ShopSign;
+
+/// Marker for a vehicle's headlight parts
+#[derive(Component)]
+pub struct Headlight;
+
+/// How dark it is for a given hour of the day
+///
+/// Returns 0.0 during the day and 1.0 in the middle of the night, with a smooth
+/// transition around dawn (~6:00) and dusk (~18:00).
+pub fn darkness_at(hour: f32) -> f32 {
+    // Sun height: -1 at midnight, +1 at noon
+    let sun_height = -(hour / 24.0 * std::f32::consts::TAU).cos();
+    // Fully dark once the sun is well below the horizon, fully lit once well above
+    let t = ((0.25 - sun_height) / 0.5).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+/// System to attach headlights to newly spawned vehicles
+pub fn attach_headlights(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut assets: ResMut<NightLightingAssets>,
+    new_vehicles: Query<(Entity, &VehicleBody), Added<VehicleBody>>,
+) {
+    for (entity, body) in new_vehicles.iter() {
+        let length = body.length;
+
+        let headlight_mesh = assets
+            .headlight_mesh
+            .get_or_insert_with(|| meshes.add(Cuboid::new(0.06, 0.06, 0.02)))
+            .clone();
+        let headlight_material = assets
+            .headlight_material
+            .get_or_insert_with(|| {
+                materials.add(StandardMaterial {
+                    base_color: Color::srgb(0.9, 0.9, 0.8),
+                    emissive: LinearRgba::BLACK,
+                    ..default()
+                })
+            })
+            .clone();
+        let beam_mesh = assets
+            .beam_mesh
+            .get_or_insert_with(|| {
+                meshes.add(Cone {
+                    radius: HEADLIGHT_BEAM_RADIUS,
+                    height: HEADLIGHT_BEAM_LENGTH,
+                })
+            })
+            .clone();
+        let beam_material = assets
+            .beam_material
+            .get_or_insert_with(|| {
+                materials.add(StandardMaterial {
+                    base_color: Color::srgba(1.0, 0.95, 0.7, 0.0),
+                    alpha_mode: AlphaMode::Blend,
+                    unlit: true,
+                    ..default()
+                })
+            })
+            .clone();
+
+        // Vehicles face +Z in local space; put two lamps on the front face
+        let front = length / 2.0;
+        commands.entity(entity).with_children(|parent| {
+            for side in [-0.1, 0.1] {
+                parent.spawn((
+                    Headlight,
+                    Mesh3d(headlight_mesh.clone()),
+                    MeshMaterial3d(headlight_material.clone()),
+                    Transform::from_translation(Vec3::new(side, 0.0, front)),
+                ));
+            }
+
+            // The cone's apex points along +Y; tip it forward so it widens ahead of the car
+            parent.spawn((
+                Headlight,
+                Mesh3d(beam_mesh),
+                MeshMaterial3d(beam_material),
+                Transform::from_translation(Vec3::new(
+                    0.0,
+                    -0.05,
+                    front + HEADLIGHT_BEAM_LENGTH / 2.0,
+                ))
+                .with_rotation(Quat::from_rotation_x(-std::f32::consts::FRAC_PI_2)),
+            ));
+        });
+    }
+}
+
+/// System to attach a glowing sign to newly spawned shops
+pub fn attach_shop_signs(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut assets: ResMut<NightLightingAssets>,
+    new_visuals: Query<(Entity, &VisualLink), Added<VisualLink>>,
+) {
+    let new_shops = new_visuals
+        .iter()
+        .filter(|(_, link)| matches!(link.0, VisualId::Shop(_)));
+    for (entity, _) in new_shops {
+        let sign_mesh = assets
+            .sign_mesh
+            .get_or_insert_with(|| meshes.add(Cuboid::new(0.9, 0.25, 0.05)))
+            .clone();
+        let sign_material = assets
+            .sign_material
+            .get_or_insert_with(|| {
+                materials.add(StandardMaterial {
+                    base_color: Color::srgb(1.0, 0.5, 0.8),
+                    emissive: LinearRgba::BLACK,
+                    ..default()
+                })
+            })
+            .clone();
+
+        let sign = commands
+            .spawn((
+                ShopSign,
+                Mesh3d(sign_mesh),
+                MeshMaterial3d(sign_material),
+                Transform::from_translation(Vec3::new(0.0, 0.35, 0.63)),
+            ))
+            .id();
+        commands.entity(entity).add_child(sign);
+    }
+}
+
+/// System to update sun, ambient light and emissive materials from the simulated clock
+pub fn update_day_night_lighting(
+    sim_world: Res<SimWorldResource>,
+    assets: Res<NightLightingAssets>,
+    mut ambient: ResMut<AmbientLight>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut sun_query: Query<&mut DirectionalLight>,
+) {
+    let darkness = darkness_at(sim_world.0.time_of_day());
+
+    for mut sun in sun_query.iter_mut() {
+        sun.illuminance = DAY_ILLUMINANCE + (NIGHT_ILLUMINANCE - DAY_ILLUMINANCE) * darkness;
+    }
+    ambient.brightness = DAY_AMBIENT + (NIGHT_AMBIENT - DAY_AMBIENT) * darkness;
+
+    if let Some(material) = assets
+        .headlight_material
+        .as_ref()
+        .and_then(|handle| materials.get_mut(handle))
+    {
+        material.emissive = LinearRgba::rgb(8.0, 8.0, 6.0) * darkness;
+    }
+    if let Some(material) = assets
+        .beam_material
+        .as_ref()
+        .and_then(|handle| materials.get_mut(handle))
+    {
+        material
+            .base_color
+            .set_alpha(HEADLIGHT_BEAM_MAX_ALPHA * darkness);
+    }
+    if let Some(material) = assets
+        .sign_material
+        .as_ref()
+        .and_then(|handle| materials.get_mut(handle))
+    {
+        material.emissive = LinearRgba::rgb(6.0, 2.0, 4.0) * darkness;
+    }
+}
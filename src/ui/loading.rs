@@ -0,0 +1,110 @@
+//! Loading progress bar
+//!
+//! Visuals are spawned at most
+//! [`SPAWNS_PER_FRAME`](super::renderer::SPAWNS_PER_FRAME) a frame, so a big city
+//! appears over several frames. Until it has all appeared, a bar in the
+//! middle of the screen shows how far along it is.
+
+use bevy::prelude::*;
+
+use super::renderer::VisualSync;
+
+/// Width of the loading bar
+const BAR_WIDTH: f32 = 300.0;
+
+/// Marker for the loading bar and its label
+#[derive(Component)]
+pub struct LoadingBar;
+
+/// Marker for the filled part of the loading bar
+#[derive(Component)]
+pub struct LoadingBarFill;
+
+/// Marker for the loading bar's label
+#[derive(Component)]
+pub struct LoadingText;
+
+/// System to create the (hidden) loading bar
+pub fn setup_loading_bar(mut commands: Commands) {
+    commands
+        .spawn((
+            LoadingBar,
+            Node {
+                position_type: PositionType::Absolute,
+                top: Val::Percent(45.0),
+                left: Val::Percent(50.0),
+                margin: UiRect::left(Val::Px(-BAR_WIDTH / 2.0)),
+                width: Val::Px(BAR_WIDTH),
+                padding: UiRect::all(Val::Px(10.0)),
+                flex_direction: FlexDirection::Column,
+                row_gap: Val::Px(6.0),
+                ..default()
+            },
+            BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.7)),
+            Visibility::Hidden,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                LoadingText,
+                Text::new("Loading city..."),
+                TextFont {
+                    font_size: 14.0,
+                    ..default()
+                },
+                TextColor(Color::WHITE),
+            ));
+            parent
+                .spawn((
+                    Node {
+                        width: Val::Percent(100.0),
+                        height: Val::Px(8.0),
+                        ..default()
+                    },
+                    BackgroundColor(Color::srgb(0.2, 0.2, 0.2)),
+                ))
+                .with_children(|track| {
+                    track.spawn((
+                        LoadingBarFill,
+                        Node {
+                            width: Val::Percent(0.0),
+                            height: Val::Percent(100.0),
+                            ..default()
+                        },
+                        BackgroundColor(Color::srgb(0.3, 0.8, 0.4)),
+                    ));
+                });
+        });
+}
+
+/// System to show the loading bar while visuals are waiting to be spawned
+pub fn update_loading_bar(
+    visual_sync: Res<VisualSync>,
+    mut bar_query: Query<&mut Visibility, With<LoadingBar>>,
+    mut fill_query: Query<&mut Node, With<LoadingBarFill>>,
+    mut text_query: Query<&mut Text, With<LoadingText>>,
+) {
+    let progress = visual_sync.0.loading_progress();
+    for mut visibility in bar_query.iter_mut() {
+        visibility.set_if_neq(if progress.is_some() {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        });
+    }
+    let Some(progress) = progress else {
+        return;
+    };
+    for mut node in fill_query.iter_mut() {
+        node.width = Val::Percent(progress * 100.0);
+    }
+    let label = format!(
+        "Loading city... {:.0}% ({} to go)",
+        progress * 100.0,
+        visual_sync.0.pending_spawns()
+    );
+    for mut text in text_query.iter_mut() {
+        if **text != label {
+            **text = label.clone();
+        }
+    }
+}
@@ -3,68 +3,379 @@
 //! This module is purely for visualization - all simulation logic is in the `simulation` module.
 //! The UI reads state from `SimWorld` and renders it using Bevy's 3D graphics.
 
+mod achievements;
+mod alarms;
+mod assignment;
+mod autosave;
 mod building;
+mod catchment;
+mod cinematic;
 mod components;
+mod diagnostics;
+mod economics;
+mod economy_reload;
+mod fleet;
+mod hotspots;
+mod incidents;
 mod input;
+mod intersection_drag;
+mod lighting;
+mod loading;
+mod multiplayer;
+mod recap;
+mod renderer;
+mod report;
+mod road_menu;
+mod route_preview;
+mod score;
+mod signal_advice;
+mod signals;
+mod speed_heat;
 pub mod spawner;
+mod sustainability;
 mod sync;
+mod terrain;
+mod traffic_volume;
+mod trails;
+mod transit;
+mod trips;
+mod tutorial;
+mod upgrades;
+mod vehicle_models;
 mod world;
+mod worlds;
 
 use bevy::prelude::*;
 
 pub use components::{EntityMappings, SimWorldResource, UI_STARTING_BUDGET};
+pub use achievements::Achievements;
+pub use autosave::Autosave;
+pub use cinematic::Cinematic;
+pub use diagnostics::EntityBudgetWatch;
+pub use economy_reload::EconomyReloader;
+pub use multiplayer::MultiplayerSession;
+pub use recap::RecapScreen;
+pub use report::RunReport;
+pub use score::{RunScores, UI_SCENARIO};
+pub use tutorial::Tutorial;
 
+use achievements::{
+    evaluate_achievements, setup_achievement_ui, update_achievement_panel,
+    update_achievement_toasts,
+};
+use alarms::{handle_alarm_jump, setup_alarm_ui, update_alarm_banner};
+use autosave::{autosave_city, finish_autosave};
+use report::write_run_report;
 use building::{
-    handle_build_buttons, handle_build_keyboard, handle_placement_click, setup_building_ui,
-    update_button_borders, update_cursor_position, update_ghost_preview,
+    handle_build_buttons, handle_build_keyboard, handle_cleanup_keyboard, handle_placement_click,
+    setup_building_ui, update_button_borders, update_cursor_position, update_ghost_preview,
+    update_placement_hint,
 };
+use catchment::{setup_catchment_ui, update_catchments};
 use components::*;
+use diagnostics::{check_entity_budgets, setup_diagnostics_ui, update_memory_panel};
+use economics::{setup_economics_ui, update_economics_panel};
+use economy_reload::reload_economy;
+use fleet::handle_fleet_keyboard;
+use hotspots::{draw_hotspots, handle_hotspot_keyboard, HotspotOverlay};
+use incidents::{handle_insurance_keyboard, report_game_events};
+use cinematic::{setup_cinematic_ui, update_cinematic};
 use input::{handle_camera_mouse, handle_camera_movement, handle_input};
-use spawner::{spawn_initial_visuals, ApartmentVisualAssets};
+use intersection_drag::{draw_intersection_drag, drag_intersections, IntersectionDrag};
+use lighting::{
+    attach_headlights, attach_shop_signs, update_day_night_lighting, NightLightingAssets,
+};
+use loading::{setup_loading_bar, update_loading_bar};
+use multiplayer::{lock_spectator_build_mode, setup_multiplayer_ui, update_multiplayer_status};
+use recap::{handle_recap_buttons, setup_recap_ui, show_recap};
+use renderer::{sync_visuals, VehicleEntityPool, VisualSync};
+use road_menu::{
+    draw_road_signs, handle_road_menu_buttons, open_road_menu, setup_road_menu,
+    update_road_menu, RoadMenu,
+};
+use route_preview::{
+    handle_route_preview_keyboard, setup_route_preview_ui, update_route_preview, RoutePreview,
+};
+use assignment::{
+    draw_assignment, handle_assignment_keyboard, setup_assignment_ui, update_assignment,
+    TrafficModel,
+};
+use score::record_run_summary;
+use signal_advice::{setup_signal_advice_ui, update_signal_advice};
+use signals::{
+    draw_signal_lights, drag_phase_bars, handle_signal_buttons, handle_signal_click,
+    layout_signal_panel, setup_signal_ui, update_signal_panel, SignalEditor,
+};
+use spawner::ApartmentVisualAssets;
+use speed_heat::{draw_speed_heat, handle_speed_heat_keyboard, SpeedHeatOverlay};
+use sustainability::{setup_sustainability_ui, update_sustainability_panel};
 use sync::{
-    sync_cars, tick_simulation, update_factory_delivery_indicators, update_factory_indicators,
-    update_global_demand_text, update_apartment_indicators, update_shop_indicators,
+    pace_idle_app, rebuild_road_arrows, tick_simulation, update_arrow_density,
+    update_global_demand_text,
+};
+use terrain::{draw_ferries, setup_water};
+use traffic_volume::{
+    draw_traffic_volume, handle_traffic_volume_keyboard, TrafficVolumeOverlay,
+};
+use trails::{
+    draw_vehicle_trails, handle_trail_keyboard, record_vehicle_trails, TrailSettings,
+    VehicleTrails,
+};
+use transit::{
+    draw_bus_routes, handle_bus_route_click, handle_bus_route_keyboard, BusRouteDrawing,
+};
+use trips::{setup_trip_ui, update_trip_panel};
+use tutorial::{advance_tutorial, gate_tutorial_build_mode, setup_tutorial_ui, update_tutorial_ui};
+use upgrades::{
+    handle_upgrade_buttons, handle_upgrade_keyboard, setup_upgrade_ui, update_upgrade_panel,
+};
+use vehicle_models::{
+    embed_vehicle_models, load_vehicle_models, roll_wheels, swap_in_vehicle_models,
+    VehicleModels,
 };
 use world::setup_world;
+use worlds::{
+    handle_world_keyboard, setup_world_panel, tick_hidden_world, update_world_panel, WorldSlots,
+};
 
 /// Plugin to register all UI systems
 pub struct TrafficSimUIPlugin;
 
 impl Plugin for TrafficSimUIPlugin {
     fn build(&self, app: &mut App) {
+        embed_vehicle_models(app);
         app.init_resource::<SimWorldResource>()
             .init_resource::<EntityMappings>()
             .init_resource::<CameraSettings>()
             .init_resource::<BuildingState>()
             .init_resource::<ApartmentVisualAssets>()
+            .init_resource::<NightLightingAssets>()
+            .init_resource::<TrailSettings>()
+            .init_resource::<VehicleTrails>()
+            .init_resource::<TrafficVolumeOverlay>()
+            .init_resource::<SpeedHeatOverlay>()
+            .init_resource::<HotspotOverlay>()
+            .init_resource::<TrafficModel>()
+            .init_resource::<RoadArrowDensity>()
+            .init_resource::<Tutorial>()
+            .init_resource::<Cinematic>()
+            .init_resource::<Achievements>()
+            .init_resource::<RunScores>()
+            .init_resource::<RecapScreen>()
+            .init_resource::<EntityBudgetWatch>()
+            .init_resource::<PendingCommands>()
+            .init_resource::<MultiplayerSession>()
+            .init_resource::<SignalEditor>()
+            .init_resource::<RoadMenu>()
+            .init_resource::<IntersectionDrag>()
+            .init_resource::<RoutePreview>()
+            .init_resource::<VisualSync>()
+            .init_resource::<VehicleEntityPool>()
+            .init_resource::<VehicleModels>()
+            .init_resource::<WorldSlots>()
+            .init_resource::<BusRouteDrawing>()
+            .init_resource::<bevy::winit::WinitSettings>()
             .add_systems(
                 Startup,
                 (
                     setup_world,
-                    spawn_initial_visuals.after(setup_world),
                     setup_building_ui,
+                    (setup_tutorial_ui, setup_cinematic_ui),
+                    setup_achievement_ui,
+                    setup_alarm_ui,
+                    setup_multiplayer_ui,
+                    setup_signal_ui,
+                    setup_road_menu,
+                    setup_economics_ui,
+                    setup_upgrade_ui,
+                    setup_assignment_ui,
+                    (setup_trip_ui, setup_sustainability_ui),
+                    setup_signal_advice_ui,
+                    (setup_catchment_ui, setup_recap_ui),
+                    setup_water,
+                    setup_diagnostics_ui,
+                    setup_loading_bar,
+                    setup_route_preview_ui,
+                    load_vehicle_models,
+                    setup_world_panel,
+                ),
+            )
+            .add_systems(FixedUpdate, (tick_simulation, tick_hidden_world))
+            .add_systems(Update, pace_idle_app)
+            .add_systems(Update, (autosave_city, reload_economy))
+            .add_systems(Update, (check_entity_budgets, update_memory_panel))
+            .add_systems(Last, (finish_autosave, write_run_report))
+            .add_systems(
+                Update,
+                (
+                    update_arrow_density,
+                    rebuild_road_arrows,
+                    sync_visuals,
+                    update_loading_bar,
+                )
+                    .chain(),
+            )
+            .add_systems(
+                Update,
+                (
+                    update_multiplayer_status,
+                    lock_spectator_build_mode
+                        .after(handle_build_buttons)
+                        .after(handle_build_keyboard)
+                        .before(handle_placement_click),
                 ),
             )
-            .add_systems(FixedUpdate, tick_simulation)
             .add_systems(
                 Update,
                 (
-                    sync_cars,
-                    update_factory_indicators,
-                    update_apartment_indicators,
-                    update_factory_delivery_indicators,
-                    update_shop_indicators,
                     update_global_demand_text,
                     handle_input,
                     handle_camera_movement,
-                    handle_camera_mouse,
+                    handle_camera_mouse.after(drag_intersections),
+                    update_cinematic
+                        .after(handle_camera_movement)
+                        .after(handle_camera_mouse),
                     handle_build_buttons,
                     handle_build_keyboard,
                     update_cursor_position,
                     update_ghost_preview,
+                    update_placement_hint,
                     handle_placement_click,
                     update_button_borders,
                 ),
+            )
+            .add_systems(
+                Update,
+                (
+                    attach_headlights,
+                    attach_shop_signs,
+                    update_day_night_lighting,
+                    swap_in_vehicle_models,
+                    roll_wheels,
+                ),
+            )
+            .add_systems(
+                Update,
+                (
+                    record_run_summary,
+                    show_recap.after(record_run_summary),
+                    handle_recap_buttons,
+                    evaluate_achievements,
+                    update_achievement_toasts,
+                    update_achievement_panel,
+                    update_alarm_banner,
+                    handle_alarm_jump,
+                ),
+            )
+            .add_systems(
+                Update,
+                (
+                    handle_insurance_keyboard,
+                    handle_fleet_keyboard,
+                    handle_cleanup_keyboard,
+                    report_game_events,
+                    update_economics_panel,
+                    update_trip_panel,
+                    update_sustainability_panel,
+                    update_signal_advice,
+                    update_catchments,
+                    draw_ferries,
+                ),
+            )
+            .add_systems(
+                Update,
+                (
+                    advance_tutorial,
+                    gate_tutorial_build_mode
+                        .after(handle_build_buttons)
+                        .after(handle_build_keyboard)
+                        .before(handle_placement_click),
+                    update_tutorial_ui.after(update_button_borders),
+                ),
+            )
+            .add_systems(
+                Update,
+                (
+                    handle_signal_click.after(update_cursor_position),
+                    handle_signal_buttons,
+                    drag_phase_bars,
+                    layout_signal_panel
+                        .after(handle_signal_click)
+                        .after(handle_signal_buttons),
+                    update_signal_panel.after(layout_signal_panel),
+                    draw_signal_lights,
+                ),
+            )
+            .add_systems(
+                Update,
+                (
+                    handle_bus_route_click.after(update_cursor_position),
+                    handle_bus_route_keyboard,
+                    draw_bus_routes,
+                ),
+            )
+            .add_systems(
+                Update,
+                (
+                    open_road_menu.after(update_cursor_position),
+                    handle_road_menu_buttons,
+                    update_road_menu
+                        .after(open_road_menu)
+                        .after(handle_road_menu_buttons),
+                    draw_road_signs,
+                ),
+            )
+            .add_systems(
+                Update,
+                (
+                    drag_intersections.after(update_cursor_position),
+                    draw_intersection_drag.after(drag_intersections),
+                ),
+            )
+            .add_systems(
+                Update,
+                (
+                    handle_trail_keyboard,
+                    record_vehicle_trails,
+                    draw_vehicle_trails,
+                )
+                    .chain(),
+            )
+            .add_systems(
+                Update,
+                (handle_traffic_volume_keyboard, draw_traffic_volume).chain(),
+            )
+            .add_systems(
+                Update,
+                (handle_speed_heat_keyboard, draw_speed_heat).chain(),
+            )
+            .add_systems(Update, (handle_hotspot_keyboard, draw_hotspots).chain())
+            .add_systems(
+                Update,
+                (
+                    handle_assignment_keyboard,
+                    update_assignment,
+                    draw_assignment,
+                )
+                    .chain(),
+            )
+            .add_systems(
+                Update,
+                (
+                    handle_upgrade_keyboard,
+                    handle_upgrade_buttons,
+                    update_upgrade_panel.after(handle_upgrade_keyboard),
+                ),
+            )
+            .add_systems(
+                Update,
+                (handle_route_preview_keyboard, update_route_preview)
+                    .chain()
+                    .after(update_cursor_position),
+            )
+            .add_systems(
+                Update,
+                (handle_world_keyboard.before(sync_visuals), update_world_panel).chain(),
             );
     }
 }
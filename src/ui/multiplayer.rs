@@ -0,0 +1,112 @@
+//! Multiplayer session state and status display
+//!
+//! The UI either plays alone, hosts a LAN session or joins one. See
+//! [`crate::multiplayer`] for how the lockstep session works.
+
+use bevy::prelude::*;
+
+use super::components::{BuildingMode, BuildingState};
+use crate::multiplayer::{LockstepClient, LockstepHost};
+
+/// How the UI takes part in a multiplayer session
+#[derive(Resource, Default)]
+pub enum MultiplayerSession {
+    /// Single player
+    #[default]
+    Offline,
+    /// Runs the authoritative simulation for everyone
+    Host(LockstepHost),
+    /// Mirrors the host's simulation (as a player or a spectator)
+    Client(LockstepClient),
+}
+
+impl MultiplayerSession {
+    /// Whether this session only watches
+    pub fn is_spectating(&self) -> bool {
+        matches!(self, MultiplayerSession::Client(client) if client.is_spectator())
+    }
+}
+
+/// Marker for the multiplayer status text
+#[derive(Component)]
+pub struct MultiplayerStatusText;
+
+/// System to create the multiplayer status text (empty when offline)
+pub fn setup_multiplayer_ui(mut commands: Commands) {
+    commands.spawn((
+        MultiplayerStatusText,
+        Text::new(""),
+        TextFont {
+            font_size: 14.0,
+            ..default()
+        },
+        TextColor(Color::srgb(0.6, 0.9, 1.0)),
+        Node {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(70.0),
+            right: Val::Px(10.0),
+            ..default()
+        },
+    ));
+}
+
+/// System to show who is connected and whether the world is in sync
+pub fn update_multiplayer_status(
+    session: Res<MultiplayerSession>,
+    mut text_query: Query<(&mut Text, &mut TextColor), With<MultiplayerStatusText>>,
+) {
+    let (status, color) = match session.as_ref() {
+        MultiplayerSession::Offline => (String::new(), Color::WHITE),
+        MultiplayerSession::Host(host) => {
+            let addr = host
+                .local_addr()
+                .map_or_else(|_| "?".to_string(), |addr| addr.to_string());
+            (
+                format!(
+                    "Hosting on {} - {} player(s), {} spectator(s)",
+                    addr,
+                    host.client_count(),
+                    host.spectator_count()
+                ),
+                Color::srgb(0.6, 0.9, 1.0),
+            )
+        }
+        MultiplayerSession::Client(client) if client.is_spectator() => (
+            format!(
+                "Spectating - tick {}{}",
+                client.tick(),
+                if client.is_desynced() {
+                    " - OUT OF SYNC"
+                } else {
+                    ""
+                }
+            ),
+            Color::srgb(0.8, 0.8, 0.8),
+        ),
+        MultiplayerSession::Client(client) if client.is_desynced() => (
+            "Connected to host - OUT OF SYNC".to_string(),
+            Color::srgb(1.0, 0.4, 0.3),
+        ),
+        MultiplayerSession::Client(client) => (
+            format!("Connected to host - tick {}", client.tick()),
+            Color::srgb(0.6, 0.9, 1.0),
+        ),
+    };
+    for (mut text, mut text_color) in text_query.iter_mut() {
+        if **text != status {
+            **text = status.clone();
+            text_color.0 = color;
+        }
+    }
+}
+
+/// System to keep spectators out of build modes
+pub fn lock_spectator_build_mode(
+    session: Res<MultiplayerSession>,
+    mut building_state: ResMut<BuildingState>,
+) {
+    if session.is_spectating() && building_state.mode != BuildingMode::None {
+        building_state.mode = BuildingMode::None;
+        building_state.road_start = None;
+    }
+}
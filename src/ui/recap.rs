@@ -0,0 +1,337 @@
+//! Recap screen shown when the game ends
+//!
+//! Once the live game is won or lost a full-screen recap covers the city,
+//! worded and arranged as the scenario's economy says (see
+//! [`crate::simulation::RecapConfig`]): the money over the run as a
+//! sparkline, the biggest traffic jam, the distance driven and the MVP
+//! factory. Its buttons keep playing the city as a sandbox (if the scenario
+//! allows it), restart the game from scratch with the same seed, or export
+//! the run's report.
+
+use bevy::prelude::*;
+use std::path::PathBuf;
+
+use super::autosave::Autosave;
+use super::components::SimWorldResource;
+use super::multiplayer::MultiplayerSession;
+use super::renderer::VisualSync;
+use super::report::RunReport;
+use super::score::RunScores;
+use super::worlds::WorldSlots;
+use crate::simulation::{Recap, RecapSection, SaveFile};
+
+/// File the recap exports the report to without `--report`
+pub const RECAP_REPORT_FILE: &str = "run_report.html";
+
+/// Characters wide the money sparkline is drawn
+const SPARKLINE_WIDTH: usize = 48;
+
+/// Blocks the sparkline is drawn with, lowest first
+const SPARKLINE_BLOCKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// Whether the recap of the current game has been seen and dismissed
+#[derive(Resource, Default)]
+pub struct RecapScreen {
+    /// Seed the game was started with, to restart it with
+    pub seed: u64,
+    /// Whether the player chose to keep playing after the recap
+    dismissed: bool,
+}
+
+impl RecapScreen {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            dismissed: false,
+        }
+    }
+}
+
+/// Marker for the recap screen
+#[derive(Component)]
+pub struct RecapPanel;
+
+/// Marker for the recap's heading
+#[derive(Component)]
+pub struct RecapTitle;
+
+/// Marker for the recap's sections
+#[derive(Component)]
+pub struct RecapText;
+
+/// What a recap button does
+#[derive(Component, Clone, Copy, PartialEq, Eq)]
+pub enum RecapButton {
+    Sandbox,
+    Restart,
+    Export,
+}
+
+impl RecapButton {
+    const ALL: [RecapButton; 3] = [
+        RecapButton::Sandbox,
+        RecapButton::Restart,
+        RecapButton::Export,
+    ];
+
+    fn label(self) -> &'static str {
+        match self {
+            RecapButton::Sandbox => "Continue in sandbox",
+            RecapButton::Restart => "Restart with the same seed",
+            RecapButton::Export => "Export report",
+        }
+    }
+}
+
+/// System to create the (hidden) recap screen
+pub fn setup_recap_ui(mut commands: Commands) {
+    commands
+        .spawn((
+            RecapPanel,
+            Node {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                position_type: PositionType::Absolute,
+                flex_direction: FlexDirection::Column,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                row_gap: Val::Px(16.0),
+                ..default()
+            },
+            BackgroundColor(Color::srgba(0.02, 0.03, 0.06, 0.92)),
+            GlobalZIndex(10),
+            Visibility::Hidden,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                RecapTitle,
+                Text::new(""),
+                TextFont {
+                    font_size: 40.0,
+                    ..default()
+                },
+                TextColor(Color::WHITE),
+            ));
+            parent.spawn((
+                RecapText,
+                Text::new(""),
+                TextFont {
+                    font_size: 18.0,
+                    ..default()
+                },
+                TextColor(Color::WHITE),
+                Node {
+                    max_width: Val::Px(720.0),
+                    ..default()
+                },
+            ));
+            parent
+                .spawn(Node {
+                    column_gap: Val::Px(12.0),
+                    ..default()
+                })
+                .with_children(|row| {
+                    for button in RecapButton::ALL {
+                        row.spawn((
+                            button,
+                            Button,
+                            Node {
+                                padding: UiRect::axes(Val::Px(14.0), Val::Px(8.0)),
+                                border: UiRect::all(Val::Px(1.0)),
+                                ..default()
+                            },
+                            BorderColor::all(Color::WHITE),
+                            BackgroundColor(Color::srgb(0.25, 0.25, 0.3)),
+                        ))
+                        .with_children(|button_node| {
+                            button_node.spawn((
+                                Text::new(button.label()),
+                                TextFont {
+                                    font_size: 16.0,
+                                    ..default()
+                                },
+                                TextColor(Color::WHITE),
+                            ));
+                        });
+                    }
+                });
+        });
+}
+
+/// The money over the run as a line of blocks, lowest to highest
+fn sparkline(money: &[(f32, i32)]) -> String {
+    let Some(min) = money.iter().map(|(_, money)| *money).min() else {
+        return String::new();
+    };
+    let max = money.iter().map(|(_, money)| *money).max().unwrap_or(min);
+    let span = (max - min).max(1) as f32;
+    let step = money.len().div_ceil(SPARKLINE_WIDTH).max(1);
+    money
+        .iter()
+        .step_by(step)
+        .map(|(_, money)| {
+            let level = (*money - min) as f32 / span * (SPARKLINE_BLOCKS.len() - 1) as f32;
+            SPARKLINE_BLOCKS[level.round() as usize]
+        })
+        .collect()
+}
+
+/// The recap's sections, in the order the scenario lists them
+fn recap_text(recap: &Recap) -> String {
+    let mut lines = Vec::new();
+    if !recap.message.is_empty() {
+        lines.push(recap.message.clone());
+        lines.push(String::new());
+    }
+    for section in &recap.sections {
+        let line = match section {
+            RecapSection::Money => match (recap.money_range(), recap.money.last()) {
+                (Some((min, max)), Some((_, last))) => format!(
+                    "{}: ${} (lowest ${}, highest ${})\n{}",
+                    section.label(),
+                    last,
+                    min,
+                    max,
+                    sparkline(&recap.money)
+                ),
+                _ => format!("{}: -", section.label()),
+            },
+            RecapSection::BiggestJam => match recap.biggest_jam {
+                Some(jam) => format!(
+                    "{}: {} vehicles stopped on road {} at {:.0}s",
+                    section.label(),
+                    jam.vehicles,
+                    jam.road_id.0 .0,
+                    jam.time
+                ),
+                None => format!("{}: none", section.label()),
+            },
+            RecapSection::DistanceDriven => {
+                format!("{}: {:.1} km", section.label(), recap.distance_driven_km())
+            }
+            RecapSection::MvpFactory => match recap.mvp_factory {
+                Some(mvp) => format!(
+                    "{}: factory {} - {:.0} shop deliveries earning ${:.0}, {} shifts worked",
+                    section.label(),
+                    mvp.factory_id.0 .0,
+                    mvp.deliveries,
+                    mvp.revenue,
+                    mvp.shifts
+                ),
+                None => format!("{}: none yet", section.label()),
+            },
+        };
+        lines.push(line);
+    }
+    lines.join("\n")
+}
+
+/// System to show the recap once the live game is won or lost
+pub fn show_recap(
+    sim_world: Res<SimWorldResource>,
+    slots: Res<WorldSlots>,
+    screen: Res<RecapScreen>,
+    mut panel_query: Query<&mut Visibility, With<RecapPanel>>,
+    mut title_query: Query<&mut Text, (With<RecapTitle>, Without<RecapText>)>,
+    mut text_query: Query<&mut Text, (With<RecapText>, Without<RecapTitle>)>,
+    mut button_query: Query<(&RecapButton, &mut Node)>,
+) {
+    let Ok(mut visibility) = panel_query.single_mut() else {
+        return;
+    };
+    if screen.dismissed || !slots.showing_live() || *visibility != Visibility::Hidden {
+        return;
+    }
+    let ended = sim_world
+        .0
+        .game_state
+        .as_ref()
+        .is_some_and(|game_state| game_state.is_won || game_state.is_lost);
+    if !ended {
+        return;
+    }
+    // Built once as the screen opens; the MVP factory takes a while to find
+    let recap = sim_world.0.recap();
+    for mut title in title_query.iter_mut() {
+        **title = recap.title.clone();
+    }
+    for mut text in text_query.iter_mut() {
+        **text = recap_text(&recap);
+    }
+    for (button, mut node) in button_query.iter_mut() {
+        if *button == RecapButton::Sandbox {
+            node.display = if recap.sandbox {
+                Display::Flex
+            } else {
+                Display::None
+            };
+        }
+    }
+    *visibility = Visibility::Inherited;
+    info!("Showing the recap of the run");
+}
+
+/// System to keep playing, restart or export the report from the recap
+#[allow(clippy::too_many_arguments)]
+pub fn handle_recap_buttons(
+    interaction_query: Query<(&Interaction, &RecapButton), Changed<Interaction>>,
+    mut panel_query: Query<&mut Visibility, With<RecapPanel>>,
+    mut sim_world: ResMut<SimWorldResource>,
+    mut screen: ResMut<RecapScreen>,
+    mut scores: ResMut<RunScores>,
+    mut visual_sync: ResMut<VisualSync>,
+    session: Res<MultiplayerSession>,
+    report: Option<Res<RunReport>>,
+    autosave: Option<ResMut<Autosave>>,
+) {
+    let Some(button) = interaction_query
+        .iter()
+        .find(|(interaction, _)| **interaction == Interaction::Pressed)
+        .map(|(_, button)| *button)
+    else {
+        return;
+    };
+    match button {
+        RecapButton::Sandbox => {
+            screen.dismissed = true;
+            info!("Playing on in the sandbox");
+        }
+        RecapButton::Restart => {
+            // A co-op world belongs to the whole session
+            if !matches!(*session, MultiplayerSession::Offline) {
+                warn!("Only offline games can be restarted");
+                return;
+            }
+            let save = SaveFile::of_world(screen.seed, &sim_world.0);
+            match save.create_world() {
+                Ok(world) => {
+                    sim_world.0 = world;
+                    visual_sync.0.resync();
+                    scores.summary = None;
+                    scores.new_best = false;
+                    if let Some(mut autosave) = autosave {
+                        autosave.save = save;
+                    }
+                    screen.dismissed = false;
+                    info!("Restarted the game with seed {}", screen.seed);
+                }
+                Err(e) => {
+                    warn!("Couldn't restart the game: {:#}", e);
+                    return;
+                }
+            }
+        }
+        RecapButton::Export => {
+            let path = report.map_or_else(|| PathBuf::from(RECAP_REPORT_FILE), |r| r.0.clone());
+            let title = format!("Game on {}", scores.scenario);
+            match sim_world.0.save_run_report(&path, &title) {
+                Ok(()) => info!("Wrote the game's report to {}", path.display()),
+                Err(e) => warn!("{:#}", e),
+            }
+            return;
+        }
+    }
+    for mut visibility in panel_query.iter_mut() {
+        *visibility = Visibility::Hidden;
+    }
+}
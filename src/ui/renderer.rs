@@ -0,0 +1,460 @@
+//! Bevy implementation of the simulation's renderer interface
+//!
+//! [`BevyRenderer`] turns the simulation's [`Visual`]s into meshes: it spawns
+//! an entity per visual, moves it and recolors its indicators as the visual
+//! changes, and despawns it once the simulation object is gone. Which visuals
+//! need which of those is worked out by the simulation's [`RenderSync`], so
+//! nothing here reads simulation internals.
+//!
+//! Vehicles come and go by the thousand at rush hour, so a finished
+//! vehicle's entity is hidden and kept in the [`VehicleEntityPool`] rather
+//! than despawned, and the next vehicle with the same look is drawn with it,
+//! model, wheels and headlights included.
+
+use std::collections::HashMap;
+
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+
+use super::components::{
+    DeliveryIndicator, DemandIndicator, EntityMappings, GroundLevel, ProductionBar,
+    RoadArrowDensity, RoadVisual, SimSynced, SimWorldResource, VehicleLook, VisualLink,
+};
+use super::spawner::{
+    build_arrow_mesh, build_road_mesh, delivery_indicator_color, demand_indicator_color,
+    production_bar_transform, road_transform, spawn_apartment_visual, spawn_decoration_visual,
+    spawn_factory_visual, spawn_intersection_visual, spawn_road_visual, spawn_shop_visual,
+    spawn_special_visual, spawn_vehicle_visual, vehicle_height, ApartmentVisualAssets,
+};
+use super::vehicle_models::WheelRoll;
+use crate::simulation::{RenderSync, SimRenderer, Visual, VisualId, VisualKind};
+
+/// Parts of a visual's entity the renderer moves and rebuilds
+type SyncedParts = (
+    &'static mut Transform,
+    Option<&'static Children>,
+    Option<&'static mut RoadVisual>,
+    Option<&'static Mesh3d>,
+    Option<&'static mut GroundLevel>,
+);
+
+/// Most visuals spawned per frame; the rest wait for later frames
+pub const SPAWNS_PER_FRAME: usize = 250;
+
+/// Most hidden vehicle entities kept for reuse
+pub const VEHICLE_POOL_SIZE: usize = 500;
+
+/// Entities of finished vehicles, hidden until a vehicle with the same look
+/// needs drawing
+#[derive(Resource, Default)]
+pub struct VehicleEntityPool {
+    idle: HashMap<VehicleLook, Vec<Entity>>,
+    /// Entities in `idle`, of every look
+    len: usize,
+}
+
+impl VehicleEntityPool {
+    fn take(&mut self, look: VehicleLook) -> Option<Entity> {
+        let entity = self.idle.get_mut(&look)?.pop()?;
+        self.len -= 1;
+        Some(entity)
+    }
+
+    /// Keep an entity for reuse, unless the pool is full
+    fn keep(&mut self, look: VehicleLook, entity: Entity) -> bool {
+        if self.len >= VEHICLE_POOL_SIZE {
+            return false;
+        }
+        self.idle.entry(look).or_default().push(entity);
+        self.len += 1;
+        true
+    }
+
+    /// Hidden vehicle entities waiting for reuse
+    pub fn idle(&self) -> usize {
+        self.len
+    }
+}
+
+/// What the Bevy front-end is currently showing
+#[derive(Resource)]
+pub struct VisualSync(pub RenderSync);
+
+impl Default for VisualSync {
+    fn default() -> Self {
+        Self(RenderSync::with_spawn_budget(SPAWNS_PER_FRAME))
+    }
+}
+
+/// Draws simulation visuals as Bevy entities
+#[derive(SystemParam)]
+pub struct BevyRenderer<'w, 's> {
+    commands: Commands<'w, 's>,
+    meshes: ResMut<'w, Assets<Mesh>>,
+    materials: ResMut<'w, Assets<StandardMaterial>>,
+    mappings: ResMut<'w, EntityMappings>,
+    apartment_assets: ResMut<'w, ApartmentVisualAssets>,
+    vehicle_pool: ResMut<'w, VehicleEntityPool>,
+    density: Res<'w, RoadArrowDensity>,
+    synced: Query<'w, 's, SyncedParts, With<SimSynced>>,
+    vehicle_looks: Query<'w, 's, &'static VehicleLook>,
+    wheel_rolls: Query<'w, 's, &'static mut WheelRoll>,
+    demand_indicators:
+        Query<'w, 's, &'static MeshMaterial3d<StandardMaterial>, With<DemandIndicator>>,
+    delivery_indicators:
+        Query<'w, 's, &'static MeshMaterial3d<StandardMaterial>, With<DeliveryIndicator>>,
+    production_bars:
+        Query<'w, 's, &'static mut Transform, (With<ProductionBar>, Without<SimSynced>)>,
+    gizmos: Gizmos<'w, 's>,
+}
+
+impl BevyRenderer<'_, '_> {
+    /// Entity drawing a visual, if it has been spawned
+    fn entity(&self, id: VisualId) -> Option<Entity> {
+        let mappings = &self.mappings;
+        match id {
+            VisualId::Intersection(id) => mappings.intersections.get(&id),
+            VisualId::Road(id) => mappings.roads.get(&id),
+            VisualId::Apartment(id) => mappings.apartments.get(&id),
+            VisualId::Factory(id) => mappings.factories.get(&id),
+            VisualId::Shop(id) => mappings.shops.get(&id),
+            VisualId::Special(id) => mappings.special_buildings.get(&id),
+            VisualId::Decoration(id) => mappings.decorations.get(&id),
+            VisualId::Vehicle(id) => mappings.cars.get(&id),
+        }
+        .copied()
+    }
+
+    /// Recolor a building's demand indicator and, for factories, its
+    /// delivery indicators
+    fn recolor_indicators(&mut self, entity: Entity, busy: bool, deliveries_ready: u32) {
+        let Ok((_, Some(children), ..)) = self.synced.get(entity) else {
+            return;
+        };
+        let mut delivery_index = 0;
+        for child in children.iter() {
+            let (material, color) = if let Ok(material) = self.demand_indicators.get(child) {
+                (material, demand_indicator_color(busy))
+            } else if let Ok(material) = self.delivery_indicators.get(child) {
+                delivery_index += 1;
+                (
+                    material,
+                    delivery_indicator_color(delivery_index - 1, deliveries_ready),
+                )
+            } else {
+                continue;
+            };
+            if let Some(material) = self.materials.get_mut(&material.0) {
+                material.base_color = color;
+            }
+        }
+    }
+
+    /// Draw a vehicle with a hidden entity of the same look, if one is kept
+    fn reuse_vehicle_entity(&mut self, visual: &Visual) -> bool {
+        let (VisualKind::Vehicle { vehicle_type, .. }, VisualId::Vehicle(id)) =
+            (&visual.kind, visual.id)
+        else {
+            return false;
+        };
+        let look = VehicleLook::new(*vehicle_type, visual.color, visual.size);
+        let Some(entity) = self.vehicle_pool.take(look) else {
+            return false;
+        };
+        let position = visual.position;
+        let translation = Vec3::new(
+            position.x,
+            position.y + vehicle_height(*vehicle_type),
+            position.z,
+        );
+        if let Ok((mut transform, ..)) = self.synced.get_mut(entity) {
+            transform.translation = translation;
+            transform.rotation = Quat::from_rotation_y(visual.angle);
+        }
+        // Don't roll the wheels for the jump from where the last vehicle was
+        if let Ok(mut roll) = self.wheel_rolls.get_mut(entity) {
+            roll.last_position = translation;
+        }
+        self.commands
+            .entity(entity)
+            .insert((VisualLink(visual.id), Visibility::Inherited));
+        self.mappings.cars.insert(id, entity);
+        true
+    }
+
+    /// Fill a factory's production bar up to its progress toward the next
+    /// delivery
+    fn fill_production_bar(&mut self, entity: Entity, progress: f32) {
+        let Ok((_, Some(children), ..)) = self.synced.get(entity) else {
+            return;
+        };
+        for child in children.iter() {
+            if let Ok(mut transform) = self.production_bars.get_mut(child) {
+                *transform = production_bar_transform(progress);
+            }
+        }
+    }
+}
+
+impl SimRenderer for BevyRenderer<'_, '_> {
+    fn spawn_visual(&mut self, visual: &Visual) {
+        if self.reuse_vehicle_entity(visual) {
+            return;
+        }
+        let tint = visual
+            .color
+            .map(|[red, green, blue]| Color::srgb(red, green, blue));
+        let (commands, meshes, materials, mappings) = (
+            &mut self.commands,
+            &mut self.meshes,
+            &mut self.materials,
+            &mut self.mappings,
+        );
+        let position = &visual.position;
+        match (&visual.kind, visual.id) {
+            (VisualKind::Intersection, VisualId::Intersection(id)) => {
+                spawn_intersection_visual(commands, meshes, materials, id, position, mappings)
+            }
+            (
+                VisualKind::Road {
+                    start,
+                    end,
+                    two_way,
+                    kind,
+                    driving_side,
+                },
+                VisualId::Road(id),
+            ) => spawn_road_visual(
+                commands,
+                meshes,
+                materials,
+                id,
+                start,
+                end,
+                *two_way,
+                *kind,
+                *driving_side,
+                self.density.spacing,
+                mappings,
+            ),
+            (
+                VisualKind::Apartment {
+                    busy,
+                    commute_radius,
+                },
+                VisualId::Apartment(id),
+            ) => spawn_apartment_visual(
+                commands,
+                meshes,
+                materials,
+                id,
+                position,
+                *busy,
+                *commute_radius,
+                tint,
+                mappings,
+                &mut self.apartment_assets,
+            ),
+            (
+                VisualKind::Factory {
+                    busy,
+                    deliveries_ready,
+                    max_deliveries,
+                    production_progress,
+                },
+                VisualId::Factory(id),
+            ) => spawn_factory_visual(
+                commands,
+                meshes,
+                materials,
+                id,
+                position,
+                *busy,
+                *deliveries_ready,
+                *max_deliveries,
+                *production_progress,
+                tint,
+                mappings,
+            ),
+            (VisualKind::Shop, VisualId::Shop(id)) => {
+                spawn_shop_visual(commands, meshes, materials, id, position, tint, mappings)
+            }
+            (VisualKind::Special(kind), VisualId::Special(id)) => {
+                spawn_special_visual(commands, meshes, materials, id, *kind, position, mappings)
+            }
+            (VisualKind::Decoration(kind), VisualId::Decoration(id)) => {
+                spawn_decoration_visual(commands, meshes, materials, id, *kind, position, mappings)
+            }
+            (VisualKind::Vehicle { vehicle_type, .. }, VisualId::Vehicle(id)) => {
+                spawn_vehicle_visual(
+                    commands,
+                    meshes,
+                    materials,
+                    id,
+                    *vehicle_type,
+                    position,
+                    visual.angle,
+                    tint,
+                    visual.size,
+                    mappings,
+                );
+                if let Some(&entity) = mappings.cars.get(&id) {
+                    let look = VehicleLook::new(*vehicle_type, visual.color, visual.size);
+                    commands.entity(entity).insert(look);
+                }
+            }
+            _ => warn!("Visual {:?} doesn't match its kind", visual.id),
+        }
+        if !matches!(
+            visual.kind,
+            VisualKind::Road { .. } | VisualKind::Vehicle { .. }
+        ) {
+            if let Some(entity) = self.entity(visual.id) {
+                self.commands
+                    .entity(entity)
+                    .insert(GroundLevel(visual.position.y));
+            }
+        }
+    }
+
+    fn update_visual(&mut self, visual: &Visual) {
+        let Some(entity) = self.entity(visual.id) else {
+            return;
+        };
+        let position = visual.position;
+        match &visual.kind {
+            VisualKind::Road {
+                start,
+                end,
+                two_way,
+                driving_side,
+                ..
+            } => {
+                let Ok((mut transform, _, Some(mut road), Some(body_mesh), _)) =
+                    self.synced.get_mut(entity)
+                else {
+                    return;
+                };
+                if let Some(mesh) = self.meshes.get_mut(&body_mesh.0) {
+                    *mesh = build_road_mesh(start, end, *two_way);
+                }
+                if let Some(mesh) = self.meshes.get_mut(&road.arrow_mesh) {
+                    *mesh = build_arrow_mesh(
+                        start.distance(end),
+                        *two_way,
+                        *driving_side,
+                        self.density.spacing,
+                    );
+                }
+                *transform = road_transform(start, end);
+                road.start = *start;
+                road.end = *end;
+                road.two_way = *two_way;
+                road.driving_side = *driving_side;
+                road.arrow_spacing = self.density.spacing;
+            }
+            VisualKind::Vehicle { vehicle_type, .. } => {
+                if let Ok((mut transform, ..)) = self.synced.get_mut(entity) {
+                    transform.translation = Vec3::new(
+                        position.x,
+                        position.y + vehicle_height(*vehicle_type),
+                        position.z,
+                    );
+                    transform.rotation = Quat::from_rotation_y(visual.angle);
+                }
+            }
+            kind => {
+                // Buildings and intersections follow their intersection when
+                // it is dragged
+                if let Ok((mut transform, _, _, _, ground)) = self.synced.get_mut(entity) {
+                    if transform.translation.x != position.x
+                        || transform.translation.z != position.z
+                    {
+                        transform.translation.x = position.x;
+                        transform.translation.z = position.z;
+                        // Keep it on the ground over hills
+                        if let Some(mut ground) = ground {
+                            transform.translation.y += position.y - ground.0;
+                            ground.0 = position.y;
+                        }
+                    }
+                }
+                match kind {
+                    VisualKind::Apartment { busy, .. } => self.recolor_indicators(entity, *busy, 0),
+                    VisualKind::Factory {
+                        busy,
+                        deliveries_ready,
+                        production_progress,
+                        ..
+                    } => {
+                        self.recolor_indicators(entity, *busy, *deliveries_ready);
+                        self.fill_production_bar(entity, *production_progress);
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    fn despawn_visual(&mut self, id: VisualId) {
+        let mappings = &mut self.mappings;
+        let entity = match id {
+            VisualId::Intersection(id) => mappings.intersections.remove(&id),
+            VisualId::Road(id) => mappings.roads.remove(&id),
+            VisualId::Apartment(id) => mappings.apartments.remove(&id),
+            VisualId::Factory(id) => mappings.factories.remove(&id),
+            VisualId::Shop(id) => mappings.shops.remove(&id),
+            VisualId::Special(id) => mappings.special_buildings.remove(&id),
+            VisualId::Decoration(id) => mappings.decorations.remove(&id),
+            VisualId::Vehicle(id) => mappings.cars.remove(&id),
+        };
+        let Some(entity) = entity else {
+            return;
+        };
+        // Hide a vehicle for the next one of its look to reuse
+        if let Ok(&look) = self.vehicle_looks.get(entity) {
+            if self.vehicle_pool.keep(look, entity) {
+                self.commands
+                    .entity(entity)
+                    .remove::<VisualLink>()
+                    .insert(Visibility::Hidden);
+                return;
+            }
+        }
+        self.commands.entity(entity).despawn();
+    }
+
+    fn draw_overlay(&mut self, visual: &Visual) {
+        // Hazard ring over vehicles stopped by a breakdown or collision
+        if let VisualKind::Vehicle { stalled: true, .. } = visual.kind {
+            self.gizmos.circle(
+                Isometry3d::new(
+                    Vec3::new(visual.position.x, 0.8, visual.position.z),
+                    Quat::from_rotation_arc(Vec3::Z, Vec3::Y),
+                ),
+                0.5,
+                Color::srgb(1.0, 0.5, 0.2),
+            );
+        }
+        // Red ring over delivery trucks running past their deadline
+        if let VisualKind::Vehicle { overdue: true, .. } = visual.kind {
+            self.gizmos.circle(
+                Isometry3d::new(
+                    Vec3::new(visual.position.x, 1.0, visual.position.z),
+                    Quat::from_rotation_arc(Vec3::Z, Vec3::Y),
+                ),
+                0.7,
+                Color::srgb(1.0, 0.1, 0.1),
+            );
+        }
+    }
+}
+
+/// System to bring the Bevy scene up to date with the simulation
+pub fn sync_visuals(
+    mut sim_world: ResMut<SimWorldResource>,
+    mut visual_sync: ResMut<VisualSync>,
+    mut renderer: BevyRenderer,
+) {
+    visual_sync.0.sync(&mut sim_world.0, &mut renderer);
+}
@@ -0,0 +1,36 @@
+//! Writing a report of the game when the app exits
+//!
+//! Given `--report`, the live world's report is written as the app exits,
+//! like the headless runs do when they finish.
+
+use bevy::prelude::*;
+use std::path::PathBuf;
+
+use super::components::SimWorldResource;
+use super::score::RunScores;
+use super::worlds::WorldSlots;
+
+/// File the game's report is written to on exit
+#[derive(Resource)]
+pub struct RunReport(pub PathBuf);
+
+/// System to write the report of the live world as the app exits
+pub fn write_run_report(
+    mut exits: MessageReader<AppExit>,
+    sim_world: Res<SimWorldResource>,
+    slots: Res<WorldSlots>,
+    scores: Res<RunScores>,
+    report: Option<Res<RunReport>>,
+) {
+    if exits.read().count() == 0 {
+        return;
+    }
+    let Some(report) = report else {
+        return;
+    };
+    let title = format!("Game on {}", scores.scenario);
+    match slots.live(&sim_world.0).save_run_report(&report.0, &title) {
+        Ok(()) => info!("Wrote the game's report to {}", report.0.display()),
+        Err(e) => warn!("{:#}", e),
+    }
+}
@@ -0,0 +1,495 @@
+//! Road context menu, restriction signage and traffic sensors
+//!
+//! Right-clicking a road opens a small menu at the cursor. Its toggles cycle
+//! the road between open to all vehicles, no trucks, trucks only and buses
+//! only, and
+//! between one- and two-way; a one-way road can also be reversed, or set
+//! to run the other way through the morning and evening rush hours
+//! (contraflow) or not. Others
+//! close the road for now or reopen it, and put a traffic sensor on the road
+//! or take it off, and the menu shows the road's traffic if it is monitored.
+//! Each sends a [`BuildCommand`] so the change reaches co-op peers.
+//! Restricted roads get a sign at each end they can be entered from, closed
+//! roads a barricade there, and roads with a sensor a green ring in each
+//! lane.
+
+use bevy::ecs::hierarchy::ChildSpawnerCommands;
+use bevy::prelude::*;
+
+use super::components::{BuildingState, PendingCommands, SimWorldResource};
+use super::multiplayer::MultiplayerSession;
+use crate::simulation::{
+    BuildCommand, DrivingSide, Position, RoadId, RoadRestriction, SimWorld, RUSH_HOUR_CONTRAFLOW,
+};
+
+/// Height of a restriction sign's plate above the road
+const SIGN_HEIGHT: f32 = 1.0;
+
+/// Radius of a restriction sign's plate
+const SIGN_RADIUS: f32 = 0.3;
+
+/// Radius of the ring marking a road's sensor
+const SENSOR_RADIUS: f32 = 0.2;
+
+/// Height of the rail of a closed road's barricade
+const BARRICADE_HEIGHT: f32 = 0.4;
+
+/// Stripes painted on a barricade's rail
+const BARRICADE_STRIPES: usize = 6;
+
+/// Road the context menu is open for
+#[derive(Resource, Default)]
+pub struct RoadMenu {
+    pub road: Option<RoadId>,
+}
+
+/// Marker for the road context menu
+#[derive(Component)]
+pub struct RoadMenuPanel;
+
+/// Button text describing the road's current state
+#[derive(Component, Clone, Copy, PartialEq)]
+pub enum RoadMenuLabel {
+    Restriction,
+    Direction,
+    Contraflow,
+    Closure,
+    Sensor,
+    /// The road's traffic, or why it isn't known
+    Metrics,
+}
+
+/// Buttons in the road context menu
+#[derive(Component, Clone, Copy, PartialEq)]
+pub enum RoadMenuButton {
+    ToggleRestriction,
+    ToggleTwoWay,
+    Reverse,
+    ToggleContraflow,
+    ToggleClosed,
+    ToggleSensor,
+    Close,
+}
+
+/// System to create the (hidden) road context menu
+pub fn setup_road_menu(mut commands: Commands) {
+    commands
+        .spawn((
+            RoadMenuPanel,
+            Node {
+                position_type: PositionType::Absolute,
+                padding: UiRect::all(Val::Px(8.0)),
+                flex_direction: FlexDirection::Column,
+                row_gap: Val::Px(6.0),
+                ..default()
+            },
+            BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.85)),
+            Visibility::Hidden,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Text::new("Road"),
+                TextFont {
+                    font_size: 14.0,
+                    ..default()
+                },
+                TextColor(Color::srgb(0.7, 0.7, 0.7)),
+            ));
+            spawn_menu_button(
+                parent,
+                RoadMenuButton::ToggleRestriction,
+                "",
+                Some(RoadMenuLabel::Restriction),
+            );
+            spawn_menu_button(
+                parent,
+                RoadMenuButton::ToggleTwoWay,
+                "",
+                Some(RoadMenuLabel::Direction),
+            );
+            spawn_menu_button(parent, RoadMenuButton::Reverse, "Reverse", None);
+            spawn_menu_button(
+                parent,
+                RoadMenuButton::ToggleContraflow,
+                "",
+                Some(RoadMenuLabel::Contraflow),
+            );
+            spawn_menu_button(
+                parent,
+                RoadMenuButton::ToggleClosed,
+                "",
+                Some(RoadMenuLabel::Closure),
+            );
+            spawn_menu_button(
+                parent,
+                RoadMenuButton::ToggleSensor,
+                "",
+                Some(RoadMenuLabel::Sensor),
+            );
+            parent.spawn((
+                RoadMenuLabel::Metrics,
+                Text::new(""),
+                TextFont {
+                    font_size: 12.0,
+                    ..default()
+                },
+                TextColor(Color::srgb(0.6, 0.9, 0.6)),
+            ));
+            spawn_menu_button(parent, RoadMenuButton::Close, "Close", None);
+        });
+}
+
+fn spawn_menu_button(
+    parent: &mut ChildSpawnerCommands,
+    button: RoadMenuButton,
+    text: &str,
+    label: Option<RoadMenuLabel>,
+) {
+    parent
+        .spawn((
+            button,
+            Button,
+            Node {
+                padding: UiRect::axes(Val::Px(8.0), Val::Px(4.0)),
+                border: UiRect::all(Val::Px(1.0)),
+                ..default()
+            },
+            BorderColor::all(Color::WHITE),
+            BackgroundColor(Color::srgb(0.25, 0.25, 0.3)),
+        ))
+        .with_children(|button| {
+            let mut text = button.spawn((
+                Text::new(text),
+                TextFont {
+                    font_size: 13.0,
+                    ..default()
+                },
+                TextColor(Color::WHITE),
+            ));
+            if let Some(label) = label {
+                text.insert(label);
+            }
+        });
+}
+
+/// System to open the context menu for the road under the cursor on a
+/// right-click, or close it when clicking away from roads
+#[allow(clippy::too_many_arguments)]
+pub fn open_road_menu(
+    mouse_button: Res<ButtonInput<MouseButton>>,
+    windows: Query<&Window>,
+    building_state: Res<BuildingState>,
+    sim_world: Res<SimWorldResource>,
+    session: Res<MultiplayerSession>,
+    mut menu: ResMut<RoadMenu>,
+    mut panel_query: Query<&mut Node, With<RoadMenuPanel>>,
+    interaction_query: Query<&Interaction, With<Button>>,
+) {
+    if !mouse_button.just_pressed(MouseButton::Right)
+        || session.is_spectating()
+        || interaction_query
+            .iter()
+            .any(|interaction| *interaction != Interaction::None)
+    {
+        return;
+    }
+    let (Some(cursor), Some(screen)) = (
+        building_state.cursor_position,
+        windows
+            .single()
+            .ok()
+            .and_then(|window| window.cursor_position()),
+    ) else {
+        return;
+    };
+
+    let network = &sim_world.0.road_network;
+    menu.road = network
+        .find_closest_point_on_road(&cursor)
+        .filter(|(_, closest, _, _)| cursor.distance(closest) <= building_state.snap_distance)
+        .map(|(road_id, closest, _, _)| {
+            // Of a two-way road, pick the direction whose lane was clicked
+            let Some((road, opposite)) = network
+                .get_road(road_id)
+                .zip(network.opposite_road(road_id))
+            else {
+                return road_id;
+            };
+            let (Some(start), Some(end)) = (
+                network.get_intersection_position(road.start_intersection),
+                network.get_intersection_position(road.end_intersection),
+            ) else {
+                return road_id;
+            };
+            let lane = sim_world.0.driving_side.lane_offset(start, end, 1.0);
+            let side = (cursor.x - closest.x) * lane.x + (cursor.z - closest.z) * lane.z;
+            if side >= 0.0 {
+                road_id
+            } else {
+                opposite
+            }
+        });
+    if menu.road.is_some() {
+        for mut node in panel_query.iter_mut() {
+            node.left = Val::Px(screen.x);
+            node.top = Val::Px(screen.y);
+        }
+    }
+}
+
+/// System to show the menu while its road exists and keep the toggles' text
+/// current
+pub fn update_road_menu(
+    sim_world: Res<SimWorldResource>,
+    mut menu: ResMut<RoadMenu>,
+    mut panel_query: Query<&mut Visibility, With<RoadMenuPanel>>,
+    mut label_query: Query<(&mut Text, &RoadMenuLabel)>,
+    mut button_query: Query<(&mut Node, &RoadMenuButton)>,
+) {
+    // Roads are replaced when split or reversed, which closes the menu
+    let road = menu
+        .road
+        .and_then(|road_id| sim_world.0.road_network.get_road(road_id));
+    if road.is_none() && menu.road.is_some() {
+        menu.road = None;
+    }
+
+    for mut visibility in panel_query.iter_mut() {
+        let wanted = if road.is_some() {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+        if *visibility != wanted {
+            *visibility = wanted;
+        }
+    }
+    if let Some(road) = road {
+        let sensor_cost = sim_world
+            .0
+            .game_state
+            .as_ref()
+            .map_or(0, |game_state| game_state.economy.cost_sensor);
+        for (mut text, label) in label_query.iter_mut() {
+            let description = match label {
+                RoadMenuLabel::Restriction => format!("Vehicles: {}", road.restriction.label()),
+                RoadMenuLabel::Direction if road.is_two_way => "Two-way".to_string(),
+                RoadMenuLabel::Direction => "One-way".to_string(),
+                RoadMenuLabel::Contraflow => contraflow_label(&sim_world.0, road.id),
+                RoadMenuLabel::Closure if road.closed => "Reopen road".to_string(),
+                RoadMenuLabel::Closure => "Close road".to_string(),
+                RoadMenuLabel::Sensor if road.sensor => "Remove sensor".to_string(),
+                RoadMenuLabel::Sensor => format!("Add sensor - ${}", sensor_cost),
+                RoadMenuLabel::Metrics => match sim_world.0.road_metrics(road.id) {
+                    Some(metrics) => format!(
+                        "{} vehicles, {} queued\nAvg speed: {:.1}\nTotal: {} vehicles",
+                        metrics.vehicles, metrics.queued, metrics.average_speed, metrics.volume
+                    ),
+                    None => "No sensor - traffic unknown".to_string(),
+                },
+            };
+            if **text != description {
+                **text = description;
+            }
+        }
+        // Only one-way roads can be reversed or run contraflow
+        let display = if road.is_two_way {
+            Display::None
+        } else {
+            Display::Flex
+        };
+        for (mut node, button) in button_query.iter_mut() {
+            let one_way_only = matches!(
+                button,
+                RoadMenuButton::Reverse | RoadMenuButton::ToggleContraflow
+            );
+            if one_way_only && node.display != display {
+                node.display = display;
+            }
+        }
+    }
+}
+
+/// The road's contraflow windows and whether it is turned round now
+fn contraflow_label(world: &SimWorld, road_id: RoadId) -> String {
+    let windows = world.contraflow_windows(road_id);
+    if windows.is_empty() {
+        return "Rush hour contraflow: off".to_string();
+    }
+    let hours: Vec<String> = windows
+        .iter()
+        .map(|window| format!("{}-{}h", window.start_hour, window.end_hour))
+        .collect();
+    let state = if world.is_contraflow_clearing(road_id) {
+        ", clearing"
+    } else if world.is_contraflow_reversed(road_id) {
+        ", reversed"
+    } else {
+        ""
+    };
+    format!("Contraflow: {}{}", hours.join(", "), state)
+}
+
+/// System to handle the road context menu's buttons
+pub fn handle_road_menu_buttons(
+    sim_world: Res<SimWorldResource>,
+    mut menu: ResMut<RoadMenu>,
+    mut pending: ResMut<PendingCommands>,
+    interaction_query: Query<(&Interaction, &RoadMenuButton), Changed<Interaction>>,
+) {
+    let Some(road) = menu
+        .road
+        .and_then(|road_id| sim_world.0.road_network.get_road(road_id))
+    else {
+        return;
+    };
+    let (road_id, restriction, two_way, sensor, closed) = (
+        road.id,
+        road.restriction,
+        road.is_two_way,
+        road.sensor,
+        road.closed,
+    );
+    for (interaction, button) in interaction_query.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        match button {
+            RoadMenuButton::ToggleRestriction => pending.0.push(BuildCommand::RestrictRoad {
+                road: road_id,
+                restriction: restriction.next(),
+            }),
+            RoadMenuButton::ToggleTwoWay => pending.0.push(BuildCommand::SetTwoWay {
+                road: road_id,
+                two_way: !two_way,
+            }),
+            RoadMenuButton::Reverse => pending.0.push(BuildCommand::ReverseRoad { road: road_id }),
+            RoadMenuButton::ToggleContraflow => {
+                let windows = if sim_world.0.contraflow_windows(road_id).is_empty() {
+                    RUSH_HOUR_CONTRAFLOW.to_vec()
+                } else {
+                    Vec::new()
+                };
+                pending
+                    .0
+                    .push(BuildCommand::ScheduleContraflow { road: road_id, windows });
+            }
+            RoadMenuButton::ToggleClosed => pending.0.push(BuildCommand::CloseRoad {
+                road: road_id,
+                closed: !closed,
+            }),
+            RoadMenuButton::ToggleSensor => pending.0.push(BuildCommand::SetSensor {
+                road: road_id,
+                installed: !sensor,
+            }),
+            RoadMenuButton::Close => menu.road = None,
+        }
+    }
+}
+
+/// System to draw signs on restricted roads, barricades on closed ones, rings
+/// on roads with a sensor and highlight the road the menu is open for
+///
+/// A red ringed sign means no trucks; a blue one with a bar means trucks
+/// only. Signs stand on the side of the road traffic keeps to where
+/// vehicles enter it, and barricades across its lane there.
+pub fn draw_road_signs(sim_world: Res<SimWorldResource>, menu: Res<RoadMenu>, mut gizmos: Gizmos) {
+    let network = &sim_world.0.road_network;
+    let side = sim_world.0.driving_side;
+    for road in network.roads().values() {
+        let (Some(start), Some(end)) = (
+            network.get_intersection_position(road.start_intersection),
+            network.get_intersection_position(road.end_intersection),
+        ) else {
+            continue;
+        };
+        let start_3d = Vec3::new(start.x, 0.1, start.z);
+        let end_3d = Vec3::new(end.x, 0.1, end.z);
+
+        if menu.road == Some(road.id) {
+            gizmos.line(start_3d, end_3d, Color::srgb(1.0, 0.9, 0.2));
+        }
+
+        if road.sensor {
+            let lane = if road.is_two_way { 0.15 } else { 0.0 };
+            let offset = side.lane_offset(start, end, lane);
+            let middle = (start_3d + end_3d) / 2.0 + Vec3::new(offset.x, 0.0, offset.z);
+            gizmos.circle(
+                Isometry3d::new(middle, Quat::from_rotation_arc(Vec3::Z, Vec3::Y)),
+                SENSOR_RADIUS,
+                Color::srgb(0.2, 0.9, 0.3),
+            );
+        }
+
+        if road.closed {
+            draw_barricade(&mut gizmos, start, end, start_3d, end_3d, road.is_two_way, side);
+        }
+
+        let color = match road.restriction {
+            RoadRestriction::All => continue,
+            RoadRestriction::NoTrucks => Color::srgb(0.9, 0.1, 0.1),
+            RoadRestriction::TrucksOnly => Color::srgb(0.2, 0.4, 1.0),
+            RoadRestriction::BusOnly => Color::srgb(0.95, 0.55, 0.1),
+        };
+        let direction = (end_3d - start_3d).normalize_or_zero();
+        let offset = side.lane_offset(start, end, 0.7);
+        let base = start_3d + direction * 1.5 + Vec3::new(offset.x, 0.0, offset.z);
+        let plate = base + Vec3::Y * SIGN_HEIGHT;
+        // Face the plate towards oncoming traffic
+        let facing = Quat::from_rotation_arc(Vec3::Z, -direction);
+
+        gizmos.line(
+            base,
+            plate - Vec3::Y * SIGN_RADIUS,
+            Color::srgb(0.6, 0.6, 0.6),
+        );
+        gizmos.circle(Isometry3d::new(plate, facing), SIGN_RADIUS, color);
+        let across = facing * Vec3::X * SIGN_RADIUS * 0.7;
+        match road.restriction {
+            RoadRestriction::NoTrucks => {
+                let diagonal = facing * Vec3::new(0.7, 0.7, 0.0) * SIGN_RADIUS;
+                gizmos.line(plate - diagonal, plate + diagonal, color);
+            }
+            _ => gizmos.line(plate - across, plate + across, color),
+        }
+    }
+}
+
+/// Draw a striped barricade across a closed road's lane near where vehicles
+/// enter it
+fn draw_barricade(
+    gizmos: &mut Gizmos,
+    start: &Position,
+    end: &Position,
+    start_3d: Vec3,
+    end_3d: Vec3,
+    two_way: bool,
+    side: DrivingSide,
+) {
+    let direction = (end_3d - start_3d).normalize_or_zero();
+    // Two-way roads are barricaded lane by lane, from the centre line out
+    let (inner, outer) = if two_way { (0.0, 0.6) } else { (-0.3, 0.3) };
+    let inner = side.lane_offset(start, end, inner);
+    let outer = side.lane_offset(start, end, outer);
+    let base = start_3d + direction * 1.0;
+    let left = base + Vec3::new(inner.x, 0.0, inner.z);
+    let right = base + Vec3::new(outer.x, 0.0, outer.z);
+    let rise = Vec3::Y * BARRICADE_HEIGHT;
+    for post in [left, right] {
+        gizmos.line(post, post + rise, Color::srgb(0.6, 0.6, 0.6));
+    }
+    for stripe in 0..BARRICADE_STRIPES {
+        let from = stripe as f32 / BARRICADE_STRIPES as f32;
+        let to = (stripe + 1) as f32 / BARRICADE_STRIPES as f32;
+        let color = if stripe % 2 == 0 {
+            Color::srgb(1.0, 0.4, 0.0)
+        } else {
+            Color::WHITE
+        };
+        gizmos.line(
+            left.lerp(right, from) + rise,
+            left.lerp(right, to) + rise,
+            color,
+        );
+    }
+}
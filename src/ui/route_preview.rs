@@ -0,0 +1,169 @@
+//! Route previews
+//!
+//! Press L over an intersection to preview routes from it, then point at
+//! another intersection to see the way a car would go and how long cars and
+//! trucks are estimated to take with the traffic on the roads now. Press L
+//! away from any intersection to stop.
+
+use bevy::prelude::*;
+
+use super::components::{BuildingState, SimWorldResource};
+use crate::simulation::{IntersectionId, Position, SimWorld, VehicleType};
+
+/// Height of the previewed route above the roads
+const ROUTE_HEIGHT: f32 = 0.25;
+
+/// Color of the previewed route
+const ROUTE_COLOR: Color = Color::srgb(0.2, 1.0, 0.6);
+
+/// Intersection routes are previewed from, if any
+#[derive(Resource, Default)]
+pub struct RoutePreview {
+    pub origin: Option<IntersectionId>,
+}
+
+/// Marker for the route preview panel
+#[derive(Component)]
+pub struct RoutePreviewPanel;
+
+/// Marker for the route preview panel's text
+#[derive(Component)]
+pub struct RoutePreviewText;
+
+/// The intersection under the cursor, if it is close enough to snap to
+fn hovered_intersection(
+    world: &SimWorld,
+    building_state: &BuildingState,
+) -> Option<IntersectionId> {
+    let cursor = building_state.cursor_position?;
+    let network = &world.road_network;
+    network.find_closest_intersection(&cursor).filter(|id| {
+        network
+            .get_intersection_position(*id)
+            .is_some_and(|position| position.distance(&cursor) <= building_state.snap_distance)
+    })
+}
+
+/// Where a route point is drawn
+fn route_point(position: &Position) -> Vec3 {
+    Vec3::new(position.x, position.y + ROUTE_HEIGHT, position.z)
+}
+
+/// System to create the (hidden) route preview panel
+pub fn setup_route_preview_ui(mut commands: Commands) {
+    commands
+        .spawn((
+            RoutePreviewPanel,
+            Node {
+                position_type: PositionType::Absolute,
+                bottom: Val::Px(60.0),
+                left: Val::Px(10.0),
+                padding: UiRect::all(Val::Px(10.0)),
+                ..default()
+            },
+            BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.8)),
+            Visibility::Hidden,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                RoutePreviewText,
+                Text::new(""),
+                TextFont {
+                    font_size: 14.0,
+                    ..default()
+                },
+                TextColor(Color::WHITE),
+            ));
+        });
+}
+
+/// System to pick the intersection routes are previewed from (L)
+pub fn handle_route_preview_keyboard(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    building_state: Res<BuildingState>,
+    sim_world: Res<SimWorldResource>,
+    mut preview: ResMut<RoutePreview>,
+) {
+    if !keyboard.just_pressed(KeyCode::KeyL) {
+        return;
+    }
+    preview.origin = hovered_intersection(&sim_world.0, &building_state)
+        .filter(|hovered| preview.origin != Some(*hovered));
+}
+
+/// System to draw the route to the intersection under the cursor and show
+/// its estimated travel times
+pub fn update_route_preview(
+    building_state: Res<BuildingState>,
+    sim_world: Res<SimWorldResource>,
+    mut preview: ResMut<RoutePreview>,
+    mut panel_query: Query<&mut Visibility, With<RoutePreviewPanel>>,
+    mut text_query: Query<&mut Text, With<RoutePreviewText>>,
+    mut gizmos: Gizmos,
+) {
+    let world = &sim_world.0;
+    let network = &world.road_network;
+    // The origin may have been removed
+    let origin_position = preview
+        .origin
+        .and_then(|origin| network.get_intersection_position(origin).copied());
+    if origin_position.is_none() {
+        preview.origin = None;
+    }
+    for mut visibility in panel_query.iter_mut() {
+        visibility.set_if_neq(if origin_position.is_some() {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        });
+    }
+    let (Some(origin), Some(origin_position)) = (preview.origin, origin_position) else {
+        return;
+    };
+
+    gizmos.circle(
+        Isometry3d::new(
+            route_point(&origin_position),
+            Quat::from_rotation_arc(Vec3::Z, Vec3::Y),
+        ),
+        0.8,
+        ROUTE_COLOR,
+    );
+
+    let target = hovered_intersection(world, &building_state).filter(|target| *target != origin);
+    let description = match target {
+        None => "Route preview\nPoint at an intersection (L again to stop)".to_string(),
+        Some(target) => match world.estimate_route(origin, target, VehicleType::Car) {
+            None => "Route preview\nCars can't get there".to_string(),
+            Some(car) => {
+                let mut from = origin_position;
+                for position in car
+                    .path
+                    .iter()
+                    .filter_map(|id| network.get_intersection_position(*id))
+                {
+                    gizmos.line(route_point(&from), route_point(position), ROUTE_COLOR);
+                    from = *position;
+                }
+                let truck = world
+                    .estimate_route(origin, target, VehicleType::Truck)
+                    .map_or_else(
+                        || "no route".to_string(),
+                        |truck| format!("~{:.0}s", truck.secs),
+                    );
+                format!(
+                    "Route preview\n{:.0} long, {} intersections\nCar ~{:.0}s, truck {}",
+                    car.distance,
+                    car.path.len(),
+                    car.secs,
+                    truck
+                )
+            }
+        },
+    };
+    for mut text in text_query.iter_mut() {
+        if **text != description {
+            **text = description.clone();
+        }
+    }
+}
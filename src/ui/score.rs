@@ -0,0 +1,85 @@
+//! End-of-run scoring for the UI
+//!
+//! When the game is won or lost, the run is summarized and scored once and
+//! recorded against the local best score for the scenario.
+
+use bevy::prelude::*;
+use std::path::PathBuf;
+
+use super::components::SimWorldResource;
+use super::worlds::WorldSlots;
+use crate::simulation::{BestScores, RunSummary};
+
+/// Scenario name runs in the UI are recorded under by default
+pub const UI_SCENARIO: &str = "sandbox";
+
+/// Best scores per scenario plus the summary of the current run, once it ends
+#[derive(Resource)]
+pub struct RunScores {
+    /// Scenario the current run is recorded under
+    pub scenario: String,
+    pub best: BestScores,
+    /// File best scores are saved to; `None` keeps them in memory only
+    pub path: Option<PathBuf>,
+    /// Summary of the current run, set when the game ends
+    pub summary: Option<RunSummary>,
+    /// Whether the current run set a new best score
+    pub new_best: bool,
+}
+
+impl Default for RunScores {
+    fn default() -> Self {
+        Self {
+            scenario: UI_SCENARIO.to_string(),
+            best: BestScores::default(),
+            path: None,
+            summary: None,
+            new_best: false,
+        }
+    }
+}
+
+impl RunScores {
+    /// Load best scores from a file, falling back to none on error
+    pub fn load(scenario: &str, path: PathBuf) -> Self {
+        let best = BestScores::load(&path).unwrap_or_else(|e| {
+            warn!("{:#}", e);
+            BestScores::default()
+        });
+        Self {
+            scenario: scenario.to_string(),
+            best,
+            path: Some(path),
+            ..default()
+        }
+    }
+}
+
+/// System to score the run once the game is won or lost
+pub fn record_run_summary(
+    sim_world: Res<SimWorldResource>,
+    slots: Res<WorldSlots>,
+    mut scores: ResMut<RunScores>,
+) {
+    if scores.summary.is_some() {
+        return;
+    }
+    let Some(game_state) = &slots.live(&sim_world.0).game_state else {
+        return;
+    };
+    if !game_state.is_won && !game_state.is_lost {
+        return;
+    }
+
+    let summary = RunSummary::from_game_state(&scores.scenario, game_state);
+    info!("Run finished with score {}", summary.score);
+    scores.new_best = scores.best.record(&summary);
+    if scores.new_best {
+        if let Some(path) = &scores.path {
+            if let Err(e) = scores.best.save(path) {
+                warn!("{:#}", e);
+            }
+        }
+    }
+    scores.summary = Some(summary);
+}
@@ -0,0 +1,141 @@
+//! Signal suggestion panel
+//!
+//! Press G to list the intersections without a signal where a signal or
+//! roundabout would save the most waiting, with a ring over each of them on
+//! the map. The best suggestion gets the widest ring, and a roundabout's
+//! ring has an arrowhead showing which way traffic would go round it.
+
+use std::f32::consts::{FRAC_PI_2, PI};
+
+use bevy::prelude::*;
+
+use super::components::SimWorldResource;
+use crate::simulation::{DrivingSide, Treatment};
+
+/// Suggestions listed and marked on the map
+const MAX_SUGGESTIONS: usize = 5;
+
+/// Height of the rings above the intersections
+const RING_HEIGHT: f32 = 0.3;
+
+/// Length of the arms of the arrowhead on a roundabout's ring
+const CIRCULATION_ARROW_LENGTH: f32 = 0.4;
+
+/// Marker for the signal suggestion panel
+#[derive(Component)]
+pub struct SignalAdvicePanel;
+
+/// Marker for the signal suggestion panel's text
+#[derive(Component)]
+pub struct SignalAdviceText;
+
+/// System to create the (hidden) signal suggestion panel
+pub fn setup_signal_advice_ui(mut commands: Commands) {
+    commands
+        .spawn((
+            SignalAdvicePanel,
+            Node {
+                position_type: PositionType::Absolute,
+                top: Val::Px(60.0),
+                right: Val::Px(10.0),
+                padding: UiRect::all(Val::Px(10.0)),
+                ..default()
+            },
+            BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.8)),
+            Visibility::Hidden,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                SignalAdviceText,
+                Text::new(""),
+                TextFont {
+                    font_size: 14.0,
+                    ..default()
+                },
+                TextColor(Color::WHITE),
+            ));
+        });
+}
+
+/// System to toggle the signal suggestion panel (G), and keep it and the
+/// rings over the suggested intersections current while it is shown
+pub fn update_signal_advice(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    sim_world: Res<SimWorldResource>,
+    mut panel_query: Query<&mut Visibility, With<SignalAdvicePanel>>,
+    mut text_query: Query<&mut Text, With<SignalAdviceText>>,
+    mut gizmos: Gizmos,
+) {
+    let mut shown = false;
+    for mut visibility in panel_query.iter_mut() {
+        if keyboard.just_pressed(KeyCode::KeyG) {
+            *visibility = match *visibility {
+                Visibility::Hidden => Visibility::Inherited,
+                _ => Visibility::Hidden,
+            };
+        }
+        shown |= *visibility != Visibility::Hidden;
+    }
+    if !shown {
+        return;
+    }
+
+    let suggestions = sim_world.0.signal_suggestions(MAX_SUGGESTIONS);
+    let mut description = String::from("Signal suggestions");
+    if suggestions.is_empty() {
+        description.push_str("\nNo intersection is holding traffic up");
+    }
+    for (rank, suggestion) in suggestions.iter().enumerate() {
+        description.push_str(&format!(
+            "\n{}. {} at ({:.0}, {:.0}): {:.1}s wait/vehicle, saves ~{:.0}s/min",
+            rank + 1,
+            suggestion.treatment.label(),
+            suggestion.position.x,
+            suggestion.position.z,
+            suggestion.delay_per_vehicle(),
+            suggestion.projected_savings_per_minute
+        ));
+
+        let color = match suggestion.treatment {
+            Treatment::Signal => Color::srgb(1.0, 0.8, 0.1),
+            Treatment::Roundabout => Color::srgb(0.3, 0.8, 1.0),
+        };
+        let center = Vec3::new(suggestion.position.x, RING_HEIGHT, suggestion.position.z);
+        let radius = 1.0 + 0.3 * (MAX_SUGGESTIONS - rank) as f32;
+        gizmos.circle(
+            Isometry3d::new(center, Quat::from_rotation_arc(Vec3::Z, Vec3::Y)),
+            radius,
+            color,
+        );
+        if suggestion.treatment == Treatment::Roundabout {
+            draw_circulation(&mut gizmos, center, radius, sim_world.0.driving_side, color);
+        }
+    }
+    for mut text in text_query.iter_mut() {
+        if **text != description {
+            **text = description.clone();
+        }
+    }
+}
+
+/// Draw an arrowhead on a roundabout's ring, at its point along +Z, pointing
+/// the way traffic goes round on this side of the road
+fn draw_circulation(
+    gizmos: &mut Gizmos,
+    center: Vec3,
+    radius: f32,
+    side: DrivingSide,
+    color: Color,
+) {
+    let tip = center + Vec3::new(0.0, 0.0, radius);
+    // Headings as in the simulation: 0 along +Z, turning left is positive
+    let heading = side.circulation() * FRAC_PI_2;
+    for spread in [-0.5, 0.5] {
+        let back = heading + PI + spread;
+        gizmos.line(
+            tip,
+            tip + Vec3::new(back.sin(), 0.0, back.cos()) * CIRCULATION_ARROW_LENGTH,
+            color,
+        );
+    }
+}
@@ -0,0 +1,533 @@
+//! Traffic signal phase editor
+//!
+//! In signal mode (5), clicking an intersection selects it and opens a panel
+//! listing its signal's phases. Drag a phase's bar to change how long it
+//! lasts, add or remove phases, or copy the timings and click other
+//! intersections to paste them. "Cycle control" switches the intersection
+//! between uncontrolled, a stop sign, a yield and a traffic light. Changes
+//! go out as [`BuildCommand::Signal`]s and [`BuildCommand::SetControl`]s, so
+//! they apply to the running simulation and reach co-op peers.
+
+use bevy::ecs::hierarchy::ChildSpawnerCommands;
+use bevy::prelude::*;
+
+use super::components::{BuildingMode, BuildingState, PendingCommands, SimWorldResource};
+use crate::simulation::{
+    copy_timings, BuildCommand, IntersectionControl, IntersectionId, SignalPhase, SimWorld,
+    MAX_PHASE_SECS, MIN_PHASE_SECS,
+};
+
+/// Width of a phase bar per second of the phase, in pixels
+const PIXELS_PER_SEC: f32 = 3.0;
+
+/// Dragged durations snap to multiples of this many seconds
+const DURATION_STEP: f32 = 0.5;
+
+/// Selection and in-progress edits of the signal editor
+#[derive(Resource, Default)]
+pub struct SignalEditor {
+    /// Intersection shown in the panel
+    pub selected: Option<IntersectionId>,
+    /// Timings pasted onto each intersection clicked while set
+    pub clipboard: Option<Vec<SignalPhase>>,
+    drag: Option<PhaseDrag>,
+    /// Selection, its control and phase count (`None` without a signal)
+    /// the panel was last laid out for
+    layout: Option<(IntersectionId, IntersectionControl, Option<usize>)>,
+}
+
+/// A phase bar being dragged
+struct PhaseDrag {
+    phase: usize,
+    start_x: f32,
+    start_duration: f32,
+    /// Duration last sent to the simulation
+    sent: f32,
+}
+
+/// Marker for the signal editor panel
+#[derive(Component)]
+pub struct SignalPanel;
+
+/// Bar showing a phase's duration; drag it to change the duration
+#[derive(Component)]
+pub struct PhaseBar(usize);
+
+/// Text describing a phase
+#[derive(Component)]
+pub struct PhaseLabel(usize);
+
+/// Buttons in the signal editor panel
+#[derive(Component, Clone, Copy)]
+pub enum SignalButton {
+    AddSignal,
+    RemoveSignal,
+    AddPhase,
+    RemovePhase(usize),
+    CopyTimings,
+    CycleControl,
+    Close,
+}
+
+/// System to create the (hidden) signal editor panel
+pub fn setup_signal_ui(mut commands: Commands) {
+    commands.spawn((
+        SignalPanel,
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(150.0),
+            right: Val::Px(10.0),
+            padding: UiRect::all(Val::Px(10.0)),
+            flex_direction: FlexDirection::Column,
+            row_gap: Val::Px(6.0),
+            ..default()
+        },
+        BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.8)),
+        Visibility::Hidden,
+    ));
+}
+
+/// System to select intersections, or paste copied timings onto them, in
+/// signal mode
+pub fn handle_signal_click(
+    mouse_button: Res<ButtonInput<MouseButton>>,
+    building_state: Res<BuildingState>,
+    sim_world: Res<SimWorldResource>,
+    mut editor: ResMut<SignalEditor>,
+    mut pending: ResMut<PendingCommands>,
+    interaction_query: Query<&Interaction, With<Button>>,
+) {
+    if building_state.mode != BuildingMode::Signal {
+        if editor.selected.is_some() || editor.clipboard.is_some() {
+            editor.selected = None;
+            editor.clipboard = None;
+        }
+        return;
+    }
+    if !mouse_button.just_pressed(MouseButton::Left)
+        || interaction_query
+            .iter()
+            .any(|interaction| *interaction != Interaction::None)
+    {
+        return;
+    }
+    let Some(cursor) = building_state.cursor_position else {
+        return;
+    };
+
+    let network = &sim_world.0.road_network;
+    let clicked = network.find_closest_intersection(&cursor).filter(|id| {
+        network
+            .get_intersection_position(*id)
+            .is_some_and(|position| position.distance(&cursor) <= building_state.snap_distance)
+    });
+    let Some(intersection_id) = clicked else {
+        editor.selected = None;
+        return;
+    };
+
+    if let Some(clipboard) = &editor.clipboard {
+        let phases = copy_timings(clipboard, &current_phases(&sim_world.0, intersection_id));
+        if phases.is_empty() {
+            info!("No roads lead into that intersection");
+            return;
+        }
+        pending.0.push(BuildCommand::Signal {
+            intersection: intersection_id,
+            phases: Some(phases),
+        });
+    }
+    editor.selected = Some(intersection_id);
+}
+
+/// Phases of an intersection's signal, or the phases a new one would have
+fn current_phases(world: &SimWorld, intersection_id: IntersectionId) -> Vec<SignalPhase> {
+    world
+        .intersections
+        .get(&intersection_id)
+        .and_then(|intersection| intersection.signal.as_ref())
+        .map_or_else(
+            || world.default_signal_phases(intersection_id),
+            |signal| signal.phases().to_vec(),
+        )
+}
+
+/// System to lay out the panel for the selected intersection
+///
+/// The panel is rebuilt only when the selection or the number of phases
+/// changes; [`update_signal_panel`] keeps it current in between.
+pub fn layout_signal_panel(
+    mut commands: Commands,
+    sim_world: Res<SimWorldResource>,
+    mut editor: ResMut<SignalEditor>,
+    mut panel_query: Query<(Entity, &mut Visibility), With<SignalPanel>>,
+) {
+    let layout = editor.selected.map(|intersection_id| {
+        let intersection = sim_world.0.intersections.get(&intersection_id);
+        let control = intersection.map_or(IntersectionControl::Uncontrolled, |intersection| {
+            intersection.control
+        });
+        let phase_count = intersection
+            .and_then(|intersection| intersection.signal.as_ref())
+            .map(|signal| signal.phases().len());
+        (intersection_id, control, phase_count)
+    });
+    if layout == editor.layout {
+        return;
+    }
+    editor.layout = layout;
+
+    for (panel, mut visibility) in panel_query.iter_mut() {
+        commands.entity(panel).despawn_children();
+        let Some((intersection_id, control, phase_count)) = layout else {
+            *visibility = Visibility::Hidden;
+            continue;
+        };
+        *visibility = Visibility::Inherited;
+        commands.entity(panel).with_children(|parent| {
+            parent.spawn((
+                Text::new(format!("Intersection {}", intersection_id.0 .0)),
+                TextFont {
+                    font_size: 16.0,
+                    ..default()
+                },
+                TextColor(Color::WHITE),
+            ));
+
+            let Some(phase_count) = phase_count else {
+                parent.spawn((
+                    Text::new(control.label()),
+                    TextFont {
+                        font_size: 14.0,
+                        ..default()
+                    },
+                    TextColor(Color::srgb(0.7, 0.7, 0.7)),
+                ));
+                spawn_row(parent, |row| {
+                    spawn_signal_button(row, SignalButton::AddSignal, "Add signal");
+                    spawn_signal_button(row, SignalButton::CycleControl, "Cycle control");
+                    spawn_signal_button(row, SignalButton::Close, "Close");
+                });
+                return;
+            };
+
+            for phase in 0..phase_count {
+                parent.spawn((
+                    PhaseLabel(phase),
+                    Text::new(""),
+                    TextFont {
+                        font_size: 14.0,
+                        ..default()
+                    },
+                    TextColor(Color::WHITE),
+                ));
+                spawn_row(parent, |row| {
+                    row.spawn((
+                        PhaseBar(phase),
+                        Button,
+                        Node {
+                            height: Val::Px(14.0),
+                            ..default()
+                        },
+                        BackgroundColor(Color::srgb(0.5, 0.5, 0.5)),
+                    ));
+                    if phase_count > 1 {
+                        spawn_signal_button(row, SignalButton::RemovePhase(phase), "x");
+                    }
+                });
+            }
+            spawn_row(parent, |row| {
+                spawn_signal_button(row, SignalButton::AddPhase, "Add phase");
+                spawn_signal_button(row, SignalButton::CopyTimings, "Copy timings");
+            });
+            spawn_row(parent, |row| {
+                spawn_signal_button(row, SignalButton::RemoveSignal, "Remove signal");
+                spawn_signal_button(row, SignalButton::CycleControl, "Cycle control");
+                spawn_signal_button(row, SignalButton::Close, "Close");
+            });
+        });
+    }
+}
+
+fn spawn_row(parent: &mut ChildSpawnerCommands, children: impl FnOnce(&mut ChildSpawnerCommands)) {
+    parent
+        .spawn(Node {
+            align_items: AlignItems::Center,
+            column_gap: Val::Px(6.0),
+            ..default()
+        })
+        .with_children(children);
+}
+
+fn spawn_signal_button(parent: &mut ChildSpawnerCommands, button: SignalButton, text: &str) {
+    parent
+        .spawn((
+            button,
+            Button,
+            Node {
+                padding: UiRect::axes(Val::Px(8.0), Val::Px(4.0)),
+                border: UiRect::all(Val::Px(1.0)),
+                ..default()
+            },
+            BorderColor::all(Color::WHITE),
+            BackgroundColor(Color::srgb(0.25, 0.25, 0.3)),
+        ))
+        .with_children(|button| {
+            button.spawn((
+                Text::new(text),
+                TextFont {
+                    font_size: 13.0,
+                    ..default()
+                },
+                TextColor(Color::WHITE),
+            ));
+        });
+}
+
+/// System to keep the phase bars and labels in step with the simulation
+pub fn update_signal_panel(
+    sim_world: Res<SimWorldResource>,
+    editor: Res<SignalEditor>,
+    mut bar_query: Query<(&PhaseBar, &mut Node, &mut BackgroundColor)>,
+    mut label_query: Query<(&PhaseLabel, &mut Text)>,
+    mut button_query: Query<(&SignalButton, &mut BorderColor)>,
+) {
+    let Some(signal) = editor
+        .selected
+        .and_then(|intersection_id| sim_world.0.intersections.get(&intersection_id))
+        .and_then(|intersection| intersection.signal.as_ref())
+    else {
+        return;
+    };
+
+    for (bar, mut node, mut color) in bar_query.iter_mut() {
+        let Some(phase) = signal.phases().get(bar.0) else {
+            continue;
+        };
+        node.width = Val::Px(phase.duration * PIXELS_PER_SEC);
+        color.0 = if bar.0 == signal.current_phase() {
+            Color::srgb(0.2, 0.8, 0.3)
+        } else {
+            Color::srgb(0.5, 0.5, 0.5)
+        };
+    }
+    for (label, mut text) in label_query.iter_mut() {
+        let Some(phase) = signal.phases().get(label.0) else {
+            continue;
+        };
+        let mut description = format!(
+            "Phase {} - {:.1}s, {} road(s) green",
+            label.0 + 1,
+            phase.duration,
+            phase.green.len()
+        );
+        if label.0 == signal.current_phase() {
+            description.push_str(&format!(" ({:.0}s left)", signal.time_left().ceil()));
+        }
+        if **text != description {
+            **text = description;
+        }
+    }
+    // Highlight the copy button while pasting
+    for (button, mut border) in button_query.iter_mut() {
+        if matches!(button, SignalButton::CopyTimings) {
+            *border = BorderColor::all(if editor.clipboard.is_some() {
+                Color::srgb(0.0, 1.0, 0.0)
+            } else {
+                Color::WHITE
+            });
+        }
+    }
+}
+
+/// System to handle the signal editor's buttons
+pub fn handle_signal_buttons(
+    sim_world: Res<SimWorldResource>,
+    mut editor: ResMut<SignalEditor>,
+    mut pending: ResMut<PendingCommands>,
+    interaction_query: Query<(&Interaction, &SignalButton), Changed<Interaction>>,
+) {
+    let Some(intersection_id) = editor.selected else {
+        return;
+    };
+    for (interaction, button) in interaction_query.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        let mut phases = current_phases(&sim_world.0, intersection_id);
+        let new_phases = match *button {
+            SignalButton::AddSignal => {
+                if phases.is_empty() {
+                    info!("No roads lead into that intersection");
+                    continue;
+                }
+                Some(phases)
+            }
+            SignalButton::RemoveSignal => None,
+            SignalButton::AddPhase => {
+                // Give the next approach in turn another green
+                let defaults = sim_world.0.default_signal_phases(intersection_id);
+                let Some(next) = defaults.get(phases.len() % defaults.len().max(1)) else {
+                    continue;
+                };
+                phases.push(next.clone());
+                Some(phases)
+            }
+            SignalButton::RemovePhase(phase) => {
+                if phase >= phases.len() || phases.len() == 1 {
+                    continue;
+                }
+                phases.remove(phase);
+                Some(phases)
+            }
+            SignalButton::CopyTimings => {
+                editor.clipboard = match editor.clipboard {
+                    Some(_) => None,
+                    None => Some(phases),
+                };
+                continue;
+            }
+            SignalButton::CycleControl => {
+                let control = sim_world
+                    .0
+                    .intersection_control(intersection_id)
+                    .unwrap_or_default();
+                pending.0.push(BuildCommand::SetControl {
+                    intersection: intersection_id,
+                    control: control.next(),
+                });
+                continue;
+            }
+            SignalButton::Close => {
+                editor.selected = None;
+                editor.clipboard = None;
+                continue;
+            }
+        };
+        pending.0.push(BuildCommand::Signal {
+            intersection: intersection_id,
+            phases: new_phases,
+        });
+    }
+}
+
+/// System to change phase durations by dragging their bars
+pub fn drag_phase_bars(
+    mouse_button: Res<ButtonInput<MouseButton>>,
+    windows: Query<&Window>,
+    sim_world: Res<SimWorldResource>,
+    mut editor: ResMut<SignalEditor>,
+    mut pending: ResMut<PendingCommands>,
+    bar_query: Query<(&Interaction, &PhaseBar), Changed<Interaction>>,
+) {
+    let Some(cursor_x) = windows
+        .single()
+        .ok()
+        .and_then(|window| window.cursor_position())
+        .map(|cursor| cursor.x)
+    else {
+        return;
+    };
+    let Some(intersection_id) = editor.selected else {
+        editor.drag = None;
+        return;
+    };
+    let phases = current_phases(&sim_world.0, intersection_id);
+
+    for (interaction, bar) in bar_query.iter() {
+        if *interaction == Interaction::Pressed {
+            if let Some(phase) = phases.get(bar.0) {
+                editor.drag = Some(PhaseDrag {
+                    phase: bar.0,
+                    start_x: cursor_x,
+                    start_duration: phase.duration,
+                    sent: phase.duration,
+                });
+            }
+        }
+    }
+
+    if !mouse_button.pressed(MouseButton::Left) {
+        editor.drag = None;
+        return;
+    }
+    let Some(drag) = &mut editor.drag else {
+        return;
+    };
+    let duration = ((drag.start_duration + (cursor_x - drag.start_x) / PIXELS_PER_SEC)
+        / DURATION_STEP)
+        .round()
+        * DURATION_STEP;
+    let duration = duration.clamp(MIN_PHASE_SECS, MAX_PHASE_SECS);
+    if duration == drag.sent || drag.phase >= phases.len() {
+        return;
+    }
+    drag.sent = duration;
+    let mut phases = phases;
+    phases[drag.phase].duration = duration;
+    pending.0.push(BuildCommand::Signal {
+        intersection: intersection_id,
+        phases: Some(phases),
+    });
+}
+
+/// System to draw each signal's light on its incoming roads, ring stop
+/// signs and yields, and mark the selected intersection
+pub fn draw_signal_lights(
+    sim_world: Res<SimWorldResource>,
+    editor: Res<SignalEditor>,
+    mut gizmos: Gizmos,
+) {
+    let network = &sim_world.0.road_network;
+    for intersection in sim_world.0.intersections.values() {
+        let sign_color = match intersection.control {
+            IntersectionControl::Stop => Some(Color::srgb(0.9, 0.1, 0.1)),
+            IntersectionControl::Yield => Some(Color::srgb(1.0, 0.8, 0.1)),
+            IntersectionControl::Uncontrolled | IntersectionControl::TrafficLight => None,
+        };
+        if let Some(color) = sign_color {
+            gizmos.circle(
+                Isometry3d::new(
+                    Vec3::new(intersection.position.x, 0.15, intersection.position.z),
+                    Quat::from_rotation_x(std::f32::consts::FRAC_PI_2),
+                ),
+                0.9,
+                color,
+            );
+        }
+        let Some(signal) = &intersection.signal else {
+            continue;
+        };
+        let end = Vec3::new(intersection.position.x, 0.2, intersection.position.z);
+        for road_id in signal.phases().iter().flat_map(|phase| &phase.green) {
+            let Some(start) = network
+                .get_road(*road_id)
+                .and_then(|road| network.get_intersection_position(road.start_intersection))
+            else {
+                continue;
+            };
+            let start = Vec3::new(start.x, 0.2, start.z);
+            let direction = (start - end).normalize_or_zero();
+            let color = if signal.is_green(*road_id) {
+                Color::srgb(0.1, 1.0, 0.2)
+            } else {
+                Color::srgb(1.0, 0.15, 0.1)
+            };
+            gizmos.line(end + direction * 0.8, end + direction * 1.6, color);
+        }
+    }
+
+    if let Some(position) = editor
+        .selected
+        .and_then(|intersection_id| network.get_intersection_position(intersection_id))
+    {
+        gizmos.circle(
+            Isometry3d::new(
+                Vec3::new(position.x, 0.1, position.z),
+                Quat::from_rotation_x(std::f32::consts::FRAC_PI_2),
+            ),
+            1.2,
+            Color::srgb(1.0, 0.9, 0.2),
+        );
+    }
+}
@@ -1,88 +1,76 @@
-//! Systems for spawning visual entities from simulation state
+//! Spawning the Bevy entities that draw simulation objects
 
 use bevy::prelude::*;
 
 use super::components::{
-    DeliveryIndicator, DemandIndicator, EntityMappings, FactoryLink, ApartmentLink, IntersectionLink,
-    RoadLink, ShopLink, SimSynced, SimWorldResource,
+    DeliveryIndicator, DemandIndicator, EntityMappings, ProductionBar, RoadVisual, SimSynced,
+    VehicleBody, VisualLink,
 };
-use crate::simulation::SimRoadNetwork;
+use super::vehicle_models::{wheel_radius, wheel_rotation, AwaitingModel, Wheel, WheelRoll};
 use crate::simulation::{
-    FactoryId, ApartmentId, IntersectionId, Position, RoadId, ShopId, SimRoad, COMMUTE_HEALTHY_DISTANCE,
+    CarId, DecorationId, DecorationKind, DrivingSide, FactoryId, ApartmentId, IntersectionId, Position, RoadId,
+    RoadKind, ShopId, SpecialBuildingId, SpecialKind, VehicleType, VisualId,
 };
 
-#[derive(Resource, Default)]
-pub struct ApartmentVisualAssets {
-    commute_radius_mesh: Option<Handle<Mesh>>,
-    commute_radius_material: Option<Handle<StandardMaterial>>,
+/// Visual length of a car mesh
+pub const CAR_LENGTH: f32 = 0.5;
+/// Visual length of a truck mesh
+pub const TRUCK_LENGTH: f32 = 0.8;
+/// Visual length of an ambulance mesh
+pub const AMBULANCE_LENGTH: f32 = 0.7;
+
+const TWO_WAY_ROAD_WIDTH: f32 = 0.6;
+const ONE_WAY_ROAD_WIDTH: f32 = 0.4;
+const ROAD_HEIGHT: f32 = 0.02;
+/// Lane offset from the road center line on two-way roads (matches car lane offset)
+const TWO_WAY_LANE_OFFSET: f32 = 0.15;
+const ARROW_ARM_WIDTH: f32 = 0.04;
+const ARROW_ARM_HEIGHT: f32 = 0.03;
+const ARROW_ARM_LENGTH: f32 = 0.15;
+const ARROW_ANGLE: f32 = 0.5;
+/// Full length of a factory's production bar
+const PRODUCTION_BAR_LENGTH: f32 = 1.2;
+/// Height of the production bar above the factory's center
+const PRODUCTION_BAR_Y: f32 = 0.5;
+/// Distance of the production bar from the factory's center, in front of it
+const PRODUCTION_BAR_Z: f32 = 0.78;
+
+/// Color of a demand indicator: red while the building's vehicles are out,
+/// green while they are home
+pub fn demand_indicator_color(busy: bool) -> Color {
+    if busy {
+        Color::srgb(1.0, 0.0, 0.0)
+    } else {
+        Color::srgb(0.0, 1.0, 0.0)
+    }
 }
 
-/// System to create initial visual entities from simulation state
-pub fn spawn_initial_visuals(
-    mut commands: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<StandardMaterial>>,
-    sim_world: Res<SimWorldResource>,
-    mut mappings: ResMut<EntityMappings>,
-    mut apartment_assets: ResMut<ApartmentVisualAssets>,
-) {
-    let world = &sim_world.0;
-
-    spawn_intersections(
-        &mut commands,
-        &mut meshes,
-        &mut materials,
-        world,
-        &mut mappings,
-    );
-    spawn_roads(
-        &mut commands,
-        &mut meshes,
-        &mut materials,
-        world,
-        &mut mappings,
-    );
-    spawn_apartments(
-        &mut commands,
-        &mut meshes,
-        &mut materials,
-        world,
-        &mut mappings,
-        &mut apartment_assets,
-    );
-    spawn_factories(
-        &mut commands,
-        &mut meshes,
-        &mut materials,
-        world,
-        &mut mappings,
-    );
-    spawn_shops(
-        &mut commands,
-        &mut meshes,
-        &mut materials,
-        world,
-        &mut mappings,
-    );
+/// Color of a factory's `index`th delivery indicator: gold while that
+/// delivery is ready, dark gray otherwise
+pub fn delivery_indicator_color(index: u32, deliveries_ready: u32) -> Color {
+    if index < deliveries_ready {
+        Color::srgb(1.0, 0.8, 0.0)
+    } else {
+        Color::srgb(0.3, 0.3, 0.3)
+    }
 }
 
-fn spawn_intersections(
-    commands: &mut Commands,
-    meshes: &mut ResMut<Assets<Mesh>>,
-    materials: &mut ResMut<Assets<StandardMaterial>>,
-    world: &crate::simulation::SimWorld,
-    mappings: &mut ResMut<EntityMappings>,
-) {
-    for (id, intersection) in &world.intersections {
-        spawn_intersection_visual(
-            commands,
-            meshes,
-            materials,
-            *id,
-            &intersection.position,
-            mappings,
-        );
-    }
+/// Transform of a factory's production bar, filled from the left up to
+/// `progress` (0 to 1) toward the next delivery
+pub fn production_bar_transform(progress: f32) -> Transform {
+    let progress = progress.clamp(0.0, 1.0);
+    Transform::from_translation(Vec3::new(
+        (progress - 1.0) * PRODUCTION_BAR_LENGTH / 2.0,
+        PRODUCTION_BAR_Y,
+        PRODUCTION_BAR_Z,
+    ))
+    .with_scale(Vec3::new(progress, 1.0, 1.0))
+}
+
+#[derive(Resource, Default)]
+pub struct ApartmentVisualAssets {
+    commute_radius_mesh: Option<Handle<Mesh>>,
+    commute_radius_material: Option<Handle<StandardMaterial>>,
 }
 
 /// Spawn a single intersection visual
@@ -101,225 +89,215 @@ pub fn spawn_intersection_visual(
     let entity = commands
         .spawn((
             SimSynced,
-            IntersectionLink(id),
+            VisualLink(VisualId::Intersection(id)),
             Mesh3d(meshes.add(Cuboid::new(
                 INTERSECTION_SIZE,
                 INTERSECTION_HEIGHT,
                 INTERSECTION_SIZE,
             ))),
             MeshMaterial3d(materials.add(intersection_color)),
-            Transform::from_translation(Vec3::new(pos.x, INTERSECTION_HEIGHT / 2.0, pos.z)),
+            Transform::from_translation(Vec3::new(pos.x, pos.y + INTERSECTION_HEIGHT / 2.0, pos.z)),
         ))
         .id();
     mappings.intersections.insert(id, entity);
 }
 
-fn spawn_roads(
-    commands: &mut Commands,
-    meshes: &mut ResMut<Assets<Mesh>>,
-    materials: &mut ResMut<Assets<StandardMaterial>>,
-    world: &crate::simulation::SimWorld,
-    mappings: &mut ResMut<EntityMappings>,
-) {
-    // Track which road pairs we've rendered (to avoid double-rendering two-way roads)
-    let mut rendered_road_pairs: std::collections::HashSet<(
-        crate::simulation::IntersectionId,
-        crate::simulation::IntersectionId,
-    )> = std::collections::HashSet::new();
-
-    for (id, road) in world.road_network.get_all_roads() {
-        // For two-way roads, only render once per pair
-        let pair_key = if road.start_intersection.0 .0 < road.end_intersection.0 .0 {
-            (road.start_intersection, road.end_intersection)
-        } else {
-            (road.end_intersection, road.start_intersection)
-        };
-
-        if road.is_two_way && rendered_road_pairs.contains(&pair_key) {
-            continue;
-        }
-        if road.is_two_way {
-            rendered_road_pairs.insert(pair_key);
-        }
-
-        spawn_road_visual(
-            commands,
-            meshes,
-            materials,
-            &world.road_network,
-            *id,
-            road,
-            mappings,
-        );
-    }
-}
-
 /// Spawn a single road visual
+///
+/// Two-way roads are drawn once, for their canonical direction, with arrows
+/// for both directions baked into a single arrow mesh.
+#[allow(clippy::too_many_arguments)]
 pub fn spawn_road_visual(
     commands: &mut Commands,
     meshes: &mut ResMut<Assets<Mesh>>,
     materials: &mut ResMut<Assets<StandardMaterial>>,
-    road_network: &SimRoadNetwork,
     id: RoadId,
-    road: &SimRoad,
+    start: &Position,
+    end: &Position,
+    two_way: bool,
+    kind: RoadKind,
+    driving_side: DrivingSide,
+    arrow_spacing: f32,
     mappings: &mut ResMut<EntityMappings>,
 ) {
-    const TWO_WAY_ROAD_WIDTH: f32 = 0.6;
-    const ROAD_HEIGHT: f32 = 0.02;
-    let road_color = Color::srgb(0.2, 0.2, 0.2);
-
-    let start_pos = road_network.get_intersection_position(road.start_intersection);
-    let end_pos = road_network.get_intersection_position(road.end_intersection);
-
-    if let (Some(start), Some(end)) = (start_pos, end_pos) {
-        let length = start.distance(end);
-        let midpoint = Position::new(
-            (start.x + end.x) / 2.0,
-            (start.y + end.y) / 2.0,
-            (start.z + end.z) / 2.0,
-        );
-        let angle = start.angle_to(end);
-        let rotation = Quat::from_rotation_y(angle);
-        let width = if road.is_two_way {
-            TWO_WAY_ROAD_WIDTH
-        } else {
-            0.4
-        };
-
-        let entity = commands
-            .spawn((
-                SimSynced,
-                RoadLink(id),
-                Mesh3d(meshes.add(Cuboid::new(width, ROAD_HEIGHT, length))),
-                MeshMaterial3d(materials.add(road_color)),
-                Transform::from_translation(Vec3::new(midpoint.x, ROAD_HEIGHT / 2.0, midpoint.z))
-                    .with_rotation(rotation),
-            ))
-            .id();
-        mappings.roads.insert(id, entity);
-
-        // Add direction arrows
-        spawn_direction_arrows(
-            commands,
-            meshes,
-            materials,
-            start,
-            end,
-            if road.is_two_way { -0.15 } else { 0.0 },
-            entity,
-            false,
-        );
-
-        if road.is_two_way {
-            spawn_direction_arrows(commands, meshes, materials, end, start, 0.15, entity, true);
-        }
-    }
-}
-
-/// Helper function to spawn V-shaped directional arrow indicators on a road
-fn spawn_direction_arrows(
-    commands: &mut Commands,
-    meshes: &mut ResMut<Assets<Mesh>>,
-    materials: &mut ResMut<Assets<StandardMaterial>>,
-    start_pos: &Position,
-    end_pos: &Position,
-    offset_x: f32,
-    parent_entity: Entity,
-    reverse_direction: bool,
-) {
-    const ARROW_ARM_WIDTH: f32 = 0.04;
-    const ARROW_ARM_HEIGHT: f32 = 0.03;
-    const ARROW_ARM_LENGTH: f32 = 0.15;
-    const ARROW_ANGLE: f32 = 0.5;
-    const ARROW_SPACING: f32 = 2.0;
+    let road_color = match kind {
+        RoadKind::Street => Color::srgb(0.2, 0.2, 0.2),
+        RoadKind::Bridge => Color::srgb(0.5, 0.45, 0.4),
+        RoadKind::Ferry => Color::srgb(0.15, 0.3, 0.55),
+    };
     let arrow_color = Color::srgb(0.9, 0.9, 0.3);
 
-    let length = start_pos.distance(end_pos);
-    let arrow_angle_offset = if reverse_direction {
-        0.0
+    let arrow_mesh = meshes.add(build_arrow_mesh(
+        start.distance(end),
+        two_way,
+        driving_side,
+        arrow_spacing,
+    ));
+
+    let entity = commands
+        .spawn((
+            SimSynced,
+            VisualLink(VisualId::Road(id)),
+            RoadVisual {
+                start: *start,
+                end: *end,
+                two_way,
+                driving_side,
+                arrow_spacing,
+                arrow_mesh: arrow_mesh.clone(),
+            },
+            Mesh3d(meshes.add(build_road_mesh(start, end, two_way))),
+            MeshMaterial3d(materials.add(road_color)),
+            road_transform(start, end),
+        ))
+        .id();
+    mappings.roads.insert(id, entity);
+
+    let arrows = commands
+        .spawn((
+            Mesh3d(arrow_mesh),
+            MeshMaterial3d(materials.add(arrow_color)),
+            Transform::default(),
+        ))
+        .id();
+    commands.entity(entity).add_child(arrows);
+}
+
+/// Build the flat road surface mesh in road-local space (length along Z)
+pub fn build_road_mesh(start: &Position, end: &Position, is_two_way: bool) -> Mesh {
+    let width = if is_two_way {
+        TWO_WAY_ROAD_WIDTH
     } else {
-        std::f32::consts::PI / 2.0
+        ONE_WAY_ROAD_WIDTH
     };
+    Mesh::from(Cuboid::new(width, ROAD_HEIGHT, start.distance(end)))
+}
 
-    let num_arrows = (length / ARROW_SPACING).max(1.0) as i32;
+/// Transform placing a road-local mesh between two intersections, tilted
+/// to climb from one to the other
+pub fn road_transform(start: &Position, end: &Position) -> Transform {
+    Transform::from_translation(Vec3::new(
+        (start.x + end.x) / 2.0,
+        (start.y + end.y) / 2.0 + ROAD_HEIGHT / 2.0,
+        (start.z + end.z) / 2.0,
+    ))
+    .with_rotation(
+        Quat::from_rotation_y(start.angle_to(end))
+            * Quat::from_rotation_x(-start.grade_to(end).atan()),
+    )
+}
 
-    for i in 0..num_arrows {
-        let t = (i as f32 + 0.5) / num_arrows as f32;
-        let z_offset = (t - 0.5) * length;
+/// Build a single mesh containing every V-shaped direction arrow on a road
+///
+/// The mesh is in road-local space: +Z points from the start intersection to
+/// the end intersection and X is across the road. One-way roads get a single
+/// centered row of arrows; two-way roads get one row per lane, matching the
+/// lane offset vehicles drive on for the world's driving side.
+pub fn build_arrow_mesh(
+    length: f32,
+    is_two_way: bool,
+    driving_side: DrivingSide,
+    spacing: f32,
+) -> Mesh {
+    // The lane traffic heading along +Z keeps to
+    let forward = driving_side
+        .lane_offset(
+            &Position::new(0.0, 0.0, 0.0),
+            &Position::new(0.0, 0.0, 1.0),
+            TWO_WAY_LANE_OFFSET,
+        )
+        .x;
+    // (lane offset across the road, direction along +Z or -Z)
+    let two_way_lanes = [(forward, 1.0), (-forward, -1.0)];
+    let lanes: &[(f32, f32)] = if is_two_way {
+        &two_way_lanes
+    } else {
+        &[(0.0, 1.0)]
+    };
 
-        commands.entity(parent_entity).with_children(|parent| {
-            parent.spawn((
-                Mesh3d(meshes.add(Cuboid::new(
-                    ARROW_ARM_WIDTH,
-                    ARROW_ARM_HEIGHT,
-                    ARROW_ARM_LENGTH,
-                ))),
-                MeshMaterial3d(materials.add(arrow_color)),
-                Transform::from_translation(Vec3::new(
-                    offset_x - ARROW_ARM_LENGTH * 0.5 * ARROW_ANGLE.sin(),
-                    ARROW_ARM_HEIGHT,
-                    z_offset + ARROW_ARM_LENGTH * 0.5 * ARROW_ANGLE.cos(),
-                ))
-                .with_rotation(Quat::from_rotation_y(-ARROW_ANGLE + arrow_angle_offset)),
-            ));
+    let num_arrows = (length / spacing).max(1.0) as i32;
+    let arm = Mesh::from(Cuboid::new(
+        ARROW_ARM_WIDTH,
+        ARROW_ARM_HEIGHT,
+        ARROW_ARM_LENGTH,
+    ));
+
+    let mut combined: Option<Mesh> = None;
+    for &(lane_x, direction) in lanes {
+        for i in 0..num_arrows {
+            let t = (i as f32 + 0.5) / num_arrows as f32;
+            let center_z = (t - 0.5) * length;
+            let tip = Vec3::new(
+                lane_x,
+                ARROW_ARM_HEIGHT,
+                center_z + direction * ARROW_ARM_LENGTH * 0.5 * ARROW_ANGLE.cos(),
+            );
 
-            parent.spawn((
-                Mesh3d(meshes.add(Cuboid::new(
-                    ARROW_ARM_WIDTH,
-                    ARROW_ARM_HEIGHT,
-                    ARROW_ARM_LENGTH,
-                ))),
-                MeshMaterial3d(materials.add(arrow_color)),
-                Transform::from_translation(Vec3::new(
-                    offset_x + ARROW_ARM_LENGTH * 0.5 * ARROW_ANGLE.sin(),
-                    ARROW_ARM_HEIGHT,
-                    z_offset + ARROW_ARM_LENGTH * 0.5 * ARROW_ANGLE.cos(),
-                ))
-                .with_rotation(Quat::from_rotation_y(ARROW_ANGLE + arrow_angle_offset)),
-            ));
-        });
+            for side in [-1.0, 1.0] {
+                // Unit vector running from the tip back along this arm
+                let back = Vec3::new(
+                    side * ARROW_ANGLE.sin(),
+                    0.0,
+                    -direction * ARROW_ANGLE.cos(),
+                );
+                let arm_mesh = arm.clone().transformed_by(
+                    Transform::from_translation(tip + back * ARROW_ARM_LENGTH * 0.5)
+                        .with_rotation(Quat::from_rotation_y(back.x.atan2(back.z))),
+                );
+
+                match combined.as_mut() {
+                    Some(mesh) => {
+                        if let Err(e) = mesh.merge(&arm_mesh) {
+                            warn!("Failed to merge road arrow mesh: {}", e);
+                        }
+                    }
+                    None => combined = Some(arm_mesh),
+                }
+            }
+        }
     }
+
+    combined.unwrap_or(arm)
 }
 
-fn spawn_apartments(
-    commands: &mut Commands,
-    meshes: &mut ResMut<Assets<Mesh>>,
-    materials: &mut ResMut<Assets<StandardMaterial>>,
-    world: &crate::simulation::SimWorld,
-    mappings: &mut ResMut<EntityMappings>,
-    apartment_assets: &mut ApartmentVisualAssets,
-) {
-    for (id, apartment) in &world.apartments {
-        if let Some(intersection) = world.intersections.get(&apartment.intersection_id) {
-            spawn_apartment_visual(
-                commands,
-                meshes,
-                materials,
-                *id,
-                &intersection.position,
-                mappings,
-                apartment_assets,
-            );
-        }
+/// Pick the spacing between road arrows for a given camera height
+///
+/// Arrows thin out as the camera zooms out so distant roads don't turn into
+/// a solid yellow stripe.
+pub fn arrow_spacing_for_camera_height(height: f32) -> f32 {
+    if height < 30.0 {
+        2.0
+    } else if height < 90.0 {
+        4.0
+    } else {
+        8.0
     }
 }
 
 /// Spawn a single apartment visual
+///
+/// `commute_radius` is the economy's healthy commute distance, drawn as a ring
+/// around the apartment. `tint` replaces the standard color for content pack
+/// types.
+#[allow(clippy::too_many_arguments)]
 pub fn spawn_apartment_visual(
     commands: &mut Commands,
     meshes: &mut ResMut<Assets<Mesh>>,
     materials: &mut ResMut<Assets<StandardMaterial>>,
     id: ApartmentId,
     pos: &Position,
+    busy: bool,
+    commute_radius: f32,
+    tint: Option<Color>,
     mappings: &mut ResMut<EntityMappings>,
     apartment_assets: &mut ApartmentVisualAssets,
 ) {
     const APARTMENT_SIZE: f32 = 1.0;
     const COMMUTE_RADIUS_HEIGHT: f32 = 0.02;
-    let apartment_color = Color::srgb(0.7, 0.6, 0.4);
+    let apartment_color = tint.unwrap_or(Color::srgb(0.7, 0.6, 0.4));
     let commute_radius_mesh = apartment_assets
         .commute_radius_mesh
-        .get_or_insert_with(|| meshes.add(Annulus::new(COMMUTE_HEALTHY_DISTANCE - 0.05, COMMUTE_HEALTHY_DISTANCE)))
+        .get_or_insert_with(|| meshes.add(Annulus::new(commute_radius - 0.05, commute_radius)))
         .clone();
     let commute_radius_material = apartment_assets
         .commute_radius_material
@@ -336,10 +314,10 @@ pub fn spawn_apartment_visual(
     let entity = commands
         .spawn((
             SimSynced,
-            ApartmentLink(id),
+            VisualLink(VisualId::Apartment(id)),
             Mesh3d(meshes.add(Cuboid::new(APARTMENT_SIZE, APARTMENT_SIZE, APARTMENT_SIZE))),
             MeshMaterial3d(materials.add(apartment_color)),
-            Transform::from_translation(Vec3::new(pos.x, APARTMENT_SIZE / 2.0, pos.z)),
+            Transform::from_translation(Vec3::new(pos.x, pos.y + APARTMENT_SIZE / 2.0, pos.z)),
         ))
         .id();
     mappings.apartments.insert(id, entity);
@@ -360,41 +338,27 @@ pub fn spawn_apartment_visual(
         .spawn((
             DemandIndicator,
             Mesh3d(meshes.add(Sphere::new(0.2))),
-            MeshMaterial3d(materials.add(Color::srgb(0.0, 1.0, 0.0))),
+            MeshMaterial3d(materials.add(demand_indicator_color(busy))),
             Transform::from_translation(Vec3::new(0.0, 1.2, 0.0)),
         ))
         .id();
     commands.entity(entity).add_child(indicator);
 }
 
-fn spawn_factories(
-    commands: &mut Commands,
-    meshes: &mut ResMut<Assets<Mesh>>,
-    materials: &mut ResMut<Assets<StandardMaterial>>,
-    world: &crate::simulation::SimWorld,
-    mappings: &mut ResMut<EntityMappings>,
-) {
-    for (id, factory) in &world.factories {
-        if let Some(intersection) = world.intersections.get(&factory.intersection_id) {
-            spawn_factory_visual(
-                commands,
-                meshes,
-                materials,
-                *id,
-                &intersection.position,
-                mappings,
-            );
-        }
-    }
-}
-
-/// Spawn a single factory visual
+/// Spawn a single factory visual with a delivery indicator per stockpile slot
+/// and a bar showing progress toward the next delivery
+#[allow(clippy::too_many_arguments)]
 pub fn spawn_factory_visual(
     commands: &mut Commands,
     meshes: &mut ResMut<Assets<Mesh>>,
     materials: &mut ResMut<Assets<StandardMaterial>>,
     id: FactoryId,
     pos: &Position,
+    busy: bool,
+    deliveries_ready: u32,
+    max_deliveries: u32,
+    production_progress: f32,
+    tint: Option<Color>,
     mappings: &mut ResMut<EntityMappings>,
 ) {
     const FACTORY_SIZE: f32 = 1.5;
@@ -402,15 +366,15 @@ pub fn spawn_factory_visual(
     const DELIVERY_INDICATOR_X_OFFSET: f32 = 0.9;
     const DELIVERY_INDICATOR_BASE_Y: f32 = 0.3;
     const DELIVERY_INDICATOR_Y_SPACING: f32 = 0.4;
-    let factory_color = Color::srgb(0.5, 0.5, 0.7);
+    let factory_color = tint.unwrap_or(Color::srgb(0.5, 0.5, 0.7));
 
     let entity = commands
         .spawn((
             SimSynced,
-            FactoryLink(id),
+            VisualLink(VisualId::Factory(id)),
             Mesh3d(meshes.add(Cuboid::new(FACTORY_SIZE, FACTORY_SIZE, FACTORY_SIZE))),
             MeshMaterial3d(materials.add(factory_color)),
-            Transform::from_translation(Vec3::new(pos.x, FACTORY_SIZE / 2.0, pos.z)),
+            Transform::from_translation(Vec3::new(pos.x, pos.y + FACTORY_SIZE / 2.0, pos.z)),
         ))
         .id();
     mappings.factories.insert(id, entity);
@@ -420,19 +384,19 @@ pub fn spawn_factory_visual(
         .spawn((
             DemandIndicator,
             Mesh3d(meshes.add(Sphere::new(0.25))),
-            MeshMaterial3d(materials.add(Color::srgb(0.0, 1.0, 0.0))),
+            MeshMaterial3d(materials.add(demand_indicator_color(busy))),
             Transform::from_translation(Vec3::new(0.0, 1.5, 0.0)),
         ))
         .id();
     commands.entity(entity).add_child(indicator);
 
-    // Add delivery count indicators (side spheres - max 2)
-    for i in 0..2 {
+    // Add delivery count indicators (side spheres)
+    for i in 0..max_deliveries {
         let delivery_indicator = commands
             .spawn((
                 DeliveryIndicator,
                 Mesh3d(meshes.add(Sphere::new(DELIVERY_INDICATOR_RADIUS))),
-                MeshMaterial3d(materials.add(Color::srgb(0.3, 0.3, 0.3))), // Dark gray by default
+                MeshMaterial3d(materials.add(delivery_indicator_color(i, deliveries_ready))),
                 Transform::from_translation(Vec3::new(
                     DELIVERY_INDICATOR_X_OFFSET,
                     DELIVERY_INDICATOR_BASE_Y + i as f32 * DELIVERY_INDICATOR_Y_SPACING,
@@ -442,27 +406,17 @@ pub fn spawn_factory_visual(
             .id();
         commands.entity(entity).add_child(delivery_indicator);
     }
-}
 
-fn spawn_shops(
-    commands: &mut Commands,
-    meshes: &mut ResMut<Assets<Mesh>>,
-    materials: &mut ResMut<Assets<StandardMaterial>>,
-    world: &crate::simulation::SimWorld,
-    mappings: &mut ResMut<EntityMappings>,
-) {
-    for (id, shop) in &world.shops {
-        if let Some(intersection) = world.intersections.get(&shop.intersection_id) {
-            spawn_shop_visual(
-                commands,
-                meshes,
-                materials,
-                *id,
-                &intersection.position,
-                mappings,
-            );
-        }
-    }
+    // Add production bar (front face), scaled as shifts are worked
+    let production_bar = commands
+        .spawn((
+            ProductionBar,
+            Mesh3d(meshes.add(Cuboid::new(PRODUCTION_BAR_LENGTH, 0.12, 0.05))),
+            MeshMaterial3d(materials.add(Color::srgb(0.2, 0.6, 1.0))),
+            production_bar_transform(production_progress),
+        ))
+        .id();
+    commands.entity(entity).add_child(production_bar);
 }
 
 /// Spawn a single shop visual
@@ -472,30 +426,198 @@ pub fn spawn_shop_visual(
     materials: &mut ResMut<Assets<StandardMaterial>>,
     id: ShopId,
     pos: &Position,
+    tint: Option<Color>,
     mappings: &mut ResMut<EntityMappings>,
 ) {
     const SHOP_SIZE: f32 = 1.2;
-    let shop_color = Color::srgb(0.8, 0.4, 0.6);
+    let shop_color = tint.unwrap_or(Color::srgb(0.8, 0.4, 0.6));
 
     let entity = commands
         .spawn((
             SimSynced,
-            ShopLink(id),
+            VisualLink(VisualId::Shop(id)),
             Mesh3d(meshes.add(Cuboid::new(SHOP_SIZE, SHOP_SIZE, SHOP_SIZE))),
             MeshMaterial3d(materials.add(shop_color)),
-            Transform::from_translation(Vec3::new(pos.x, SHOP_SIZE / 2.0, pos.z)),
+            Transform::from_translation(Vec3::new(pos.x, pos.y + SHOP_SIZE / 2.0, pos.z)),
         ))
         .id();
     mappings.shops.insert(id, entity);
 
-    // Add demand indicator
+    // Add demand indicator; shops only receive deliveries, so it stays green
     let indicator = commands
         .spawn((
             DemandIndicator,
             Mesh3d(meshes.add(Sphere::new(0.22))),
-            MeshMaterial3d(materials.add(Color::srgb(0.0, 1.0, 0.0))),
+            MeshMaterial3d(materials.add(demand_indicator_color(false))),
             Transform::from_translation(Vec3::new(0.0, 1.3, 0.0)),
         ))
         .id();
     commands.entity(entity).add_child(indicator);
 }
+
+/// Spawn a single school, hospital or depot visual
+///
+/// Schools are a wide yellow block; hospitals are a tall white block with a
+/// red cross on the roof; depots are a low orange block.
+pub fn spawn_special_visual(
+    commands: &mut Commands,
+    meshes: &mut ResMut<Assets<Mesh>>,
+    materials: &mut ResMut<Assets<StandardMaterial>>,
+    id: SpecialBuildingId,
+    kind: SpecialKind,
+    pos: &Position,
+    mappings: &mut ResMut<EntityMappings>,
+) {
+    let (size, color) = match kind {
+        SpecialKind::School => (Vec3::new(2.0, 1.0, 1.4), Color::srgb(0.9, 0.75, 0.2)),
+        SpecialKind::Hospital => (Vec3::new(1.6, 1.8, 1.6), Color::srgb(0.95, 0.95, 0.95)),
+        SpecialKind::Depot => (Vec3::new(1.8, 0.8, 1.8), Color::srgb(0.85, 0.5, 0.15)),
+        SpecialKind::Terminal => (Vec3::new(3.0, 0.6, 1.4), Color::srgb(0.45, 0.35, 0.3)),
+    };
+
+    let entity = commands
+        .spawn((
+            SimSynced,
+            VisualLink(VisualId::Special(id)),
+            Mesh3d(meshes.add(Cuboid::from_size(size))),
+            MeshMaterial3d(materials.add(color)),
+            Transform::from_translation(Vec3::new(pos.x, pos.y + size.y / 2.0, pos.z)),
+        ))
+        .id();
+    mappings.special_buildings.insert(id, entity);
+
+    if kind == SpecialKind::Hospital {
+        let cross_material = materials.add(Color::srgb(0.9, 0.1, 0.1));
+        for cross_size in [Vec3::new(0.9, 0.05, 0.3), Vec3::new(0.3, 0.05, 0.9)] {
+            let arm = commands
+                .spawn((
+                    Mesh3d(meshes.add(Cuboid::from_size(cross_size))),
+                    MeshMaterial3d(cross_material.clone()),
+                    Transform::from_translation(Vec3::new(0.0, size.y / 2.0 + 0.025, 0.0)),
+                ))
+                .id();
+            commands.entity(entity).add_child(arm);
+        }
+    }
+}
+
+/// Spawn a tree, or a park lawn with a tree in the middle
+pub fn spawn_decoration_visual(
+    commands: &mut Commands,
+    meshes: &mut ResMut<Assets<Mesh>>,
+    materials: &mut ResMut<Assets<StandardMaterial>>,
+    id: DecorationId,
+    kind: DecorationKind,
+    pos: &Position,
+    mappings: &mut ResMut<EntityMappings>,
+) {
+    const TRUNK_HEIGHT: f32 = 0.6;
+    const LAWN_SIZE: f32 = 5.0;
+
+    let entity = commands
+        .spawn((
+            SimSynced,
+            VisualLink(VisualId::Decoration(id)),
+            Mesh3d(meshes.add(Cylinder::new(0.1, TRUNK_HEIGHT))),
+            MeshMaterial3d(materials.add(Color::srgb(0.4, 0.28, 0.15))),
+            Transform::from_translation(Vec3::new(pos.x, pos.y + TRUNK_HEIGHT / 2.0, pos.z)),
+        ))
+        .id();
+    mappings.decorations.insert(id, entity);
+
+    let crown = commands
+        .spawn((
+            Mesh3d(meshes.add(Sphere::new(0.5))),
+            MeshMaterial3d(materials.add(Color::srgb(0.15, 0.5, 0.2))),
+            Transform::from_translation(Vec3::new(0.0, TRUNK_HEIGHT / 2.0 + 0.3, 0.0)),
+        ))
+        .id();
+    commands.entity(entity).add_child(crown);
+
+    if kind == DecorationKind::Park {
+        let lawn = commands
+            .spawn((
+                Mesh3d(meshes.add(Cuboid::new(LAWN_SIZE, 0.02, LAWN_SIZE))),
+                MeshMaterial3d(materials.add(Color::srgb(0.4, 0.7, 0.35))),
+                Transform::from_translation(Vec3::new(0.0, -TRUNK_HEIGHT / 2.0 + 0.01, 0.0)),
+            ))
+            .id();
+        commands.entity(entity).add_child(lawn);
+    }
+}
+
+/// Height of a vehicle's center above the ground
+pub fn vehicle_height(vehicle_type: VehicleType) -> f32 {
+    match vehicle_type {
+        VehicleType::Car => 0.3,
+        VehicleType::Truck => 0.4,
+        VehicleType::Ambulance => 0.35,
+        VehicleType::TowTruck => 0.4,
+        VehicleType::Van => 0.35,
+    }
+}
+
+/// Spawn a single car, truck, ambulance, tow truck or van visual
+///
+/// `tint` and `size` (width, height, length) replace the standard look for
+/// content pack vehicles. Vehicles with the standard look are swapped for
+/// their type's model once it has loaded.
+#[allow(clippy::too_many_arguments)]
+pub fn spawn_vehicle_visual(
+    commands: &mut Commands,
+    meshes: &mut ResMut<Assets<Mesh>>,
+    materials: &mut ResMut<Assets<StandardMaterial>>,
+    id: CarId,
+    vehicle_type: VehicleType,
+    pos: &Position,
+    angle: f32,
+    tint: Option<Color>,
+    size: Option<[f32; 3]>,
+    mappings: &mut ResMut<EntityMappings>,
+) {
+    let (width, height, length, color) = match vehicle_type {
+        VehicleType::Car => (0.3, 0.2, CAR_LENGTH, Color::srgb(0.8, 0.2, 0.2)),
+        VehicleType::Truck => (0.4, 0.35, TRUCK_LENGTH, Color::srgb(0.2, 0.4, 0.8)),
+        VehicleType::Ambulance => (0.35, 0.3, AMBULANCE_LENGTH, Color::srgb(0.95, 0.95, 0.95)),
+        VehicleType::TowTruck => (0.4, 0.35, TRUCK_LENGTH, Color::srgb(1.0, 0.6, 0.1)),
+        VehicleType::Van => (0.35, 0.3, CAR_LENGTH, Color::srgb(0.55, 0.4, 0.3)),
+    };
+    let standard_look = tint.is_none() && size.is_none();
+    let [width, height, length] = size.unwrap_or([width, height, length]);
+    let translation = Vec3::new(pos.x, pos.y + vehicle_height(vehicle_type), pos.z);
+    let radius = wheel_radius(vehicle_type, height);
+
+    let wheel_mesh = meshes.add(Cylinder::new(radius, 0.05));
+    let wheel_material = materials.add(Color::srgb(0.1, 0.1, 0.1));
+    let mut entity = commands.spawn((
+        SimSynced,
+        VisualLink(VisualId::Vehicle(id)),
+        VehicleBody { length },
+        WheelRoll {
+            radius,
+            angle: 0.0,
+            last_position: translation,
+        },
+        Mesh3d(meshes.add(Cuboid::new(width, height, length))),
+        MeshMaterial3d(materials.add(tint.unwrap_or(color))),
+        Transform::from_translation(translation).with_rotation(Quat::from_rotation_y(angle)),
+    ));
+    if standard_look {
+        entity.insert(AwaitingModel(vehicle_type));
+    }
+    // A wheel at each corner, resting on the ground
+    let axle = length / 2.0 - radius * 1.2;
+    let ground = radius - vehicle_height(vehicle_type);
+    entity.with_children(|parent| {
+        for (side, end) in [(-1.0, -1.0), (-1.0, 1.0), (1.0, -1.0), (1.0, 1.0)] {
+            parent.spawn((
+                Wheel,
+                Mesh3d(wheel_mesh.clone()),
+                MeshMaterial3d(wheel_material.clone()),
+                Transform::from_translation(Vec3::new(side * width / 2.0, ground, end * axle))
+                    .with_rotation(wheel_rotation(0.0)),
+            ));
+        }
+    });
+    mappings.cars.insert(id, entity.id());
+}
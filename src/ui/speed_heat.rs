@@ -0,0 +1,78 @@
+//! Vehicle speed heat overlay
+//!
+//! Outlines every vehicle in a color for how fast it is going compared with
+//! the speed it could drive its road at, from green at free flow to red when
+//! stopped. A run of red vehicles moving back through green traffic is a
+//! shockwave, and stop-and-go waves show as red and green bands. Where the
+//! road density shows how full a road is, this shows how its traffic flows.
+
+use bevy::prelude::*;
+
+use super::components::{SimWorldResource, VehicleBody, VisualLink};
+use crate::simulation::VisualId;
+
+/// How far the outline stands off the vehicle's body
+const OUTLINE_MARGIN: f32 = 0.08;
+
+/// Width and height of the outline, wide enough for any vehicle type
+const OUTLINE_SIZE: Vec2 = Vec2::new(0.5, 0.45);
+
+/// Settings for the speed heat overlay
+#[derive(Resource, Default)]
+pub struct SpeedHeatOverlay {
+    /// Whether the overlay is drawn
+    pub enabled: bool,
+}
+
+/// System to toggle the speed heat overlay (F2)
+pub fn handle_speed_heat_keyboard(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut overlay: ResMut<SpeedHeatOverlay>,
+) {
+    if keyboard.just_pressed(KeyCode::F2) {
+        overlay.enabled = !overlay.enabled;
+        info!(
+            "Speed heat overlay {}",
+            if overlay.enabled { "on" } else { "off" }
+        );
+    }
+}
+
+/// Color for a vehicle going at `share` of its free-flow speed
+fn speed_color(share: f32) -> Color {
+    let share = share.clamp(0.0, 1.0);
+    // Red through yellow to green
+    Color::srgb((2.0 * (1.0 - share)).min(1.0), (2.0 * share).min(1.0), 0.1)
+}
+
+/// System to outline each vehicle colored by its speed
+pub fn draw_speed_heat(
+    overlay: Res<SpeedHeatOverlay>,
+    sim_world: Res<SimWorldResource>,
+    vehicles: Query<(&Transform, &VehicleBody, &VisualLink)>,
+    mut gizmos: Gizmos,
+) {
+    if !overlay.enabled {
+        return;
+    }
+    let world = &sim_world.0;
+    for (transform, body, link) in vehicles.iter() {
+        let VisualId::Vehicle(car_id) = link.0 else {
+            continue;
+        };
+        let Some(share) = world
+            .cars
+            .get(&car_id)
+            .and_then(|car| car.speed_share(&world.road_network))
+        else {
+            continue;
+        };
+        let size = OUTLINE_SIZE.extend(body.length) + Vec3::splat(OUTLINE_MARGIN);
+        gizmos.cuboid(
+            Transform::from_translation(transform.translation)
+                .with_rotation(transform.rotation)
+                .with_scale(size),
+            speed_color(share),
+        );
+    }
+}
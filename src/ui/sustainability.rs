@@ -0,0 +1,104 @@
+//! Sustainability panel
+//!
+//! Press F6 to show the fuel burned and CO2 given off by the city's
+//! completed trips, how much of their time vehicles spent idling, and the
+//! CO2 per trip of each purpose and vehicle type, for judging a layout by
+//! how much it makes vehicles stop and detour.
+
+use bevy::prelude::*;
+
+use super::components::SimWorldResource;
+
+/// Marker for the sustainability panel
+#[derive(Component)]
+pub struct SustainabilityPanel;
+
+/// Marker for the sustainability panel's text
+#[derive(Component)]
+pub struct SustainabilityPanelText;
+
+/// System to create the (hidden) sustainability panel
+pub fn setup_sustainability_ui(mut commands: Commands) {
+    commands
+        .spawn((
+            SustainabilityPanel,
+            Node {
+                position_type: PositionType::Absolute,
+                bottom: Val::Px(120.0),
+                left: Val::Px(10.0),
+                padding: UiRect::all(Val::Px(10.0)),
+                ..default()
+            },
+            BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.8)),
+            Visibility::Hidden,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                SustainabilityPanelText,
+                Text::new(""),
+                TextFont {
+                    font_size: 14.0,
+                    ..default()
+                },
+                TextColor(Color::WHITE),
+            ));
+        });
+}
+
+/// System to toggle the sustainability panel (F6) and keep it current while
+/// it is shown
+pub fn update_sustainability_panel(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    sim_world: Res<SimWorldResource>,
+    mut panel_query: Query<&mut Visibility, With<SustainabilityPanel>>,
+    mut text_query: Query<&mut Text, With<SustainabilityPanelText>>,
+) {
+    let mut shown = false;
+    for mut visibility in panel_query.iter_mut() {
+        if keyboard.just_pressed(KeyCode::F6) {
+            *visibility = match *visibility {
+                Visibility::Hidden => Visibility::Inherited,
+                _ => Visibility::Hidden,
+            };
+        }
+        shown |= *visibility != Visibility::Hidden;
+    }
+    if !shown {
+        return;
+    }
+
+    let city = sim_world.0.emissions();
+    let mut description = String::from("Emissions");
+    if city.trips == 0 {
+        description.push_str("\nNo trips completed yet");
+    } else {
+        let idle = city
+            .idle_share()
+            .map_or("-".to_string(), |share| format!("{:.0}%", share * 100.0));
+        description.push_str(&format!(
+            "\n{:.1} kg CO2 from {:.1} L of fuel over {} trips",
+            city.co2_kg, city.fuel_litres, city.trips
+        ));
+        description.push_str(&format!(
+            "\n{:.2} kg CO2 per trip, {} of the time idling",
+            city.co2_per_trip(),
+            idle
+        ));
+        for row in sim_world.0.trip_statistics() {
+            if row.stats.trips == 0 {
+                continue;
+            }
+            description.push_str(&format!(
+                "\n{} ({:?}): {:.2} kg CO2 per trip",
+                row.purpose.label(),
+                row.vehicle_type,
+                row.stats.co2_kg / row.stats.trips as f32
+            ));
+        }
+    }
+    for mut text in text_query.iter_mut() {
+        if **text != description {
+            **text = description.clone();
+        }
+    }
+}
@@ -1,154 +1,144 @@
-//! Systems for syncing Bevy entities with simulation state
+//! Systems for ticking the simulation and keeping the HUD and road arrows
+//! current
+//!
+//! Simulation objects are drawn by the renderer in `renderer.rs`.
+
+use std::time::Duration;
 
 use bevy::prelude::*;
+use bevy::winit::{UpdateMode, WinitSettings};
 
+use super::assignment::TrafficModel;
+use super::autosave::Autosave;
 use super::components::{
-    CarLink, DeliveryIndicator, DemandIndicator, EntityMappings, FactoryLink, ApartmentLink, ShopLink,
-    SimSynced, SimWorldResource,
-};
-use crate::{
-    simulation::{CarId, VehicleType, GOAL_DELIVERIES, GOAL_MONEY},
-    ui::components::GlobalDemandText,
+    MainCamera, PendingCommands, RoadArrowDensity, RoadVisual, SimWorldResource,
 };
+use super::multiplayer::MultiplayerSession;
+use super::score::RunScores;
+use super::spawner::{arrow_spacing_for_camera_height, build_arrow_mesh};
+use super::worlds::WorldSlots;
+use crate::simulation::SpeedGoalProgress;
+use crate::ui::components::GlobalDemandText;
 
 /// System to run simulation tick
-pub fn tick_simulation(time: Res<Time>, mut sim_world: ResMut<SimWorldResource>) {
-    sim_world.0.tick(time.delta_secs());
-}
-
-/// System to sync car visuals from simulation state
-pub fn sync_cars(
-    mut commands: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<StandardMaterial>>,
-    sim_world: Res<SimWorldResource>,
-    mut mappings: ResMut<EntityMappings>,
-    mut car_query: Query<(Entity, &CarLink, &mut Transform)>,
+///
+/// Applies the player's queued build commands first, recording them in the
+/// autosave if there is one and the live world is shown; the experimental
+/// world's commands aren't saved. In a multiplayer session
+/// the host orders everyone's commands into a turn instead, and a client sends
+/// its commands to the host and mirrors the host's turns rather than ticking
+/// on its own. Spectators play the host's turns back at real-time pace.
+/// Offline, the world doesn't tick while the macroscopic model is shown.
+pub fn tick_simulation(
+    time: Res<Time>,
+    mut sim_world: ResMut<SimWorldResource>,
+    mut pending: ResMut<PendingCommands>,
+    mut session: ResMut<MultiplayerSession>,
+    slots: Res<WorldSlots>,
+    autosave: Option<ResMut<Autosave>>,
+    model: Res<TrafficModel>,
 ) {
-    let world = &sim_world.0;
-    const CAR_LENGTH: f32 = 0.5;
-    const TRUCK_LENGTH: f32 = 0.8;
-
-    // Update existing cars and track which ones still exist
-    let mut existing_car_ids: std::collections::HashSet<CarId> = std::collections::HashSet::new();
-
-    for (entity, link, mut transform) in car_query.iter_mut() {
-        if let Some(car) = world.cars.get(&link.0) {
-            existing_car_ids.insert(link.0);
-            let y_height = match car.vehicle_type {
-                VehicleType::Car => 0.3,
-                VehicleType::Truck => 0.4,
-            };
-            transform.translation = Vec3::new(car.position.x, y_height, car.position.z);
-            transform.rotation = Quat::from_rotation_y(car.angle);
-        } else {
-            // Car no longer exists in simulation, despawn
-            commands.entity(entity).despawn();
-            mappings.cars.remove(&link.0);
+    let commands = std::mem::take(&mut pending.0);
+    let world = &mut sim_world.0;
+    match session.as_mut() {
+        MultiplayerSession::Offline => {
+            match autosave {
+                Some(mut autosave) if slots.showing_live() => {
+                    autosave.save.apply_commands(world, &commands)
+                }
+                _ => world.apply_commands(&commands),
+            }
+            // The macroscopic model stands in for the vehicles until F4
+            // switches back
+            if !model.macroscopic {
+                world.tick(time.delta_secs());
+            }
         }
-    }
-
-    // Spawn new cars/trucks
-    for (id, car) in &world.cars {
-        if !existing_car_ids.contains(id) {
-            let (width, height, length, color, y_height) = match car.vehicle_type {
-                VehicleType::Car => (0.3, 0.2, CAR_LENGTH, Color::srgb(0.8, 0.2, 0.2), 0.3),
-                VehicleType::Truck => (0.4, 0.35, TRUCK_LENGTH, Color::srgb(0.2, 0.4, 0.8), 0.4),
-            };
-
-            let entity = commands
-                .spawn((
-                    SimSynced,
-                    CarLink(*id),
-                    Mesh3d(meshes.add(Cuboid::new(width, height, length))),
-                    MeshMaterial3d(materials.add(color)),
-                    Transform::from_translation(Vec3::new(
-                        car.position.x,
-                        y_height,
-                        car.position.z,
-                    ))
-                    .with_rotation(Quat::from_rotation_y(car.angle)),
-                ))
-                .id();
-            mappings.cars.insert(*id, entity);
+        MultiplayerSession::Host(host) => {
+            host.step(world, time.delta_secs(), commands);
+        }
+        MultiplayerSession::Client(client) if client.is_spectator() => {
+            if let Err(e) = client.poll_paced(world, time.delta_secs()) {
+                warn!("{:#}; continuing offline", e);
+                *session = MultiplayerSession::Offline;
+            }
+        }
+        MultiplayerSession::Client(client) => {
+            let sent = commands
+                .into_iter()
+                .try_for_each(|command| client.send(command));
+            if let Err(e) = sent.and_then(|()| client.poll(world).map(|_| ())) {
+                warn!("{:#}; continuing offline", e);
+                *session = MultiplayerSession::Offline;
+            }
         }
     }
 }
 
-/// System to update factory demand indicators
-pub fn update_factory_indicators(
-    sim_world: Res<SimWorldResource>,
-    factory_query: Query<(&FactoryLink, &Children)>,
-    mut indicator_query: Query<&mut MeshMaterial3d<StandardMaterial>, With<DemandIndicator>>,
-    mut materials: ResMut<Assets<StandardMaterial>>,
+/// System to pick road arrow spacing from the camera's zoom level
+pub fn update_arrow_density(
+    camera_query: Query<&Transform, With<MainCamera>>,
+    mut density: ResMut<RoadArrowDensity>,
 ) {
-    for (link, children) in factory_query.iter() {
-        if let Some(factory) = sim_world.0.factories.get(&link.0) {
-            for child in children.iter() {
-                if let Ok(material_handle) = indicator_query.get_mut(child) {
-                    if let Some(material) = materials.get_mut(&material_handle.0) {
-                        // Red if truck is out (busy), green if truck is home (available)
-                        if factory.truck.is_some() {
-                            material.base_color = Color::srgb(1.0, 0.0, 0.0); // Red - busy
-                        } else {
-                            material.base_color = Color::srgb(0.0, 1.0, 0.0); // Green - available
-                        }
-                    }
-                }
-            }
-        }
+    let Ok(camera) = camera_query.single() else {
+        return;
+    };
+    let spacing = arrow_spacing_for_camera_height(camera.translation.y);
+    // Only write on bucket changes so change detection stays meaningful
+    if density.spacing != spacing {
+        density.spacing = spacing;
     }
 }
 
-/// System to update apartment demand indicators
-pub fn update_apartment_indicators(
-    sim_world: Res<SimWorldResource>,
-    apartment_query: Query<(&ApartmentLink, &Children)>,
-    mut indicator_query: Query<&mut MeshMaterial3d<StandardMaterial>, With<DemandIndicator>>,
-    mut materials: ResMut<Assets<StandardMaterial>>,
+/// System to rebuild road arrows at the new spacing when the camera zooms
+pub fn rebuild_road_arrows(
+    density: Res<RoadArrowDensity>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut road_query: Query<&mut RoadVisual>,
 ) {
-    for (link, children) in apartment_query.iter() {
-        if let Some(apartment) = sim_world.0.apartments.get(&link.0) {
-            for child in children.iter() {
-                if let Ok(material_handle) = indicator_query.get_mut(child) {
-                    if let Some(material) = materials.get_mut(&material_handle.0) {
-                        // Red if any car is out (busy), green if all cars are home (available)
-                        if apartment.cars.iter().any(|c| c.is_some()) {
-                            material.base_color = Color::srgb(1.0, 0.0, 0.0); // Red - busy
-                        } else {
-                            material.base_color = Color::srgb(0.0, 1.0, 0.0); // Green - available
-                        }
-                    }
-                }
-            }
+    if !density.is_changed() {
+        return;
+    }
+    for mut visual in road_query.iter_mut() {
+        if visual.arrow_spacing == density.spacing {
+            continue;
+        }
+        if let Some(mesh) = meshes.get_mut(&visual.arrow_mesh) {
+            *mesh = build_arrow_mesh(
+                visual.start.distance(&visual.end),
+                visual.two_way,
+                visual.driving_side,
+                density.spacing,
+            );
         }
+        visual.arrow_spacing = density.spacing;
     }
 }
 
-/// System to update shop demand indicators
-pub fn update_shop_indicators(
-    sim_world: Res<SimWorldResource>,
-    shop_query: Query<(&ShopLink, &Children)>,
-    mut indicator_query: Query<&mut MeshMaterial3d<StandardMaterial>, With<DemandIndicator>>,
-    mut materials: ResMut<Assets<StandardMaterial>>,
-) {
-    // Shops are passive - just show green always (they just receive deliveries)
-    for (link, children) in shop_query.iter() {
-        if sim_world.0.shops.get(&link.0).is_some() {
-            for child in children.iter() {
-                if let Ok(material_handle) = indicator_query.get_mut(child) {
-                    if let Some(material) = materials.get_mut(&material_handle.0) {
-                        material.base_color = Color::srgb(0.0, 1.0, 0.0); // Green - always ready
-                    }
-                }
-            }
-        }
+/// How often the app wakes while unfocused over an idle world
+const IDLE_REDRAW_INTERVAL: Duration = Duration::from_millis(500);
+
+/// System to let the app sleep in the background while the world is idle
+///
+/// Unfocused, the app only wakes for input or every
+/// [`IDLE_REDRAW_INTERVAL`] while there's nothing to simulate, and runs
+/// continuously again as soon as there is. Focused, it always runs
+/// continuously.
+pub fn pace_idle_app(sim_world: Res<SimWorldResource>, mut winit: ResMut<WinitSettings>) {
+    let unfocused_mode = if sim_world.0.is_idle() {
+        UpdateMode::reactive_low_power(IDLE_REDRAW_INTERVAL)
+    } else {
+        UpdateMode::Continuous
+    };
+    if winit.unfocused_mode != unfocused_mode {
+        winit.unfocused_mode = unfocused_mode;
     }
 }
 
 /// System to update global demand text in the UI toolbar
 pub fn update_global_demand_text(
     sim_world: Res<SimWorldResource>,
+    scores: Res<RunScores>,
     mut text_query: Query<(&GlobalDemandText, &mut Text)>,
 ) {
     let demand = sim_world.0.calculate_global_demand();
@@ -156,19 +146,49 @@ pub fn update_global_demand_text(
     for (demand_type, mut text) in text_query.iter_mut() {
         match demand_type {
             GlobalDemandText::FactoriesWaiting => {
-                **text = format!(
-                    "Factories Busy: {}/{}",
-                    demand.factories_waiting, demand.total_factories
-                );
+                **text = if sim_world.0.factory_supply_required() {
+                    let unsupplied = sim_world
+                        .0
+                        .factories
+                        .values()
+                        .filter(|factory| factory.materials < factory.materials_per_delivery)
+                        .count();
+                    format!(
+                        "Factories Busy: {}/{} | Out of materials: {}",
+                        demand.factories_waiting, demand.total_factories, unsupplied
+                    )
+                } else {
+                    format!(
+                        "Factories Busy: {}/{}",
+                        demand.factories_waiting, demand.total_factories
+                    )
+                };
             }
             GlobalDemandText::ShopsWaiting => {
-                **text = format!("Shops: {}", demand.total_shops);
+                **text = if sim_world.0.demand_ramp().is_some() {
+                    format!(
+                        "Shops: {} | Demand x{:.2}, {} open orders",
+                        demand.total_shops,
+                        sim_world.0.demand_level(),
+                        sim_world.0.open_orders()
+                    )
+                } else {
+                    format!("Shops: {}", demand.total_shops)
+                };
             }
             GlobalDemandText::ApartmentsWaiting => {
-                **text = format!(
-                    "Apartments Busy: {}/{}",
-                    demand.apartments_waiting, demand.total_apartments
-                );
+                let waiting = sim_world.0.trips_waiting_for_connection();
+                **text = if waiting > 0 {
+                    format!(
+                        "Apartments Busy: {}/{} | {} trips waiting for connection",
+                        demand.apartments_waiting, demand.total_apartments, waiting
+                    )
+                } else {
+                    format!(
+                        "Apartments Busy: {}/{}",
+                        demand.apartments_waiting, demand.total_apartments
+                    )
+                };
             }
             GlobalDemandText::Money => {
                 if let Some(game_state) = &sim_world.0.game_state {
@@ -177,6 +197,35 @@ pub fn update_global_demand_text(
                     **text = "Money: N/A".to_string();
                 }
             }
+            GlobalDemandText::Insurance => {
+                if let Some(game_state) = &sim_world.0.game_state {
+                    **text = if game_state.insured {
+                        format!(
+                            "Insurance: on, ${} due in {:.0}s [I] | Incidents: {}",
+                            game_state.economy.insurance_premium,
+                            game_state.premium_due_in.max(0.0),
+                            game_state.incidents
+                        )
+                    } else {
+                        format!("Insurance: off [I] | Incidents: {}", game_state.incidents)
+                    };
+                } else {
+                    **text = "Insurance: N/A".to_string();
+                }
+            }
+            GlobalDemandText::Fleet => {
+                let wear = sim_world.0.average_fleet_wear();
+                let worn = sim_world.0.worn_vehicle_count();
+                **text = match &sim_world.0.game_state {
+                    Some(game_state) if worn > 0 => format!(
+                        "Fleet Wear: {:.0}% | Worn: {} [R: replace ${}]",
+                        wear * 100.0,
+                        worn,
+                        game_state.economy.cost_vehicle_replacement * worn as i32
+                    ),
+                    _ => format!("Fleet Wear: {:.0}%", wear * 100.0),
+                };
+            }
             GlobalDemandText::WorkerTrips => {
                 if let Some(game_state) = &sim_world.0.game_state {
                     **text = format!("Worker Trips: {}", game_state.worker_trips_completed);
@@ -187,8 +236,10 @@ pub fn update_global_demand_text(
             GlobalDemandText::ShopDeliveries => {
                 if let Some(game_state) = &sim_world.0.game_state {
                     **text = format!(
-                        "Shop Deliveries: {} / {}",
-                        game_state.shop_deliveries_completed, GOAL_DELIVERIES
+                        "Shop Deliveries: {} / {} | On time: {:.0}%",
+                        game_state.shop_deliveries_completed,
+                        game_state.economy.goal_deliveries,
+                        game_state.on_time_rate() * 100.0
                     );
                 } else {
                     **text = "Shop Deliveries: N/A".to_string();
@@ -196,12 +247,43 @@ pub fn update_global_demand_text(
             }
             GlobalDemandText::GoalStatus => {
                 if let Some(game_state) = &sim_world.0.game_state {
+                    let score = match &scores.summary {
+                        Some(summary) if scores.new_best => {
+                            format!(" Score: {} (new best!)", summary.score)
+                        }
+                        Some(summary) => match scores.best.best(&summary.scenario) {
+                            Some(best) => {
+                                format!(" Score: {} (best: {})", summary.score, best.score)
+                            }
+                            None => format!(" Score: {}", summary.score),
+                        },
+                        None => String::new(),
+                    };
                     if game_state.is_won {
-                        **text = "🎉 YOU WIN! Goal Complete! 🎉".to_string();
+                        **text = format!("🎉 YOU WIN! Goal Complete! 🎉{}", score);
+                    } else if game_state.is_lost && game_state.missed_speed_goal() {
+                        **text = format!("💀 RUSH HOUR GRIDLOCK - Game Over 💀{}", score);
+                    } else if game_state.is_lost && game_state.missed_service_level() {
+                        **text = format!("💀 TOO MANY LATE DELIVERIES - Game Over 💀{}", score);
                     } else if game_state.is_lost {
-                        **text = "💀 BANKRUPT - Game Over 💀".to_string();
+                        **text = format!("💀 BANKRUPT - Game Over 💀{}", score);
+                    } else if let Some(progress) = sim_world.0.speed_goal_progress() {
+                        **text = speed_goal_progress_text(&progress);
                     } else {
-                        **text = format!("Goal: {} deliveries OR ${}", GOAL_DELIVERIES, GOAL_MONEY);
+                        **text = format!(
+                            "Goal: {} deliveries OR ${}",
+                            game_state.economy.goal_deliveries, game_state.economy.goal_money
+                        );
+                        if let Some(goal) = &game_state.economy.speed_goal {
+                            text.push_str(&format!(
+                                ", and average speed {:.1}+ from {} to {} ({}/{} rush hours)",
+                                goal.min_average_speed,
+                                clock(goal.start_hour),
+                                clock(goal.end_hour),
+                                game_state.speed_goal.met.min(goal.rush_hours),
+                                goal.rush_hours
+                            ));
+                        }
                     }
                 } else {
                     **text = "Goal: N/A".to_string();
@@ -211,33 +293,27 @@ pub fn update_global_demand_text(
     }
 }
 
-/// System to update factory delivery indicators
-pub fn update_factory_delivery_indicators(
-    sim_world: Res<SimWorldResource>,
-    factory_query: Query<(&FactoryLink, &Children)>,
-    mut indicator_query: Query<&mut MeshMaterial3d<StandardMaterial>, With<DeliveryIndicator>>,
-    mut materials: ResMut<Assets<StandardMaterial>>,
-) {
-    const DELIVERY_INDICATOR_ACTIVE_COLOR: Color = Color::srgb(1.0, 0.8, 0.0); // Gold/yellow
-    const DELIVERY_INDICATOR_EMPTY_COLOR: Color = Color::srgb(0.3, 0.3, 0.3); // Dark gray
-
-    for (link, children) in factory_query.iter() {
-        if let Some(factory) = sim_world.0.factories.get(&link.0) {
-            // Iterate over delivery indicator children (query filters for DeliveryIndicator component)
-            let mut indicator_index = 0;
-            for child in children.iter() {
-                if let Ok(mut material_handle) = indicator_query.get_mut(child) {
-                    if let Some(material) = materials.get_mut(&material_handle.0) {
-                        // Light up indicators based on deliveries_ready count
-                        if indicator_index < factory.deliveries_ready as usize {
-                            material.base_color = DELIVERY_INDICATOR_ACTIVE_COLOR;
-                        } else {
-                            material.base_color = DELIVERY_INDICATOR_EMPTY_COLOR;
-                        }
-                        indicator_index += 1;
-                    }
-                }
-            }
+/// Live progress through the speed goal's rush hour
+fn speed_goal_progress_text(progress: &SpeedGoalProgress) -> String {
+    let average = match progress.average {
+        Some(average) if average >= progress.goal.min_average_speed => {
+            format!("{:.1} ✔", average)
         }
-    }
+        Some(average) => format!("{:.1} ✘", average),
+        None => "-".to_string(),
+    };
+    format!(
+        "Rush hour {}-{}: average speed {} (goal {:.1}+) | {:.0}% through",
+        clock(progress.goal.start_hour),
+        clock(progress.goal.end_hour),
+        average,
+        progress.goal.min_average_speed,
+        progress.elapsed * 100.0
+    )
+}
+
+/// An hour of the day as a 24-hour clock time
+fn clock(hour: f32) -> String {
+    let minutes = (hour * 60.0).round() as u32 % (24 * 60);
+    format!("{}:{:02}", minutes / 60, minutes % 60)
 }
@@ -0,0 +1,100 @@
+//! Terrain, water and ferries
+//!
+//! Hills are drawn as a mesh following the heightmap in place of the flat
+//! ground plane. Water is drawn once at startup as flat blue patches over
+//! the ground.
+//! Ferries are drawn every frame as a boat outline at their landing or out
+//! on the water, with a mark for each vehicle on board.
+
+use bevy::asset::RenderAssetUsages;
+use bevy::mesh::{Indices, PrimitiveTopology};
+use bevy::prelude::*;
+
+use super::components::SimWorldResource;
+use crate::simulation::{FerryState, Heightmap, FERRY_CAPACITY};
+
+/// Height of the water above the ground at its middle, below the roads
+const WATER_HEIGHT: f32 = 0.005;
+
+/// Size of a ferry's deck (width, length)
+const FERRY_SIZE: Vec2 = Vec2::new(1.2, 2.6);
+
+/// Build the ground mesh for a heightmap, one vertex per sample
+pub fn build_terrain_mesh(heightmap: &Heightmap) -> Mesh {
+    let (columns, rows) = (heightmap.columns, heightmap.rows);
+    let mut positions = Vec::with_capacity(columns * rows);
+    for row in 0..rows {
+        for col in 0..columns {
+            positions.push([
+                heightmap.min_x + col as f32 * heightmap.cell_size,
+                heightmap.sample(row, col),
+                heightmap.min_z + row as f32 * heightmap.cell_size,
+            ]);
+        }
+    }
+    // Two triangles per cell, wound to face up
+    let mut indices = Vec::with_capacity((columns - 1) * (rows - 1) * 6);
+    for row in 0..rows - 1 {
+        for col in 0..columns - 1 {
+            let corner = (row * columns + col) as u32;
+            let (right, below) = (corner + 1, corner + columns as u32);
+            indices.extend([corner, below, right, right, below, below + 1]);
+        }
+    }
+    Mesh::new(PrimitiveTopology::TriangleList, RenderAssetUsages::default())
+        .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+        .with_inserted_indices(Indices::U32(indices))
+        .with_computed_normals()
+}
+
+/// System to draw the world's water
+pub fn setup_water(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    sim_world: Res<SimWorldResource>,
+) {
+    let material = materials.add(Color::srgb(0.15, 0.35, 0.6));
+    for region in &sim_world.0.water {
+        let center = region.center();
+        let size = Vec2::new(region.max_x - region.min_x, region.max_z - region.min_z);
+        commands.spawn((
+            Mesh3d(meshes.add(Plane3d::default().mesh().size(size.x, size.y))),
+            MeshMaterial3d(material.clone()),
+            Transform::from_xyz(
+                center.x,
+                sim_world.0.ground_height(center.x, center.z) + WATER_HEIGHT,
+                center.z,
+            ),
+        ));
+    }
+}
+
+/// System to draw each ferry, green while it is loading
+pub fn draw_ferries(sim_world: Res<SimWorldResource>, mut gizmos: Gizmos) {
+    let road_network = &sim_world.0.road_network;
+    for ferry in sim_world.0.ferries.values() {
+        let Some((position, angle)) = ferry.position(road_network) else {
+            continue;
+        };
+        let color = match ferry.state {
+            FerryState::Docked { .. } => Color::srgb(0.3, 0.9, 0.4),
+            FerryState::Sailing { .. } => Color::WHITE,
+        };
+        let rotation = Quat::from_rotation_y(angle) * Quat::from_rotation_arc(Vec3::Z, Vec3::Y);
+        let center = Vec3::new(position.x, position.y + 0.2, position.z);
+        gizmos.rect(Isometry3d::new(center, rotation), FERRY_SIZE, color);
+
+        // One notch along the deck per vehicle on board
+        let forward = Quat::from_rotation_y(angle) * Vec3::Z;
+        let spacing = FERRY_SIZE.y / FERRY_CAPACITY as f32;
+        for place in 0..ferry.aboard.len() {
+            let offset = (place as f32 + 0.5) * spacing - FERRY_SIZE.y / 2.0;
+            gizmos.sphere(
+                Isometry3d::from_translation(center + forward * offset),
+                0.15,
+                color,
+            );
+        }
+    }
+}
@@ -0,0 +1,86 @@
+//! Traffic volume overlay
+//!
+//! Colors every road by how many vehicles have driven onto it since the run
+//! began, from blue for quiet roads to red for the busiest one. Unlike the
+//! road density, which only shows the vehicles on a road right now, this
+//! shows which roads carry the load over time. Each direction of a two-way
+//! road is drawn over its own lane. When the game requires sensors, only
+//! roads with one are drawn.
+
+use bevy::prelude::*;
+
+use super::components::SimWorldResource;
+
+/// Height of the overlay above the road surface
+const OVERLAY_HEIGHT: f32 = 0.15;
+
+/// How far a two-way road's lanes are drawn from its center line
+const LANE_OFFSET: f32 = 0.5;
+
+/// Settings for the traffic volume overlay
+#[derive(Resource, Default)]
+pub struct TrafficVolumeOverlay {
+    /// Whether the overlay is drawn
+    pub enabled: bool,
+}
+
+/// System to toggle the traffic volume overlay (U)
+pub fn handle_traffic_volume_keyboard(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut overlay: ResMut<TrafficVolumeOverlay>,
+) {
+    if keyboard.just_pressed(KeyCode::KeyU) {
+        overlay.enabled = !overlay.enabled;
+        info!(
+            "Traffic volume overlay {}",
+            if overlay.enabled { "on" } else { "off" }
+        );
+    }
+}
+
+/// Color for a road's share of the busiest road's volume
+fn volume_color(share: f32) -> Color {
+    let share = share.clamp(0.0, 1.0);
+    Color::srgb(0.2 + 0.8 * share, 0.4 * (1.0 - share), 1.0 - share)
+}
+
+/// System to draw each road colored by its traffic volume
+pub fn draw_traffic_volume(
+    overlay: Res<TrafficVolumeOverlay>,
+    sim_world: Res<SimWorldResource>,
+    mut gizmos: Gizmos,
+) {
+    if !overlay.enabled {
+        return;
+    }
+    let world = &sim_world.0;
+    let network = &world.road_network;
+    let monitored = || {
+        network
+            .roads()
+            .values()
+            .filter(|road| world.road_is_monitored(road.id))
+    };
+    let max_volume = monitored()
+        .map(|road| network.traffic_volume(road.id))
+        .max()
+        .unwrap_or(0)
+        .max(1) as f32;
+
+    for road in monitored() {
+        let (Some(start), Some(end)) = (
+            network.get_intersection_position(road.start_intersection),
+            network.get_intersection_position(road.end_intersection),
+        ) else {
+            continue;
+        };
+        let lane = if road.is_two_way { LANE_OFFSET } else { 0.0 };
+        let offset = world.driving_side.lane_offset(start, end, lane);
+        let share = network.traffic_volume(road.id) as f32 / max_volume;
+        gizmos.line(
+            Vec3::new(start.x + offset.x, OVERLAY_HEIGHT, start.z + offset.z),
+            Vec3::new(end.x + offset.x, OVERLAY_HEIGHT, end.z + offset.z),
+            volume_color(share),
+        );
+    }
+}
@@ -0,0 +1,201 @@
+//! Vehicle trail visualization for debugging traffic flow
+//!
+//! When enabled, each vehicle leaves a fading line behind it covering the last
+//! few simulated seconds, which makes flow patterns and weaving easy to spot.
+//! Trails are drawn with gizmos, so no extra entities are spawned per sample.
+
+use bevy::prelude::*;
+use std::collections::{HashMap, VecDeque};
+
+use super::components::SimWorldResource;
+use crate::simulation::{CarId, VehicleType};
+
+/// Which vehicle types get a trail
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TrailFilter {
+    #[default]
+    All,
+    CarsOnly,
+    TrucksOnly,
+}
+
+impl TrailFilter {
+    /// Check whether a vehicle type passes the filter
+    pub fn includes(&self, vehicle_type: VehicleType) -> bool {
+        match self {
+            TrailFilter::All => true,
+            TrailFilter::CarsOnly => vehicle_type == VehicleType::Car,
+            TrailFilter::TrucksOnly => vehicle_type == VehicleType::Truck,
+        }
+    }
+
+    /// The next filter in the cycle All -> Cars -> Trucks -> All
+    pub fn next(&self) -> Self {
+        match self {
+            TrailFilter::All => TrailFilter::CarsOnly,
+            TrailFilter::CarsOnly => TrailFilter::TrucksOnly,
+            TrailFilter::TrucksOnly => TrailFilter::All,
+        }
+    }
+}
+
+/// Settings for the trail overlay
+#[derive(Resource)]
+pub struct TrailSettings {
+    /// Whether trails are recorded and drawn
+    pub enabled: bool,
+    /// Which vehicle types show trails
+    pub filter: TrailFilter,
+    /// How many simulated seconds of history each trail covers
+    pub duration_secs: f32,
+    /// Minimum simulated time between two recorded samples
+    pub sample_interval_secs: f32,
+    /// Upper bound on the number of trail points kept across all vehicles
+    pub max_points: usize,
+}
+
+impl Default for TrailSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            filter: TrailFilter::All,
+            duration_secs: 5.0,
+            sample_interval_secs: 0.1,
+            max_points: 5000,
+        }
+    }
+}
+
+/// A single vehicle's recorded trail
+struct Trail {
+    vehicle_type: VehicleType,
+    /// (simulation time, position) samples, oldest first
+    samples: VecDeque<(f32, Vec3)>,
+}
+
+/// Recorded trails for all vehicles
+///
+/// Trails outlive their vehicles until the last sample fades out, so a car
+/// that just arrived still shows where it came from.
+#[derive(Resource, Default)]
+pub struct VehicleTrails {
+    trails: HashMap<CarId, Trail>,
+    last_sample_time: f32,
+}
+
+impl VehicleTrails {
+    fn point_count(&self) -> usize {
+        self.trails.values().map(|t| t.samples.len()).sum()
+    }
+}
+
+/// System to toggle trails (T) and cycle the vehicle filter (Y)
+pub fn handle_trail_keyboard(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut settings: ResMut<TrailSettings>,
+    mut trails: ResMut<VehicleTrails>,
+) {
+    if keyboard.just_pressed(KeyCode::KeyT) {
+        settings.enabled = !settings.enabled;
+        if !settings.enabled {
+            trails.trails.clear();
+        }
+        info!(
+            "Vehicle trails {}",
+            if settings.enabled { "on" } else { "off" }
+        );
+    }
+    if keyboard.just_pressed(KeyCode::KeyY) {
+        settings.filter = settings.filter.next();
+        info!("Vehicle trail filter: {:?}", settings.filter);
+    }
+}
+
+/// System to sample vehicle positions into their trails
+pub fn record_vehicle_trails(
+    settings: Res<TrailSettings>,
+    sim_world: Res<SimWorldResource>,
+    mut trails: ResMut<VehicleTrails>,
+) {
+    if !settings.enabled {
+        return;
+    }
+
+    let world = &sim_world.0;
+    let now = world.time;
+
+    // Drop samples that have faded out, trails with nothing left and trails
+    // the filter now hides, so they don't eat into the point cap
+    let oldest_allowed = now - settings.duration_secs;
+    trails.trails.retain(|_, trail| {
+        if !settings.filter.includes(trail.vehicle_type) {
+            return false;
+        }
+        while trail
+            .samples
+            .front()
+            .is_some_and(|(time, _)| *time < oldest_allowed)
+        {
+            trail.samples.pop_front();
+        }
+        !trail.samples.is_empty()
+    });
+
+    if now - trails.last_sample_time < settings.sample_interval_secs {
+        return;
+    }
+    trails.last_sample_time = now;
+
+    // Respect the global point cap: skip sampling until old points fade
+    let mut budget = settings.max_points.saturating_sub(trails.point_count());
+
+    for (id, car) in &world.cars {
+        if budget == 0 {
+            break;
+        }
+        if !settings.filter.includes(car.vehicle_type) {
+            continue;
+        }
+        let trail = trails.trails.entry(*id).or_insert_with(|| Trail {
+            vehicle_type: car.vehicle_type,
+            samples: VecDeque::new(),
+        });
+        trail
+            .samples
+            .push_back((now, Vec3::new(car.position.x, 0.1, car.position.z)));
+        budget -= 1;
+    }
+}
+
+/// System to draw the recorded trails as fading lines
+pub fn draw_vehicle_trails(
+    settings: Res<TrailSettings>,
+    sim_world: Res<SimWorldResource>,
+    trails: Res<VehicleTrails>,
+    mut gizmos: Gizmos,
+) {
+    if !settings.enabled {
+        return;
+    }
+
+    let now = sim_world.0.time;
+
+    for trail in trails.trails.values() {
+        if !settings.filter.includes(trail.vehicle_type) {
+            continue;
+        }
+        let (red, green, blue) = match trail.vehicle_type {
+            VehicleType::Car => (1.0, 0.4, 0.4),
+            VehicleType::Truck => (0.4, 0.6, 1.0),
+            VehicleType::Ambulance => (1.0, 1.0, 1.0),
+            VehicleType::TowTruck => (1.0, 0.7, 0.2),
+            VehicleType::Van => (0.8, 0.6, 0.45),
+        };
+
+        for ((_, start), (time, end)) in trail.samples.iter().zip(trail.samples.iter().skip(1)) {
+            // Newer segments are more opaque
+            let age = ((now - time) / settings.duration_secs).clamp(0.0, 1.0);
+            gizmos.line(*start, *end, Color::srgba(red, green, blue, 1.0 - age));
+        }
+    }
+}
@@ -0,0 +1,167 @@
+//! Bus route drawing tool
+//!
+//! In bus route mode (;), clicking intersections lays out a route's stops
+//! in order; Backspace takes the last one back and Enter opens the route as
+//! a [`BuildCommand::BusRoute`], looping from the last stop to the first.
+//! Every route is drawn with its stops, and its bus with a notch per rider.
+
+use bevy::prelude::*;
+
+use super::components::{BuildingMode, BuildingState, PendingCommands, SimWorldResource};
+use crate::simulation::{
+    BuildCommand, BusState, IntersectionId, Position, BUS_CAPACITY, MIN_BUS_STOPS,
+};
+
+/// Colour of open routes and their buses
+const ROUTE_COLOR: Color = Color::srgb(0.95, 0.55, 0.1);
+
+/// Colour of the route being drawn
+const DRAWING_COLOR: Color = Color::srgb(1.0, 0.9, 0.2);
+
+/// Length and width of a bus
+const BUS_SIZE: Vec2 = Vec2::new(0.8, 2.0);
+
+/// Stops of the route being drawn
+#[derive(Resource, Default)]
+pub struct BusRouteDrawing {
+    pub stops: Vec<IntersectionId>,
+}
+
+/// System to add the intersection clicked to the route being drawn, in bus
+/// route mode
+pub fn handle_bus_route_click(
+    mouse_button: Res<ButtonInput<MouseButton>>,
+    building_state: Res<BuildingState>,
+    sim_world: Res<SimWorldResource>,
+    mut drawing: ResMut<BusRouteDrawing>,
+    interaction_query: Query<&Interaction, With<Button>>,
+) {
+    if building_state.mode != BuildingMode::BusRoute {
+        if !drawing.stops.is_empty() {
+            drawing.stops.clear();
+        }
+        return;
+    }
+    if !mouse_button.just_pressed(MouseButton::Left)
+        || interaction_query
+            .iter()
+            .any(|interaction| *interaction != Interaction::None)
+    {
+        return;
+    }
+    let Some(cursor) = building_state.cursor_position else {
+        return;
+    };
+
+    let network = &sim_world.0.road_network;
+    let clicked = network.find_closest_intersection(&cursor).filter(|id| {
+        network
+            .get_intersection_position(*id)
+            .is_some_and(|position| position.distance(&cursor) <= building_state.snap_distance)
+    });
+    if let Some(stop) = clicked {
+        if drawing.stops.last() != Some(&stop) {
+            drawing.stops.push(stop);
+        }
+    }
+}
+
+/// System to open the route being drawn (Enter) or take its last stop back
+/// (Backspace)
+pub fn handle_bus_route_keyboard(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    building_state: Res<BuildingState>,
+    mut drawing: ResMut<BusRouteDrawing>,
+    mut pending: ResMut<PendingCommands>,
+) {
+    if building_state.mode != BuildingMode::BusRoute {
+        return;
+    }
+    if keyboard.just_pressed(KeyCode::Backspace) {
+        drawing.stops.pop();
+    }
+    if keyboard.just_pressed(KeyCode::Enter) {
+        if drawing.stops.len() < MIN_BUS_STOPS {
+            info!("A bus route needs at least {} stops", MIN_BUS_STOPS);
+            return;
+        }
+        pending.0.push(BuildCommand::BusRoute {
+            stops: std::mem::take(&mut drawing.stops),
+        });
+    }
+}
+
+/// System to draw each bus route and its bus, and the route being drawn
+pub fn draw_bus_routes(
+    sim_world: Res<SimWorldResource>,
+    building_state: Res<BuildingState>,
+    drawing: Res<BusRouteDrawing>,
+    mut gizmos: Gizmos,
+) {
+    let network = &sim_world.0.road_network;
+    let at = |stop: &IntersectionId| {
+        network
+            .get_intersection_position(*stop)
+            .map(|position| Vec3::new(position.x, position.y + 0.15, position.z))
+    };
+
+    for route in sim_world.0.bus_routes().values() {
+        let stops: Vec<Vec3> = route.stops.iter().filter_map(at).collect();
+        for (index, stop) in stops.iter().enumerate() {
+            let next = stops[(index + 1) % stops.len()];
+            gizmos.line(*stop, next, ROUTE_COLOR.with_alpha(0.5));
+            gizmos.circle(
+                Isometry3d::new(*stop, Quat::from_rotation_arc(Vec3::Z, Vec3::Y)),
+                0.6,
+                ROUTE_COLOR,
+            );
+        }
+
+        // The bus faces the intersection it is making for, and carries one
+        // notch per rider
+        let bus = &route.bus;
+        let center = Vec3::new(bus.position.x, bus.position.y + 0.25, bus.position.z);
+        let heading = match &bus.state {
+            BusState::Driving { waypoints, .. } => waypoints.first().and_then(at),
+            BusState::AtStop { .. } => None,
+        }
+        .map(|target| target - center)
+        .filter(|direction| direction.xz().length() > 0.01)
+        .map_or(0.0, |direction| direction.x.atan2(direction.z));
+        let rotation = Quat::from_rotation_y(heading) * Quat::from_rotation_arc(Vec3::Z, Vec3::Y);
+        gizmos.rect(Isometry3d::new(center, rotation), BUS_SIZE, ROUTE_COLOR);
+        let forward = Quat::from_rotation_y(heading) * Vec3::Z;
+        let spacing = BUS_SIZE.y / BUS_CAPACITY as f32;
+        for place in 0..bus.riders.len() {
+            let offset = (place as f32 + 0.5) * spacing - BUS_SIZE.y / 2.0;
+            gizmos.sphere(
+                Isometry3d::from_translation(center + forward * offset),
+                0.08,
+                ROUTE_COLOR,
+            );
+        }
+    }
+
+    if building_state.mode != BuildingMode::BusRoute {
+        return;
+    }
+    let stops: Vec<Vec3> = drawing.stops.iter().filter_map(at).collect();
+    for pair in stops.windows(2) {
+        gizmos.line(pair[0], pair[1], DRAWING_COLOR);
+    }
+    for stop in &stops {
+        gizmos.circle(
+            Isometry3d::new(*stop, Quat::from_rotation_arc(Vec3::Z, Vec3::Y)),
+            0.6,
+            DRAWING_COLOR,
+        );
+    }
+    // The next leg runs to the cursor
+    let cursor = building_state
+        .snapped_position
+        .or(building_state.cursor_position)
+        .map(|Position { x, y, z }| Vec3::new(x, y + 0.15, z));
+    if let (Some(last), Some(cursor)) = (stops.last(), cursor) {
+        gizmos.line(*last, cursor, DRAWING_COLOR.with_alpha(0.5));
+    }
+}
@@ -0,0 +1,171 @@
+//! Trip breakdown panel
+//!
+//! Press J to show how many trips of each purpose and vehicle type have been
+//! completed and how long they took, for judging changes to the spawning
+//! rules while the city runs. Below them, the labor market: how many of
+//! today's worker shifts were worked or turned away, and the factories and
+//! houses with the lowest employment rates, where workers can't reach work.
+
+use bevy::prelude::*;
+
+use super::components::SimWorldResource;
+use crate::simulation::{LaborCounts, SimWorld, VehicleType};
+
+/// Factories and houses listed with the lowest employment
+const LOWEST_EMPLOYMENT_SHOWN: usize = 3;
+
+/// Marker for the trip breakdown panel
+#[derive(Component)]
+pub struct TripPanel;
+
+/// Marker for the trip breakdown panel's text
+#[derive(Component)]
+pub struct TripPanelText;
+
+/// System to create the (hidden) trip breakdown panel
+pub fn setup_trip_ui(mut commands: Commands) {
+    commands
+        .spawn((
+            TripPanel,
+            Node {
+                position_type: PositionType::Absolute,
+                bottom: Val::Px(120.0),
+                right: Val::Px(10.0),
+                padding: UiRect::all(Val::Px(10.0)),
+                ..default()
+            },
+            BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.8)),
+            Visibility::Hidden,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                TripPanelText,
+                Text::new(""),
+                TextFont {
+                    font_size: 14.0,
+                    ..default()
+                },
+                TextColor(Color::WHITE),
+            ));
+        });
+}
+
+/// System to toggle the trip breakdown panel (J) and keep it current while
+/// it is shown
+pub fn update_trip_panel(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    sim_world: Res<SimWorldResource>,
+    mut panel_query: Query<&mut Visibility, With<TripPanel>>,
+    mut text_query: Query<&mut Text, With<TripPanelText>>,
+) {
+    let mut shown = false;
+    for mut visibility in panel_query.iter_mut() {
+        if keyboard.just_pressed(KeyCode::KeyJ) {
+            *visibility = match *visibility {
+                Visibility::Hidden => Visibility::Inherited,
+                _ => Visibility::Hidden,
+            };
+        }
+        shown |= *visibility != Visibility::Hidden;
+    }
+    if !shown {
+        return;
+    }
+
+    let rows = sim_world.0.trip_statistics();
+    let mut description = String::from("Completed trips");
+    if rows.is_empty() {
+        description.push_str("\nNone yet");
+    }
+    for row in rows {
+        let vehicle = match row.vehicle_type {
+            VehicleType::Car => "car",
+            VehicleType::Truck => "truck",
+            VehicleType::Ambulance => "ambulance",
+            VehicleType::TowTruck => "tow truck",
+            VehicleType::Van => "van",
+        };
+        description.push_str(&format!(
+            "\n{} ({}): {} trips, avg {:.1}s, longest {:.1}s",
+            row.purpose.label(),
+            vehicle,
+            row.stats.trips,
+            row.stats.average_secs(),
+            row.stats.longest_secs
+        ));
+    }
+    let modes = sim_world.0.commute_modes();
+    if let Some(walk_share) = modes.walk_share() {
+        description.push_str(&format!(
+            "\nWorkers on foot: {:.0}% of trips ({} walked, avg {:.1}s)",
+            walk_share * 100.0,
+            modes.walked,
+            modes.average_walk_secs()
+        ));
+    }
+    if let Some(ride_share) = modes.ride_share().filter(|_| modes.rode > 0) {
+        description.push_str(&format!(
+            "\nWorkers by bus: {:.0}% of trips ({} boardings)",
+            ride_share * 100.0,
+            sim_world.0.transit_ridership()
+        ));
+    }
+    describe_labor(&sim_world.0, &mut description);
+    for mut text in text_query.iter_mut() {
+        if **text != description {
+            **text = description.clone();
+        }
+    }
+}
+
+/// Summary of a day's or building's worker shifts
+fn describe_counts(counts: &LaborCounts) -> String {
+    let rate = counts
+        .employment_rate()
+        .map_or("-".to_string(), |rate| format!("{:.0}%", rate * 100.0));
+    format!(
+        "{} employed ({} of {} shifts, {} turned away)",
+        rate, counts.fulfilled, counts.requested, counts.rejected
+    )
+}
+
+/// Append the labor market section: today's shifts, then the standing
+/// factories and houses with the lowest employment
+fn describe_labor(world: &SimWorld, description: &mut String) {
+    description.push_str("\n\nLabor market");
+    let Some(today) = world.labor_by_day().last() else {
+        description.push_str("\nNo shifts yet");
+        return;
+    };
+    description.push_str(&format!(
+        "\nDay {}: {}",
+        today.day + 1,
+        describe_counts(&today.counts)
+    ));
+    description.push_str(&format!(
+        "\nOverall: {}",
+        describe_counts(&world.labor_totals())
+    ));
+
+    let mut factories: Vec<(String, LaborCounts)> = world
+        .labor_by_factory()
+        .into_iter()
+        .filter(|(id, _)| world.factories.contains_key(id))
+        .map(|(id, counts)| (format!("Factory {}", id.0 .0), counts))
+        .collect();
+    let mut houses: Vec<(String, LaborCounts)> = world
+        .labor_by_apartment()
+        .into_iter()
+        .filter(|(id, _)| world.apartments.contains_key(id))
+        .map(|(id, counts)| (format!("House {}", id.0 .0), counts))
+        .collect();
+    for rows in [&mut factories, &mut houses] {
+        rows.sort_by(|(_, a), (_, b)| {
+            let rate = |counts: &LaborCounts| counts.employment_rate().unwrap_or(1.0);
+            rate(a).total_cmp(&rate(b))
+        });
+        for (name, counts) in rows.iter().take(LOWEST_EMPLOYMENT_SHOWN) {
+            description.push_str(&format!("\n{}: {}", name, describe_counts(counts)));
+        }
+    }
+}
@@ -0,0 +1,333 @@
+//! Scripted tutorial for new players
+//!
+//! The tutorial is a small state machine that watches the simulation and moves
+//! on once the player has done what the current step asks. While it runs, build
+//! modes the current step doesn't need are locked and the relevant toolbar
+//! button is highlighted.
+
+use bevy::prelude::*;
+
+use super::components::{BuildModeButton, BuildingMode, BuildingState, SimWorldResource};
+use crate::simulation::SimWorld;
+
+/// How long the closing message stays on screen, in real seconds
+const COMPLETE_MESSAGE_SECS: f32 = 8.0;
+
+/// A single step of the tutorial
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TutorialStep {
+    BuildRoad,
+    PlaceApartment,
+    PlaceFactory,
+    ConnectApartmentToFactory,
+    WatchCommute,
+    PlaceShop,
+    WatchDelivery,
+    Complete,
+}
+
+impl TutorialStep {
+    /// The step that follows this one
+    fn next(&self) -> Self {
+        match self {
+            TutorialStep::BuildRoad => TutorialStep::PlaceApartment,
+            TutorialStep::PlaceApartment => TutorialStep::PlaceFactory,
+            TutorialStep::PlaceFactory => TutorialStep::ConnectApartmentToFactory,
+            TutorialStep::ConnectApartmentToFactory => TutorialStep::WatchCommute,
+            TutorialStep::WatchCommute => TutorialStep::PlaceShop,
+            TutorialStep::PlaceShop => TutorialStep::WatchDelivery,
+            TutorialStep::WatchDelivery | TutorialStep::Complete => TutorialStep::Complete,
+        }
+    }
+
+    /// Whether the player has done what this step asks
+    fn is_complete(&self, world: &SimWorld) -> bool {
+        let game_state = world.game_state.as_ref();
+        match self {
+            TutorialStep::BuildRoad => world.road_network.road_count() > 0,
+            TutorialStep::PlaceApartment => !world.apartments.is_empty(),
+            TutorialStep::PlaceFactory => !world.factories.is_empty(),
+            TutorialStep::ConnectApartmentToFactory => world.apartments.values().any(|apartment| {
+                world.factories.values().any(|factory| {
+                    world
+                        .road_network
+                        .is_reachable(apartment.intersection_id, factory.intersection_id)
+                        && world
+                            .road_network
+                            .is_reachable(factory.intersection_id, apartment.intersection_id)
+                })
+            }),
+            TutorialStep::WatchCommute => {
+                game_state.is_some_and(|game_state| game_state.worker_trips_completed > 0)
+            }
+            TutorialStep::PlaceShop => world.factories.values().any(|factory| {
+                world.shops.values().any(|shop| {
+                    world
+                        .road_network
+                        .is_reachable(factory.intersection_id, shop.intersection_id)
+                        && world
+                            .road_network
+                            .is_reachable(shop.intersection_id, factory.intersection_id)
+                })
+            }),
+            TutorialStep::WatchDelivery => {
+                game_state.is_some_and(|game_state| game_state.shop_deliveries_completed > 0)
+            }
+            TutorialStep::Complete => false,
+        }
+    }
+
+    /// Build modes the player may use during this step
+    fn allows(&self, mode: BuildingMode) -> bool {
+        match self {
+            TutorialStep::BuildRoad => mode == BuildingMode::Road,
+            TutorialStep::PlaceApartment => {
+                matches!(mode, BuildingMode::Road | BuildingMode::Apartment)
+            }
+            TutorialStep::PlaceFactory => {
+                matches!(mode, BuildingMode::Road | BuildingMode::Factory)
+            }
+            TutorialStep::ConnectApartmentToFactory | TutorialStep::WatchCommute => {
+                mode == BuildingMode::Road
+            }
+            TutorialStep::PlaceShop | TutorialStep::WatchDelivery => {
+                matches!(mode, BuildingMode::Road | BuildingMode::Shop)
+            }
+            TutorialStep::Complete => true,
+        }
+    }
+
+    /// Toolbar button to draw attention to
+    fn highlight(&self) -> Option<BuildingMode> {
+        match self {
+            TutorialStep::BuildRoad | TutorialStep::ConnectApartmentToFactory => {
+                Some(BuildingMode::Road)
+            }
+            TutorialStep::PlaceApartment => Some(BuildingMode::Apartment),
+            TutorialStep::PlaceFactory => Some(BuildingMode::Factory),
+            TutorialStep::PlaceShop => Some(BuildingMode::Shop),
+            TutorialStep::WatchCommute | TutorialStep::WatchDelivery | TutorialStep::Complete => {
+                None
+            }
+        }
+    }
+
+    /// Instructions shown to the player
+    fn instructions(&self, world: &SimWorld) -> String {
+        let economy = world
+            .game_state
+            .as_ref()
+            .map(|game_state| game_state.economy.clone())
+            .unwrap_or_default();
+        match self {
+            TutorialStep::BuildRoad => "Welcome! Everything starts with roads.\n\
+                 Press 1 or click Road, then click two points on the ground to build one."
+                .to_string(),
+            TutorialStep::PlaceApartment => "Apartments are where your workers live.\n\
+                 Press 2 or click House, then click beside your road to place one."
+                .to_string(),
+            TutorialStep::PlaceFactory => "Factories employ workers and make goods.\n\
+                 Press 3 or click Factory and place one by a road a little way from the apartment."
+                .to_string(),
+            TutorialStep::ConnectApartmentToFactory => format!(
+                "Workers need a route to work.\n\
+                 Build roads until the apartment and factory are connected. \
+                 Commutes under {:.0} units cost you a penalty.",
+                economy.commute_healthy_distance
+            ),
+            TutorialStep::WatchCommute => format!(
+                "Watch the red cars drive to the factory and back.\n\
+                 Each finished shift earns ${}.",
+                economy.revenue_worker_delivery
+            ),
+            TutorialStep::PlaceShop => {
+                "Finished shifts turn into goods the factory must deliver.\n\
+                 Press 4 or click Shop, place a shop and connect it to the factory by road."
+                    .to_string()
+            }
+            TutorialStep::WatchDelivery => format!(
+                "The blue truck carries goods to the shop and returns.\n\
+                 Each delivery earns ${}.",
+                economy.revenue_shop_delivery
+            ),
+            TutorialStep::Complete => format!(
+                "Tutorial complete! All tools are unlocked.\n\
+                 Win by making {} shop deliveries or reaching ${}.",
+                economy.goal_deliveries, economy.goal_money
+            ),
+        }
+    }
+}
+
+/// Tutorial progress
+#[derive(Resource)]
+pub struct Tutorial {
+    /// Whether the tutorial is running
+    pub active: bool,
+    /// Current step
+    pub step: TutorialStep,
+    /// Real seconds the closing message has been shown
+    complete_timer: f32,
+}
+
+impl Default for Tutorial {
+    fn default() -> Self {
+        Self {
+            active: false,
+            step: TutorialStep::BuildRoad,
+            complete_timer: 0.0,
+        }
+    }
+}
+
+impl Tutorial {
+    /// A tutorial that starts running immediately
+    pub fn started() -> Self {
+        Self {
+            active: true,
+            ..default()
+        }
+    }
+}
+
+/// Marker for the tutorial panel
+#[derive(Component)]
+pub struct TutorialPanel;
+
+/// Marker for the tutorial instruction text
+#[derive(Component)]
+pub struct TutorialText;
+
+/// System to create the tutorial panel (hidden unless the tutorial is running)
+pub fn setup_tutorial_ui(mut commands: Commands) {
+    commands
+        .spawn((
+            TutorialPanel,
+            Node {
+                width: Val::Percent(100.0),
+                position_type: PositionType::Absolute,
+                bottom: Val::Px(90.0),
+                justify_content: JustifyContent::Center,
+                ..default()
+            },
+            Visibility::Hidden,
+        ))
+        .with_children(|parent| {
+            parent
+                .spawn((
+                    Node {
+                        padding: UiRect::all(Val::Px(12.0)),
+                        max_width: Val::Px(620.0),
+                        ..default()
+                    },
+                    BackgroundColor(Color::srgba(0.05, 0.1, 0.2, 0.85)),
+                ))
+                .with_children(|panel| {
+                    panel.spawn((
+                        TutorialText,
+                        Text::new(""),
+                        TextFont {
+                            font_size: 16.0,
+                            ..default()
+                        },
+                        TextColor(Color::WHITE),
+                    ));
+                });
+        });
+}
+
+/// System to move the tutorial on once the current step is done (F1 skips it)
+pub fn advance_tutorial(
+    time: Res<Time>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    sim_world: Res<SimWorldResource>,
+    mut tutorial: ResMut<Tutorial>,
+) {
+    if !tutorial.active {
+        return;
+    }
+
+    if keyboard.just_pressed(KeyCode::F1) {
+        info!("Tutorial skipped");
+        tutorial.active = false;
+        return;
+    }
+
+    // A single action can satisfy several steps (e.g. a road that already
+    // connects everything), so keep advancing while steps are complete
+    while tutorial.step.is_complete(&sim_world.0) {
+        tutorial.step = tutorial.step.next();
+        info!("Tutorial step: {:?}", tutorial.step);
+    }
+
+    if tutorial.step == TutorialStep::Complete {
+        tutorial.complete_timer += time.delta_secs();
+        if tutorial.complete_timer >= COMPLETE_MESSAGE_SECS {
+            tutorial.active = false;
+        }
+    }
+}
+
+/// System to drop out of build modes the current tutorial step doesn't allow
+pub fn gate_tutorial_build_mode(
+    tutorial: Res<Tutorial>,
+    mut building_state: ResMut<BuildingState>,
+) {
+    if !tutorial.active || tutorial.step.allows(building_state.mode) {
+        return;
+    }
+    if building_state.mode != BuildingMode::None {
+        info!(
+            "{:?} mode is locked until the tutorial asks for it",
+            building_state.mode
+        );
+        building_state.mode = BuildingMode::None;
+        building_state.road_start = None;
+    }
+}
+
+/// System to show the current instructions and highlight the relevant button
+pub fn update_tutorial_ui(
+    time: Res<Time>,
+    tutorial: Res<Tutorial>,
+    building_state: Res<BuildingState>,
+    sim_world: Res<SimWorldResource>,
+    mut panel_query: Query<&mut Visibility, With<TutorialPanel>>,
+    mut text_query: Query<&mut Text, With<TutorialText>>,
+    mut button_query: Query<(&BuildModeButton, &mut BorderColor, &Interaction)>,
+) {
+    // Only touch the UI while running, plus once on the frame it stops
+    if !tutorial.active && !tutorial.is_changed() {
+        return;
+    }
+
+    for mut visibility in panel_query.iter_mut() {
+        *visibility = if tutorial.active {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+    }
+    if tutorial.is_changed() {
+        for mut text in text_query.iter_mut() {
+            **text = tutorial.step.instructions(&sim_world.0);
+        }
+    }
+
+    let highlight = tutorial.step.highlight().filter(|_| tutorial.active);
+    let pulse = 0.5 + 0.5 * (time.elapsed_secs() * 4.0).sin();
+    for (button, mut border_color, interaction) in button_query.iter_mut() {
+        if *interaction == Interaction::Hovered {
+            continue;
+        }
+        *border_color = BorderColor::all(if building_state.mode == button.0 {
+            Color::srgb(0.0, 1.0, 0.0)
+        } else if highlight == Some(button.0) {
+            Color::srgb(0.0, 0.5 + 0.5 * pulse, 1.0)
+        } else if tutorial.active && !tutorial.step.allows(button.0) {
+            Color::srgb(0.4, 0.4, 0.4)
+        } else {
+            Color::WHITE
+        });
+    }
+}
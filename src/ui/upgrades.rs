@@ -0,0 +1,195 @@
+//! Capacity upgrade panel
+//!
+//! Press F3 to show the upgrades the economy offers: the vehicle cap, cars
+//! per house and factory storage, each with its level and the cost of the
+//! next one. Clicking Buy sends a [`BuildCommand::BuyUpgrade`], so purchases
+//! reach co-op peers like any other build.
+
+use bevy::ecs::hierarchy::ChildSpawnerCommands;
+use bevy::prelude::*;
+
+use super::components::{PendingCommands, SimWorldResource};
+use super::multiplayer::MultiplayerSession;
+use crate::simulation::{BuildCommand, SimWorld, UpgradeKind};
+
+/// Marker for the upgrade panel
+#[derive(Component)]
+pub struct UpgradePanel;
+
+/// Text describing an upgrade's level and what it does
+#[derive(Component)]
+pub struct UpgradeLabel(UpgradeKind);
+
+/// Button buying the next level of an upgrade
+#[derive(Component)]
+pub struct UpgradeButton(UpgradeKind);
+
+/// Text on an upgrade's button
+#[derive(Component)]
+pub struct UpgradeButtonText(UpgradeKind);
+
+/// System to create the (hidden) upgrade panel
+pub fn setup_upgrade_ui(mut commands: Commands) {
+    commands
+        .spawn((
+            UpgradePanel,
+            Node {
+                position_type: PositionType::Absolute,
+                bottom: Val::Px(120.0),
+                right: Val::Px(10.0),
+                padding: UiRect::all(Val::Px(10.0)),
+                flex_direction: FlexDirection::Column,
+                row_gap: Val::Px(6.0),
+                ..default()
+            },
+            BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.8)),
+            Visibility::Hidden,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Text::new("Upgrades"),
+                TextFont {
+                    font_size: 16.0,
+                    ..default()
+                },
+                TextColor(Color::WHITE),
+            ));
+            for kind in UpgradeKind::ALL {
+                spawn_upgrade_row(parent, kind);
+            }
+        });
+}
+
+fn spawn_upgrade_row(parent: &mut ChildSpawnerCommands, kind: UpgradeKind) {
+    parent
+        .spawn(Node {
+            align_items: AlignItems::Center,
+            column_gap: Val::Px(8.0),
+            ..default()
+        })
+        .with_children(|row| {
+            row.spawn((
+                UpgradeButton(kind),
+                Button,
+                Node {
+                    padding: UiRect::axes(Val::Px(8.0), Val::Px(4.0)),
+                    border: UiRect::all(Val::Px(1.0)),
+                    ..default()
+                },
+                BorderColor::all(Color::WHITE),
+                BackgroundColor(Color::srgb(0.25, 0.25, 0.3)),
+            ))
+            .with_children(|button| {
+                button.spawn((
+                    UpgradeButtonText(kind),
+                    Text::new(""),
+                    TextFont {
+                        font_size: 13.0,
+                        ..default()
+                    },
+                    TextColor(Color::WHITE),
+                ));
+            });
+            row.spawn((
+                UpgradeLabel(kind),
+                Text::new(""),
+                TextFont {
+                    font_size: 14.0,
+                    ..default()
+                },
+                TextColor(Color::WHITE),
+            ));
+        });
+}
+
+/// System to toggle the upgrade panel (F3)
+pub fn handle_upgrade_keyboard(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut panel_query: Query<&mut Visibility, With<UpgradePanel>>,
+) {
+    if !keyboard.just_pressed(KeyCode::F3) {
+        return;
+    }
+    for mut visibility in panel_query.iter_mut() {
+        *visibility = match *visibility {
+            Visibility::Hidden => Visibility::Inherited,
+            _ => Visibility::Hidden,
+        };
+    }
+}
+
+/// What an upgrade's levels so far give
+fn upgrade_description(world: &SimWorld, kind: UpgradeKind) -> String {
+    let Some(upgrades) = world.upgrades() else {
+        return "Not offered by this economy".to_string();
+    };
+    let level = world.upgrade_level(kind);
+    let effect = match kind {
+        UpgradeKind::CarCap => format!(
+            "{} of {} vehicles on the roads",
+            world.cars.len(),
+            world.max_cars().unwrap_or_default()
+        ),
+        UpgradeKind::ApartmentCars => {
+            format!("+{} cars per house", world.extra_apartment_cars())
+        }
+        UpgradeKind::FactoryStorage => {
+            format!("+{} deliveries stored per factory", world.extra_factory_storage())
+        }
+    };
+    format!(
+        "{} {}/{} - {}",
+        kind.label(),
+        level,
+        upgrades.track(kind).levels,
+        effect
+    )
+}
+
+/// System to keep the panel's levels, effects and prices current
+pub fn update_upgrade_panel(
+    sim_world: Res<SimWorldResource>,
+    panel_query: Query<&Visibility, With<UpgradePanel>>,
+    mut label_query: Query<(&UpgradeLabel, &mut Text), Without<UpgradeButtonText>>,
+    mut button_text_query: Query<(&UpgradeButtonText, &mut Text), Without<UpgradeLabel>>,
+) {
+    if panel_query
+        .iter()
+        .all(|visibility| *visibility == Visibility::Hidden)
+    {
+        return;
+    }
+    let world = &sim_world.0;
+    for (label, mut text) in label_query.iter_mut() {
+        let description = upgrade_description(world, label.0);
+        if **text != description {
+            **text = description;
+        }
+    }
+    for (button, mut text) in button_text_query.iter_mut() {
+        let price = match world.upgrade_cost(button.0) {
+            Some(cost) => format!("Buy ${}", cost),
+            None if world.upgrades().is_some() => "Maxed".to_string(),
+            None => "-".to_string(),
+        };
+        if **text != price {
+            **text = price;
+        }
+    }
+}
+
+/// System to buy upgrades when their buttons are clicked
+pub fn handle_upgrade_buttons(
+    session: Res<MultiplayerSession>,
+    mut pending: ResMut<PendingCommands>,
+    interaction_query: Query<(&Interaction, &UpgradeButton), Changed<Interaction>>,
+) {
+    if session.is_spectating() {
+        return;
+    }
+    for (interaction, button) in interaction_query.iter() {
+        if *interaction == Interaction::Pressed {
+            pending.0.push(BuildCommand::BuyUpgrade { upgrade: button.0 });
+        }
+    }
+}
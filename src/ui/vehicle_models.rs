@@ -0,0 +1,133 @@
+//! Low-poly vehicle models and rolling wheels
+//!
+//! Each vehicle type has a small glTF model embedded in the binary. Vehicles
+//! are spawned as cuboids and swapped for their type's model once it has
+//! loaded, so a model that fails to load leaves its vehicles as cuboids.
+//! Vehicles given a color or size by a content pack stay cuboids as well,
+//! since the models carry their own colors and proportions.
+//!
+//! Every vehicle has four wheels, which roll with the distance it covers.
+
+use std::collections::HashMap;
+use std::f32::consts::{FRAC_PI_2, TAU};
+
+use bevy::asset::{embedded_asset, embedded_path, AssetPath, RecursiveDependencyLoadState};
+use bevy::prelude::*;
+
+use super::spawner::vehicle_height;
+use crate::simulation::VehicleType;
+
+/// Marker for a vehicle drawn as a cuboid until its model has loaded
+#[derive(Component)]
+pub struct AwaitingModel(pub VehicleType);
+
+/// Marker for a vehicle's wheel
+#[derive(Component)]
+pub struct Wheel;
+
+/// How far a vehicle's wheels have turned
+#[derive(Component)]
+pub struct WheelRoll {
+    pub radius: f32,
+    /// Turn of the wheels in radians
+    pub angle: f32,
+    /// Where the vehicle was last frame
+    pub last_position: Vec3,
+}
+
+/// The scene of each vehicle type's model
+#[derive(Resource, Default)]
+pub struct VehicleModels {
+    /// Models that failed to load are dropped, leaving their vehicles as
+    /// cuboids
+    scenes: HashMap<VehicleType, Handle<Scene>>,
+}
+
+/// Radius of the wheels of a vehicle whose body is `height` tall, filling the
+/// gap beneath it
+pub fn wheel_radius(vehicle_type: VehicleType, height: f32) -> f32 {
+    let clearance = vehicle_height(vehicle_type) - height / 2.0;
+    (clearance / 2.0).max(0.05)
+}
+
+/// Rotation of a wheel turned `angle` radians, its axle across the vehicle
+pub fn wheel_rotation(angle: f32) -> Quat {
+    Quat::from_rotation_x(angle) * Quat::from_rotation_z(FRAC_PI_2)
+}
+
+/// Embed the vehicle models in the binary
+pub fn embed_vehicle_models(app: &mut App) {
+    embedded_asset!(app, "models/car.gltf");
+    embedded_asset!(app, "models/truck.gltf");
+    embedded_asset!(app, "models/ambulance.gltf");
+    embedded_asset!(app, "models/tow_truck.gltf");
+    embedded_asset!(app, "models/van.gltf");
+}
+
+/// System to start loading the vehicle models
+pub fn load_vehicle_models(asset_server: Res<AssetServer>, mut models: ResMut<VehicleModels>) {
+    let paths = [
+        (VehicleType::Car, embedded_path!("models/car.gltf")),
+        (VehicleType::Truck, embedded_path!("models/truck.gltf")),
+        (VehicleType::Ambulance, embedded_path!("models/ambulance.gltf")),
+        (VehicleType::TowTruck, embedded_path!("models/tow_truck.gltf")),
+        (VehicleType::Van, embedded_path!("models/van.gltf")),
+    ];
+    for (vehicle_type, path) in paths {
+        let path = AssetPath::from_path_buf(path).with_source("embedded");
+        let scene = asset_server.load(GltfAssetLabel::Scene(0).from_asset(path));
+        models.scenes.insert(vehicle_type, scene);
+    }
+}
+
+/// System to swap vehicles' cuboids for their models as the models load
+pub fn swap_in_vehicle_models(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut models: ResMut<VehicleModels>,
+    waiting: Query<(Entity, &AwaitingModel)>,
+) {
+    for (entity, awaiting) in waiting.iter() {
+        let vehicle_type = awaiting.0;
+        let Some(scene) = models.scenes.get(&vehicle_type) else {
+            commands.entity(entity).remove::<AwaitingModel>();
+            continue;
+        };
+        match asset_server.recursive_dependency_load_state(scene) {
+            RecursiveDependencyLoadState::Loaded => {
+                let scene = scene.clone();
+                commands
+                    .entity(entity)
+                    .remove::<(AwaitingModel, Mesh3d, MeshMaterial3d<StandardMaterial>)>()
+                    .with_child(SceneRoot(scene));
+            }
+            RecursiveDependencyLoadState::Failed(err) => {
+                warn!("Couldn't load the {:?} model, drawing cuboids: {}", vehicle_type, err);
+                models.scenes.remove(&vehicle_type);
+                commands.entity(entity).remove::<AwaitingModel>();
+            }
+            _ => {}
+        }
+    }
+}
+
+/// System to roll vehicles' wheels by the distance they moved forward
+pub fn roll_wheels(
+    mut vehicles: Query<(&Transform, &mut WheelRoll, &Children), Without<Wheel>>,
+    mut wheels: Query<&mut Transform, With<Wheel>>,
+) {
+    for (transform, mut roll, children) in vehicles.iter_mut() {
+        let moved = transform.translation - roll.last_position;
+        roll.last_position = transform.translation;
+        let forward = moved.dot(transform.rotation * Vec3::Z);
+        if forward == 0.0 {
+            continue;
+        }
+        roll.angle = (roll.angle + forward / roll.radius) % TAU;
+        for child in children.iter() {
+            if let Ok(mut wheel) = wheels.get_mut(child) {
+                wheel.rotation = wheel_rotation(roll.angle);
+            }
+        }
+    }
+}
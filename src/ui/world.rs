@@ -2,19 +2,24 @@
 
 use bevy::prelude::*;
 
-use super::components::{Ground, MainCamera};
+use super::components::{Ground, MainCamera, SimWorldResource};
+use super::terrain::build_terrain_mesh;
 
 /// System to setup the world environment (ground, lighting, camera)
 pub fn setup_world(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
+    sim_world: Res<SimWorldResource>,
 ) {
-    // Spawn a 3D camera with top-down view
+    // Spawn a 3D camera with top-down view over the middle of the world
+    let bounds = sim_world.0.bounds();
+    let center = bounds.center();
+    let center = Vec3::new(center.x, 0.0, center.z);
     commands.spawn((
         MainCamera,
         Camera3d::default(),
-        Transform::from_xyz(0.0, 70.0, 0.0).looking_at(Vec3::ZERO, Vec3::Z),
+        Transform::from_translation(center + Vec3::Y * 70.0).looking_at(center, Vec3::Z),
     ));
 
     // Spawn a directional light
@@ -27,10 +32,22 @@ pub fn setup_world(
         Transform::from_xyz(4.0, 8.0, 4.0).looking_at(Vec3::ZERO, Vec3::Y),
     ));
 
-    // Spawn a ground plane
+    // Spawn a ground plane covering the world's bounds, or the hills if the
+    // world has them
+    let (ground, offset) = match &sim_world.0.heightmap {
+        Some(heightmap) => (build_terrain_mesh(heightmap), Vec3::ZERO),
+        None => (
+            Plane3d::default()
+                .mesh()
+                .size(bounds.width(), bounds.depth())
+                .into(),
+            center,
+        ),
+    };
     commands.spawn((
         Ground,
-        Mesh3d(meshes.add(Plane3d::default().mesh().size(200.0, 200.0))),
+        Mesh3d(meshes.add(ground)),
         MeshMaterial3d(materials.add(Color::srgb(0.3, 0.5, 0.3))),
+        Transform::from_translation(offset),
     ));
 }
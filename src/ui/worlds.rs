@@ -0,0 +1,192 @@
+//! Live and experimental worlds for A/B play
+//!
+//! An offline game with an autosave can copy its live city into an
+//! experimental world (C) to try out a different layout, and switch which of
+//! the two is shown (Tab). The copy starts like the live city's save being
+//! loaded: the same layout and money, with traffic starting afresh. Copying
+//! again replaces the experimental world. Both worlds keep running while the
+//! other is shown, and a panel compares how they are doing.
+//!
+//! The world shown is the one in [`SimWorldResource`], which every other
+//! system reads and builds on; the other waits in [`WorldSlots`]. Only the
+//! live world is autosaved, scored and counted towards achievements.
+//! Switching has the renderer resync with the world now shown, so what the
+//! two share is updated in place instead of being drawn again.
+
+use bevy::prelude::*;
+
+use super::autosave::Autosave;
+use super::components::SimWorldResource;
+use super::renderer::VisualSync;
+use crate::simulation::SimWorld;
+
+/// Which world is shown
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum WorldSlot {
+    #[default]
+    Live,
+    Experimental,
+}
+
+impl WorldSlot {
+    /// Name for display
+    pub fn label(self) -> &'static str {
+        match self {
+            WorldSlot::Live => "Live",
+            WorldSlot::Experimental => "Experimental",
+        }
+    }
+}
+
+/// The world not being shown, if there is an experimental one
+#[derive(Resource, Default)]
+pub struct WorldSlots {
+    pub showing: WorldSlot,
+    hidden: Option<SimWorld>,
+}
+
+impl WorldSlots {
+    /// The live world, given the world being shown
+    pub fn live<'a>(&'a self, shown: &'a SimWorld) -> &'a SimWorld {
+        match (&self.hidden, self.showing) {
+            (Some(hidden), WorldSlot::Experimental) => hidden,
+            _ => shown,
+        }
+    }
+
+    /// Whether the live world is shown
+    pub fn showing_live(&self) -> bool {
+        self.showing == WorldSlot::Live
+    }
+
+    /// The experimental world, given the world being shown
+    pub fn experimental<'a>(&'a self, shown: &'a SimWorld) -> Option<&'a SimWorld> {
+        match self.showing {
+            WorldSlot::Live => self.hidden.as_ref(),
+            WorldSlot::Experimental => Some(shown),
+        }
+    }
+
+    /// The world not being shown, if there is one
+    pub fn hidden_mut(&mut self) -> Option<&mut SimWorld> {
+        self.hidden.as_mut()
+    }
+}
+
+/// Marker for the panel comparing the live and experimental worlds
+#[derive(Component)]
+pub struct WorldPanelText;
+
+/// System to setup the world comparison panel
+pub fn setup_world_panel(mut commands: Commands) {
+    commands.spawn((
+        WorldPanelText,
+        Text::new(""),
+        TextFont {
+            font_size: 14.0,
+            ..default()
+        },
+        TextColor(Color::srgb(1.0, 0.9, 0.6)),
+        Node {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(90.0),
+            right: Val::Px(10.0),
+            ..default()
+        },
+    ));
+}
+
+/// System to copy the live world into the experimental one (C) and switch
+/// which is shown (Tab)
+pub fn handle_world_keyboard(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut sim_world: ResMut<SimWorldResource>,
+    mut slots: ResMut<WorldSlots>,
+    mut visual_sync: ResMut<VisualSync>,
+    autosave: Option<Res<Autosave>>,
+) {
+    if keyboard.just_pressed(KeyCode::KeyC) {
+        // Only offline games keep the save the copy is made from
+        let Some(autosave) = autosave else {
+            warn!("Only offline games with an autosave can copy the city");
+            return;
+        };
+        let mut save = autosave.save.clone();
+        save.record_game_state(slots.live(&sim_world.0));
+        match save.create_world() {
+            Ok(copy) => {
+                match slots.showing {
+                    WorldSlot::Live => slots.hidden = Some(copy),
+                    WorldSlot::Experimental => {
+                        sim_world.0 = copy;
+                        visual_sync.0.resync();
+                    }
+                }
+                info!("Copied the live city into the experimental world; Tab switches");
+            }
+            Err(e) => warn!("Couldn't copy the city: {:#}", e),
+        }
+    }
+
+    if keyboard.just_pressed(KeyCode::Tab) {
+        let Some(hidden) = &mut slots.hidden else {
+            return;
+        };
+        std::mem::swap(&mut sim_world.0, hidden);
+        slots.showing = match slots.showing {
+            WorldSlot::Live => WorldSlot::Experimental,
+            WorldSlot::Experimental => WorldSlot::Live,
+        };
+        visual_sync.0.resync();
+        info!("Showing the {} world", slots.showing.label().to_lowercase());
+    }
+}
+
+/// System to keep the world not being shown running
+pub fn tick_hidden_world(time: Res<Time>, mut slots: ResMut<WorldSlots>) {
+    if let Some(hidden) = &mut slots.hidden {
+        hidden.tick(time.delta_secs());
+    }
+}
+
+/// System to compare the live and experimental worlds while there are two
+pub fn update_world_panel(
+    sim_world: Res<SimWorldResource>,
+    slots: Res<WorldSlots>,
+    mut text_query: Query<&mut Text, With<WorldPanelText>>,
+) {
+    let text = match slots.experimental(&sim_world.0) {
+        Some(experimental) => {
+            let summary = |slot: WorldSlot, world: &SimWorld| {
+                let marker = if slot == slots.showing { "▶" } else { " " };
+                let (money, trips, deliveries) =
+                    world.game_state.as_ref().map_or((0, 0, 0), |game_state| {
+                        (
+                            game_state.money,
+                            game_state.worker_trips_completed,
+                            game_state.shop_deliveries_completed,
+                        )
+                    });
+                format!(
+                    "{} {}: ${}, {} trips, {} deliveries",
+                    marker,
+                    slot.label(),
+                    money,
+                    trips,
+                    deliveries
+                )
+            };
+            format!(
+                "{}\n{}\n[Tab] switch  [C] copy live",
+                summary(WorldSlot::Live, slots.live(&sim_world.0)),
+                summary(WorldSlot::Experimental, experimental)
+            )
+        }
+        None => String::new(),
+    };
+    for mut panel in text_query.iter_mut() {
+        if panel.0 != text {
+            panel.0 = text.clone();
+        }
+    }
+}
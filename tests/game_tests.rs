@@ -2,10 +2,45 @@
 //!
 //! This test validates that the game mechanics work correctly
 
+use std::collections::{BTreeSet, HashMap};
+use std::path::Path;
+
+use traffic_sim::multiplayer::{
+    state_checksum, LockstepClient, LockstepHost, SessionSetup, SPECTATOR_BATCH_SECS,
+};
 use traffic_sim::simulation::{
-    GameState, Position, SimWorld, COMMUTE_HEALTHY_DISTANCE, COST_APARTMENT, COST_ROAD,
-    GOAL_DELIVERIES, GOAL_MONEY, REVENUE_SHOP_DELIVERY, REVENUE_WORKER_DELIVERY,
-    SHORT_COMMUTE_PENALTY, STARTING_BUDGET,
+    copy_timings, crossing_distance, crossing_secs, cul_de_sac_cost, cul_de_sac_lots,
+    junction_cost, parse_road_counts, slope_cost_factor, slope_speed_factor, wear_breakdown_factor,
+    wear_speed_factor, Achievement, AchievementTracker, Action, ActionConfig, AlarmMetric, Anomaly,
+    AnomalyConfig, AssignmentConfig, AuditTrip, Autopilot, Autosaver, BestScores, BudgetWatch,
+    BuildCommand, BuildingKind, BuildingSite, BusState, CalibrationConfig, CameraTour, CarFilter,
+    CarId, CarUpdateResult, CommuteCurve, ContentPack, ContentRegistry, ContraflowRoad,
+    ContraflowWindow, CurveSpeedLimit, DecorationKind, DemandRamp, DemandWeight, DriverKind,
+    DriverMix, DrivingSide, EconomyConfig, EconomyWatcher, EntityBudgets, EnvConfig,
+    Equirectangular, EventSchedule, GameEvent, GameState, Heightmap, HighlightKind, IncidentKind,
+    IntersectionChange, IntersectionControl, IntersectionId, IntersectionRules, JunctionKind,
+    MapProjection, PathCacheConfig, PathCacheEviction, Personality, PlacedBuilding, Position,
+    RampCurve, RandomEvents, RecapConfig, RecapOutcome, RecapSection, Region, RenderSync,
+    ReportFormat, RoadChange, RoadCount, RoadExit, RoadField, RoadId, RoadKind, RoadRestriction,
+    RunSummary, RunningMarker, SaveFile, ScenarioEvent, ScheduledEvent, SignalPhase, SimEvent,
+    SimRenderer, SimWarning, SimWorld, SpecialKind, SpeedGoal, TrafficEnv, Treatment, TripPurpose,
+    TripType, Turn, TurnArc, UpgradeConfig, UpgradeKind, VehicleRecord, VehicleSpawn, VehicleType,
+    Visual, VisualId, VisualKind, WarningLog, WaterRegion, WindowedMean, WorldBounds,
+    BREAKDOWN_STALL_SECS, BUS_CAPACITY, CAR_LENGTH, CAR_POOL_CAPACITY, COMMUTE_HEALTHY_DISTANCE,
+    COST_APARTMENT, COST_BREAKDOWN_CLEANUP, COST_BRIDGE, COST_BUS_STOP, COST_DEPOT, COST_FACTORY,
+    COST_PARK, COST_ROAD, COST_SENSOR, COST_SHOP, COST_TERMINAL, COST_VEHICLE_REPLACEMENT,
+    DEFAULT_WORLD_SIZE, EMISSIONS_CSV_HEADER, ETA_INTERSECTION_SECS, FACTORY_MATERIALS_CAPACITY,
+    FACTORY_SHIFTS_PER_DELIVERY, FERRY_CAPACITY, GOAL_DELIVERIES, GOAL_MONEY,
+    HOTSPOT_HALF_LIFE_SECS, HOTSPOT_MAX_POLLUTION, HOTSPOT_MIN_SEVERITY, INSURANCE_PERIOD_SECS,
+    INSURANCE_PREMIUM, INTERSECTION_APPROACH_DISTANCE, LABOR_STATS_CSV_HEADER, LANE_WIDTH,
+    MAX_APPEAL, MAX_DRIVEWAY_LENGTH, MIN_SUGGESTION_WAIT_SECS, MIN_WALK_SECS, PATH_CACHE_CAPACITY,
+    PATH_CACHE_MAX_AGE_SECS, PEDESTRIAN_YIELD_SECS, PREEMPTION_WINDOW_SECS,
+    RECAP_MONEY_INTERVAL_SECS, RECAP_MONEY_SAMPLES, REPORT_CONGESTED_ROADS, REVENUE_SHOP_DELIVERY,
+    REVENUE_WORKER_DELIVERY, REVERSING_SPEED_FACTOR, ROAD_COUNTS_CSV_HEADER, ROAD_STATS_CSV_HEADER,
+    SAVE_VERSION, SHORT_COMMUTE_PENALTY, SNAPSHOT_VERSION, SPAWN_CLEARANCE, STARTING_BUDGET,
+    STOP_SIGN_SECS, TERMINAL_SHIPMENT_SECS, TERMINAL_SHIPMENT_SIZE, THUMBNAIL_WIDTH, VAN_LOAD,
+    VEHICLE_WORN_OUT_AGE_SECS, VEHICLE_WORN_OUT_MILEAGE, WALKING_DISTANCE, WARNING_INTERVAL_SECS,
+    YIELD_GAP_SECS,
 };
 
 #[test]
@@ -24,7 +59,7 @@ fn test_game_state_revenue() {
     let initial_money = game_state.money;
 
     // Complete a worker trip
-    game_state.complete_worker_trip(COMMUTE_HEALTHY_DISTANCE + 5.0);
+    game_state.complete_worker_trip(COMMUTE_HEALTHY_DISTANCE + 5.0, 0.0);
     assert_eq!(game_state.worker_trips_completed, 1);
     assert_eq!(game_state.money, initial_money + REVENUE_WORKER_DELIVERY);
 
@@ -139,7 +174,7 @@ fn test_short_commute_penalty_applied() {
     let mut game_state = GameState::new();
     let initial_money = game_state.money;
 
-    game_state.complete_worker_trip(0.0);
+    game_state.complete_worker_trip(0.0, 0.0);
 
     let expected_penalty = SHORT_COMMUTE_PENALTY;
     assert_eq!(
@@ -147,3 +182,6515 @@ fn test_short_commute_penalty_applied() {
         initial_money + REVENUE_WORKER_DELIVERY - expected_penalty
     );
 }
+
+#[test]
+fn test_custom_economy_from_toml() {
+    let economy = EconomyConfig::from_toml_str(
+        r#"
+        starting_budget = 1000
+        cost_apartment = 400
+        goal_deliveries = 3
+        "#,
+    )
+    .expect("economy TOML should parse");
+
+    // Unlisted fields keep their defaults
+    assert_eq!(economy.cost_road, COST_ROAD);
+    assert_eq!(economy.revenue_shop_delivery, REVENUE_SHOP_DELIVERY);
+
+    let mut world = SimWorld::new_with_economy(economy.clone());
+    assert_eq!(world.game_state.as_ref().unwrap().money, 1000);
+
+    let intersection_id = world.add_intersection(Position::new(0.0, 0.0, 0.0));
+    assert!(world.try_add_apartment(intersection_id).is_some());
+    assert_eq!(world.game_state.as_ref().unwrap().money, 600);
+
+    let mut game_state = GameState::with_economy(economy);
+    for _ in 0..3 {
+        game_state.complete_shop_delivery();
+    }
+    game_state.update(0.1);
+    assert!(game_state.is_won);
+}
+
+#[test]
+fn test_economy_toml_round_trip_and_validation() {
+    let economy = EconomyConfig::default();
+    let text = economy.to_toml_string().expect("economy should serialize");
+    assert_eq!(EconomyConfig::from_toml_str(&text).unwrap(), economy);
+
+    assert!(EconomyConfig::from_toml_str("cost_road = -5").is_err());
+    assert!(EconomyConfig::from_toml_str("cost_raod = 5").is_err());
+}
+
+#[test]
+fn test_edited_economy_reloads_into_running_game() {
+    let path =
+        std::env::temp_dir().join(format!("traffic_sim_economy_{}.toml", std::process::id()));
+    let write = |text: &str, secs: u64| {
+        std::fs::write(&path, text).unwrap();
+        // Set the time explicitly, as two quick writes can share one
+        let file = std::fs::File::options().write(true).open(&path).unwrap();
+        file.set_modified(std::time::UNIX_EPOCH + std::time::Duration::from_secs(secs))
+            .unwrap();
+    };
+    write("cost_road = 50", 1000);
+    let mut world = SimWorld::new_with_economy(EconomyConfig::load(&path).unwrap());
+    let mut watcher = EconomyWatcher::new(path.clone());
+    assert!(
+        watcher.poll().is_none(),
+        "the file as loaded isn't a change"
+    );
+
+    write(
+        "cost_road = 80\nstarting_budget = 1\n[demand_ramp]\nstart_level = 2.0",
+        2000,
+    );
+    let economy = watcher
+        .poll()
+        .expect("saved file should be noticed")
+        .unwrap();
+    assert!(watcher.poll().is_none());
+    let money = world.game_state.as_ref().unwrap().money;
+    let reload = world.reload_economy(economy).unwrap();
+    assert_eq!(reload.applied, ["cost_road", "demand_ramp"]);
+    assert_eq!(reload.deferred, ["starting_budget"]);
+    let game_state = world.game_state.as_ref().unwrap();
+    assert_eq!(game_state.economy.cost_road, 80);
+    assert_eq!(game_state.economy.starting_budget, STARTING_BUDGET);
+    assert_eq!(game_state.money, money);
+    assert_eq!(world.demand_level(), 2.0);
+
+    // A bad edit leaves the economy in effect alone
+    write("cost_road = -1", 3000);
+    assert!(watcher.poll().unwrap().is_err());
+    std::fs::remove_file(&path).ok();
+    assert_eq!(world.game_state.as_ref().unwrap().economy.cost_road, 80);
+    assert!(watcher.poll().is_none(), "a removed file isn't a change");
+
+    assert!(SimWorld::new()
+        .reload_economy(EconomyConfig::default())
+        .is_err());
+}
+
+#[test]
+fn test_worker_pay_formula_is_configurable() {
+    let economy = EconomyConfig::from_toml_str(
+        r#"
+        commute_healthy_distance = 20.0
+        short_commute_penalty = 8
+        commute_penalty_curve = "quadratic"
+        commute_time_allowance_secs = 10.0
+        commute_time_penalty_per_sec = 0.5
+        quick_commute_bonus = 3
+        "#,
+    )
+    .expect("economy TOML should parse");
+    assert_eq!(economy.commute_penalty_curve, CommuteCurve::Quadratic);
+
+    // Half the healthy distance short costs a quarter of the penalty
+    assert_eq!(economy.short_commute_penalty_for(10.0), 2);
+    assert_eq!(economy.short_commute_penalty_for(25.0), 0);
+    let base = economy.revenue_worker_delivery;
+    // A quick drive home earns the bonus, a slow one pays per extra second,
+    // but never more than the base pay
+    assert_eq!(economy.worker_trip_pay(10.0, 10.0), base - 2 + 3);
+    assert_eq!(economy.worker_trip_pay(25.0, 14.0), base - 2);
+    assert_eq!(economy.worker_trip_pay(25.0, 1000.0), 0);
+
+    let step = EconomyConfig {
+        commute_penalty_curve: CommuteCurve::Step,
+        ..EconomyConfig::default()
+    };
+    assert_eq!(
+        step.short_commute_penalty_for(COMMUTE_HEALTHY_DISTANCE - 1.0),
+        SHORT_COMMUTE_PENALTY
+    );
+    let mut game_state = GameState::with_economy(economy.clone());
+    let initial_money = game_state.money;
+    game_state.complete_worker_trip(10.0, 10.0);
+    assert_eq!(game_state.money, initial_money + base + 1);
+    assert_eq!(economy.worker_pay_formula().len(), 4);
+
+    assert!(EconomyConfig::from_toml_str(r#"commute_penalty_curve = "cubic""#).is_err());
+    assert!(EconomyConfig::from_toml_str("commute_time_penalty_per_sec = -1.0").is_err());
+}
+
+#[test]
+fn test_content_pack_building_variant() {
+    let pack = ContentPack::from_toml_str(
+        r#"
+        [[vehicles]]
+        name = "van"
+        base = "truck"
+        min_speed = 3.0
+        max_speed = 3.0
+
+        [[buildings]]
+        name = "warehouse"
+        kind = "factory"
+        cost = 800
+        work_time = 2.0
+        max_deliveries = 4
+        shifts_per_delivery = 2
+        materials_per_delivery = 0
+        vehicle = "van"
+        "#,
+    )
+    .expect("content pack should parse");
+
+    let mut world = SimWorld::new_with_game();
+    world.content.register_pack(pack).unwrap();
+
+    let intersection_id = world.add_intersection(Position::new(0.0, 0.0, 0.0));
+    let initial_money = world.game_state.as_ref().unwrap().money;
+    let placed = world
+        .try_add_building_from_def("warehouse", intersection_id)
+        .unwrap();
+
+    let Some(PlacedBuilding::Factory(factory_id)) = placed else {
+        panic!("warehouse should be placed as a factory, got {:?}", placed);
+    };
+    let factory = &world.factories[&factory_id];
+    assert_eq!(factory.work_time, 2.0);
+    assert_eq!(factory.max_deliveries, 4);
+    assert_eq!(factory.shifts_per_delivery, 2);
+    assert_eq!(factory.materials_per_delivery, 0);
+    assert_eq!(factory.variant.as_deref(), Some("warehouse"));
+    assert_eq!(
+        world.game_state.as_ref().unwrap().money,
+        initial_money - 800
+    );
+
+    assert!(world
+        .try_add_building_from_def("missing", intersection_id)
+        .is_err());
+}
+
+#[test]
+fn test_factory_needs_shifts_and_materials_per_delivery() {
+    let mut world = SimWorld::new();
+    let home = world.add_intersection(Position::new(0.0, 0.0, 0.0));
+    let work = world.add_intersection(Position::new(30.0, 0.0, 0.0));
+    let apartment = world.add_apartment(home);
+    let factory_id = world.add_factory(work);
+    let factory = world.factories.get_mut(&factory_id).unwrap();
+    assert_eq!(factory.shifts_per_delivery, FACTORY_SHIFTS_PER_DELIVERY);
+
+    // Each shift brings the next delivery closer; the last one finishes it
+    for shift in 1..FACTORY_SHIFTS_PER_DELIVERY {
+        assert!(factory.receive_worker(apartment));
+        assert_eq!(factory.update(factory.work_time, false), vec![apartment]);
+        assert_eq!(factory.shifts_worked, shift);
+        assert_eq!(factory.deliveries_ready, 0);
+    }
+    assert!(factory.production_progress() > 0.0 && factory.production_progress() < 1.0);
+    assert!(factory.receive_worker(apartment));
+    factory.update(factory.work_time, false);
+    assert_eq!(factory.deliveries_ready, 1);
+    assert_eq!(factory.shifts_worked, 0);
+
+    // Without raw materials the finished shifts wait for a delivery to come in
+    factory.materials_per_delivery = 2;
+    factory.materials = 1;
+    for _ in 0..FACTORY_SHIFTS_PER_DELIVERY + 1 {
+        assert!(factory.receive_worker(apartment));
+    }
+    factory.update(factory.work_time, true);
+    assert_eq!(factory.shifts_worked, FACTORY_SHIFTS_PER_DELIVERY);
+    assert_eq!(factory.deliveries_ready, 1);
+    factory.materials = 3;
+    factory.update(0.1, true);
+    assert_eq!(factory.deliveries_ready, 2);
+    assert_eq!(factory.materials, 1);
+    assert_eq!(factory.production_progress(), 0.0);
+}
+
+#[test]
+fn test_content_pack_rejects_mismatched_vehicle() {
+    let pack = ContentPack::from_toml_str(
+        r#"
+        [[vehicles]]
+        name = "hatchback"
+        base = "car"
+        min_speed = 2.0
+        max_speed = 4.0
+
+        [[buildings]]
+        name = "depot"
+        kind = "factory"
+        vehicle = "hatchback"
+        "#,
+    )
+    .unwrap();
+
+    let mut world = SimWorld::new();
+    assert!(world.content.register_pack(pack).is_err());
+    assert!(world.content.vehicles().is_empty());
+}
+
+#[test]
+fn test_content_pack_rejects_unusable_building_values() {
+    for (kind, field) in [
+        ("factory", "work_time = 0.0"),
+        ("factory", "shifts_per_delivery = 0"),
+        ("factory", "max_deliveries = 0"),
+        ("apartment", "cars = 0"),
+        ("shop", "cost = -50"),
+    ] {
+        let pack = ContentPack::from_toml_str(&format!(
+            "[[buildings]]\nname = \"odd\"\nkind = \"{}\"\n{}\n",
+            kind, field
+        ))
+        .unwrap();
+        let mut world = SimWorld::new();
+        assert!(
+            world.content.register_pack(pack).is_err(),
+            "{} accepted",
+            field
+        );
+        assert!(world.content.building("odd").is_none());
+    }
+}
+
+#[test]
+fn test_achievements_unlock_from_game_state() {
+    let mut world = SimWorld::new_with_game();
+    let mut tracker = AchievementTracker::default();
+    assert!(tracker.evaluate(&world).is_empty());
+
+    let game_state = world.game_state.as_mut().unwrap();
+    game_state.complete_shop_delivery();
+    game_state.money = 50;
+    assert_eq!(tracker.evaluate(&world), vec![Achievement::FirstDelivery]);
+
+    // Unlocks are only reported once
+    assert!(tracker.evaluate(&world).is_empty());
+
+    // Staying solvent on low funds for long enough unlocks the budget achievement
+    world.time += 61.0;
+    assert_eq!(
+        tracker.evaluate(&world),
+        vec![Achievement::ShoestringBudget]
+    );
+    assert!(!tracker.is_unlocked(Achievement::HundredDeliveries));
+}
+
+#[test]
+fn test_achievements_persist_to_disk() {
+    let path = std::env::temp_dir().join(format!(
+        "traffic_sim_achievements_{}.toml",
+        std::process::id()
+    ));
+    let mut tracker = AchievementTracker::default();
+    tracker.unlocked.insert(Achievement::FreeFlowingCity);
+    tracker.save(&path).unwrap();
+
+    let loaded = AchievementTracker::load(&path).unwrap();
+    std::fs::remove_file(&path).ok();
+    assert!(loaded.is_unlocked(Achievement::FreeFlowingCity));
+    assert_eq!(loaded.unlocked.len(), 1);
+}
+
+#[test]
+fn test_run_summary_score_is_verifiable() {
+    let mut game_state = GameState::new();
+    game_state.complete_worker_trip(COMMUTE_HEALTHY_DISTANCE, 0.0);
+    game_state.complete_shop_delivery();
+    game_state.time = 30.0;
+
+    let summary = RunSummary::from_game_state("test", &game_state);
+    assert_eq!(summary.average_commute, COMMUTE_HEALTHY_DISTANCE);
+    // Healthy commutes keep full trip points; no time bonus without a win
+    let money_points = i64::from(game_state.money) / 10;
+    assert_eq!(summary.score, 100 + 10 + money_points);
+    assert!(summary.verify());
+
+    // The summary survives a JSON round trip and still verifies
+    let loaded: RunSummary = serde_json::from_str(&summary.to_json().unwrap()).unwrap();
+    assert_eq!(loaded, summary);
+    assert!(loaded.verify());
+
+    // Tampering with the score or the statistics is detected
+    let mut tampered = summary.clone();
+    tampered.score += 1;
+    assert!(!tampered.verify());
+    let mut tampered = summary;
+    tampered.shop_deliveries += 1;
+    assert!(!tampered.verify());
+}
+
+#[test]
+fn test_best_scores_keep_highest_verified_run() {
+    let mut game_state = GameState::new();
+    game_state.complete_shop_delivery();
+    let first = RunSummary::from_game_state("test", &game_state);
+    game_state.complete_shop_delivery();
+    let better = RunSummary::from_game_state("test", &game_state);
+
+    let mut best_scores = BestScores::default();
+    assert!(best_scores.record(&first));
+    assert!(best_scores.record(&better));
+    assert!(!best_scores.record(&first));
+    assert_eq!(best_scores.best("test").unwrap().score, better.score);
+    assert!(best_scores.best("other").is_none());
+
+    // Runs with a score that doesn't match their statistics are never recorded
+    let mut forged = better.clone();
+    forged.score += 1000;
+    assert!(!best_scores.record(&forged));
+
+    let path = std::env::temp_dir().join(format!(
+        "traffic_sim_best_scores_{}.json",
+        std::process::id()
+    ));
+    best_scores.save(&path).unwrap();
+    let loaded = BestScores::load(&path).unwrap();
+    std::fs::remove_file(&path).ok();
+    assert_eq!(loaded.best("test"), Some(&better));
+}
+
+#[test]
+fn test_lockstep_client_mirrors_host() {
+    let setup = SessionSetup::new(7, EconomyConfig::default(), &ContentRegistry::default());
+    let mut host_world = setup.create_world().unwrap();
+    let mut host = LockstepHost::bind("127.0.0.1:0", setup).unwrap();
+    let addr = host.local_addr().unwrap();
+
+    // The host builds before anyone joins, so the client has history to replay
+    let building = |kind, x, z| BuildCommand::Building {
+        kind,
+        variant: None,
+        position: Position::new(x, 0.0, z),
+        snap_distance: 2.0,
+    };
+    host.step(
+        &mut host_world,
+        0.1,
+        vec![
+            BuildCommand::Road {
+                start: Position::new(-20.0, 0.0, 0.0),
+                end: Position::new(20.0, 0.0, 0.0),
+                snap_distance: 2.0,
+            },
+            building(BuildingKind::Apartment, -20.0, 0.0),
+            building(BuildingKind::Factory, 20.0, 0.0),
+        ],
+    );
+    for _ in 0..50 {
+        host.step(&mut host_world, 0.1, Vec::new());
+    }
+
+    // Joining blocks until the host admits the player during a step
+    let joining = std::thread::spawn(move || LockstepClient::connect(addr));
+    while !joining.is_finished() {
+        host.step(&mut host_world, 0.1, Vec::new());
+        std::thread::sleep(std::time::Duration::from_millis(5));
+    }
+    let mut client = joining.join().unwrap().unwrap();
+    let mut client_world = client.create_world().unwrap();
+    assert_eq!(client_world.apartments.len(), 1);
+
+    // A command from the client is built on the host and mirrored back
+    client.send(building(BuildingKind::Shop, 0.0, 3.0)).unwrap();
+    for _ in 0..200 {
+        if !host_world.shops.is_empty() {
+            break;
+        }
+        host.step(&mut host_world, 0.1, Vec::new());
+        std::thread::sleep(std::time::Duration::from_millis(5));
+    }
+    assert_eq!(host_world.shops.len(), 1);
+
+    for _ in 0..200 {
+        client.poll(&mut client_world).unwrap();
+        if client.tick() == host.tick() {
+            break;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(5));
+    }
+    assert_eq!(client.tick(), host.tick());
+    assert!(!client.is_desynced());
+    assert_eq!(client_world.shops.len(), 1);
+    assert_eq!(state_checksum(&client_world), state_checksum(&host_world));
+}
+
+#[test]
+fn test_spectator_plays_back_batched_turns() {
+    let mut setup = SessionSetup::new(11, EconomyConfig::default(), &ContentRegistry::default());
+    setup.test_world = true;
+    let mut host_world = setup.create_world().unwrap();
+    let mut host = LockstepHost::bind("127.0.0.1:0", setup).unwrap();
+    let addr = host.local_addr().unwrap();
+
+    let joining = std::thread::spawn(move || LockstepClient::spectate(addr));
+    while !joining.is_finished() {
+        host.step(&mut host_world, 0.25, Vec::new());
+        std::thread::sleep(std::time::Duration::from_millis(5));
+    }
+    let mut spectator = joining.join().unwrap().unwrap();
+    let mut spectator_world = spectator.create_world().unwrap();
+    assert!(spectator.is_spectator());
+    assert!(spectator
+        .send(BuildCommand::Road {
+            start: Position::new(0.0, 0.0, 0.0),
+            end: Position::new(10.0, 0.0, 0.0),
+            snap_distance: 2.0,
+        })
+        .is_err());
+
+    // Each step fills a whole batch, so the host has sent everything it ran
+    // whatever the join left in the spectator's batch
+    for _ in 0..20 {
+        host.step(&mut host_world, SPECTATOR_BATCH_SECS, Vec::new());
+    }
+    assert_eq!(host.spectator_count(), 1);
+    assert_eq!(host.client_count(), 0);
+
+    // Playback falls behind by more than the max delay and skips ahead
+    for _ in 0..200 {
+        spectator.poll_paced(&mut spectator_world, 0.25).unwrap();
+        if spectator.tick() == host.tick() {
+            break;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(5));
+    }
+    assert_eq!(spectator.tick(), host.tick());
+    assert!(!spectator.is_desynced());
+    assert_eq!(
+        state_checksum(&spectator_world),
+        state_checksum(&host_world)
+    );
+}
+
+#[test]
+fn test_autopilot_builds_a_profitable_city() {
+    let mut world = SimWorld::new_with_seed(42);
+    world.game_state = Some(GameState::new());
+    let mut autopilot = Autopilot::new();
+
+    for _ in 0..3000 {
+        autopilot.step(&mut world);
+        world.tick(0.1);
+    }
+
+    assert!(!world.apartments.is_empty());
+    assert!(!world.factories.is_empty());
+    assert!(!world.shops.is_empty());
+    let game_state = world.game_state.as_ref().unwrap();
+    assert!(!game_state.is_lost);
+    assert!(game_state.shop_deliveries_completed > 0);
+    // Grid spacing keeps every commute healthy
+    assert!(game_state.average_commute_distance() >= COMMUTE_HEALTHY_DISTANCE);
+}
+
+#[test]
+fn test_traffic_env_rewards_connecting_buildings() {
+    let config = EnvConfig {
+        grid_size: 3,
+        starting_buildings: [1, 1, 1],
+        ..EnvConfig::default()
+    };
+    let mut env = TrafficEnv::new(config.clone()).unwrap();
+    let mut replay = TrafficEnv::new(config).unwrap();
+    let observation = env.reset(3);
+    assert_eq!(replay.reset(3), observation);
+    assert_eq!(observation.len(), env.observation_size());
+    assert_eq!(env.action_count(), 1 + env.edges().len());
+    assert!(env.step_index(env.action_count()).is_err());
+
+    // Connect the whole grid, paying for every road
+    let mut total_reward = 0.0;
+    for action in 1..env.action_count() {
+        let step = env.step_index(action).unwrap();
+        assert!(step.action_applied);
+        assert_eq!(replay.step_index(action).unwrap(), step);
+        total_reward += step.reward;
+    }
+    // Building the same road twice does nothing
+    assert!(!env.step_index(1).unwrap().action_applied);
+    replay.step_index(1).unwrap();
+
+    for _ in 0..100 {
+        let step = env.step_index(0).unwrap();
+        assert_eq!(replay.step_index(0).unwrap(), step);
+        total_reward += step.reward;
+        if step.done() {
+            break;
+        }
+    }
+    assert!(total_reward > 0.0);
+    assert!(
+        env.world()
+            .game_state
+            .as_ref()
+            .unwrap()
+            .shop_deliveries_completed
+            > 0
+    );
+}
+
+#[test]
+fn test_traffic_env_times_signals() {
+    let config = EnvConfig {
+        grid_size: 2,
+        starting_buildings: [1, 1, 0],
+        actions: ActionConfig {
+            signal_timings: vec![5.0, 30.0],
+            ..ActionConfig::default()
+        },
+        ..EnvConfig::default()
+    };
+    let mut env = TrafficEnv::new(config.clone()).unwrap();
+    assert_eq!(env.action_count(), 1 + env.edges().len() + 4 * 2);
+    assert_eq!(
+        env.action(env.action_count() - 1),
+        Some(Action::SetSignalTiming {
+            point: 3,
+            timing: 1
+        })
+    );
+    let bad = EnvConfig {
+        actions: ActionConfig {
+            signal_timings: vec![0.5],
+            ..ActionConfig::default()
+        },
+        ..config
+    };
+    assert!(TrafficEnv::new(bad).is_err());
+
+    // A grid point with no roads in has nothing to time
+    let signal = Action::SetSignalTiming {
+        point: 0,
+        timing: 0,
+    };
+    assert!(!env.step(signal).action_applied);
+    for edge in 0..env.edges().len() {
+        assert!(env.step(Action::BuildRoad { edge }).action_applied);
+    }
+
+    // Timing a point puts a signal there with every phase that long...
+    assert!(env.step(signal).action_applied);
+    let point = env
+        .world()
+        .road_network
+        .find_closest_intersection(&Position::new(0.0, 0.0, 0.0));
+    let light = |env: &TrafficEnv| {
+        let intersection = &env.world().intersections[&point.unwrap()];
+        assert_eq!(intersection.control, IntersectionControl::TrafficLight);
+        intersection.signal.clone().unwrap()
+    };
+    let short = light(&env);
+    assert_eq!(short.phases().len(), 2);
+    assert!(short.phases().iter().all(|phase| phase.duration == 5.0));
+    assert!(short.time_left() <= 5.0);
+
+    // ...and retiming it keeps its phases but changes how long they last
+    assert!(
+        env.step(Action::SetSignalTiming {
+            point: 0,
+            timing: 1
+        })
+        .action_applied
+    );
+    let long = light(&env);
+    assert_eq!(long.cycle_secs(), 60.0);
+    assert!(long
+        .phases()
+        .iter()
+        .map(|phase| &phase.green)
+        .eq(short.phases().iter().map(|phase| &phase.green)));
+    assert!(long.time_left() > 5.0);
+}
+
+#[test]
+fn test_traffic_signal_holds_cars_at_red() {
+    let mut world = SimWorld::new_with_seed(1);
+    let west = world.add_intersection(Position::new(-10.0, 0.0, 0.0));
+    let middle = world.add_intersection(Position::new(0.0, 0.0, 0.0));
+    let east = world.add_intersection(Position::new(10.0, 0.0, 0.0));
+    let (from_west, _) = world.add_two_way_road(west, middle).unwrap();
+    let (_, from_east) = world.add_two_way_road(middle, east).unwrap();
+
+    let phases = world.default_signal_phases(middle);
+    assert_eq!(phases.len(), 2);
+
+    // Traffic from the east gets a long green first
+    let command = BuildCommand::Signal {
+        intersection: middle,
+        phases: Some(vec![
+            SignalPhase {
+                green: vec![from_east],
+                duration: 60.0,
+            },
+            SignalPhase {
+                green: vec![from_west],
+                duration: 5.0,
+            },
+        ]),
+    };
+    let json = serde_json::to_string(&command).unwrap();
+    assert_eq!(
+        serde_json::from_str::<BuildCommand>(&json).unwrap(),
+        command
+    );
+    assert!(world.apply_command(&command).unwrap());
+
+    world
+        .spawn_vehicle(west, east, VehicleType::Car, TripType::Outbound, None, None)
+        .unwrap();
+    for _ in 0..300 {
+        world.tick(0.1);
+    }
+    // Still waiting at the red light, at most a car length from it
+    let car = world.cars.values().next().expect("car waits at the signal");
+    assert_eq!(car.current_road, from_west);
+    assert!(car.position.x < 0.0 && car.position.x > -1.5);
+
+    for _ in 0..400 {
+        world.tick(0.1);
+    }
+    assert!(world.cars.is_empty());
+
+    // Timings copy by position onto an intersection with different roads
+    let copied = copy_timings(
+        world.intersections[&middle]
+            .signal
+            .as_ref()
+            .unwrap()
+            .phases(),
+        &world.default_signal_phases(east),
+    );
+    assert_eq!(copied.len(), 1);
+    assert_eq!(copied[0].duration, 60.0);
+
+    assert!(world
+        .apply_command(&BuildCommand::Signal {
+            intersection: middle,
+            phases: Some(Vec::new()),
+        })
+        .is_err());
+    world
+        .apply_command(&BuildCommand::Signal {
+            intersection: middle,
+            phases: None,
+        })
+        .unwrap();
+    assert!(world.intersections[&middle].signal.is_none());
+}
+
+#[test]
+fn test_road_restrictions_route_vehicles_by_type() {
+    let mut world = SimWorld::new_with_seed(1);
+    let a = world.add_intersection(Position::new(0.0, 0.0, 0.0));
+    let b = world.add_intersection(Position::new(10.0, 0.0, 10.0));
+    let c = world.add_intersection(Position::new(20.0, 0.0, 0.0));
+    let (short_cut, short_cut_back) = world.add_two_way_road(a, c).unwrap();
+    world.add_two_way_road(a, b).unwrap();
+    world.add_two_way_road(b, c).unwrap();
+
+    // A residential street closed to trucks, in both directions
+    let command = BuildCommand::RestrictRoad {
+        road: short_cut,
+        restriction: RoadRestriction::NoTrucks,
+    };
+    let json = serde_json::to_string(&command).unwrap();
+    assert_eq!(
+        serde_json::from_str::<BuildCommand>(&json).unwrap(),
+        command
+    );
+    assert!(world.apply_command(&command).unwrap());
+    let back = world.road_network.get_road(short_cut_back).unwrap();
+    assert_eq!(back.restriction, RoadRestriction::NoTrucks);
+
+    assert_eq!(
+        world.road_network.find_path_for(a, c, VehicleType::Car),
+        Some(vec![c])
+    );
+    assert_eq!(
+        world.road_network.find_path_for(a, c, VehicleType::Truck),
+        Some(vec![b, c])
+    );
+    let truck = world
+        .spawn_vehicle(a, c, VehicleType::Truck, TripType::Outbound, None, None)
+        .unwrap();
+    assert_ne!(world.cars[&truck].current_road, short_cut);
+
+    // Splitting a restricted road keeps the restriction on both halves
+    world
+        .split_road_at_position(short_cut, Position::new(10.0, 0.0, 0.0))
+        .unwrap();
+    assert!(world.road_network.get_road(short_cut).is_none());
+    let restricted = world
+        .road_network
+        .roads()
+        .values()
+        .filter(|road| road.restriction == RoadRestriction::NoTrucks)
+        .count();
+    assert_eq!(restricted, 4);
+
+    // Cars can't reach a destination only served by a trucks-only road
+    let d = world.add_intersection(Position::new(20.0, 0.0, -10.0));
+    let (to_d, _) = world.add_two_way_road(c, d).unwrap();
+    world
+        .set_road_restriction(to_d, RoadRestriction::TrucksOnly)
+        .unwrap();
+    assert!(world
+        .spawn_vehicle(a, d, VehicleType::Car, TripType::Outbound, None, None)
+        .is_err());
+    assert!(world
+        .road_network
+        .find_path_for(a, d, VehicleType::Truck)
+        .is_some());
+
+    // A bus lane is closed to everything but buses and emergencies
+    world
+        .set_road_restriction(to_d, RoadRestriction::BusOnly)
+        .unwrap();
+    assert_eq!(RoadRestriction::TrucksOnly.next(), RoadRestriction::BusOnly);
+    for vehicle_type in [VehicleType::Car, VehicleType::Truck, VehicleType::Van] {
+        assert!(world
+            .road_network
+            .find_path_for(a, d, vehicle_type)
+            .is_none());
+    }
+    assert!(world
+        .road_network
+        .find_path_for(a, d, VehicleType::Ambulance)
+        .is_some());
+    assert!(world.road_network.find_bus_path(a, d).is_some());
+}
+
+#[test]
+fn test_school_and_hospital_trip_profiles() {
+    let mut world = SimWorld::new_with_seed(1);
+    let home = world.add_intersection(Position::new(0.0, 0.0, 0.0));
+    let site = world.add_intersection(Position::new(10.0, 0.0, 0.0));
+    world.add_two_way_road(home, site).unwrap();
+    world.add_apartment(home);
+
+    let command = BuildCommand::Special {
+        kind: SpecialKind::School,
+        position: Position::new(10.0, 0.0, 0.0),
+        snap_distance: 1.0,
+    };
+    let json = serde_json::to_string(&command).unwrap();
+    assert_eq!(
+        serde_json::from_str::<BuildCommand>(&json).unwrap(),
+        command
+    );
+    assert!(world.apply_command(&command).unwrap());
+    let school = *world.special_buildings.keys().next().unwrap();
+
+    // The day starts at 8am, inside the morning drop-off peak
+    let profile = &world.special_buildings[&school].profile;
+    assert!(profile.trips_per_hour(8.0) > 0.0);
+    assert_eq!(profile.trips_per_hour(12.0), 0.0);
+    for _ in 0..400 {
+        world.tick(0.1);
+    }
+    let visits = world.special_buildings[&school].visits_completed;
+    assert!(
+        visits >= 20,
+        "only {} drop-offs in the morning peak",
+        visits
+    );
+    assert!(world.cars.is_empty());
+
+    // Nobody drives to school in the middle of the day
+    for _ in 0..200 {
+        world.tick(0.1);
+    }
+    assert!(world.time_of_day() < 15.0);
+    assert_eq!(world.special_buildings[&school].visits_completed, visits);
+    assert!(world.cars.is_empty());
+
+    // An ambulance runs a red light that never turns green for its road
+    let mut world = SimWorld::new_with_seed(1);
+    let hospital_site = world.add_intersection(Position::new(-10.0, 0.0, 0.0));
+    let middle = world.add_intersection(Position::new(0.0, 0.0, 0.0));
+    let home = world.add_intersection(Position::new(10.0, 0.0, 0.0));
+    let side = world.add_intersection(Position::new(0.0, 0.0, 10.0));
+    let (from_hospital, _) = world.add_two_way_road(hospital_site, middle).unwrap();
+    let (_, from_home) = world.add_two_way_road(middle, home).unwrap();
+    let (_, from_side) = world.add_two_way_road(middle, side).unwrap();
+    world.add_apartment(home);
+    world
+        .set_signal(
+            middle,
+            Some(vec![
+                SignalPhase {
+                    green: vec![from_side],
+                    duration: 120.0,
+                },
+                SignalPhase {
+                    green: vec![from_hospital, from_home],
+                    duration: 2.0,
+                },
+            ]),
+        )
+        .unwrap();
+    assert!(!world.intersections[&middle]
+        .signal
+        .as_ref()
+        .unwrap()
+        .is_green(from_hospital));
+    assert!(!world.intersections[&middle]
+        .signal
+        .as_ref()
+        .unwrap()
+        .is_green(from_home));
+
+    let hospital = world.add_special_building(hospital_site, SpecialKind::Hospital);
+    let building = world.special_buildings.get_mut(&hospital).unwrap();
+    building.profile.base_trips_per_hour = 0.0;
+    building.profile.emergencies_per_hour = 60.0;
+    for _ in 0..300 {
+        world.tick(0.1);
+    }
+    let building = &world.special_buildings[&hospital];
+    assert!(building.emergency_runs_completed >= 1);
+    assert_eq!(building.visits_completed, 0);
+}
+
+#[test]
+fn test_cul_de_sac_is_built_and_paid_for_at_once() {
+    let mut world = SimWorld::new_with_game();
+    let main_start = world.add_intersection(Position::new(0.0, 0.0, 0.0));
+    let main_end = world.add_intersection(Position::new(0.0, 0.0, 40.0));
+    world.add_two_way_road(main_start, main_end).unwrap();
+    let economy = world.game_state.as_ref().unwrap().economy.clone();
+
+    // Branch off the middle of the main road, splitting it
+    let command = BuildCommand::CulDeSac {
+        start: Position::new(0.5, 0.0, 20.0),
+        end: Position::new(20.0, 0.0, 20.0),
+        lots: 4,
+        snap_distance: 1.0,
+    };
+    let json = serde_json::to_string(&command).unwrap();
+    assert_eq!(
+        serde_json::from_str::<BuildCommand>(&json).unwrap(),
+        command
+    );
+    assert!(world.apply_command(&command).unwrap());
+    assert_eq!(
+        world.game_state.as_ref().unwrap().money,
+        STARTING_BUDGET - cul_de_sac_cost(&economy, 4)
+    );
+    assert_eq!(world.apartments.len(), 4);
+    // Main road halves, four street segments, one more to the dead end and a
+    // driveway per lot
+    assert_eq!(world.road_network.roads().len(), 2 * (2 + 5 + 4));
+    for apartment in world.apartments.values() {
+        assert!(world
+            .road_network
+            .find_path(main_start, apartment.intersection_id)
+            .is_some());
+    }
+
+    // Nothing is built or charged without the money for all of it
+    let roads = world.road_network.roads().len();
+    let intersections = world.intersections.len();
+    world.game_state.as_mut().unwrap().money = cul_de_sac_cost(&economy, 4) - 1;
+    assert!(!world.apply_command(&command).unwrap());
+    assert_eq!(world.road_network.roads().len(), roads);
+    assert_eq!(world.intersections.len(), intersections);
+    assert_eq!(world.apartments.len(), 4);
+
+    // Nor for a street too short for its lots
+    let money = world.game_state.as_ref().unwrap().money;
+    assert!(world
+        .apply_command(&BuildCommand::CulDeSac {
+            start: Position::new(-10.0, 0.0, 0.0),
+            end: Position::new(-14.0, 0.0, 0.0),
+            lots: 3,
+            snap_distance: 1.0,
+        })
+        .is_err());
+    assert_eq!(world.game_state.as_ref().unwrap().money, money);
+    assert_eq!(world.intersections.len(), intersections);
+}
+
+#[test]
+fn test_buildings_follow_placement_rules() {
+    let mut world = SimWorld::new_with_economy(EconomyConfig::default());
+    let west = world.add_intersection(Position::new(0.0, 0.0, 0.0));
+    let middle = world.add_intersection(Position::new(20.0, 0.0, 0.0));
+    let east = world.add_intersection(Position::new(40.0, 0.0, 0.0));
+    world.add_two_way_road(west, middle).unwrap();
+    world.add_two_way_road(middle, east).unwrap();
+    let building = |kind, x, z| BuildCommand::Building {
+        kind,
+        variant: None,
+        position: Position::new(x, 0.0, z),
+        snap_distance: 1.0,
+    };
+
+    // A dead end takes one building and no more
+    assert!(world
+        .apply_command(&building(BuildingKind::Apartment, 0.0, 0.0))
+        .unwrap());
+    let money = world.game_state.as_ref().unwrap().money;
+    let roads = world.road_network.road_count();
+    let refused = |world: &mut SimWorld, command: BuildCommand, reason: &str| {
+        let err = world.apply_command(&command).unwrap_err().to_string();
+        assert!(err.contains(reason), "unexpected reason: {}", err);
+    };
+    refused(
+        &mut world,
+        building(BuildingKind::Factory, 0.5, 0.0),
+        "already a building",
+    );
+
+    // Nor can one go where the road runs straight through, or on the road
+    refused(
+        &mut world,
+        building(BuildingKind::Shop, 20.0, 0.0),
+        "straight through",
+    );
+    refused(
+        &mut world,
+        building(BuildingKind::Shop, 10.0, 0.5),
+        "middle of a road",
+    );
+    let far = MAX_DRIVEWAY_LENGTH + 1.0;
+    refused(
+        &mut world,
+        building(BuildingKind::Shop, 10.0, far),
+        "within",
+    );
+    assert_eq!(world.game_state.as_ref().unwrap().money, money);
+    assert_eq!(world.road_network.road_count(), roads);
+    assert_eq!(world.shops.len() + world.factories.len(), 0);
+
+    // Beside the road, it gets a driveway to the road, paid for as a road
+    let beside = Position::new(10.0, 0.0, 3.0);
+    assert!(matches!(
+        world.building_site(beside, 1.0),
+        Ok(BuildingSite::Driveway { join, .. }) if join == Position::new(10.0, 0.0, 0.0)
+    ));
+    assert!(world
+        .apply_command(&building(BuildingKind::Shop, beside.x, beside.z))
+        .unwrap());
+    assert_eq!(
+        world.game_state.as_ref().unwrap().money,
+        money - COST_SHOP - COST_ROAD
+    );
+    // The road is split where the driveway joins it
+    assert_eq!(world.road_network.road_count(), roads + 4);
+    let shop = world.shops.values().next().unwrap().intersection_id;
+    assert_eq!(
+        world.road_network.get_intersection_position(shop),
+        Some(&beside)
+    );
+    assert!(world.road_network.is_reachable(west, shop));
+    assert!(world.road_network.is_reachable(shop, east));
+
+    // Money for the building alone isn't enough, and nothing is charged
+    world.game_state.as_mut().unwrap().money = COST_FACTORY;
+    let intersections = world.intersections.len();
+    assert!(!world
+        .apply_command(&building(BuildingKind::Factory, 30.0, -3.0))
+        .unwrap());
+    assert_eq!(world.game_state.as_ref().unwrap().money, COST_FACTORY);
+    assert_eq!(world.intersections.len(), intersections);
+    world.game_state.as_mut().unwrap().money = COST_ROAD;
+    assert!(!world
+        .apply_command(&building(BuildingKind::Factory, 30.0, -3.0))
+        .unwrap());
+    assert_eq!(world.game_state.as_ref().unwrap().money, COST_ROAD);
+    assert_eq!(world.intersections.len(), intersections);
+    assert_eq!(world.road_network.road_count(), roads + 4);
+}
+
+#[test]
+fn test_two_way_roads_are_paired() {
+    let mut world = SimWorld::new_with_seed(1);
+    let a = world.add_intersection(Position::new(0.0, 0.0, 0.0));
+    let b = world.add_intersection(Position::new(20.0, 0.0, 0.0));
+    let (forward, backward) = world.add_two_way_road(a, b).unwrap();
+    let pair = world.road_network.two_way_road(backward).unwrap();
+    assert_eq!((pair.forward, pair.backward), (forward, backward));
+    assert_eq!(world.road_network.canonical_road(backward), forward);
+    assert_eq!(world.road_network.opposite_road(forward), Some(backward));
+
+    // Splitting a two-way road leaves two paired halves, and the car
+    // waiting to set off along it sets off along the near half instead
+    let car = world
+        .spawn_vehicle(b, a, VehicleType::Car, TripType::Outbound, None, None)
+        .unwrap();
+    let (middle, ..) = world
+        .split_road_at_position(forward, Position::new(10.0, 0.0, 0.0))
+        .unwrap();
+    let near_half = world.road_network.find_road_between(b, middle).unwrap();
+    assert_eq!(world.cars[&car].current_road, near_half);
+    assert_eq!(world.cars[&car].path, vec![middle, a]);
+    world.check_invariants().unwrap();
+    assert_eq!(world.road_network.road_count(), 4);
+    let halves: Vec<_> = world.road_network.two_way_roads().collect();
+    assert_eq!(halves.len(), 2);
+
+    // Making a half one-way removes only its other direction
+    let half = halves[0].backward;
+    let command = BuildCommand::SetTwoWay {
+        road: half,
+        two_way: false,
+    };
+    let json = serde_json::to_string(&command).unwrap();
+    assert_eq!(
+        serde_json::from_str::<BuildCommand>(&json).unwrap(),
+        command
+    );
+    assert!(world.apply_command(&command).unwrap());
+    assert!(world.road_network.get_road(halves[0].forward).is_none());
+    assert!(!world.road_network.get_road(half).unwrap().is_two_way);
+    assert_eq!(world.road_network.canonical_road(half), half);
+    assert_eq!(world.road_network.two_way_roads().count(), 1);
+
+    // Two-way roads can't be reversed; one-way ones can, and can be paired
+    // up again
+    assert!(world
+        .apply_command(&BuildCommand::ReverseRoad {
+            road: halves[1].forward
+        })
+        .is_err());
+    let (start, end) = {
+        let road = world.road_network.get_road(half).unwrap();
+        (road.start_intersection, road.end_intersection)
+    };
+    let reversed = world.reverse_one_way_road(half).unwrap();
+    assert_eq!(
+        world.road_network.find_road_between(end, start).unwrap(),
+        reversed
+    );
+    assert!(world.road_network.find_road_between(start, end).is_err());
+    world.set_road_two_way(reversed, true).unwrap();
+    assert_eq!(world.road_network.two_way_roads().count(), 2);
+
+    // Removing a two-way road takes both directions with it
+    world.remove_two_way_road(start, end).unwrap();
+    assert_eq!(world.road_network.road_count(), 2);
+    assert_eq!(world.road_network.two_way_roads().count(), 1);
+}
+
+#[test]
+fn test_moving_an_intersection_reshapes_its_roads() {
+    let mut world = SimWorld::new_with_seed(1);
+    let a = world.add_intersection(Position::new(0.0, 0.0, 0.0));
+    let b = world.add_intersection(Position::new(10.0, 0.0, 0.0));
+    let c = world.add_intersection(Position::new(10.0, 0.0, 10.0));
+    let (a_to_b, _) = world.add_two_way_road(a, b).unwrap();
+    world.add_two_way_road(b, c).unwrap();
+    world.add_shop(b);
+
+    let car = world
+        .spawn_vehicle(a, c, VehicleType::Car, TripType::Outbound, None, None)
+        .unwrap();
+    for _ in 0..10 {
+        world.tick(0.1);
+    }
+    let before = &world.cars[&car];
+    assert_eq!(before.current_road, a_to_b);
+    let share = before.distance_along_road.into_inner() / 10.0;
+
+    // Stretch the road the car is on to twice its length
+    let command = BuildCommand::MoveIntersection {
+        intersection: b,
+        position: Position::new(20.0, 0.0, 0.0),
+    };
+    let json = serde_json::to_string(&command).unwrap();
+    assert_eq!(
+        serde_json::from_str::<BuildCommand>(&json).unwrap(),
+        command
+    );
+    assert!(world.apply_command(&command).unwrap());
+
+    let road = world.road_network.get_road(a_to_b).unwrap();
+    assert_eq!(road.length, 20.0);
+    assert_eq!(
+        world.intersections[&b].position,
+        Position::new(20.0, 0.0, 0.0)
+    );
+    let after = &world.cars[&car];
+    assert!((after.distance_along_road.into_inner() / 20.0 - share).abs() < 1e-5);
+    assert!((after.position.x / 20.0 - share).abs() < 0.05);
+
+    // The car still finds its way, and the shop moved with its intersection
+    for _ in 0..600 {
+        world.tick(0.1);
+    }
+    assert!(!world.cars.contains_key(&car));
+    let shop = world.shops.values().next().unwrap();
+    assert_eq!(shop.intersection_id, b);
+
+    // Roads can't be squashed to nothing
+    assert!(world
+        .move_intersection(b, Position::new(0.5, 0.0, 0.0))
+        .is_err());
+    assert_eq!(
+        world.intersections[&b].position,
+        Position::new(20.0, 0.0, 0.0)
+    );
+}
+
+#[test]
+fn test_incidents_charge_cleanup_unless_insured() {
+    // Every vehicle breaks down as soon as it can
+    let economy = EconomyConfig {
+        breakdowns_per_vehicle_hour: 1.0e6,
+        collisions_per_congested_road_hour: 0.0,
+        ..EconomyConfig::default()
+    };
+    let mut world = SimWorld::new_with_economy(economy);
+    world.game_state.as_mut().unwrap().money = 1000;
+    let home = world.add_intersection(Position::new(0.0, 0.0, 0.0));
+    let work = world.add_intersection(Position::new(20.0, 0.0, 0.0));
+    world.add_two_way_road(home, work).unwrap();
+    world.add_apartment(home);
+    world.add_factory(work);
+
+    world.tick(0.1);
+    let car = world.cars.values().next().expect("a worker should set off");
+    let (car_id, stalled_at) = (car.id, car.position);
+    assert!(car.stalled_secs > 0.0);
+    let game_state = world.game_state.as_mut().unwrap();
+    assert_eq!(game_state.incidents, 1);
+    assert_eq!(game_state.money, 1000 - COST_BREAKDOWN_CLEANUP);
+    assert_eq!(
+        game_state.take_events(),
+        vec![GameEvent::Incident {
+            kind: IncidentKind::Breakdown,
+            cost: COST_BREAKDOWN_CLEANUP,
+        }]
+    );
+
+    // The broken-down car stays put, and the next car waits at home
+    // rather than setting off on top of it
+    world.tick(0.1);
+    assert_eq!(world.cars[&car_id].position, stalled_at);
+    assert_eq!(world.spawn_queue_len(home), 1);
+    let game_state = world.game_state.as_ref().unwrap();
+    assert_eq!(game_state.incidents, 1);
+    assert_eq!(game_state.money, 1000 - COST_BREAKDOWN_CLEANUP);
+
+    // Insurance pays its first premium up front and covers cleanup after
+    let command = BuildCommand::SetInsurance { insured: true };
+    let json = serde_json::to_string(&command).unwrap();
+    assert_eq!(
+        serde_json::from_str::<BuildCommand>(&json).unwrap(),
+        command
+    );
+    assert!(world.apply_command(&command).unwrap());
+    let money = world.game_state.as_ref().unwrap().money;
+    assert_eq!(money, 1000 - COST_BREAKDOWN_CLEANUP - INSURANCE_PREMIUM);
+    for _ in 0..(BREAKDOWN_STALL_SECS * 10.0) as usize {
+        world.tick(0.1);
+    }
+    let game_state = world.game_state.as_mut().unwrap();
+    assert!(game_state.incidents > 1);
+    assert_eq!(game_state.money, money);
+    assert!(game_state.take_events().contains(&GameEvent::Incident {
+        kind: IncidentKind::Breakdown,
+        cost: 0,
+    }));
+
+    // A premium that can't be paid ends the policy
+    game_state.money = INSURANCE_PREMIUM - 1;
+    for _ in 0..(INSURANCE_PERIOD_SECS * 10.0) as usize {
+        world.tick(0.1);
+    }
+    let game_state = world.game_state.as_mut().unwrap();
+    assert!(!game_state.insured);
+    assert!(game_state
+        .take_events()
+        .contains(&GameEvent::InsuranceLapsed));
+
+    // Without a game there are no incidents
+    let mut world = SimWorld::new_with_seed(1);
+    let home = world.add_intersection(Position::new(0.0, 0.0, 0.0));
+    let work = world.add_intersection(Position::new(20.0, 0.0, 0.0));
+    world.add_two_way_road(home, work).unwrap();
+    world.add_apartment(home);
+    world.add_factory(work);
+    world.tick(0.1);
+    assert!(world.cars.values().all(|car| car.stalled_secs == 0.0));
+}
+
+#[test]
+fn test_vehicles_wear_out_until_replaced() {
+    // Wear on its own, without breakdowns
+    let record = VehicleRecord {
+        bought_at: 0.0,
+        mileage: VEHICLE_WORN_OUT_MILEAGE / 4.0,
+    };
+    assert_eq!(record.wear(0.0), 0.25);
+    assert_eq!(record.wear(VEHICLE_WORN_OUT_AGE_SECS / 2.0), 0.5);
+    assert_eq!(record.wear(VEHICLE_WORN_OUT_AGE_SECS * 2.0), 1.0);
+    assert_eq!(wear_speed_factor(0.0), 1.0);
+    assert!(wear_speed_factor(1.0) < 1.0);
+    assert!(wear_breakdown_factor(1.0) > wear_breakdown_factor(0.0));
+
+    let economy = EconomyConfig {
+        breakdowns_per_vehicle_hour: 0.0,
+        ..EconomyConfig::default()
+    };
+    let mut world = SimWorld::new_with_seed(1);
+    world.game_state = Some(GameState::with_economy(economy));
+    let home = world.add_intersection(Position::new(0.0, 0.0, 0.0));
+    let work = world.add_intersection(Position::new(20.0, 0.0, 0.0));
+    world.add_two_way_road(home, work).unwrap();
+    let apartment = world.add_apartment(home);
+    world.add_factory(work);
+
+    // Mileage is kept on the apartment, outliving the trips
+    for _ in 0..100 {
+        world.tick(0.1);
+    }
+    let mileage: f32 = world.apartments[&apartment]
+        .vehicles
+        .iter()
+        .map(|record| record.mileage)
+        .sum();
+    assert!(mileage > 0.0);
+    assert_eq!(world.worn_vehicle_count(), 0);
+
+    // Old vehicles drive worn out
+    for record in &mut world.apartments.get_mut(&apartment).unwrap().vehicles {
+        record.bought_at = world.time - VEHICLE_WORN_OUT_AGE_SECS;
+    }
+    world.tick(0.1);
+    assert!(world.cars.values().any(|car| car.wear == 1.0));
+    assert_eq!(world.worn_vehicle_count(), 10);
+    assert!(world.average_fleet_wear() > 0.5);
+
+    // Replacing them needs the money for all of them
+    world.game_state.as_mut().unwrap().money = COST_VEHICLE_REPLACEMENT * 10 - 1;
+    assert!(!world
+        .apply_command(&BuildCommand::ReplaceWornVehicles)
+        .unwrap());
+    assert_eq!(world.worn_vehicle_count(), 10);
+
+    world.game_state.as_mut().unwrap().money = COST_VEHICLE_REPLACEMENT * 10;
+    world.game_state.as_mut().unwrap().take_events();
+    assert!(world
+        .apply_command(&BuildCommand::ReplaceWornVehicles)
+        .unwrap());
+    assert_eq!(world.worn_vehicle_count(), 0);
+    let game_state = world.game_state.as_mut().unwrap();
+    assert_eq!(game_state.money, 0);
+    assert_eq!(
+        game_state.take_events(),
+        vec![GameEvent::VehiclesReplaced { count: 10 }]
+    );
+    assert!(world.apartments[&apartment]
+        .vehicles
+        .iter()
+        .all(|record| record.mileage == 0.0));
+}
+
+/// Renderer that records what it was asked to draw
+#[derive(Default)]
+struct RecordingRenderer {
+    spawned: Vec<VisualId>,
+    updated: Vec<Visual>,
+    despawned: Vec<VisualId>,
+    shown: HashMap<VisualId, Visual>,
+}
+
+impl SimRenderer for RecordingRenderer {
+    fn spawn_visual(&mut self, visual: &Visual) {
+        self.spawned.push(visual.id);
+        self.shown.insert(visual.id, visual.clone());
+    }
+
+    fn update_visual(&mut self, visual: &Visual) {
+        self.updated.push(visual.clone());
+        self.shown.insert(visual.id, visual.clone());
+    }
+
+    fn despawn_visual(&mut self, id: VisualId) {
+        self.despawned.push(id);
+        self.shown.remove(&id);
+    }
+}
+
+#[test]
+fn test_render_sync_spawns_updates_and_despawns_visuals() {
+    let mut world = SimWorld::new();
+    let home = world.add_intersection(Position::new(0.0, 0.0, 0.0));
+    let work = world.add_intersection(Position::new(10.0, 0.0, 0.0));
+    world.add_two_way_road(home, work).unwrap();
+    let apartment = world.add_apartment(home);
+
+    // Everything is spawned once; a two-way road is a single visual
+    let mut sync = RenderSync::default();
+    let mut renderer = RecordingRenderer::default();
+    sync.sync(&mut world, &mut renderer);
+    assert_eq!(renderer.spawned.len(), 4);
+    assert!(renderer.spawned.contains(&VisualId::Apartment(apartment)));
+    assert_eq!(sync.len(), 4);
+
+    // Nothing changed, so nothing is redrawn
+    let mut renderer = RecordingRenderer::default();
+    sync.sync(&mut world, &mut renderer);
+    assert!(renderer.spawned.is_empty() && renderer.updated.is_empty());
+
+    // Moving an intersection updates it, its road and its building
+    world
+        .move_intersection(home, Position::new(0.0, 0.0, 5.0))
+        .unwrap();
+    let mut renderer = RecordingRenderer::default();
+    sync.sync(&mut world, &mut renderer);
+    assert_eq!(renderer.updated.len(), 3);
+    assert!(renderer.updated.iter().any(|visual| matches!(
+        visual.kind,
+        VisualKind::Road { start, .. } if start == Position::new(0.0, 0.0, 5.0)
+    )));
+
+    world.remove_apartment(apartment);
+    let mut renderer = RecordingRenderer::default();
+    sync.sync(&mut world, &mut renderer);
+    assert_eq!(renderer.despawned, vec![VisualId::Apartment(apartment)]);
+    assert_eq!(sync.len(), 3);
+}
+
+#[test]
+fn test_render_sync_spreads_spawning_over_syncs() {
+    let mut world = SimWorld::create_test_world_with_seed(5);
+    for _ in 0..50 {
+        world.tick(0.1);
+    }
+    let total = world.visuals().len();
+    let budget = 10;
+    assert!(total > 3 * budget);
+
+    let mut sync = RenderSync::with_spawn_budget(budget);
+    let mut renderer = RecordingRenderer::default();
+    sync.sync(&mut world, &mut renderer);
+    assert_eq!(renderer.spawned.len(), budget);
+    // The layout comes first, so the roads appear before the traffic
+    assert!(renderer
+        .spawned
+        .iter()
+        .all(|id| matches!(id, VisualId::Intersection(_) | VisualId::Road(_))));
+    assert!(sync.pending_spawns() > 0);
+    let first = sync.loading_progress().unwrap();
+    assert!(first > 0.0 && first < 1.0);
+
+    // Each sync spawns at most the budget while the world keeps moving, and
+    // the renderer ends up showing all of it
+    let mut syncs = 1;
+    while sync.loading_progress().is_some() {
+        let spawned = renderer.spawned.len();
+        world.tick(0.1);
+        sync.sync(&mut world, &mut renderer);
+        assert!(renderer.spawned.len() - spawned <= budget);
+        syncs += 1;
+        assert!(syncs < 100, "spawning never caught up");
+    }
+    assert_eq!(sync.pending_spawns(), 0);
+    let expected: HashMap<VisualId, Visual> = world
+        .visuals()
+        .into_iter()
+        .map(|visual| (visual.id, visual))
+        .collect();
+    assert_eq!(renderer.shown, expected);
+}
+
+#[test]
+fn test_render_sync_switches_between_worlds() {
+    let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/saves")
+        .join(format!("v{}.json", SAVE_VERSION));
+    let mut save = SaveFile::load(&path).unwrap();
+    let mut live = save.create_world().unwrap();
+    for _ in 0..50 {
+        live.tick(0.1);
+    }
+    save.record_game_state(&live);
+    let mut copy = save.create_world().unwrap();
+    for _ in 0..20 {
+        copy.tick(0.1);
+    }
+
+    let mut sync = RenderSync::default();
+    let mut renderer = RecordingRenderer::default();
+    sync.sync(&mut live, &mut renderer);
+
+    // The copy shares the live world's layout, so only the traffic is redrawn
+    renderer.spawned.clear();
+    renderer.despawned.clear();
+    sync.resync();
+    sync.sync(&mut copy, &mut renderer);
+    assert!(renderer
+        .spawned
+        .iter()
+        .chain(&renderer.despawned)
+        .all(|id| matches!(id, VisualId::Vehicle(_))));
+    let expected: HashMap<VisualId, Visual> = copy
+        .visuals()
+        .into_iter()
+        .map(|visual| (visual.id, visual))
+        .collect();
+    assert_eq!(renderer.shown, expected);
+
+    // Switching back shows the live world as it is now
+    live.tick(0.1);
+    sync.resync();
+    sync.sync(&mut live, &mut renderer);
+    let expected: HashMap<VisualId, Visual> = live
+        .visuals()
+        .into_iter()
+        .map(|visual| (visual.id, visual))
+        .collect();
+    assert_eq!(renderer.shown, expected);
+}
+
+#[test]
+fn test_world_changes_only_list_what_the_tick_touched() {
+    let mut world = SimWorld::new_with_seed(1);
+    let home = world.add_intersection(Position::new(0.0, 0.0, 0.0));
+    let work = world.add_intersection(Position::new(20.0, 0.0, 0.0));
+    let shop_site = world.add_intersection(Position::new(0.0, 0.0, 20.0));
+    world.add_two_way_road(home, work).unwrap();
+    world.add_two_way_road(home, shop_site).unwrap();
+    let apartment = world.add_apartment(home);
+    let factory = world.add_factory(work);
+    let shop = world.add_shop(shop_site);
+
+    // The first catch-up is always a full one
+    assert!(world.take_changes().full);
+    assert!(!world.take_changes().full);
+
+    // A car leaving home changes the car and its apartment, nothing else
+    world.tick(0.1);
+    let changes = world.take_changes();
+    assert!(!changes.full);
+    assert!(changes.visuals.contains(&VisualId::Apartment(apartment)));
+    assert!(changes
+        .visuals
+        .iter()
+        .any(|id| matches!(id, VisualId::Vehicle(_))));
+    assert!(!changes.visuals.contains(&VisualId::Factory(factory)));
+    assert!(!changes.visuals.contains(&VisualId::Shop(shop)));
+    assert!(!changes
+        .visuals
+        .iter()
+        .any(|id| matches!(id, VisualId::Road(_) | VisualId::Intersection(_))));
+
+    // Building or knocking down a building changes just that building;
+    // editing the roads asks for a full catch-up
+    world.remove_shop(shop);
+    let changes = world.take_changes();
+    assert!(!changes.full);
+    assert!(changes.visuals.contains(&VisualId::Shop(shop)));
+    world.add_intersection(Position::new(-20.0, 0.0, 0.0));
+    assert!(world.take_changes().full);
+
+    // Syncing only the changes keeps a renderer showing the whole world
+    let mut sync = RenderSync::default();
+    let mut renderer = RecordingRenderer::default();
+    for tick in 0..100 {
+        world.tick(0.1);
+        if tick == 50 {
+            world.add_shop(shop_site);
+            world.remove_apartment(apartment);
+        }
+        sync.sync(&mut world, &mut renderer);
+        let expected: HashMap<VisualId, Visual> = world
+            .visuals()
+            .into_iter()
+            .map(|visual| (visual.id, visual))
+            .collect();
+        assert_eq!(renderer.shown, expected);
+    }
+}
+
+#[test]
+fn test_sim_events_report_what_happened() {
+    let mut world = SimWorld::create_test_world_with_seed(3);
+    let heard = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    let listener = heard.clone();
+    world.add_event_listener(move |event| listener.lock().unwrap().push(*event));
+
+    // Nothing is queued until events are first drained
+    world.tick(0.1);
+    assert!(world.drain_events().is_empty());
+    assert!(!heard.lock().unwrap().is_empty());
+    heard.lock().unwrap().clear();
+
+    let mut events = Vec::new();
+    for _ in 0..6000 {
+        world.tick(0.1);
+        events.extend(world.drain_events());
+    }
+    assert_eq!(*heard.lock().unwrap(), events);
+    assert!(!events.iter().any(SimEvent::is_layout));
+
+    // Every vehicle that left had set off first, and the ones that
+    // haven't left are still out
+    let mut out = BTreeSet::new();
+    for event in &events {
+        match event {
+            SimEvent::CarSpawned { car, .. } => assert!(out.insert(*car)),
+            SimEvent::CarDespawned { car } => {
+                out.remove(car);
+            }
+            _ => {}
+        }
+    }
+    assert!(out.iter().all(|car| world.cars.contains_key(car)));
+    let count = |matches: fn(&SimEvent) -> bool| events.iter().filter(|e| matches(e)).count();
+    let deliveries = count(|e| matches!(e, SimEvent::DeliveryCompleted { .. }));
+    assert!(deliveries > 0);
+    assert!(count(|e| matches!(e, SimEvent::WorkerArrived { .. })) > 0);
+    assert_eq!(
+        deliveries,
+        world
+            .shops
+            .values()
+            .map(|shop| shop.cars_received)
+            .sum::<usize>()
+    );
+
+    // Knocking down an intersection takes its roads and buildings with it
+    let (&road_id, road) = world.road_network.roads().iter().next().unwrap();
+    let intersection = road.end_intersection;
+    let buildings: Vec<PlacedBuilding> = world
+        .apartments
+        .values()
+        .filter(|a| a.intersection_id == intersection)
+        .map(|a| PlacedBuilding::Apartment(a.id))
+        .chain(
+            world
+                .factories
+                .values()
+                .filter(|f| f.intersection_id == intersection)
+                .map(|f| PlacedBuilding::Factory(f.id)),
+        )
+        .collect();
+    world.remove_intersection(intersection).unwrap();
+    let events = world.drain_events();
+    assert!(events.contains(&SimEvent::RoadRemoved { road: road_id }));
+    assert!(events.contains(&SimEvent::IntersectionRemoved { intersection }));
+    for building in buildings {
+        assert!(events.contains(&SimEvent::BuildingRemoved { building }));
+    }
+}
+
+#[test]
+fn test_traffic_volume_accumulates_and_exports() {
+    let mut world = SimWorld::create_test_world_with_seed(7);
+    let road_ids: Vec<_> = world.road_network.roads().keys().copied().collect();
+    let mut previous: HashMap<_, u32> = HashMap::new();
+
+    for _ in 0..40 {
+        for _ in 0..25 {
+            world.tick(0.05);
+        }
+        for road_id in &road_ids {
+            let volume = world.road_network.traffic_volume(*road_id);
+            // Totals never go down, and every vehicle on a road was counted
+            // when it entered
+            assert!(volume >= previous.get(road_id).copied().unwrap_or(0));
+            assert!(volume as usize >= world.road_network.get_cars_on_road(*road_id).len());
+            previous.insert(*road_id, volume);
+        }
+    }
+    let total: u32 = previous.values().sum();
+    assert!(total > 0, "vehicles should have driven onto some roads");
+    assert_eq!(
+        world.road_network.max_traffic_volume(),
+        *previous.values().max().unwrap()
+    );
+
+    let path = std::env::temp_dir().join(format!("traffic_sim_roads_{}.csv", std::process::id()));
+    world.save_road_stats_csv(&path).unwrap();
+    let csv = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).ok();
+
+    let mut lines = csv.lines();
+    assert_eq!(lines.next(), Some(ROAD_STATS_CSV_HEADER));
+    let rows: Vec<Vec<&str>> = lines.map(|line| line.split(',').collect()).collect();
+    assert_eq!(rows.len(), world.road_network.road_count());
+    let exported: u32 = rows.iter().map(|row| row[6].parse::<u32>().unwrap()).sum();
+    assert_eq!(exported, total);
+}
+
+#[test]
+fn test_trip_statistics_break_down_by_purpose() {
+    let mut world = SimWorld::create_test_world_with_seed(3);
+    assert!(world.trip_statistics().is_empty());
+    for _ in 0..2000 {
+        world.tick(0.05);
+    }
+
+    let rows = world.trip_statistics();
+    let count = |purpose: TripPurpose, vehicle_type: VehicleType| {
+        rows.iter()
+            .find(|row| row.purpose == purpose && row.vehicle_type == vehicle_type)
+            .map_or(0, |row| row.stats.trips)
+    };
+    assert!(count(TripPurpose::Commute, VehicleType::Car) > 0);
+    assert!(count(TripPurpose::Freight, VehicleType::Truck) > 0);
+    assert!(count(TripPurpose::Return, VehicleType::Car) > 0);
+
+    for row in &rows {
+        assert!(row.stats.trips > 0);
+        assert!(row.stats.average_secs() > 0.0);
+        assert!(row.stats.longest_secs >= row.stats.average_secs());
+    }
+    // Rows come in purpose order
+    assert!(rows
+        .windows(2)
+        .all(|pair| pair[0].purpose <= pair[1].purpose));
+}
+
+#[test]
+fn test_emissions_count_fuel_burned_on_completed_trips() {
+    // Standing still burns fuel at the idle rate; speeding up costs more
+    // than cruising at the same speed
+    let car = VehicleType::Car.fuel_profile();
+    assert!((car.litres(0.0, 3600.0, 0.0, 0.0) - car.idle_litres_per_hour).abs() < 1e-4);
+    assert!(car.litres(1.0, 0.1, 0.0, 10.0) > car.litres(1.0, 0.1, 10.0, 10.0));
+    assert_eq!(
+        car.litres(1.0, 0.1, 10.0, 0.0),
+        car.litres(1.0, 0.1, 0.0, 0.0)
+    );
+    let truck = VehicleType::Truck.fuel_profile();
+    assert!(truck.litres(200.0, 20.0, 10.0, 10.0) > car.litres(200.0, 20.0, 10.0, 10.0));
+
+    let mut world = SimWorld::create_test_world_with_seed(3);
+    assert_eq!(world.emissions().trips, 0);
+    assert_eq!(world.emissions().idle_share(), None);
+    for _ in 0..2000 {
+        world.tick(0.05);
+    }
+
+    let city = world.emissions();
+    let rows = world.trip_statistics();
+    assert_eq!(
+        city.trips,
+        rows.iter().map(|row| row.stats.trips).sum::<u32>()
+    );
+    assert!(city.fuel_litres > 0.0);
+    for row in &rows {
+        let profile = row.vehicle_type.fuel_profile();
+        assert!(row.stats.fuel_litres > 0.0);
+        let co2 = row.stats.fuel_litres * profile.co2_kg_per_litre;
+        assert!((row.stats.co2_kg - co2).abs() < 1e-3);
+        assert!(row.stats.idle_secs <= row.stats.total_secs);
+    }
+    let idle_share = city.idle_share().unwrap();
+    assert!((0.0..=1.0).contains(&idle_share));
+    assert!((city.co2_per_trip() * city.trips as f32 - city.co2_kg).abs() < 1e-3);
+
+    let csv = world.emissions_csv();
+    let mut lines = csv.lines();
+    assert_eq!(lines.next(), Some(EMISSIONS_CSV_HEADER));
+    let total: Vec<&str> = lines.last().unwrap().split(',').collect();
+    assert_eq!(total[0], "total");
+    assert_eq!(total[2].parse::<u32>().unwrap(), city.trips);
+    assert_eq!(csv.lines().count(), rows.len() + 2);
+}
+
+#[test]
+fn test_labor_stats_show_factories_workers_cant_reach() {
+    let mut world = SimWorld::create_test_world_with_seed(3);
+    assert_eq!(world.labor_totals().employment_rate(), None);
+    // A factory off the road network never gets its workers
+    let stranded_site = world.add_intersection(Position::new(45.0, 0.0, 45.0));
+    let stranded = world.add_factory(stranded_site);
+    for _ in 0..3000 {
+        world.tick(0.1);
+    }
+
+    let totals = world.labor_totals();
+    assert!(totals.fulfilled > 0);
+    assert!(totals.requested >= totals.fulfilled + totals.rejected);
+
+    let factories = world.labor_by_factory();
+    let stranded_counts = factories
+        .iter()
+        .find(|(id, _)| *id == stranded)
+        .map(|(_, counts)| *counts)
+        .unwrap();
+    assert!(stranded_counts.requested > 0);
+    assert_eq!(stranded_counts.employment_rate(), Some(0.0));
+    let reached = factories.iter().filter(|(id, _)| *id != stranded);
+    assert!(reached
+        .clone()
+        .all(|(_, counts)| counts.employment_rate().unwrap() > 0.0));
+    let requested: u32 = factories.iter().map(|(_, counts)| counts.requested).sum();
+    assert_eq!(requested, totals.requested);
+    let fulfilled: u32 = world
+        .labor_by_apartment()
+        .iter()
+        .map(|(_, counts)| counts.fulfilled)
+        .sum();
+    assert_eq!(fulfilled, totals.fulfilled);
+
+    let csv = world.labor_stats_csv();
+    let mut lines = csv.lines();
+    assert_eq!(lines.next(), Some(LABOR_STATS_CSV_HEADER));
+    let rows: Vec<Vec<&str>> = lines.map(|line| line.split(',').collect()).collect();
+    assert_eq!(
+        rows.len(),
+        world.labor_by_day().len() + factories.len() + world.labor_by_apartment().len()
+    );
+    let stranded_row = rows
+        .iter()
+        .find(|row| row[0] == "factory" && row[1] == stranded.0 .0.to_string())
+        .unwrap();
+    assert_eq!(stranded_row[5], "0.000");
+
+    // Once a road reaches it, the waiting workers get to work the next day
+    let corner = world
+        .intersections
+        .values()
+        .find(|intersection| intersection.position == Position::new(30.0, 0.0, 30.0))
+        .map(|intersection| intersection.id)
+        .unwrap();
+    world.add_two_way_road(corner, stranded_site).unwrap();
+    for _ in 0..3000 {
+        world.tick(0.1);
+    }
+    let days = world.labor_by_day();
+    assert!(days.len() > 1);
+    assert!(days.windows(2).all(|pair| pair[0].day < pair[1].day));
+    assert!(days.last().unwrap().counts.fulfilled > 0);
+    let (_, stranded_counts) = world
+        .labor_by_factory()
+        .into_iter()
+        .find(|(id, _)| *id == stranded)
+        .unwrap();
+    assert!(stranded_counts.fulfilled > 0);
+}
+
+#[test]
+fn test_run_report_documents_the_run() {
+    let mut world = SimWorld::create_test_world_with_seed(3);
+    world.game_state = Some(GameState::new());
+    for _ in 0..1000 {
+        world.tick(0.1);
+    }
+
+    assert_eq!(
+        ReportFormat::for_path(Path::new("run.HTML")),
+        ReportFormat::Html
+    );
+    assert_eq!(
+        ReportFormat::for_path(Path::new("run.htm")),
+        ReportFormat::Html
+    );
+    assert_eq!(
+        ReportFormat::for_path(Path::new("run.md")),
+        ReportFormat::Markdown
+    );
+    assert_eq!(
+        ReportFormat::for_path(Path::new("run")),
+        ReportFormat::Markdown
+    );
+
+    let map = world.map_svg();
+    assert!(map.starts_with("<svg") && map.trim_end().ends_with("</svg>"));
+    assert_eq!(
+        map.matches("<circle").count(),
+        world.apartments.len() + world.factories.len() + world.shops.len()
+    );
+    assert_eq!(
+        map.matches("<line").count(),
+        world.road_network.road_count()
+    );
+
+    let markdown = world.run_report("Seed 3", ReportFormat::Markdown);
+    assert!(markdown.starts_with("# Seed 3\n"));
+    assert!(markdown.contains("![Map of the city](data:image/svg+xml;base64,PHN2Zy"));
+    for heading in [
+        "Summary",
+        "Money over time",
+        "Houses",
+        "Factories",
+        "Shops",
+        "Most congested roads",
+    ] {
+        assert!(
+            markdown.contains(&format!("\n## {}\n", heading)),
+            "no {} table",
+            heading
+        );
+    }
+    let money = world.game_state.as_ref().unwrap().money;
+    assert!(markdown.contains(&format!("| Money | ${} |", money)));
+    let house_rows = markdown
+        .split("## Houses")
+        .nth(1)
+        .unwrap()
+        .split("\n## ")
+        .next()
+        .unwrap()
+        .lines()
+        .filter(|line| line.starts_with("| ") && !line.starts_with("| House"))
+        .count();
+    assert_eq!(house_rows, world.apartments.len());
+    let road_rows = markdown
+        .split("## Most congested roads")
+        .nth(1)
+        .unwrap()
+        .lines()
+        .filter(|line| line.starts_with("| ") && !line.starts_with("| Road"))
+        .count();
+    assert!(road_rows > 0 && road_rows <= REPORT_CONGESTED_ROADS);
+
+    let path = std::env::temp_dir().join(format!("traffic_sim_report_{}.html", std::process::id()));
+    world.save_run_report(&path, "Seed <3>").unwrap();
+    let html = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).ok();
+    assert!(html.contains("<h1>Seed &lt;3&gt;</h1>"));
+    assert!(html.contains(&map));
+    assert_eq!(html.matches("<table>").count(), 6);
+}
+
+#[test]
+fn test_shop_catchments_credit_factories_and_houses() {
+    let mut world = SimWorld::create_test_world_with_seed(3);
+    world.game_state = Some(GameState::new());
+    // A house off the road network never sends a worker, so no shop draws on it
+    let stranded_site = world.add_intersection(Position::new(45.0, 0.0, 45.0));
+    let stranded = world.add_apartment(stranded_site);
+    let shops = world.shops.len();
+    assert!(world
+        .shop_catchments()
+        .iter()
+        .all(|catchment| catchment.deliveries == 0 && catchment.apartments.is_empty()));
+    for _ in 0..3000 {
+        world.tick(0.1);
+    }
+
+    let catchments = world.shop_catchments();
+    assert_eq!(catchments.len(), shops);
+    assert!(catchments
+        .windows(2)
+        .all(|pair| pair[0].shop_id < pair[1].shop_id));
+    let deliveries: u32 = catchments
+        .iter()
+        .map(|catchment| catchment.deliveries)
+        .sum();
+    assert!(deliveries > 0);
+    for catchment in &catchments {
+        // Each delivery is credited whole to its factory and split between houses
+        let from_factories: f32 = catchment.factories.iter().map(|(_, a)| a.deliveries).sum();
+        assert_eq!(from_factories, catchment.deliveries as f32);
+        let from_houses: f32 = catchment.apartments.iter().map(|(_, a)| a.deliveries).sum();
+        assert!((from_houses - catchment.deliveries as f32).abs() < 1e-3);
+        let revenue: f32 = catchment.apartments.iter().map(|(_, a)| a.revenue).sum();
+        assert!((revenue - catchment.revenue as f32).abs() < 1e-2);
+        assert!(catchment.revenue <= catchment.deliveries as i32 * REVENUE_SHOP_DELIVERY);
+        assert!(catchment.apartments.iter().all(|(id, _)| *id != stranded));
+        if catchment.deliveries > 0 {
+            assert!(catchment.revenue > 0);
+            assert!(catchment.main_apartment().is_some());
+        }
+    }
+    assert!(world.underserved_apartments().contains(&stranded));
+}
+
+#[test]
+fn test_demand_ramp_grows_until_deliveries_fall_behind() {
+    let economy = EconomyConfig::from_toml_str(
+        r#"
+        [demand_ramp]
+        start_level = 1.0
+        curve = "exponential"
+        growth_per_minute = 1.0
+        max_level = 8.0
+        shop_orders_per_minute = 6.0
+        delivery_sla_secs = 30.0
+        "#,
+    )
+    .expect("economy TOML should parse");
+    let ramp = economy.demand_ramp.clone().expect("ramp should be set");
+    assert_eq!(ramp.curve, RampCurve::Exponential);
+    assert_eq!(ramp.level_at(0.0), 1.0);
+    assert_eq!(ramp.level_at(120.0), 4.0);
+    assert_eq!(ramp.level_at(600.0), 8.0);
+    let text = economy.to_toml_string().unwrap();
+    assert_eq!(EconomyConfig::from_toml_str(&text).unwrap(), economy);
+    assert!(EconomyConfig::from_toml_str("[demand_ramp]\nstart_level = 0.0").is_err());
+
+    // Without a ramp shops never order
+    let mut steady = SimWorld::create_test_world_with_seed(5);
+    steady.game_state = Some(GameState::new());
+    for _ in 0..2000 {
+        steady.tick(0.05);
+    }
+    assert_eq!(steady.open_orders(), 0);
+    assert_eq!(steady.demand_level(), 1.0);
+    assert!(steady.sla_breach().is_none());
+
+    let mut world = SimWorld::create_test_world_with_seed(5);
+    world.game_state = Some(GameState::with_economy(economy));
+    for _ in 0..20000 {
+        world.tick(0.05);
+        if world.sla_breach().is_some() {
+            break;
+        }
+    }
+    let breach = world
+        .sla_breach()
+        .expect("orders should outgrow the deliveries");
+    assert!(breach.level > 1.0 && breach.level <= 8.0);
+    assert!(breach.time > 30.0);
+    let events = world.game_state.as_mut().unwrap().take_events();
+    assert!(events.contains(&GameEvent::DeliverySlaBroken {
+        level: breach.level
+    }));
+}
+
+#[test]
+fn test_late_deliveries_earn_less_and_can_lose_the_game() {
+    let mut game_state = GameState::new();
+    game_state.complete_shop_delivery();
+    game_state.complete_late_shop_delivery();
+    assert_eq!(
+        game_state.money,
+        STARTING_BUDGET + REVENUE_SHOP_DELIVERY + REVENUE_SHOP_DELIVERY / 2
+    );
+    assert_eq!(game_state.late_deliveries, 1);
+    assert_eq!(game_state.on_time_rate(), 0.5);
+
+    // The on-time rate only loses the game once enough deliveries are in
+    let economy =
+        EconomyConfig::from_toml_str("min_on_time_rate = 0.8").expect("economy TOML should parse");
+    assert!(EconomyConfig::from_toml_str("late_delivery_revenue_share = 1.5").is_err());
+    let mut game_state = GameState::with_economy(economy.clone());
+    for _ in 0..3 {
+        game_state.complete_late_shop_delivery();
+    }
+    game_state.update(0.1);
+    assert!(!game_state.is_lost);
+    for _ in 0..7 {
+        game_state.complete_shop_delivery();
+    }
+    game_state.update(0.1);
+    assert!(game_state.is_lost);
+    assert!(game_state.missed_service_level());
+
+    // With a deadline no truck can meet, every delivery is late
+    let economy = EconomyConfig::from_toml_str("delivery_deadline_secs = 0.5")
+        .expect("economy TOML should parse");
+    let mut world = SimWorld::create_test_world_with_seed(5);
+    world.game_state = Some(GameState::with_economy(economy));
+    let mut saw_overdue = false;
+    for _ in 0..4000 {
+        world.tick(0.05);
+        saw_overdue |= world.cars.keys().any(|&car_id| {
+            matches!(
+                world
+                    .visual(VisualId::Vehicle(car_id))
+                    .map(|visual| visual.kind),
+                Some(VisualKind::Vehicle { overdue: true, .. })
+            )
+        });
+    }
+    assert!(saw_overdue, "late trucks should be marked overdue");
+    let game_state = world.game_state.as_ref().unwrap();
+    assert!(game_state.shop_deliveries_completed > 0);
+    assert_eq!(
+        game_state.late_deliveries,
+        game_state.shop_deliveries_completed
+    );
+    assert_eq!(game_state.on_time_rate(), 0.0);
+}
+
+#[test]
+fn test_recurring_warnings_are_rate_limited_and_counted() {
+    let mut log = WarningLog::default();
+    assert!(log.warn(SimWarning::TowTruckStuck, 0.0, || "stuck".to_string()));
+    let mut logged = 0;
+    for tick in 1..=1200 {
+        let time = tick as f32 * 0.1;
+        if log.warn(SimWarning::TowTruckStuck, time, || "stuck".to_string()) {
+            logged += 1;
+        }
+    }
+    // Once a minute over the two minutes
+    assert_eq!(logged, (120.0 / WARNING_INTERVAL_SECS) as usize);
+    assert!(log.warn(SimWarning::FerryDeck, 100.0, || "deck".to_string()));
+    assert_eq!(log.count(SimWarning::TowTruckStuck), 1201);
+    assert_eq!(log.count(SimWarning::VanStuck), 0);
+    assert_eq!(log.total(), 1202);
+    assert_eq!(
+        log.counts().collect::<Vec<_>>(),
+        [
+            (SimWarning::FerryDeck, 1),
+            (SimWarning::TowTruckStuck, 1201)
+        ]
+    );
+
+    // A healthy run raises none
+    let mut world = SimWorld::create_test_world_with_seed(5);
+    for _ in 0..2000 {
+        world.tick(0.1);
+    }
+    assert_eq!(world.warnings().total(), 0);
+}
+
+#[test]
+fn test_depot_tow_truck_clears_breakdowns() {
+    // The first worker breaks down as soon as they set off, and nothing else does
+    let economy = EconomyConfig {
+        breakdowns_per_vehicle_hour: 1.0e6,
+        collisions_per_congested_road_hour: 0.0,
+        ..EconomyConfig::default()
+    };
+    let build = |with_depot: bool| {
+        let mut world = SimWorld::new_with_economy(economy.clone());
+        let home = world.add_intersection(Position::new(0.0, 0.0, 0.0));
+        let work = world.add_intersection(Position::new(40.0, 0.0, 0.0));
+        let yard = world.add_intersection(Position::new(-10.0, 0.0, 0.0));
+        world.add_two_way_road(home, work).unwrap();
+        world.add_two_way_road(yard, home).unwrap();
+        if with_depot {
+            let command = BuildCommand::Special {
+                kind: SpecialKind::Depot,
+                position: Position::new(-10.0, 0.0, 0.0),
+                snap_distance: 1.0,
+            };
+            assert!(world.apply_command(&command).unwrap());
+        }
+        world.add_apartment(home);
+        world.add_factory(work);
+        world.tick(0.1);
+        world
+            .game_state
+            .as_mut()
+            .unwrap()
+            .economy
+            .breakdowns_per_vehicle_hour = 0.0;
+        let broken = world.breakdowns_awaiting_tow().to_vec();
+        assert_eq!(broken.len(), 1);
+        (world, broken[0])
+    };
+
+    let (mut world, broken) = build(true);
+    assert_eq!(
+        world.game_state.as_ref().unwrap().money,
+        STARTING_BUDGET - COST_DEPOT - COST_BREAKDOWN_CLEANUP
+    );
+    let depot = *world.special_buildings.keys().next().unwrap();
+    let tow_truck = world.special_buildings[&depot]
+        .tow_truck
+        .expect("the depot should send its tow truck");
+    assert_eq!(world.cars[&tow_truck].vehicle_type, VehicleType::TowTruck);
+    assert_eq!(world.cars[&tow_truck].purpose(), TripPurpose::Recovery);
+    for _ in 0..50 {
+        world.tick(0.1);
+    }
+    assert_eq!(world.cars[&broken].stalled_secs, 0.0);
+    assert!(world.breakdowns_awaiting_tow().is_empty());
+    assert_eq!(world.special_buildings[&depot].tows_completed, 1);
+    for _ in 0..50 {
+        world.tick(0.1);
+    }
+    assert!(world.special_buildings[&depot].tow_truck.is_none());
+
+    // Without a depot the breakdown takes its full time to clear
+    let (mut world, broken) = build(false);
+    for _ in 0..50 {
+        world.tick(0.1);
+    }
+    assert!(world.cars[&broken].stalled_secs > 0.0);
+    assert_eq!(world.breakdowns_awaiting_tow(), &[broken]);
+    for _ in 0..(BREAKDOWN_STALL_SECS * 10.0) as usize {
+        world.tick(0.1);
+    }
+    assert!(world.breakdowns_awaiting_tow().is_empty());
+}
+
+#[test]
+fn test_sensors_unlock_road_metrics() {
+    let mut world = SimWorld::new_with_game();
+    let home = world.add_intersection(Position::new(0.0, 0.0, 0.0));
+    let work = world.add_intersection(Position::new(40.0, 0.0, 0.0));
+    let (road, reverse) = world.add_two_way_road(home, work).unwrap();
+    world.add_apartment(home);
+    world.add_factory(work);
+    for _ in 0..50 {
+        world.tick(0.1);
+    }
+    assert!(world.sensors_required());
+    assert!(world.road_metrics(road).is_none());
+
+    // A sensor covers both directions and is only paid for once
+    let command = BuildCommand::SetSensor {
+        road,
+        installed: true,
+    };
+    let json = serde_json::to_string(&command).unwrap();
+    assert_eq!(
+        serde_json::from_str::<BuildCommand>(&json).unwrap(),
+        command
+    );
+    let money = world.game_state.as_ref().unwrap().money;
+    assert!(world.apply_command(&command).unwrap());
+    assert!(world.apply_command(&command).unwrap());
+    assert_eq!(
+        world.game_state.as_ref().unwrap().money,
+        money - COST_SENSOR
+    );
+    assert!(world.road_is_monitored(reverse));
+    let metrics = world.road_metrics(road).expect("the road has a sensor");
+    assert_eq!(metrics.volume, world.road_network.traffic_volume(road));
+    assert!(metrics.volume > 0);
+    assert_eq!(
+        metrics.vehicles,
+        world.road_network.get_cars_on_road(road).len()
+    );
+    assert!(metrics.queued <= metrics.vehicles);
+
+    // The sensor stays with both halves of a split road
+    let (_, first, second) = world
+        .split_road_at_position(road, Position::new(20.0, 0.0, 0.0))
+        .unwrap();
+    assert!(world.road_is_monitored(first) && world.road_is_monitored(second));
+
+    // Taking it off is free, and a new one needs the money for it
+    let money = world.game_state.as_ref().unwrap().money;
+    let remove = BuildCommand::SetSensor {
+        road: first,
+        installed: false,
+    };
+    assert!(world.apply_command(&remove).unwrap());
+    assert!(world.road_metrics(first).is_none());
+    assert_eq!(world.game_state.as_ref().unwrap().money, money);
+    world.game_state.as_mut().unwrap().money = COST_SENSOR - 1;
+    assert!(!world
+        .apply_command(&BuildCommand::SetSensor {
+            road: first,
+            installed: true,
+        })
+        .unwrap());
+    assert!(world.road_metrics(first).is_none());
+
+    // Without the requirement every road is monitored
+    let mut sandbox = SimWorld::new_with_economy(EconomyConfig {
+        sensors_required: false,
+        ..EconomyConfig::default()
+    });
+    let a = sandbox.add_intersection(Position::new(0.0, 0.0, 0.0));
+    let b = sandbox.add_intersection(Position::new(10.0, 0.0, 0.0));
+    let road = sandbox.add_road(a, b, false).unwrap();
+    assert!(sandbox.road_metrics(road).is_some());
+}
+
+#[test]
+fn test_signal_suggestions_rank_unsignalled_intersections() {
+    let mut world = SimWorld::create_test_world_with_seed(7);
+    assert!(world.signal_suggestions(5).is_empty());
+    for _ in 0..3000 {
+        world.tick(0.1);
+    }
+
+    let suggestions = world.signal_suggestions(5);
+    assert!(!suggestions.is_empty(), "the test city should have queues");
+    assert!(suggestions.len() <= 5);
+    for pair in suggestions.windows(2) {
+        assert!(pair[0].projected_savings_per_minute >= pair[1].projected_savings_per_minute);
+    }
+    for suggestion in &suggestions {
+        assert!(suggestion.approaches >= 2);
+        assert!(suggestion.wait_secs >= MIN_SUGGESTION_WAIT_SECS);
+        assert!(suggestion.projected_savings_per_minute > 0.0);
+        assert!(world.intersections[&suggestion.intersection]
+            .signal
+            .is_none());
+        if suggestion.treatment == Treatment::Signal {
+            assert!(suggestion.approaches >= 3);
+        }
+    }
+
+    // Taking the advice removes the intersection from the list
+    let best = suggestions[0].intersection;
+    assert!(world
+        .apply_command(&BuildCommand::Signal {
+            intersection: best,
+            phases: Some(world.default_signal_phases(best)),
+        })
+        .unwrap());
+    assert!(world
+        .signal_suggestions(usize::MAX)
+        .iter()
+        .all(|suggestion| suggestion.intersection != best));
+}
+
+#[test]
+fn test_rail_terminal_supplies_factories() {
+    let economy = EconomyConfig {
+        factory_supply_required: true,
+        breakdowns_per_vehicle_hour: 0.0,
+        collisions_per_congested_road_hour: 0.0,
+        ..EconomyConfig::default()
+    };
+    let mut world = SimWorld::new_with_economy(economy);
+    let home = world.add_intersection(Position::new(0.0, 0.0, 0.0));
+    let work = world.add_intersection(Position::new(30.0, 0.0, 0.0));
+    let store = world.add_intersection(Position::new(30.0, 0.0, 30.0));
+    let rail = world.add_intersection(Position::new(60.0, 0.0, 0.0));
+    world.add_two_way_road(home, work).unwrap();
+    world.add_two_way_road(work, store).unwrap();
+    world.add_two_way_road(work, rail).unwrap();
+    world.add_apartment(home);
+    let factory = world.add_factory(work);
+    world.add_shop(store);
+
+    // Workers come and go, but nothing is made without materials
+    for _ in 0..600 {
+        world.tick(0.1);
+    }
+    assert!(world.game_state.as_ref().unwrap().worker_trips_completed > 0);
+    assert_eq!(world.factories[&factory].deliveries_ready, 0);
+    assert_eq!(
+        world.game_state.as_ref().unwrap().shop_deliveries_completed,
+        0
+    );
+
+    let money = world.game_state.as_ref().unwrap().money;
+    assert!(world
+        .apply_command(&BuildCommand::Special {
+            kind: SpecialKind::Terminal,
+            position: Position::new(60.0, 0.0, 0.0),
+            snap_distance: 1.0,
+        })
+        .unwrap());
+    assert_eq!(
+        world.game_state.as_ref().unwrap().money,
+        money - COST_TERMINAL
+    );
+    let terminal = *world.special_buildings.keys().next().unwrap();
+
+    // The first train unloads and the van sets off with a load
+    for _ in 0..(TERMINAL_SHIPMENT_SECS * 10.0) as usize + 1 {
+        world.tick(0.1);
+    }
+    let van = world.special_buildings[&terminal]
+        .van
+        .expect("the terminal should send its van");
+    assert_eq!(world.cars[&van].vehicle_type, VehicleType::Van);
+    assert_eq!(world.cars[&van].purpose(), TripPurpose::Supply);
+    assert_eq!(
+        world.special_buildings[&terminal].materials,
+        TERMINAL_SHIPMENT_SIZE - VAN_LOAD
+    );
+
+    for _ in 0..1200 {
+        world.tick(0.1);
+    }
+    assert!(world.special_buildings[&terminal].supplies_delivered > 0);
+    assert!(world.factories[&factory].materials <= FACTORY_MATERIALS_CAPACITY);
+    assert!(world.game_state.as_ref().unwrap().shop_deliveries_completed > 0);
+
+    // Without the requirement the van stays at the terminal
+    let mut world = SimWorld::new_with_game();
+    let a = world.add_intersection(Position::new(0.0, 0.0, 0.0));
+    let b = world.add_intersection(Position::new(30.0, 0.0, 0.0));
+    world.add_two_way_road(a, b).unwrap();
+    world.add_factory(a);
+    let terminal = world.add_special_building(b, SpecialKind::Terminal);
+    for _ in 0..(TERMINAL_SHIPMENT_SECS * 10.0) as usize + 10 {
+        world.tick(0.1);
+    }
+    assert!(world.special_buildings[&terminal].van.is_none());
+    assert_eq!(
+        world.special_buildings[&terminal].materials,
+        TERMINAL_SHIPMENT_SIZE
+    );
+}
+
+#[test]
+fn test_cul_de_sacs_stay_out_of_the_water() {
+    let mut world = SimWorld::new_with_game();
+    world.add_water(WaterRegion::new(
+        Position::new(15.0, 0.0, -10.0),
+        Position::new(35.0, 0.0, 10.0),
+    ));
+    let money = world.game_state.as_ref().unwrap().money;
+
+    // A cul-de-sac over the water is turned down like a road over it
+    let road = BuildCommand::Road {
+        start: Position::new(0.0, 0.0, 0.0),
+        end: Position::new(50.0, 0.0, 0.0),
+        snap_distance: 1.0,
+    };
+    assert!(world.apply_command(&road).is_err());
+    let cul_de_sac = BuildCommand::CulDeSac {
+        start: Position::new(0.0, 0.0, 0.0),
+        end: Position::new(50.0, 0.0, 0.0),
+        lots: 4,
+        snap_distance: 1.0,
+    };
+    assert!(world.apply_command(&cul_de_sac).is_err());
+    assert_eq!(world.game_state.as_ref().unwrap().money, money);
+    assert_eq!(world.road_network.road_count(), 0);
+    assert!(world.intersections.is_empty());
+    assert!(world.apartments.is_empty());
+
+    // Nor may a driveway reach into it from a street alongside, though a
+    // street as far out on the other side is fine
+    assert!(world
+        .apply_command(&BuildCommand::Road {
+            start: Position::new(0.0, 0.0, 12.0),
+            end: Position::new(50.0, 0.0, 12.0),
+            snap_distance: 1.0,
+        })
+        .unwrap());
+    let money = world.game_state.as_ref().unwrap().money;
+    let roads = world.road_network.road_count();
+    let intersections = world.intersections.len();
+    assert!(world
+        .apply_command(&BuildCommand::CulDeSac {
+            start: Position::new(0.0, 0.0, -12.0),
+            end: Position::new(50.0, 0.0, -12.0),
+            lots: 4,
+            snap_distance: 1.0,
+        })
+        .is_err());
+    assert_eq!(world.game_state.as_ref().unwrap().money, money);
+    assert_eq!(world.road_network.road_count(), roads);
+    assert_eq!(world.intersections.len(), intersections);
+    assert!(world.apartments.is_empty());
+}
+
+#[test]
+fn test_water_needs_a_bridge_or_ferry() {
+    let economy = EconomyConfig {
+        breakdowns_per_vehicle_hour: 0.0,
+        collisions_per_congested_road_hour: 0.0,
+        ..EconomyConfig::default()
+    };
+    let mut world = SimWorld::new_with_economy(economy);
+    world.add_water(WaterRegion::new(
+        Position::new(10.0, 0.0, -50.0),
+        Position::new(20.0, 0.0, 50.0),
+    ));
+    let money = world.game_state.as_ref().unwrap().money;
+
+    // Nothing goes on or over the water for free
+    let road = BuildCommand::Road {
+        start: Position::new(0.0, 0.0, 0.0),
+        end: Position::new(30.0, 0.0, 0.0),
+        snap_distance: 1.0,
+    };
+    assert!(world.apply_command(&road).is_err());
+    let building = BuildCommand::Building {
+        kind: BuildingKind::Shop,
+        variant: None,
+        position: Position::new(15.0, 0.0, 30.0),
+        snap_distance: 1.0,
+    };
+    assert!(world.apply_command(&building).is_err());
+    let long_bridge = BuildCommand::Bridge {
+        start: Position::new(0.0, 0.0, 20.0),
+        end: Position::new(30.0, 0.0, 20.0),
+        snap_distance: 1.0,
+    };
+    assert!(world.apply_command(&long_bridge).is_err());
+    assert_eq!(world.game_state.as_ref().unwrap().money, money);
+    assert_eq!(world.road_network.road_count(), 0);
+
+    // A short bridge spans it
+    assert!(world
+        .apply_command(&BuildCommand::Bridge {
+            start: Position::new(8.0, 0.0, 20.0),
+            end: Position::new(22.0, 0.0, 20.0),
+            snap_distance: 1.0,
+        })
+        .unwrap());
+    assert_eq!(
+        world.game_state.as_ref().unwrap().money,
+        money - COST_BRIDGE
+    );
+    assert!(world
+        .road_network
+        .roads()
+        .values()
+        .all(|road| road.kind == RoadKind::Bridge));
+
+    // Workers commute over a ferry crossing, a few at a time
+    let home = world.add_intersection(Position::new(0.0, 0.0, 0.0));
+    let work = world.add_intersection(Position::new(30.0, 0.0, 0.0));
+    for (start, end) in [(0.0, 5.0), (25.0, 30.0)] {
+        assert!(world
+            .apply_command(&BuildCommand::Road {
+                start: Position::new(start, 0.0, 0.0),
+                end: Position::new(end, 0.0, 0.0),
+                snap_distance: 1.0,
+            })
+            .unwrap());
+    }
+    assert!(world
+        .apply_command(&BuildCommand::Ferry {
+            start: Position::new(5.0, 0.0, 0.0),
+            end: Position::new(25.0, 0.0, 0.0),
+            snap_distance: 1.0,
+        })
+        .unwrap());
+    assert_eq!(world.ferries.len(), 1);
+    world.add_apartment(home);
+    world.add_factory(work);
+
+    for _ in 0..3000 {
+        world.tick(0.1);
+        let ferry = world.ferries.values().next().unwrap();
+        assert!(ferry.aboard.len() <= FERRY_CAPACITY);
+    }
+    assert!(world.ferries.values().next().unwrap().crossings > 1);
+    assert!(world.game_state.as_ref().unwrap().worker_trips_completed > 0);
+}
+
+#[test]
+fn test_hills_cost_more_and_slow_climbs() {
+    // Flat from x = 0 to 10, then rising 5 by x = 20
+    let ramp = Heightmap {
+        min_x: 0.0,
+        min_z: 0.0,
+        cell_size: 10.0,
+        columns: 3,
+        rows: 2,
+        heights: vec![0.0, 0.0, 5.0, 0.0, 0.0, 5.0],
+    };
+    let mut world = SimWorld::new_with_game();
+    assert!(world
+        .set_heightmap(Heightmap {
+            heights: vec![0.0; 5],
+            ..ramp.clone()
+        })
+        .is_err());
+    world.set_heightmap(ramp).unwrap();
+
+    // Intersections sit on the ground
+    let low = world.add_intersection(Position::new(0.0, 0.0, 5.0));
+    let foot = world.add_intersection(Position::new(10.0, 0.0, 5.0));
+    let top = world.add_intersection(Position::new(20.0, 0.0, 5.0));
+    let top_height = world.road_network.get_intersection_position(top).unwrap().y;
+    assert!((top_height - 5.0).abs() < 1e-4);
+
+    // The climb costs more than the flat road
+    let money = world.game_state.as_ref().unwrap().money;
+    world.try_add_two_way_road(low, foot).unwrap().unwrap();
+    let flat_cost = money - world.game_state.as_ref().unwrap().money;
+    assert_eq!(flat_cost, COST_ROAD);
+    let money = world.game_state.as_ref().unwrap().money;
+    let (up, down) = world.try_add_two_way_road(foot, top).unwrap().unwrap();
+    let climb_cost = money - world.game_state.as_ref().unwrap().money;
+    assert_eq!(
+        climb_cost,
+        (COST_ROAD as f32 * slope_cost_factor(0.5)).round() as i32
+    );
+    assert!(climb_cost > flat_cost);
+
+    // Vehicles slow down going up, not coming down
+    let up_grade = world.road_network.get_road(up).unwrap().grade;
+    let down_grade = world.road_network.get_road(down).unwrap().grade;
+    assert!((up_grade - 0.5).abs() < 1e-4 && (down_grade + 0.5).abs() < 1e-4);
+    assert!(slope_speed_factor(up_grade) < 1.0);
+    assert_eq!(slope_speed_factor(down_grade), 1.0);
+    let car_id = world
+        .spawn_vehicle(foot, top, VehicleType::Car, TripType::Outbound, None, None)
+        .unwrap();
+    world.tick(0.1);
+    let car = &world.cars[&car_id];
+    assert!(car.current_speed > 0.0);
+    assert!(car.current_speed <= car.speed * slope_speed_factor(up_grade) + 1e-3);
+    // Climbing as fast as the hill allows is still free flow
+    let share = car.speed_share(&world.road_network).unwrap();
+    assert!((share - 1.0).abs() < 1e-3);
+    assert!(car.position.y > 0.0);
+
+    // Episodes with hills are raised off the ground the same way each time
+    let config = EnvConfig {
+        hills: 4.0,
+        ..EnvConfig::default()
+    };
+    let mut env = TrafficEnv::new(config.clone()).unwrap();
+    let mut replay = TrafficEnv::new(config).unwrap();
+    env.reset(7);
+    replay.reset(7);
+    let heights = |env: &TrafficEnv| -> Vec<f32> {
+        env.world()
+            .intersections
+            .values()
+            .map(|intersection| intersection.position.y)
+            .collect()
+    };
+    assert_eq!(heights(&env), heights(&replay));
+    assert!(heights(&env).iter().any(|height| *height > 0.0));
+}
+
+#[test]
+fn test_cul_de_sacs_on_slopes_cost_as_much_as_by_hand() {
+    // Flat from x = 0 to 10, then rising 5 by x = 20
+    let ramp = Heightmap {
+        min_x: 0.0,
+        min_z: 0.0,
+        cell_size: 10.0,
+        columns: 3,
+        rows: 2,
+        heights: vec![0.0, 0.0, 5.0, 0.0, 0.0, 5.0],
+    };
+    let (start, end, lots) = (
+        Position::new(2.0, 0.0, 5.0),
+        Position::new(18.0, 0.0, 5.0),
+        2,
+    );
+    let mut built = SimWorld::new_with_game();
+    built.set_heightmap(ramp.clone()).unwrap();
+    let mut by_hand = SimWorld::new_with_game();
+    by_hand.set_heightmap(ramp).unwrap();
+    let economy = built.game_state.as_ref().unwrap().economy.clone();
+
+    let cost = built.cul_de_sac_slope_cost(start, end, lots, 1.0);
+    assert!(cost > cul_de_sac_cost(&economy, lots));
+    assert!(built
+        .apply_command(&BuildCommand::CulDeSac {
+            start,
+            end,
+            lots,
+            snap_distance: 1.0,
+        })
+        .unwrap());
+    assert_eq!(
+        built.game_state.as_ref().unwrap().money,
+        STARTING_BUDGET - cost
+    );
+
+    // The street, then a driveway and apartment per lot
+    by_hand
+        .try_add_road_at_positions(start, end, 1.0)
+        .unwrap()
+        .unwrap();
+    for (along, lot_position) in cul_de_sac_lots(start, end, lots) {
+        let (_, lot, _, _) = by_hand
+            .try_add_road_at_positions(along, lot_position, 0.5)
+            .unwrap()
+            .unwrap();
+        by_hand.try_add_apartment(lot).unwrap();
+    }
+    assert_eq!(
+        by_hand.game_state.as_ref().unwrap().money,
+        built.game_state.as_ref().unwrap().money
+    );
+}
+
+#[test]
+fn test_decorations_raise_appeal() {
+    let mut world = SimWorld::new_with_economy(EconomyConfig::default());
+    world.add_water(WaterRegion::new(
+        Position::new(40.0, 0.0, -10.0),
+        Position::new(50.0, 0.0, 10.0),
+    ));
+    assert!(world
+        .apply_command(&BuildCommand::Road {
+            start: Position::new(0.0, 0.0, 0.0),
+            end: Position::new(20.0, 0.0, 0.0),
+            snap_distance: 1.0,
+        })
+        .unwrap());
+    let money = world.game_state.as_ref().unwrap().money;
+
+    // Nothing grows on the road or in the water
+    assert!(world
+        .add_decoration(DecorationKind::Tree, Position::new(10.0, 0.0, 0.5))
+        .is_err());
+    assert!(world
+        .add_decoration(DecorationKind::Tree, Position::new(45.0, 0.0, 0.0))
+        .is_err());
+    assert!(world.decorations.is_empty());
+
+    // A park beside the road pays for itself in appeal
+    let home = world
+        .road_network
+        .find_closest_intersection(&Position::new(0.0, 0.0, 0.0));
+    let apartment = world.add_apartment(home.unwrap());
+    assert_eq!(world.apartment_appeal(apartment), 0.0);
+    assert!(world
+        .apply_command(&BuildCommand::Decoration {
+            kind: DecorationKind::Park,
+            position: Position::new(2.0, 0.0, 5.0),
+        })
+        .unwrap());
+    assert_eq!(world.game_state.as_ref().unwrap().money, money - COST_PARK);
+    assert!(world.apartment_appeal(apartment) > 0.0);
+    assert_eq!(world.appeal_at(&Position::new(30.0, 0.0, 30.0)), 0.0);
+    assert!(world
+        .visuals()
+        .iter()
+        .any(|visual| visual.kind == VisualKind::Decoration(DecorationKind::Park)));
+
+    // Appeal tops out, and eases the penalty on a short commute
+    for step in 0..5 {
+        world
+            .add_decoration(DecorationKind::Park, Position::new(step as f32, 0.0, -5.0))
+            .unwrap();
+    }
+    assert_eq!(world.apartment_appeal(apartment), MAX_APPEAL);
+    let economy = EconomyConfig::default();
+    assert!(
+        economy.worker_trip_pay_with_appeal(0.0, 0.0, MAX_APPEAL)
+            > economy.worker_trip_pay(0.0, 0.0)
+    );
+    assert_eq!(
+        economy.worker_trip_pay_with_appeal(100.0, 0.0, MAX_APPEAL),
+        economy.worker_trip_pay(100.0, 0.0)
+    );
+}
+
+#[test]
+fn test_saves_of_every_version_load() {
+    let sample = |version: u32| {
+        let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/saves")
+            .join(format!("v{}.json", version));
+        SaveFile::load(&path).unwrap()
+    };
+
+    // Every released version has a sample save, and each still loads
+    for version in 0..=SAVE_VERSION {
+        let save = sample(version);
+        assert_eq!(save.version, SAVE_VERSION);
+        let world = save.create_world().unwrap();
+        assert!(world.road_network.road_count() >= 6);
+        assert_eq!(world.apartments.len(), 1);
+        assert_eq!(world.factories.len(), 1);
+        assert_eq!(world.shops.len(), 1);
+
+        // Loaded saves come back the same after saving them again
+        let reloaded = SaveFile::from_json_str(&save.to_json_string().unwrap()).unwrap();
+        assert_eq!(reloaded, save);
+        let replayed = reloaded.create_world().unwrap();
+        assert_eq!(state_checksum(&replayed), state_checksum(&world));
+        assert_eq!(replayed.state_hash(), world.state_hash());
+    }
+
+    // Version 0 paid for its commands as they were replayed; later saves
+    // keep the game's progress
+    let v0 = sample(0);
+    assert!(v0.game_state.is_none());
+    let world = v0.create_world().unwrap();
+    assert!(world.game_state.as_ref().unwrap().money < STARTING_BUDGET);
+    for version in 1..=SAVE_VERSION {
+        let save = sample(version);
+        let world = save.create_world().unwrap();
+        let money = save.game_state.as_ref().unwrap().money;
+        assert_eq!(world.game_state.as_ref().unwrap().money, money);
+        assert!(!world.water.is_empty() && world.heightmap.is_some());
+        assert_eq!(world.decorations.len(), 1);
+    }
+
+    // Saves before version 4 drove on the right; the later samples drive
+    // on the left
+    for version in 0..4 {
+        assert_eq!(sample(version).driving_side, DrivingSide::Right);
+    }
+    for version in 4..=SAVE_VERSION {
+        let save = sample(version);
+        assert_eq!(save.driving_side, DrivingSide::Left);
+        assert_eq!(save.create_world().unwrap().driving_side, DrivingSide::Left);
+    }
+
+    // Saves before version 5 had no metadata
+    for version in 0..5 {
+        assert!(sample(version).metadata.is_none());
+    }
+    let latest = sample(SAVE_VERSION);
+    let metadata = latest.metadata.as_ref().unwrap();
+    assert_eq!(metadata.money, latest.game_state.as_ref().map(|g| g.money));
+    assert!(metadata.thumbnail_png().unwrap().starts_with(b"\x89PNG"));
+
+    // Saves before version 6 are bounded by what they built; later ones
+    // keep their own bounds
+    for version in 0..6 {
+        let save = sample(version);
+        let bounds = save.bounds.unwrap();
+        assert!(save
+            .commands
+            .iter()
+            .flat_map(|saved| saved.command.positions())
+            .all(|p| bounds.contains(&p)));
+        assert_eq!(
+            save.create_world().unwrap().configured_bounds(),
+            Some(bounds)
+        );
+    }
+    assert_eq!(
+        latest.bounds,
+        Some(WorldBounds::new(
+            Position::new(-60.0, 0.0, -60.0),
+            Position::new(90.0, 0.0, 90.0)
+        ))
+    );
+
+    // Since version 2 commands made after traffic started still find the
+    // roads they were made for
+    let v2 = sample(2);
+    let world = v2.create_world().unwrap();
+    let restricted = v2
+        .commands
+        .iter()
+        .find_map(|saved| match saved.command {
+            BuildCommand::RestrictRoad { road, .. } => Some(road),
+            _ => None,
+        })
+        .unwrap();
+    assert_eq!(
+        world.road_network.get_road(restricted).unwrap().restriction,
+        RoadRestriction::NoTrucks
+    );
+
+    // Saves from a newer build are refused
+    let newer = format!(r#"{{"version": {}}}"#, SAVE_VERSION + 1);
+    assert!(SaveFile::from_json_str(&newer).is_err());
+    assert!(SaveFile::from_json_str("[]").is_err());
+}
+
+#[test]
+fn test_save_metadata_describes_the_city() {
+    let mut world = SimWorld::create_test_world_with_seed(5);
+    world.game_state = Some(GameState::new());
+    for _ in 0..600 {
+        world.tick(0.1);
+    }
+    let mut save = SaveFile::of_world(5, &world);
+    assert!(save.metadata.is_none());
+    save.record_game_state(&world);
+
+    let metadata = save.metadata.clone().unwrap();
+    let game_state = world.game_state.as_ref().unwrap();
+    assert_eq!(metadata.money, Some(game_state.money));
+    assert_eq!(metadata.deliveries, game_state.shop_deliveries_completed);
+    assert_eq!(metadata.play_time, world.time);
+    let summary = metadata.summary();
+    assert!(summary.starts_with("saved just now, ") && summary.contains(" played, $"));
+
+    // The thumbnail is the world's map, drawn without a front-end
+    let png = metadata.thumbnail_png().unwrap();
+    assert_eq!(png, world.map_png(THUMBNAIL_WIDTH));
+    assert!(png.starts_with(b"\x89PNG\r\n\x1a\n"));
+    assert_eq!(png[16..20], THUMBNAIL_WIDTH.to_be_bytes());
+    let height = u32::from_be_bytes(png[20..24].try_into().unwrap());
+    assert!((1..=THUMBNAIL_WIDTH * 4).contains(&height));
+
+    // It survives saving and loading
+    let reloaded = SaveFile::from_json_str(&save.to_json_string().unwrap()).unwrap();
+    assert_eq!(reloaded.metadata, Some(metadata));
+}
+
+#[test]
+fn test_save_round_trip_keeps_state_hash() {
+    let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/saves")
+        .join(format!("v{}.json", SAVE_VERSION));
+    let mut save = SaveFile::load(&path).unwrap();
+    let mut world = save.create_world().unwrap();
+
+    // Replace a road's other direction while traffic takes IDs in between
+    let road = *world.road_network.roads().keys().next().unwrap();
+    for two_way in [false, true] {
+        save.apply_commands(&mut world, &[BuildCommand::SetTwoWay { road, two_way }]);
+        for _ in 0..100 {
+            world.tick(0.1);
+        }
+    }
+    save.record_game_state(&world);
+    let reloaded = SaveFile::from_json_str(&save.to_json_string().unwrap()).unwrap();
+    let mut loaded = reloaded.create_world().unwrap();
+    assert_eq!(loaded.state_hash(), world.state_hash());
+
+    // Each carries on giving out the same IDs
+    let mut again = reloaded.create_world().unwrap();
+    let road = *world.road_network.roads().keys().last().unwrap();
+    for two_way in [false, true] {
+        let command = BuildCommand::SetTwoWay { road, two_way };
+        for world in [&mut world, &mut loaded, &mut again] {
+            assert!(world.apply_command(&command).unwrap());
+        }
+    }
+    assert_eq!(loaded.state_hash(), world.state_hash());
+
+    // Loading the save again plays out the same way
+    for _ in 0..200 {
+        loaded.tick(0.1);
+        again.tick(0.1);
+    }
+    assert_eq!(state_checksum(&loaded), state_checksum(&again));
+    assert_eq!(loaded.state_hash(), again.state_hash());
+}
+
+#[test]
+fn test_autosave_survives_a_crash() {
+    let path =
+        std::env::temp_dir().join(format!("traffic_sim_autosave_{}.json", std::process::id()));
+    let mut world = SimWorld::new_with_seed(3);
+    world.game_state = Some(GameState::new());
+    let mut save = SaveFile::of_world(3, &world);
+    let position = |x: f32, z: f32| Position::new(x, 0.0, z);
+    let road = |start: (f32, f32), end: (f32, f32)| BuildCommand::Road {
+        start: position(start.0, start.1),
+        end: position(end.0, end.1),
+        snap_distance: 1.0,
+    };
+    save.apply_commands(
+        &mut world,
+        &[
+            road((0.0, 0.0), (20.0, 0.0)),
+            road((20.0, 0.0), (20.0, 20.0)),
+            BuildCommand::Building {
+                kind: BuildingKind::Apartment,
+                variant: None,
+                position: position(0.0, 0.0),
+                snap_distance: 1.0,
+            },
+            BuildCommand::Building {
+                kind: BuildingKind::Factory,
+                variant: None,
+                position: position(20.0, 20.0),
+                snap_distance: 1.0,
+            },
+        ],
+    );
+    for _ in 0..300 {
+        world.tick(0.1);
+    }
+
+    // A session starts, autosaves in the background and never exits cleanly
+    assert!(!RunningMarker::left_behind(&path));
+    let marker = RunningMarker::create(&path).unwrap();
+    let mut autosaver = Autosaver::new(path.clone());
+    save.apply_commands(&mut world, &[road((20.0, 20.0), (40.0, 20.0))]);
+    let new_road = world
+        .road_network
+        .roads()
+        .keys()
+        .copied()
+        .max_by_key(|id| id.0 .0)
+        .unwrap();
+    save.apply_commands(
+        &mut world,
+        &[BuildCommand::SetTwoWay {
+            road: new_road,
+            two_way: false,
+        }],
+    );
+    assert_eq!(save.commands.len(), 6);
+    save.record_game_state(&world);
+    assert!(autosaver.autosave(&save).unwrap());
+    autosaver.finish().unwrap();
+    drop(marker);
+    assert!(RunningMarker::left_behind(&path));
+
+    // The next session restores the city as it was, funds and all
+    let restored = SaveFile::load(&path).unwrap();
+    assert_eq!(restored, save);
+    let restored_world = restored.create_world().unwrap();
+    assert_eq!(
+        restored_world.game_state.as_ref().unwrap().money,
+        world.game_state.as_ref().unwrap().money
+    );
+    assert_eq!(
+        restored_world.road_network.road_count(),
+        world.road_network.road_count()
+    );
+    assert!(restored_world.road_network.get_road(new_road).is_some());
+    assert!(restored_world.road_network.two_way_road(new_road).is_none());
+
+    // A clean exit clears the marker
+    RunningMarker::create(&path).unwrap().clear().unwrap();
+    assert!(!RunningMarker::left_behind(&path));
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_memory_report_and_entity_budgets() {
+    let mut world = SimWorld::create_test_world_with_seed(3);
+    let empty = SimWorld::new().memory_report();
+    let report = world.memory_report();
+    let names: Vec<_> = report
+        .categories
+        .iter()
+        .map(|category| category.name)
+        .collect();
+    assert_eq!(
+        names,
+        [
+            "cars",
+            "roads",
+            "intersections",
+            "buildings",
+            "caches",
+            "stats"
+        ]
+    );
+    assert_eq!(
+        report.category("roads").unwrap().entries,
+        world.road_network.road_count()
+    );
+    assert_eq!(
+        report.category("buildings").unwrap().entries,
+        world.building_count()
+    );
+    assert!(report.total_bytes() > empty.total_bytes());
+    assert_eq!(
+        report.total_bytes(),
+        report
+            .categories
+            .iter()
+            .map(|category| category.bytes)
+            .sum::<usize>()
+    );
+
+    // Cars take memory as they spawn
+    for _ in 0..200 {
+        world.tick(0.1);
+    }
+    assert!(!world.cars.is_empty());
+    let driving = world.memory_report();
+    assert_eq!(driving.category("cars").unwrap().entries, world.cars.len());
+    assert!(driving.category("cars").unwrap().bytes > report.category("cars").unwrap().bytes);
+
+    // Each budget is reported once when it is exceeded, and again only after
+    // the world has been back within it
+    let roads = world.road_network.road_count();
+    let mut watch = BudgetWatch::new(EntityBudgets {
+        max_roads: Some(roads - 1),
+        max_buildings: Some(world.building_count()),
+        ..EntityBudgets::default()
+    });
+    let overruns = watch.check(&world);
+    assert_eq!(overruns.len(), 1);
+    assert_eq!(overruns[0].entity, "roads");
+    assert_eq!(overruns[0].count, roads);
+    assert!(watch.is_over());
+    assert!(watch.check(&world).is_empty());
+    watch.budgets.max_roads = Some(roads);
+    assert!(watch.check(&world).is_empty());
+    assert!(!watch.is_over());
+    watch.budgets.max_roads = Some(roads - 1);
+    assert_eq!(watch.check(&world).len(), 1);
+
+    // Without budgets nothing is reported
+    assert!(BudgetWatch::default().check(&world).is_empty());
+}
+
+#[test]
+fn test_background_routing_is_deterministic() {
+    let mut world = SimWorld::create_test_world_with_seed(11);
+    world.game_state = Some(GameState::new());
+    world.set_route_workers(2);
+    assert_eq!(world.route_workers(), 2);
+
+    // New commuters wait at their start for a tick while their routes are found
+    world.tick(0.1);
+    assert!(!world.cars.is_empty());
+    assert_eq!(world.vehicles_awaiting_route(), world.cars.len());
+    let waiting: Vec<_> = world
+        .cars
+        .values()
+        .map(|car| (car.id, car.position))
+        .collect();
+    for car in world.cars.values() {
+        assert!(car.awaiting_route.is_some() && car.path.is_empty());
+    }
+    world.tick(0.1);
+    for (car_id, start) in waiting {
+        let car = &world.cars[&car_id];
+        assert!(car.awaiting_route.is_none() && !car.path.is_empty());
+        assert_eq!(car.destination(), car.path.last().copied());
+        assert!(
+            car.position.distance(&start) > 0.0,
+            "Cars set off once routed"
+        );
+    }
+
+    // Runs with any number of workers match each other
+    let run = |workers: usize| {
+        let mut world = SimWorld::create_test_world_with_seed(11);
+        world.game_state = Some(GameState::new());
+        world.set_route_workers(workers);
+        let mut checksums = Vec::new();
+        for tick in 1..=1500 {
+            world.tick(0.1);
+            if tick % 100 == 0 {
+                checksums.push(state_checksum(&world));
+            }
+        }
+        let trips = world.game_state.as_ref().unwrap().worker_trips_completed;
+        (checksums, trips)
+    };
+    let (one_worker, trips) = run(1);
+    assert!(trips > 0, "Routed commuters complete their trips");
+    assert_eq!(run(4), (one_worker, trips));
+
+    // Vehicles still waiting set off when routing goes back inline
+    world.tick(0.1);
+    world.set_route_workers(0);
+    assert_eq!(world.route_workers(), 0);
+    assert_eq!(world.vehicles_awaiting_route(), 0);
+    assert!(world.cars.values().all(|car| car.awaiting_route.is_none()));
+}
+
+#[test]
+fn test_spawn_vehicles_in_a_batch() {
+    let mut world = SimWorld::create_test_world_with_seed(3);
+    let home = world.apartments.values().next().unwrap().intersection_id;
+    let mut factories: Vec<_> = world
+        .factories
+        .values()
+        .map(|f| f.intersection_id)
+        .collect();
+    factories.sort_by_key(|intersection| intersection.0 .0);
+    let island = world.add_intersection(Position::new(80.0, 0.0, 80.0));
+
+    // Route lengths before any vehicle is on the road
+    let route_length = |world: &SimWorld, path: &[_]| {
+        let mut from = home;
+        path.iter()
+            .map(|&to| {
+                let road = world.road_network.find_road_between(from, to).unwrap();
+                from = to;
+                world.road_network.get_road(road).unwrap().length
+            })
+            .sum::<f32>()
+    };
+    let shortest: Vec<f32> = factories
+        .iter()
+        .map(|&factory| {
+            let path = world
+                .road_network
+                .find_path_for(home, factory, VehicleType::Car)
+                .unwrap();
+            route_length(&world, &path)
+        })
+        .collect();
+
+    let mut batch: Vec<_> = factories
+        .iter()
+        .map(|&factory| VehicleSpawn::new(home, factory, VehicleType::Car, TripType::Outbound))
+        .collect();
+    batch.push(VehicleSpawn::new(
+        home,
+        island,
+        VehicleType::Car,
+        TripType::Outbound,
+    ));
+    let results = world.spawn_vehicles(&batch);
+    assert_eq!(results.len(), batch.len());
+    assert!(
+        results.last().unwrap().is_err(),
+        "Unreachable vehicles don't spawn"
+    );
+    assert_eq!(world.cars.len(), factories.len());
+
+    // Vehicles sharing an origin share a search, but each takes a shortest
+    // route and sets off along it
+    for ((result, &factory), length) in results.iter().zip(&factories).zip(shortest) {
+        let car = &world.cars[result.as_ref().unwrap()];
+        assert_eq!(car.path.last(), Some(&factory));
+        assert!(car.awaiting_route.is_none());
+        assert!((route_length(&world, &car.path) - length).abs() < 1e-3);
+        assert_eq!(
+            world
+                .road_network
+                .find_road_between(home, car.path[0])
+                .unwrap(),
+            car.current_road
+        );
+    }
+
+    // Batches set off straight away even with background routing
+    world.set_route_workers(2);
+    let results = world.spawn_vehicles(&batch[..1]);
+    let car = &world.cars[results[0].as_ref().unwrap()];
+    assert!(car.awaiting_route.is_none() && !car.path.is_empty());
+    assert_eq!(world.vehicles_awaiting_route(), 0);
+}
+
+#[test]
+fn test_shortest_path_tree() {
+    let mut world = SimWorld::create_test_world_with_seed(1);
+    let origin = world.apartments.values().next().unwrap().intersection_id;
+    let island = world.add_intersection(Position::new(80.0, 0.0, 80.0));
+    let network = &world.road_network;
+
+    let tree = network.shortest_paths_from(origin);
+    assert_eq!(tree.origin, origin);
+    assert_eq!(tree.distance_to(origin), Some(0.0));
+    assert_eq!(tree.path_to(origin), Some(vec![]));
+    assert_eq!(tree.distance_to(island), None);
+    assert_eq!(tree.path_to(island), None);
+
+    // Every other intersection is reached along a route as long as its distance
+    assert_eq!(tree.distances, network.travel_distances(&[origin]));
+    assert_eq!(tree.distances.len(), network.intersection_count() - 1);
+    for (&intersection, &distance) in &tree.distances {
+        let path = tree.path_to(intersection).unwrap();
+        let mut from = origin;
+        let mut length = 0.0;
+        for &to in &path {
+            let road = network.find_road_between(from, to).unwrap();
+            length += network.get_road(road).unwrap().length;
+            from = to;
+        }
+        assert_eq!(from, intersection);
+        assert!((length - distance).abs() < 1e-3);
+    }
+
+    // An intersection without roads only reaches itself
+    let nowhere = network.shortest_paths_from(island);
+    assert_eq!(nowhere.distances.len(), 1);
+}
+
+#[test]
+fn test_travel_time_estimates_match_realized_trips() {
+    let mut world = SimWorld::create_test_world_with_seed(2);
+    world.game_state = Some(GameState::new());
+
+    // A straight route estimate is its length at the typical speed plus the
+    // allowance at each intersection, and time to get back up to speed
+    let from = world.apartments.values().next().unwrap().intersection_id;
+    let to = world.factories.values().next().unwrap().intersection_id;
+    let estimate = world.estimate_route(from, to, VehicleType::Car).unwrap();
+    let speed = VehicleType::Car.typical_speed();
+    let expected = estimate.distance / speed
+        + estimate.path.len() as f32
+            * (ETA_INTERSECTION_SECS + world.handling.car.launch_secs(speed));
+    assert!(
+        (estimate.secs - expected).abs() < 1e-3,
+        "Empty roads aren't congested"
+    );
+    let truck = world.estimate_route(from, to, VehicleType::Truck).unwrap();
+    assert!(truck.secs < estimate.secs, "Trucks are faster");
+
+    // Estimate each trip as it sets off and compare with how long it took
+    let mut trips: HashMap<_, (f32, f32)> = HashMap::new();
+    let mut finished = Vec::new();
+    for _ in 0..3000 {
+        world.tick(0.1);
+        let car_ids: Vec<_> = world.cars.keys().copied().collect();
+        for car_id in car_ids {
+            let trip_secs = world.cars[&car_id].trip_secs;
+            if let Some((_, taken)) = trips.get_mut(&car_id) {
+                *taken = trip_secs;
+            } else if let Some(left) = world.estimate_arrival_secs(car_id) {
+                trips.insert(car_id, (trip_secs + left, trip_secs));
+            }
+        }
+        trips.retain(|car_id, &mut (estimated, taken)| {
+            let driving = world.cars.contains_key(car_id);
+            if !driving {
+                finished.push((estimated, taken + 0.1));
+            }
+            driving
+        });
+    }
+    assert!(finished.len() > 100);
+    let estimated: f32 = finished.iter().map(|(estimated, _)| estimated).sum();
+    let taken: f32 = finished.iter().map(|(_, taken)| taken).sum();
+    assert!(
+        (0.8..1.25).contains(&(estimated / taken)),
+        "Estimates total {:.0}s for trips that took {:.0}s",
+        estimated,
+        taken
+    );
+    let close = finished
+        .iter()
+        .filter(|(estimated, taken)| (0.67..1.5).contains(&(estimated / taken)))
+        .count();
+    assert!(
+        close * 4 >= finished.len() * 3,
+        "Most estimates are within half again"
+    );
+}
+
+#[test]
+fn test_vehicles_queue_at_their_start_for_room_to_set_off() {
+    let mut world = SimWorld::new_with_seed(4);
+    let home = world.add_intersection(Position::new(0.0, 0.0, 0.0));
+    let work = world.add_intersection(Position::new(30.0, 0.0, 0.0));
+    world.add_two_way_road(home, work).unwrap();
+    let spawn = |world: &mut SimWorld| {
+        world
+            .spawn_vehicle(home, work, VehicleType::Car, TripType::Outbound, None, None)
+            .unwrap()
+    };
+
+    // The first car gets straight onto the road; the others wait their turn
+    let first = spawn(&mut world);
+    let second = spawn(&mut world);
+    let third = spawn(&mut world);
+    assert!(!world.cars[&first].queued_at_start);
+    assert!(world.cars[&second].queued_at_start && world.cars[&third].queued_at_start);
+    assert_eq!(world.spawn_queue_len(home), 2);
+    assert_eq!(world.vehicles_queued_to_spawn(), 2);
+    let road = world.cars[&first].current_road;
+    assert_eq!(world.road_network.get_cars_on_road(road).len(), 1);
+
+    // Queued cars set off in order once the car ahead has made room
+    let start = world.cars[&third].position;
+    let mut released = Vec::new();
+    for _ in 0..100 {
+        world.tick(0.1);
+        for car_id in [second, third] {
+            if !world.cars[&car_id].queued_at_start && !released.contains(&car_id) {
+                released.push(car_id);
+            }
+        }
+        if world.cars[&third].queued_at_start {
+            assert_eq!(world.cars[&third].position, start, "Queued cars stay put");
+        }
+        // No car is ever on top of another near the start of the road
+        let mut distances: Vec<f32> = [first, second, third]
+            .iter()
+            .filter(|id| !world.cars[id].queued_at_start)
+            .map(|id| world.cars[id].distance_along_road.into_inner())
+            .collect();
+        distances.sort_by(f32::total_cmp);
+        for pair in distances.windows(2) {
+            assert!(pair[1] - pair[0] >= SPAWN_CLEARANCE * 0.5);
+        }
+        if released.len() == 2 {
+            break;
+        }
+    }
+    assert_eq!(released, vec![second, third]);
+    assert_eq!(world.vehicles_queued_to_spawn(), 0);
+    let delays = world.spawn_delay_stats();
+    assert_eq!(delays.trips, 2);
+    assert!(delays.longest_secs > 0.0 && delays.longest_secs >= delays.average_secs());
+
+    // A car waiting when its road is removed gives up its trip
+    spawn(&mut world);
+    let queued = spawn(&mut world);
+    world.remove_two_way_road(home, work).unwrap();
+    world.tick(0.1);
+    assert!(!world.cars.contains_key(&queued));
+    assert_eq!(world.vehicles_queued_to_spawn(), 0);
+}
+
+#[test]
+fn test_unreachable_trips_wait_for_a_connection() {
+    let mut world = SimWorld::new_with_seed(5);
+    let home = world.add_intersection(Position::new(0.0, 0.0, 0.0));
+    let work = world.add_intersection(Position::new(20.0, 0.0, 0.0));
+    let depot = world.add_intersection(Position::new(40.0, 0.0, 0.0));
+    world.add_two_way_road(work, depot).unwrap();
+    world.add_apartment(home);
+    world.add_factory(work);
+
+    // A worker from an unconnected house waits instead of giving up, and
+    // the house sends nobody else meanwhile
+    for _ in 0..10 {
+        world.tick(0.1);
+    }
+    assert!(world.cars.is_empty());
+    assert_eq!(world.trips_waiting_for_connection(), 1);
+    let trip = world.pending_trips()[0];
+    assert_eq!((trip.spawn.from, trip.spawn.to), (home, work));
+    assert_eq!(trip.spawn.trip_type, TripType::Outbound);
+
+    // Connecting the house sends the worker on their way
+    world.add_two_way_road(home, work).unwrap();
+    world.tick(0.1);
+    assert_eq!(world.trips_waiting_for_connection(), 0);
+    let car = world
+        .cars
+        .values()
+        .next()
+        .expect("the worker should set off");
+    assert_eq!(
+        (car.start_intersection, car.destination()),
+        (home, Some(work))
+    );
+
+    // A trip whose house is removed no longer waits
+    let mut world = SimWorld::new_with_seed(5);
+    let home = world.add_intersection(Position::new(0.0, 0.0, 0.0));
+    let work = world.add_intersection(Position::new(20.0, 0.0, 0.0));
+    let depot = world.add_intersection(Position::new(40.0, 0.0, 0.0));
+    world.add_two_way_road(work, depot).unwrap();
+    let apartment = world.add_apartment(home);
+    world.add_factory(work);
+    world.tick(0.1);
+    assert_eq!(world.trips_waiting_for_connection(), 1);
+    world.remove_apartment(apartment);
+    world.add_two_way_road(home, depot).unwrap();
+    world.tick(0.1);
+    assert_eq!(world.trips_waiting_for_connection(), 0);
+    assert!(world.cars.is_empty());
+
+    // With background routing, a worker whose route comes back empty waits
+    // too: this house's only road leads to a dead end
+    let mut world = SimWorld::new_with_seed(5);
+    world.set_route_workers(1);
+    let home = world.add_intersection(Position::new(0.0, 0.0, 0.0));
+    let dead_end = world.add_intersection(Position::new(0.0, 0.0, 20.0));
+    let work = world.add_intersection(Position::new(20.0, 0.0, 0.0));
+    let depot = world.add_intersection(Position::new(40.0, 0.0, 0.0));
+    world.add_road(home, dead_end, false).unwrap();
+    world.add_two_way_road(work, depot).unwrap();
+    world.add_apartment(home);
+    world.add_factory(work);
+    for _ in 0..10 {
+        world.tick(0.1);
+    }
+    assert!(world.cars.is_empty());
+    assert_eq!(world.trips_waiting_for_connection(), 1);
+    world.add_road(dead_end, work, false).unwrap();
+    world.tick(0.1);
+    world.tick(0.1);
+    assert_eq!(world.trips_waiting_for_connection(), 0);
+    let car = world
+        .cars
+        .values()
+        .next()
+        .expect("the worker should set off");
+    assert_eq!(car.path, vec![dead_end, work]);
+}
+
+/// A junction with roads 10 long to the south, west, north and east, in a
+/// world playing under `rules`
+fn junction_world(rules: IntersectionRules) -> (SimWorld, [IntersectionId; 5]) {
+    let economy = EconomyConfig {
+        intersection_rules: rules,
+        breakdowns_per_vehicle_hour: 0.0,
+        collisions_per_congested_road_hour: 0.0,
+        ..EconomyConfig::default()
+    };
+    let mut world = SimWorld::new_with_seed(7);
+    world.game_state = Some(GameState::with_economy(economy));
+    let junction = world.add_intersection(Position::new(0.0, 0.0, 0.0));
+    let south = world.add_intersection(Position::new(0.0, 0.0, -10.0));
+    let west = world.add_intersection(Position::new(-10.0, 0.0, 0.0));
+    let north = world.add_intersection(Position::new(0.0, 0.0, 10.0));
+    let east = world.add_intersection(Position::new(10.0, 0.0, 0.0));
+    for end in [south, west, north, east] {
+        world.add_two_way_road(junction, end).unwrap();
+    }
+    (world, [junction, south, west, north, east])
+}
+
+/// Ticks a car from `from` takes to reach `to`, if it does within `limit`
+fn ticks_to_arrive(
+    world: &mut SimWorld,
+    from: IntersectionId,
+    to: IntersectionId,
+    limit: usize,
+) -> Option<usize> {
+    let car = world
+        .spawn_vehicle(from, to, VehicleType::Car, TripType::Outbound, None, None)
+        .unwrap();
+    (1..=limit).find(|_| {
+        world.tick(0.1);
+        !world.cars.contains_key(&car)
+    })
+}
+
+#[test]
+fn test_crossing_time_follows_junction_geometry() {
+    // A junction of two-way roads is two lanes wide, one of one-way roads
+    // one lane
+    let (mut world, [junction, south, ..]) = junction_world(IntersectionRules::default());
+    assert!((world.road_network.intersection_width(junction) - 2.0 * LANE_WIDTH).abs() < 1e-6);
+    let a = world.add_intersection(Position::new(30.0, 0.0, 0.0));
+    let b = world.add_intersection(Position::new(40.0, 0.0, 0.0));
+    world.add_road(a, b, false).unwrap();
+    assert!((world.road_network.intersection_width(b) - LANE_WIDTH).abs() < 1e-6);
+
+    // The kerbside turn is the shortest way across, then straight on, the
+    // turn across the oncoming traffic and a U-turn
+    let width = 2.0 * LANE_WIDTH;
+    for side in [DrivingSide::Right, DrivingSide::Left] {
+        let farside = match side.kerbside_turn() {
+            Turn::Right => Turn::Left,
+            _ => Turn::Right,
+        };
+        let distances = [side.kerbside_turn(), Turn::Straight, farside, Turn::UTurn]
+            .map(|turn| crossing_distance(width, turn, side));
+        assert!(
+            distances.windows(2).all(|pair| pair[0] < pair[1]),
+            "{:?}",
+            distances
+        );
+    }
+
+    // Longer and slower vehicles hold the junction longer
+    let car = crossing_secs(
+        width,
+        Turn::Straight,
+        DrivingSide::Right,
+        VehicleType::Car.length(),
+        4.0,
+    );
+    let truck = crossing_secs(
+        width,
+        Turn::Straight,
+        DrivingSide::Right,
+        VehicleType::Truck.length(),
+        4.0,
+    );
+    assert!((car - (width + CAR_LENGTH) / 4.0).abs() < 1e-6);
+    assert!(truck > car);
+    assert!(crossing_secs(width, Turn::Straight, DrivingSide::Right, CAR_LENGTH, 2.0) > car);
+
+    // With cornering taken out of it, turning right onto the west road is
+    // quicker than left onto the east one, which crosses more of the junction
+    let trip = |to_index: usize| {
+        let (mut world, ends) = junction_world(IntersectionRules::default());
+        world.curve_speed = CurveSpeedLimit {
+            lateral_accel: 1.0e6,
+            ..CurveSpeedLimit::default()
+        };
+        ticks_to_arrive(&mut world, south, ends[to_index], 1000).unwrap()
+    };
+    let (right, straight, left) = (trip(2), trip(3), trip(4));
+    assert!(right <= straight && straight <= left && right < left);
+}
+
+#[test]
+fn test_right_turn_on_red() {
+    // Heading north, west is a right turn and east a left one
+    let heading_north = Position::new(0.0, 0.0, -10.0).angle_to(&Position::new(0.0, 0.0, 0.0));
+    let heading_west = Position::new(0.0, 0.0, 0.0).angle_to(&Position::new(-10.0, 0.0, 0.0));
+    let heading_east = Position::new(0.0, 0.0, 0.0).angle_to(&Position::new(10.0, 0.0, 0.0));
+    assert_eq!(Turn::between(heading_north, heading_west), Turn::Right);
+    assert_eq!(Turn::between(heading_north, heading_east), Turn::Left);
+    assert_eq!(Turn::between(heading_north, heading_north), Turn::Straight);
+    assert_eq!(Turn::between(heading_west, heading_east), Turn::UTurn);
+
+    // The light is red for the south road for a minute
+    let red_for_south = |rules: IntersectionRules| {
+        let (mut world, [junction, south, west, _, east]) = junction_world(rules);
+        let from_east = world
+            .road_network
+            .find_road_between(east, junction)
+            .unwrap();
+        let from_south = world
+            .road_network
+            .find_road_between(south, junction)
+            .unwrap();
+        world
+            .set_signal(
+                junction,
+                Some(vec![
+                    SignalPhase {
+                        green: vec![from_east],
+                        duration: 60.0,
+                    },
+                    SignalPhase {
+                        green: vec![from_south],
+                        duration: 10.0,
+                    },
+                ]),
+            )
+            .unwrap();
+        (world, south, west, east)
+    };
+
+    // Without the rule, a car turning right waits for the green
+    let (mut world, south, west, _) = red_for_south(IntersectionRules::default());
+    assert_eq!(ticks_to_arrive(&mut world, south, west, 300), None);
+
+    // With it, the car stops and then turns
+    let rules = IntersectionRules {
+        right_turn_on_red: true,
+        ..IntersectionRules::default()
+    };
+    let (mut world, south, west, east) = red_for_south(rules);
+    assert!(ticks_to_arrive(&mut world, south, west, 300).is_some());
+
+    // Turning left still waits
+    assert_eq!(ticks_to_arrive(&mut world, south, east, 300), None);
+}
+
+#[test]
+fn test_left_hand_traffic_mirrors_lanes_and_turns() {
+    assert_eq!(DrivingSide::default(), DrivingSide::Right);
+    assert_eq!(DrivingSide::Right.kerbside_turn(), Turn::Right);
+    assert_eq!(DrivingSide::Left.kerbside_turn(), Turn::Left);
+    assert_eq!(
+        DrivingSide::Left.circulation(),
+        -DrivingSide::Right.circulation()
+    );
+
+    let rules = IntersectionRules {
+        right_turn_on_red: true,
+        ..IntersectionRules::default()
+    };
+    let driving_on = |side: DrivingSide| {
+        let (mut world, ids) = junction_world(rules);
+        world.driving_side = side;
+        (world, ids)
+    };
+
+    // Heading north up the south road, a car keeps to its own side of it
+    let lane_x = |side: DrivingSide| {
+        let (mut world, [_, south, _, north, _]) = driving_on(side);
+        let car = world
+            .spawn_vehicle(
+                south,
+                north,
+                VehicleType::Car,
+                TripType::Outbound,
+                None,
+                None,
+            )
+            .unwrap();
+        for _ in 0..10 {
+            world.tick(0.1);
+        }
+        world.cars[&car].position.x
+    };
+    assert!(lane_x(DrivingSide::Right) < 0.0);
+    assert!(lane_x(DrivingSide::Left) > 0.0);
+
+    // Driving on the left, the kerbside turn on red is the left one
+    let red_for_south = || {
+        let (mut world, [junction, south, west, _, east]) = driving_on(DrivingSide::Left);
+        let from_east = world
+            .road_network
+            .find_road_between(east, junction)
+            .unwrap();
+        let from_south = world
+            .road_network
+            .find_road_between(south, junction)
+            .unwrap();
+        world
+            .set_signal(
+                junction,
+                Some(vec![
+                    SignalPhase {
+                        green: vec![from_east],
+                        duration: 60.0,
+                    },
+                    SignalPhase {
+                        green: vec![from_south],
+                        duration: 10.0,
+                    },
+                ]),
+            )
+            .unwrap();
+        (world, south, west, east)
+    };
+    let (mut world, south, _, east) = red_for_south();
+    assert!(ticks_to_arrive(&mut world, south, east, 300).is_some());
+    let (mut world, south, west, _) = red_for_south();
+    assert_eq!(ticks_to_arrive(&mut world, south, west, 300), None);
+
+    // Saves keep the side, and it counts towards the state hash
+    let (world, _) = driving_on(DrivingSide::Left);
+    let save = SaveFile::of_world(7, &world);
+    assert_eq!(save.driving_side, DrivingSide::Left);
+    let reloaded = SaveFile::from_json_str(&save.to_json_string().unwrap()).unwrap();
+    let loaded = reloaded.create_world().unwrap();
+    assert_eq!(loaded.driving_side, DrivingSide::Left);
+    let (right, _) = driving_on(DrivingSide::Right);
+    let right = SaveFile::of_world(7, &right).create_world().unwrap();
+    assert_ne!(right.state_hash(), loaded.state_hash());
+}
+
+#[test]
+fn test_yield_to_pedestrians() {
+    let yielding = IntersectionRules {
+        yield_to_pedestrians: true,
+        ..IntersectionRules::default()
+    };
+    let trip = |rules: IntersectionRules, turning: bool| {
+        let (mut world, [_, south, west, north, _]) = junction_world(rules);
+        let to = if turning { west } else { north };
+        ticks_to_arrive(&mut world, south, to, 1000).unwrap()
+    };
+
+    // Turning cars take longer to clear the junction while they wait for
+    // people crossing
+    let delay = trip(yielding, true) - trip(IntersectionRules::default(), true);
+    let expected = (PEDESTRIAN_YIELD_SECS / 0.1).round() as usize;
+    assert!(
+        delay + 1 >= expected && delay <= expected + 1,
+        "delayed {} ticks",
+        delay
+    );
+
+    // Cars going straight on don't
+    assert_eq!(
+        trip(yielding, false),
+        trip(IntersectionRules::default(), false)
+    );
+
+    // The rules can be set from an economy file
+    let economy =
+        EconomyConfig::from_toml_str("[intersection_rules]\nright_turn_on_red = true\n").unwrap();
+    assert!(economy.intersection_rules.right_turn_on_red);
+    assert!(!economy.intersection_rules.yield_to_pedestrians);
+}
+
+#[test]
+fn test_late_trucks_preempt_signals() {
+    // The light is red for the south road for a minute
+    let red_for_south = |rules: IntersectionRules| {
+        let (mut world, [junction, south, _, north, east]) = junction_world(rules);
+        let from_east = world
+            .road_network
+            .find_road_between(east, junction)
+            .unwrap();
+        let from_south = world
+            .road_network
+            .find_road_between(south, junction)
+            .unwrap();
+        let phases = vec![
+            SignalPhase {
+                green: vec![from_east],
+                duration: 60.0,
+            },
+            SignalPhase {
+                green: vec![from_south],
+                duration: 10.0,
+            },
+        ];
+        world.set_signal(junction, Some(phases)).unwrap();
+        (world, junction, south, north)
+    };
+    let truck_arrives = |world: &mut SimWorld, from, to, deadline: f32| {
+        let truck = world
+            .spawn_vehicle(from, to, VehicleType::Truck, TripType::Outbound, None, None)
+            .unwrap();
+        world.cars.get_mut(&truck).unwrap().delivery_deadline = Some(deadline);
+        (1..=300).any(|_| {
+            world.tick(0.1);
+            !world.cars.contains_key(&truck)
+        })
+    };
+    let preempting = IntersectionRules {
+        late_truck_preemption: true,
+        ..IntersectionRules::default()
+    };
+
+    // Without the rule, even a late truck waits for the green
+    let (mut world, _, south, north) = red_for_south(IntersectionRules::default());
+    assert!(!truck_arrives(&mut world, south, north, 0.0));
+    assert_eq!(world.signal_preemptions().count, 0);
+
+    // With it, a truck with plenty of time still waits
+    let (mut world, _, south, north) = red_for_south(preempting);
+    assert!(!truck_arrives(&mut world, south, north, 1000.0));
+    assert_eq!(world.signal_preemptions().count, 0);
+
+    // but one near its deadline switches the light and goes through
+    let (mut world, junction, south, north) = red_for_south(preempting);
+    assert!(truck_arrives(
+        &mut world,
+        south,
+        north,
+        PREEMPTION_WINDOW_SECS
+    ));
+    let preemptions = world.signal_preemptions();
+    assert_eq!(preemptions.count, 1);
+    assert!(preemptions.green_cut_secs > 50.0 && preemptions.green_cut_secs <= 60.0);
+
+    // The signal then gives way at most once a cycle: with the south road's
+    // phase swapped out again, the next late truck waits
+    let signal = world.intersections[&junction].signal.as_ref().unwrap();
+    assert_eq!(signal.cycle_secs(), 70.0);
+    world
+        .set_signal(
+            junction,
+            Some(signal.phases().iter().rev().cloned().collect()),
+        )
+        .unwrap();
+    assert!(!truck_arrives(&mut world, south, north, 0.0));
+    assert_eq!(world.signal_preemptions().count, 1);
+
+    // The rule can be set from an economy file
+    let economy =
+        EconomyConfig::from_toml_str("[intersection_rules]\nlate_truck_preemption = true\n")
+            .unwrap();
+    assert!(economy.intersection_rules.late_truck_preemption);
+}
+
+#[test]
+fn test_driver_personalities() {
+    let run = |driver_mix: Option<DriverMix>, ticks: usize| {
+        let mut world = SimWorld::create_test_world_with_seed(4);
+        world.game_state = Some(GameState::with_economy(EconomyConfig {
+            driver_mix,
+            ..EconomyConfig::default()
+        }));
+        for _ in 0..ticks {
+            world.tick(0.1);
+        }
+        world
+    };
+
+    // Without a mix every driver is standard
+    let world = run(None, 300);
+    assert!(!world.cars.is_empty());
+    assert!(world
+        .cars
+        .values()
+        .all(|car| car.personality == Personality::default()));
+    assert!(world.driver_summaries().is_empty());
+
+    // Half aggressive and half cautious, each exactly as profiled
+    let mix = DriverMix {
+        aggressive_share: 0.5,
+        cautious_share: 0.5,
+        spread: 0.0,
+        ..DriverMix::default()
+    };
+    let world = run(Some(mix), 300);
+    for car in world.cars.values() {
+        let personality = car.personality;
+        assert_ne!(personality.kind, DriverKind::Standard);
+        assert_eq!(personality.profile, mix.profile(personality.kind));
+        let range = car.vehicle_type.speed_range();
+        let factor = personality.profile.speed_factor;
+        assert!(car.speed >= range.start * factor && car.speed <= range.end * factor);
+    }
+    let summaries = world.driver_summaries();
+    assert_eq!(
+        summaries
+            .iter()
+            .map(|summary| summary.kind)
+            .collect::<Vec<_>>(),
+        [DriverKind::Aggressive, DriverKind::Cautious]
+    );
+    for summary in &summaries {
+        assert!(summary.drivers > 0);
+        assert!(
+            (summary.average.following_gap - mix.profile(summary.kind).following_gap).abs() < 1e-4
+        );
+    }
+    let drivers: u32 = summaries.iter().map(|summary| summary.drivers).sum();
+    assert!(drivers as usize >= world.cars.len());
+
+    // The spread sets each driver apart from their kind
+    let world = run(
+        Some(DriverMix {
+            aggressive_share: 0.0,
+            cautious_share: 0.0,
+            ..DriverMix::default()
+        }),
+        300,
+    );
+    let gaps: Vec<f32> = world
+        .cars
+        .values()
+        .map(|car| car.personality.profile.following_gap)
+        .collect();
+    assert!(gaps.iter().all(|gap| (0.9..=1.1).contains(gap)));
+    assert!(gaps.iter().any(|gap| *gap != gaps[0]));
+
+    // Aggressive drivers get more done than cautious ones on the same roads
+    let trips = |kind: DriverKind| {
+        let world = run(
+            Some(DriverMix {
+                aggressive_share: if kind == DriverKind::Aggressive {
+                    1.0
+                } else {
+                    0.0
+                },
+                cautious_share: if kind == DriverKind::Cautious {
+                    1.0
+                } else {
+                    0.0
+                },
+                spread: 0.0,
+                ..DriverMix::default()
+            }),
+            3000,
+        );
+        world.game_state.unwrap().worker_trips_completed
+    };
+    assert!(trips(DriverKind::Aggressive) > trips(DriverKind::Cautious));
+
+    // Mixes are set in the economy file. Profiles left out are the default
+    // ones, and values left out of a profile are the standard driver's
+    let economy = EconomyConfig::from_toml_str(
+        "[driver_mix]\naggressive_share = 0.3\n[driver_mix.cautious]\nspeed_factor = 0.7\n",
+    )
+    .unwrap();
+    let mix = economy.driver_mix.unwrap();
+    assert_eq!(mix.aggressive_share, 0.3);
+    assert_eq!(mix.aggressive, DriverMix::default().aggressive);
+    assert_eq!(mix.cautious.speed_factor, 0.7);
+    assert_eq!(mix.cautious.following_gap, 1.0);
+    for bad in [
+        "aggressive_share = 0.7\ncautious_share = 0.5",
+        "spread = 1.0",
+        "[driver_mix.aggressive]\nfollowing_gap = 0.0",
+    ] {
+        assert!(
+            EconomyConfig::from_toml_str(&format!("[driver_mix]\n{}", bad)).is_err(),
+            "{}",
+            bad
+        );
+    }
+}
+
+#[test]
+fn test_vehicles_slow_into_bends() {
+    use std::f32::consts::{FRAC_PI_2, PI};
+
+    // Sharper bends are tighter and slower; straight on has no limit
+    let limit = CurveSpeedLimit::default();
+    assert_eq!(CurveSpeedLimit::curvature_of_turn(0.0), 0.0);
+    assert_eq!(limit.max_speed_for_turn(0.0), f32::INFINITY);
+    let right_angle = CurveSpeedLimit::curvature_of_turn(-FRAC_PI_2);
+    assert!((right_angle - 1.0 / INTERSECTION_APPROACH_DISTANCE).abs() < 1e-4);
+    assert!(
+        (limit.max_speed(right_angle) - (limit.lateral_accel / right_angle).sqrt()).abs() < 1e-4
+    );
+    assert!(limit.max_speed_for_turn(FRAC_PI_2 / 2.0) > limit.max_speed_for_turn(FRAC_PI_2));
+    assert_eq!(limit.max_speed_for_turn(PI), limit.min_speed);
+
+    // A trip round a corner takes longer than one the same length straight
+    // on
+    let trip = |curve_speed: CurveSpeedLimit, turning: bool| {
+        let (mut world, [_, south, west, north, _]) = junction_world(IntersectionRules::default());
+        world.curve_speed = curve_speed;
+        let to = if turning { west } else { north };
+        ticks_to_arrive(&mut world, south, to, 1000).unwrap()
+    };
+    let straight = trip(CurveSpeedLimit::default(), false);
+    assert!(trip(CurveSpeedLimit::default(), true) > straight);
+    let grippy = CurveSpeedLimit {
+        lateral_accel: 1.0e6,
+        ..CurveSpeedLimit::default()
+    };
+    assert_eq!(trip(grippy, false), straight);
+    // With grip to spare, the turn only differs in how long the car holds
+    // the junction before it sets off across: west is the kerbside turn,
+    // which hugs the near corner on a quarter circle a quarter of the
+    // junction across (see `crossing_distance`), and at the car's speed
+    // that is a tick less than crossing the whole width straight on
+    assert_eq!(trip(grippy, true), straight - 1);
+}
+
+#[test]
+fn test_cars_turn_along_an_arc() {
+    use std::f32::consts::FRAC_PI_2;
+
+    // The arc starts on the first road's heading and ends on the next's
+    let (mut world, [junction, south, west, _, _]) = junction_world(IntersectionRules::default());
+    let arc = TurnArc::new(
+        junction,
+        Position::new(0.0, 0.0, 0.0),
+        Position::new(0.0, 0.0, -1.0),
+        0.0,
+        Position::new(-1.0, 0.0, 0.0),
+        -FRAC_PI_2,
+        1.0,
+    );
+    assert_eq!(arc.point_at(-1.0), (arc.entry, 0.0));
+    let (exit, heading) = arc.point_at(1.0);
+    assert!(exit.distance(&arc.exit) < 1e-5);
+    assert!((heading + FRAC_PI_2).abs() < 1e-5);
+
+    // Heading north from the south and turning west, the car turns a
+    // little at a time, holding the junction until it is round
+    let car = world
+        .spawn_vehicle(
+            south,
+            west,
+            VehicleType::Car,
+            TripType::Outbound,
+            None,
+            None,
+        )
+        .unwrap();
+    let mut headings = Vec::new();
+    let mut turning_ticks = 0;
+    for _ in 0..1000 {
+        world.tick(0.1);
+        let Some(vehicle) = world.cars.get(&car) else {
+            break;
+        };
+        headings.push(vehicle.angle);
+        if let Some(turn) = vehicle.turn {
+            assert_eq!(turn.intersection, junction);
+            assert!(world.intersections[&junction].is_held_by(car));
+            turning_ticks += 1;
+        } else if headings.last().is_some_and(|angle| *angle == -FRAC_PI_2) {
+            assert!(!world.intersections[&junction].is_held_by(car));
+        }
+    }
+    assert!(!world.cars.contains_key(&car), "car should arrive");
+    assert!(turning_ticks > 1);
+    let between = headings
+        .iter()
+        .filter(|angle| **angle < -0.1 && **angle > -FRAC_PI_2 + 0.1)
+        .count();
+    assert!(between > 2, "only {} ticks mid-turn", between);
+    assert!(headings.windows(2).all(|pair| pair[1] <= pair[0] + 1e-5));
+}
+
+#[test]
+fn test_scenario_events_disrupt_the_city() {
+    let economy = EconomyConfig::from_toml_str(
+        r#"
+        [[event_schedule.events]]
+        at_secs = 10.0
+        duration_secs = 20.0
+        event = { type = "demand_surge", multiplier = 3.0 }
+
+        [event_schedule.random]
+        events_per_hour = 0.5
+        factory_strikes = false
+        "#,
+    )
+    .expect("economy TOML should parse");
+    let schedule = economy
+        .event_schedule
+        .clone()
+        .expect("schedule should be set");
+    assert_eq!(
+        schedule.events[0].event,
+        ScenarioEvent::DemandSurge { multiplier: 3.0 }
+    );
+    assert!(schedule
+        .random
+        .as_ref()
+        .is_some_and(|random| random.road_closures));
+    let text = economy.to_toml_string().unwrap();
+    assert_eq!(EconomyConfig::from_toml_str(&text).unwrap(), economy);
+    let out_of_order = EventSchedule {
+        events: vec![
+            schedule.events[0],
+            ScheduledEvent {
+                at_secs: 5.0,
+                ..schedule.events[0]
+            },
+        ],
+        random: None,
+    };
+    assert!(out_of_order.validate().is_err());
+    let nothing_allowed = RandomEvents {
+        road_closures: false,
+        factory_strikes: false,
+        demand_surges: false,
+        ..RandomEvents::default()
+    };
+    assert!(EventSchedule {
+        events: Vec::new(),
+        random: Some(nothing_allowed)
+    }
+    .validate()
+    .is_err());
+
+    // A parade, a strike and a surge, all at once
+    let mut world = SimWorld::create_test_world_with_seed(11);
+    let factory = world
+        .factories
+        .values()
+        .map(|factory| factory.intersection_id)
+        .min_by_key(|intersection| intersection.0 .0)
+        .unwrap();
+    // A grid road, which traffic can get around
+    let (pair, from, to) = world
+        .road_network
+        .two_way_roads()
+        .map(|pair| {
+            let road = world.road_network.get_road(pair.forward).unwrap();
+            (pair, road.start_intersection, road.end_intersection)
+        })
+        .find(|(_, from, to)| {
+            world.road_network.neighbour_count(*from) >= 3
+                && world.road_network.neighbour_count(*to) >= 3
+        })
+        .unwrap();
+    let scheduled = |event| ScheduledEvent {
+        at_secs: 5.0,
+        duration_secs: 10.0,
+        event,
+    };
+    let economy = EconomyConfig {
+        breakdowns_per_vehicle_hour: 0.0,
+        collisions_per_congested_road_hour: 0.0,
+        demand_ramp: Some(DemandRamp::default()),
+        event_schedule: Some(EventSchedule {
+            events: vec![
+                scheduled(ScenarioEvent::RoadClosure { from, to }),
+                scheduled(ScenarioEvent::FactoryStrike { factory }),
+                scheduled(ScenarioEvent::DemandSurge { multiplier: 2.0 }),
+            ],
+            random: None,
+        }),
+        ..EconomyConfig::default()
+    };
+    let ramp = economy.demand_ramp.clone().unwrap();
+    world.game_state = Some(GameState::with_economy(economy));
+    let closed = |world: &SimWorld| {
+        pair.roads()
+            .map(|road_id| world.road_network.get_road(road_id).unwrap().closed)
+    };
+
+    while world.time < 4.9 {
+        world.tick(0.1);
+    }
+    assert!(world.active_events().is_empty());
+    assert_eq!(world.demand_level(), ramp.level_at(world.time));
+
+    while world.time < 5.5 {
+        world.tick(0.1);
+    }
+    assert_eq!(world.active_events().len(), 3);
+    assert_eq!(closed(&world), [true; 2]);
+    assert!(world
+        .road_network
+        .find_path_for(from, to, VehicleType::Ambulance)
+        .is_some_and(|path| path.len() > 1));
+    assert!(world.factory_on_strike(factory));
+    assert!((world.demand_level() - 2.0 * ramp.level_at(world.time)).abs() < 1e-4);
+
+    // Workers on shift when the strike started have finished it, and nobody
+    // else is let in
+    while world.time < 14.5 {
+        world.tick(0.1);
+        if world.time > 11.0 {
+            let striking = world
+                .factories
+                .values()
+                .find(|candidate| candidate.intersection_id == factory)
+                .unwrap();
+            assert!(striking.workers.is_empty());
+        }
+    }
+
+    while world.time < 15.5 {
+        world.tick(0.1);
+    }
+    assert!(world.active_events().is_empty());
+    assert_eq!(closed(&world), [false; 2]);
+    assert!(!world.factory_on_strike(factory));
+    let events = world.game_state.as_mut().unwrap().take_events();
+    let count = |started: bool| {
+        events
+            .iter()
+            .filter(|event| match event {
+                GameEvent::ScenarioEventStarted { .. } => started,
+                GameEvent::ScenarioEventEnded { .. } => !started,
+                _ => false,
+            })
+            .count()
+    };
+    assert_eq!((count(true), count(false)), (3, 3));
+
+    // Random events pick their own roads to close
+    let mut world = SimWorld::create_test_world_with_seed(3);
+    world.game_state = Some(GameState::with_economy(EconomyConfig {
+        event_schedule: Some(EventSchedule {
+            events: Vec::new(),
+            random: Some(RandomEvents {
+                events_per_hour: 1000.0,
+                duration_secs: 5.0,
+                factory_strikes: false,
+                demand_surges: false,
+                ..RandomEvents::default()
+            }),
+        }),
+        ..EconomyConfig::default()
+    }));
+    for _ in 0..50 {
+        world.tick(0.1);
+    }
+    assert!(!world.active_events().is_empty());
+    for active in world.active_events() {
+        let ScenarioEvent::RoadClosure { from, to } = active.event else {
+            panic!("only road closures are allowed, got {:?}", active.event);
+        };
+        let road_id = world.road_network.find_road_between(from, to).unwrap();
+        let road = world.road_network.get_road(road_id).unwrap();
+        assert!(road.closed);
+    }
+}
+
+#[test]
+fn test_closed_roads_are_detoured_and_reopen_as_they_were() {
+    let mut world = SimWorld::new_with_seed(1);
+    let a = world.add_intersection(Position::new(0.0, 0.0, 0.0));
+    let b = world.add_intersection(Position::new(10.0, 0.0, 10.0));
+    let c = world.add_intersection(Position::new(20.0, 0.0, 0.0));
+    let d = world.add_intersection(Position::new(30.0, 0.0, 0.0));
+    let e = world.add_intersection(Position::new(-10.0, 0.0, 0.0));
+    let (short_cut, short_cut_back) = world.add_two_way_road(a, c).unwrap();
+    world.add_two_way_road(a, b).unwrap();
+    world.add_two_way_road(b, c).unwrap();
+    let (dead_end, _) = world.add_two_way_road(c, d).unwrap();
+    world.add_two_way_road(e, a).unwrap();
+    world
+        .set_road_restriction(short_cut, RoadRestriction::NoTrucks)
+        .unwrap();
+
+    let commuter = world
+        .spawn_vehicle(e, c, VehicleType::Car, TripType::Outbound, None, None)
+        .unwrap();
+    assert_eq!(world.cars[&commuter].path, vec![a, c]);
+
+    // Closing the short cut, both ways, sends traffic the long way round
+    let close = BuildCommand::CloseRoad {
+        road: short_cut,
+        closed: true,
+    };
+    let json = serde_json::to_string(&close).unwrap();
+    assert_eq!(serde_json::from_str::<BuildCommand>(&json).unwrap(), close);
+    assert!(world.apply_command(&close).unwrap());
+    assert!(world.road_network.get_road(short_cut_back).unwrap().closed);
+    assert_eq!(world.cars[&commuter].path, vec![a, b, c]);
+    assert_eq!(
+        world
+            .road_network
+            .find_path_for(a, c, VehicleType::Ambulance),
+        Some(vec![b, c])
+    );
+
+    // With no way round, vehicles wait at the barricade until it reopens
+    let stuck = world
+        .spawn_vehicle(a, d, VehicleType::Car, TripType::Outbound, None, None)
+        .unwrap();
+    world.set_road_closed(dead_end, true).unwrap();
+    assert_eq!(world.cars[&stuck].path, vec![b, c, d]);
+    for _ in 0..400 {
+        world.tick(0.05);
+    }
+    let car = &world.cars[&stuck];
+    assert_eq!(car.path, vec![c, d]);
+    assert_ne!(car.current_road, dead_end);
+    assert_eq!(car.current_speed, 0.0);
+    assert_eq!(car.speed_share(&world.road_network), Some(0.0));
+
+    world.set_road_closed(dead_end, false).unwrap();
+    let arrived = (0..400).any(|_| {
+        world.tick(0.05);
+        !world.cars.contains_key(&stuck)
+    });
+    assert!(arrived);
+
+    // Reopening keeps the road's restriction
+    assert!(world
+        .apply_command(&BuildCommand::CloseRoad {
+            road: short_cut,
+            closed: false,
+        })
+        .unwrap());
+    let road = world.road_network.get_road(short_cut_back).unwrap();
+    assert!(!road.closed);
+    assert_eq!(road.restriction, RoadRestriction::NoTrucks);
+    assert_eq!(
+        world.road_network.find_path_for(a, c, VehicleType::Car),
+        Some(vec![c])
+    );
+    assert_eq!(
+        world.road_network.find_path_for(a, c, VehicleType::Truck),
+        Some(vec![b, c])
+    );
+}
+
+#[test]
+fn test_rush_hour_speed_goal() {
+    // The mean is weighted by how long each value held
+    let mut mean = WindowedMean::default();
+    assert_eq!(mean.mean(), None);
+    mean.add(1.0, 3.0);
+    mean.add(5.0, 1.0);
+    assert_eq!(mean.secs(), 4.0);
+    assert_eq!(mean.mean(), Some(2.0));
+
+    let goal = SpeedGoal::default();
+    assert!(goal.window_contains(8.5) && !goal.window_contains(9.0));
+    let overnight = SpeedGoal {
+        start_hour: 23.0,
+        end_hour: 1.0,
+        ..SpeedGoal::default()
+    };
+    assert!(overnight.window_contains(23.5) && overnight.window_contains(0.5));
+    assert_eq!(overnight.window_hours(), 2.0);
+    assert!(SpeedGoal {
+        end_hour: 8.0,
+        ..goal
+    }
+    .validate()
+    .is_err());
+    assert!(SpeedGoal {
+        min_average_speed: 0.0,
+        ..goal
+    }
+    .validate()
+    .is_err());
+    let economy = EconomyConfig::from_toml_str("[speed_goal]\nmin_average_speed = 1.5\n").unwrap();
+    assert_eq!(economy.speed_goal.unwrap().min_average_speed, 1.5);
+
+    // The game starts at 8:00, so the default window is the first 10 seconds
+    let run = |speed_goal: Option<SpeedGoal>, ticks: usize| {
+        let mut world = SimWorld::create_test_world_with_seed(4);
+        world.game_state = Some(GameState::with_economy(EconomyConfig {
+            goal_money: STARTING_BUDGET,
+            speed_goal,
+            ..EconomyConfig::default()
+        }));
+        for _ in 0..ticks {
+            world.tick(0.1);
+        }
+        world
+    };
+
+    // Without a speed goal the money goal wins at once
+    let world = run(None, 1);
+    assert!(world.game_state.as_ref().unwrap().is_won);
+
+    // Progress shows while the window is open, and the win waits for it
+    let world = run(
+        Some(SpeedGoal {
+            min_average_speed: 0.1,
+            ..goal
+        }),
+        50,
+    );
+    let progress = world.speed_goal_progress().unwrap();
+    assert!((progress.elapsed - 0.5).abs() < 0.05);
+    assert!(progress.average.is_some_and(|average| average > 0.0));
+    assert!(!world.game_state.as_ref().unwrap().is_won);
+
+    // A rush hour kept moving counts towards the goal and wins the game
+    let mut world = run(
+        Some(SpeedGoal {
+            min_average_speed: 0.1,
+            ..goal
+        }),
+        120,
+    );
+    assert!(world.speed_goal_progress().is_none());
+    let game_state = world.game_state.as_mut().unwrap();
+    assert_eq!(
+        (game_state.speed_goal.met, game_state.speed_goal.missed),
+        (1, 0)
+    );
+    assert!(game_state.is_won && !game_state.is_lost);
+    assert!(game_state
+        .take_events()
+        .iter()
+        .any(|event| matches!(event, GameEvent::SpeedGoalMet { .. })));
+
+    // One too slow loses it
+    let mut world = run(
+        Some(SpeedGoal {
+            min_average_speed: 100.0,
+            ..goal
+        }),
+        120,
+    );
+    let game_state = world.game_state.as_mut().unwrap();
+    assert_eq!(
+        (game_state.speed_goal.met, game_state.speed_goal.missed),
+        (0, 1)
+    );
+    assert!(game_state.is_lost && !game_state.is_won);
+    assert!(game_state.missed_speed_goal());
+    assert!(game_state
+        .take_events()
+        .iter()
+        .any(|event| matches!(event, GameEvent::SpeedGoalMissed { .. })));
+}
+
+#[test]
+fn test_capacity_upgrades() {
+    // Without upgrades there is no cap and nothing to buy
+    let mut world = SimWorld::create_test_world_with_seed(4);
+    world.game_state = Some(GameState::new());
+    assert_eq!(world.max_cars(), None);
+    assert_eq!(world.upgrade_cost(UpgradeKind::CarCap), None);
+    assert!(world.try_buy_upgrade(UpgradeKind::CarCap).is_err());
+
+    let upgrades = UpgradeConfig {
+        max_cars: 5,
+        ..UpgradeConfig::default()
+    };
+    let capped = |upgrades: UpgradeConfig| {
+        let mut world = SimWorld::create_test_world_with_seed(4);
+        world.game_state = Some(GameState::with_economy(EconomyConfig {
+            upgrades: Some(upgrades),
+            ..EconomyConfig::default()
+        }));
+        world
+    };
+
+    // Houses hold workers back while the roads are at the cap
+    let mut world = capped(upgrades);
+    for _ in 0..300 {
+        world.tick(0.1);
+        let commuters = world
+            .cars
+            .values()
+            .filter(|car| {
+                car.vehicle_type == VehicleType::Car && car.trip_type == TripType::Outbound
+            })
+            .count();
+        assert!(commuters <= 5);
+    }
+
+    // Each level costs more than the last and applies to existing buildings
+    let mut world = capped(upgrades);
+    world.game_state.as_mut().unwrap().money = 10_000;
+    let apartment = *world.apartments.keys().next().unwrap();
+    let factory = *world.factories.keys().next().unwrap();
+    let cars = world.apartments[&apartment].cars.len();
+    let storage = world.factories[&factory].max_deliveries;
+    assert_eq!(world.upgrade_cost(UpgradeKind::CarCap), Some(300));
+    assert!(world
+        .apply_command(&BuildCommand::BuyUpgrade {
+            upgrade: UpgradeKind::CarCap
+        })
+        .unwrap());
+    assert_eq!(world.max_cars(), Some(30));
+    assert_eq!(world.upgrade_cost(UpgradeKind::CarCap), Some(450));
+    assert!(world.try_buy_upgrade(UpgradeKind::ApartmentCars).unwrap());
+    assert!(world.try_buy_upgrade(UpgradeKind::FactoryStorage).unwrap());
+    assert_eq!(world.apartments[&apartment].cars.len(), cars + 2);
+    assert_eq!(world.apartments[&apartment].vehicles.len(), cars + 2);
+    assert_eq!(world.factories[&factory].max_deliveries, storage + 1);
+    let game_state = world.game_state.as_mut().unwrap();
+    assert_eq!(game_state.money, 10_000 - 300 - 400 - 500);
+    assert_eq!(game_state.upgrades.level(UpgradeKind::CarCap), 1);
+    assert!(game_state
+        .take_events()
+        .contains(&GameEvent::UpgradeBought {
+            upgrade: UpgradeKind::FactoryStorage,
+            level: 1
+        }));
+
+    // Buildings placed later get them too
+    let intersection = world.apartments[&apartment].intersection_id;
+    let new_apartment = world.add_apartment(intersection);
+    assert_eq!(world.apartments[&new_apartment].cars.len(), 12);
+    let new_factory = world.add_factory(intersection);
+    assert_eq!(world.factories[&new_factory].max_deliveries, 3);
+
+    // A fully bought track can't be bought again, and one that can't be
+    // afforded isn't
+    for _ in 0..2 {
+        assert!(world.try_buy_upgrade(UpgradeKind::FactoryStorage).unwrap());
+    }
+    assert_eq!(world.upgrade_cost(UpgradeKind::FactoryStorage), None);
+    assert!(world.try_buy_upgrade(UpgradeKind::FactoryStorage).is_err());
+    world.game_state.as_mut().unwrap().money = 0;
+    assert!(!world.try_buy_upgrade(UpgradeKind::CarCap).unwrap());
+    assert_eq!(world.upgrade_level(UpgradeKind::CarCap), 1);
+}
+
+#[test]
+fn test_traffic_assignment() {
+    let mut world = SimWorld::create_test_world_with_seed(4);
+    let config = AssignmentConfig::default();
+
+    // Every house car commutes to the factories and back, and every factory
+    // delivers to the shops and back
+    let demand = world.od_demand(&config);
+    let total: f32 = demand.iter().map(|trip| trip.per_minute).sum();
+    let cars: usize = world
+        .apartments
+        .values()
+        .map(|apartment| apartment.cars.len())
+        .sum();
+    let expected = 2.0 * cars as f32 * config.worker_trips_per_car_minute
+        + 2.0 * world.factories.len() as f32 * config.deliveries_per_factory_minute;
+    assert!((total - expected).abs() < 0.01, "{} vs {}", total, expected);
+    assert!(demand.iter().all(|trip| trip.from != trip.to));
+
+    // The demand loads the roads, and nothing moves in the world itself
+    let assignment = world.assign_traffic(&config);
+    assert!((assignment.routed_per_minute - total).abs() < 0.01);
+    assert_eq!(assignment.unrouted_per_minute, 0.0);
+    assert!(assignment.roads.values().any(|road| road.flow > 0.0));
+    assert!(assignment.average_trip_secs() > 0.0);
+    assert!(world.cars.is_empty());
+    let busiest = assignment.busiest_roads(3);
+    assert!(busiest.len() <= 3);
+    assert!(busiest
+        .windows(2)
+        .all(|pair| pair[0].volume_to_capacity >= pair[1].volume_to_capacity));
+
+    // Tight roads slow down under load and take rounds to settle
+    let tight = AssignmentConfig {
+        road_capacity_per_minute: 1.0,
+        ..config
+    };
+    let congested = world.assign_traffic(&tight);
+    assert!(congested.iterations > 1);
+    assert!(congested
+        .roads
+        .values()
+        .any(|road| road.travel_secs > road.free_flow_secs));
+    assert!(congested.average_trip_secs() > assignment.average_trip_secs());
+
+    // Closed roads carry nothing, and a house cut off has no route
+    let apartment = world.apartments.values().next().unwrap().intersection_id;
+    let cut: Vec<_> = world
+        .road_network
+        .roads()
+        .values()
+        .filter(|road| road.start_intersection == apartment || road.end_intersection == apartment)
+        .map(|road| road.id)
+        .collect();
+    for road_id in &cut {
+        world.set_road_closed(*road_id, true).unwrap();
+    }
+    let cut_off = world.assign_traffic(&config);
+    assert!(cut
+        .iter()
+        .all(|road_id| !cut_off.roads.contains_key(road_id)));
+    assert!(cut_off.unrouted_per_minute > 0.0);
+    assert!((cut_off.routed_per_minute + cut_off.unrouted_per_minute - total).abs() < 0.01);
+}
+
+#[test]
+fn test_demand_calibration_recovers_counted_traffic() {
+    let config = CalibrationConfig::default();
+    let mut world = SimWorld::create_test_world_with_seed(4);
+
+    // Counts taken from the city with one house busier than usual and one
+    // factory quieter
+    let house = world.apartments.values().next().unwrap().intersection_id;
+    let factory = world.factories.values().next().unwrap().intersection_id;
+    let observed = EconomyConfig {
+        demand_weights: vec![
+            DemandWeight {
+                building: house,
+                weight: 3.0,
+            },
+            DemandWeight {
+                building: factory,
+                weight: 0.5,
+            },
+        ],
+        ..Default::default()
+    };
+    world.game_state = Some(GameState::with_economy(observed));
+    let text: String = std::iter::once(format!("{}\n", ROAD_COUNTS_CSV_HEADER))
+        .chain(
+            world
+                .assign_traffic(&config.assignment)
+                .roads
+                .values()
+                .filter(|road| road.flow > 0.0)
+                .map(|road| {
+                    let built = world.road_network.get_road(road.road_id).unwrap();
+                    let (from, to) = (built.start_intersection, built.end_intersection);
+                    format!("{},{},{}\n", from.0 .0, to.0 .0, road.flow)
+                }),
+        )
+        .collect();
+    let counts = parse_road_counts(&text).unwrap();
+    assert!(!counts.is_empty());
+
+    // Starting from the usual demand, calibration gets every counted road
+    // within tolerance, and the weights it finds give the same flows
+    world.game_state = Some(GameState::new());
+    let calibration = world.calibrate_demand(&counts, &config).unwrap();
+    assert!(
+        calibration.converged,
+        "off by {}",
+        calibration.worst_error()
+    );
+    assert!(calibration.rounds > 1);
+    assert!(calibration.worst_error() <= config.tolerance);
+    world.game_state.as_mut().unwrap().economy.demand_weights = calibration.weights.clone();
+    let calibrated = world.assign_traffic(&config.assignment);
+    for road in &calibration.roads {
+        assert!((calibrated.roads[&road.road_id].flow - road.flow).abs() < 0.01);
+    }
+    let weight_of = |building| {
+        calibration
+            .weights
+            .iter()
+            .find(|weight| weight.building == building)
+            .unwrap()
+            .weight
+    };
+    assert!(weight_of(house) > 1.0);
+    assert!(weight_of(factory) < 1.0);
+
+    // Roads the city doesn't have and malformed lines are rejected
+    let missing = [RoadCount {
+        from: house,
+        to: house,
+        per_minute: 1.0,
+    }];
+    assert!(world.calibrate_demand(&missing, &config).is_err());
+    assert!(parse_road_counts("from,to,per_minute\n1,2\n").is_err());
+    assert!(parse_road_counts("1,2,-3\n").is_err());
+}
+
+#[test]
+fn test_car_pool_recycles_finished_vehicles() {
+    let run = |capacity: usize| {
+        let mut world = SimWorld::create_test_world_with_seed(6);
+        world.set_car_pool_capacity(capacity);
+        for _ in 0..2000 {
+            world.tick(0.1);
+        }
+        world
+    };
+
+    // Finished vehicles are reused, and never more are kept than allowed
+    let pooled = run(CAR_POOL_CAPACITY);
+    assert!(pooled.car_pool().reused() > 0);
+    assert!(pooled.car_pool().idle().len() <= CAR_POOL_CAPACITY);
+    let small = run(2);
+    assert!(small.car_pool().idle().len() <= 2);
+
+    // Without a pool every vehicle is new, and the runs match exactly
+    let unpooled = run(0);
+    assert_eq!(unpooled.car_pool().reused(), 0);
+    assert!(unpooled.car_pool().idle().is_empty());
+    assert_eq!(
+        unpooled.car_pool().built(),
+        pooled.car_pool().built() + pooled.car_pool().reused()
+    );
+    assert_eq!(state_checksum(&unpooled), state_checksum(&pooled));
+    assert_eq!(state_checksum(&small), state_checksum(&pooled));
+    for (car_id, car) in &pooled.cars {
+        let other = &unpooled.cars[car_id];
+        assert_eq!(
+            (car.position, car.path.clone()),
+            (other.position, other.path.clone())
+        );
+    }
+}
+
+#[test]
+fn test_road_network_diff() {
+    let mut world = SimWorld::create_test_world_with_seed(1);
+    let original = world.road_network.clone();
+    assert!(original.diff(&world.road_network).is_empty());
+    assert_eq!(
+        original.diff(&world.road_network).to_string(),
+        "No differences\n"
+    );
+
+    // Closing a two-way road touches both its directions and nothing else
+    let road = world.road_network.two_way_roads().next().unwrap();
+    world.set_road_closed(road.forward, true).unwrap();
+    let diff = original.diff(&world.road_network);
+    assert!(diff.intersections.is_empty());
+    assert_eq!(
+        diff.touched_roads(),
+        BTreeSet::from([road.forward, road.backward])
+    );
+    assert_eq!(
+        diff.road_fields(road.forward),
+        Some(&[RoadField::Closed][..])
+    );
+    world.set_road_closed(road.forward, false).unwrap();
+    assert!(original.diff(&world.road_network).is_empty());
+
+    // Moving an intersection reshapes exactly the roads that meet there
+    let (&moved, &position) = world
+        .road_network
+        .intersection_positions()
+        .iter()
+        .next()
+        .unwrap();
+    world
+        .move_intersection(moved, Position::new(position.x + 2.0, 0.0, position.z))
+        .unwrap();
+    let meeting: BTreeSet<RoadId> = world
+        .road_network
+        .roads()
+        .values()
+        .filter(|road| road.start_intersection == moved || road.end_intersection == moved)
+        .map(|road| road.id)
+        .collect();
+    let diff = original.diff(&world.road_network);
+    assert_eq!(diff.touched_intersections(), BTreeSet::from([moved]));
+    assert_eq!(diff.touched_roads(), meeting);
+    assert!(meeting
+        .iter()
+        .all(|road_id| diff.road_fields(*road_id) == Some(&[RoadField::Shape][..])));
+
+    // Additions and removals are listed, and swap places going back
+    let moved_network = world.road_network.clone();
+    let removed = *world.road_network.roads().keys().last().unwrap();
+    world.remove_road(removed).unwrap();
+    let added = world.add_intersection(Position::new(100.0, 0.0, 100.0));
+    let diff = moved_network.diff(&world.road_network);
+    assert!(diff
+        .roads
+        .iter()
+        .any(|change| matches!(change, RoadChange::Removed(road) if road.id == removed)));
+    assert!(diff.intersections.contains(&IntersectionChange::Added {
+        id: added,
+        position: Position::new(100.0, 0.0, 100.0),
+    }));
+    let text = diff.to_string();
+    assert!(text.contains(&format!("- road {}:", removed.0 .0)));
+    assert!(text.contains(&format!("+ intersection {} at (100.0, 100.0)", added.0 .0)));
+    let back = world.road_network.diff(&moved_network);
+    assert!(back
+        .roads
+        .iter()
+        .any(|change| matches!(change, RoadChange::Added(road) if road.id == removed)));
+    assert!(back
+        .intersections
+        .iter()
+        .any(|change| matches!(change, IntersectionChange::Removed { id, .. } if *id == added)));
+}
+
+#[test]
+fn test_contraflow_turns_a_road_round_once_it_clears() {
+    // A one-way road from a to b, with a detour back round through c
+    let build = |world: &mut SimWorld| {
+        let a = world.add_intersection(Position::new(0.0, 0.0, 0.0));
+        let b = world.add_intersection(Position::new(30.0, 0.0, 0.0));
+        let c = world.add_intersection(Position::new(15.0, 0.0, 20.0));
+        let road = world.add_road(a, b, false).unwrap();
+        world.add_two_way_road(b, c).unwrap();
+        world.add_two_way_road(c, a).unwrap();
+        (a, b, road)
+    };
+    let mut world = SimWorld::new_with_seed(1);
+    let (a, b, road) = build(&mut world);
+    let car = world
+        .spawn_vehicle(a, b, VehicleType::Car, TripType::Outbound, None, None)
+        .unwrap();
+    world.tick(0.1);
+    assert_eq!(world.cars[&car].current_road, road);
+
+    // A window open now; the road closes while the car finishes its way
+    let hour = world.time_of_day();
+    let windows = vec![ContraflowWindow {
+        start_hour: hour,
+        end_hour: (hour + 1.0) % 24.0,
+    }];
+    let command = BuildCommand::ScheduleContraflow {
+        road,
+        windows: windows.clone(),
+    };
+    let json = serde_json::to_string(&command).unwrap();
+    assert_eq!(
+        serde_json::from_str::<BuildCommand>(&json).unwrap(),
+        command
+    );
+    assert!(world.apply_command(&command).unwrap());
+    let hash = world.state_hash();
+    world.tick(0.1);
+    assert!(world.is_contraflow_clearing(road));
+    assert!(!world.is_contraflow_reversed(road));
+    let closed = world.road_network.get_road(road).unwrap();
+    assert!(closed.closed);
+    assert_eq!(closed.start_intersection, a);
+    assert_eq!(world.cars[&car].current_road, road);
+
+    while world.cars.contains_key(&car) {
+        world.tick(0.1);
+    }
+    world.tick(0.1);
+    assert!(world.is_contraflow_reversed(road));
+    assert!(!world.is_contraflow_clearing(road));
+    let reversed = world.road_network.get_road(road).unwrap();
+    assert_eq!(
+        (reversed.start_intersection, reversed.end_intersection),
+        (b, a)
+    );
+    assert!(!reversed.closed);
+    assert_eq!(world.road_network.find_road_between(b, a).unwrap(), road);
+    assert!(world.road_network.find_road_between(a, b).is_err());
+    // The save keeps the road as built, so the hash doesn't change
+    assert_eq!(world.state_hash(), hash);
+
+    // Traffic back to a takes the road now
+    let back = world
+        .spawn_vehicle(b, a, VehicleType::Car, TripType::Outbound, None, None)
+        .unwrap();
+    assert_eq!(world.cars[&back].path, vec![a]);
+
+    // Cancelled, it turns back once that car is off it
+    assert!(world
+        .apply_command(&BuildCommand::ScheduleContraflow {
+            road,
+            windows: Vec::new()
+        })
+        .unwrap());
+    world.tick(0.1);
+    assert!(world.is_contraflow_clearing(road));
+    while world.is_contraflow_reversed(road) {
+        world.tick(0.1);
+    }
+    assert!(!world.cars.contains_key(&back));
+    let restored = world.road_network.get_road(road).unwrap();
+    assert_eq!(
+        (restored.start_intersection, restored.end_intersection),
+        (a, b)
+    );
+    assert!(!restored.closed);
+
+    // Two-way roads can't run contraflow
+    let two_way = world.road_network.two_way_roads().next().unwrap().forward;
+    assert!(world.schedule_contraflow(two_way, windows).is_err());
+
+    // A scenario names its roads by their intersections
+    let economy = EconomyConfig::from_toml_str(
+        r#"
+        [[contraflow]]
+        from = 0
+        to = 1
+        windows = [{ start_hour = 0.0, end_hour = 23.9 }]
+        "#,
+    )
+    .expect("economy TOML should parse");
+    assert_eq!(
+        economy.contraflow,
+        vec![ContraflowRoad {
+            from: a,
+            to: b,
+            windows: vec![ContraflowWindow {
+                start_hour: 0.0,
+                end_hour: 23.9
+            }],
+        }]
+    );
+    assert!(EconomyConfig::from_toml_str(
+        "[[contraflow]]\nfrom = 0\nto = 1\nwindows = [{ start_hour = 8.0, end_hour = 8.0 }]"
+    )
+    .is_err());
+    let mut world = SimWorld::new_with_economy(economy);
+    let (_, _, road) = build(&mut world);
+    world.tick(0.1);
+    assert!(world.is_contraflow_reversed(road));
+    assert!(world.contraflow_windows(road).is_empty());
+}
+
+#[test]
+fn test_network_audit_lists_trips_with_no_route() {
+    assert!(SimWorld::create_test_world_with_seed(42)
+        .audit_network()
+        .is_complete());
+
+    // An apartment and a factory joined both ways, and a shop only reached
+    // by a one-way road
+    let mut world = SimWorld::new_with_seed(1);
+    let home = world.add_intersection(Position::new(0.0, 0.0, 0.0));
+    let work = world.add_intersection(Position::new(20.0, 0.0, 0.0));
+    let corner = world.add_intersection(Position::new(20.0, 0.0, 20.0));
+    let store = world.add_intersection(Position::new(25.0, 0.0, 30.0));
+    world.add_two_way_road(home, work).unwrap();
+    world.add_two_way_road(work, corner).unwrap();
+    world.add_road(corner, store, false).unwrap();
+    world.add_apartment(home);
+    world.add_factory(work);
+    world.add_shop(store);
+
+    let audit = world.audit_network();
+    assert_eq!(audit.trips, 4);
+    assert!(!audit.is_complete());
+    assert_eq!(audit.unreachable.len(), 1);
+    let pair = audit.unreachable[0];
+    assert_eq!(
+        (pair.trip, pair.from, pair.to),
+        (AuditTrip::Return, store, work)
+    );
+    // The shop is nearest the corner, which leads back to the factory
+    let link = pair.missing_link.unwrap();
+    assert_eq!((link.from, link.to), (store, corner));
+    assert!((link.length - 125.0_f32.sqrt()).abs() < 1e-4);
+    assert!(audit.to_string().contains("shop -> factory"));
+
+    // A closure doesn't count, but a road trucks may not use does
+    let work_to_corner = world.road_network.find_road_between(work, corner).unwrap();
+    world.set_road_closed(work_to_corner, true).unwrap();
+    assert_eq!(world.audit_network().unreachable.len(), 1);
+    world.set_road_closed(work_to_corner, false).unwrap();
+    world
+        .set_road_restriction(work_to_corner, RoadRestriction::NoTrucks)
+        .unwrap();
+    let trips: BTreeSet<AuditTrip> = world
+        .audit_network()
+        .unreachable
+        .iter()
+        .map(|pair| pair.trip)
+        .collect();
+    assert_eq!(
+        trips,
+        BTreeSet::from([AuditTrip::Delivery, AuditTrip::Return])
+    );
+    world
+        .set_road_restriction(work_to_corner, RoadRestriction::All)
+        .unwrap();
+
+    // Building the missing link completes the network
+    world.add_road(link.from, link.to, false).unwrap();
+    let audit = world.audit_network();
+    assert!(audit.is_complete());
+    assert_eq!(audit.to_string(), "All 4 trips have a route\n");
+}
+
+#[test]
+fn test_find_anomalies_flags_idle_factories_unused_roads_and_slow_trips() {
+    // A factory no worker can get to, on a one-way spur out of it
+    let mut world = SimWorld::create_test_world_with_seed(42);
+    world.game_state = Some(GameState::new());
+    let corner = *world.intersections.keys().next().unwrap();
+    let spur = world.add_intersection(Position::new(-80.0, 0.0, -80.0));
+    world.add_road(spur, corner, false).unwrap();
+    let idle = world.add_factory(spur);
+    for _ in 0..3000 {
+        world.tick(0.1);
+    }
+
+    let health = world.find_anomalies(&AnomalyConfig::default());
+    assert!(health.anomalies.iter().any(|anomaly| matches!(
+        anomaly,
+        Anomaly::IdleFactory { factory, intersection } if *factory == idle && *intersection == spur
+    )));
+    assert!(health.anomalies.iter().any(|anomaly| matches!(
+        anomaly,
+        Anomaly::UnusedRoad { start, end, .. } if *start == spur && *end == corner
+    )));
+    assert!(!health
+        .anomalies
+        .iter()
+        .any(|anomaly| matches!(anomaly, Anomaly::SlowTrips { .. })));
+    assert!(health
+        .to_string()
+        .contains(&format!("Factory {} at intersection", idle.0 .0)));
+
+    // Every trip counts as slow when none are allowed any time
+    let strict = world.find_anomalies(&AnomalyConfig { max_trip_secs: 0.0 });
+    let slow = strict
+        .anomalies
+        .iter()
+        .filter(|anomaly| matches!(anomaly, Anomaly::SlowTrips { .. }))
+        .count();
+    assert_eq!(slow, world.trip_statistics().len());
+    assert!(strict.anomalies.len() > health.anomalies.len());
+
+    // A world with nothing built has nothing to flag
+    assert!(SimWorld::new_with_seed(1)
+        .find_anomalies(&AnomalyConfig::default())
+        .is_healthy());
+}
+
+#[test]
+fn test_alarms_go_off_after_their_window_and_clear() {
+    let economy = EconomyConfig::from_toml_str(
+        r#"
+        [[alarms]]
+        name = "Traffic"
+        metric = "vehicles"
+        above = 0.0
+        for_secs = 1.0
+
+        [[alarms]]
+        name = "Deliveries stalled"
+        metric = "deliveries_per_minute"
+        below = 2.0
+        for_secs = 30.0
+        "#,
+    )
+    .unwrap();
+    assert_eq!(economy.alarms[0].metric, AlarmMetric::Vehicles);
+    let mut world = SimWorld::new_with_seed(1);
+    world.game_state = Some(GameState::with_economy(economy));
+    let a = world.add_intersection(Position::new(0.0, 0.0, 0.0));
+    let b = world.add_intersection(Position::new(200.0, 0.0, 0.0));
+    world.add_road(a, b, false).unwrap();
+    let car = world
+        .spawn_vehicle(a, b, VehicleType::Car, TripType::Outbound, None, None)
+        .unwrap();
+
+    // The vehicle count has to stay up for a second before the alarm goes off
+    world.tick(0.5);
+    assert_eq!(world.active_alarms().count(), 0);
+    for _ in 0..10 {
+        world.tick(0.1);
+    }
+    let reports: Vec<_> = world.active_alarms().cloned().collect();
+    assert_eq!(reports.len(), 1);
+    assert_eq!(reports[0].name, "Traffic");
+    assert_eq!(reports[0].value, 1.0);
+    assert!(reports[0].context.contains("1 vehicles"));
+    // At the middle of the only road with traffic on it
+    let location = reports[0].location.unwrap();
+    assert!((location.x - 100.0).abs() < 1e-3);
+    let game_state = world.game_state.as_mut().unwrap();
+    assert!(game_state.take_events().contains(&GameEvent::AlarmRaised {
+        alarm: 0,
+        value: 1.0
+    }));
+
+    // It clears once the car has arrived, and the delivery rate, known once
+    // a minute has gone by, goes off 30 seconds after that
+    while world.cars.contains_key(&car) {
+        world.tick(0.1);
+    }
+    world.tick(0.1);
+    assert_eq!(world.active_alarms().count(), 0);
+    assert!(world
+        .game_state
+        .as_mut()
+        .unwrap()
+        .take_events()
+        .contains(&GameEvent::AlarmCleared { alarm: 0 }));
+    while world.time < 89.0 {
+        world.tick(0.5);
+    }
+    assert_eq!(world.active_alarms().count(), 0);
+    while world.time < 91.0 {
+        world.tick(0.5);
+    }
+    let stalled: Vec<_> = world.active_alarms().map(|report| report.alarm).collect();
+    assert_eq!(stalled, vec![1]);
+    assert_eq!(world.alarm_reports().len(), 2);
+    assert_eq!(
+        world.alarm_metric(AlarmMetric::DeliveriesPerMinute),
+        Some(0.0)
+    );
+
+    // An alarm needs exactly one threshold
+    let both = EconomyConfig::from_toml_str(
+        r#"
+        [[alarms]]
+        name = "Both"
+        metric = "road_density"
+        below = 0.1
+        above = 0.8
+        "#,
+    );
+    assert!(both.is_err());
+}
+
+#[test]
+fn test_cleanup_orphans_removes_bare_intersections() {
+    let mut world = SimWorld::new_with_seed(1);
+    let a = world.add_intersection(Position::new(0.0, 0.0, 0.0));
+    let orphan = world.add_intersection(Position::new(0.0, 0.0, 50.0));
+    let b = world.add_intersection(Position::new(30.0, 0.0, 0.0));
+    let lot = world.add_intersection(Position::new(30.0, 0.0, 50.0));
+    world.add_apartment(lot);
+    let c = world.add_intersection(Position::new(60.0, 0.0, 0.0));
+    world.add_road(a, b, false).unwrap();
+    let bc = world.add_road(b, c, false).unwrap();
+    let (d, e) = (
+        world.add_intersection(Position::new(0.0, 0.0, 100.0)),
+        world.add_intersection(Position::new(30.0, 0.0, 100.0)),
+    );
+    let de = world.add_road(d, e, false).unwrap();
+    world.remove_road(de).unwrap();
+
+    // A lone building's intersection stays; ones with nothing at them go
+    assert!(world.is_orphan_intersection(orphan));
+    assert!(!world.is_orphan_intersection(lot));
+    assert!(!world.is_orphan_intersection(b));
+    assert!(world.apply_command(&BuildCommand::CleanupOrphans).unwrap());
+    for id in [orphan, d, e] {
+        assert!(!world.intersections.contains_key(&id));
+        assert!(world.road_network.get_intersection_position(id).is_none());
+    }
+    assert!(world.intersections.contains_key(&lot));
+    assert_eq!(world.cleanup_orphans().unwrap(), Vec::new());
+    assert!(!world
+        .visuals()
+        .iter()
+        .any(|visual| visual.id == VisualId::Intersection(orphan)));
+
+    // Routes still find their way past the removed intersections
+    let car = world
+        .spawn_vehicle(a, c, VehicleType::Car, TripType::Outbound, None, None)
+        .unwrap();
+    assert_eq!(world.cars[&car].path, vec![b, c]);
+    assert_eq!(world.road_network.find_road_between(b, c).unwrap(), bc);
+}
+
+#[test]
+fn test_arrivals_are_handled_the_same_in_any_order() {
+    // A factory whose truck gets home the same tick as a worker arrives, a
+    // truck delivering to a shop and a worker turned away by a busy factory
+    let build = || {
+        let mut world = SimWorld::new_with_seed(3);
+        world.game_state = Some(GameState::new());
+        let home = world.add_intersection(Position::new(0.0, 0.0, 0.0));
+        let works = world.add_intersection(Position::new(40.0, 0.0, 0.0));
+        let busy_works = world.add_intersection(Position::new(0.0, 0.0, 40.0));
+        let store = world.add_intersection(Position::new(40.0, 0.0, 40.0));
+        let roads = [
+            (home, works),
+            (home, busy_works),
+            (works, store),
+            (busy_works, store),
+        ];
+        for (from, to) in roads {
+            world.add_two_way_road(from, to).unwrap();
+        }
+        let apartment = world.add_apartment(home);
+        let factory = world.add_factory(works);
+        let busy_factory = world.add_factory(busy_works);
+        world.add_shop(store);
+
+        use TripType::{Outbound, Return};
+        use VehicleType::{Car, Truck};
+        let mut spawn = |from, to, vehicle, trip, apartment, factory| {
+            world
+                .spawn_vehicle(from, to, vehicle, trip, apartment, factory)
+                .unwrap()
+        };
+        let worker = spawn(home, works, Car, Outbound, Some(apartment), None);
+        let turned_away = spawn(home, busy_works, Car, Outbound, Some(apartment), None);
+        let truck_home = spawn(store, works, Truck, Return, None, Some(factory));
+        let delivery = spawn(busy_works, store, Truck, Outbound, None, Some(busy_factory));
+        world.apartments.get_mut(&apartment).unwrap().cars = vec![Some(worker), Some(turned_away)];
+        world.factories.get_mut(&factory).unwrap().truck = Some(truck_home);
+        world.factories.get_mut(&busy_factory).unwrap().truck = Some(delivery);
+        let arrivals = vec![
+            (worker, CarUpdateResult::ArrivedAtDestination(works)),
+            (
+                turned_away,
+                CarUpdateResult::ArrivedAtDestination(busy_works),
+            ),
+            (truck_home, CarUpdateResult::ArrivedAtDestination(works)),
+            (delivery, CarUpdateResult::ArrivedAtDestination(store)),
+        ];
+        (world, arrivals, apartment, factory, busy_factory)
+    };
+
+    let (mut forward, arrivals, apartment, factory, busy_factory) = build();
+    forward.process_arrivals(arrivals.clone());
+    let (mut reversed, _, _, _, _) = build();
+    reversed.process_arrivals(arrivals.iter().rev().cloned().collect());
+
+    for world in [&forward, &reversed] {
+        // The truck got home before the worker clocked in, whatever their IDs
+        assert_eq!(world.factories[&factory].workers.len(), 1);
+        assert_eq!(world.factories[&factory].truck, None);
+        assert_eq!(
+            world.game_state.as_ref().unwrap().shop_deliveries_completed,
+            1
+        );
+        // The delivery truck and the worker turned away set off back
+        let returning = world.factories[&busy_factory].truck.unwrap();
+        assert_eq!(world.cars[&returning].trip_type, TripType::Return);
+        let slots = &world.apartments[&apartment].cars;
+        assert_eq!(slots[0], None);
+        assert_eq!(world.cars[&slots[1].unwrap()].trip_type, TripType::Return);
+        assert_eq!(world.cars.len(), 2);
+    }
+    assert_eq!(
+        forward.cars.keys().collect::<Vec<_>>(),
+        reversed.cars.keys().collect::<Vec<_>>()
+    );
+    assert_eq!(state_checksum(&forward), state_checksum(&reversed));
+    assert_eq!(forward.state_hash(), reversed.state_hash());
+}
+
+#[test]
+fn test_invariants_hold_while_running_and_catch_corruption() {
+    let mut world = SimWorld::create_test_world_with_seed(42);
+    for _ in 0..1000 {
+        world.tick(0.1);
+        world.check_invariants().unwrap();
+    }
+
+    // A vehicle dropped without being taken off its road or given back
+    let (&car, _) = world
+        .cars
+        .iter()
+        .find(|(_, car)| car.awaiting_route.is_none() && car.origin_apartment.is_some())
+        .expect("the test world has cars driving");
+    world.cars.remove(&car);
+    let error = format!("{:#}", world.check_invariants().unwrap_err());
+    assert!(
+        error.contains(&format!("missing car {} is tracked", car.0 .0)),
+        "{}",
+        error
+    );
+    assert!(
+        error.contains(&format!("has missing car {} out", car.0 .0)),
+        "{}",
+        error
+    );
+}
+
+#[test]
+fn test_highlights_follow_traffic_deliveries_and_building() {
+    assert!(SimWorld::new().highlights().is_empty());
+
+    let mut world = SimWorld::create_test_world_with_seed(3);
+    for _ in 0..2000 {
+        world.tick(0.05);
+    }
+    let highlights = world.highlights();
+    let kinds: Vec<HighlightKind> = highlights.iter().map(|highlight| highlight.kind).collect();
+    assert_eq!(
+        kinds,
+        [
+            HighlightKind::BusiestIntersection,
+            HighlightKind::LatestDelivery,
+            HighlightKind::NewestConstruction,
+        ]
+    );
+    let delivered = world.shops.values().filter_map(|shop| shop.last_delivery);
+    let latest = delivered.fold(f32::MIN, f32::max);
+    assert!(latest > 0.0 && latest <= world.time);
+    assert!(highlights[1].caption.starts_with("Latest delivery: shop"));
+
+    // Whatever was built last is the newest construction
+    let position = Position::new(-40.0, 0.0, -40.0);
+    world
+        .add_decoration(DecorationKind::Park, position)
+        .unwrap();
+    let newest = world.highlights().pop().unwrap();
+    assert_eq!(newest.kind, HighlightKind::NewestConstruction);
+    assert_eq!(newest.caption, "Newest construction: a park");
+    assert_eq!((newest.position.x, newest.position.z), (-40.0, -40.0));
+}
+
+#[test]
+fn test_cars_in_region_match_a_scan_of_every_vehicle() {
+    let mut world = SimWorld::create_test_world_with_seed(3);
+    for _ in 0..600 {
+        world.tick(0.05);
+    }
+    let everywhere = Region::new(Position::new(-1e4, 0.0, -1e4), Position::new(1e4, 0.0, 1e4));
+    let on_roads = world.cars_in_region(&everywhere, &CarFilter::default());
+    let tracked = world.road_network.tracked_cars().count();
+    assert!(tracked > 0);
+    assert_eq!(on_roads.len(), tracked);
+
+    // Every part of the map finds what a scan of every vehicle would
+    let filters = [
+        CarFilter::default(),
+        CarFilter {
+            vehicle_type: Some(VehicleType::Truck),
+            ..CarFilter::default()
+        },
+        CarFilter {
+            trip_type: Some(TripType::Return),
+            slower_than: Some(1.0),
+            ..CarFilter::default()
+        },
+    ];
+    for car in &on_roads {
+        let region = Region::around(car.position, 5.0);
+        for filter in &filters {
+            let found: Vec<_> = world
+                .cars_in_region(&region, filter)
+                .iter()
+                .map(|car| car.id)
+                .collect();
+            let scanned: Vec<_> = world
+                .cars
+                .values()
+                .filter(|other| {
+                    other.awaiting_route.is_none()
+                        && !other.queued_at_start
+                        && region.contains(&other.position)
+                        && filter.matches(other)
+                })
+                .map(|other| other.id)
+                .collect();
+            assert_eq!(found, scanned);
+        }
+        assert!(world
+            .cars_in_region(&region, &CarFilter::default())
+            .iter()
+            .any(|other| other.id == car.id));
+    }
+    let stopped = CarFilter {
+        slower_than: Some(0.0),
+        ..CarFilter::default()
+    };
+    assert!(world.cars_in_region(&everywhere, &stopped).is_empty());
+}
+
+#[test]
+fn test_junction_presets_are_built_and_paid_for_at_once() {
+    let mut world = SimWorld::new_with_game();
+    let economy = world.game_state.as_ref().unwrap().economy.clone();
+    assert_eq!(
+        junction_cost(&economy, JunctionKind::Tee),
+        3 * economy.cost_road
+    );
+    assert_eq!(
+        junction_cost(&economy, JunctionKind::Diamond),
+        9 * economy.cost_road + economy.cost_bridge
+    );
+
+    // A T-junction whose west arm joins an existing road's end
+    let road_end = world.add_intersection(Position::new(-8.0, 0.0, 0.0));
+    let far = world.add_intersection(Position::new(-20.0, 0.0, 0.0));
+    world.add_two_way_road(far, road_end).unwrap();
+    let command = BuildCommand::Junction {
+        kind: JunctionKind::Tee,
+        center: Position::new(0.0, 0.0, 0.0),
+        toward: Position::new(5.0, 0.0, 0.0),
+        snap_distance: 1.0,
+    };
+    let json = serde_json::to_string(&command).unwrap();
+    assert_eq!(
+        serde_json::from_str::<BuildCommand>(&json).unwrap(),
+        command
+    );
+    assert!(world.apply_command(&command).unwrap());
+    assert_eq!(
+        world.game_state.as_ref().unwrap().money,
+        STARTING_BUDGET - junction_cost(&economy, JunctionKind::Tee)
+    );
+    assert_eq!(world.road_network.roads().len(), 2 * (1 + 3));
+    assert_eq!(world.intersections.len(), 2 + 3);
+    let tee = world
+        .road_network
+        .find_closest_intersection(&Position::new(0.0, 0.0, 0.0))
+        .unwrap();
+    assert_eq!(
+        world.road_network.get_roads_at_intersection(tee).len(),
+        2 * 3
+    );
+
+    // A crossroads gives each road through it a phase of its signal
+    let cross = world
+        .add_junction(
+            JunctionKind::Cross,
+            Position::new(0.0, 0.0, 40.0),
+            Position::new(0.0, 0.0, 50.0),
+            1.0,
+        )
+        .unwrap();
+    let center = cross.intersections[4];
+    let phases = world.intersections[&center]
+        .signal
+        .as_ref()
+        .unwrap()
+        .phases()
+        .to_vec();
+    assert_eq!(phases.len(), 2);
+    for phase in &phases {
+        assert_eq!(phase.green.len(), 2);
+        let [a, b] = [phase.green[0], phase.green[1]].map(|id| world.road_network.get_road(id));
+        let (a, b) = (a.unwrap(), b.unwrap());
+        assert_eq!((a.end_intersection, b.end_intersection), (center, center));
+        // The two green roads come from opposite sides
+        assert!(((a.angle - b.angle).abs() - std::f32::consts::PI).abs() < 1e-3);
+    }
+
+    // A diamond carries its main road over the cross road on a bridge, and
+    // the ramps lead from each to the other
+    let diamond = world
+        .add_junction(
+            JunctionKind::Diamond,
+            Position::new(60.0, 0.0, 0.0),
+            Position::new(60.0, 0.0, 10.0),
+            1.0,
+        )
+        .unwrap();
+    let bridges: Vec<_> = diamond
+        .roads
+        .iter()
+        .filter(|(forward, _)| {
+            world.road_network.get_road(*forward).unwrap().kind == RoadKind::Bridge
+        })
+        .collect();
+    assert_eq!(bridges.len(), 1);
+    let [south, north, east, west] = [0, 1, 2, 3].map(|index| diamond.intersections[index]);
+    for (from, to) in [
+        (south, north),
+        (north, south),
+        (east, west),
+        (south, east),
+        (west, north),
+    ] {
+        assert!(world.road_network.find_path(from, to).is_some());
+    }
+
+    // Nothing is built or charged without the money for all of it, nor
+    // where a street would cross water
+    let roads = world.road_network.roads().len();
+    let intersections = world.intersections.len();
+    world.game_state.as_mut().unwrap().money = junction_cost(&economy, JunctionKind::Cross) - 1;
+    let cross_at = |x: f32| BuildCommand::Junction {
+        kind: JunctionKind::Cross,
+        center: Position::new(x, 0.0, -40.0),
+        toward: Position::new(x + 1.0, 0.0, -40.0),
+        snap_distance: 1.0,
+    };
+    assert!(!world.apply_command(&cross_at(0.0)).unwrap());
+    world.game_state.as_mut().unwrap().money = STARTING_BUDGET;
+    world.water.push(WaterRegion::new(
+        Position::new(-100.0, 0.0, -36.0),
+        Position::new(100.0, 0.0, -34.0),
+    ));
+    assert!(world.apply_command(&cross_at(0.0)).is_err());
+    assert_eq!(world.game_state.as_ref().unwrap().money, STARTING_BUDGET);
+    assert_eq!(world.road_network.roads().len(), roads);
+    assert_eq!(world.intersections.len(), intersections);
+}
+
+#[test]
+fn test_workers_walk_to_a_factory_next_door() {
+    let mut world = SimWorld::new_with_game();
+    let home = world.add_intersection(Position::new(0.0, 0.0, 0.0));
+    let next_door = world.add_intersection(Position::new(WALKING_DISTANCE - 1.0, 0.0, 0.0));
+    let across_town = world.add_intersection(Position::new(40.0, 0.0, 0.0));
+    world.add_two_way_road(next_door, across_town).unwrap();
+    let walker = world.add_apartment(home);
+    let driver = world.add_apartment(across_town);
+    world.add_factory(next_door);
+
+    let mut walked = false;
+    for _ in 0..1200 {
+        world.tick(0.05);
+        // The house next door needs no road and sends out no car
+        assert!(world
+            .cars
+            .values()
+            .all(|car| car.origin_apartment != Some(walker)));
+        for walk in world.walks() {
+            assert_eq!(walk.apartment, walker);
+            assert!(walk.arrives - walk.started >= MIN_WALK_SECS);
+            walked = true;
+        }
+    }
+    assert!(walked);
+    assert!(world
+        .cars
+        .values()
+        .any(|car| car.origin_apartment == Some(driver)));
+    assert!(world.game_state.as_ref().unwrap().worker_trips_completed > 0);
+
+    let modes = world.commute_modes();
+    assert!(modes.walked > 0 && modes.driven > 0);
+    let share = modes.walk_share().unwrap();
+    assert!(share > 0.0 && share < 1.0);
+    assert!(modes.average_walk_secs() >= MIN_WALK_SECS);
+}
+
+#[test]
+fn test_snapshot_resumes_traffic_where_it_left_off() {
+    let mut world = SimWorld::create_test_world_with_seed(9);
+    world.game_state = Some(GameState::with_economy(EconomyConfig::default()));
+    world.set_route_workers(2);
+    for _ in 0..800 {
+        world.tick(0.05);
+    }
+    assert!(world.cars.values().any(|car| !car.path.is_empty()));
+
+    let path =
+        std::env::temp_dir().join(format!("traffic_sim_snapshot_{}.json", std::process::id()));
+    // Taking a snapshot leaves the world as it was
+    let waiting = world.vehicles_awaiting_route();
+    let hash = world.state_hash();
+    world.save_to_file(&path).unwrap();
+    assert_eq!(world.vehicles_awaiting_route(), waiting);
+    assert_eq!(world.state_hash(), hash);
+    assert_eq!(
+        world.to_snapshot_json().unwrap(),
+        std::fs::read_to_string(&path).unwrap()
+    );
+    let mut loaded = SimWorld::load_from_file(&path).unwrap();
+    std::fs::remove_file(&path).ok();
+    assert_eq!(loaded.time, world.time);
+    assert_eq!(loaded.state_hash(), world.state_hash());
+    assert_eq!(state_checksum(&loaded), state_checksum(&world));
+
+    // Both carry on alike, vehicles mid-route and waiting for routes and
+    // random choices included
+    assert_eq!(loaded.route_workers(), 2);
+    assert_eq!(loaded.vehicles_awaiting_route(), waiting);
+    for _ in 0..800 {
+        world.tick(0.05);
+        loaded.tick(0.05);
+    }
+    assert_eq!(state_checksum(&loaded), state_checksum(&world));
+    assert_eq!(loaded.state_hash(), world.state_hash());
+    let routes = |world: &SimWorld| -> Vec<_> {
+        world
+            .cars
+            .values()
+            .map(|car| (car.id, car.path.clone()))
+            .collect()
+    };
+    assert_eq!(routes(&loaded), routes(&world));
+
+    // Snapshots from other builds aren't read
+    let text = world.to_snapshot_json().unwrap();
+    let other = text.replacen(
+        &format!("\"version\":{}", SNAPSHOT_VERSION),
+        &format!("\"version\":{}", SNAPSHOT_VERSION + 1),
+        1,
+    );
+    assert!(SimWorld::from_snapshot_json(&other).is_err());
+}
+
+#[test]
+fn test_market_pricing_pays_more_where_goods_are_short() {
+    let economy = EconomyConfig::from_toml_str(
+        r#"
+        delivery_deadline_secs = 1000.0
+
+        [market_pricing]
+        target_stock = 1.0
+        sales_per_minute = 0.0
+        "#,
+    )
+    .expect("economy TOML should parse");
+    let pricing = economy
+        .market_pricing
+        .clone()
+        .expect("pricing should be set");
+    assert_eq!(pricing.shop_price(0.0), 1.5);
+    assert_eq!(pricing.shop_price(1.0), 1.0);
+    assert_eq!(pricing.shop_price(5.0), 0.5);
+    assert_eq!(pricing.factory_price(0, 2), 1.5);
+    assert_eq!(pricing.factory_price(1, 2), 1.0);
+    assert_eq!(pricing.factory_price(2, 2), 0.5);
+    assert!(EconomyConfig::from_toml_str("[market_pricing]\ntarget_stock = 0.0").is_err());
+    assert!(EconomyConfig::from_toml_str("[market_pricing]\nmax_discount = 1.5").is_err());
+
+    let mut game_state = GameState::with_economy(economy.clone());
+    game_state.complete_priced_shop_delivery(false, 1.5);
+    game_state.complete_priced_shop_delivery(true, 0.4);
+    assert_eq!(
+        game_state.money,
+        STARTING_BUDGET + REVENUE_SHOP_DELIVERY * 3 / 2 + REVENUE_SHOP_DELIVERY / 5
+    );
+    assert_eq!(game_state.late_deliveries, 1);
+    let pay = economy.priced_worker_trip_pay(COMMUTE_HEALTHY_DISTANCE, 0.0, 0.0, 1.5);
+    assert_eq!(
+        pay,
+        economy.worker_trip_pay(COMMUTE_HEALTHY_DISTANCE, 0.0) + REVENUE_WORKER_DELIVERY / 2
+    );
+
+    // Nothing sells, so each delivery to a shop finds one more on the shelf:
+    // the first earns the premium, the second the usual revenue and the rest
+    // the discount
+    let mut world = SimWorld::create_test_world_with_seed(5);
+    world.game_state = Some(GameState::with_economy(economy));
+    for _ in 0..4000 {
+        world.tick(0.05);
+    }
+    for shop in world.shops.values() {
+        assert_eq!(shop.stock, shop.cars_received as f32);
+    }
+    let catchments = world.shop_catchments();
+    assert!(catchments.iter().any(|catchment| catchment.deliveries > 2));
+    for catchment in &catchments {
+        let expected = match catchment.deliveries {
+            0 => 0,
+            1 => REVENUE_SHOP_DELIVERY * 3 / 2,
+            n => REVENUE_SHOP_DELIVERY * 5 / 2 + (n as i32 - 2) * REVENUE_SHOP_DELIVERY / 2,
+        };
+        assert_eq!(catchment.revenue, expected);
+    }
+
+    // Without market pricing every delivery earns the same, and shops sell
+    // off their stock
+    let mut world = SimWorld::new();
+    world.game_state = Some(GameState::new());
+    let site = world.add_intersection(Position::new(0.0, 0.0, 0.0));
+    let shop = world.add_shop(site);
+    world.shops.get_mut(&shop).unwrap().stock = 3.0;
+    assert_eq!(world.shop_delivery_price(site), 1.0);
+    world.tick(30.0);
+    assert_eq!(world.shops[&shop].stock, 2.5);
+}
+
+#[test]
+fn test_stop_signs_and_yields_control_intersections() {
+    let (mut world, [junction, south, west, north, east]) =
+        junction_world(IntersectionRules::default());
+    assert_eq!(
+        world.intersection_control(junction),
+        Some(IntersectionControl::Uncontrolled)
+    );
+    let mut control = IntersectionControl::Uncontrolled;
+    for expected in [
+        IntersectionControl::Stop,
+        IntersectionControl::Yield,
+        IntersectionControl::TrafficLight,
+        IntersectionControl::Uncontrolled,
+    ] {
+        control = control.next();
+        assert_eq!(control, expected);
+    }
+
+    // A traffic light is the intersection's signal
+    world
+        .set_intersection_control(junction, IntersectionControl::TrafficLight)
+        .unwrap();
+    assert_eq!(
+        world.intersections[&junction]
+            .signal
+            .as_ref()
+            .unwrap()
+            .phases()
+            .len(),
+        4
+    );
+    assert!(world
+        .apply_command(&BuildCommand::SetControl {
+            intersection: junction,
+            control: IntersectionControl::Stop,
+        })
+        .unwrap());
+    assert!(world.intersections[&junction].signal.is_none());
+    assert_eq!(
+        world.intersection_control(junction),
+        Some(IntersectionControl::Stop)
+    );
+    world
+        .set_signal(junction, Some(world.default_signal_phases(junction)))
+        .unwrap();
+    assert_eq!(
+        world.intersection_control(junction),
+        Some(IntersectionControl::TrafficLight)
+    );
+    world.set_signal(junction, None).unwrap();
+    assert_eq!(
+        world.intersection_control(junction),
+        Some(IntersectionControl::Uncontrolled)
+    );
+
+    let trip = |control: IntersectionControl| {
+        let (mut world, [junction, south, _, north, _]) =
+            junction_world(IntersectionRules::default());
+        world.set_intersection_control(junction, control).unwrap();
+        ticks_to_arrive(&mut world, south, north, 1000).unwrap()
+    };
+    // A lone car stops at a stop sign but not at a yield
+    let free = trip(IntersectionControl::Uncontrolled);
+    let stop_delay = trip(IntersectionControl::Stop) - free;
+    assert!(
+        stop_delay + 1 >= (STOP_SIGN_SECS / 0.1) as usize,
+        "delayed {} ticks",
+        stop_delay
+    );
+    assert!(trip(IntersectionControl::Yield) < free + stop_delay);
+
+    // At a stop sign cars from every road cross in the order they stopped
+    let (mut world, _) = junction_world(IntersectionRules::default());
+    world
+        .set_intersection_control(junction, IntersectionControl::Stop)
+        .unwrap();
+    let cars: Vec<_> = [(south, north), (west, east), (north, south), (east, west)]
+        .into_iter()
+        .map(|(from, to)| {
+            world
+                .spawn_vehicle(from, to, VehicleType::Car, TripType::Outbound, None, None)
+                .unwrap()
+        })
+        .collect();
+    let (mut stopped, mut crossed) = (Vec::new(), Vec::new());
+    for _ in 0..2000 {
+        world.tick(0.1);
+        // The car at the front of the queue leaves it as it sets off
+        let intersection = &world.intersections[&junction];
+        for car in intersection
+            .occupied_by
+            .iter()
+            .chain(&intersection.stop_queue)
+        {
+            if !stopped.contains(car) {
+                stopped.push(*car);
+            }
+        }
+        if let Some(car) = intersection.occupied_by {
+            if !crossed.contains(&car) {
+                crossed.push(car);
+            }
+        }
+        if cars.iter().all(|car| !world.cars.contains_key(car)) {
+            break;
+        }
+    }
+    assert_eq!(crossed.len(), cars.len());
+    assert_eq!(crossed, stopped);
+
+    // At a yield each car waits for a gap after the one before has cleared
+    let (mut world, _) = junction_world(IntersectionRules::default());
+    world
+        .set_intersection_control(junction, IntersectionControl::Yield)
+        .unwrap();
+    for (from, to) in [(south, north), (west, east), (north, south), (east, west)] {
+        world
+            .spawn_vehicle(from, to, VehicleType::Car, TripType::Outbound, None, None)
+            .unwrap();
+    }
+    let (mut clear_ticks, mut gaps) = (0, Vec::new());
+    for _ in 0..2000 {
+        let was_clear = world.intersections[&junction].occupied_by.is_none();
+        world.tick(0.1);
+        match world.intersections[&junction].occupied_by {
+            None => clear_ticks += 1,
+            Some(_) if was_clear => {
+                gaps.push(clear_ticks);
+                clear_ticks = 0;
+            }
+            Some(_) => {}
+        }
+        if world.cars.is_empty() {
+            break;
+        }
+    }
+    assert_eq!(gaps.len(), 4);
+    let gap_ticks = (YIELD_GAP_SECS / 0.1) as usize;
+    assert!(gaps.iter().all(|gap| *gap + 1 >= gap_ticks), "{:?}", gaps);
+}
+
+#[test]
+fn test_idling_at_junctions_makes_hotspots() {
+    let (mut world, [junction, south, west, north, east]) =
+        junction_world(IntersectionRules::default());
+    assert!(world.hotspots().is_empty());
+    let near = Position::new(3.0, 0.0, 3.0);
+    let far = Position::new(30.0, 0.0, 30.0);
+    assert_eq!(world.appeal_at(&near), 0.0);
+
+    // Trucks queueing at a stop sign from every side foul the air around it
+    world
+        .set_intersection_control(junction, IntersectionControl::Stop)
+        .unwrap();
+    for _ in 0..3 {
+        for (from, to) in [(south, north), (west, east), (north, south), (east, west)] {
+            world
+                .spawn_vehicle(from, to, VehicleType::Truck, TripType::Outbound, None, None)
+                .unwrap();
+        }
+    }
+    let mut worst = 0.0_f32;
+    for _ in 0..3000 {
+        world.tick(0.1);
+        worst = worst.max(world.intersections[&junction].idling.severity());
+        if world.cars.is_empty() {
+            break;
+        }
+    }
+    assert!(world.cars.is_empty());
+    assert!(worst >= HOTSPOT_MIN_SEVERITY);
+    let hotspots = world.hotspots();
+    assert_eq!(hotspots[0].intersection, junction);
+    assert!(hotspots[0].idle_secs > 0.0 && hotspots[0].co2_kg > 0.0);
+    assert!(hotspots
+        .windows(2)
+        .all(|pair| pair[0].severity >= pair[1].severity));
+
+    // Homes nearby lose appeal and pay more for a short commute
+    assert!(world.appeal_at(&near) < 0.0);
+    assert_eq!(world.appeal_at(&far), 0.0);
+    assert!(world.pollution_at(&near) <= hotspots[0].severity * HOTSPOT_MAX_POLLUTION + 1e-6);
+    let economy = EconomyConfig::default();
+    assert!(
+        economy.worker_trip_pay_with_appeal(0.0, 0.0, world.appeal_at(&near))
+            < economy.worker_trip_pay(0.0, 0.0)
+    );
+
+    // Once the traffic has gone the hotspot fades
+    for _ in 0..(10.0 * HOTSPOT_HALF_LIFE_SECS / 0.1) as usize {
+        world.tick(0.1);
+    }
+    assert!(world.hotspots().is_empty());
+    assert_eq!(world.appeal_at(&near), 0.0);
+}
+
+#[test]
+fn test_camera_tours_ease_between_keyframes() {
+    let tour = CameraTour::from_toml_str(
+        r#"
+        [[keyframes]]
+        time = 2.0
+        position = { x = 0.0, y = 0.0, z = 0.0 }
+        caption = "The centre"
+
+        [[keyframes]]
+        time = 6.0
+        position = { x = 40.0, y = 0.0, z = -20.0 }
+        zoom = 3.0
+
+        [[keyframes]]
+        time = 10.0
+        position = { x = 40.0, y = 0.0, z = -20.0 }
+        zoom = 3.0
+        caption = "The factories"
+        "#,
+    )
+    .expect("tour TOML should parse");
+    assert_eq!(tour.duration(), 10.0);
+    assert!(tour.save.is_none());
+
+    // Waiting at the first keyframe, halfway to the second and holding at
+    // the last
+    let start = tour.pose_at(0.0);
+    assert_eq!(start.position, Position::new(0.0, 0.0, 0.0));
+    assert_eq!(start.zoom, 1.0);
+    assert_eq!(start.caption, Some("The centre"));
+    let halfway = tour.pose_at(4.0);
+    assert!((halfway.position.x - 20.0).abs() < 1e-4);
+    assert!((halfway.position.z + 10.0).abs() < 1e-4);
+    assert!((halfway.zoom - 2.0).abs() < 1e-4);
+    assert_eq!(halfway.caption, Some("The centre"));
+    // Easing starts slowly
+    assert!(tour.pose_at(3.0).position.x < 10.0);
+    let end = tour.pose_at(60.0);
+    assert_eq!(end.position, Position::new(40.0, 0.0, -20.0));
+    assert_eq!(end.caption, Some("The factories"));
+
+    assert!(CameraTour::from_toml_str("keyframes = []").is_err());
+    assert!(CameraTour::from_toml_str(
+        "[[keyframes]]\ntime = 1.0\nposition = { x = 0.0, y = 0.0, z = 0.0 }\nzoom = 0.0"
+    )
+    .is_err());
+    assert!(CameraTour::from_toml_str(
+        "[[keyframes]]\ntime = 5.0\nposition = { x = 0.0, y = 0.0, z = 0.0 }\n\
+         [[keyframes]]\ntime = 1.0\nposition = { x = 0.0, y = 0.0, z = 0.0 }"
+    )
+    .is_err());
+
+    // A tour's save is found next to it, and its city is the same each time
+    let dir = std::env::temp_dir().join(format!("camera_tour_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let sample = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/saves")
+        .join(format!("v{}.json", SAVE_VERSION));
+    std::fs::copy(&sample, dir.join("city.json")).unwrap();
+    let path = dir.join("tour.toml");
+    std::fs::write(
+        &path,
+        "save = \"city.json\"\n[[keyframes]]\ntime = 0.0\nposition = { x = 0.0, y = 0.0, z = 0.0 }\n",
+    )
+    .unwrap();
+    let tour = CameraTour::load(&path).unwrap();
+    assert_eq!(tour.save.as_deref(), Some(dir.join("city.json").as_path()));
+    let mut first = tour.create_world(0).unwrap();
+    let mut second = tour.create_world(1).unwrap();
+    let city = SaveFile::load(&sample).unwrap().create_world().unwrap();
+    assert_eq!(first.state_hash(), city.state_hash());
+    for _ in 0..200 {
+        first.tick(0.1);
+        second.tick(0.1);
+    }
+    assert_eq!(state_checksum(&first), state_checksum(&second));
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    let tour = CameraTour::from_toml_str(
+        "[[keyframes]]\ntime = 0.0\nposition = { x = 0.0, y = 0.0, z = 0.0 }\n",
+    )
+    .unwrap();
+    assert_eq!(
+        tour.create_world(5).unwrap().state_hash(),
+        SimWorld::create_test_world_with_seed(5).state_hash()
+    );
+}
+
+#[test]
+fn test_trucks_slow_junctions_down_as_freight_grows() {
+    // A truck moving off gains speed slowly
+    let (mut world, [_, south, _, north, _]) = junction_world(IntersectionRules::default());
+    let truck = world
+        .spawn_vehicle(
+            south,
+            north,
+            VehicleType::Truck,
+            TripType::Outbound,
+            None,
+            None,
+        )
+        .unwrap();
+    world.tick(0.1);
+    let launch = world.handling.truck.launch_accel * 0.1;
+    assert!(world.cars[&truck].current_speed <= launch + 1e-4);
+    assert!(world.cars[&truck].speed > launch);
+
+    // Vehicles turning through a yield, `trucks` of every eight of them
+    // trucks; ticks until all are through
+    let clear_ticks = |trucks: usize, car_like_trucks: bool| {
+        let (mut world, [junction, south, west, north, east]) =
+            junction_world(IntersectionRules::default());
+        world
+            .set_intersection_control(junction, IntersectionControl::Yield)
+            .unwrap();
+        if car_like_trucks {
+            world.handling.truck = world.handling.car;
+        }
+        assert_eq!(world.handling.get(VehicleType::Truck), world.handling.truck);
+        let trips = [(south, west), (west, north), (north, east), (east, south)];
+        for index in 0..8 {
+            let (from, to) = trips[index % trips.len()];
+            let vehicle_type = if index % 8 < trucks {
+                VehicleType::Truck
+            } else {
+                VehicleType::Car
+            };
+            world
+                .spawn_vehicle(from, to, vehicle_type, TripType::Outbound, None, None)
+                .unwrap();
+        }
+        (1..=5000)
+            .find(|_| {
+                world.tick(0.1);
+                world.cars.is_empty()
+            })
+            .unwrap()
+    };
+
+    // Truck handling only matters where there are trucks, and the more
+    // there are the more it costs the junction
+    assert_eq!(clear_ticks(0, false), clear_ticks(0, true));
+    let delay =
+        |trucks: usize| clear_ticks(trucks, false) as i64 - clear_ticks(trucks, true) as i64;
+    let (some, all) = (delay(2), delay(8));
+    assert!(some > 0, "{} ticks", some);
+    assert!(
+        all > some,
+        "{} ticks with all trucks, {} with some",
+        all,
+        some
+    );
+}
+
+#[test]
+fn test_path_cache_reuses_fresh_routes_within_its_size() {
+    // The cache is off unless turned on, leaving routing as it was
+    let mut off = SimWorld::create_test_world_with_seed(4);
+    for _ in 0..500 {
+        off.tick(0.1);
+    }
+    let stats = off.path_cache_stats();
+    assert_eq!(
+        (stats.hits, stats.misses, stats.entries, stats.capacity),
+        (0, 0, 0, 0)
+    );
+    assert_eq!(PathCacheConfig::default().capacity, 0);
+
+    // Commuters in the test world often share routes
+    let mut world = SimWorld::create_test_world_with_seed(4);
+    world.set_path_cache_config(PathCacheConfig::enabled());
+    for _ in 0..2000 {
+        world.tick(0.1);
+    }
+    let stats = world.path_cache_stats();
+    assert!(stats.hits > 0 && stats.misses > 0, "{:?}", stats);
+    assert!(stats.hit_rate() > 0.0 && stats.hit_rate() < 1.0);
+    assert!(stats.entries <= PATH_CACHE_CAPACITY);
+    assert!(world.memory_report().category("caches").unwrap().entries >= stats.entries);
+
+    // A route is handed out again while fresh, then searched for afresh
+    let (mut world, [junction, south, west, north, east]) =
+        junction_world(IntersectionRules::default());
+    world.set_path_cache_config(PathCacheConfig::enabled());
+    let spawn = |world: &mut SimWorld, from, to| {
+        world
+            .spawn_vehicle(from, to, VehicleType::Car, TripType::Outbound, None, None)
+            .unwrap()
+    };
+    let first = spawn(&mut world, south, north);
+    let second = spawn(&mut world, south, north);
+    assert_eq!(world.cars[&first].path, world.cars[&second].path);
+    assert_eq!(
+        (
+            world.path_cache_stats().hits,
+            world.path_cache_stats().misses
+        ),
+        (1, 1)
+    );
+    for _ in 0..=(PATH_CACHE_MAX_AGE_SECS / 0.1) as usize {
+        world.tick(0.1);
+    }
+    spawn(&mut world, south, north);
+    let stats = world.path_cache_stats();
+    assert_eq!((stats.hits, stats.misses, stats.expirations), (1, 2, 1));
+
+    // Changing the roads throws the routes away, and the change counts
+    // towards the last minute's invalidations until a minute has passed
+    world.remove_two_way_road(junction, east).unwrap();
+    spawn(&mut world, south, west);
+    let stats = world.path_cache_stats();
+    assert_eq!(
+        (
+            stats.entries,
+            stats.invalidations,
+            stats.invalidations_per_minute
+        ),
+        (1, 1, 1)
+    );
+    for _ in 0..610 {
+        world.tick(0.1);
+    }
+    assert_eq!(world.path_cache_stats().invalidations_per_minute, 0);
+
+    // A full cache drops the least recently used route, or the oldest
+    let evicted = |eviction: PathCacheEviction| {
+        let (mut world, [_, south, west, north, east]) =
+            junction_world(IntersectionRules::default());
+        world.set_path_cache_config(PathCacheConfig {
+            capacity: 2,
+            eviction,
+            ..PathCacheConfig::enabled()
+        });
+        for (from, to) in [(south, north), (west, east), (south, north), (north, south)] {
+            spawn(&mut world, from, to);
+        }
+        let stats = world.path_cache_stats();
+        assert_eq!((stats.entries, stats.evictions), (2, 1));
+        // Whether the first route was dropped, so only its reuse above hit
+        spawn(&mut world, south, north);
+        world.path_cache_stats().hits == 1
+    };
+    assert!(!evicted(PathCacheEviction::Lru));
+    assert!(evicted(PathCacheEviction::Oldest));
+
+    // An empty cache keeps nothing
+    let (mut world, [_, south, _, north, _]) = junction_world(IntersectionRules::default());
+    world.set_path_cache_config(PathCacheConfig {
+        capacity: 0,
+        ..PathCacheConfig::enabled()
+    });
+    spawn(&mut world, south, north);
+    spawn(&mut world, south, north);
+    assert_eq!(world.path_cache_stats().hits, 0);
+    assert_eq!(world.path_cache_stats().entries, 0);
+}
+
+#[test]
+fn test_recap_follows_the_run_as_the_scenario_words_it() {
+    let mut world = SimWorld::create_test_world_with_seed(3);
+    world.game_state = Some(GameState::new());
+    for _ in 0..2000 {
+        world.tick(0.1);
+    }
+    let recap = world.recap();
+    assert_eq!(recap.outcome, RecapOutcome::Playing);
+    assert_eq!(recap.sections, RecapSection::ALL);
+    assert!(recap.sandbox);
+    // The timeline starts at the first tick and ends with the money now
+    let money = world.game_state.as_ref().unwrap().money;
+    assert_eq!(recap.money[0].0, 0.1);
+    assert_eq!(recap.money.last(), Some(&(world.time, money)));
+    assert!(recap.money.windows(2).all(|pair| pair[0].0 < pair[1].0));
+    assert!(recap.distance_driven > 0.0);
+    let jam = recap
+        .biggest_jam
+        .expect("vehicles queue at the test world's junctions");
+    assert!(jam.vehicles > 0 && jam.time <= world.time);
+    let mvp = recap
+        .mvp_factory
+        .expect("the factories have made deliveries");
+    let factory_labor: Vec<_> = world.labor_by_factory();
+    assert!(factory_labor
+        .iter()
+        .any(|(id, labor)| *id == mvp.factory_id && labor.fulfilled == mvp.shifts));
+    assert!(mvp.deliveries > 0.0);
+
+    // A long game thins its samples out rather than keeping them all
+    let mut world = SimWorld::new_with_game();
+    for _ in 0..(RECAP_MONEY_INTERVAL_SECS as usize * RECAP_MONEY_SAMPLES * 3) {
+        world.tick(1.0);
+    }
+    let recap = world.recap();
+    assert!(
+        recap.money.len() > RECAP_MONEY_SAMPLES / 2 && recap.money.len() <= RECAP_MONEY_SAMPLES + 1
+    );
+    assert_eq!(recap.distance_driven, 0.0);
+    assert!(recap.biggest_jam.is_none() && recap.mvp_factory.is_none());
+
+    // Each scenario words its own recap
+    let economy = EconomyConfig::from_toml_str(
+        r#"
+        [recap]
+        victory_title = "The port is open"
+        message = "Ships are unloading around the clock."
+        sections = ["mvp_factory", "money"]
+        sandbox = false
+        "#,
+    )
+    .unwrap();
+    assert_eq!(
+        economy.recap.defeat_title,
+        RecapConfig::default().defeat_title
+    );
+    assert!(EconomyConfig::from_toml_str("[recap]\nsections = [\"money\", \"money\"]").is_err());
+    let mut world = SimWorld::new_with_economy(economy);
+    world.tick(0.1);
+    world.game_state.as_mut().unwrap().is_won = true;
+    let recap = world.recap();
+    assert_eq!(recap.outcome, RecapOutcome::Won);
+    assert_eq!(recap.title, "The port is open");
+    assert_eq!(recap.message, "Ships are unloading around the clock.");
+    assert_eq!(
+        recap.sections,
+        [RecapSection::MvpFactory, RecapSection::Money]
+    );
+    assert!(!recap.sandbox);
+}
+
+#[test]
+fn test_vehicles_on_a_removed_road_find_another_way() {
+    let mut world = SimWorld::new_with_seed(5);
+    let a = world.add_intersection(Position::new(0.0, 0.0, 0.0));
+    let b = world.add_intersection(Position::new(20.0, 0.0, 20.0));
+    let c = world.add_intersection(Position::new(40.0, 0.0, 0.0));
+    let d = world.add_intersection(Position::new(60.0, 0.0, 0.0));
+    world.add_two_way_road(a, c).unwrap();
+    world.add_two_way_road(a, b).unwrap();
+    world.add_two_way_road(b, c).unwrap();
+    world.add_two_way_road(c, d).unwrap();
+    let spawn = |world: &mut SimWorld, from, to| {
+        world
+            .spawn_vehicle(from, to, VehicleType::Car, TripType::Outbound, None, None)
+            .unwrap()
+    };
+    let share_along = |world: &SimWorld, car_id: CarId| {
+        let car = &world.cars[&car_id];
+        let road = world.road_network.get_road(car.current_road).unwrap();
+        car.distance_along_road.into_inner() / road.length
+    };
+
+    // Short of halfway, a car backs up slowly to where it came from...
+    let backing = spawn(&mut world, a, d);
+    assert_eq!(world.cars[&backing].path, vec![c, d]);
+    while share_along(&world, backing) < 0.1 {
+        world.tick(0.1);
+    }
+    world.remove_two_way_road(a, c).unwrap();
+    let exit = world.cars[&backing]
+        .road_exit
+        .expect("backing off the road");
+    assert_eq!((exit.to, exit.reversing), (a, true));
+    let speed = world.cars[&backing].speed;
+    while world.cars[&backing].road_exit.is_some() {
+        world.tick(0.1);
+        world.check_invariants().unwrap();
+        assert!(world.cars[&backing].current_speed <= speed * REVERSING_SPEED_FACTOR + 1e-3);
+    }
+    // ...and goes the long way round
+    assert_eq!(world.cars[&backing].path, vec![b, c, d]);
+    assert_eq!(world.cars[&backing].start_intersection, a);
+    let arrived = (0..1000).any(|_| {
+        world.tick(0.1);
+        !world.cars.contains_key(&backing)
+    });
+    assert!(arrived);
+
+    // Past halfway, it drives on to the end and reroutes from there
+    let onward = spawn(&mut world, b, d);
+    while share_along(&world, onward) < 0.6 {
+        world.tick(0.1);
+    }
+    world.remove_road(world.cars[&onward].current_road).unwrap();
+    assert_eq!(
+        world.cars[&onward]
+            .road_exit
+            .map(|exit| (exit.to, exit.reversing)),
+        Some((c, false))
+    );
+    while world.cars[&onward].road_exit.is_some() {
+        world.tick(0.1);
+    }
+    assert_eq!(world.cars[&onward].path, vec![d]);
+    assert_eq!(world.cars[&onward].start_intersection, c);
+    world.check_invariants().unwrap();
+
+    // With no way on at all, it is despawned
+    while world.cars[&onward].queued_at_start || share_along(&world, onward) < 0.1 {
+        world.tick(0.1);
+    }
+    world.remove_two_way_road(c, d).unwrap();
+    assert!(matches!(
+        world.cars[&onward].road_exit,
+        Some(RoadExit { to, reversing: true, .. }) if to == c
+    ));
+    let gone = (0..200).any(|_| {
+        world.tick(0.1);
+        !world.cars.contains_key(&onward)
+    });
+    assert!(gone);
+    world.check_invariants().unwrap();
+}
+
+#[test]
+fn test_vehicles_waiting_on_a_removed_road_find_another_way() {
+    let mut world = SimWorld::new_with_seed(5);
+    let a = world.add_intersection(Position::new(0.0, 0.0, 0.0));
+    let b = world.add_intersection(Position::new(20.0, 0.0, 20.0));
+    let c = world.add_intersection(Position::new(40.0, 0.0, 0.0));
+    world.add_two_way_road(a, c).unwrap();
+    world.add_two_way_road(a, b).unwrap();
+    world.add_two_way_road(b, c).unwrap();
+    let spawn = |world: &mut SimWorld| {
+        world
+            .spawn_vehicle(a, c, VehicleType::Car, TripType::Outbound, None, None)
+            .unwrap()
+    };
+
+    // The second car waits for the first to clear the start of the road...
+    let first = spawn(&mut world);
+    let waiting = spawn(&mut world);
+    assert!(world.cars[&waiting].queued_at_start);
+    assert_eq!(world.cars[&waiting].path, vec![c]);
+
+    // ...and when the road goes, sets off the long way round instead
+    world.remove_two_way_road(a, c).unwrap();
+    assert!(world.cars[&waiting].queued_at_start);
+    assert_eq!(world.cars[&waiting].path, vec![b, c]);
+    assert_eq!(world.spawn_queue_len(a), 1);
+    world.check_invariants().unwrap();
+    let target = *world.road_network.get_intersection_position(c).unwrap();
+    let mut last_seen = world.cars[&waiting].position;
+    while let Some(car) = world.cars.get(&waiting) {
+        last_seen = car.position;
+        world.tick(0.1);
+        world.check_invariants().unwrap();
+        assert!(world.time < 100.0, "the car never arrived");
+    }
+    assert!(last_seen.distance(&target) < 2.0);
+    // The car it waited behind backed off the road and went round too
+    assert_eq!(world.cars[&first].path.last(), Some(&c));
+}
+
+#[test]
+fn test_vehicles_routed_over_a_removed_road_take_the_detour() {
+    let mut world = SimWorld::new_with_seed(5);
+    let a = world.add_intersection(Position::new(0.0, 0.0, 0.0));
+    let b = world.add_intersection(Position::new(20.0, 0.0, 0.0));
+    let c = world.add_intersection(Position::new(40.0, 0.0, 0.0));
+    let d = world.add_intersection(Position::new(30.0, 0.0, 20.0));
+    world.add_road(a, b, false).unwrap();
+    let direct = world.add_road(b, c, false).unwrap();
+    world.add_road(b, d, false).unwrap();
+    world.add_road(d, c, false).unwrap();
+    let car = world
+        .spawn_vehicle(a, c, VehicleType::Car, TripType::Outbound, None, None)
+        .unwrap();
+    world.tick(0.1);
+    assert_eq!(world.cars[&car].path, vec![b, c]);
+
+    // The car is still on its first road when the one after it goes
+    world.remove_road(direct).unwrap();
+    assert_eq!(world.cars[&car].path, vec![b, d, c]);
+    world.check_invariants().unwrap();
+    let target = *world.road_network.get_intersection_position(c).unwrap();
+    let mut last_seen = world.cars[&car].position;
+    while let Some(vehicle) = world.cars.get(&car) {
+        last_seen = vehicle.position;
+        world.tick(0.1);
+        assert!(world.time < 100.0, "the car never arrived");
+    }
+    assert!(last_seen.distance(&target) < 2.0);
+}
+
+#[test]
+fn test_vehicles_on_a_split_road_carry_on() {
+    let mut world = SimWorld::new_with_seed(5);
+    let a = world.add_intersection(Position::new(0.0, 0.0, 0.0));
+    let b = world.add_intersection(Position::new(40.0, 0.0, 0.0));
+    let c = world.add_intersection(Position::new(40.0, 0.0, 20.0));
+    let (road, _) = world.add_two_way_road(a, b).unwrap();
+    world.add_two_way_road(b, c).unwrap();
+    let spawn = |world: &mut SimWorld| {
+        world
+            .spawn_vehicle(a, c, VehicleType::Car, TripType::Outbound, None, None)
+            .unwrap()
+    };
+    let ahead = spawn(&mut world);
+    for _ in 0..70 {
+        world.tick(0.1);
+    }
+    let behind = spawn(&mut world);
+    for _ in 0..10 {
+        world.tick(0.1);
+    }
+    assert!(world.cars[&ahead].distance_along_road.into_inner() > 20.0);
+    assert!(world.cars[&behind].distance_along_road.into_inner() < 20.0);
+    let positions = [world.cars[&ahead].position, world.cars[&behind].position];
+
+    // Each car stays where it was, on the half of the road it was on
+    let (middle, first, second) = world
+        .split_road_at_position(road, Position::new(20.0, 0.0, 0.0))
+        .unwrap();
+    world.check_invariants().unwrap();
+    assert_eq!(world.cars[&ahead].current_road, second);
+    assert_eq!(world.cars[&ahead].path, vec![b, c]);
+    assert_eq!(world.cars[&behind].current_road, first);
+    assert_eq!(world.cars[&behind].path, vec![middle, b, c]);
+    assert_eq!(
+        [world.cars[&ahead].position, world.cars[&behind].position],
+        positions
+    );
+
+    // And both get where they were going
+    let target = *world.road_network.get_intersection_position(c).unwrap();
+    let mut last_seen = positions;
+    while world.cars.contains_key(&ahead) || world.cars.contains_key(&behind) {
+        for (seen, car) in last_seen.iter_mut().zip([ahead, behind]) {
+            if let Some(car) = world.cars.get(&car) {
+                *seen = car.position;
+            }
+        }
+        world.tick(0.1);
+        world.check_invariants().unwrap();
+        assert!(world.time < 100.0, "a car never arrived");
+    }
+    assert!(last_seen.iter().all(|seen| seen.distance(&target) < 2.0));
+}
+
+#[test]
+fn test_world_bounds_hold_building_in() {
+    // A world without bounds of its own is the default square, widened to
+    // cover what was built outside it
+    let mut world = SimWorld::new_with_game();
+    let half = DEFAULT_WORLD_SIZE / 2.0;
+    assert_eq!(
+        world.bounds(),
+        WorldBounds::around(Position::default(), half)
+    );
+    assert_eq!(world.configured_bounds(), None);
+    let far = world.add_intersection(Position::new(150.0, 0.0, -20.0));
+    assert_eq!(world.bounds().max_x, 150.0);
+    world.remove_intersection(far).unwrap();
+    assert_eq!(world.bounds().max_x, half);
+
+    // Commands only build inside the bounds
+    let road = |start: Position, end: Position| BuildCommand::Road {
+        start,
+        end,
+        snap_distance: 2.0,
+    };
+    let outside = road(
+        Position::new(0.0, 0.0, 0.0),
+        Position::new(half + 10.0, 0.0, 0.0),
+    );
+    assert_eq!(outside.positions().len(), 2);
+    let error = world.apply_command(&outside).unwrap_err();
+    assert!(error.to_string().contains("outside the world"));
+    assert_eq!(world.road_network.road_count(), 0);
+    assert!(world
+        .apply_command(&road(
+            Position::new(-50.0, 0.0, 0.0),
+            Position::new(50.0, 0.0, 0.0)
+        ))
+        .unwrap());
+
+    // A map can set its own bounds, as long as they cover what's built
+    let small = WorldBounds::new(
+        Position::new(-60.0, 0.0, -20.0),
+        Position::new(60.0, 0.0, 20.0),
+    );
+    world.set_bounds(Some(small)).unwrap();
+    assert_eq!(world.bounds(), small);
+    assert!(world
+        .set_bounds(Some(WorldBounds::around(Position::default(), 10.0)))
+        .is_err());
+    assert!(world
+        .set_bounds(Some(WorldBounds::around(Position::default(), 0.0)))
+        .is_err());
+    let decoration = |position| BuildCommand::Decoration {
+        kind: DecorationKind::Tree,
+        position,
+    };
+    assert!(world
+        .apply_command(&decoration(Position::new(0.0, 0.0, 30.0)))
+        .is_err());
+    assert!(world
+        .apply_command(&decoration(Position::new(0.0, 0.0, 10.0)))
+        .unwrap());
+
+    // A junction or cul-de-sac must fit whole, not just its center or ends
+    let junction = BuildCommand::Junction {
+        kind: JunctionKind::Cross,
+        center: Position::new(0.0, 0.0, 15.0),
+        toward: Position::new(0.0, 0.0, 20.0),
+        snap_distance: 2.0,
+    };
+    assert_eq!(junction.positions().len(), 5);
+    let roads = world.road_network.road_count();
+    let error = world.apply_command(&junction).unwrap_err();
+    assert!(error.to_string().contains("outside the world"));
+    let cul_de_sac = BuildCommand::CulDeSac {
+        start: Position::new(-40.0, 0.0, 19.0),
+        end: Position::new(-20.0, 0.0, 19.0),
+        lots: 2,
+        snap_distance: 2.0,
+    };
+    assert_eq!(cul_de_sac.positions().len(), 6);
+    assert!(world.apply_command(&cul_de_sac).is_err());
+    assert_eq!(world.road_network.road_count(), roads);
+
+    // Saves keep the bounds
+    let mut save = SaveFile::of_world(3, &world);
+    assert_eq!(save.bounds, Some(small));
+    save.apply_commands(&mut world, &[decoration(Position::new(20.0, 0.0, -10.0))]);
+    assert_eq!(save.create_world().unwrap().bounds(), small);
+
+    // Positions clamp into the bounds and snap to a grid
+    let point = Position::new(73.0, 2.0, -4.4);
+    assert_eq!(point.clamp_to(&small), Position::new(60.0, 2.0, -4.4));
+    assert_eq!(point.snap_to_grid(5.0), Position::new(75.0, 2.0, -5.0));
+    assert_eq!(point.snap_to_grid(0.0), point);
+
+    // Latitude and longitude project on to the map and back
+    let projection = Equirectangular::new(51.5, -0.12);
+    assert_eq!(projection.project(51.5, -0.12), Position::default());
+    let north = Position::from_lat_lon(51.501, -0.12, &projection);
+    assert!(north.x.abs() < 1e-3 && north.z > 0.0);
+    let (lat, lon) = Position::new(40.0, 0.0, -25.0).to_lat_lon(&projection);
+    let back = projection.project(lat, lon);
+    assert!((back.x - 40.0).abs() < 1e-2 && (back.z + 25.0).abs() < 1e-2);
+}
+
+#[test]
+fn test_idle_world_ticks_cheaply_until_there_is_work() {
+    let run = |power_saving: bool| {
+        let mut world = SimWorld::new_with_seed(5);
+        world.game_state = Some(GameState::new());
+        world.set_power_saving(power_saving);
+        let home = world.add_intersection(Position::new(-40.0, 0.0, 0.0));
+        let work = world.add_intersection(Position::new(40.0, 0.0, 0.0));
+        world.add_two_way_road(home, work).unwrap();
+
+        // Roads alone give the world nothing to do
+        assert!(world.is_idle());
+        for _ in 0..100 {
+            world.tick(0.1);
+        }
+        assert!((world.time - 10.0).abs() < 1e-3);
+
+        // The first buildings wake it
+        world.add_apartment(home);
+        world.add_factory(work);
+        assert!(!world.is_idle());
+        for _ in 0..1000 {
+            world.tick(0.1);
+        }
+        world
+    };
+
+    let saving = run(true);
+    assert!(saving.power_saving());
+    assert_eq!(saving.idle_mode().idle_ticks, 100);
+    assert_eq!(saving.idle_mode().full_ticks, 1000);
+    assert!((saving.idle_mode().idle_share() - 100.0 / 1100.0).abs() < 1e-6);
+    assert!(
+        !saving.cars.is_empty() || saving.game_state.as_ref().unwrap().worker_trips_completed > 0
+    );
+
+    // Skipping the idle work changes nothing
+    let full = run(false);
+    assert_eq!(full.idle_mode().idle_ticks, 0);
+    assert_eq!(full.idle_mode().full_ticks, 1100);
+    assert_eq!(state_checksum(&full), state_checksum(&saving));
+}
+
+#[test]
+fn test_workers_ride_the_bus_between_stops() {
+    let mut world = SimWorld::new_with_game();
+    let home = world.add_intersection(Position::new(-30.0, 0.0, 0.0));
+    let middle = world.add_intersection(Position::new(0.0, 0.0, 0.0));
+    let work = world.add_intersection(Position::new(30.0, 0.0, 0.0));
+    let off_route = world.add_intersection(Position::new(0.0, 0.0, 30.0));
+    world.add_two_way_road(home, middle).unwrap();
+    world.add_two_way_road(middle, work).unwrap();
+    world.add_two_way_road(middle, off_route).unwrap();
+    let rider = world.add_apartment(home);
+    let driver = world.add_apartment(off_route);
+    world.add_factory(work);
+
+    // A route needs two different stops with a way between them, and costs
+    // each stop
+    assert!(world
+        .apply_command(&BuildCommand::BusRoute { stops: vec![home] })
+        .is_err());
+    assert!(world
+        .apply_command(&BuildCommand::BusRoute {
+            stops: vec![home, home]
+        })
+        .is_err());
+    let money = world.game_state.as_ref().unwrap().money;
+    let route = BuildCommand::BusRoute {
+        stops: vec![home, work],
+    };
+    assert_eq!(route.describe(), "bus route");
+    assert!(world.apply_command(&route).unwrap());
+    assert_eq!(
+        world.game_state.as_ref().unwrap().money,
+        money - 2 * COST_BUS_STOP
+    );
+    let route_id = *world.bus_routes().keys().next().unwrap();
+    assert!(!world.is_idle());
+
+    let mut rode = false;
+    for _ in 0..3000 {
+        world.tick(0.05);
+        // The house on the route sends out no car
+        assert!(world
+            .cars
+            .values()
+            .all(|car| car.origin_apartment != Some(rider)));
+        let route = &world.bus_routes()[&route_id];
+        assert!(route.bus.riders.len() <= BUS_CAPACITY);
+        rode |= !route.bus.riders.is_empty();
+    }
+    assert!(rode);
+    assert!(world.transit_ridership() > 0);
+    assert!(world
+        .cars
+        .values()
+        .any(|car| car.origin_apartment == Some(driver)));
+    let modes = world.commute_modes();
+    assert!(modes.rode > 0 && modes.driven > 0);
+    assert!(modes.ride_share().unwrap() > 0.0);
+    assert!(world.game_state.as_ref().unwrap().worker_trips_completed > 0);
+
+    // Removing a stop takes the route out of service
+    world.remove_intersection(work).unwrap();
+    assert!(world.bus_routes().is_empty());
+    assert!(world
+        .apply_command(&BuildCommand::RemoveBusRoute { route: route_id })
+        .is_err());
+
+    // Buses take bus lanes cars have to go round, but not roads for trucks
+    let mut world = SimWorld::new_with_seed(1);
+    let a = world.add_intersection(Position::new(0.0, 0.0, 0.0));
+    let b = world.add_intersection(Position::new(20.0, 0.0, 0.0));
+    let detour = world.add_intersection(Position::new(10.0, 0.0, 20.0));
+    let (lane, _) = world.add_two_way_road(a, b).unwrap();
+    world.add_two_way_road(a, detour).unwrap();
+    world.add_two_way_road(detour, b).unwrap();
+    world
+        .set_road_restriction(lane, RoadRestriction::BusOnly)
+        .unwrap();
+    assert_eq!(
+        world.road_network.find_path_for(a, b, VehicleType::Car),
+        Some(vec![detour, b])
+    );
+    world.add_bus_route(vec![a, b]).unwrap();
+    // The waypoints of the bus's next leg, once it has called at a stop
+    let next_leg = |world: &mut SimWorld| {
+        let state = |world: &SimWorld| {
+            world
+                .bus_routes()
+                .values()
+                .next()
+                .unwrap()
+                .bus
+                .state
+                .clone()
+        };
+        while !matches!(state(world), BusState::AtStop { .. }) {
+            world.tick(0.1);
+        }
+        loop {
+            if let BusState::Driving { waypoints, .. } = state(world) {
+                return waypoints;
+            }
+            world.tick(0.1);
+        }
+    };
+    assert_eq!(next_leg(&mut world), vec![b]);
+    world
+        .set_road_restriction(lane, RoadRestriction::TrucksOnly)
+        .unwrap();
+    assert_eq!(next_leg(&mut world), vec![detour, a]);
+}